@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
 use std::fs;
 use std::io::{BufRead, BufReader};
@@ -10,10 +10,12 @@ use anyhow::{Context, Result};
 use nix::mount::{MntFlags, MsFlags, mount, umount2};
 use tokio::sync::{Notify, mpsc};
 use tokio_util::sync::CancellationToken;
-use tracing::{Instrument, debug, error, info, info_span};
+use tracing::{Instrument, debug, error, info, info_span, warn};
 use udev::Enumerator;
 
-use crate::core::hardware::{BlockDevice, HardwareAdapter, HardwareEvent, SupportedFilesystem};
+use crate::core::hardware::{
+    BlockDevice, HardwareAdapter, HardwareEvent, SupportedFilesystem, parent_disk,
+};
 
 /// Configuration for the Linux adapter
 #[derive(Debug, Clone)]
@@ -22,6 +24,12 @@ pub struct LinuxAdapterConfig {
     pub mount_base: PathBuf,
     /// Whether to auto-mount devices
     pub auto_mount: bool,
+    /// Still report devices that fail to mount, with an empty mount point,
+    /// so an engine that images the block device can preserve them
+    pub keep_unmountable: bool,
+    /// Filesystem UUIDs and volume labels to mount read-only (quarantined
+    /// devices)
+    pub read_only: HashSet<String>,
 }
 
 impl Default for LinuxAdapterConfig {
@@ -29,6 +37,8 @@ impl Default for LinuxAdapterConfig {
         Self {
             mount_base: PathBuf::from("/run/bksd"),
             auto_mount: true,
+            keep_unmountable: false,
+            read_only: HashSet::new(),
         }
     }
 }
@@ -168,9 +178,46 @@ impl HardwareAdapter for LinuxAdapter {
                 continue;
             };
 
-            // Only include mounted devices for enumeration
-            let Some(mount_point) = get_mount_point(devnode) else {
-                continue;
+            // Mount what isn't mounted yet, as for an add event
+            let mount_point = match get_mount_point(devnode) {
+                Some(existing) => existing,
+                None if self.config.auto_mount => {
+                    let read_only = self.config.read_only.contains(uuid)
+                        || self.config.read_only.contains(&label);
+                    match mount_device_blocking(
+                        devnode,
+                        uuid,
+                        &supported_fs,
+                        read_only,
+                        &self.config,
+                    ) {
+                        Ok(mp) => {
+                            self.mount_state
+                                .lock()
+                                .unwrap()
+                                .mounted_by_us
+                                .insert(uuid.to_string(), mp.clone());
+                            mp
+                        }
+                        Err(e) if self.config.keep_unmountable => {
+                            warn!(
+                                device = %devnode.display(),
+                                error = %e,
+                                "Failed to mount device, imaging it unmounted"
+                            );
+                            PathBuf::new()
+                        }
+                        Err(e) => {
+                            error!(
+                                device = %devnode.display(),
+                                error = %e,
+                                "Failed to mount device"
+                            );
+                            continue;
+                        }
+                    }
+                }
+                None => continue,
             };
 
             let capacity = get_device_capacity(devnode).unwrap_or(0);
@@ -228,6 +275,20 @@ impl HardwareAdapter for LinuxAdapter {
 
         Ok(())
     }
+
+    fn eject_device(&self, device: &BlockDevice) -> Result<()> {
+        // Detach the whole disk, like udisks' power-off: the kernel flushes
+        // and stops it, and USB readers cut power to the slot
+        let disk = parent_disk(&device.path).unwrap_or_else(|| device.path.clone());
+        let name = disk
+            .file_name()
+            .with_context(|| format!("No device name in {}", disk.display()))?;
+        let delete = Path::new("/sys/block").join(name).join("device/delete");
+        fs::write(&delete, "1").with_context(|| format!("Failed to eject {}", disk.display()))?;
+
+        info!(disk = %disk.display(), label = %device.label, "Device ejected");
+        Ok(())
+    }
 }
 
 /// Run udev monitor in a blocking thread (udev types are not Send/Sync)
@@ -343,7 +404,9 @@ async fn process_event_data(
             let mount_point = if let Some(existing) = get_mount_point(&devnode) {
                 existing
             } else if config.auto_mount {
-                match mount_device(&devnode, &uuid, &supported_fs, config).await {
+                let read_only =
+                    config.read_only.contains(&uuid) || config.read_only.contains(&label);
+                match mount_device(&devnode, &uuid, &supported_fs, read_only, config).await {
                     Ok(mp) => {
                         mount_state
                             .lock()
@@ -352,6 +415,14 @@ async fn process_event_data(
                             .insert(uuid.clone(), mp.clone());
                         mp
                     }
+                    Err(e) if config.keep_unmountable => {
+                        warn!(
+                            device = %devnode.display(),
+                            error = %e,
+                            "Failed to mount device, imaging it unmounted"
+                        );
+                        PathBuf::new()
+                    }
                     Err(e) => {
                         error!(
                             device = %devnode.display(),
@@ -416,6 +487,25 @@ async fn mount_device(
     device_path: &Path,
     uuid: &str,
     fs_type: &SupportedFilesystem,
+    read_only: bool,
+    config: &LinuxAdapterConfig,
+) -> Result<PathBuf> {
+    let device_path = device_path.to_path_buf();
+    let uuid = uuid.to_string();
+    let fs_type = *fs_type;
+    let config = config.clone();
+    tokio::task::spawn_blocking(move || {
+        mount_device_blocking(&device_path, &uuid, &fs_type, read_only, &config)
+    })
+    .await?
+}
+
+/// `mount_device` for callers already off the async runtime.
+fn mount_device_blocking(
+    device_path: &Path,
+    uuid: &str,
+    fs_type: &SupportedFilesystem,
+    read_only: bool,
     config: &LinuxAdapterConfig,
 ) -> Result<PathBuf> {
     let mount_point = config.mount_base.join(uuid);
@@ -423,7 +513,10 @@ async fn mount_device(
     fs::create_dir_all(&mount_point)
         .with_context(|| format!("Failed to create mount point: {}", mount_point.display()))?;
 
-    let flags = MsFlags::MS_NOEXEC | MsFlags::MS_NOSUID;
+    let mut flags = MsFlags::MS_NOEXEC | MsFlags::MS_NOSUID;
+    if read_only {
+        flags |= MsFlags::MS_RDONLY | MsFlags::MS_NODEV;
+    }
 
     // Filesystem-specific options
     let options: Option<&str> = match fs_type {
@@ -434,26 +527,16 @@ async fn mount_device(
         _ => None,
     };
 
-    let device_path = device_path.to_path_buf();
-    let fs_str = fs_type.as_str().to_string();
-    let options = options.map(|s| s.to_string());
-
     let err_device = device_path.display().to_string();
     let err_mount = mount_point.display().to_string();
 
-    tokio::task::spawn_blocking({
-        let mount_point = mount_point.clone();
-        move || {
-            mount(
-                Some(device_path.as_path()),
-                mount_point.as_path(),
-                Some(fs_str.as_str()),
-                flags,
-                options.as_deref(),
-            )
-        }
-    })
-    .await?
+    mount(
+        Some(device_path),
+        mount_point.as_path(),
+        Some(fs_type.as_str()),
+        flags,
+        options,
+    )
     .with_context(|| format!("Failed to mount {} to {}", err_device, err_mount))?;
 
     debug!(
@@ -466,7 +549,7 @@ async fn mount_device(
 }
 
 /// Sync filesystem buffers for a mount point
-fn sync_filesystem(mount_point: &Path) -> Result<()> {
+pub(super) fn sync_filesystem(mount_point: &Path) -> Result<()> {
     let file = fs::File::open(mount_point)
         .with_context(|| format!("Failed to open mount point: {}", mount_point.display()))?;
 
@@ -0,0 +1,738 @@
+//! Hardware adapter for macOS.
+//!
+//! Cards are seen through DiskArbitration, which reports every volume as it
+//! appears and disappears, and are mounted and ejected with `diskutil`, so
+//! the daemon doesn't need to run as root. macOS mounts cards itself as they
+//! are inserted; the adapter waits for that, and mounts a card itself only
+//! if macOS doesn't (with `auto_mount` on). Quarantined cards are
+//! refused the automount, which would write Spotlight and fseventsd files to
+//! them, and mounted read-only by bksd instead.
+//!
+//! DiskArbitration identifies a volume by its own UUID, which for FAT and
+//! exFAT cards is derived from the volume serial and isn't the `XXXX-XXXX`
+//! Linux reports, so `[devices.<uuid>]` entries written on Linux don't match
+//! on macOS. Volume labels are the same on both.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::{CStr, CString, c_char, c_void};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+use core_foundation_sys::base::{
+    CFAllocatorRef, CFGetTypeID, CFIndex, CFRelease, CFTypeID, CFTypeRef, kCFAllocatorDefault,
+};
+use core_foundation_sys::dictionary::{CFDictionaryGetValue, CFDictionaryRef};
+use core_foundation_sys::number::{
+    CFBooleanGetTypeID, CFBooleanGetValue, CFBooleanRef, CFNumberGetTypeID, CFNumberGetValue,
+    CFNumberRef, kCFNumberSInt64Type,
+};
+use core_foundation_sys::runloop::{
+    CFRunLoopGetCurrent, CFRunLoopRef, CFRunLoopRunInMode, kCFRunLoopDefaultMode,
+};
+use core_foundation_sys::string::{
+    CFStringGetCString, CFStringGetLength, CFStringGetMaximumSizeForEncoding, CFStringGetTypeID,
+    CFStringRef, kCFStringEncodingUTF8,
+};
+use core_foundation_sys::url::{CFURLGetFileSystemRepresentation, CFURLGetTypeID, CFURLRef};
+use core_foundation_sys::uuid::{CFUUIDCreateString, CFUUIDGetTypeID, CFUUIDRef};
+use nix::sys::statvfs::{FsFlags, statvfs};
+use tokio::sync::{Notify, mpsc};
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, debug, error, info, info_span, warn};
+
+use crate::core::hardware::{
+    BlockDevice, HardwareAdapter, HardwareEvent, SupportedFilesystem, parent_disk,
+};
+
+type DASessionRef = *const c_void;
+type DADiskRef = *const c_void;
+type DADissenterRef = *const c_void;
+type DADiskCallback = extern "C" fn(disk: DADiskRef, context: *mut c_void);
+type DADiskApprovalCallback =
+    extern "C" fn(disk: DADiskRef, context: *mut c_void) -> DADissenterRef;
+
+/// `kDAReturnNotPermitted`
+const DA_RETURN_NOT_PERMITTED: i32 = 0xF8DA_0008_u32 as i32;
+
+#[link(name = "DiskArbitration", kind = "framework")]
+unsafe extern "C" {
+    static kDADiskDescriptionVolumeKindKey: CFStringRef;
+    static kDADiskDescriptionVolumeNameKey: CFStringRef;
+    static kDADiskDescriptionVolumePathKey: CFStringRef;
+    static kDADiskDescriptionVolumeUUIDKey: CFStringRef;
+    static kDADiskDescriptionMediaSizeKey: CFStringRef;
+    static kDADiskDescriptionDeviceInternalKey: CFStringRef;
+
+    fn DASessionCreate(allocator: CFAllocatorRef) -> DASessionRef;
+    fn DASessionScheduleWithRunLoop(
+        session: DASessionRef,
+        run_loop: CFRunLoopRef,
+        mode: CFStringRef,
+    );
+    fn DASessionUnscheduleFromRunLoop(
+        session: DASessionRef,
+        run_loop: CFRunLoopRef,
+        mode: CFStringRef,
+    );
+    fn DARegisterDiskAppearedCallback(
+        session: DASessionRef,
+        match_: CFDictionaryRef,
+        callback: DADiskCallback,
+        context: *mut c_void,
+    );
+    fn DARegisterDiskDisappearedCallback(
+        session: DASessionRef,
+        match_: CFDictionaryRef,
+        callback: DADiskCallback,
+        context: *mut c_void,
+    );
+    fn DARegisterDiskMountApprovalCallback(
+        session: DASessionRef,
+        match_: CFDictionaryRef,
+        callback: DADiskApprovalCallback,
+        context: *mut c_void,
+    );
+    fn DAUnregisterCallback(session: DASessionRef, callback: *mut c_void, context: *mut c_void);
+    fn DAUnregisterApprovalCallback(
+        session: DASessionRef,
+        callback: *mut c_void,
+        context: *mut c_void,
+    );
+    fn DADissenterCreate(
+        allocator: CFAllocatorRef,
+        status: i32,
+        string: CFStringRef,
+    ) -> DADissenterRef;
+    fn DADiskCreateFromBSDName(
+        allocator: CFAllocatorRef,
+        session: DASessionRef,
+        name: *const c_char,
+    ) -> DADiskRef;
+    fn DADiskCopyDescription(disk: DADiskRef) -> CFDictionaryRef;
+    fn DADiskGetBSDName(disk: DADiskRef) -> *const c_char;
+}
+
+/// How long to wait for macOS to automount a card before mounting it
+const AUTOMOUNT_WAIT: Duration = Duration::from_secs(5);
+
+/// Configuration for the macOS adapter
+#[derive(Debug, Clone)]
+pub struct MacosAdapterConfig {
+    /// Whether to mount devices macOS didn't automount
+    pub auto_mount: bool,
+    /// Volume UUIDs and labels to mount read-only (quarantined devices)
+    pub read_only: HashSet<String>,
+}
+
+impl Default for MacosAdapterConfig {
+    fn default() -> Self {
+        Self {
+            auto_mount: true,
+            read_only: HashSet::new(),
+        }
+    }
+}
+
+impl MacosAdapterConfig {
+    fn is_read_only(&self, disk: &DiskInfo) -> bool {
+        self.read_only.contains(&disk.uuid) || self.read_only.contains(&disk.label)
+    }
+}
+
+/// A volume as DiskArbitration describes it (Send-safe)
+#[derive(Debug, Clone)]
+struct DiskInfo {
+    /// e.g. `disk4s1`
+    bsd_name: String,
+    uuid: String,
+    label: String,
+    filesystem: SupportedFilesystem,
+    mount_point: Option<PathBuf>,
+    size: u64,
+}
+
+#[derive(Debug)]
+enum DiskEvent {
+    Appeared(DiskInfo),
+    Disappeared(DiskInfo),
+}
+
+/// State the DiskArbitration callbacks run with, on the session thread
+struct CallbackContext {
+    tx: mpsc::Sender<DiskEvent>,
+    /// Volumes connected before the session started, whose initial appear
+    /// events `list_devices` covers
+    present: HashSet<String>,
+    read_only: HashSet<String>,
+    /// BSD names bksd is mounting read-only itself
+    approved: Arc<Mutex<HashSet<String>>>,
+}
+
+pub struct MacosAdapter {
+    config: MacosAdapterConfig,
+    cancel_token: CancellationToken,
+    /// Map of UUID -> mount point for devices we mounted
+    mounted_by_us: Arc<Mutex<HashMap<String, PathBuf>>>,
+    approved: Arc<Mutex<HashSet<String>>>,
+    stopped_notify: Arc<Notify>,
+}
+
+impl MacosAdapter {
+    pub fn new(config: MacosAdapterConfig) -> Self {
+        Self {
+            config,
+            cancel_token: CancellationToken::new(),
+            mounted_by_us: Arc::new(Mutex::new(HashMap::new())),
+            approved: Arc::new(Mutex::new(HashSet::new())),
+            stopped_notify: Arc::new(Notify::new()),
+        }
+    }
+
+    pub fn with_defaults() -> Self {
+        Self::new(MacosAdapterConfig::default())
+    }
+}
+
+impl HardwareAdapter for MacosAdapter {
+    fn start(&self, event_sender: mpsc::Sender<HardwareEvent>) {
+        let cancel_token = self.cancel_token.clone();
+        let mounted_by_us = self.mounted_by_us.clone();
+        let approved = self.approved.clone();
+        let stopped_notify = self.stopped_notify.clone();
+        let config = self.config.clone();
+
+        let (disk_tx, mut disk_rx) = mpsc::channel::<DiskEvent>(32);
+
+        // DiskArbitration delivers its callbacks on the run loop of the
+        // thread that scheduled the session
+        let context = CallbackContext {
+            tx: disk_tx,
+            present: disk_names().into_iter().collect(),
+            read_only: config.read_only.clone(),
+            approved: approved.clone(),
+        };
+        let cancel_for_session = cancel_token.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = run_session_blocking(context, cancel_for_session) {
+                error!(error = %e, "DiskArbitration session error");
+            }
+        });
+
+        let da_span = info_span!("diskarbitration");
+        tokio::spawn(
+            async move {
+                info!("Event processor started");
+
+                while let Some(event) = disk_rx.recv().await {
+                    if cancel_token.is_cancelled() {
+                        break;
+                    }
+
+                    let hw_event = match event {
+                        DiskEvent::Appeared(disk) => {
+                            let config = config.clone();
+                            let mounted_by_us = mounted_by_us.clone();
+                            let approved = approved.clone();
+                            let prepared = tokio::task::spawn_blocking(move || {
+                                prepare(disk, &config, &mounted_by_us, &approved)
+                            })
+                            .await;
+                            let Ok(Some(device)) = prepared else {
+                                continue;
+                            };
+                            info!(
+                                label = %device.label,
+                                uuid = %device.uuid,
+                                mount_point = %device.mount_point.display(),
+                                "Device added"
+                            );
+                            HardwareEvent::DeviceAdded(device)
+                        }
+                        DiskEvent::Disappeared(disk) => {
+                            mounted_by_us.lock().unwrap().remove(&disk.uuid);
+                            info!(uuid = %disk.uuid, "Device removed");
+                            HardwareEvent::DeviceRemoved(disk.uuid)
+                        }
+                    };
+
+                    if event_sender.send(hw_event).await.is_err() {
+                        break;
+                    }
+                }
+
+                stopped_notify.notify_one();
+            }
+            .instrument(da_span),
+        );
+    }
+
+    fn stop(&self) {
+        self.cancel_token.cancel();
+    }
+
+    fn list_devices(&self) -> Result<Vec<BlockDevice>> {
+        Ok(disk_names()
+            .iter()
+            .filter_map(|name| describe_bsd(name))
+            .filter_map(|disk| prepare(disk, &self.config, &self.mounted_by_us, &self.approved))
+            .collect())
+    }
+
+    fn cleanup_device(&self, device: &BlockDevice) -> Result<()> {
+        debug!(
+            label = %device.label,
+            uuid = %device.uuid,
+            "Cleaning up device"
+        );
+
+        // macOS has no syncfs(); sync() flushes every filesystem
+        unsafe { libc::sync() };
+
+        let should_unmount = self
+            .mounted_by_us
+            .lock()
+            .unwrap()
+            .contains_key(&device.uuid);
+
+        if should_unmount {
+            debug!(
+                mount_point = %device.mount_point.display(),
+                "Unmounting device"
+            );
+            diskutil(&["unmount", &device.path.to_string_lossy()])?;
+            self.mounted_by_us.lock().unwrap().remove(&device.uuid);
+        } else {
+            debug!("Device was not mounted by us, skipping unmount");
+        }
+
+        Ok(())
+    }
+
+    fn eject_device(&self, device: &BlockDevice) -> Result<()> {
+        // Ejecting the whole disk unmounts every volume on it and lets the
+        // reader release the card
+        let disk = parent_disk(&device.path).unwrap_or_else(|| device.path.clone());
+        diskutil(&["eject", &disk.to_string_lossy()])
+            .with_context(|| format!("Failed to eject {}", disk.display()))?;
+
+        info!(disk = %disk.display(), label = %device.label, "Device ejected");
+        Ok(())
+    }
+}
+
+/// Run a DiskArbitration session on this thread until cancelled.
+fn run_session_blocking(context: CallbackContext, cancel_token: CancellationToken) -> Result<()> {
+    let context = Box::into_raw(Box::new(context)).cast::<c_void>();
+    unsafe {
+        let session = DASessionCreate(kCFAllocatorDefault);
+        if session.is_null() {
+            drop(Box::from_raw(context.cast::<CallbackContext>()));
+            bail!("Failed to create a DiskArbitration session");
+        }
+        let run_loop = CFRunLoopGetCurrent();
+        DASessionScheduleWithRunLoop(session, run_loop, kCFRunLoopDefaultMode);
+        DARegisterDiskAppearedCallback(session, std::ptr::null(), disk_appeared, context);
+        DARegisterDiskDisappearedCallback(session, std::ptr::null(), disk_disappeared, context);
+        DARegisterDiskMountApprovalCallback(session, std::ptr::null(), approve_mount, context);
+
+        info!("DiskArbitration session started");
+
+        while !cancel_token.is_cancelled() {
+            CFRunLoopRunInMode(kCFRunLoopDefaultMode, 0.5, 0);
+        }
+        info!("Shutdown requested, stopping DiskArbitration session");
+
+        DAUnregisterCallback(session, disk_appeared as *mut c_void, context);
+        DAUnregisterCallback(session, disk_disappeared as *mut c_void, context);
+        DAUnregisterApprovalCallback(session, approve_mount as *mut c_void, context);
+        DASessionUnscheduleFromRunLoop(session, run_loop, kCFRunLoopDefaultMode);
+        CFRelease(session);
+        drop(Box::from_raw(context.cast::<CallbackContext>()));
+    }
+    Ok(())
+}
+
+extern "C" fn disk_appeared(disk: DADiskRef, context: *mut c_void) {
+    let context = unsafe { &mut *context.cast::<CallbackContext>() };
+    let Some(disk) = (unsafe { describe(disk) }) else {
+        return;
+    };
+    // Already connected: list_devices reports it
+    if context.present.contains(&disk.bsd_name) {
+        return;
+    }
+    let _ = context.tx.blocking_send(DiskEvent::Appeared(disk));
+}
+
+extern "C" fn disk_disappeared(disk: DADiskRef, context: *mut c_void) {
+    let context = unsafe { &mut *context.cast::<CallbackContext>() };
+    let Some(disk) = (unsafe { describe(disk) }) else {
+        return;
+    };
+    // Reinserted, it is new again
+    context.present.remove(&disk.bsd_name);
+    let _ = context.tx.blocking_send(DiskEvent::Disappeared(disk));
+}
+
+/// Refuse the automount of quarantined cards, allowing only bksd's own
+/// read-only mount.
+extern "C" fn approve_mount(disk: DADiskRef, context: *mut c_void) -> DADissenterRef {
+    let context = unsafe { &*context.cast::<CallbackContext>() };
+    let Some(disk) = (unsafe { describe(disk) }) else {
+        return std::ptr::null();
+    };
+    let quarantined =
+        context.read_only.contains(&disk.uuid) || context.read_only.contains(&disk.label);
+    if !quarantined || context.approved.lock().unwrap().contains(&disk.bsd_name) {
+        return std::ptr::null();
+    }
+    debug!(device = %disk.bsd_name, "Refusing automount of quarantined device");
+    unsafe {
+        DADissenterCreate(
+            kCFAllocatorDefault,
+            DA_RETURN_NOT_PERMITTED,
+            std::ptr::null(),
+        )
+    }
+}
+
+/// Mount `disk` as configured and turn it into the device to back up, or
+/// None if it isn't to be backed up.
+fn prepare(
+    disk: DiskInfo,
+    config: &MacosAdapterConfig,
+    mounted_by_us: &Mutex<HashMap<String, PathBuf>>,
+    approved: &Mutex<HashSet<String>>,
+) -> Option<BlockDevice> {
+    let read_only = config.is_read_only(&disk);
+    let device = Path::new("/dev").join(&disk.bsd_name);
+
+    let mounted = match disk.mount_point.clone() {
+        Some(existing) if read_only && !is_read_only_mount(&existing) => {
+            // Mounted writable before bksd could refuse it
+            warn!(
+                device = %device.display(),
+                "Quarantined device is mounted read-write, remounting read-only"
+            );
+            diskutil(&["unmount", &device.to_string_lossy()])
+                .and_then(|()| mount(&disk, true, approved).map(Some))
+        }
+        Some(existing) => Ok(Some((existing, false))),
+        None if read_only => mount(&disk, true, approved).map(Some),
+        None => match wait_for_automount(&disk.bsd_name) {
+            Some(existing) => Ok(Some((existing, false))),
+            None if config.auto_mount => mount(&disk, false, approved).map(Some),
+            None => Ok(None),
+        },
+    };
+
+    let mount_point = match mounted {
+        Ok(Some((mount_point, ours))) => {
+            if ours {
+                mounted_by_us
+                    .lock()
+                    .unwrap()
+                    .insert(disk.uuid.clone(), mount_point.clone());
+            }
+            mount_point
+        }
+        Ok(None) => return None,
+        Err(e) => {
+            error!(
+                device = %device.display(),
+                error = %e,
+                "Failed to mount device"
+            );
+            return None;
+        }
+    };
+
+    Some(BlockDevice {
+        uuid: disk.uuid,
+        label: disk.label,
+        path: device,
+        mount_point,
+        capacity: disk.size,
+        filesystem: disk.filesystem.as_str().to_string(),
+    })
+}
+
+/// Where macOS automounted the volume `bsd_name`, once it has.
+fn wait_for_automount(bsd_name: &str) -> Option<PathBuf> {
+    let started = Instant::now();
+    loop {
+        if let Some(mount_point) = describe_bsd(bsd_name).and_then(|disk| disk.mount_point) {
+            return Some(mount_point);
+        }
+        if started.elapsed() >= AUTOMOUNT_WAIT {
+            return None;
+        }
+        std::thread::sleep(Duration::from_millis(250));
+    }
+}
+
+/// Mount `disk` with `diskutil`, returning where it went and that bksd
+/// mounted it.
+fn mount(
+    disk: &DiskInfo,
+    read_only: bool,
+    approved: &Mutex<HashSet<String>>,
+) -> Result<(PathBuf, bool)> {
+    let device = format!("/dev/{}", disk.bsd_name);
+    let result = if read_only {
+        approved.lock().unwrap().insert(disk.bsd_name.clone());
+        let result = diskutil(&["mount", "readOnly", &device]);
+        approved.lock().unwrap().remove(&disk.bsd_name);
+        result
+    } else {
+        diskutil(&["mount", &device])
+    };
+    result.with_context(|| format!("Failed to mount {}", device))?;
+
+    let mount_point = describe_bsd(&disk.bsd_name)
+        .and_then(|disk| disk.mount_point)
+        .with_context(|| format!("{} isn't mounted after diskutil mount", device))?;
+    debug!(
+        device = %device,
+        mount_point = %mount_point.display(),
+        read_only,
+        "Mounted device"
+    );
+    Ok((mount_point, true))
+}
+
+fn is_read_only_mount(mount_point: &Path) -> bool {
+    statvfs(mount_point).is_ok_and(|stat| stat.flags().contains(FsFlags::ST_RDONLY))
+}
+
+fn diskutil(args: &[&str]) -> Result<()> {
+    let output = Command::new("diskutil")
+        .args(args)
+        .output()
+        .context("Failed to run diskutil")?;
+    if !output.status.success() {
+        // diskutil reports some failures on stdout
+        let message = match String::from_utf8_lossy(&output.stderr).trim() {
+            "" => String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            stderr => stderr.to_string(),
+        };
+        bail!("diskutil {} failed: {}", args.join(" "), message);
+    }
+    Ok(())
+}
+
+/// BSD names of the disks and slices in /dev (`disk4`, `disk4s1`).
+fn disk_names() -> Vec<String> {
+    let Ok(entries) = fs::read_dir("/dev") else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| is_disk_name(name))
+        .collect()
+}
+
+fn is_disk_name(name: &str) -> bool {
+    name.strip_prefix("disk").is_some_and(|rest| {
+        let mut parts = rest.splitn(2, 's');
+        parts.all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+    })
+}
+
+/// Map a DiskArbitration volume kind to the filesystem bksd knows it as.
+fn filesystem(volume_kind: &str) -> Option<SupportedFilesystem> {
+    match volume_kind {
+        "msdos" => Some(SupportedFilesystem::Vfat),
+        kind => SupportedFilesystem::from_str(kind),
+    }
+}
+
+/// Describe the volume `bsd_name` as it is now.
+fn describe_bsd(bsd_name: &str) -> Option<DiskInfo> {
+    let name = CString::new(bsd_name).ok()?;
+    unsafe {
+        let session = DASessionCreate(kCFAllocatorDefault);
+        if session.is_null() {
+            return None;
+        }
+        let disk = DADiskCreateFromBSDName(kCFAllocatorDefault, session, name.as_ptr());
+        let info = if disk.is_null() {
+            None
+        } else {
+            let info = describe(disk);
+            CFRelease(disk);
+            info
+        };
+        CFRelease(session);
+        info
+    }
+}
+
+/// An external volume bksd can back up, or None for internal disks,
+/// unformatted media and filesystems it doesn't support.
+unsafe fn describe(disk: DADiskRef) -> Option<DiskInfo> {
+    unsafe {
+        let bsd_name = DADiskGetBSDName(disk);
+        if bsd_name.is_null() {
+            return None;
+        }
+        let bsd_name = CStr::from_ptr(bsd_name).to_str().ok()?.to_string();
+
+        let description = DADiskCopyDescription(disk);
+        if description.is_null() {
+            return None;
+        }
+        let info = describe_volume(bsd_name, description);
+        CFRelease(description.cast());
+        info
+    }
+}
+
+unsafe fn describe_volume(bsd_name: String, description: CFDictionaryRef) -> Option<DiskInfo> {
+    unsafe {
+        let internal = value(
+            description,
+            kDADiskDescriptionDeviceInternalKey,
+            CFBooleanGetTypeID(),
+        )
+        .is_some_and(|internal| CFBooleanGetValue(internal as CFBooleanRef));
+        if internal {
+            return None;
+        }
+        let kind = value(
+            description,
+            kDADiskDescriptionVolumeKindKey,
+            CFStringGetTypeID(),
+        )
+        .and_then(|kind| cf_string(kind as CFStringRef))?;
+        let filesystem = filesystem(&kind)?;
+
+        let uuid = value(
+            description,
+            kDADiskDescriptionVolumeUUIDKey,
+            CFUUIDGetTypeID(),
+        )
+        .and_then(|uuid| {
+            let string = CFUUIDCreateString(kCFAllocatorDefault, uuid as CFUUIDRef);
+            if string.is_null() {
+                return None;
+            }
+            let uuid = cf_string(string);
+            CFRelease(string.cast());
+            uuid
+        })?;
+        let label = value(
+            description,
+            kDADiskDescriptionVolumeNameKey,
+            CFStringGetTypeID(),
+        )
+        .and_then(|name| cf_string(name as CFStringRef))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| uuid.clone());
+        let mount_point = value(
+            description,
+            kDADiskDescriptionVolumePathKey,
+            CFURLGetTypeID(),
+        )
+        .and_then(|url| cf_url_path(url as CFURLRef));
+        let size = value(
+            description,
+            kDADiskDescriptionMediaSizeKey,
+            CFNumberGetTypeID(),
+        )
+        .and_then(|size| {
+            let mut bytes: i64 = 0;
+            CFNumberGetValue(
+                size as CFNumberRef,
+                kCFNumberSInt64Type,
+                (&mut bytes as *mut i64).cast(),
+            )
+            .then_some(bytes.max(0) as u64)
+        })
+        .unwrap_or(0);
+
+        Some(DiskInfo {
+            bsd_name,
+            uuid,
+            label,
+            filesystem,
+            mount_point,
+            size,
+        })
+    }
+}
+
+/// The value under `key` in `dictionary`, if it is there and of type
+/// `type_id`.
+unsafe fn value(
+    dictionary: CFDictionaryRef,
+    key: CFStringRef,
+    type_id: CFTypeID,
+) -> Option<CFTypeRef> {
+    unsafe {
+        let value = CFDictionaryGetValue(dictionary, key.cast());
+        (!value.is_null() && CFGetTypeID(value) == type_id).then_some(value)
+    }
+}
+
+unsafe fn cf_string(string: CFStringRef) -> Option<String> {
+    unsafe {
+        let capacity =
+            CFStringGetMaximumSizeForEncoding(CFStringGetLength(string), kCFStringEncodingUTF8) + 1;
+        let mut buffer = vec![0u8; capacity as usize];
+        if CFStringGetCString(
+            string,
+            buffer.as_mut_ptr().cast(),
+            capacity,
+            kCFStringEncodingUTF8,
+        ) == 0
+        {
+            return None;
+        }
+        CStr::from_bytes_until_nul(&buffer)
+            .ok()?
+            .to_str()
+            .ok()
+            .map(str::to_string)
+    }
+}
+
+unsafe fn cf_url_path(url: CFURLRef) -> Option<PathBuf> {
+    let mut buffer = [0u8; libc::PATH_MAX as usize];
+    let ok = unsafe {
+        CFURLGetFileSystemRepresentation(url, 1, buffer.as_mut_ptr(), buffer.len() as CFIndex)
+    };
+    if ok == 0 {
+        return None;
+    }
+    let path = CStr::from_bytes_until_nul(&buffer).ok()?.to_str().ok()?;
+    Some(PathBuf::from(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disk_names_and_filesystems() {
+        assert!(is_disk_name("disk4"));
+        assert!(is_disk_name("disk4s1"));
+        assert!(!is_disk_name("rdisk4s1"));
+        assert!(!is_disk_name("disk4s"));
+        assert!(!is_disk_name("disk"));
+        assert!(!is_disk_name("disk4s1s2"));
+        assert_eq!(
+            parent_disk(Path::new("/dev/disk4s1")),
+            Some(PathBuf::from("/dev/disk4"))
+        );
+        assert_eq!(parent_disk(Path::new("/dev/disk4")), None);
+
+        assert_eq!(filesystem("msdos"), Some(SupportedFilesystem::Vfat));
+        assert_eq!(filesystem("exfat"), Some(SupportedFilesystem::Exfat));
+        assert_eq!(filesystem("apfs"), None);
+    }
+}
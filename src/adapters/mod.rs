@@ -1,46 +1,134 @@
+use anyhow::Result;
+
 use crate::config::AppConfig;
+#[cfg(target_os = "linux")]
+use crate::config::HardwareAdapterType;
 use crate::core::hardware::HardwareAdapter;
 use tracing::warn;
 
 #[cfg(target_os = "linux")]
 pub mod linux;
+#[cfg(target_os = "macos")]
+pub mod macos;
 mod simulated;
+#[cfg(all(target_os = "linux", feature = "udisks2"))]
+pub mod udisks2;
 
 #[cfg(target_os = "linux")]
 pub use linux::{LinuxAdapter, LinuxAdapterConfig};
+#[cfg(target_os = "macos")]
+pub use macos::{MacosAdapter, MacosAdapterConfig};
 pub use simulated::{SimulatedAdapter, Simulator};
+#[cfg(all(target_os = "linux", feature = "udisks2"))]
+pub use udisks2::{Udisks2Adapter, Udisks2AdapterConfig};
 
-pub fn get_adapter(config: &AppConfig) -> Box<dyn HardwareAdapter> {
+/// The adapter devices arrive through. In simulation mode that is
+/// `simulator`'s (a fresh one if None), fed from stdin as well. Fails for
+/// an adapter this build doesn't include.
+pub fn get_adapter(
+    config: &AppConfig,
+    simulator: Option<&Simulator>,
+) -> Result<Box<dyn HardwareAdapter>> {
     if config.simulation {
-        let (adapter, controller) = simulated::SimulatedAdapter::new();
+        let controller = simulator.cloned().unwrap_or_default();
+        let adapter = controller.adapter();
 
         std::thread::spawn(move || {
             for line in std::io::stdin().lines().map_while(Result::ok) {
                 let parts: Vec<&str> = line.split_whitespace().collect();
                 match parts.as_slice() {
-                    ["add", uuid] => controller.add_device(uuid, 64),
-                    ["add"] => controller.add_device("123", 64),
-                    ["rm", uuid] => controller.remove_device(uuid),
-                    ["rm"] => controller.remove_device("123"),
+                    ["add", uuid] => {
+                        controller.add_device(uuid, 64);
+                    }
+                    ["add"] => {
+                        controller.add_device("123", 64);
+                    }
+                    ["rm", uuid] => {
+                        controller.remove_device(uuid);
+                    }
+                    ["rm"] => {
+                        controller.remove_device("123");
+                    }
                     _ => warn!(input = %line, "Invalid command. Use: 'add <uuid>' or 'rm <uuid>'"),
                 }
             }
         });
 
-        return Box::new(adapter);
+        return Ok(Box::new(adapter));
     }
 
     #[cfg(target_os = "linux")]
     {
+        let read_only = config
+            .devices
+            .iter()
+            .filter(|(_, device)| device.quarantine)
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        match config.hardware_adapter {
+            HardwareAdapterType::Udev => {}
+            #[cfg(feature = "udisks2")]
+            HardwareAdapterType::Udisks2 => {
+                let adapter_config = Udisks2AdapterConfig {
+                    auto_mount: true,
+                    read_only,
+                };
+                return Ok(Box::new(udisks2::Udisks2Adapter::new(adapter_config)));
+            }
+            #[cfg(not(feature = "udisks2"))]
+            HardwareAdapterType::Udisks2 => {
+                // A figment error, so the daemon exits with CONFIG_INVALID
+                anyhow::bail!(figment::Error::from(
+                    "The udisks2 adapter requires bksd built with the udisks2 feature".to_string()
+                ));
+            }
+        }
+
         let adapter_config = LinuxAdapterConfig {
             mount_base: config.mount_base.clone(),
             auto_mount: true,
+            keep_unmountable: config.transfer_engine
+                == crate::core::transfer_engine::TransferEngineType::RawImage,
+            read_only,
         };
-        Box::new(linux::LinuxAdapter::new(adapter_config))
+        Ok(Box::new(linux::LinuxAdapter::new(adapter_config)))
     }
 
-    #[cfg(not(target_os = "linux"))]
+    #[cfg(target_os = "macos")]
     {
-        panic!("Non-simulation mode only supported on Linux");
+        let adapter_config = MacosAdapterConfig {
+            auto_mount: true,
+            read_only: config
+                .devices
+                .iter()
+                .filter(|(_, device)| device.quarantine)
+                .map(|(key, _)| key.clone())
+                .collect(),
+        };
+        Ok(Box::new(macos::MacosAdapter::new(adapter_config)))
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        anyhow::bail!("Non-simulation mode only supported on Linux and macOS");
+    }
+}
+
+#[cfg(all(test, target_os = "linux", not(feature = "udisks2")))]
+mod tests {
+    use super::*;
+    use crate::cli::exit;
+
+    #[test]
+    fn test_missing_adapter_is_a_config_error() {
+        let config = AppConfig {
+            hardware_adapter: HardwareAdapterType::Udisks2,
+            ..Default::default()
+        };
+        let Err(e) = get_adapter(&config, None) else {
+            panic!("expected an error");
+        };
+        assert_eq!(exit::code_for(&e), exit::CONFIG_INVALID);
     }
 }
@@ -1,12 +1,14 @@
 use std::{
     path::PathBuf,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use anyhow::Result;
 use tokio::sync::mpsc;
 use tracing::{debug, info};
 
+use crate::config::SimProfile;
 use crate::core::hardware::{BlockDevice, HardwareAdapter, HardwareEvent};
 
 enum SimulatedCommand {
@@ -14,48 +16,152 @@ enum SimulatedCommand {
     InjectRemove(String),
 }
 
+type CommandReceiver = Arc<Mutex<Option<mpsc::UnboundedReceiver<SimulatedCommand>>>>;
+type InsertedDevices = Arc<Mutex<Vec<(BlockDevice, Option<SimProfile>)>>>;
+
+/// Injects simulated devices, from stdin, the `sim.*` RPC methods or tests.
 #[derive(Clone)]
 pub struct Simulator {
     tx: mpsc::UnboundedSender<SimulatedCommand>,
+    /// Taken by the adapter when it starts
+    rx: CommandReceiver,
+    /// Devices added and not yet removed, oldest first, with the profile
+    /// each was added with
+    devices: InsertedDevices,
+}
+
+impl Default for Simulator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Simulator {
-    pub fn add_device(&self, uuid: &str, size_gb: u64) {
-        let device = BlockDevice {
-            uuid: uuid.to_string(),
-            label: format!("TEST_DEVICE_{}", uuid),
-            path: PathBuf::from(format!("/tmp/test_{}", uuid)),
-            mount_point: PathBuf::from(format!("/tmp/mnt_{}", uuid)),
-            capacity: size_gb * 1024 * 1024 * 1024,
-            filesystem: "ext4".to_string(),
-        };
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            tx,
+            rx: Arc::new(Mutex::new(Some(rx))),
+            devices: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// The adapter this simulator's devices arrive through.
+    pub fn adapter(&self) -> SimulatedAdapter {
+        SimulatedAdapter {
+            cmd_rx: self.rx.clone(),
+        }
+    }
 
-        let _ = self.tx.send(SimulatedCommand::InjectAdd(device));
+    pub fn add_device(&self, uuid: &str, size_gb: u64) -> BlockDevice {
+        self.insert(device(uuid, size_gb, None, "ext4"), None)
     }
 
-    pub fn remove_device(&self, uuid: &str) {
+    /// Add a device as described by `profile`. Unless `remove_after_secs`
+    /// is set it stays until removed; with it, it is pulled after that long
+    /// (needs a Tokio runtime).
+    pub fn add_profiled(&self, uuid: &str, profile: SimProfile) -> BlockDevice {
+        let device = device(
+            uuid,
+            profile.size_gb,
+            profile.label.as_deref(),
+            &profile.filesystem,
+        );
+        let remove_after = profile.remove_after_secs;
+        let device = self.insert(device, Some(profile));
+
+        if let Some(secs) = remove_after
+            && let Ok(runtime) = tokio::runtime::Handle::try_current()
+        {
+            let simulator = self.clone();
+            let uuid = uuid.to_string();
+            runtime.spawn(async move {
+                tokio::time::sleep(Duration::from_secs(secs)).await;
+                info!(uuid = %uuid, "Pulling simulated device");
+                simulator.remove_device(&uuid);
+            });
+        }
+        device
+    }
+
+    fn insert(&self, device: BlockDevice, profile: Option<SimProfile>) -> BlockDevice {
+        {
+            let mut devices = self.devices.lock().unwrap();
+            devices.retain(|(d, _)| d.uuid != device.uuid);
+            devices.push((device.clone(), profile));
+        }
+        let _ = self.tx.send(SimulatedCommand::InjectAdd(device.clone()));
+        device
+    }
+
+    /// Profile an inserted device was added with, if any.
+    pub fn profile(&self, uuid: &str) -> Option<SimProfile> {
+        self.devices
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(d, _)| d.uuid == uuid)
+            .and_then(|(_, profile)| profile.clone())
+    }
+
+    /// Remove a device. Returns whether it had been added.
+    pub fn remove_device(&self, uuid: &str) -> bool {
+        let removed = {
+            let mut devices = self.devices.lock().unwrap();
+            let before = devices.len();
+            devices.retain(|(d, _)| d.uuid != uuid);
+            devices.len() < before
+        };
         let _ = self
             .tx
             .send(SimulatedCommand::InjectRemove(uuid.to_string()));
+        removed
+    }
+
+    /// Devices currently inserted, oldest first.
+    pub fn devices(&self) -> Vec<BlockDevice> {
+        self.devices
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(d, _)| d.clone())
+            .collect()
+    }
+
+    /// Lowest numbered UUID not already inserted, for adding a device
+    /// without naming one.
+    pub fn next_uuid(&self) -> String {
+        let devices = self.devices.lock().unwrap();
+        (1..)
+            .map(|n: u32| format!("sim-{}", n))
+            .find(|uuid| devices.iter().all(|(d, _)| &d.uuid != uuid))
+            .expect("unbounded range")
+    }
+}
+
+fn device(uuid: &str, size_gb: u64, label: Option<&str>, filesystem: &str) -> BlockDevice {
+    BlockDevice {
+        uuid: uuid.to_string(),
+        label: label
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("TEST_DEVICE_{}", uuid)),
+        path: PathBuf::from(format!("/tmp/test_{}", uuid)),
+        mount_point: PathBuf::from(format!("/tmp/mnt_{}", uuid)),
+        capacity: size_gb * 1024 * 1024 * 1024,
+        filesystem: filesystem.to_string(),
     }
 }
 
 pub struct SimulatedAdapter {
     // We wrap the receiver in a Mutex so we can move it out inside `start()`
     // which takes &self. (Start is only called once).
-    cmd_rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<SimulatedCommand>>>>,
+    cmd_rx: CommandReceiver,
 }
 
 impl SimulatedAdapter {
     pub fn new() -> (Self, Simulator) {
-        let (tx, rx) = mpsc::unbounded_channel();
-
-        (
-            Self {
-                cmd_rx: Arc::new(Mutex::new(Some(rx))),
-            },
-            Simulator { tx },
-        )
+        let simulator = Simulator::new();
+        (simulator.adapter(), simulator)
     }
 }
 
@@ -92,7 +198,7 @@ impl HardwareAdapter for SimulatedAdapter {
     }
 
     fn list_devices(&self) -> Result<Vec<BlockDevice>> {
-        // Simulated adapter doesn't track devices persistently
+        // Devices are tracked by the Simulator, not the adapter
         Ok(vec![])
     }
 
@@ -104,4 +210,9 @@ impl HardwareAdapter for SimulatedAdapter {
         );
         Ok(())
     }
+
+    fn eject_device(&self, device: &BlockDevice) -> Result<()> {
+        info!(label = %device.label, "SimulatedAdapter ejecting device");
+        Ok(())
+    }
 }
@@ -0,0 +1,580 @@
+//! Hardware adapter for Linux that goes through udisks2.
+//!
+//! The udev adapter mounts devices itself, which needs root. This one leaves
+//! detection and mounting to the udisks2 daemon over D-Bus, as desktop file
+//! managers do, so bksd can run as a normal user (a systemd user service).
+//! udisks2 asks polkit whether the user may mount and power off devices,
+//! which an active desktop session may by default; a user service outside
+//! any session needs a polkit rule allowing it.
+//!
+//! Cards are mounted where udisks2 puts them (`/run/media/<user>/<label>`),
+//! not under `mount_base`. A card the desktop automounted first is backed up
+//! where it is, unless it is quarantined and mounted read-write, in which
+//! case it is remounted read-only. Devices udisks2 hints are system or
+//! ignored (internal disks, recovery partitions) are never touched.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::os::unix::ffi::OsStringExt;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use nix::sys::statvfs::{FsFlags, statvfs};
+use tokio::sync::{Notify, mpsc};
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, debug, error, info, info_span, warn};
+use zbus::blocking;
+use zbus::export::futures_core::Stream;
+use zbus::names::InterfaceName;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+
+use super::linux::sync_filesystem;
+use crate::core::hardware::{BlockDevice, HardwareAdapter, HardwareEvent, SupportedFilesystem};
+
+const UDISKS2: &str = "org.freedesktop.UDisks2";
+const MANAGER_PATH: &str = "/org/freedesktop/UDisks2";
+const BLOCK_INTERFACE: &str = "org.freedesktop.UDisks2.Block";
+const FILESYSTEM_INTERFACE: &str = "org.freedesktop.UDisks2.Filesystem";
+const DRIVE_INTERFACE: &str = "org.freedesktop.UDisks2.Drive";
+
+/// Mount options, matching what the udev adapter mounts with
+const MOUNT_OPTIONS: &str = "noexec,nosuid";
+const READ_ONLY_MOUNT_OPTIONS: &str = "ro,noexec,nosuid,nodev";
+
+/// Properties of one D-Bus interface, by name
+type Properties = HashMap<String, OwnedValue>;
+
+/// Configuration for the udisks2 adapter
+#[derive(Debug, Clone)]
+pub struct Udisks2AdapterConfig {
+    /// Whether to mount devices nothing else has
+    pub auto_mount: bool,
+    /// Filesystem UUIDs and volume labels to mount read-only (quarantined
+    /// devices)
+    pub read_only: HashSet<String>,
+}
+
+impl Default for Udisks2AdapterConfig {
+    fn default() -> Self {
+        Self {
+            auto_mount: true,
+            read_only: HashSet::new(),
+        }
+    }
+}
+
+/// A block device with a filesystem, as udisks2 describes it
+#[derive(Debug, Clone)]
+struct Volume {
+    uuid: String,
+    label: String,
+    device: PathBuf,
+    filesystem: SupportedFilesystem,
+    size: u64,
+    /// Object of the drive the device is on (`/` if none)
+    drive: OwnedObjectPath,
+    mount_point: Option<PathBuf>,
+}
+
+impl Volume {
+    /// The volume described by an object's block and filesystem properties,
+    /// or None if it isn't one bksd backs up.
+    fn from_properties(block: &Properties, filesystem: &Properties) -> Option<Self> {
+        let hidden = get::<bool>(block, "HintSystem").unwrap_or(false)
+            || get::<bool>(block, "HintIgnore").unwrap_or(false);
+        if hidden {
+            return None;
+        }
+        let fs_type = SupportedFilesystem::from_str(&get::<String>(block, "IdType")?)?;
+        let uuid = get::<String>(block, "IdUUID").filter(|uuid| !uuid.is_empty())?;
+        let label = get::<String>(block, "IdLabel")
+            .filter(|label| !label.is_empty())
+            .unwrap_or_else(|| uuid.clone());
+        let device = bytes_to_path(get(block, "Device")?);
+        let mount_point = get::<Vec<Vec<u8>>>(filesystem, "MountPoints")
+            .and_then(|points| points.into_iter().next())
+            .map(bytes_to_path);
+
+        Some(Self {
+            uuid,
+            label,
+            device,
+            filesystem: fs_type,
+            size: get(block, "Size").unwrap_or(0),
+            drive: get(block, "Drive").unwrap_or_default(),
+            mount_point,
+        })
+    }
+}
+
+/// What the adapter and its event task share
+#[derive(Clone)]
+struct State {
+    config: Udisks2AdapterConfig,
+    /// System bus connection for blocking calls, made on first use
+    bus: Arc<Mutex<Option<blocking::Connection>>>,
+    /// Map of UUID -> mount point for devices we mounted
+    mounted_by_us: Arc<Mutex<HashMap<String, PathBuf>>>,
+    /// Map of object path -> UUID of devices reported, to tell which one
+    /// was removed
+    reported: Arc<Mutex<HashMap<String, String>>>,
+}
+
+pub struct Udisks2Adapter {
+    state: State,
+    cancel_token: CancellationToken,
+    stopped_notify: Arc<Notify>,
+}
+
+impl Udisks2Adapter {
+    pub fn new(config: Udisks2AdapterConfig) -> Self {
+        Self {
+            state: State {
+                config,
+                bus: Arc::new(Mutex::new(None)),
+                mounted_by_us: Arc::new(Mutex::new(HashMap::new())),
+                reported: Arc::new(Mutex::new(HashMap::new())),
+            },
+            cancel_token: CancellationToken::new(),
+            stopped_notify: Arc::new(Notify::new()),
+        }
+    }
+
+    pub fn with_defaults() -> Self {
+        Self::new(Udisks2AdapterConfig::default())
+    }
+}
+
+impl HardwareAdapter for Udisks2Adapter {
+    fn start(&self, event_sender: mpsc::Sender<HardwareEvent>) {
+        let cancel_token = self.cancel_token.clone();
+        let stopped_notify = self.stopped_notify.clone();
+        let state = self.state.clone();
+
+        let udisks_span = info_span!("udisks2_monitor");
+        tokio::spawn(
+            async move {
+                if let Err(e) = run_monitor(event_sender, state, cancel_token).await {
+                    error!(error = %e, "udisks2 monitor error");
+                }
+                stopped_notify.notify_one();
+            }
+            .instrument(udisks_span),
+        );
+    }
+
+    fn stop(&self) {
+        self.cancel_token.cancel();
+    }
+
+    fn list_devices(&self) -> Result<Vec<BlockDevice>> {
+        let bus = self.state.bus()?;
+        let objects = blocking::fdo::ObjectManagerProxy::builder(&bus)
+            .destination(UDISKS2)?
+            .path(MANAGER_PATH)?
+            .build()?
+            .get_managed_objects()
+            .context("Failed to list udisks2 objects")?;
+
+        let mut devices = Vec::new();
+        for (object, interfaces) in objects {
+            let interface = |name: &str| {
+                interfaces
+                    .iter()
+                    .find(|(interface, _)| interface.as_str() == name)
+                    .map(|(_, properties)| properties)
+            };
+            let (Some(block), Some(filesystem)) =
+                (interface(BLOCK_INTERFACE), interface(FILESYSTEM_INTERFACE))
+            else {
+                continue;
+            };
+            let Some(volume) = Volume::from_properties(block, filesystem) else {
+                continue;
+            };
+            if let Some(device) = self.state.prepare(object.as_str(), volume) {
+                devices.push(device);
+            }
+        }
+
+        Ok(devices)
+    }
+
+    fn cleanup_device(&self, device: &BlockDevice) -> Result<()> {
+        debug!(
+            label = %device.label,
+            uuid = %device.uuid,
+            "Cleaning up device"
+        );
+
+        sync_filesystem(&device.mount_point)?;
+
+        let should_unmount = self
+            .state
+            .mounted_by_us
+            .lock()
+            .unwrap()
+            .contains_key(&device.uuid);
+
+        if should_unmount {
+            debug!(
+                mount_point = %device.mount_point.display(),
+                "Unmounting device"
+            );
+            let object = self
+                .state
+                .object_of(&device.uuid)
+                .with_context(|| format!("{} is no longer known to udisks2", device.label))?;
+            self.state
+                .unmount(&object)
+                .with_context(|| format!("Failed to unmount {}", device.mount_point.display()))?;
+            self.state
+                .mounted_by_us
+                .lock()
+                .unwrap()
+                .remove(&device.uuid);
+        } else {
+            debug!("Device was not mounted by us, skipping unmount");
+        }
+
+        Ok(())
+    }
+
+    fn eject_device(&self, device: &BlockDevice) -> Result<()> {
+        let object = self
+            .state
+            .object_of(&device.uuid)
+            .with_context(|| format!("{} is no longer known to udisks2", device.label))?;
+        let volume = self
+            .state
+            .volume(&object)?
+            .with_context(|| format!("{} is no longer a volume", device.label))?;
+
+        // udisks2 won't power off a drive with a mounted filesystem, such as
+        // one the desktop mounted
+        if volume.mount_point.is_some() {
+            self.state.unmount(&object)?;
+        }
+        if volume.drive.as_str() == "/" {
+            anyhow::bail!("{} has no drive to power off", device.path.display());
+        }
+        // Power the whole drive off, as the udev adapter does: the kernel
+        // flushes and stops it, and USB readers cut power to the slot
+        let bus = self.state.bus()?;
+        blocking::Proxy::new(&bus, UDISKS2, volume.drive.as_str(), DRIVE_INTERFACE)?
+            .call_method("PowerOff", &(options(&[]),))
+            .with_context(|| format!("Failed to eject {}", device.path.display()))?;
+
+        info!(drive = %volume.drive.as_str(), label = %device.label, "Device ejected");
+        Ok(())
+    }
+}
+
+/// Report devices as udisks2 adds and removes them, until cancelled.
+async fn run_monitor(
+    event_sender: mpsc::Sender<HardwareEvent>,
+    state: State,
+    cancel_token: CancellationToken,
+) -> Result<()> {
+    let bus = zbus::Connection::system()
+        .await
+        .context("Failed to connect to the system bus")?;
+    let manager = zbus::fdo::ObjectManagerProxy::builder(&bus)
+        .destination(UDISKS2)?
+        .path(MANAGER_PATH)?
+        .build()
+        .await?;
+    let mut added = manager.receive_interfaces_added().await?;
+    let mut removed = manager.receive_interfaces_removed().await?;
+
+    info!("udisks2 monitor started");
+
+    loop {
+        let hw_event = tokio::select! {
+            _ = cancel_token.cancelled() => {
+                info!("Shutdown requested, stopping udisks2 monitor");
+                break;
+            }
+            Some(signal) = next(&mut added) => {
+                let Ok(args) = signal.args() else { continue };
+                // Partitions gain the filesystem interface once probed
+                if !args
+                    .interfaces_and_properties
+                    .keys()
+                    .any(|interface| interface.as_str() == FILESYSTEM_INTERFACE)
+                {
+                    continue;
+                }
+                let object = args.object_path.to_string();
+                let state = state.clone();
+                let prepared = tokio::task::spawn_blocking(move || {
+                    let volume = match state.volume(&object) {
+                        Ok(volume) => volume?,
+                        Err(e) => {
+                            warn!(object = %object, error = %e, "Failed to read device");
+                            return None;
+                        }
+                    };
+                    state.prepare(&object, volume)
+                })
+                .await;
+                let Ok(Some(device)) = prepared else { continue };
+                info!(
+                    label = %device.label,
+                    uuid = %device.uuid,
+                    mount_point = %device.mount_point.display(),
+                    "Device added"
+                );
+                HardwareEvent::DeviceAdded(device)
+            }
+            Some(signal) = next(&mut removed) => {
+                let Ok(args) = signal.args() else { continue };
+                if !args
+                    .interfaces
+                    .iter()
+                    .any(|interface| interface.as_str() == BLOCK_INTERFACE)
+                {
+                    continue;
+                }
+                let Some(uuid) = state
+                    .reported
+                    .lock()
+                    .unwrap()
+                    .remove(args.object_path.as_str())
+                else {
+                    continue;
+                };
+                state.mounted_by_us.lock().unwrap().remove(&uuid);
+                info!(uuid = %uuid, "Device removed");
+                HardwareEvent::DeviceRemoved(uuid)
+            }
+            else => break,
+        };
+
+        if event_sender.send(hw_event).await.is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// The next signal from a zbus signal stream.
+async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+    std::future::poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+}
+
+impl State {
+    fn bus(&self) -> Result<blocking::Connection> {
+        let mut bus = self.bus.lock().unwrap();
+        if let Some(bus) = bus.as_ref() {
+            return Ok(bus.clone());
+        }
+        let connection =
+            blocking::Connection::system().context("Failed to connect to the system bus")?;
+        *bus = Some(connection.clone());
+        Ok(connection)
+    }
+
+    /// Object path of the reported device `uuid`.
+    fn object_of(&self, uuid: &str) -> Option<String> {
+        self.reported
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, reported)| *reported == uuid)
+            .map(|(object, _)| object.clone())
+    }
+
+    fn properties(&self, object: &str, interface: &'static str) -> Result<Properties> {
+        let bus = self.bus()?;
+        let properties = blocking::fdo::PropertiesProxy::builder(&bus)
+            .destination(UDISKS2)?
+            .path(object)?
+            .build()?
+            .get_all(InterfaceName::from_static_str(interface)?)?;
+        Ok(properties)
+    }
+
+    /// The volume at `object` as it is now, or None if it isn't one bksd
+    /// backs up.
+    fn volume(&self, object: &str) -> Result<Option<Volume>> {
+        let block = self.properties(object, BLOCK_INTERFACE)?;
+        let filesystem = self.properties(object, FILESYSTEM_INTERFACE)?;
+        Ok(Volume::from_properties(&block, &filesystem))
+    }
+
+    /// Mount `volume` (at `object`) as configured and turn it into the
+    /// device to back up, or None if it isn't to be backed up.
+    fn prepare(&self, object: &str, volume: Volume) -> Option<BlockDevice> {
+        let read_only = self.config.read_only.contains(&volume.uuid)
+            || self.config.read_only.contains(&volume.label);
+
+        let mounted = match volume.mount_point.clone() {
+            Some(existing) if read_only && !is_read_only_mount(&existing) => {
+                // Automounted by the desktop before bksd saw it
+                warn!(
+                    device = %volume.device.display(),
+                    "Quarantined device is mounted read-write, remounting read-only"
+                );
+                self.unmount(object)
+                    .and_then(|()| self.mount(object, true))
+                    .map(Some)
+            }
+            Some(existing) => Ok(Some((existing, false))),
+            None if self.config.auto_mount => self.mount(object, read_only).map(Some),
+            None => Ok(None),
+        };
+
+        let mount_point = match mounted {
+            Ok(Some((mount_point, ours))) => {
+                if ours {
+                    self.mounted_by_us
+                        .lock()
+                        .unwrap()
+                        .insert(volume.uuid.clone(), mount_point.clone());
+                }
+                mount_point
+            }
+            Ok(None) => return None,
+            Err(e) => {
+                error!(
+                    device = %volume.device.display(),
+                    error = %e,
+                    "Failed to mount device"
+                );
+                return None;
+            }
+        };
+
+        self.reported
+            .lock()
+            .unwrap()
+            .insert(object.to_string(), volume.uuid.clone());
+
+        Some(BlockDevice {
+            uuid: volume.uuid,
+            label: volume.label,
+            path: volume.device,
+            mount_point,
+            capacity: volume.size,
+            filesystem: volume.filesystem.as_str().to_string(),
+        })
+    }
+
+    /// Mount the filesystem at `object`, returning where it went and
+    /// whether bksd mounted it.
+    fn mount(&self, object: &str, read_only: bool) -> Result<(PathBuf, bool)> {
+        let mount_options = match read_only {
+            true => READ_ONLY_MOUNT_OPTIONS,
+            false => MOUNT_OPTIONS,
+        };
+        let bus = self.bus()?;
+        let result = blocking::Proxy::new(&bus, UDISKS2, object, FILESYSTEM_INTERFACE)?
+            .call::<_, _, String>("Mount", &(options(&[("options", mount_options)]),));
+        match result {
+            Ok(mount_point) => {
+                debug!(
+                    object = %object,
+                    mount_point = %mount_point,
+                    read_only,
+                    "Mounted device"
+                );
+                Ok((PathBuf::from(mount_point), true))
+            }
+            // The desktop got there first
+            Err(e) => match self.volume(object)?.and_then(|volume| volume.mount_point) {
+                Some(mount_point) if !read_only || is_read_only_mount(&mount_point) => {
+                    Ok((mount_point, false))
+                }
+                _ => Err(e).context("udisks2 refused to mount"),
+            },
+        }
+    }
+
+    fn unmount(&self, object: &str) -> Result<()> {
+        let bus = self.bus()?;
+        blocking::Proxy::new(&bus, UDISKS2, object, FILESYSTEM_INTERFACE)?
+            .call_method("Unmount", &(options(&[]),))
+            .context("udisks2 refused to unmount")?;
+        Ok(())
+    }
+}
+
+/// The `a{sv}` options udisks2 methods take, never prompting for a password.
+fn options<'a>(values: &[(&'a str, &'a str)]) -> HashMap<&'a str, Value<'a>> {
+    let mut options: HashMap<&str, Value> = values
+        .iter()
+        .map(|(key, value)| (*key, Value::from(*value)))
+        .collect();
+    options.insert("auth.no_user_interaction", Value::from(true));
+    options
+}
+
+/// Property `key`, if it is there and of type `T`.
+fn get<T: TryFrom<OwnedValue>>(properties: &Properties, key: &str) -> Option<T> {
+    properties.get(key)?.try_clone().ok()?.try_into().ok()
+}
+
+/// A path from a NUL-terminated D-Bus byte string.
+fn bytes_to_path(mut bytes: Vec<u8>) -> PathBuf {
+    while bytes.last() == Some(&0) {
+        bytes.pop();
+    }
+    PathBuf::from(OsString::from_vec(bytes))
+}
+
+fn is_read_only_mount(mount_point: &Path) -> bool {
+    statvfs(mount_point).is_ok_and(|stat| stat.flags().contains(FsFlags::ST_RDONLY))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(values: Vec<(&str, Value<'static>)>) -> Properties {
+        values
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.try_into().unwrap()))
+            .collect()
+    }
+
+    fn block(id_type: &'static str, hint_system: bool) -> Properties {
+        properties(vec![
+            ("IdType", Value::from(id_type)),
+            ("IdUUID", Value::from("1234-ABCD")),
+            ("IdLabel", Value::from("")),
+            ("Device", Value::from(b"/dev/sdb1\0".to_vec())),
+            ("Size", Value::from(64_000_000_000u64)),
+            ("HintSystem", Value::from(hint_system)),
+        ])
+    }
+
+    #[test]
+    fn test_volume_from_properties() {
+        let card = block("exfat", false);
+        let mounted = properties(vec![(
+            "MountPoints",
+            Value::from(vec![b"/run/media/photo/1234-ABCD\0".to_vec()]),
+        )]);
+
+        let volume = Volume::from_properties(&card, &Properties::new()).unwrap();
+        assert_eq!(volume.label, "1234-ABCD");
+        assert_eq!(volume.device, PathBuf::from("/dev/sdb1"));
+        assert_eq!(volume.filesystem, SupportedFilesystem::Exfat);
+        assert_eq!(volume.size, 64_000_000_000);
+        assert_eq!(volume.mount_point, None);
+
+        let volume = Volume::from_properties(&card, &mounted).unwrap();
+        assert_eq!(
+            volume.mount_point,
+            Some(PathBuf::from("/run/media/photo/1234-ABCD"))
+        );
+
+        // Internal disks and filesystems bksd doesn't back up
+        assert!(Volume::from_properties(&block("exfat", true), &mounted).is_none());
+        assert!(Volume::from_properties(&block("xfs", false), &mounted).is_none());
+    }
+}
@@ -0,0 +1,188 @@
+//! Process exit codes for CLI commands.
+//!
+//! Scripts can branch on the outcome of `bksd status`, `bksd jobs`,
+//! `bksd wait`, `bksd plan` and `bksd verify` without parsing output. Anything not covered by a specific
+//! code exits with 1.
+
+use std::fmt;
+use std::process::ExitCode;
+
+use crate::rpc::client::ClientError;
+
+/// Command succeeded (and the job it reported on, if any, succeeded).
+pub const SUCCESS: u8 = 0;
+/// Unclassified error.
+pub const ERROR: u8 = 1;
+/// The daemon could not be reached over RPC.
+pub const DAEMON_UNREACHABLE: u8 = 2;
+/// The job being reported on failed.
+pub const JOB_FAILED: u8 = 3;
+/// The job being reported on, or the folder given to `bksd verify`, failed
+/// hash verification.
+pub const VERIFICATION_FAILED: u8 = 4;
+/// The configuration file or environment could not be parsed.
+pub const CONFIG_INVALID: u8 = 5;
+/// `bksd wait` gave up before the jobs finished.
+pub const TIMED_OUT: u8 = 6;
+/// `bksd plan` found the cards won't fit, even after archive tiering.
+pub const INSUFFICIENT_SPACE: u8 = 7;
+
+/// A command ran fine but the job it reported on did not succeed.
+#[derive(Debug)]
+pub enum JobOutcome {
+    Failed { job_id: String, error: String },
+    VerificationFailed { job_id: String, error: String },
+}
+
+impl JobOutcome {
+    /// Classify a failed job from its recorded error message.
+    pub fn from_error(job_id: &str, error: &str) -> Self {
+        let job_id = job_id.to_string();
+        let error = error.to_string();
+        if error.starts_with("Verification failed") {
+            JobOutcome::VerificationFailed { job_id, error }
+        } else {
+            JobOutcome::Failed { job_id, error }
+        }
+    }
+}
+
+impl fmt::Display for JobOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobOutcome::Failed { job_id, error } => write!(f, "Job {} failed: {}", job_id, error),
+            JobOutcome::VerificationFailed { job_id, error } => {
+                write!(f, "Job {} failed verification: {}", job_id, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for JobOutcome {}
+
+/// A wait deadline passed before the awaited jobs finished.
+#[derive(Debug)]
+pub struct TimedOut {
+    pub secs: u64,
+}
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Timed out after {}s waiting for jobs to finish",
+            self.secs
+        )
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
+/// A backup folder didn't match its badge.
+#[derive(Debug)]
+pub struct NotVerified {
+    pub path: String,
+    pub reason: String,
+}
+
+impl fmt::Display for NotVerified {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failed verification: {}", self.path, self.reason)
+    }
+}
+
+impl std::error::Error for NotVerified {}
+
+/// A capacity plan came up short.
+#[derive(Debug)]
+pub struct InsufficientSpace {
+    pub count: u64,
+    pub fit: u64,
+}
+
+impl fmt::Display for InsufficientSpace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Not enough space: only {} of {} card(s) fit",
+            self.fit, self.count
+        )
+    }
+}
+
+impl std::error::Error for InsufficientSpace {}
+
+/// Pick the exit code for an error by looking through its cause chain.
+pub fn code_for(err: &anyhow::Error) -> u8 {
+    for cause in err.chain() {
+        if let Some(ClientError::Connect(_)) = cause.downcast_ref::<ClientError>() {
+            return DAEMON_UNREACHABLE;
+        }
+        if cause.downcast_ref::<figment::Error>().is_some() {
+            return CONFIG_INVALID;
+        }
+        if cause.downcast_ref::<TimedOut>().is_some() {
+            return TIMED_OUT;
+        }
+        if cause.downcast_ref::<InsufficientSpace>().is_some() {
+            return INSUFFICIENT_SPACE;
+        }
+        if cause.downcast_ref::<NotVerified>().is_some() {
+            return VERIFICATION_FAILED;
+        }
+        match cause.downcast_ref::<JobOutcome>() {
+            Some(JobOutcome::Failed { .. }) => return JOB_FAILED,
+            Some(JobOutcome::VerificationFailed { .. }) => return VERIFICATION_FAILED,
+            None => {}
+        }
+    }
+    ERROR
+}
+
+/// Print the error the way `main() -> Result` would and map it to an exit code.
+pub fn report(result: anyhow::Result<()>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::from(SUCCESS),
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            ExitCode::from(code_for(&e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_codes_follow_cause_chain() {
+        let unreachable = Err::<(), _>(ClientError::Connect(std::io::Error::from(
+            std::io::ErrorKind::ConnectionRefused,
+        )))
+        .context("Failed to connect to daemon. Is it running?")
+        .unwrap_err();
+        assert_eq!(code_for(&unreachable), DAEMON_UNREACHABLE);
+
+        let verify = anyhow::Error::new(JobOutcome::from_error(
+            "job-1",
+            "Verification failed: 1 file(s) did not match",
+        ));
+        assert_eq!(code_for(&verify), VERIFICATION_FAILED);
+
+        let failed = anyhow::Error::new(JobOutcome::from_error("job-1", "No space left"));
+        assert_eq!(code_for(&failed), JOB_FAILED);
+
+        assert_eq!(code_for(&TimedOut { secs: 5 }.into()), TIMED_OUT);
+        assert_eq!(
+            code_for(&InsufficientSpace { count: 4, fit: 2 }.into()),
+            INSUFFICIENT_SPACE
+        );
+        let badge = NotVerified {
+            path: "/backups/A001".to_string(),
+            reason: "Backup contents changed since it was badged".to_string(),
+        };
+        assert_eq!(code_for(&badge.into()), VERIFICATION_FAILED);
+        assert_eq!(code_for(&anyhow::anyhow!("boom")), ERROR);
+    }
+}
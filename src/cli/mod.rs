@@ -2,4 +2,5 @@
 //!
 //! Contains the interactive TUI and related utilities.
 
+pub mod exit;
 pub mod tui;
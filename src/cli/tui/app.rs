@@ -1,20 +1,29 @@
 //! TUI application state and logic.
 
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::net::SocketAddr;
 
 use anyhow::Result;
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::{Position, Rect};
 use serde::Deserialize;
 
 use crate::core::models::{Job, JobWithHistory};
-use crate::core::transfer_engine::TransferStatus;
+use crate::core::timestamp::DisplayZone;
+use crate::core::transfer_engine::{FileHash, TransferStatus};
+use crate::db::files::DuplicateFile;
 use crate::rpc::RpcClient;
 
+use super::input::{KeyMap, PaletteKey};
+use super::theme::Theme;
+
 /// Response type for daemon.status RPC call.
 #[derive(Debug, Clone, Deserialize)]
 pub struct DaemonStatus {
     pub version: String,
     pub uptime_secs: u64,
+    #[allow(dead_code)]
     pub active_jobs: usize,
     pub simulation: bool,
 }
@@ -23,9 +32,17 @@ pub struct DaemonStatus {
 #[derive(Debug, Clone, Deserialize)]
 pub struct ActiveProgress {
     pub jobs: HashMap<String, TransferStatus>,
+    #[allow(dead_code)]
     pub count: usize,
 }
 
+/// A simulated device, from the sim.devices RPC call.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SimDevice {
+    pub uuid: String,
+    pub capacity: u64,
+}
+
 /// Cached data fetched from the daemon via RPC.
 #[derive(Debug, Default)]
 pub struct AppData {
@@ -34,6 +51,12 @@ pub struct AppData {
     pub recent_jobs: Vec<Job>,
     pub all_jobs: Vec<Job>,
     pub selected_job: Option<JobWithHistory>,
+    /// Manifest of the selected job, empty for engines that keep none
+    pub selected_files: Vec<FileHash>,
+    /// Files of the selected job that other jobs also backed up
+    pub selected_duplicates: Vec<DuplicateFile>,
+    /// Devices inserted on a simulation-mode daemon, oldest first
+    pub sim_devices: Vec<SimDevice>,
 }
 
 /// Current view being displayed.
@@ -50,6 +73,8 @@ pub enum View {
         selected: usize,
         /// Pagination offset
         offset: u32,
+        /// Only show jobs with this status
+        status: Option<String>,
     },
     /// Single job detail view.
     Detail {
@@ -66,6 +91,26 @@ impl Default for View {
     }
 }
 
+/// Modal layer drawn on top of the current view.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Overlay {
+    #[default]
+    None,
+    /// Key and command reference
+    Help,
+    /// `:` command palette with the text typed so far
+    Palette(String),
+}
+
+/// Screen regions from the last render, used to resolve mouse clicks.
+#[derive(Debug, Default)]
+pub struct HitAreas {
+    /// Inner area of the visible job list (one row per job)
+    pub list: Option<Rect>,
+    /// Clickable footer hints
+    pub footer: Vec<(Rect, Action)>,
+}
+
 /// Actions that can be triggered by user input.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Action {
@@ -78,6 +123,115 @@ pub enum Action {
     Back,
     Refresh,
     History,
+    Help,
+    Palette,
+    AddDevice,
+    RemoveDevice,
+}
+
+impl Action {
+    /// Parse an action name as used in the `[tui.keybindings]` config section.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "quit" => Some(Self::Quit),
+            "up" => Some(Self::Up),
+            "down" => Some(Self::Down),
+            "left" => Some(Self::Left),
+            "right" => Some(Self::Right),
+            "select" => Some(Self::Select),
+            "back" => Some(Self::Back),
+            "refresh" => Some(Self::Refresh),
+            "history" => Some(Self::History),
+            "help" => Some(Self::Help),
+            "palette" => Some(Self::Palette),
+            "add_device" => Some(Self::AddDevice),
+            "remove_device" => Some(Self::RemoveDevice),
+            _ => None,
+        }
+    }
+
+    /// Human-readable description for the help overlay.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Quit => "Quit",
+            Self::Up => "Move up / scroll up",
+            Self::Down => "Move down / scroll down",
+            Self::Left => "Left",
+            Self::Right => "Right",
+            Self::Select => "Open job details",
+            Self::Back => "Back to dashboard",
+            Self::Refresh => "Refresh",
+            Self::History => "Job history",
+            Self::Help => "Toggle this help",
+            Self::Palette => "Command palette",
+            Self::AddDevice => "Insert a simulated device",
+            Self::RemoveDevice => "Remove the newest simulated device",
+        }
+    }
+}
+
+/// Command entered in the `:` palette.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaletteCommand {
+    /// Open a job by full id or unique prefix
+    Job(String),
+    /// Cancel an active job by full id or unique prefix
+    Cancel(String),
+    /// Show history filtered by status (None clears the filter)
+    Filter(Option<String>),
+    History,
+    Refresh,
+    Help,
+    Quit,
+    /// Insert a simulated device, optionally with a given UUID
+    AddDevice(Option<String>),
+    /// Remove a simulated device (None removes the newest)
+    RemoveDevice(Option<String>),
+}
+
+impl PaletteCommand {
+    /// Palette commands and their usage, for the help overlay.
+    pub const USAGE: &'static [(&'static str, &'static str)] = &[
+        ("job <id>", "Open a job by id or id prefix"),
+        ("cancel <id>", "Cancel an active job by id or id prefix"),
+        (
+            "filter [status]",
+            "History filtered by status (no status clears)",
+        ),
+        ("history", "Job history"),
+        ("refresh", "Refresh"),
+        ("help", "Show help"),
+        ("quit", "Quit"),
+        ("add [uuid]", "Insert a simulated device"),
+        (
+            "rm [uuid]",
+            "Remove a simulated device (newest if none given)",
+        ),
+    ];
+
+    /// Parse palette input such as `job 0192ab` or `filter failed`.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut parts = input.split_whitespace();
+        let Some(command) = parts.next() else {
+            return Err("empty command".to_string());
+        };
+        let arg = parts.next().map(str::to_string);
+
+        match (command, arg) {
+            ("job" | "j", Some(id)) => Ok(Self::Job(id)),
+            ("job" | "j", None) => Err("usage: job <id>".to_string()),
+            ("cancel" | "c", Some(id)) => Ok(Self::Cancel(id)),
+            ("cancel" | "c", None) => Err("usage: cancel <id>".to_string()),
+            ("filter" | "f", status) => Ok(Self::Filter(status)),
+            ("history", _) => Ok(Self::History),
+            ("refresh" | "r", _) => Ok(Self::Refresh),
+            ("help" | "h", _) => Ok(Self::Help),
+            ("quit" | "q", _) => Ok(Self::Quit),
+            ("add", uuid) => Ok(Self::AddDevice(uuid)),
+            ("rm" | "remove", uuid) => Ok(Self::RemoveDevice(uuid)),
+            (other, _) => Err(format!("unknown command: {}", other)),
+        }
+    }
 }
 
 /// Main TUI application state.
@@ -85,17 +239,29 @@ pub struct TuiApp {
     client: RpcClient,
     pub view: View,
     pub data: AppData,
+    pub keymap: KeyMap,
+    pub theme: Theme,
+    /// Zone timestamps are shown in
+    pub zone: DisplayZone,
+    pub overlay: Overlay,
+    /// Written during render, read when handling mouse input
+    pub hit_areas: RefCell<HitAreas>,
     pub running: bool,
     pub error: Option<String>,
 }
 
 impl TuiApp {
     /// Create a new TUI application connected to the daemon at the given address.
-    pub fn new(addr: SocketAddr) -> Self {
+    pub fn new(addr: SocketAddr, keymap: KeyMap, theme: Theme, zone: DisplayZone) -> Self {
         Self {
             client: RpcClient::new(addr),
             view: View::default(),
             data: AppData::default(),
+            keymap,
+            theme,
+            zone,
+            overlay: Overlay::None,
+            hit_areas: RefCell::new(HitAreas::default()),
             running: true,
             error: None,
         }
@@ -143,9 +309,88 @@ impl TuiApp {
             Err(e) => self.error = Some(format!("Failed to fetch jobs: {}", e)),
         }
 
+        if self.is_simulation() {
+            match self
+                .client
+                .call::<Vec<SimDevice>>("sim.devices", Some(serde_json::json!({})))
+                .await
+            {
+                Ok(devices) => self.data.sim_devices = devices,
+                Err(e) => self.error = Some(format!("Failed to fetch devices: {}", e)),
+            }
+        }
+
         Ok(())
     }
 
+    /// Whether the daemon is in simulation mode, so devices can be injected.
+    pub fn is_simulation(&self) -> bool {
+        self.data
+            .daemon_status
+            .as_ref()
+            .is_some_and(|status| status.simulation)
+    }
+
+    /// Insert a simulated device, then refresh so its job shows up.
+    async fn add_device(&mut self, uuid: Option<String>) {
+        if !self.is_simulation() {
+            self.error = Some("Daemon is not in simulation mode".to_string());
+            return;
+        }
+        if let Err(e) = self
+            .client
+            .call::<SimDevice>("sim.add", Some(serde_json::json!({ "uuid": uuid })))
+            .await
+        {
+            self.error = Some(format!("Failed to add device: {}", e));
+            return;
+        }
+        let _ = self.refresh_dashboard().await;
+    }
+
+    /// Cancel an active job, resolving `prefix` against the active jobs.
+    async fn cancel_job(&mut self, prefix: String) {
+        let mut matches = self
+            .data
+            .active_jobs
+            .keys()
+            .filter(|id| id.starts_with(&prefix));
+        let id = match (matches.next(), matches.next()) {
+            (Some(id), None) => id.clone(),
+            (Some(_), Some(_)) => {
+                self.error = Some(format!("Ambiguous job prefix: {}", prefix));
+                return;
+            }
+            (None, _) => prefix,
+        };
+        if let Err(e) = self
+            .client
+            .call::<serde_json::Value>("jobs.cancel", Some(serde_json::json!({ "id": id })))
+            .await
+        {
+            self.error = Some(format!("Failed to cancel job: {}", e));
+            return;
+        }
+        self.refresh_active_jobs().await;
+    }
+
+    /// Remove a simulated device (the newest if `uuid` is None).
+    async fn remove_device(&mut self, uuid: Option<String>) {
+        if !self.is_simulation() {
+            self.error = Some("Daemon is not in simulation mode".to_string());
+            return;
+        }
+        if let Err(e) = self
+            .client
+            .call::<serde_json::Value>("sim.remove", Some(serde_json::json!({ "uuid": uuid })))
+            .await
+        {
+            self.error = Some(format!("Failed to remove device: {}", e));
+            return;
+        }
+        let _ = self.refresh_dashboard().await;
+    }
+
     /// Refresh only active jobs (for polling during dashboard view).
     pub async fn refresh_active_jobs(&mut self) {
         if let Ok(progress) = self
@@ -157,13 +402,13 @@ impl TuiApp {
         }
     }
 
-    /// Fetch all jobs for the history view.
-    pub async fn fetch_history(&mut self, offset: u32) {
+    /// Fetch all jobs for the history view, optionally filtered by status.
+    pub async fn fetch_history(&mut self, offset: u32, status: Option<&str>) {
         match self
             .client
             .call::<Vec<Job>>(
                 "jobs.list",
-                Some(serde_json::json!({ "limit": 50, "offset": offset })),
+                Some(serde_json::json!({ "limit": 50, "offset": offset, "status": status })),
             )
             .await
         {
@@ -180,24 +425,41 @@ impl TuiApp {
             .await
         {
             Ok(job) => self.data.selected_job = Some(job),
-            Err(e) => self.error = Some(format!("Failed to fetch job: {}", e)),
+            Err(e) => {
+                self.error = Some(format!("Failed to fetch job: {}", e));
+                return;
+            }
         }
+
+        let params = Some(serde_json::json!({ "id": job_id }));
+        self.data.selected_files = self
+            .client
+            .call("jobs.files", params.clone())
+            .await
+            .unwrap_or_default();
+        self.data.selected_duplicates = self
+            .client
+            .call("jobs.duplicates", params)
+            .await
+            .unwrap_or_default();
     }
 
     /// Handle an action and update state accordingly.
     pub async fn handle_action(&mut self, action: Action) {
+        // Any key dismisses the help overlay
+        if self.overlay == Overlay::Help && action != Action::Quit {
+            self.overlay = Overlay::None;
+            return;
+        }
+
         match action {
             Action::Quit => self.running = false,
+            Action::Help => self.overlay = Overlay::Help,
+            Action::Palette => self.overlay = Overlay::Palette(String::new()),
             Action::Refresh => {
                 let _ = self.refresh_dashboard().await;
             }
-            Action::History => {
-                self.fetch_history(0).await;
-                self.view = View::History {
-                    selected: 0,
-                    offset: 0,
-                };
-            }
+            Action::History => self.show_history(None).await,
             Action::Back => {
                 self.view = View::Dashboard { selected: 0 };
                 let _ = self.refresh_dashboard().await;
@@ -206,6 +468,8 @@ impl TuiApp {
             Action::Down => self.navigate_down(),
             Action::Left | Action::Right => {} // No-op in simplified UI
             Action::Select => self.select_item().await,
+            Action::AddDevice => self.add_device(None).await,
+            Action::RemoveDevice => self.remove_device(None).await,
         }
     }
 
@@ -218,11 +482,16 @@ impl TuiApp {
                     };
                 }
             }
-            View::History { selected, offset } => {
+            View::History {
+                selected,
+                offset,
+                status,
+            } => {
                 if *selected > 0 {
                     self.view = View::History {
                         selected: *selected - 1,
                         offset: *offset,
+                        status: status.clone(),
                     };
                 }
             }
@@ -244,11 +513,16 @@ impl TuiApp {
                     };
                 }
             }
-            View::History { selected, offset } => {
+            View::History {
+                selected,
+                offset,
+                status,
+            } => {
                 if *selected + 1 < self.data.all_jobs.len() {
                     self.view = View::History {
                         selected: *selected + 1,
                         offset: *offset,
+                        status: status.clone(),
                     };
                 }
             }
@@ -261,26 +535,152 @@ impl TuiApp {
         }
     }
 
+    /// Handle a mouse event: wheel scrolls, clicks select list rows or
+    /// trigger footer hints. Clicking the selected row opens it.
+    pub async fn handle_mouse(&mut self, mouse: MouseEvent) {
+        match mouse.kind {
+            MouseEventKind::ScrollUp => self.handle_action(Action::Up).await,
+            MouseEventKind::ScrollDown => self.handle_action(Action::Down).await,
+            MouseEventKind::Down(MouseButton::Left) => {
+                let position = Position::new(mouse.column, mouse.row);
+
+                if self.overlay != Overlay::None {
+                    self.overlay = Overlay::None;
+                    return;
+                }
+
+                let (footer_action, row) = {
+                    let hit_areas = self.hit_areas.borrow();
+                    let footer_action = hit_areas
+                        .footer
+                        .iter()
+                        .find(|(rect, _)| rect.contains(position))
+                        .map(|(_, action)| *action);
+                    let row = hit_areas
+                        .list
+                        .filter(|rect| rect.contains(position))
+                        .map(|rect| (mouse.row - rect.y) as usize);
+                    (footer_action, row)
+                };
+
+                if let Some(action) = footer_action {
+                    self.handle_action(action).await;
+                } else if let Some(row) = row {
+                    self.click_row(row).await;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    async fn click_row(&mut self, row: usize) {
+        match &self.view {
+            View::Dashboard { selected } if row < self.data.recent_jobs.len() => {
+                if row == *selected {
+                    self.select_item().await;
+                } else {
+                    self.view = View::Dashboard { selected: row };
+                }
+            }
+            View::History {
+                selected,
+                offset,
+                status,
+            } if row < self.data.all_jobs.len() => {
+                if row == *selected {
+                    self.select_item().await;
+                } else {
+                    self.view = View::History {
+                        selected: row,
+                        offset: *offset,
+                        status: status.clone(),
+                    };
+                }
+            }
+            _ => {}
+        }
+    }
+
+    async fn show_history(&mut self, status: Option<String>) {
+        self.fetch_history(0, status.as_deref()).await;
+        self.view = View::History {
+            selected: 0,
+            offset: 0,
+            status,
+        };
+    }
+
+    async fn open_job(&mut self, id: String) {
+        self.fetch_job_detail(&id).await;
+        self.view = View::Detail {
+            job_id: id,
+            scroll: 0,
+        };
+    }
+
+    /// Handle a key press while the command palette is open.
+    pub async fn handle_palette_key(&mut self, key: PaletteKey) {
+        let Overlay::Palette(input) = &mut self.overlay else {
+            return;
+        };
+
+        match key {
+            PaletteKey::Char(c) => input.push(c),
+            PaletteKey::Backspace => {
+                input.pop();
+            }
+            PaletteKey::Cancel => self.overlay = Overlay::None,
+            PaletteKey::Submit => {
+                let input = std::mem::take(input);
+                self.overlay = Overlay::None;
+                match PaletteCommand::parse(&input) {
+                    Ok(command) => self.run_command(command).await,
+                    Err(e) => self.error = Some(e),
+                }
+            }
+        }
+    }
+
+    async fn run_command(&mut self, command: PaletteCommand) {
+        self.error = None;
+        match command {
+            PaletteCommand::Job(prefix) => {
+                // Resolve a prefix against jobs we already know about
+                let id = self
+                    .data
+                    .recent_jobs
+                    .iter()
+                    .chain(self.data.all_jobs.iter())
+                    .find(|j| j.id.starts_with(&prefix))
+                    .map(|j| j.id.clone())
+                    .unwrap_or(prefix);
+                self.open_job(id).await;
+            }
+            PaletteCommand::Cancel(prefix) => self.cancel_job(prefix).await,
+            PaletteCommand::Filter(status) => self.show_history(status).await,
+            PaletteCommand::History => self.show_history(None).await,
+            PaletteCommand::Refresh => {
+                let _ = self.refresh_dashboard().await;
+            }
+            PaletteCommand::Help => self.overlay = Overlay::Help,
+            PaletteCommand::Quit => self.running = false,
+            PaletteCommand::AddDevice(uuid) => self.add_device(uuid).await,
+            PaletteCommand::RemoveDevice(uuid) => self.remove_device(uuid).await,
+        }
+    }
+
     async fn select_item(&mut self) {
         match &self.view {
             View::Dashboard { selected } => {
                 if let Some(job) = self.data.recent_jobs.get(*selected) {
                     let id = job.id.clone();
-                    self.fetch_job_detail(&id).await;
-                    self.view = View::Detail {
-                        job_id: id,
-                        scroll: 0,
-                    };
+                    self.open_job(id).await;
                 }
             }
             View::History { selected, .. } => {
                 if let Some(job) = self.data.all_jobs.get(*selected) {
                     let id = job.id.clone();
-                    self.fetch_job_detail(&id).await;
-                    self.view = View::Detail {
-                        job_id: id,
-                        scroll: 0,
-                    };
+                    self.open_job(id).await;
                 }
             }
             View::Detail { .. } => {
@@ -289,3 +689,37 @@ impl TuiApp {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_palette_command() {
+        assert_eq!(
+            PaletteCommand::parse("job 0192ab"),
+            Ok(PaletteCommand::Job("0192ab".to_string()))
+        );
+        assert_eq!(
+            PaletteCommand::parse("  filter failed "),
+            Ok(PaletteCommand::Filter(Some("failed".to_string())))
+        );
+        assert_eq!(PaletteCommand::parse("f"), Ok(PaletteCommand::Filter(None)));
+        assert_eq!(
+            PaletteCommand::parse("add card-b"),
+            Ok(PaletteCommand::AddDevice(Some("card-b".to_string())))
+        );
+        assert_eq!(
+            PaletteCommand::parse("rm"),
+            Ok(PaletteCommand::RemoveDevice(None))
+        );
+        assert_eq!(
+            PaletteCommand::parse("cancel 0192ab"),
+            Ok(PaletteCommand::Cancel("0192ab".to_string()))
+        );
+        assert!(PaletteCommand::parse("job").is_err());
+        assert!(PaletteCommand::parse("cancel").is_err());
+        assert!(PaletteCommand::parse("").is_err());
+        assert!(PaletteCommand::parse("launch").is_err());
+    }
+}
@@ -1,34 +1,341 @@
 //! Input handling for the TUI.
 
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow, bail};
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
 
 use super::app::Action;
 
-/// Convert a crossterm key event to an Action.
-pub fn handle_key_event(key: KeyEvent) -> Option<Action> {
+/// Default bindings, in display order. The first key of each action is the
+/// one shown in the footer.
+const DEFAULT_BINDINGS: &[(Action, &[&str])] = &[
+    (Action::Quit, &["q", "ctrl+c"]),
+    (Action::Back, &["esc"]),
+    (Action::Up, &["up", "k"]),
+    (Action::Down, &["down", "j"]),
+    (Action::Left, &["left"]),
+    (Action::Right, &["right", "l"]),
+    (Action::Select, &["enter", "space"]),
+    (Action::Refresh, &["r", "f5"]),
+    (Action::History, &["h"]),
+    (Action::Help, &["?"]),
+    (Action::Palette, &[":"]),
+    (Action::AddDevice, &["a"]),
+    (Action::RemoveDevice, &["x"]),
+];
+
+/// A single key with modifiers, as written in the config (e.g. `ctrl+c`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    /// Parse a key name such as `q`, `H`, `esc`, `f5` or `ctrl+c`.
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut parts: Vec<&str> = s.split('+').collect();
+        let key = parts.pop().filter(|k| !k.is_empty()).unwrap_or("+");
+
+        for part in parts {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" => modifiers |= KeyModifiers::CONTROL,
+                "alt" => modifiers |= KeyModifiers::ALT,
+                "" => {}
+                other => return Err(anyhow!("unknown modifier \"{}\" in \"{}\"", other, s)),
+            }
+        }
+
+        let code = match key.to_ascii_lowercase().as_str() {
+            "esc" | "escape" => KeyCode::Esc,
+            "enter" | "return" => KeyCode::Enter,
+            "space" => KeyCode::Char(' '),
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdn" => KeyCode::PageDown,
+            lower if lower.len() > 1 && lower.starts_with('f') => lower[1..]
+                .parse::<u8>()
+                .map(KeyCode::F)
+                .map_err(|_| anyhow!("unknown key \"{}\"", s))?,
+            _ => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => return Err(anyhow!("unknown key \"{}\"", s)),
+                }
+            }
+        };
+
+        Ok(Self { code, modifiers })
+    }
+
+    fn from_event(key: &KeyEvent) -> Self {
+        // Shift is implied by the character itself ('H' vs 'h')
+        let modifiers = key.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT);
+        Self {
+            code: key.code,
+            modifiers,
+        }
+    }
+
+    /// Short label for the footer/help (e.g. `↑`, `Enter`, `Ctrl+c`).
+    pub fn label(&self) -> String {
+        let key = match self.code {
+            KeyCode::Esc => "Esc".to_string(),
+            KeyCode::Enter => "Enter".to_string(),
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Tab => "Tab".to_string(),
+            KeyCode::Backspace => "Backspace".to_string(),
+            KeyCode::Up => "↑".to_string(),
+            KeyCode::Down => "↓".to_string(),
+            KeyCode::Left => "←".to_string(),
+            KeyCode::Right => "→".to_string(),
+            KeyCode::Home => "Home".to_string(),
+            KeyCode::End => "End".to_string(),
+            KeyCode::PageUp => "PgUp".to_string(),
+            KeyCode::PageDown => "PgDn".to_string(),
+            KeyCode::F(n) => format!("F{}", n),
+            KeyCode::Char(c) => c.to_string(),
+            other => format!("{:?}", other),
+        };
+
+        let mut label = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            label.push_str("Ctrl+");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            label.push_str("Alt+");
+        }
+        label.push_str(&key);
+        label
+    }
+}
+
+/// Effective key → action mapping: defaults with config overrides applied.
+#[derive(Debug, Clone)]
+pub struct KeyMap {
+    /// Keys per action, in display order
+    bindings: Vec<(Action, Vec<KeyBinding>)>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let bindings = DEFAULT_BINDINGS
+            .iter()
+            .map(|(action, keys)| {
+                let keys = keys
+                    .iter()
+                    .map(|k| KeyBinding::parse(k).expect("default keybinding"))
+                    .collect();
+                (*action, keys)
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl KeyMap {
+    /// Build a keymap from config overrides (action name → key list).
+    ///
+    /// An override replaces all default keys for that action, and any key it
+    /// claims is removed from other actions so it can't trigger both. Two
+    /// overrides claiming the same key are rejected.
+    pub fn with_overrides(overrides: &HashMap<String, Vec<String>>) -> Result<Self> {
+        let mut map = Self::default();
+
+        let mut parsed = Vec::new();
+        for (name, keys) in overrides {
+            let action = Action::from_name(name)
+                .ok_or_else(|| anyhow!("unknown TUI action \"{}\" in keybindings", name))?;
+            let keys = keys
+                .iter()
+                .map(|k| KeyBinding::parse(k))
+                .collect::<Result<Vec<_>>>()
+                .map_err(|e| anyhow!("invalid keybinding for \"{}\": {}", name, e))?;
+            parsed.push((name, action, keys));
+        }
+        // Sorted so the error names the same pair on every run
+        parsed.sort_by(|a, b| a.0.cmp(b.0));
+        for (i, (name, _, keys)) in parsed.iter().enumerate() {
+            for (other_name, _, other_keys) in &parsed[i + 1..] {
+                if let Some(key) = keys.iter().find(|k| other_keys.contains(k)) {
+                    bail!(
+                        "key \"{}\" is bound to both \"{}\" and \"{}\" in keybindings",
+                        key.label(),
+                        name,
+                        other_name
+                    );
+                }
+            }
+        }
+
+        for (_, action, keys) in parsed {
+            for (other, other_keys) in map.bindings.iter_mut() {
+                if *other == action {
+                    *other_keys = keys.clone();
+                } else {
+                    other_keys.retain(|k| !keys.contains(k));
+                }
+            }
+        }
+
+        Ok(map)
+    }
+
+    /// Look up the action bound to a key event.
+    pub fn action_for(&self, key: &KeyEvent) -> Option<Action> {
+        let pressed = KeyBinding::from_event(key);
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.contains(&pressed))
+            .map(|(action, _)| *action)
+    }
+
+    /// Label of the primary key for an action, or `-` if it is unbound.
+    pub fn key_label(&self, action: Action) -> String {
+        self.keys(action)
+            .first()
+            .map(KeyBinding::label)
+            .unwrap_or_else(|| "-".to_string())
+    }
+
+    /// Every action with its bound keys, in display order.
+    pub fn bindings(&self) -> impl Iterator<Item = (Action, &[KeyBinding])> {
+        self.bindings
+            .iter()
+            .map(|(action, keys)| (*action, keys.as_slice()))
+    }
+
+    /// All keys bound to an action.
+    pub fn keys(&self, action: Action) -> &[KeyBinding] {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map(|(_, keys)| keys.as_slice())
+            .unwrap_or(&[])
+    }
+}
+
+/// Text-entry keys for the command palette, which bypasses the keymap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteKey {
+    Char(char),
+    Backspace,
+    Submit,
+    Cancel,
+}
+
+/// Convert a crossterm Event to a palette key.
+pub fn handle_palette_event(event: Event) -> Option<PaletteKey> {
+    let Event::Key(key) = event else {
+        return None;
+    };
+
     match key.code {
-        KeyCode::Char('q') => Some(Action::Quit),
-        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Action::Quit),
-        KeyCode::Esc => Some(Action::Back),
-        KeyCode::Up | KeyCode::Char('k') => Some(Action::Up),
-        KeyCode::Down | KeyCode::Char('j') => Some(Action::Down),
-        KeyCode::Left | KeyCode::Char('h') if !key.modifiers.contains(KeyModifiers::NONE) => {
-            Some(Action::Left)
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            Some(PaletteKey::Cancel)
         }
-        KeyCode::Left => Some(Action::Left),
-        KeyCode::Right | KeyCode::Char('l') => Some(Action::Right),
-        KeyCode::Enter | KeyCode::Char(' ') => Some(Action::Select),
-        KeyCode::Char('r') => Some(Action::Refresh),
-        KeyCode::Char('h') => Some(Action::History),
-        KeyCode::F(5) => Some(Action::Refresh),
+        KeyCode::Char(c) => Some(PaletteKey::Char(c)),
+        KeyCode::Backspace => Some(PaletteKey::Backspace),
+        KeyCode::Enter => Some(PaletteKey::Submit),
+        KeyCode::Esc => Some(PaletteKey::Cancel),
         _ => None,
     }
 }
 
 /// Convert a crossterm Event to an Action.
-pub fn handle_event(event: Event) -> Option<Action> {
+pub fn handle_event(keymap: &KeyMap, event: Event) -> Option<Action> {
     match event {
-        Event::Key(key) => handle_key_event(key),
+        Event::Key(key) => keymap.action_for(&key),
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(
+            KeyBinding::parse("ctrl+c").unwrap().label(),
+            "Ctrl+c".to_string()
+        );
+        assert_eq!(KeyBinding::parse("F5").unwrap().label(), "F5");
+        assert_eq!(KeyBinding::parse("H").unwrap().label(), "H");
+        assert_eq!(KeyBinding::parse("+").unwrap().label(), "+");
+        assert!(KeyBinding::parse("hyper+x").is_err());
+        assert!(KeyBinding::parse("nope").is_err());
+    }
+
+    #[test]
+    fn test_default_keymap() {
+        let map = KeyMap::default();
+        assert_eq!(
+            map.action_for(&key(KeyCode::Char('h'), KeyModifiers::NONE)),
+            Some(Action::History)
+        );
+        assert_eq!(
+            map.action_for(&key(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            map.action_for(&key(KeyCode::Char('c'), KeyModifiers::NONE)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_overrides_steal_keys() {
+        let overrides = HashMap::from([
+            (
+                "left".to_string(),
+                vec!["left".to_string(), "h".to_string()],
+            ),
+            ("history".to_string(), vec!["H".to_string()]),
+        ]);
+        let map = KeyMap::with_overrides(&overrides).unwrap();
+
+        assert_eq!(
+            map.action_for(&key(KeyCode::Char('h'), KeyModifiers::NONE)),
+            Some(Action::Left)
+        );
+        assert_eq!(
+            map.action_for(&key(KeyCode::Char('H'), KeyModifiers::SHIFT)),
+            Some(Action::History)
+        );
+        assert_eq!(map.key_label(Action::History), "H");
+    }
+
+    #[test]
+    fn test_conflicting_overrides_rejected() {
+        let overrides = HashMap::from([
+            ("left".to_string(), vec!["h".to_string()]),
+            ("history".to_string(), vec!["h".to_string()]),
+        ]);
+        let err = KeyMap::with_overrides(&overrides).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "key \"h\" is bound to both \"history\" and \"left\" in keybindings"
+        );
+    }
+
+    #[test]
+    fn test_unknown_action_rejected() {
+        let overrides = HashMap::from([("launch".to_string(), vec!["x".to_string()])]);
+        assert!(KeyMap::with_overrides(&overrides).is_err());
+    }
+}
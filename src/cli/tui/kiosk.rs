@@ -0,0 +1,284 @@
+//! Kiosk mode: a read-only, full-screen wall display.
+//!
+//! Rotates between summary panels (cards in progress, recent completions,
+//! recent failures) with counts drawn in a large block font so they are
+//! readable across the room. No input is needed; `q` or Ctrl+C exits.
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use ratatui::{
+    Frame, Terminal,
+    backend::CrosstermBackend,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+};
+
+use crate::core::models::Job;
+use crate::core::transfer_engine::TransferStatus;
+
+use super::app::TuiApp;
+use super::theme::Theme;
+
+/// How long each panel stays on screen
+const ROTATE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How often data is refreshed from the daemon
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Glyph rows for digits 0-9, 3 columns x 5 rows
+const DIGITS: [[&str; 5]; 10] = [
+    ["###", "# #", "# #", "# #", "###"],
+    ["  #", "  #", "  #", "  #", "  #"],
+    ["###", "  #", "###", "#  ", "###"],
+    ["###", "  #", "###", "  #", "###"],
+    ["# #", "# #", "###", "  #", "  #"],
+    ["###", "#  ", "###", "  #", "###"],
+    ["###", "#  ", "###", "# #", "###"],
+    ["###", "  #", "  #", "  #", "  #"],
+    ["###", "# #", "###", "# #", "###"],
+    ["###", "# #", "###", "  #", "###"],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Panel {
+    InProgress,
+    Completed,
+    Failed,
+}
+
+impl Panel {
+    const ALL: [Panel; 3] = [Panel::InProgress, Panel::Completed, Panel::Failed];
+
+    fn title(&self) -> &'static str {
+        match self {
+            Panel::InProgress => "CARDS IN PROGRESS",
+            Panel::Completed => "RECENT COMPLETIONS",
+            Panel::Failed => "RECENT FAILURES",
+        }
+    }
+
+    fn style(&self, theme: &Theme) -> Style {
+        match self {
+            Panel::InProgress => theme.accent,
+            Panel::Completed => theme.success,
+            Panel::Failed => theme.failure,
+        }
+    }
+}
+
+/// Run the kiosk display loop until the user quits.
+pub async fn run(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    app: &mut TuiApp,
+) -> Result<()> {
+    let started = Instant::now();
+    let mut last_refresh = Instant::now();
+    app.refresh_dashboard().await?;
+
+    loop {
+        let panel_index =
+            (started.elapsed().as_secs() / ROTATE_INTERVAL.as_secs()) as usize % Panel::ALL.len();
+        let panel = Panel::ALL[panel_index];
+
+        terminal.draw(|frame| render(frame, app, panel))?;
+
+        if event::poll(Duration::from_millis(500))?
+            && let Event::Key(key) = event::read()?
+        {
+            let ctrl_c =
+                key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+            if key.code == KeyCode::Char('q') || ctrl_c {
+                break;
+            }
+        }
+
+        if last_refresh.elapsed() >= REFRESH_INTERVAL {
+            app.refresh_dashboard().await?;
+            last_refresh = Instant::now();
+        }
+    }
+
+    Ok(())
+}
+
+fn render(frame: &mut Frame, app: &TuiApp, panel: Panel) {
+    let theme = &app.theme;
+    let style = panel.style(theme);
+    let block = Block::default()
+        .title(Span::styled(
+            format!(" BKSD  {} ", panel.title()),
+            style.add_modifier(Modifier::BOLD),
+        ))
+        .title_alignment(Alignment::Center)
+        .borders(Borders::ALL)
+        .border_style(style);
+
+    let inner = block.inner(frame.area());
+    frame.render_widget(block, frame.area());
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1), // Spacer
+            Constraint::Length(5), // Big count
+            Constraint::Length(1), // Spacer
+            Constraint::Min(0),    // Entries
+            Constraint::Length(1), // Rotation indicator
+        ])
+        .split(inner);
+
+    let (count, lines) = match panel {
+        Panel::InProgress => in_progress_lines(app),
+        Panel::Completed => job_lines(app, &jobs_with_status(app, "complete"), theme.success),
+        Panel::Failed => job_lines(app, &jobs_with_status(app, "failed"), theme.failure),
+    };
+
+    let digits = big_number(count, style);
+    frame.render_widget(
+        Paragraph::new(digits).alignment(Alignment::Center),
+        chunks[1],
+    );
+    frame.render_widget(
+        Paragraph::new(lines).alignment(Alignment::Center),
+        chunks[3],
+    );
+    render_indicator(frame, theme, panel, chunks[4]);
+
+    if let Some(error) = &app.error {
+        let area = Rect {
+            height: 1,
+            ..chunks[2]
+        };
+        frame.render_widget(
+            Paragraph::new(Span::styled(error.clone(), theme.failure)).alignment(Alignment::Center),
+            area,
+        );
+    }
+}
+
+fn render_indicator(frame: &mut Frame, theme: &Theme, panel: Panel, area: Rect) {
+    let dots: Vec<Span> = Panel::ALL
+        .iter()
+        .map(|p| {
+            if *p == panel {
+                Span::styled(" ● ", p.style(theme))
+            } else {
+                Span::styled(" ○ ", theme.muted)
+            }
+        })
+        .collect();
+    frame.render_widget(
+        Paragraph::new(Line::from(dots)).alignment(Alignment::Center),
+        area,
+    );
+}
+
+fn in_progress_lines(app: &TuiApp) -> (usize, Vec<Line<'static>>) {
+    let mut jobs: Vec<(&String, &TransferStatus)> = app.data.active_jobs.iter().collect();
+    jobs.sort_by(|a, b| a.0.cmp(b.0));
+
+    let lines = jobs
+        .iter()
+        .map(|(id, status)| {
+            let short = &id[..8.min(id.len())];
+            let text = match status {
+                TransferStatus::InProgress { percentage, .. } => {
+                    format!("{}  {:>3}%", short, percentage)
+                }
+                TransferStatus::CopyComplete => format!("{}  verifying", short),
+                TransferStatus::Verifying {
+                    bytes_verified,
+                    total_bytes,
+                    ..
+                } if *total_bytes > 0 => {
+                    let pct = (bytes_verified * 100 / total_bytes).min(100);
+                    format!("{}  verifying {:>3}%", short, pct)
+                }
+                TransferStatus::Verifying { current, total, .. } => {
+                    format!("{}  verifying {}/{}", short, current, total)
+                }
+                TransferStatus::GeneratingParity { percentage } => {
+                    format!("{}  parity {:>3}%", short, percentage)
+                }
+                TransferStatus::Ready => format!("{}  preparing", short),
+                TransferStatus::Complete { .. } => format!("{}  complete", short),
+                TransferStatus::Failed(_) => format!("{}  failed", short),
+                TransferStatus::Cancelled => format!("{}  cancelled", short),
+                TransferStatus::Paused => format!("{}  paused", short),
+            };
+            Line::from(Span::styled(
+                text,
+                Style::default().add_modifier(Modifier::BOLD),
+            ))
+        })
+        .collect();
+
+    (jobs.len(), lines)
+}
+
+fn jobs_with_status<'a>(app: &'a TuiApp, status: &str) -> Vec<&'a Job> {
+    app.data
+        .recent_jobs
+        .iter()
+        .filter(|j| j.status.eq_ignore_ascii_case(status))
+        .collect()
+}
+
+fn job_lines(app: &TuiApp, jobs: &[&Job], style: Style) -> (usize, Vec<Line<'static>>) {
+    let lines = jobs
+        .iter()
+        .map(|job| {
+            let short = &job.id[..8.min(job.id.len())];
+            let created = app.zone.format(&job.created_at, "%Y-%m-%d %H:%M");
+            Line::from(vec![
+                Span::styled(format!("{}  ", short), style),
+                Span::raw(format!("{}  {}", created, job.target_id)),
+            ])
+        })
+        .collect();
+    (jobs.len(), lines)
+}
+
+/// Render a number in the 3x5 block font, each glyph cell doubled in width.
+fn big_number(n: usize, style: Style) -> Vec<Line<'static>> {
+    let digits: Vec<usize> = n
+        .to_string()
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .map(|d| d as usize)
+        .collect();
+
+    (0..5)
+        .map(|row| {
+            let text = digits
+                .iter()
+                .map(|d| {
+                    DIGITS[*d][row]
+                        .chars()
+                        .map(|c| if c == '#' { "██" } else { "  " })
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("  ");
+            Line::from(Span::styled(text, style))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_big_number_shape() {
+        let lines = big_number(10, Style::default());
+        assert_eq!(lines.len(), 5);
+        // Two glyphs, 6 columns each, separated by 2 spaces
+        assert_eq!(lines[0].width(), 14);
+    }
+}
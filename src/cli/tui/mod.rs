@@ -5,6 +5,8 @@
 
 mod app;
 mod input;
+mod kiosk;
+mod theme;
 mod ui;
 
 use std::io::{self, stdout};
@@ -13,31 +15,54 @@ use std::time::Duration;
 
 use anyhow::{Context, Result};
 use crossterm::{
-    event, execute,
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event},
+    execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
 use ratatui::{Terminal, backend::CrosstermBackend};
 
-use app::{TuiApp, View};
+use crate::config::TuiConfig;
+use crate::core::timestamp::DisplayZone;
+use app::{Overlay, TuiApp, View};
+use input::KeyMap;
+use theme::Theme;
 
 /// Run the TUI connected to the daemon at the given address.
-pub async fn run(addr: SocketAddr) -> Result<()> {
+/// In kiosk mode a rotating, input-free wall display is shown instead.
+pub async fn run(
+    addr: SocketAddr,
+    config: &TuiConfig,
+    zone: DisplayZone,
+    kiosk: bool,
+) -> Result<()> {
+    // Validate keybindings before taking over the terminal
+    let keymap = KeyMap::with_overrides(&config.keybindings)?;
+
     // Setup terminal
     enable_raw_mode().context("Failed to enable raw mode")?;
     let mut stdout = stdout();
-    execute!(stdout, EnterAlternateScreen).context("Failed to enter alternate screen")?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)
+        .context("Failed to enter alternate screen")?;
 
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend).context("Failed to create terminal")?;
 
     // Create app and run
-    let mut app = TuiApp::new(addr);
-    let result = run_app(&mut terminal, &mut app).await;
+    let mut app = TuiApp::new(addr, keymap, Theme::from_config(&config.theme), zone);
+    let result = if kiosk {
+        kiosk::run(&mut terminal, &mut app).await
+    } else {
+        run_app(&mut terminal, &mut app).await
+    };
 
     // Restore terminal
     disable_raw_mode().context("Failed to disable raw mode")?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)
-        .context("Failed to leave alternate screen")?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )
+    .context("Failed to leave alternate screen")?;
     terminal.show_cursor().context("Failed to show cursor")?;
 
     result
@@ -66,7 +91,13 @@ async fn run_app(
 
         if event::poll(timeout)? {
             let event = event::read()?;
-            if let Some(action) = input::handle_event(event) {
+            if let Event::Mouse(mouse) = event {
+                app.handle_mouse(mouse).await;
+            } else if matches!(app.overlay, Overlay::Palette(_)) {
+                if let Some(key) = input::handle_palette_event(event) {
+                    app.handle_palette_key(key).await;
+                }
+            } else if let Some(action) = input::handle_event(&app.keymap, event) {
                 app.handle_action(action).await;
             }
         } else if matches!(app.view, View::Dashboard { .. }) {
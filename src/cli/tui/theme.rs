@@ -0,0 +1,103 @@
+//! Color themes for the TUI.
+//!
+//! Every style in the UI comes from a semantic role here rather than a
+//! hard-coded color, so palettes can be swapped. Besides the default there is
+//! a colorblind-safe palette (Okabe-Ito blue/orange instead of green/red) and
+//! a monochrome theme that relies on bold/reverse only. Monochrome is forced
+//! when the `NO_COLOR` environment variable is set.
+
+use ratatui::style::{Color, Modifier, Style};
+
+use crate::config::TuiTheme;
+
+/// Resolved styles for each semantic role.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    /// Borders, field labels, key names
+    pub accent: Style,
+    /// Completed jobs, active transfer banner
+    pub success: Style,
+    /// Failed jobs and errors
+    pub failure: Style,
+    /// In-flight jobs and notices
+    pub warning: Style,
+    /// Placeholders and secondary chrome
+    pub muted: Style,
+    /// Emphasized body text
+    pub text: Style,
+    /// Selected list row
+    pub selected: Style,
+    /// Section headings
+    pub heading: Style,
+}
+
+impl Theme {
+    /// Build the configured theme, honouring `NO_COLOR`.
+    pub fn from_config(theme: &TuiTheme) -> Self {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        if no_color {
+            return Self::mono();
+        }
+
+        match theme {
+            TuiTheme::Default => Self::default_palette(),
+            TuiTheme::Colorblind => Self::colorblind(),
+            TuiTheme::Mono => Self::mono(),
+        }
+    }
+
+    fn default_palette() -> Self {
+        Self {
+            accent: Style::default().fg(Color::Cyan),
+            success: Style::default().fg(Color::Green),
+            failure: Style::default().fg(Color::Red),
+            warning: Style::default().fg(Color::Yellow),
+            muted: Style::default().fg(Color::DarkGray),
+            text: Style::default().fg(Color::White),
+            selected: Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+            heading: Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        }
+    }
+
+    /// Okabe-Ito palette: distinguishable with red-green color blindness
+    fn colorblind() -> Self {
+        let sky_blue = Color::Rgb(86, 180, 233);
+        let blue = Color::Rgb(0, 114, 178);
+        let orange = Color::Rgb(230, 159, 0);
+        let purple = Color::Rgb(204, 121, 167);
+
+        Self {
+            accent: Style::default().fg(sky_blue),
+            success: Style::default().fg(blue).add_modifier(Modifier::BOLD),
+            failure: Style::default().fg(orange).add_modifier(Modifier::BOLD),
+            warning: Style::default().fg(purple),
+            muted: Style::default().fg(Color::DarkGray),
+            text: Style::default().fg(Color::White),
+            selected: Style::default().add_modifier(Modifier::REVERSED),
+            heading: Style::default().fg(purple).add_modifier(Modifier::BOLD),
+        }
+    }
+
+    fn mono() -> Self {
+        Self {
+            accent: Style::default(),
+            success: Style::default(),
+            failure: Style::default().add_modifier(Modifier::BOLD),
+            warning: Style::default(),
+            muted: Style::default().add_modifier(Modifier::DIM),
+            text: Style::default(),
+            selected: Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD),
+            heading: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::default_palette()
+    }
+}
@@ -1,16 +1,22 @@
 //! UI rendering for the TUI.
 
+use std::collections::HashMap;
+use std::path::Path;
+
 use ratatui::{
     Frame,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::Style,
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
 };
 
+use crate::core::failure;
 use crate::core::transfer_engine::TransferStatus;
+use crate::db::files::DuplicateFile;
 
-use super::app::{TuiApp, View};
+use super::app::{Action, Overlay, PaletteCommand, TuiApp, View};
+use super::theme::Theme;
 
 /// Main render function - dispatches to view-specific renderers.
 pub fn render(frame: &mut Frame, app: &TuiApp) {
@@ -24,6 +30,7 @@ pub fn render(frame: &mut Frame, app: &TuiApp) {
         .split(frame.area());
 
     render_header(frame, app, chunks[0]);
+    app.hit_areas.borrow_mut().list = None;
 
     match &app.view {
         View::Dashboard { selected } => {
@@ -38,9 +45,14 @@ pub fn render(frame: &mut Frame, app: &TuiApp) {
     }
 
     render_footer(frame, app, chunks[2]);
+
+    if app.overlay == Overlay::Help {
+        render_help(frame, app);
+    }
 }
 
 fn render_header(frame: &mut Frame, app: &TuiApp, area: Rect) {
+    let theme = &app.theme;
     let status = app.data.daemon_status.as_ref();
 
     let title = match status {
@@ -55,12 +67,25 @@ fn render_header(frame: &mut Frame, app: &TuiApp, area: Rect) {
     let block = Block::default()
         .title(title)
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan));
+        .border_style(theme.accent);
 
     frame.render_widget(block, area);
 }
 
 fn render_dashboard(frame: &mut Frame, app: &TuiApp, area: Rect, selected: usize) {
+    let area = if app.is_simulation() {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3), // Simulated devices
+                Constraint::Min(0),
+            ])
+            .split(area);
+        render_sim_devices(frame, app, chunks[0]);
+        chunks[1]
+    } else {
+        area
+    };
     let has_active = !app.data.active_jobs.is_empty();
 
     if has_active {
@@ -80,33 +105,64 @@ fn render_dashboard(frame: &mut Frame, app: &TuiApp, area: Rect, selected: usize
     }
 }
 
+/// Devices inserted on a simulation-mode daemon, with the keys that add
+/// and remove them.
+fn render_sim_devices(frame: &mut Frame, app: &TuiApp, area: Rect) {
+    let theme = &app.theme;
+    let block = Block::default()
+        .title(format!(
+            "Simulated Devices  [{}] Add  [{}] Remove",
+            app.keymap.key_label(Action::AddDevice),
+            app.keymap.key_label(Action::RemoveDevice)
+        ))
+        .borders(Borders::ALL)
+        .border_style(theme.warning);
+
+    let line = if app.data.sim_devices.is_empty() {
+        Line::from(Span::styled("  No devices inserted", theme.muted))
+    } else {
+        let devices = app
+            .data
+            .sim_devices
+            .iter()
+            .map(|d| format!("{} ({})", d.uuid, format_bytes(d.capacity)))
+            .collect::<Vec<_>>()
+            .join("  ");
+        Line::from(format!("  {}", devices))
+    };
+
+    frame.render_widget(Paragraph::new(line).block(block), area);
+}
+
 fn render_active_banner(frame: &mut Frame, app: &TuiApp, area: Rect) {
+    let theme = &app.theme;
     // Get first active job (typically only one)
     let (job_id, status) = match app.data.active_jobs.iter().next() {
         Some((id, s)) => (id, s),
         None => return,
     };
 
-    let content = format_active_banner(&job_id[..8.min(job_id.len())], status);
+    let content = format_active_banner(theme, &job_id[..8.min(job_id.len())], status);
 
     let block = Block::default()
         .title("Active Transfer")
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Green));
+        .border_style(theme.success);
 
     let paragraph = Paragraph::new(content).block(block);
     frame.render_widget(paragraph, area);
 }
 
 fn render_recent_jobs(frame: &mut Frame, app: &TuiApp, area: Rect, selected: usize) {
+    let theme = &app.theme;
     let block = Block::default()
         .title("Recent Jobs")
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan));
+        .border_style(theme.accent);
 
     if app.data.recent_jobs.is_empty() {
         let text = Paragraph::new("  No recent jobs")
-            .style(Style::default().fg(Color::DarkGray))
+            .style(theme.muted)
             .block(block);
         frame.render_widget(text, area);
         return;
@@ -120,18 +176,12 @@ fn render_recent_jobs(frame: &mut Frame, app: &TuiApp, area: Rect, selected: usi
         .map(|(i, job)| {
             let is_selected = i == selected;
             let style = if is_selected {
-                Style::default()
-                    .bg(Color::DarkGray)
-                    .add_modifier(Modifier::BOLD)
+                theme.selected
             } else {
                 Style::default()
             };
 
-            let status_icon = match job.status.as_str() {
-                "Complete" => Span::styled("✓", Style::default().fg(Color::Green)),
-                "Failed" => Span::styled("✗", Style::default().fg(Color::Red)),
-                _ => Span::styled("•", Style::default().fg(Color::Yellow)),
-            };
+            let status_icon = status_icon(theme, &job.status);
 
             // Safe substring handling for job id and created_at
             let job_id_short = if job.id.len() >= 8 {
@@ -139,11 +189,7 @@ fn render_recent_jobs(frame: &mut Frame, app: &TuiApp, area: Rect, selected: usi
             } else {
                 &job.id
             };
-            let created_short = if job.created_at.len() >= 16 {
-                &job.created_at[..16]
-            } else {
-                &job.created_at
-            };
+            let created_short = app.zone.format(&job.created_at, "%Y-%m-%d %H:%M");
 
             let line = Line::from(vec![
                 Span::raw(if is_selected { "> " } else { "  " }),
@@ -158,19 +204,28 @@ fn render_recent_jobs(frame: &mut Frame, app: &TuiApp, area: Rect, selected: usi
         })
         .collect();
 
+    app.hit_areas.borrow_mut().list = Some(block.inner(area));
     let list = List::new(items).block(block);
     frame.render_widget(list, area);
 }
 
 fn render_history(frame: &mut Frame, app: &TuiApp, area: Rect, selected: usize) {
+    let theme = &app.theme;
+    let title = match &app.view {
+        View::History {
+            status: Some(status),
+            ..
+        } => format!("Job History ({})", status),
+        _ => "Job History".to_string(),
+    };
     let block = Block::default()
-        .title("Job History")
+        .title(title)
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan));
+        .border_style(theme.accent);
 
     if app.data.all_jobs.is_empty() {
         let text = Paragraph::new("  No jobs found")
-            .style(Style::default().fg(Color::DarkGray))
+            .style(theme.muted)
             .block(block);
         frame.render_widget(text, area);
         return;
@@ -184,18 +239,12 @@ fn render_history(frame: &mut Frame, app: &TuiApp, area: Rect, selected: usize)
         .map(|(i, job)| {
             let is_selected = i == selected;
             let style = if is_selected {
-                Style::default()
-                    .bg(Color::DarkGray)
-                    .add_modifier(Modifier::BOLD)
+                theme.selected
             } else {
                 Style::default()
             };
 
-            let status_icon = match job.status.as_str() {
-                "Complete" => Span::styled("✓", Style::default().fg(Color::Green)),
-                "Failed" => Span::styled("✗", Style::default().fg(Color::Red)),
-                _ => Span::styled("•", Style::default().fg(Color::Yellow)),
-            };
+            let status_icon = status_icon(theme, &job.status);
 
             let line = Line::from(vec![
                 Span::raw("  "),
@@ -203,7 +252,7 @@ fn render_history(frame: &mut Frame, app: &TuiApp, area: Rect, selected: usize)
                 Span::raw(format!(
                     "  {}  {}  {}",
                     &job.id[..8],
-                    &job.created_at[..16],
+                    app.zone.format(&job.created_at, "%Y-%m-%d %H:%M"),
                     job.status
                 )),
             ]);
@@ -212,21 +261,23 @@ fn render_history(frame: &mut Frame, app: &TuiApp, area: Rect, selected: usize)
         })
         .collect();
 
+    app.hit_areas.borrow_mut().list = Some(block.inner(area));
     let list = List::new(items).block(block);
     frame.render_widget(list, area);
 }
 
-fn render_detail(frame: &mut Frame, app: &TuiApp, area: Rect, _job_id: &str, _scroll: u16) {
+fn render_detail(frame: &mut Frame, app: &TuiApp, area: Rect, _job_id: &str, scroll: u16) {
+    let theme = &app.theme;
     let block = Block::default()
         .title("Job Details")
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan));
+        .border_style(theme.accent);
 
     let job = match &app.data.selected_job {
         Some(j) => j,
         None => {
             let text = Paragraph::new("  Loading...")
-                .style(Style::default().fg(Color::DarkGray))
+                .style(theme.muted)
                 .block(block);
             frame.render_widget(text, area);
             return;
@@ -235,48 +286,85 @@ fn render_detail(frame: &mut Frame, app: &TuiApp, area: Rect, _job_id: &str, _sc
 
     let mut lines = vec![
         Line::from(vec![
-            Span::styled("  Job ID:      ", Style::default().fg(Color::Cyan)),
+            Span::styled("  Job ID:      ", theme.accent),
             Span::raw(&job.job.id),
         ]),
         Line::from(vec![
-            Span::styled("  Target:      ", Style::default().fg(Color::Cyan)),
+            Span::styled("  Target:      ", theme.accent),
             Span::raw(&job.job.target_id),
         ]),
         Line::from(vec![
-            Span::styled("  Destination: ", Style::default().fg(Color::Cyan)),
+            Span::styled("  Destination: ", theme.accent),
             Span::raw(job.job.destination_path.as_deref().unwrap_or("-")),
         ]),
         Line::from(vec![
-            Span::styled("  Created:     ", Style::default().fg(Color::Cyan)),
-            Span::raw(&job.job.created_at),
+            Span::styled("  Location:    ", theme.accent),
+            Span::raw(match &job.job.archive_label {
+                Some(label) => format!("on archive drive {}", label),
+                None => "backup directory".to_string(),
+            }),
+        ]),
+        Line::from(vec![
+            Span::styled("  Station:     ", theme.accent),
+            Span::raw(job.job.origin_host.as_deref().unwrap_or("-")),
         ]),
         Line::from(vec![
-            Span::styled("  Status:      ", Style::default().fg(Color::Cyan)),
+            Span::styled("  Slate:       ", theme.accent),
+            Span::raw(job.job.label_fields.summary().unwrap_or("-".to_string())),
+        ]),
+        Line::from(vec![
+            Span::styled("  Created:     ", theme.accent),
+            Span::raw(app.zone.format(&job.job.created_at, "%Y-%m-%d %H:%M:%S %Z")),
+        ]),
+        Line::from(vec![
+            Span::styled("  Status:      ", theme.accent),
             Span::raw(&job.job.status),
         ]),
+    ];
+
+    if job.job.status == "failed"
+        && let Some(hint) = job
+            .history
+            .iter()
+            .rev()
+            .find_map(|e| e.description.as_deref())
+            .and_then(failure::hint)
+    {
+        lines.push(Line::from(vec![
+            Span::styled("  What to do:  ", theme.accent),
+            Span::styled(hint, theme.warning),
+        ]));
+    }
+
+    if let Some(stats) = job.history.iter().rev().find_map(|e| e.stats.as_ref()) {
+        lines.push(Line::from(vec![
+            Span::styled("  Files:       ", theme.accent),
+            Span::raw(stats.file_counts()),
+        ]));
+        if let Some(largest) = &stats.largest_file {
+            lines.push(Line::from(vec![
+                Span::styled("  Largest:     ", theme.accent),
+                Span::raw(format!("{} ({})", largest.path, format_bytes(largest.size))),
+            ]));
+        }
+        lines.push(Line::from(vec![
+            Span::styled("  Throughput:  ", theme.accent),
+            Span::raw(stats.summary()),
+        ]));
+    }
+
+    lines.extend([
         Line::from(""),
-        Line::from(Span::styled(
-            "  Status History",
-            Style::default()
-                .fg(Color::Yellow)
-                .add_modifier(Modifier::BOLD),
-        )),
+        Line::from(Span::styled("  Status History", theme.heading)),
         Line::from("  ─────────────────────────────────────────"),
-    ];
+    ]);
 
     for entry in &job.history {
-        let timestamp = if entry.created_at.len() >= 19 {
-            &entry.created_at[11..19]
-        } else {
-            &entry.created_at
-        };
+        let timestamp = app.zone.format(&entry.created_at, "%H:%M:%S");
 
         let mut parts = vec![
             Span::raw(format!("  {}  ", timestamp)),
-            Span::styled(
-                format!("{:<12}", entry.status),
-                Style::default().fg(Color::White),
-            ),
+            Span::styled(format!("{:<12}", entry.status), theme.text),
         ];
 
         if let Some(desc) = &entry.description {
@@ -286,52 +374,213 @@ fn render_detail(frame: &mut Frame, app: &TuiApp, area: Rect, _job_id: &str, _sc
         if let (Some(bytes), Some(secs)) = (entry.total_bytes, entry.duration_secs) {
             parts.push(Span::styled(
                 format!("  {} in {}s", format_bytes(bytes), secs),
-                Style::default().fg(Color::Green),
+                theme.success,
             ));
         }
 
         lines.push(Line::from(parts));
     }
 
-    let paragraph = Paragraph::new(lines).block(block);
+    if !job.notices.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            format!("  Notices ({})", job.notices.len()),
+            theme.heading,
+        )));
+        lines.push(Line::from("  ─────────────────────────────────────────"));
+
+        for notice in &job.notices {
+            lines.push(Line::from(vec![
+                Span::styled("  ! ", theme.warning),
+                Span::raw(notice.message.clone()),
+            ]));
+        }
+    }
+
+    if !app.data.selected_files.is_empty() {
+        let duplicates: HashMap<&Path, &DuplicateFile> = app
+            .data
+            .selected_duplicates
+            .iter()
+            .rev()
+            .map(|d| (d.relative_path.as_path(), d))
+            .collect();
+        let mut heading = format!("  Files ({})", app.data.selected_files.len());
+        if !duplicates.is_empty() {
+            heading.push_str(&format!(", {} also in other jobs", duplicates.len()));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(heading, theme.heading)));
+        lines.push(Line::from("  ─────────────────────────────────────────"));
+
+        for file in &app.data.selected_files {
+            let mut parts = vec![
+                Span::raw(format!("  {}", file.relative_path.display())),
+                Span::styled(format!("  {}", format_bytes(file.size)), theme.muted),
+            ];
+            if let Some(duplicate) = duplicates.get(file.relative_path.as_path()) {
+                parts.push(Span::styled(
+                    format!(
+                        "  also in {} as {}",
+                        &duplicate.other_job_id[..8.min(duplicate.other_job_id.len())],
+                        duplicate.other_path.display()
+                    ),
+                    theme.warning,
+                ));
+            }
+            lines.push(Line::from(parts));
+        }
+    }
+
+    let paragraph = Paragraph::new(lines).block(block).scroll((scroll, 0));
     frame.render_widget(paragraph, area);
 }
 
 fn render_footer(frame: &mut Frame, app: &TuiApp, area: Rect) {
-    let help_text = match &app.view {
-        View::Dashboard { .. } => {
-            "[↑↓] Navigate  [Enter] Details  [h] History  [r] Refresh  [q] Quit"
+    let theme = &app.theme;
+    let keys = &app.keymap;
+    let navigate = (
+        None,
+        format!(
+            "[{}/{}] Navigate",
+            keys.key_label(Action::Up),
+            keys.key_label(Action::Down)
+        ),
+    );
+    let hint = |action: Action, label: &str| {
+        (
+            Some(action),
+            format!("[{}] {}", keys.key_label(action), label),
+        )
+    };
+
+    let mut hints = match &app.view {
+        View::Dashboard { .. } if app.is_simulation() => vec![
+            navigate,
+            hint(Action::Select, "Details"),
+            hint(Action::AddDevice, "Add device"),
+            hint(Action::RemoveDevice, "Remove device"),
+            hint(Action::History, "History"),
+            hint(Action::Quit, "Quit"),
+        ],
+        View::Dashboard { .. } => vec![
+            navigate,
+            hint(Action::Select, "Details"),
+            hint(Action::History, "History"),
+            hint(Action::Refresh, "Refresh"),
+            hint(Action::Quit, "Quit"),
+        ],
+        View::History { .. } => vec![
+            navigate,
+            hint(Action::Select, "Details"),
+            hint(Action::Back, "Back"),
+            hint(Action::Quit, "Quit"),
+        ],
+        View::Detail { .. } => vec![hint(Action::Back, "Back"), hint(Action::Quit, "Quit")],
+    };
+    hints.push(hint(Action::Help, "Help"));
+
+    // Record where each hint lands so it can be clicked
+    let mut hit_areas = app.hit_areas.borrow_mut();
+    hit_areas.footer.clear();
+
+    let help_text = match &app.overlay {
+        Overlay::Palette(input) => format!(":{}█", input),
+        _ => {
+            // Inside the border, after the two-space indent
+            let mut x = area.x + 3;
+            for (action, text) in &hints {
+                let width = text.chars().count() as u16;
+                if let Some(action) = action {
+                    hit_areas
+                        .footer
+                        .push((Rect::new(x, area.y + 1, width, 1), *action));
+                }
+                x += width + 2;
+            }
+            hints
+                .iter()
+                .map(|(_, text)| text.as_str())
+                .collect::<Vec<_>>()
+                .join("  ")
         }
-        View::History { .. } => "[↑↓] Navigate  [Enter] Details  [Esc] Back  [q] Quit",
-        View::Detail { .. } => "[Esc] Back  [q] Quit",
     };
 
     let mut spans = vec![Span::raw(format!("  {}", help_text))];
 
     if let Some(error) = &app.error {
-        spans.push(Span::styled(
-            format!("  Error: {}", error),
-            Style::default().fg(Color::Red),
-        ));
+        spans.push(Span::styled(format!("  Error: {}", error), theme.failure));
     }
 
     let block = Block::default()
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::DarkGray));
+        .border_style(theme.muted);
 
     let paragraph = Paragraph::new(Line::from(spans)).block(block);
     frame.render_widget(paragraph, area);
 }
 
-fn format_active_banner(job_id: &str, status: &TransferStatus) -> Line<'static> {
+/// Centered overlay listing every key binding and palette command.
+fn render_help(frame: &mut Frame, app: &TuiApp) {
+    let theme = &app.theme;
+
+    let mut lines = vec![Line::from(Span::styled("  Keys", theme.heading))];
+    for (action, keys) in app.keymap.bindings() {
+        let keys = if keys.is_empty() {
+            "-".to_string()
+        } else {
+            keys.iter().map(|k| k.label()).collect::<Vec<_>>().join(" ")
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {:<16}", keys), theme.accent),
+            Span::raw(action.description()),
+        ]));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        format!("  Commands ({})", app.keymap.key_label(Action::Palette)),
+        theme.heading,
+    )));
+    for (usage, description) in PaletteCommand::USAGE {
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {:<16}", usage), theme.accent),
+            Span::raw(*description),
+        ]));
+    }
+
+    let area = centered_rect(frame.area(), 64, lines.len() as u16 + 2);
+    let block = Block::default()
+        .title("Help")
+        .borders(Borders::ALL)
+        .border_style(theme.accent);
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// A rect of at most `width` x `height` centered within `area`.
+fn centered_rect(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+fn format_active_banner(theme: &Theme, job_id: &str, status: &TransferStatus) -> Line<'static> {
     match status {
         TransferStatus::Ready => Line::from(vec![
-            Span::styled("▶ ", Style::default().fg(Color::Green)),
+            Span::styled("▶ ", theme.success),
             Span::raw(format!("{}  Preparing...", job_id)),
         ]),
         TransferStatus::InProgress {
             percentage,
             current_file,
+            bytes_per_sec,
             ..
         } => {
             let bar = progress_bar(*percentage, 25);
@@ -340,20 +589,77 @@ fn format_active_banner(job_id: &str, status: &TransferStatus) -> Line<'static>
             } else {
                 current_file.clone()
             };
+            let speed = if *bytes_per_sec > 0 {
+                format!("  {}/s", format_bytes(*bytes_per_sec))
+            } else {
+                String::new()
+            };
             Line::from(vec![
-                Span::styled("▶ ", Style::default().fg(Color::Green)),
-                Span::raw(format!("{}  {} {:>3}%  {}", job_id, bar, percentage, file)),
+                Span::styled("▶ ", theme.success),
+                Span::raw(format!(
+                    "{}  {} {:>3}%{}  {}",
+                    job_id, bar, percentage, speed, file
+                )),
             ])
         }
         TransferStatus::CopyComplete => Line::from(vec![
-            Span::styled("▶ ", Style::default().fg(Color::Yellow)),
+            Span::styled("▶ ", theme.warning),
             Span::raw(format!("{}  Copy complete, verifying...", job_id)),
         ]),
+        TransferStatus::Verifying {
+            current,
+            total,
+            bytes_verified,
+            total_bytes,
+            bytes_per_sec,
+        } => {
+            let (pct, detail) = if *total_bytes > 0 {
+                let speed = if *bytes_per_sec > 0 {
+                    format!(" at {}/s", format_bytes(*bytes_per_sec))
+                } else {
+                    String::new()
+                };
+                (
+                    (bytes_verified * 100 / total_bytes).min(100) as u8,
+                    format!(
+                        "Verifying {} / {}{}",
+                        format_bytes(*bytes_verified),
+                        format_bytes(*total_bytes),
+                        speed
+                    ),
+                )
+            } else {
+                (
+                    (current * 100).checked_div(*total).unwrap_or(100) as u8,
+                    format!("Verifying {}/{}", current, total),
+                )
+            };
+            Line::from(vec![
+                Span::styled("▶ ", theme.warning),
+                Span::raw(format!(
+                    "{}  {} {:>3}%  {}",
+                    job_id,
+                    progress_bar(pct, 25),
+                    pct,
+                    detail
+                )),
+            ])
+        }
+        TransferStatus::GeneratingParity { percentage } => Line::from(vec![
+            Span::styled("▶ ", theme.warning),
+            Span::raw(format!(
+                "{}  {} {:>3}%  Generating parity",
+                job_id,
+                progress_bar(*percentage, 25),
+                percentage
+            )),
+        ]),
         TransferStatus::Complete {
             total_bytes,
             duration_secs,
+            ..
         } => Line::from(vec![
-            Span::styled("✓ ", Style::default().fg(Color::Green)),
+            Span::styled("✓ ", theme.success),
             Span::raw(format!(
                 "{}  Complete: {} in {}s",
                 job_id,
@@ -362,10 +668,36 @@ fn format_active_banner(job_id: &str, status: &TransferStatus) -> Line<'static>
             )),
         ]),
         TransferStatus::Failed(msg) => Line::from(vec![
-            Span::styled("✗ ", Style::default().fg(Color::Red)),
+            Span::styled("✗ ", theme.failure),
             Span::raw(format!("{}  ", job_id)),
-            Span::styled(format!("Failed: {}", msg), Style::default().fg(Color::Red)),
+            Span::styled(format!("Failed: {}", msg), theme.failure),
+        ]),
+        TransferStatus::Cancelled => Line::from(vec![
+            Span::styled("■ ", theme.warning),
+            Span::raw(format!("{}  Cancelled", job_id)),
         ]),
+        TransferStatus::Paused => Line::from(vec![
+            Span::styled("‖ ", theme.warning),
+            Span::raw(format!("{}  Paused", job_id)),
+        ]),
+    }
+}
+
+/// Icon for a job status. Shapes differ as well as colors so the state is
+/// readable in any theme.
+fn status_icon(theme: &Theme, status: &str) -> Span<'static> {
+    if status.eq_ignore_ascii_case("complete") {
+        Span::styled("✓", theme.success)
+    } else if status.eq_ignore_ascii_case("failed") {
+        Span::styled("✗", theme.failure)
+    } else if status.eq_ignore_ascii_case("cancelled") {
+        Span::styled("■", theme.warning)
+    } else if status.eq_ignore_ascii_case("skipped") {
+        Span::styled("=", theme.success)
+    } else if status.eq_ignore_ascii_case("queued") {
+        Span::styled("○", theme.warning)
+    } else {
+        Span::styled("•", theme.warning)
     }
 }
 
@@ -4,6 +4,7 @@ use figment::{
     providers::{Env, Format, Serialized, Toml},
 };
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::path::PathBuf;
 
@@ -18,6 +19,94 @@ pub enum NotificationChannelType {
     Slack,
 }
 
+/// Filesystem snapshot to take of the backup directory after each job
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotMode {
+    #[default]
+    None,
+    /// Read-only btrfs subvolume snapshot (backup directory must be a subvolume)
+    Btrfs,
+    /// ZFS snapshot of the dataset mounted at the backup directory
+    Zfs,
+}
+
+/// How native copy renames card entries the destination can't hold
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FilenameTranslation {
+    /// Write every name as it is on the card
+    #[default]
+    Off,
+    /// Rename names that differ only in case from a sibling's
+    CaseInsensitive,
+    /// Also replace characters Windows/SMB/exFAT reject, strip trailing
+    /// dots and spaces, and avoid reserved names like `CON`
+    Portable,
+}
+
+/// How much of a copy is re-read when verifying it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyMode {
+    /// Every file
+    #[default]
+    Full,
+    /// `verify_sample_percent` of the files, the largest first
+    Sample,
+}
+
+/// What becomes of the folder a failed job was writing to
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FailedDestination {
+    /// Rename it to `<name>.failed`, so it's told apart from a job still
+    /// copying
+    #[default]
+    Rename,
+    /// Remove it, and with it any chance of resuming the job
+    Delete,
+    /// Leave it as it is, at `<name>.partial`
+    Keep,
+}
+
+/// Unicode form native copy writes file names in
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnicodeNormalization {
+    /// Keep names as the card spells them
+    #[default]
+    Off,
+    /// Precomposed, as Windows and Linux tools write names
+    Nfc,
+    /// Decomposed, as macOS writes names
+    Nfd,
+}
+
+/// Where job history and other state is kept
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    /// SQLite database under /var/lib/bksd
+    #[default]
+    Sqlite,
+    /// In memory, optionally journaled to `storage_journal` (needs the
+    /// `memory-store` feature)
+    Memory,
+}
+
+/// How devices are detected and mounted on Linux
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HardwareAdapterType {
+    /// udev events, mounting under `mount_base` (needs root)
+    #[default]
+    Udev,
+    /// Through the udisks2 D-Bus service, as a normal user (needs the
+    /// `udisks2` feature)
+    Udisks2,
+}
+
 /// Configuration for the notification system
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct NotificationConfig {
@@ -27,16 +116,291 @@ pub struct NotificationConfig {
     pub slack_webhook: Option<String>,
 }
 
+/// Color palette for the TUI
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TuiTheme {
+    #[default]
+    Default,
+    /// Blue/orange instead of green/red for red-green color blindness
+    Colorblind,
+    /// No colors, only bold/reverse (also used when NO_COLOR is set)
+    Mono,
+}
+
+/// Something to do once a device's backup has completed and verified
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum CompletionAction {
+    /// Unmount and power the device down so it can be pulled. Always runs
+    /// last, whatever its position in the list
+    Eject,
+    /// Delete everything on the device. Only runs when transfers are verified
+    Wipe,
+    /// Run a shell command with the job's details in `BKSD_*` variables
+    Hook { command: String },
+    /// Also send the completion notification to this Slack webhook
+    Notify { slack_webhook: String },
+}
+
+/// Which devices are backed up when they are connected
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct DeviceFilterConfig {
+    /// Only back up devices matching one of these (empty = every device)
+    pub allow: Vec<DeviceMatch>,
+    /// Never back up devices matching one of these, even if allowed
+    pub deny: Vec<DeviceMatch>,
+}
+
+/// A description of devices. Every field that is set has to match; one with
+/// none set matches any device
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct DeviceMatch {
+    /// Filesystem UUID
+    pub uuid: Option<String>,
+    /// Volume label glob, e.g. `EOS_*`, compared case-insensitively
+    pub label: Option<String>,
+    /// Filesystem type, e.g. `exfat`
+    pub filesystem: Option<String>,
+    /// Smallest device size, in GB (10^9 bytes)
+    pub min_capacity_gb: Option<u64>,
+    /// Largest device size, in GB (10^9 bytes)
+    pub max_capacity_gb: Option<u64>,
+}
+
+/// Settings applied to one device, keyed by filesystem UUID or volume label
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct DeviceConfig {
+    /// Include patterns added to the global list for this device
+    pub include: Vec<String>,
+    /// Exclude patterns added to the global list for this device
+    pub exclude: Vec<String>,
+    /// Card directories backed up for this device, in place of the global
+    /// `source_paths` (an empty list backs up the whole card)
+    pub source_paths: Option<Vec<String>>,
+    /// Actions run in order after the device's backup completes
+    pub on_complete: Vec<CompletionAction>,
+    /// Untrusted card: mount it read-only, copy into the quarantine
+    /// directory without changing ownership, and only move the files into
+    /// the backup tree once `scan_command` passes
+    pub quarantine: bool,
+    /// Malware scan run on a quarantined copy before it is released. A
+    /// non-zero exit keeps the files in quarantine and fails the job
+    pub scan_command: Option<String>,
+    /// Name of a `[destinations.<name>]` backend the verified backup is
+    /// delivered to. Unset keeps it in the backup directory only
+    pub destination: Option<String>,
+}
+
+/// Kind of destination backend
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DestinationKind {
+    /// The backup directory itself
+    #[default]
+    Local,
+    /// Uploaded with the `sftp` client to `host`:`path`
+    Sftp,
+    /// Uploaded with `aws s3 cp` under `url`
+    S3,
+    /// Handed to `command` (e.g. `rclone copy`) from the backup directory
+    Sync,
+}
+
+/// A named destination backend devices can choose with `destination`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DestinationConfig {
+    pub kind: DestinationKind,
+    /// SFTP server as `[user@]host`
+    pub host: Option<String>,
+    /// SFTP directory backups go under
+    pub path: Option<String>,
+    /// S3 prefix backups go under, e.g. `s3://bucket/cards`
+    pub url: Option<String>,
+    /// Sync command, run with the hook variables plus `BKSD_RELATIVE_PATH`
+    pub command: Option<String>,
+    /// Checks the delivered copy; a non-zero exit fails the job. Without
+    /// one the remote copy is trusted as uploaded
+    pub verify_command: Option<String>,
+    /// Keep the backup in the backup directory once delivered
+    pub keep_local: bool,
+}
+
+impl Default for DestinationConfig {
+    fn default() -> Self {
+        Self {
+            kind: DestinationKind::Local,
+            host: None,
+            path: None,
+            url: None,
+            command: None,
+            verify_command: None,
+            keep_local: true,
+        }
+    }
+}
+
+/// Settings for the rsync transfer engine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RsyncConfig {
+    /// rsync binary to run, as a path or a name looked up on PATH
+    pub binary: PathBuf,
+    /// Extra arguments appended after bksd's own (e.g. `--modify-window=1`
+    /// for FAT cards, `--partial`)
+    pub args: Vec<String>,
+}
+
+impl Default for RsyncConfig {
+    fn default() -> Self {
+        Self {
+            binary: PathBuf::from("rsync"),
+            args: Vec::new(),
+        }
+    }
+}
+
+/// Settings for PAR2 parity generation after a verified backup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParityConfig {
+    /// Recovery data as a percentage of the backup's size; 0 disables it
+    pub redundancy: u32,
+    /// par2 binary to run (par2cmdline), as a path or a name looked up on
+    /// PATH
+    pub binary: PathBuf,
+    /// Extra arguments for `par2 create`, placed before the file list (e.g.
+    /// `-n4` to spread recovery blocks over four files)
+    pub args: Vec<String>,
+}
+
+impl Default for ParityConfig {
+    fn default() -> Self {
+        Self {
+            redundancy: 0,
+            binary: PathBuf::from("par2"),
+            args: Vec::new(),
+        }
+    }
+}
+
+/// Settings for the external transfer engine
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ExternalConfig {
+    /// Program to run for each transfer
+    pub program: Option<PathBuf>,
+    /// Arguments passed to the program
+    pub args: Vec<String>,
+}
+
+/// How a simulated card's data is split between its files
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SimFileSizes {
+    /// Every file the same size
+    #[default]
+    Even,
+    /// Every tenth file is a clip 50 times the size of the rest, like a
+    /// camera card of video with stills and sidecars
+    Clips,
+}
+
+/// A named simulated device, inserted with `sim.add {"profile": ...}`. Its
+/// files exist only here: it is always copied by the simulated engine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimProfile {
+    /// Card capacity in GiB
+    pub size_gb: u64,
+    /// Volume label (default: `TEST_DEVICE_<uuid>`)
+    pub label: Option<String>,
+    pub filesystem: String,
+    /// Number of files on the card
+    pub files: u64,
+    pub file_sizes: SimFileSizes,
+    /// Total size of the files in MiB
+    pub data_mb: u64,
+    /// Simulated copy speed in MiB/s
+    pub speed_mb_per_sec: u64,
+    /// Fail the copy with a read error once this far through
+    pub fail_at_percent: Option<u8>,
+    /// Pull the card this many seconds after it is inserted
+    pub remove_after_secs: Option<u64>,
+}
+
+impl Default for SimProfile {
+    fn default() -> Self {
+        Self {
+            size_gb: 64,
+            label: None,
+            filesystem: "ext4".to_string(),
+            files: 1,
+            file_sizes: SimFileSizes::Even,
+            data_mb: 500,
+            speed_mb_per_sec: 100,
+            fail_at_percent: None,
+            remove_after_secs: None,
+        }
+    }
+}
+
+/// Settings for the interactive TUI (`bksd tui`)
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct TuiConfig {
+    /// Keybinding overrides: action name -> keys, e.g. `history = ["H"]`.
+    /// Actions: quit, up, down, left, right, select, back, refresh, history
+    pub keybindings: HashMap<String, Vec<String>>,
+    /// Color palette: default, colorblind or mono
+    pub theme: TuiTheme,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppConfig {
     pub backup_directory: PathBuf,
     pub transfer_engine: TransferEngineType,
+    /// Times a failed transfer is retried, unless its error is one another
+    /// attempt can't fix (full or missing destination, card removed)
     pub retry_attempts: u32,
+    /// Wait before the first retry of a failed transfer in seconds, doubled
+    /// for each retry after
+    pub retry_backoff_secs: u64,
+    /// On SIGTERM, how long to wait for running jobs to finish before
+    /// marking them interrupted and exiting. Keep it below systemd's
+    /// `TimeoutStopSec`
+    pub shutdown_timeout_secs: u64,
     pub verbose: bool,
     pub simulation: bool,
     pub mount_base: PathBuf,
+    /// How devices are detected and mounted on Linux. `udisks2` lets the
+    /// daemon run without root, and ignores `mount_base`
+    pub hardware_adapter: HardwareAdapterType,
+    /// Layout of each job's directory under `backup_directory`. Placeholders:
+    /// `{label}`, `{timestamp}`, `{hostname}`, `{operator}`, `{reel}`, `{day}`,
+    /// `{camera}`, `{camera_model}`. Must include `{timestamp}`, so each
+    /// backup of a card gets its own directory
+    pub destination_template: String,
+    /// Name substituted for `{operator}` in the destination template
+    pub operator: Option<String>,
+    /// Regex matched against each card's volume label, whose named groups
+    /// `reel`, `day` and `camera` are stored on the job, e.g.
+    /// `^(?P<reel>[A-Z]\d{3})_(?P<day>DAY\d+)_(?P<camera>CAM[A-Z])$`
+    pub label_pattern: Option<String>,
+    /// IANA zone (e.g. `Europe/Berlin`) for folder names and displayed times.
+    /// Stored timestamps are always UTC. Unset uses the system zone
+    pub timezone: Option<String>,
     /// Output logs as JSON instead of pretty console format
     pub log_json: bool,
+    /// Append every RPC and web request (method, duration, outcome, peer,
+    /// identity) to this file as JSON lines. Unset keeps them at debug
+    /// level in the normal log only
+    pub access_log: Option<PathBuf>,
     /// Enable the RPC server for client connections
     pub rpc_enabled: bool,
     /// Address and port for the RPC server to bind to
@@ -45,10 +409,198 @@ pub struct AppConfig {
     pub web_enabled: bool,
     /// Address and port for the web dashboard to bind to
     pub web_bind: SocketAddr,
+    /// Bearer token required by the file and zip downloads, uploads and
+    /// `backup.trigger` on the dashboard's socket. Unset disables them
+    pub web_token: Option<String>,
     /// Verify file integrity after transfer using BLAKE3 checksums
     pub verify_transfers: bool,
+    /// Also re-read every file on the card when verifying, comparing its
+    /// hash with the copy's and the destination's (engines that hash while
+    /// copying: native copy)
+    pub paranoid_verify: bool,
+    /// Verify every file, or only a sample of them on cards too big to
+    /// read back in full (engines that hash while copying: native copy)
+    pub verify_mode: VerifyMode,
+    /// Percentage of files checked with `verify_mode = "sample"`
+    pub verify_sample_percent: u8,
+    /// Continue a device's interrupted job into its existing destination when
+    /// the card is reinserted, skipping files already copied (native copy only)
+    pub resume_interrupted: bool,
+    /// What to do with a failed job's partly written destination: rename it
+    /// with a `.failed` suffix, delete it or keep it. A renamed one is
+    /// still resumed from when the card is reinserted
+    pub failed_destination: FailedDestination,
+    /// Don't copy a card again when its contents are unchanged since its
+    /// last verified backup; the job is recorded as `skipped` instead
+    pub skip_unchanged_cards: bool,
+    /// Hold new jobs while the backup directory has less than this many GB
+    /// free, notifying when it drops below and when it is freed. Unset
+    /// disables the check
+    pub min_free_space_gb: Option<u64>,
+    /// How often the backup directory's free space is checked against
+    /// `min_free_space_gb`, in seconds
+    pub space_check_interval_secs: u64,
+    /// Back up cards that are already inserted when the daemon starts, as
+    /// if they had just been plugged in
+    pub scan_on_startup: bool,
+    /// Leave a card found at startup alone if it was backed up successfully
+    /// within this many hours, e.g. before the daemon was restarted
+    pub startup_scan_skip_hours: u64,
+    /// Times of day (`HH:MM-HH:MM`, comma-separated, in `timezone`) cards
+    /// are backed up in. One inserted outside them gets a `queued` job that
+    /// starts when the window opens. Unset backs up straight away
+    pub backup_window: Option<String>,
+    /// Number of files the native copy engine copies at once. Values above 1
+    /// help fast readers (USB3, UHS-II) that one sequential stream can't saturate
+    pub copy_threads: usize,
+    /// Times the native copy engine retries a file that failed to copy
+    /// before counting it as an error, for flaky readers' transient EIO
+    pub file_retries: u32,
+    /// Wait before the first per-file retry in milliseconds, doubled for
+    /// each retry after
+    pub file_retry_backoff_ms: u64,
+    /// Rescue mode for failing cards: native copy reads a chunk that fails
+    /// with an I/O error again a block at a time, zero-fills the blocks that
+    /// still won't read and finishes the backup with a report of what is
+    /// missing, rather than stopping at the first bad sector
+    pub rescue_mode: bool,
+    /// Native copy read/write buffer size in KB. 0 benchmarks a few sizes
+    /// against the card and destination at job start and uses the fastest
+    pub copy_buffer_kb: u32,
+    /// Bytes processed between progress updates from the copy engines and
+    /// the verifier. Lower it for slow network destinations
+    pub progress_update_interval_bytes: u64,
+    /// Minimum milliseconds between those progress updates, so fast disks
+    /// don't flood the channel
+    pub progress_update_interval_ms: u64,
+    /// Skip files this device already had backed up unchanged by an earlier
+    /// verified job, so each backup folder only holds new or changed files
+    /// (native copy only)
+    pub incremental: bool,
+    /// Hardlink files unchanged since the device's most recent backup into
+    /// the new one instead of copying them (like rsync `--link-dest`)
+    pub hardlink_unchanged: bool,
+    /// Copy extended attributes and POSIX ACLs (like rsync `-X -A`). Off by
+    /// default as FAT/exFAT cards have none and many NAS shares reject them
+    pub preserve_xattrs: bool,
+    /// Native copy: rename entries whose names the destination can't hold
+    /// or would merge, reporting each rename on the job
+    pub filename_translation: FilenameTranslation,
+    /// Native copy: write file names in this Unicode form, so backups of
+    /// macOS- and Windows-written cards name files the same way
+    pub unicode_normalization: UnicodeNormalization,
+    /// Native copy: file everything into `YYYY/MM/DD` folders by capture
+    /// date instead of mirroring the card's layout, listing where each file
+    /// came from in `BKSD_ORIGINAL_PATHS.tsv`
+    pub organize_by_date: bool,
+    /// zstd level (1-22) used by the archive transfer engine
+    pub archive_compression_level: i32,
+    /// zstd level (1-22) used by the raw image engine. Unset writes a plain
+    /// `.img` that can be loop-mounted directly
+    pub image_compression_level: Option<i32>,
+    /// Raw image engine: image the whole card including its partition table,
+    /// not just the partition that was detected
+    pub image_whole_device: bool,
+    /// Cap on transfer throughput in MB/s, so a backup to a NAS doesn't
+    /// saturate the network or disk. Unset means unlimited
+    pub max_throughput_mbps: Option<u64>,
+    /// Only back up files matching one of these globs (empty = everything)
+    pub include: Vec<String>,
+    /// Skip files and directories matching these globs, e.g. `*.tmp`,
+    /// `.Trashes/**`
+    pub exclude: Vec<String>,
+    /// Only back up these directories of each card, e.g. `["DCIM",
+    /// "PRIVATE"]` (empty = the whole card)
+    pub source_paths: Vec<String>,
+    /// Per-device settings, keyed by filesystem UUID or volume label
+    pub devices: HashMap<String, DeviceConfig>,
+    /// Which connected devices are backed up at all
+    pub device_filter: DeviceFilterConfig,
+    /// Ignore devices smaller than this many GB (10^9 bytes), such as boot
+    /// partitions
+    pub min_device_size_gb: Option<u64>,
+    /// Ignore devices larger than this many GB, such as external drives
+    pub max_device_size_gb: Option<u64>,
+    /// Destination backends device rules can deliver backups to, by name
+    pub destinations: HashMap<String, DestinationConfig>,
+    /// Simulated device profiles, by name (simulation mode only)
+    pub sim_profiles: HashMap<String, SimProfile>,
+    /// Where quarantined devices are copied before their scan passes
+    /// (default: `.quarantine` under the backup directory). Must be on the
+    /// same filesystem as the backup directory so releasing is a rename
+    pub quarantine_directory: Option<PathBuf>,
+    /// Where upload sessions stage files until they are completed and
+    /// ingested. Uploads also need `web_token`; unset disables them
+    pub upload_directory: Option<PathBuf>,
+    /// Directories `backup.trigger` may back up: the requested path must be
+    /// one of them or inside one. Empty disables manual backups
+    pub manual_backup_roots: Vec<PathBuf>,
+    /// Seconds between progress snapshots written to the job's status log
+    /// while it copies or verifies (0 = only when each phase starts)
+    pub progress_snapshot_secs: u64,
+    /// Flag suspicious source files (zero-byte media, truncated videos, DCIM
+    /// numbering gaps) before transfer
+    pub detect_anomalies: bool,
+    /// Probe copied MP4/MOV files for a usable moov index after verification
+    pub probe_videos: bool,
+    /// Hold a systemd-inhibit lock against suspend/shutdown while jobs run
+    pub inhibit_sleep: bool,
+    /// Snapshot the backup directory after each verified job
+    pub destination_snapshot: SnapshotMode,
+    /// Set the immutable attribute (chattr +i) on each backup once verified
+    pub immutable_backups: bool,
+    /// On a btrfs backup directory, copy each job into its own subvolume and
+    /// make it read-only once verified. Ignored on other filesystems
+    pub btrfs_subvolumes: bool,
+    /// Write a signed `BKSD_OK` badge into each verified backup, which
+    /// `bksd verify` checks
+    pub integrity_badge: bool,
+    /// ed25519 key badges are signed with, generated on first use
+    pub signing_key: PathBuf,
+    /// Further directories each verified backup is copied to and verified
+    /// in, one after another (e.g. a secondary USB disk)
+    pub mirror_directories: Vec<PathBuf>,
+    /// Volume label of the cold-archive drive. When a drive with this label
+    /// is connected, old backups are moved onto it instead of it being backed up
+    pub archive_label: Option<String>,
+    /// Move verified backups older than this many days to the archive drive
+    pub archive_after_days: u32,
+    /// Persistence backend for jobs, archive drives and catalogs
+    pub storage: StorageBackend,
+    /// JSONL file the memory backend appends every change to and replays at
+    /// startup. Unset keeps history only until the daemon stops
+    pub storage_journal: Option<PathBuf>,
+    /// Address of a `bksd hub` to push job reports to. Unset disables reporting
+    pub hub_addr: Option<SocketAddr>,
+    /// Shared secret the hub requires on every report. Set on the hub and on
+    /// each station
+    pub hub_token: Option<String>,
+    /// Name this station reports to the hub under (default: hostname)
+    pub station_name: Option<String>,
+    /// Seconds between reports to the hub
+    pub hub_report_secs: u64,
+    /// URL anonymized job statistics are POSTed to; unset sends nothing
+    pub telemetry_endpoint: Option<String>,
+    /// Hours covered by each telemetry report
+    pub telemetry_interval_hours: u64,
+    /// Cron expression (`minute hour day month weekday`, in `timezone`) for
+    /// re-verifying old backups against their manifests. Unset only scrubs
+    /// on request
+    pub scrub_schedule: Option<String>,
+    /// Only scrub backups at least this many days old
+    pub scrub_min_age_days: u32,
+    /// Backups re-verified per scheduled run, those unchecked longest first
+    pub scrub_jobs_per_run: u32,
+    /// rsync engine settings
+    pub rsync: RsyncConfig,
+    /// Parity files written beside each verified backup
+    pub parity: ParityConfig,
+    /// External transfer engine settings
+    pub external: ExternalConfig,
     /// Notification settings
     pub notifications: NotificationConfig,
+    /// Interactive TUI settings
+    pub tui: TuiConfig,
 }
 
 impl Default for AppConfig {
@@ -57,21 +609,98 @@ impl Default for AppConfig {
             backup_directory: PathBuf::from("/tmp/bksd"),
             transfer_engine: TransferEngineType::Rsync,
             retry_attempts: 3,
+            retry_backoff_secs: 10,
+            shutdown_timeout_secs: 60,
             verbose: false,
             simulation: false,
             mount_base: PathBuf::from("/run/bksd"),
+            hardware_adapter: HardwareAdapterType::Udev,
+            destination_template: "{label}/{timestamp}".to_string(),
+            operator: None,
+            label_pattern: None,
+            timezone: None,
             log_json: false,
+            access_log: None,
             rpc_enabled: true,
             rpc_bind: SocketAddr::from(([127, 0, 0, 1], 9847)),
             web_enabled: true,
             web_bind: SocketAddr::from(([127, 0, 0, 1], 9848)),
+            web_token: None,
             verify_transfers: true,
+            paranoid_verify: false,
+            verify_mode: VerifyMode::Full,
+            verify_sample_percent: 10,
+            resume_interrupted: true,
+            failed_destination: FailedDestination::Rename,
+            skip_unchanged_cards: true,
+            min_free_space_gb: None,
+            space_check_interval_secs: 60,
+            scan_on_startup: true,
+            startup_scan_skip_hours: 12,
+            backup_window: None,
+            copy_threads: 1,
+            file_retries: 2,
+            file_retry_backoff_ms: 500,
+            rescue_mode: false,
+            copy_buffer_kb: 128,
+            progress_update_interval_bytes: 1024 * 1024,
+            progress_update_interval_ms: 500,
+            incremental: false,
+            hardlink_unchanged: false,
+            preserve_xattrs: false,
+            filename_translation: FilenameTranslation::Off,
+            unicode_normalization: UnicodeNormalization::Off,
+            organize_by_date: false,
+            progress_snapshot_secs: 30,
+            archive_compression_level: 3,
+            image_compression_level: None,
+            image_whole_device: false,
+            max_throughput_mbps: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            source_paths: Vec::new(),
+            devices: HashMap::new(),
+            device_filter: DeviceFilterConfig::default(),
+            min_device_size_gb: None,
+            max_device_size_gb: None,
+            destinations: HashMap::new(),
+            sim_profiles: HashMap::new(),
+            quarantine_directory: None,
+            upload_directory: None,
+            manual_backup_roots: Vec::new(),
+            detect_anomalies: true,
+            probe_videos: false,
+            inhibit_sleep: true,
+            destination_snapshot: SnapshotMode::None,
+            immutable_backups: false,
+            btrfs_subvolumes: false,
+            integrity_badge: false,
+            mirror_directories: Vec::new(),
+            signing_key: PathBuf::from("/var/lib/bksd/signing.key"),
+            archive_label: None,
+            archive_after_days: 30,
+            storage: StorageBackend::Sqlite,
+            storage_journal: None,
+            hub_addr: None,
+            hub_token: None,
+            station_name: None,
+            hub_report_secs: 10,
+            telemetry_endpoint: None,
+            telemetry_interval_hours: 24,
+            scrub_schedule: None,
+            scrub_min_age_days: 7,
+            scrub_jobs_per_run: 10,
+            rsync: RsyncConfig::default(),
+            parity: ParityConfig::default(),
+            external: ExternalConfig::default(),
             notifications: NotificationConfig::default(),
+            tui: TuiConfig::default(),
         }
     }
 }
 
 impl AppConfig {
+    #[allow(clippy::result_large_err)]
     pub fn new(args: Option<&impl Serialize>) -> Result<Self, figment::Error> {
         let mut figment = Figment::new()
             .merge(Serialized::defaults(AppConfig::default()))
@@ -83,6 +712,33 @@ impl AppConfig {
 
         figment = figment.merge(Env::prefixed("BKSD_"));
 
-        figment.extract()
+        let config: Self = figment.extract()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reject settings that load but can't work together.
+    #[allow(clippy::result_large_err)]
+    fn validate(&self) -> Result<(), figment::Error> {
+        if !self.destination_template.contains("{timestamp}") {
+            return Err(figment::Error::from(format!(
+                "destination_template must include {{timestamp}}: {}",
+                self.destination_template
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_needs_timestamp() {
+        let mut config = AppConfig::default();
+        assert!(config.validate().is_ok());
+        config.destination_template = "{hostname}/{label}".to_string();
+        assert!(config.validate().is_err());
     }
 }
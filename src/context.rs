@@ -1,26 +1,151 @@
 use std::sync::Arc;
 
+use ed25519_dalek::SigningKey;
+
+use crate::adapters::Simulator;
 use crate::config::AppConfig;
-use crate::core::ProgressTracker;
+use crate::core::clock::Stopwatch;
+use crate::core::device_filter::{DeviceFilter, IgnoredDevices};
+use crate::core::label_fields::LabelPattern;
+use crate::core::manual::ManualBackups;
 use crate::core::notifications::{self, NotificationChannel};
-use tokio_rusqlite::Connection;
+use crate::core::scrub::{Schedule, Scrubber};
+use crate::core::signing;
+use crate::core::space::SpaceMonitor;
+use crate::core::subvolume;
+use crate::core::timestamp::{self, DisplayZone};
+use crate::core::transfer_engine::TransferEngineType;
+use crate::core::upload::UploadSessions;
+use crate::core::window::BackupWindow;
+use crate::core::{PowerInhibitor, ProgressTracker};
+use crate::db::Store;
 
 #[derive(Clone)]
 pub struct AppContext {
     pub config: Arc<AppConfig>,
-    pub db: Connection,
+    pub db: Arc<dyn Store>,
     pub progress: ProgressTracker,
+    pub inhibitor: PowerInhibitor,
     pub notifier: Option<Arc<dyn NotificationChannel>>,
+    /// Open upload sessions; None when uploads are disabled
+    pub uploads: Option<UploadSessions>,
+    /// Directories to back up on request (`backup.trigger`)
+    pub manual: ManualBackups,
+    /// Zone for destination folder names
+    pub zone: DisplayZone,
+    /// Compiled `label_pattern`; None when unset or invalid
+    pub label_pattern: Option<LabelPattern>,
+    /// Compiled `device_filter` and device size limits; rejects every
+    /// device when invalid
+    pub device_filter: DeviceFilter,
+    /// Connected devices the filter turned away
+    pub ignored_devices: IgnoredDevices,
+    /// Create each job's destination as a btrfs subvolume
+    /// (`btrfs_subvolumes` with the backup directory on btrfs)
+    pub subvolumes: bool,
+    /// Key to sign `BKSD_OK` badges with; None unless `integrity_badge`
+    pub signing_key: Option<SigningKey>,
+    /// When the daemon started (UTC RFC3339)
+    pub started_at: String,
+    /// Running since startup, including time the system spent suspended
+    pub uptime: Stopwatch,
+    /// Injects devices in simulation mode; None otherwise
+    pub simulator: Option<Simulator>,
+    /// Parsed `scrub_schedule`; None when unset or invalid
+    pub scrub_schedule: Option<Schedule>,
+    /// Parsed `backup_window`; None when unset or invalid
+    pub backup_window: Option<BackupWindow>,
+    /// Keeps scheduled and requested scrubs from overlapping
+    pub scrubber: Scrubber,
+    /// Whether new jobs are held for lack of backup space
+    pub space: SpaceMonitor,
 }
 
 impl AppContext {
-    pub fn new(config: AppConfig, db: Connection) -> Self {
+    pub fn new(config: AppConfig, db: Arc<dyn Store>) -> Self {
         let notifier = notifications::create_notifier(&config.notifications);
+        let inhibitor = PowerInhibitor::new(config.inhibit_sleep && !config.simulation);
+        let zone = DisplayZone::from_config(config.timezone.as_deref()).unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Invalid timezone, using system zone");
+            DisplayZone::Local
+        });
+        let label_pattern = config.label_pattern.as_deref().and_then(|pattern| {
+            LabelPattern::new(pattern)
+                .inspect_err(|e| tracing::warn!(error = %e, "Invalid label pattern, ignoring"))
+                .ok()
+        });
+        let device_filter = DeviceFilter::new(&config).unwrap_or_else(|e| {
+            tracing::error!(error = %e, "Invalid device filter, not backing up any device");
+            DeviceFilter::reject_all()
+        });
+        let subvolumes = config.btrfs_subvolumes
+            && !config.simulation
+            && if subvolume::is_btrfs(&config.backup_directory) {
+                true
+            } else {
+                tracing::warn!(
+                    "BKSD_BTRFS_SUBVOLUMES is set but the backup directory is not on btrfs, using plain directories"
+                );
+                false
+            };
+        let signing_key = (config.integrity_badge && !config.simulation)
+            .then(|| {
+                signing::load_or_create(&config.signing_key)
+                    .inspect_err(|e| {
+                        tracing::warn!(error = %e, "Signing key unavailable, not writing badges")
+                    })
+                    .ok()
+            })
+            .flatten();
+        let uploads = match (&config.upload_directory, &config.web_token) {
+            // An image engine needs a block device, which an upload hasn't got
+            (Some(_), _) if config.transfer_engine == TransferEngineType::RawImage => {
+                tracing::warn!("Uploads are not supported by the raw image engine, disabling");
+                None
+            }
+            (Some(directory), Some(_)) => Some(UploadSessions::new(directory.clone())),
+            (Some(_), None) => {
+                tracing::warn!(
+                    "BKSD_UPLOAD_DIRECTORY is set without BKSD_WEB_TOKEN, disabling uploads"
+                );
+                None
+            }
+            (None, _) => None,
+        };
+        let simulator = config.simulation.then(Simulator::new);
+        let scrub_schedule = config.scrub_schedule.as_deref().and_then(|schedule| {
+            schedule
+                .parse::<Schedule>()
+                .inspect_err(|e| tracing::warn!(error = %e, "Invalid scrub schedule, ignoring"))
+                .ok()
+        });
+        let backup_window = config.backup_window.as_deref().and_then(|window| {
+            window
+                .parse::<BackupWindow>()
+                .inspect_err(|e| tracing::warn!(error = %e, "Invalid backup window, ignoring"))
+                .ok()
+        });
         Self {
             config: Arc::new(config),
             db,
             progress: ProgressTracker::new(),
+            inhibitor,
             notifier,
+            uploads,
+            manual: ManualBackups::default(),
+            zone,
+            label_pattern,
+            device_filter,
+            ignored_devices: IgnoredDevices::default(),
+            subvolumes,
+            signing_key,
+            started_at: timestamp::now_utc(),
+            uptime: Stopwatch::start(),
+            simulator,
+            scrub_schedule,
+            backup_window,
+            scrubber: Scrubber::default(),
+            space: SpaceMonitor::default(),
         }
     }
 }
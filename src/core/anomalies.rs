@@ -0,0 +1,152 @@
+//! Anomaly detection for source media.
+//!
+//! Scans a card before transfer and flags files that usually mean the camera
+//! failed to finish writing them: zero-byte media, video containers whose box
+//! headers run past the end of the file, and RAW files far smaller than any
+//! real capture. Gaps in DCIM file numbering are reported alongside (see
+//! [`crate::core::dcim`]). The copy still proceeds - these are notices for the
+//! operator to act on before the card is formatted.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use tracing::{debug, warn};
+
+use crate::core::dcim;
+use crate::core::media::{MediaKind, read_bmff_layout};
+use crate::core::models::JobNotice;
+
+/// RAW files smaller than this are almost certainly truncated (1MB)
+const MIN_RAW_SIZE: u64 = 1024 * 1024;
+
+/// Scan the source directory and return a notice for each suspicious file
+/// and each gap in DCIM numbering.
+///
+/// Runs on a blocking thread. A source that can't be walked yields no notices;
+/// the transfer itself will surface the underlying error.
+pub async fn detect(source: &Path) -> Vec<JobNotice> {
+    let source = source.to_path_buf();
+
+    let result = tokio::task::spawn_blocking(move || {
+        let mut notices = Vec::new();
+        scan_recursive(&source, &source, &mut notices)?;
+        notices.extend(dcim::find_gaps(&source));
+        Ok::<_, anyhow::Error>(notices)
+    })
+    .await;
+
+    match result {
+        Ok(Ok(notices)) => notices,
+        Ok(Err(e)) => {
+            debug!(error = %e, "Anomaly scan skipped");
+            Vec::new()
+        }
+        Err(e) => {
+            warn!(error = %e, "Anomaly scan task failed");
+            Vec::new()
+        }
+    }
+}
+
+fn scan_recursive(base: &Path, current: &Path, notices: &mut Vec<JobNotice>) -> Result<()> {
+    for entry in fs::read_dir(current)? {
+        let path = entry?.path();
+
+        let Ok(metadata) = path.symlink_metadata() else {
+            continue;
+        };
+
+        if metadata.is_dir() {
+            scan_recursive(base, &path, notices)?;
+        } else if metadata.is_file() {
+            let relative = path.strip_prefix(base).unwrap_or(&path);
+            if let Some(notice) = check_file(&path, relative, metadata.len()) {
+                notices.push(notice);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check a single file, returning a notice if it looks like a failed write.
+fn check_file(path: &Path, relative: &Path, size: u64) -> Option<JobNotice> {
+    let kind = MediaKind::from_path(path)?;
+    let relative = relative.to_string_lossy().to_string();
+
+    if size == 0 {
+        return Some(JobNotice {
+            kind: "zero_byte_file".to_string(),
+            message: format!("{} is empty (0 bytes)", relative),
+            path: Some(relative),
+        });
+    }
+
+    match kind {
+        MediaKind::Raw if size < MIN_RAW_SIZE => Some(JobNotice {
+            kind: "small_raw".to_string(),
+            message: format!(
+                "{} is only {} bytes, smaller than any complete RAW capture",
+                relative, size
+            ),
+            path: Some(relative),
+        }),
+        MediaKind::Video => match read_bmff_layout(path) {
+            Ok(layout) if layout.truncated => Some(JobNotice {
+                kind: "truncated_video".to_string(),
+                message: format!("{} container ends mid-box (truncated recording)", relative),
+                path: Some(relative),
+            }),
+            Ok(_) => None,
+            Err(e) => Some(JobNotice {
+                kind: "unreadable_video".to_string(),
+                message: format!("{} header could not be read: {}", relative, e),
+                path: Some(relative),
+            }),
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_detect_flags_suspicious_files() {
+        let temp = tempdir().unwrap();
+        let dcim = temp.path().join("DCIM/100CANON");
+        fs::create_dir_all(&dcim).unwrap();
+
+        fs::write(dcim.join("IMG_0001.JPG"), b"").unwrap();
+        fs::write(dcim.join("IMG_0002.CR3"), vec![0u8; 2048]).unwrap();
+        fs::write(dcim.join("IMG_0003.CR3"), vec![0u8; 2 * 1024 * 1024]).unwrap();
+        fs::write(
+            dcim.join("MVI_0004.MP4"),
+            [0, 0, 0x10, 0, b'f', b't', b'y', b'p'],
+        )
+        .unwrap();
+        fs::write(temp.path().join("notes.txt"), b"").unwrap();
+
+        let mut notices = detect(temp.path()).await;
+        notices.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let kinds: Vec<&str> = notices.iter().map(|n| n.kind.as_str()).collect();
+        assert_eq!(
+            kinds,
+            vec!["zero_byte_file", "small_raw", "truncated_video"]
+        );
+        assert_eq!(
+            notices[0].path.as_deref(),
+            Some("DCIM/100CANON/IMG_0001.JPG")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_detect_missing_source() {
+        let notices = detect(Path::new("/nonexistent/bksd/source")).await;
+        assert!(notices.is_empty());
+    }
+}
@@ -0,0 +1,274 @@
+//! Cold-archive tiering.
+//!
+//! Completed backups older than `archive_after_days` are moved off the backup
+//! directory onto a designated external drive whenever that drive is plugged
+//! in. Each backup is copied with the native engine, verified against the
+//! hashes computed during the copy, and only then removed from the backup
+//! directory. The catalog keeps pointing at the job, now on the archive drive.
+
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Result, bail};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::config::AppConfig;
+use crate::core::filter::PathFilter;
+use crate::core::hardware::BlockDevice;
+use crate::core::immutable;
+use crate::core::subvolume;
+use crate::core::transfer_engine::{self, PauseToken, TransferRequest};
+use crate::core::verifier::verify_from_hashes;
+use crate::db::Store;
+
+/// Directory on the archive drive that holds tiered backups
+const ARCHIVE_ROOT: &str = "bksd-archive";
+
+/// Outcome of a tiering pass
+#[derive(Debug, Default)]
+pub struct TieringSummary {
+    pub archived: usize,
+    pub failed: usize,
+    pub bytes_moved: u64,
+}
+
+/// Whether a connected device is the configured archive drive.
+pub fn is_archive_drive(config: &AppConfig, device: &BlockDevice) -> bool {
+    config.archive_label.as_deref() == Some(device.label.as_str())
+}
+
+/// Move every eligible backup onto the archive drive.
+///
+/// Jobs are processed oldest first. A failure on one job is logged and the
+/// pass continues with the next; the failed backup stays where it was.
+pub async fn run_tiering(
+    store: &dyn Store,
+    config: &AppConfig,
+    volume: &BlockDevice,
+) -> Result<TieringSummary> {
+    let jobs = store.list_archivable(config.archive_after_days).await?;
+    let mut summary = TieringSummary::default();
+
+    info!(
+        volume = %volume.label,
+        eligible = jobs.len(),
+        older_than_days = config.archive_after_days,
+        "Starting archive tiering"
+    );
+
+    for job in jobs {
+        let Some(source) = job.destination_path.as_deref().map(PathBuf::from) else {
+            continue;
+        };
+        // Never move (and so delete) a directory outside the backup tree
+        let target = match archive_path(&config.backup_directory, &volume.mount_point, &source) {
+            Ok(target) => target,
+            Err(e) => {
+                warn!(job_id = %job.id, error = %e, "Refusing to archive backup");
+                summary.failed += 1;
+                continue;
+            }
+        };
+
+        match move_backup(&job.id, &source, &target, config).await {
+            Ok(bytes) => {
+                store
+                    .record_archive(
+                        job.id.clone(),
+                        volume.uuid.clone(),
+                        volume.label.clone(),
+                        target.to_string_lossy().to_string(),
+                    )
+                    .await?;
+
+                info!(
+                    job_id = %job.id,
+                    archive_path = %target.display(),
+                    bytes = bytes,
+                    "Backup moved to archive drive"
+                );
+                summary.archived += 1;
+                summary.bytes_moved += bytes;
+            }
+            Err(e) => {
+                warn!(job_id = %job.id, error = %e, "Failed to archive backup");
+                summary.failed += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Check a job's backup can be read, returning an error naming the archive
+/// drive to connect if it lives on one that is currently detached.
+pub async fn ensure_available(store: &dyn Store, job_id: &str) -> Result<()> {
+    if let Some(volume) = store.locate_archive(job_id.to_string()).await?
+        && !volume.connected
+    {
+        bail!(
+            "Backup is on archive drive {} (UUID {}), last seen {}. Connect it and try again.",
+            volume.label,
+            volume.uuid,
+            volume.last_seen_at
+        );
+    }
+    Ok(())
+}
+
+/// Location of a backup on the archive drive, mirroring its layout under the
+/// backup directory (e.g. `<mount>/bksd-archive/CARD_LABEL/2024-01-01_T1200_00`).
+/// Fails for a backup that isn't inside the backup directory.
+fn archive_path(backup_dir: &Path, mount_point: &Path, source: &Path) -> Result<PathBuf> {
+    let relative = match source.strip_prefix(backup_dir) {
+        Ok(relative)
+            if relative.components().next().is_some()
+                && relative
+                    .components()
+                    .all(|c| matches!(c, Component::Normal(_))) =>
+        {
+            relative
+        }
+        _ => bail!(
+            "{} is not inside the backup directory {}",
+            source.display(),
+            backup_dir.display()
+        ),
+    };
+    Ok(mount_point.join(ARCHIVE_ROOT).join(relative))
+}
+
+/// Copy a backup to the archive drive, verify it, then remove the original.
+/// If backups are marked immutable the flag is cleared before removal.
+/// Returns the number of bytes moved.
+async fn move_backup(
+    job_id: &str,
+    source: &Path,
+    target: &Path,
+    config: &AppConfig,
+) -> Result<u64> {
+    if !source.is_dir() {
+        bail!("Backup directory missing: {}", source.display());
+    }
+
+    let request = TransferRequest {
+        job_id: job_id.to_string(),
+        source: source.to_path_buf(),
+        device: None,
+        destination: target.to_path_buf(),
+        owner: None,
+        completed: Vec::new(),
+        file_log: None,
+        filter: PathFilter::default(),
+        backed_up: Vec::new(),
+        link_dest: None,
+        cancel: CancellationToken::new(),
+        pause: PauseToken::default(),
+    };
+
+    // Progress isn't surfaced for tiering; drain it so the engine never blocks
+    let (tx, mut rx) = mpsc::channel(100);
+    let drain = tokio::spawn(async move { while rx.recv().await.is_some() {} });
+
+    let engine = transfer_engine::create_backup_copy_engine(config);
+    let result = engine.transfer(&request, tx).await;
+    let _ = drain.await;
+    let result = result?;
+
+    if let Some(hashes) = &result.file_hashes {
+        verify_from_hashes(job_id, target, hashes, None, &request.cancel).await?;
+    }
+
+    if subvolume::is_subvolume(source) {
+        subvolume::unseal(source).await?;
+    }
+    if config.immutable_backups {
+        immutable::release(source).await?;
+    }
+    tokio::fs::remove_dir_all(source).await?;
+
+    Ok(result.total_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_path_mirrors_backup_layout() {
+        let path = archive_path(
+            Path::new("/srv/backups"),
+            Path::new("/run/bksd/ARCHIVE-03"),
+            Path::new("/srv/backups/EOS_DIGITAL/2024-01-01_T1200_00"),
+        )
+        .unwrap();
+        assert_eq!(
+            path,
+            PathBuf::from("/run/bksd/ARCHIVE-03/bksd-archive/EOS_DIGITAL/2024-01-01_T1200_00")
+        );
+    }
+
+    #[test]
+    fn test_archive_path_outside_backup_dir() {
+        for source in [
+            "/elsewhere/old",
+            "/srv/backups",
+            "/srv/backups/../../home/x",
+        ] {
+            assert!(
+                archive_path(
+                    Path::new("/srv/backups"),
+                    Path::new("/mnt/archive"),
+                    Path::new(source),
+                )
+                .is_err(),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_move_backup() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("backups/CARD/run");
+        std::fs::create_dir_all(source.join("DCIM")).unwrap();
+        std::fs::write(source.join("DCIM/IMG_0001.JPG"), b"image data").unwrap();
+
+        let target = temp.path().join("archive/CARD/run");
+        let bytes = move_backup("job-1", &source, &target, &AppConfig::default())
+            .await
+            .unwrap();
+
+        assert_eq!(bytes, 10);
+        assert!(!source.exists());
+        assert_eq!(
+            std::fs::read(target.join("DCIM/IMG_0001.JPG")).unwrap(),
+            b"image data"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_move_backup_keeps_layout() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("backups/CARD/run");
+        std::fs::create_dir_all(source.join("DCIM/100CANON")).unwrap();
+        std::fs::write(source.join("DCIM/100CANON/IMG:0001.JPG"), b"image data").unwrap();
+        std::fs::write(source.join("BKSD_OK"), b"ok").unwrap();
+
+        // Settings for copying cards don't apply to copying a backup
+        let config = AppConfig {
+            organize_by_date: true,
+            filename_translation: crate::config::FilenameTranslation::Portable,
+            ..Default::default()
+        };
+        let target = temp.path().join("archive/CARD/run");
+        move_backup("job-1", &source, &target, &config)
+            .await
+            .unwrap();
+
+        assert!(target.join("BKSD_OK").is_file());
+        assert!(target.join("DCIM/100CANON/IMG:0001.JPG").is_file());
+    }
+}
@@ -0,0 +1,285 @@
+//! `BKSD_OK` integrity badges.
+//!
+//! With `integrity_badge` set, each verified backup gets a small signed text
+//! file at its root:
+//!
+//! ```text
+//! BKSD_OK 1
+//! job_id: 0192...
+//! completed_at: 2026-03-01T10:15:00.000Z
+//! files: 214
+//! manifest_blake3: 5f1c...
+//! public_key: 9a0e...
+//! signature: 33b7...
+//! ```
+//!
+//! The manifest digest is the BLAKE3 of the backup's file list in `b3sum`
+//! format (`<hash>  <path>` lines sorted by path, the badge and manifest
+//! left out). That listing is delivered beside the badge as
+//! `BKSD_MANIFEST.b3`, so a client can run `b3sum --check` on it and know it
+//! is the one that was signed. The signature is the daemon's ed25519
+//! signature over every line before it. `bksd verify` checks all of it
+//! against the folder as it is now.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+
+use crate::core::restore::walk;
+use crate::core::signing::{hex, parse_hex};
+use crate::core::transfer_engine::FileHash;
+use crate::core::verifier::hash_file_sync;
+
+/// File name of the badge at the root of a backup
+pub const BADGE_NAME: &str = "BKSD_OK";
+
+/// File name of the signed `b3sum` listing delivered beside the badge
+pub const MANIFEST_NAME: &str = "BKSD_MANIFEST.b3";
+
+const HEADER: &str = "BKSD_OK 1";
+
+/// A parsed badge whose signature has been checked.
+#[derive(Debug, Clone)]
+pub struct Badge {
+    pub job_id: String,
+    pub completed_at: String,
+    pub files: usize,
+    pub manifest_blake3: String,
+    pub public_key: VerifyingKey,
+}
+
+/// The `b3sum`-style listing of `files`.
+fn manifest(files: &BTreeMap<PathBuf, [u8; 32]>) -> String {
+    let mut listing = String::new();
+    for (path, hash) in files {
+        let _ = writeln!(listing, "{}  {}", hex(hash), path.to_string_lossy());
+    }
+    listing
+}
+
+fn digest(manifest: &str) -> String {
+    blake3::hash(manifest.as_bytes()).to_hex().to_string()
+}
+
+/// Whether `path` is the badge or manifest rather than a backed up file
+fn is_badge_file(path: &Path) -> bool {
+    path == Path::new(BADGE_NAME) || path == Path::new(MANIFEST_NAME)
+}
+
+/// Hash every file under `root` except the badge and manifest. Blocking.
+fn hash_folder(root: &Path) -> Result<BTreeMap<PathBuf, [u8; 32]>> {
+    walk(root, &[])?
+        .into_iter()
+        .filter(|path| !is_badge_file(path))
+        .map(|path| {
+            let hash = hash_file_sync(&root.join(&path))?;
+            Ok((path, *hash.as_bytes()))
+        })
+        .collect()
+}
+
+/// Sign and write a badge and manifest for a verified backup. `hashes` from the transfer
+/// are reused when they cover exactly the files in the folder; otherwise
+/// the folder is hashed. Blocking.
+pub fn write(
+    root: &Path,
+    job_id: &str,
+    completed_at: &str,
+    hashes: Option<&[FileHash]>,
+    key: &SigningKey,
+) -> Result<PathBuf> {
+    let on_disk: Vec<PathBuf> = walk(root, &[])?
+        .into_iter()
+        .filter(|path| !is_badge_file(path))
+        .collect();
+    let files = match hashes {
+        Some(hashes)
+            if hashes.len() == on_disk.len()
+                && hashes
+                    .iter()
+                    .all(|fh| on_disk.binary_search(&fh.relative_path).is_ok()) =>
+        {
+            hashes
+                .iter()
+                .map(|fh| (fh.relative_path.clone(), fh.hash))
+                .collect()
+        }
+        _ => hash_folder(root)?,
+    };
+    let listing = manifest(&files);
+
+    let mut body = String::new();
+    let _ = writeln!(body, "{}", HEADER);
+    let _ = writeln!(body, "job_id: {}", job_id);
+    let _ = writeln!(body, "completed_at: {}", completed_at);
+    let _ = writeln!(body, "files: {}", files.len());
+    let _ = writeln!(body, "manifest_blake3: {}", digest(&listing));
+    let _ = writeln!(body, "public_key: {}", hex(key.verifying_key().as_bytes()));
+    let signature = key.sign(body.as_bytes());
+    let _ = writeln!(body, "signature: {}", hex(&signature.to_bytes()));
+
+    std::fs::write(root.join(MANIFEST_NAME), listing)?;
+    let path = root.join(BADGE_NAME);
+    std::fs::write(&path, body)?;
+    Ok(path)
+}
+
+/// Read the badge at the root of `root` and check its signature, without
+/// looking at the files.
+pub fn read(root: &Path) -> Result<Badge> {
+    let path = root.join(BADGE_NAME);
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("No {} in {}", BADGE_NAME, root.display()))?;
+
+    let Some(split) = text.find("signature: ") else {
+        bail!("{} is not signed", BADGE_NAME);
+    };
+    let (signed, signature_line) = text.split_at(split);
+    let mut lines = signed.lines();
+    if lines.next() != Some(HEADER) {
+        bail!("{} is not a version 1 badge", BADGE_NAME);
+    }
+    let fields: BTreeMap<&str, &str> = lines.filter_map(|line| line.split_once(": ")).collect();
+    let field = |name: &str| {
+        fields
+            .get(name)
+            .copied()
+            .ok_or_else(|| anyhow!("{} has no {}", BADGE_NAME, name))
+    };
+
+    let public_key = VerifyingKey::from_bytes(&parse_hex::<32>(field("public_key")?)?)
+        .map_err(|e| anyhow!("Invalid public key in {}: {}", BADGE_NAME, e))?;
+    let signature = Signature::from_bytes(&parse_hex::<64>(
+        signature_line.trim_start_matches("signature: ").trim(),
+    )?);
+    public_key
+        .verify(signed.as_bytes(), &signature)
+        .map_err(|_| anyhow!("{} signature does not match its contents", BADGE_NAME))?;
+
+    Ok(Badge {
+        job_id: field("job_id")?.to_string(),
+        completed_at: field("completed_at")?.to_string(),
+        files: field("files")?.parse()?,
+        manifest_blake3: field("manifest_blake3")?.to_string(),
+        public_key,
+    })
+}
+
+/// The files listed in `root`'s `BKSD_MANIFEST.b3`, without checking it
+/// against a badge. Sizes are taken from the files there now (0 if missing).
+pub fn read_manifest(root: &Path) -> Result<Vec<FileHash>> {
+    let path = root.join(MANIFEST_NAME);
+    let listing = std::fs::read_to_string(&path)
+        .with_context(|| format!("No {} in {}", MANIFEST_NAME, root.display()))?;
+    listing
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (hash, relative) = line
+                .split_once("  ")
+                .ok_or_else(|| anyhow!("Malformed {} line: {}", MANIFEST_NAME, line))?;
+            let relative_path = PathBuf::from(relative);
+            Ok(FileHash {
+                hash: parse_hex::<32>(hash)?,
+                size: std::fs::metadata(root.join(&relative_path))
+                    .map(|m| m.len())
+                    .unwrap_or(0),
+                relative_path,
+            })
+        })
+        .collect()
+}
+
+/// Check the badge in `root`: signed by one of the `trusted` keys, and
+/// describing the files that are there now. Blocking: hashes every file.
+pub fn verify(root: &Path, trusted: &[VerifyingKey]) -> Result<Badge> {
+    let badge = read(root)?;
+    if !trusted.contains(&badge.public_key) {
+        bail!(
+            "{} was signed by key {}, not a trusted key",
+            BADGE_NAME,
+            hex(badge.public_key.as_bytes())
+        );
+    }
+    // Badges written before manifests were delivered have none
+    if let Ok(listing) = std::fs::read_to_string(root.join(MANIFEST_NAME))
+        && digest(&listing) != badge.manifest_blake3
+    {
+        bail!("{} does not match the badge", MANIFEST_NAME);
+    }
+    let files = hash_folder(root)?;
+    if files.len() != badge.files {
+        bail!(
+            "Backup holds {} file(s) but was badged with {}",
+            files.len(),
+            badge.files
+        );
+    }
+    if digest(&manifest(&files)) != badge.manifest_blake3 {
+        bail!("Backup contents changed since it was badged");
+    }
+    Ok(badge)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_badge_round_trip() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        std::fs::create_dir_all(root.join("DCIM")).unwrap();
+        std::fs::write(root.join("DCIM/A.JPG"), b"first").unwrap();
+        std::fs::write(root.join("DCIM/B.JPG"), b"second").unwrap();
+
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let other = SigningKey::from_bytes(&[8; 32]);
+        // Transfer hashes that don't cover the folder are ignored
+        let partial = vec![FileHash {
+            relative_path: PathBuf::from("DCIM/A.JPG"),
+            hash: *blake3::hash(b"first").as_bytes(),
+            size: 5,
+        }];
+        write(
+            root,
+            "job-1",
+            "2026-01-01T10:00:00.000Z",
+            Some(&partial),
+            &key,
+        )
+        .unwrap();
+
+        let badge = verify(root, &[key.verifying_key()]).unwrap();
+        assert_eq!(badge.job_id, "job-1");
+        assert_eq!(badge.files, 2);
+        assert!(verify(root, &[other.verifying_key()]).is_err());
+        assert!(verify(root, &[other.verifying_key(), key.verifying_key()]).is_ok());
+
+        // The delivered manifest is the signed listing
+        let listing = std::fs::read_to_string(root.join(MANIFEST_NAME)).unwrap();
+        assert!(listing.ends_with("  DCIM/B.JPG\n"));
+        assert_eq!(digest(&listing), badge.manifest_blake3);
+        let listed = read_manifest(root).unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[1].relative_path, PathBuf::from("DCIM/B.JPG"));
+        assert_eq!(listed[1].hash, *blake3::hash(b"second").as_bytes());
+        assert_eq!(listed[1].size, 6);
+        std::fs::write(root.join(MANIFEST_NAME), listing.replace("B.JPG", "C.JPG")).unwrap();
+        assert!(verify(root, &[key.verifying_key()]).is_err());
+        std::fs::write(root.join(MANIFEST_NAME), listing).unwrap();
+
+        // A changed file no longer matches the digest
+        std::fs::write(root.join("DCIM/B.JPG"), b"edited").unwrap();
+        assert!(read(root).is_ok());
+        assert!(verify(root, &[key.verifying_key()]).is_err());
+
+        // An edited badge no longer matches its signature
+        let text = std::fs::read_to_string(root.join(BADGE_NAME)).unwrap();
+        std::fs::write(root.join(BADGE_NAME), text.replace("job-1", "job-2")).unwrap();
+        assert!(read(root).is_err());
+    }
+}
@@ -0,0 +1,154 @@
+//! Which camera body a card was shot on.
+//!
+//! Crews running several bodies often leave every card with the camera's
+//! default label (`EOS_DIGITAL`, `NO NAME`), so the label alone doesn't keep
+//! their backups apart. The model is read from the EXIF `Make` and `Model`
+//! of the first few photos under `DCIM`; when none of them has any, the
+//! free-text part of the DCF folder names (`100CANON` gives `CANON`) stands
+//! in. It is stored on the job as `camera_model` and can be used in
+//! `destination_template` as `{camera_model}`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::core::dcim::is_dcf_folder;
+use crate::core::media::{self, MediaKind};
+
+/// Photos whose EXIF is read, in folder and name order
+const SAMPLED_FILES: usize = 5;
+
+/// The camera model of the card mounted at `source`, if it can be told.
+pub async fn detect(source: &Path) -> Option<String> {
+    let source = source.to_path_buf();
+    tokio::task::spawn_blocking(move || detect_sync(&source))
+        .await
+        .ok()
+        .flatten()
+}
+
+fn detect_sync(source: &Path) -> Option<String> {
+    let folders = sorted_entries(&source.join("DCIM"), |path| {
+        path.is_dir()
+            && path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(is_dcf_folder)
+    });
+
+    let photos = folders.iter().flat_map(|folder| {
+        sorted_entries(folder, |path| {
+            path.is_file()
+                && matches!(
+                    MediaKind::from_path(path),
+                    Some(MediaKind::Image | MediaKind::Raw)
+                )
+        })
+    });
+    let mut models: Vec<(String, usize)> = Vec::new();
+    for photo in photos.take(SAMPLED_FILES) {
+        if let Ok(Some(model)) = media::camera_model(&photo) {
+            match models.iter_mut().find(|(seen, _)| *seen == model) {
+                Some((_, count)) => *count += 1,
+                None => models.push((model, 1)),
+            }
+        }
+    }
+    // The most common, the first seen on a tie (max_by_key keeps the last)
+    if let Some((model, _)) = models.into_iter().rev().max_by_key(|(_, count)| *count) {
+        return Some(model);
+    }
+
+    folders.iter().find_map(|folder| {
+        let name = folder.file_name()?.to_str()?;
+        let free_text = name.get(3..)?.trim_matches('_');
+        (!free_text.is_empty()).then(|| free_text.to_string())
+    })
+}
+
+/// Entries of `dir` passing `keep`, sorted by name.
+fn sorted_entries(dir: &Path, keep: impl Fn(&Path) -> bool) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| keep(path))
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// A JPEG whose EXIF names the camera
+    fn photo(make: &str, model: &str) -> Vec<u8> {
+        let make = format!("{}\0", make);
+        let model = format!("{}\0", model);
+        let make_at = 38u32;
+        let model_at = make_at + make.len() as u32;
+
+        let mut tiff = b"II\x2a\0\x08\0\0\0\x02\0".to_vec();
+        for (tag, value, at) in [(0x010Fu16, &make, make_at), (0x0110, &model, model_at)] {
+            tiff.extend(tag.to_le_bytes());
+            tiff.extend(2u16.to_le_bytes());
+            tiff.extend((value.len() as u32).to_le_bytes());
+            tiff.extend(at.to_le_bytes());
+        }
+        tiff.extend([0, 0, 0, 0]);
+        tiff.extend(make.as_bytes());
+        tiff.extend(model.as_bytes());
+
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        jpeg.extend(((tiff.len() + 8) as u16).to_be_bytes());
+        jpeg.extend(b"Exif\0\0");
+        jpeg.extend(tiff);
+        jpeg.extend([0xFF, 0xDA, 0, 2]);
+        jpeg
+    }
+
+    #[tokio::test]
+    async fn test_detect_camera_model() {
+        let temp = tempdir().unwrap();
+
+        let canon = temp.path().join("canon");
+        fs::create_dir_all(canon.join("DCIM/100CANON")).unwrap();
+        fs::write(
+            canon.join("DCIM/100CANON/IMG_0001.JPG"),
+            photo("Canon", "Canon EOS R5"),
+        )
+        .unwrap();
+        fs::write(canon.join("DCIM/100CANON/IMG_0001.XMP"), b"sidecar").unwrap();
+        assert_eq!(detect(&canon).await.as_deref(), Some("Canon EOS R5"));
+
+        // The model doesn't repeat the make
+        let sony = temp.path().join("sony");
+        fs::create_dir_all(sony.join("DCIM/100MSDCF")).unwrap();
+        for name in ["DSC00001.JPG", "DSC00002.JPG"] {
+            fs::write(
+                sony.join("DCIM/100MSDCF").join(name),
+                photo("SONY", "ILCE-7M4"),
+            )
+            .unwrap();
+        }
+        assert_eq!(detect(&sony).await.as_deref(), Some("SONY ILCE-7M4"));
+
+        // No EXIF: the DCF folder name has to do
+        let fuji = temp.path().join("fuji");
+        fs::create_dir_all(fuji.join("DCIM/100_FUJI")).unwrap();
+        fs::write(fuji.join("DCIM/100_FUJI/DSCF0001.RAF"), b"raw").unwrap();
+        assert_eq!(detect(&fuji).await.as_deref(), Some("FUJI"));
+
+        let drive = temp.path().join("drive");
+        fs::create_dir_all(drive.join("Documents")).unwrap();
+        assert_eq!(detect(&drive).await, None);
+
+        // Non-ASCII folder names are skipped, not sliced mid-character
+        let odd = temp.path().join("odd");
+        fs::create_dir_all(odd.join("DCIM/1é23456")).unwrap();
+        assert_eq!(detect(&odd).await, None);
+    }
+}
@@ -0,0 +1,296 @@
+//! Capacity planning for upcoming shoots.
+//!
+//! Answers "will N cards of this size fit?" before anyone leaves for a shoot.
+//! The backup directory's free space is reduced by what active jobs still
+//! have to write, and archive tiering is forecast to free the backups that
+//! are already old enough to move to the archive drive, as far as that drive
+//! has room for them.
+
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use nix::sys::statvfs::statvfs;
+use serde::{Deserialize, Serialize};
+
+use crate::config::AppConfig;
+use crate::core::progress::ProgressTracker;
+use crate::core::transfer_engine::TransferStatus;
+use crate::db::Store;
+
+/// Space on the backup directory's filesystem.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupSpace {
+    pub path: String,
+    pub total_bytes: u64,
+    pub free_bytes: u64,
+    /// Bytes active jobs are still expected to write
+    pub reserved_bytes: u64,
+    pub active_jobs: usize,
+    /// Completed backups old enough for archive tiering
+    pub archivable_jobs: usize,
+    pub archivable_bytes: u64,
+}
+
+/// The configured archive drive, as last seen.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveSpace {
+    pub label: String,
+    pub connected: bool,
+    /// Drive size, if known
+    pub capacity: Option<u64>,
+    /// Bytes of backups already moved onto the drive
+    pub stored_bytes: u64,
+}
+
+impl ArchiveSpace {
+    /// Estimated room left, or None when the drive's size is unknown.
+    pub fn free_bytes(&self) -> Option<u64> {
+        self.capacity
+            .map(|capacity| capacity.saturating_sub(self.stored_bytes))
+    }
+}
+
+/// Whether a batch of cards fits.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapacityPlan {
+    pub card_bytes: u64,
+    pub count: u64,
+    pub required_bytes: u64,
+    pub backup: BackupSpace,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive: Option<ArchiveSpace>,
+    /// Free space left for new cards after active jobs finish
+    pub available_bytes: u64,
+    /// Backup directory space archive tiering is expected to free
+    pub reclaimable_bytes: u64,
+    /// Cards that fit as things stand
+    pub cards_fit: u64,
+    /// Cards that fit once archivable backups have moved to the archive drive
+    pub cards_fit_after_tiering: u64,
+}
+
+impl CapacityPlan {
+    pub fn fits(&self) -> bool {
+        self.cards_fit >= self.count
+    }
+
+    pub fn fits_after_tiering(&self) -> bool {
+        self.cards_fit_after_tiering >= self.count
+    }
+}
+
+/// Work out how many cards fit given the current state of the destinations.
+pub fn plan(
+    card_bytes: u64,
+    count: u64,
+    backup: BackupSpace,
+    archive: Option<ArchiveSpace>,
+) -> CapacityPlan {
+    let available_bytes = backup.free_bytes.saturating_sub(backup.reserved_bytes);
+
+    // Tiering only frees what the archive drive can take
+    let reclaimable_bytes = match &archive {
+        None => 0,
+        Some(drive) => match drive.free_bytes() {
+            Some(free) => backup.archivable_bytes.min(free),
+            None => backup.archivable_bytes,
+        },
+    };
+
+    let cards = |bytes: u64| bytes.checked_div(card_bytes).unwrap_or(0);
+
+    CapacityPlan {
+        card_bytes,
+        count,
+        required_bytes: card_bytes.saturating_mul(count),
+        cards_fit: cards(available_bytes),
+        cards_fit_after_tiering: cards(available_bytes + reclaimable_bytes),
+        available_bytes,
+        reclaimable_bytes,
+        backup,
+        archive,
+    }
+}
+
+/// Measure the backup directory, active jobs and archive drive for `plan()`.
+pub async fn gather(
+    store: &dyn Store,
+    config: &AppConfig,
+    progress: &ProgressTracker,
+) -> Result<(BackupSpace, Option<ArchiveSpace>)> {
+    let (total_bytes, free_bytes) = disk_space(&config.backup_directory)?;
+
+    let statuses = progress.get_all().await;
+    let details = progress.get_details().await;
+    let reserved_bytes = statuses
+        .iter()
+        .map(|(job_id, status)| match status {
+            TransferStatus::InProgress {
+                total_bytes,
+                bytes_copied,
+                ..
+            } => total_bytes.saturating_sub(*bytes_copied),
+            // Still scanning: assume the whole card
+            TransferStatus::Ready => details
+                .get(job_id)
+                .and_then(|d| used_space(&d.info.source).ok())
+                .unwrap_or(0),
+            _ => 0,
+        })
+        .sum();
+
+    let (archivable_jobs, archivable_bytes) = if config.archive_label.is_some() {
+        store.archivable_bytes(config.archive_after_days).await?
+    } else {
+        (0, 0)
+    };
+
+    let archive = match &config.archive_label {
+        None => None,
+        Some(label) => {
+            let volume = store
+                .list_volumes()
+                .await?
+                .into_iter()
+                .find(|v| &v.label == label);
+            let stored_bytes = match &volume {
+                Some(v) => store.stored_bytes(v.uuid.clone()).await?,
+                None => 0,
+            };
+            Some(ArchiveSpace {
+                label: label.clone(),
+                connected: volume.as_ref().is_some_and(|v| v.connected),
+                capacity: volume.and_then(|v| v.capacity),
+                stored_bytes,
+            })
+        }
+    };
+
+    Ok((
+        BackupSpace {
+            path: config.backup_directory.to_string_lossy().to_string(),
+            total_bytes,
+            free_bytes,
+            reserved_bytes,
+            active_jobs: statuses.len(),
+            archivable_jobs,
+            archivable_bytes,
+        },
+        archive,
+    ))
+}
+
+/// Total and available bytes on the filesystem holding `path`. A backup
+/// directory that hasn't been created yet is measured at its nearest
+/// existing parent.
+pub fn disk_space(path: &Path) -> Result<(u64, u64)> {
+    let existing = path
+        .ancestors()
+        .find(|p| p.exists())
+        .context("No existing parent directory")?;
+    let stat = statvfs(existing)
+        .with_context(|| format!("Failed to read free space of {}", existing.display()))?;
+    let fragment = stat.fragment_size() as u64;
+    Ok((
+        stat.blocks() as u64 * fragment,
+        stat.blocks_available() as u64 * fragment,
+    ))
+}
+
+/// Bytes in use on the filesystem mounted at `path`.
+fn used_space(path: &Path) -> Result<u64> {
+    let stat = statvfs(path)?;
+    Ok(
+        (stat.blocks() as u64).saturating_sub(stat.blocks_free() as u64)
+            * stat.fragment_size() as u64,
+    )
+}
+
+/// Parse a card size such as `128G`, `64GB`, `1.5T` or `512GiB`.
+///
+/// Plain suffixes are decimal, the way card capacities are sold; `KiB`,
+/// `MiB`, `GiB` and `TiB` are binary. A bare number is bytes.
+pub fn parse_size(input: &str) -> Result<u64> {
+    let trimmed = input.trim();
+    let split = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+
+    let value: f64 = number
+        .parse()
+        .with_context(|| format!("Invalid size \"{}\"", input))?;
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1_000,
+        "M" | "MB" => 1_000_000,
+        "G" | "GB" => 1_000_000_000,
+        "T" | "TB" => 1_000_000_000_000,
+        "KIB" => 1 << 10,
+        "MIB" => 1 << 20,
+        "GIB" => 1 << 30,
+        "TIB" => 1 << 40,
+        other => bail!("Unknown size unit \"{}\" in \"{}\"", other, input),
+    };
+
+    let bytes = (value * multiplier as f64) as u64;
+    if bytes == 0 {
+        bail!("Size must be greater than zero");
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GB: u64 = 1_000_000_000;
+
+    fn backup(free: u64, reserved: u64, archivable: u64) -> BackupSpace {
+        BackupSpace {
+            path: "/mnt/backups".to_string(),
+            total_bytes: 4000 * GB,
+            free_bytes: free,
+            reserved_bytes: reserved,
+            active_jobs: 0,
+            archivable_jobs: 3,
+            archivable_bytes: archivable,
+        }
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("128G").unwrap(), 128 * GB);
+        assert_eq!(parse_size("64gb").unwrap(), 64 * GB);
+        assert_eq!(parse_size("1.5T").unwrap(), 1500 * GB);
+        assert_eq!(parse_size("256GiB").unwrap(), 256 << 30);
+        assert_eq!(parse_size("4096").unwrap(), 4096);
+        assert!(parse_size("128X").is_err());
+        assert!(parse_size("GB").is_err());
+        assert!(parse_size("0").is_err());
+    }
+
+    #[test]
+    fn test_plan_subtracts_reservations() {
+        let plan = plan(128 * GB, 4, backup(600 * GB, 100 * GB, 0), None);
+        assert_eq!(plan.available_bytes, 500 * GB);
+        assert_eq!(plan.cards_fit, 3);
+        assert!(!plan.fits());
+        assert!(!plan.fits_after_tiering());
+    }
+
+    #[test]
+    fn test_plan_tiering_limited_by_archive_drive() {
+        let archive = ArchiveSpace {
+            label: "COLD_01".to_string(),
+            connected: false,
+            capacity: Some(1000 * GB),
+            stored_bytes: 900 * GB,
+        };
+        let plan = plan(128 * GB, 5, backup(500 * GB, 0, 300 * GB), Some(archive));
+        assert_eq!(plan.reclaimable_bytes, 100 * GB);
+        assert_eq!(plan.cards_fit, 3);
+        assert_eq!(plan.cards_fit_after_tiering, 4);
+        assert!(!plan.fits_after_tiering());
+    }
+}
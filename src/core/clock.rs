@@ -0,0 +1,52 @@
+//! Elapsed time that keeps counting through system suspend.
+//!
+//! `std::time::Instant` is backed by CLOCK_MONOTONIC, which stops while the
+//! machine is suspended, so an ingest laptop that sleeps overnight would
+//! under-report its uptime and any transfer spanning the suspend. Linux's
+//! CLOCK_BOOTTIME is equally immune to wall clock changes but includes time
+//! spent suspended. On macOS CLOCK_MONOTONIC itself keeps counting asleep.
+
+use std::time::Duration;
+
+use nix::time::{ClockId, clock_gettime};
+
+/// Measures elapsed time on the boot clock.
+#[derive(Debug, Clone, Copy)]
+pub struct Stopwatch {
+    started: Duration,
+}
+
+impl Stopwatch {
+    pub fn start() -> Self {
+        Self { started: now() }
+    }
+
+    /// Time since `start()`, including any time the system was suspended.
+    pub fn elapsed(&self) -> Duration {
+        now().saturating_sub(self.started)
+    }
+}
+
+#[cfg(target_os = "linux")]
+const BOOT_CLOCK: ClockId = ClockId::CLOCK_BOOTTIME;
+#[cfg(not(target_os = "linux"))]
+const BOOT_CLOCK: ClockId = ClockId::CLOCK_MONOTONIC;
+
+fn now() -> Duration {
+    clock_gettime(BOOT_CLOCK)
+        .or_else(|_| clock_gettime(ClockId::CLOCK_MONOTONIC))
+        .map(Duration::from)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stopwatch_advances() {
+        let watch = Stopwatch::start();
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(watch.elapsed() >= Duration::from_millis(20));
+    }
+}
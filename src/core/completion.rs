@@ -0,0 +1,217 @@
+//! Per-device completion actions.
+//!
+//! A device rule (`[devices.<uuid or label>]`) can list `on_complete`
+//! actions, so a camera card gets wiped and ejected while a client delivery
+//! SSD runs a hand-off hook instead. Actions run in order once the backup
+//! has completed and verified, stopping at the first failure so a failed
+//! hook never leads on to a wipe. `eject` always runs last, after the
+//! device has been unmounted.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow, bail};
+use tokio::process::Command;
+use tracing::info;
+
+use crate::config::{AppConfig, CompletionAction, NotificationChannelType, NotificationConfig};
+use crate::core::notifications::{self, JobEvent};
+
+/// A finished job, as passed to hooks.
+#[derive(Debug, Clone)]
+pub struct CompletedJob {
+    pub job_id: String,
+    pub device_label: String,
+    pub device_uuid: String,
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub total_bytes: u64,
+}
+
+/// Actions configured for a device, matched by filesystem UUID or volume
+/// label (UUID rule first).
+pub fn actions_for(config: &AppConfig, uuid: &str, label: &str) -> Vec<CompletionAction> {
+    [uuid, label]
+        .into_iter()
+        .filter_map(|key| config.devices.get(key))
+        .flat_map(|device| device.on_complete.iter().cloned())
+        .collect()
+}
+
+/// Whether the device should be ejected once everything else is done.
+pub fn wants_eject(actions: &[CompletionAction]) -> bool {
+    actions.contains(&CompletionAction::Eject)
+}
+
+/// Run every action except `eject`, in order. `event` is the Completed
+/// notification, for `notify` actions. `wipe_refusal` says why the card
+/// can't be wiped, and must be None only when every file on it is in the
+/// verified backup.
+pub async fn run(
+    actions: &[CompletionAction],
+    job: &CompletedJob,
+    event: &JobEvent,
+    wipe_refusal: Option<&str>,
+) -> Result<()> {
+    for action in actions {
+        match action {
+            CompletionAction::Eject => continue,
+            CompletionAction::Wipe => {
+                if let Some(reason) = wipe_refusal {
+                    bail!("Refusing to wipe {}: {}", job.device_label, reason);
+                }
+                wipe(&job.source).await?;
+                info!(source = %job.source.display(), "Device wiped");
+            }
+            CompletionAction::Hook { command } => {
+                hook(command, job).await?;
+                info!(command = %command, "Completion hook finished");
+            }
+            CompletionAction::Notify { slack_webhook } => {
+                let config = NotificationConfig {
+                    channel: NotificationChannelType::Slack,
+                    slack_webhook: Some(slack_webhook.clone()),
+                };
+                let notifier = notifications::create_notifier(&config)
+                    .ok_or_else(|| anyhow!("Empty Slack webhook in notify action"))?;
+                notifier.notify(event.clone()).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Delete everything on the mounted device, leaving the mount point itself.
+async fn wipe(source: &Path) -> Result<()> {
+    if source.as_os_str().is_empty() {
+        bail!("Device is not mounted, nothing to wipe");
+    }
+    let source = source.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        for entry in std::fs::read_dir(&source)? {
+            let path = entry?.path();
+            if path.is_dir() && !path.is_symlink() {
+                std::fs::remove_dir_all(&path)?;
+            } else {
+                std::fs::remove_file(&path)?;
+            }
+        }
+        Ok::<_, std::io::Error>(())
+    })
+    .await?
+    .map_err(|e| anyhow!("Failed to wipe device: {}", e))
+}
+
+/// `sh -c command` with the job described in `BKSD_*` variables.
+pub(crate) fn hook_command(command: &str, job: &CompletedJob) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg(command)
+        .env("BKSD_JOB_ID", &job.job_id)
+        .env("BKSD_DEVICE_LABEL", &job.device_label)
+        .env("BKSD_DEVICE_UUID", &job.device_uuid)
+        .env("BKSD_SOURCE", &job.source)
+        .env("BKSD_DESTINATION", &job.destination)
+        .env("BKSD_TOTAL_BYTES", job.total_bytes.to_string());
+    cmd
+}
+
+async fn hook(command: &str, job: &CompletedJob) -> Result<()> {
+    let output = hook_command(command, job)
+        .output()
+        .await
+        .map_err(|e| anyhow!("Failed to run hook `{}`: {}", command, e))?;
+
+    if !output.status.success() {
+        bail!(
+            "Hook `{}` failed ({}): {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::transfer_engine::TransferStats;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn job(source: &Path, destination: &Path) -> CompletedJob {
+        CompletedJob {
+            job_id: "job-1".to_string(),
+            device_label: "EOS_DIGITAL".to_string(),
+            device_uuid: "1234-ABCD".to_string(),
+            source: source.to_path_buf(),
+            destination: destination.to_path_buf(),
+            total_bytes: 42,
+        }
+    }
+
+    fn event() -> JobEvent {
+        JobEvent::Completed {
+            job_id: "job-1".to_string(),
+            device_label: "EOS_DIGITAL".to_string(),
+            label_fields: Default::default(),
+            total_bytes: 42,
+            duration_secs: 1,
+            stats: TransferStats::default(),
+            notices: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_actions_run_in_order_and_stop_on_failure() {
+        let temp = tempdir().unwrap();
+        let card = temp.path().join("card");
+        fs::create_dir_all(card.join("DCIM/100CANON")).unwrap();
+        fs::write(card.join("DCIM/100CANON/IMG_0001.CR3"), b"raw").unwrap();
+        let marker = temp.path().join("hook-ran");
+
+        let actions = vec![
+            CompletionAction::Hook {
+                command: format!("echo \"$BKSD_DEVICE_LABEL\" > {}", marker.display()),
+            },
+            CompletionAction::Wipe,
+        ];
+        run(&actions, &job(&card, temp.path()), &event(), None)
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(&marker).unwrap(), "EOS_DIGITAL\n");
+        assert!(card.exists());
+        assert_eq!(fs::read_dir(&card).unwrap().count(), 0);
+
+        // A failing hook stops the wipe after it
+        fs::write(card.join("keep.txt"), b"keep").unwrap();
+        let actions = vec![
+            CompletionAction::Hook {
+                command: "exit 3".to_string(),
+            },
+            CompletionAction::Wipe,
+        ];
+        assert!(
+            run(&actions, &job(&card, temp.path()), &event(), None)
+                .await
+                .is_err()
+        );
+        assert!(card.join("keep.txt").exists());
+
+        // Unverified or incomplete backups are never wiped
+        let actions = vec![CompletionAction::Wipe];
+        let err = run(
+            &actions,
+            &job(&card, temp.path()),
+            &event(),
+            Some("transfers are not fully verified"),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Refusing to wipe EOS_DIGITAL: transfers are not fully verified"
+        );
+        assert!(card.join("keep.txt").exists());
+    }
+}
@@ -0,0 +1,198 @@
+//! DCIM sequence gap detection.
+//!
+//! Cameras following the DCF layout name files `DCIM/100CANON/IMG_0450.CR3`,
+//! numbering sequentially within each folder. A hole in that sequence usually
+//! means a shot was deleted in camera or a clip was lost, which the operator
+//! should know before wiping the card.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use crate::core::models::JobNotice;
+
+/// Maximum gap notices reported per folder before summarizing
+const MAX_GAPS_PER_FOLDER: usize = 10;
+
+/// Find gaps in DCF file numbering under `<source>/DCIM`.
+///
+/// Files sharing a number (e.g. `IMG_0001.CR3` and `IMG_0001.JPG`) count once.
+/// Returns no notices if the card has no DCIM folder.
+pub fn find_gaps(source: &Path) -> Vec<JobNotice> {
+    let dcim = source.join("DCIM");
+    let Ok(folders) = fs::read_dir(&dcim) else {
+        return Vec::new();
+    };
+
+    let mut folder_names: Vec<String> = folders
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+        .filter_map(|e| e.file_name().to_str().map(str::to_string))
+        .filter(|name| is_dcf_folder(name))
+        .collect();
+    folder_names.sort();
+
+    let mut notices = Vec::new();
+
+    for folder in folder_names {
+        let Ok(entries) = fs::read_dir(dcim.join(&folder)) else {
+            continue;
+        };
+
+        // prefix -> numbers seen
+        let mut sequences: BTreeMap<String, BTreeSet<u32>> = BTreeMap::new();
+        for entry in entries.filter_map(|e| e.ok()) {
+            if let Some(name) = entry.file_name().to_str()
+                && let Some((prefix, number)) = parse_dcf_name(name)
+            {
+                sequences.entry(prefix).or_default().insert(number);
+            }
+        }
+
+        let folder_path = format!("DCIM/{}", folder);
+        for (prefix, numbers) in &sequences {
+            notices.extend(gaps_in_sequence(&folder_path, prefix, numbers));
+        }
+    }
+
+    notices
+}
+
+/// DCF folders are three digits (100-999) followed by five characters
+pub fn is_dcf_folder(name: &str) -> bool {
+    name.len() == 8 && name.as_bytes()[..3].iter().all(u8::is_ascii_digit)
+}
+
+/// Split a DCF filename like `IMG_0451.JPG` into ("IMG_", 451)
+fn parse_dcf_name(name: &str) -> Option<(String, u32)> {
+    let stem = name.split('.').next()?;
+    if stem.len() != 8 || !stem.is_ascii() {
+        return None;
+    }
+
+    let (prefix, digits) = stem.split_at(4);
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    Some((prefix.to_string(), digits.parse().ok()?))
+}
+
+fn gaps_in_sequence(folder: &str, prefix: &str, numbers: &BTreeSet<u32>) -> Vec<JobNotice> {
+    let mut gaps: Vec<(u32, u32)> = Vec::new();
+    let mut previous: Option<u32> = None;
+
+    for &n in numbers {
+        if let Some(prev) = previous
+            && n > prev + 1
+        {
+            gaps.push((prev + 1, n - 1));
+        }
+        previous = Some(n);
+    }
+
+    let mut notices: Vec<JobNotice> = gaps
+        .iter()
+        .take(MAX_GAPS_PER_FOLDER)
+        .map(|&(first, last)| {
+            let message = if first == last {
+                format!("{}: {}{:04} missing from sequence", folder, prefix, first)
+            } else {
+                format!(
+                    "{}: {}{:04}-{}{:04} missing from sequence ({} files)",
+                    folder,
+                    prefix,
+                    first,
+                    prefix,
+                    last,
+                    last - first + 1
+                )
+            };
+            JobNotice {
+                kind: "dcim_gap".to_string(),
+                path: Some(folder.to_string()),
+                message,
+            }
+        })
+        .collect();
+
+    if gaps.len() > MAX_GAPS_PER_FOLDER {
+        notices.push(JobNotice {
+            kind: "dcim_gap".to_string(),
+            path: Some(folder.to_string()),
+            message: format!(
+                "{}: {} more {} gap(s) not listed",
+                folder,
+                gaps.len() - MAX_GAPS_PER_FOLDER,
+                prefix
+            ),
+        });
+    }
+
+    notices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_dcf_name() {
+        assert_eq!(
+            parse_dcf_name("IMG_0451.JPG"),
+            Some(("IMG_".to_string(), 451))
+        );
+        assert_eq!(
+            parse_dcf_name("DSC09999.ARW"),
+            Some(("DSC0".to_string(), 9999))
+        );
+        assert_eq!(parse_dcf_name("README.TXT"), None);
+        assert_eq!(parse_dcf_name("IMG_ABCD.JPG"), None);
+    }
+
+    #[test]
+    fn test_is_dcf_folder() {
+        assert!(is_dcf_folder("100CANON"));
+        assert!(is_dcf_folder("101_PANA"));
+        assert!(is_dcf_folder("100ÉOSX"));
+        assert!(!is_dcf_folder("1é23456"));
+        assert!(!is_dcf_folder("MISC"));
+        assert!(!is_dcf_folder("ABCCANON"));
+    }
+
+    #[test]
+    fn test_find_gaps() {
+        let temp = tempdir().unwrap();
+        let folder = temp.path().join("DCIM/100CANON");
+        fs::create_dir_all(&folder).unwrap();
+
+        for name in [
+            "IMG_0450.CR3",
+            "IMG_0450.JPG",
+            "IMG_0452.CR3",
+            "IMG_0456.CR3",
+            "MVI_0001.MP4",
+            "MVI_0002.MP4",
+        ] {
+            fs::write(folder.join(name), b"x").unwrap();
+        }
+
+        let notices = find_gaps(temp.path());
+        let messages: Vec<&str> = notices.iter().map(|n| n.message.as_str()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "DCIM/100CANON: IMG_0451 missing from sequence",
+                "DCIM/100CANON: IMG_0453-IMG_0455 missing from sequence (3 files)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_gaps_without_dcim() {
+        let temp = tempdir().unwrap();
+        assert!(find_gaps(temp.path()).is_empty());
+    }
+}
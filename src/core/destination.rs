@@ -0,0 +1,510 @@
+//! Destination path templates.
+//!
+//! Each job's backup directory is built from `destination_template` relative
+//! to the backup directory. Besides the card label and timestamp the template
+//! can include the ingest station's hostname and the configured operator, so
+//! several laptops writing into one shared NAS tree don't interleave, and the
+//! reel, day and camera parsed from the label (see `label_fields`) and the
+//! camera model read from the card's photos (see `camera`).
+//!
+//! Where a verified backup ends up is a `Destination` backend, chosen by the
+//! device rule's `destination` from the `[destinations]` table: the backup
+//! directory itself (the default), an SFTP server, an S3 bucket, or any sync
+//! command. Every backend is written through the backup directory, so the
+//! engines, verification and resume work the same for all of them; the
+//! orchestrator only looks at a backend's `Capabilities`.
+
+mod local;
+mod remote;
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow, bail};
+use async_trait::async_trait;
+use tracing::{info, warn};
+
+use crate::config::{AppConfig, DestinationConfig, DestinationKind};
+use crate::core::completion::CompletedJob;
+use crate::core::label_fields::LabelFields;
+use crate::core::models::JobNotice;
+use crate::db::Store;
+
+pub use local::LocalDestination;
+pub use remote::{RemoteDestination, Upload};
+
+/// Value substituted for `{operator}` when none is configured, and for
+/// label fields the card's label didn't provide
+const UNKNOWN: &str = "unknown";
+
+/// Values available to a destination template.
+pub struct TemplateValues<'a> {
+    pub label: &'a str,
+    pub timestamp: &'a str,
+    pub hostname: &'a str,
+    pub operator: Option<&'a str>,
+    pub label_fields: &'a LabelFields,
+}
+
+/// Hostname of this machine, or `localhost` if it can't be read.
+pub fn hostname() -> String {
+    nix::unistd::gethostname()
+        .ok()
+        .and_then(|h| h.into_string().ok())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+/// Expand a template such as `{hostname}/{label}/{timestamp}` under
+/// `backup_dir`.
+///
+/// Placeholder values can't introduce extra directories, and `..` or a
+/// leading `/` in the template can't escape the backup directory.
+pub fn build(backup_dir: &Path, template: &str, values: &TemplateValues) -> PathBuf {
+    let expanded = template
+        .replace("{label}", &path_safe(values.label))
+        .replace("{timestamp}", values.timestamp)
+        .replace("{hostname}", &path_safe(values.hostname))
+        .replace("{operator}", &path_safe(values.operator.unwrap_or(UNKNOWN)))
+        .replace("{reel}", &field(&values.label_fields.reel))
+        .replace("{day}", &field(&values.label_fields.day))
+        .replace("{camera_model}", &field(&values.label_fields.camera_model))
+        .replace("{camera}", &field(&values.label_fields.camera));
+
+    let relative: PathBuf = Path::new(&expanded)
+        .components()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .collect();
+
+    backup_dir.join(relative)
+}
+
+/// Refuse to back a device up into itself: `target` (the backup directory
+/// or a job's destination) resolving to the device's mount point or to
+/// somewhere inside it, as when the backup drive itself is plugged in.
+/// Symlinks are followed on both sides.
+pub fn check_outside_source(source: &Path, target: &Path) -> Result<()> {
+    let source = resolve(source);
+    let resolved = resolve(target);
+    if resolved.starts_with(&source) {
+        bail!(
+            "Refusing to back up {} into itself: {} is on the device",
+            source.display(),
+            target.display()
+        );
+    }
+    Ok(())
+}
+
+/// Canonicalize the longest existing prefix of `path`, keeping the rest, so
+/// destinations that don't exist yet resolve too.
+fn resolve(path: &Path) -> PathBuf {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return rest.iter().rev().fold(canonical, |path, c| path.join(c));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name);
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+/// What a destination backend can do, which decides the steps the
+/// orchestrator runs around it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The delivered copy can be checked after it is written
+    pub supports_verify: bool,
+    /// Unchanged files can be hardlinked from the card's previous backup
+    pub supports_hardlink: bool,
+}
+
+/// Where verified backups are delivered.
+#[async_trait]
+pub trait Destination: Send + Sync {
+    /// `local`, or the backend's name in `[destinations]`
+    fn name(&self) -> &str;
+
+    fn capabilities(&self) -> Capabilities;
+
+    /// Deliver the verified backup at `job.destination`. Returns where it
+    /// went, or None when it is already in place.
+    async fn publish(&self, job: &CompletedJob) -> Result<Option<String>>;
+
+    /// Check the delivered copy. Only called with `supports_verify`.
+    async fn verify(&self, job: &CompletedJob) -> Result<()>;
+
+    /// Whether the backup directory is the destination, so there is
+    /// nothing to deliver
+    fn is_local(&self) -> bool {
+        false
+    }
+
+    /// Whether the backup stays in the backup directory once delivered
+    fn keeps_local(&self) -> bool {
+        true
+    }
+}
+
+/// The destination backend for a device, from the `destination` of a rule
+/// matching its filesystem UUID or volume label (UUID rule first).
+pub fn for_device(config: &AppConfig, uuid: &str, label: &str) -> Result<Arc<dyn Destination>> {
+    let name = [uuid, label]
+        .into_iter()
+        .filter_map(|key| config.devices.get(key))
+        .find_map(|device| device.destination.clone());
+    let Some(name) = name else {
+        return Ok(Arc::new(LocalDestination));
+    };
+    let settings = config
+        .destinations
+        .get(&name)
+        .ok_or_else(|| anyhow!("Unknown destination `{}`", name))?;
+    create(config, &name, settings)
+}
+
+fn create(
+    config: &AppConfig,
+    name: &str,
+    settings: &DestinationConfig,
+) -> Result<Arc<dyn Destination>> {
+    let required = |value: &Option<String>, field: &str| {
+        value
+            .clone()
+            .ok_or_else(|| anyhow!("Destination `{}` needs `{}`", name, field))
+    };
+    let upload = match settings.kind {
+        DestinationKind::Local => return Ok(Arc::new(LocalDestination)),
+        DestinationKind::Sftp => Upload::Sftp {
+            host: required(&settings.host, "host")?,
+            path: required(&settings.path, "path")?,
+        },
+        DestinationKind::S3 => Upload::S3 {
+            url: required(&settings.url, "url")?,
+        },
+        DestinationKind::Sync => Upload::Command(required(&settings.command, "command")?),
+    };
+    Ok(Arc::new(RemoteDestination {
+        name: name.to_string(),
+        backup_directory: config.backup_directory.clone(),
+        upload,
+        verify_command: settings.verify_command.clone(),
+        keep_local: settings.keep_local,
+    }))
+}
+
+/// Deliver a verified backup to its backend, check it there if the backend
+/// can, and drop the local copy if the backend doesn't keep one. Remote
+/// deliveries get `publishing` and `published` entries in the job's status
+/// log; an error fails the job with the backup still in the backup directory.
+pub async fn deliver(backend: &dyn Destination, db: &dyn Store, job: &CompletedJob) -> Result<()> {
+    let record = |status: &str, description: String| {
+        db.update_status(
+            job.job_id.clone(),
+            status.to_string(),
+            Some(description),
+            None,
+            None,
+        )
+    };
+
+    if backend.is_local() {
+        return Ok(());
+    }
+    let _ = record("publishing", backend.name().to_string()).await;
+
+    let delivered = async {
+        let Some(location) = backend.publish(job).await? else {
+            return Ok(None);
+        };
+        if backend.capabilities().supports_verify {
+            backend
+                .verify(job)
+                .await
+                .map_err(|e| anyhow!("Verifying {} failed: {}", location, e))?;
+        }
+        Ok::<_, anyhow::Error>(Some(location))
+    }
+    .await
+    .map_err(|e| {
+        anyhow!(
+            "Delivery to {} failed: {}; backup kept in {}",
+            backend.name(),
+            e,
+            job.destination.display()
+        )
+    })?;
+    let Some(location) = delivered else {
+        return Ok(());
+    };
+    info!(job_id = %job.job_id, destination = %location, "Backup delivered");
+    let _ = record("published", location.clone()).await;
+
+    if !backend.capabilities().supports_verify {
+        let notice = JobNotice {
+            kind: "unverified_destination".to_string(),
+            path: None,
+            message: format!("{} was not verified after upload", location),
+        };
+        if let Err(e) = db.add_notices(job.job_id.clone(), vec![notice]).await {
+            warn!(job_id = %job.job_id, error = %e, "Failed to record notice");
+        }
+    }
+
+    if !backend.keeps_local() {
+        tokio::fs::remove_dir_all(&job.destination)
+            .await
+            .map_err(|e| {
+                anyhow!(
+                    "Delivered to {} but could not remove {}: {}",
+                    location,
+                    job.destination.display(),
+                    e
+                )
+            })?;
+    }
+    Ok(())
+}
+
+fn path_safe(value: &str) -> String {
+    value.replace(['/', '\\'], "_")
+}
+
+fn field(value: &Option<String>) -> String {
+    path_safe(value.as_deref().unwrap_or(UNKNOWN))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NO_FIELDS: LabelFields = LabelFields {
+        reel: None,
+        day: None,
+        camera: None,
+        camera_model: None,
+    };
+
+    fn values<'a>(operator: Option<&'a str>) -> TemplateValues<'a> {
+        TemplateValues {
+            label: "EOS_DIGITAL",
+            timestamp: "2025-01-14_T1032_05",
+            hostname: "ingest-02",
+            operator,
+            label_fields: &NO_FIELDS,
+        }
+    }
+
+    #[test]
+    fn test_default_template() {
+        let path = build(
+            Path::new("/srv/backups"),
+            "{label}/{timestamp}",
+            &values(None),
+        );
+        assert_eq!(
+            path,
+            PathBuf::from("/srv/backups/EOS_DIGITAL/2025-01-14_T1032_05")
+        );
+    }
+
+    #[test]
+    fn test_station_placeholders() {
+        let path = build(
+            Path::new("/nas"),
+            "{hostname}/{operator}/{label}/{timestamp}",
+            &values(Some("a/b")),
+        );
+        assert_eq!(
+            path,
+            PathBuf::from("/nas/ingest-02/a_b/EOS_DIGITAL/2025-01-14_T1032_05")
+        );
+
+        let path = build(
+            Path::new("/nas"),
+            "{operator}/{label}/{timestamp}",
+            &values(None),
+        );
+        assert_eq!(
+            path,
+            PathBuf::from("/nas/unknown/EOS_DIGITAL/2025-01-14_T1032_05")
+        );
+    }
+
+    #[test]
+    fn test_label_field_placeholders() {
+        let fields = LabelFields {
+            reel: Some("A047".to_string()),
+            day: Some("DAY2".to_string()),
+            camera: None,
+            camera_model: Some("Canon EOS R5".to_string()),
+        };
+        let values = TemplateValues {
+            label_fields: &fields,
+            ..values(None)
+        };
+        let path = build(Path::new("/nas"), "{day}/{camera}/{reel}", &values);
+        assert_eq!(path, PathBuf::from("/nas/DAY2/unknown/A047"));
+        let path = build(Path::new("/nas"), "{camera_model}/{label}", &values);
+        assert_eq!(path, PathBuf::from("/nas/Canon EOS R5/EOS_DIGITAL"));
+    }
+
+    #[test]
+    fn test_check_outside_source() {
+        let temp = tempfile::tempdir().unwrap();
+        let card = temp.path().join("card");
+        let backups = temp.path().join("backups");
+        std::fs::create_dir_all(card.join("DCIM")).unwrap();
+        std::fs::create_dir_all(&backups).unwrap();
+
+        assert!(check_outside_source(&card, &backups.join("CARD/2025-01-14")).is_ok());
+        assert!(check_outside_source(&card, &card).is_err());
+        assert!(check_outside_source(&card, &card.join("backups/CARD")).is_err());
+
+        // A backup directory reached through a symlink into the card
+        std::os::unix::fs::symlink(card.join("DCIM"), temp.path().join("link")).unwrap();
+        assert!(check_outside_source(&card, &temp.path().join("link/new")).is_err());
+        // A sibling sharing the card's name as a prefix is fine
+        assert!(check_outside_source(&card, &temp.path().join("card2")).is_ok());
+    }
+
+    #[test]
+    fn test_backend_for_device() {
+        let mut config = AppConfig::default();
+        let rule = |destination: &str| crate::config::DeviceConfig {
+            destination: Some(destination.to_string()),
+            ..Default::default()
+        };
+        config
+            .devices
+            .insert("EOS_DIGITAL".to_string(), rule("nas"));
+        config
+            .devices
+            .insert("MISSING".to_string(), rule("nowhere"));
+        config.devices.insert("BROKEN".to_string(), rule("bucket"));
+        config.destinations.insert(
+            "nas".to_string(),
+            DestinationConfig {
+                kind: DestinationKind::Sftp,
+                host: Some("backup@nas".to_string()),
+                path: Some("/ingest".to_string()),
+                ..Default::default()
+            },
+        );
+        config.destinations.insert(
+            "bucket".to_string(),
+            DestinationConfig {
+                kind: DestinationKind::S3,
+                ..Default::default()
+            },
+        );
+
+        let local = for_device(&config, "1234-ABCD", "OTHER").unwrap();
+        assert!(local.is_local());
+        assert!(local.capabilities().supports_hardlink);
+
+        let nas = for_device(&config, "1234-ABCD", "EOS_DIGITAL").unwrap();
+        assert_eq!(nas.name(), "nas");
+        assert_eq!(
+            nas.capabilities(),
+            Capabilities {
+                supports_verify: false,
+                supports_hardlink: false,
+            }
+        );
+        assert!(for_device(&config, "1234-ABCD", "MISSING").is_err());
+        assert!(for_device(&config, "1234-ABCD", "BROKEN").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sync_delivery() {
+        let temp = tempfile::tempdir().unwrap();
+        let backups = temp.path().join("backups");
+        let backup = backups.join("EOS_DIGITAL/2026-01-01_T1000_00");
+        std::fs::create_dir_all(backup.join("DCIM")).unwrap();
+        std::fs::write(backup.join("DCIM/A.JPG"), b"photo").unwrap();
+        let remote = temp.path().join("remote");
+
+        let config = AppConfig {
+            backup_directory: backups,
+            ..Default::default()
+        };
+        let settings = DestinationConfig {
+            kind: DestinationKind::Sync,
+            command: Some(format!(
+                "mkdir -p \"{0}/$(dirname \"$BKSD_RELATIVE_PATH\")\" && \
+                 cp -r \"$BKSD_DESTINATION\" \"{0}/$BKSD_RELATIVE_PATH\"",
+                remote.display()
+            )),
+            verify_command: Some(format!(
+                "diff -r \"$BKSD_DESTINATION\" \"{}/$BKSD_RELATIVE_PATH\"",
+                remote.display()
+            )),
+            keep_local: false,
+            ..Default::default()
+        };
+        let backend = create(&config, "archive", &settings).unwrap();
+        assert!(backend.capabilities().supports_verify);
+
+        let db = crate::db::memory::MemoryStore::open(None).unwrap();
+        let drive = crate::core::TargetDrive {
+            uuid: "card-1".to_string(),
+            label: "EOS_DIGITAL".to_string(),
+            mount_path: "/run/bksd/card".to_string(),
+            raw_size: 64,
+        };
+        db.create_job(
+            "job-1".to_string(),
+            drive,
+            backup.to_string_lossy().to_string(),
+            "host".to_string(),
+            Default::default(),
+        )
+        .await
+        .unwrap();
+        let job = CompletedJob {
+            job_id: "job-1".to_string(),
+            device_label: "EOS_DIGITAL".to_string(),
+            device_uuid: "card-1".to_string(),
+            source: PathBuf::from("/run/bksd/card"),
+            destination: backup.clone(),
+            total_bytes: 5,
+        };
+
+        deliver(backend.as_ref(), &db, &job).await.unwrap();
+        let delivered = remote.join("EOS_DIGITAL/2026-01-01_T1000_00/DCIM/A.JPG");
+        assert_eq!(std::fs::read(delivered).unwrap(), b"photo");
+        assert!(!backup.exists());
+
+        let history = db.get_with_history("job-1".to_string()).await.unwrap();
+        let statuses: Vec<&str> = history.history.iter().map(|e| e.status.as_str()).collect();
+        assert!(statuses.ends_with(&["publishing", "published"]));
+        assert_eq!(
+            history.history.last().unwrap().description.as_deref(),
+            Some("archive:EOS_DIGITAL/2026-01-01_T1000_00")
+        );
+
+        // Nothing left to upload fails the job
+        assert!(deliver(backend.as_ref(), &db, &job).await.is_err());
+    }
+
+    #[test]
+    fn test_template_cannot_escape() {
+        let path = build(Path::new("/nas"), "/../{label}", &values(None));
+        assert_eq!(path, PathBuf::from("/nas/EOS_DIGITAL"));
+
+        // Nor can a label, as uploads and manual backups choose their own
+        let values = TemplateValues {
+            label: "../../etc",
+            ..values(None)
+        };
+        let path = build(Path::new("/nas"), "{label}/{timestamp}", &values);
+        assert_eq!(path, PathBuf::from("/nas/.._.._etc/2025-01-14_T1032_05"));
+    }
+}
@@ -0,0 +1,35 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use super::{Capabilities, Destination};
+use crate::core::completion::CompletedJob;
+
+/// The backup directory itself: the engine writes the backup in place, so
+/// it is already delivered and verified once the copy is.
+pub struct LocalDestination;
+
+#[async_trait]
+impl Destination for LocalDestination {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            supports_verify: true,
+            supports_hardlink: true,
+        }
+    }
+
+    async fn publish(&self, _job: &CompletedJob) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    async fn verify(&self, _job: &CompletedJob) -> Result<()> {
+        Ok(())
+    }
+
+    fn is_local(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,165 @@
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use anyhow::{Result, anyhow, bail};
+use async_trait::async_trait;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use super::{Capabilities, Destination};
+use crate::core::completion::{self, CompletedJob};
+
+/// How a backup leaves the machine
+pub enum Upload {
+    /// `sftp -b -` to `[user@]host`, under `path`
+    Sftp { host: String, path: String },
+    /// `aws s3 cp --recursive` under an `s3://bucket/prefix` URL
+    S3 { url: String },
+    /// A shell command such as `rclone copy`
+    Command(String),
+}
+
+/// A backend outside the backup directory. The engine writes the backup
+/// into the backup directory as usual, which serves as the staging area;
+/// once verified there it is uploaded, checked with `verify_command` if one
+/// is set, and then removed locally unless `keep_local` is set.
+pub struct RemoteDestination {
+    pub name: String,
+    pub backup_directory: PathBuf,
+    pub upload: Upload,
+    pub verify_command: Option<String>,
+    pub keep_local: bool,
+}
+
+impl RemoteDestination {
+    /// The backup's path under the backup directory, which it keeps remotely.
+    fn relative(&self, job: &CompletedJob) -> PathBuf {
+        match job.destination.strip_prefix(&self.backup_directory) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => PathBuf::from(job.destination.file_name().unwrap_or_default()),
+        }
+    }
+
+    /// Where the backup ends up, as shown to the operator.
+    fn location(&self, job: &CompletedJob) -> String {
+        let relative = self.relative(job);
+        match &self.upload {
+            Upload::Sftp { host, path } => {
+                format!("{}:{}", host, Path::new(path).join(relative).display())
+            }
+            Upload::S3 { url } => format!("{}/{}", url.trim_end_matches('/'), relative.display()),
+            Upload::Command(_) => format!("{}:{}", self.name, relative.display()),
+        }
+    }
+
+    fn hook(&self, command: &str, job: &CompletedJob) -> Command {
+        let mut cmd = completion::hook_command(command, job);
+        cmd.env("BKSD_RELATIVE_PATH", self.relative(job))
+            .env("BKSD_REMOTE_PATH", self.location(job));
+        cmd
+    }
+
+    /// sftp batch creating the remote directories and uploading the backup.
+    fn sftp_batch(&self, job: &CompletedJob, path: &str) -> Result<String> {
+        let quoted = |path: &Path| {
+            let text = path.to_string_lossy();
+            if text.contains('"') {
+                bail!("Can't upload {} over SFTP: it contains a quote", text);
+            }
+            Ok(format!("\"{}\"", text))
+        };
+
+        let mut batch = String::new();
+        let mut directory = PathBuf::from(path);
+        batch.push_str(&format!("-mkdir {}\n", quoted(&directory)?));
+        let relative = self.relative(job);
+        if let Some(parent) = relative.parent() {
+            for component in parent.components() {
+                directory.push(component);
+                batch.push_str(&format!("-mkdir {}\n", quoted(&directory)?));
+            }
+        }
+        batch.push_str(&format!(
+            "put -r {} {}\n",
+            quoted(&job.destination)?,
+            quoted(&Path::new(path).join(relative))?
+        ));
+        Ok(batch)
+    }
+}
+
+#[async_trait]
+impl Destination for RemoteDestination {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            supports_verify: self.verify_command.is_some(),
+            // The previous backup may only exist remotely
+            supports_hardlink: false,
+        }
+    }
+
+    async fn publish(&self, job: &CompletedJob) -> Result<Option<String>> {
+        let location = self.location(job);
+        match &self.upload {
+            Upload::Sftp { host, path } => {
+                let batch = self.sftp_batch(job, path)?;
+                let mut child = Command::new("sftp")
+                    .args(["-b", "-", host])
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .spawn()
+                    .map_err(|e| anyhow!("Failed to run sftp: {}", e))?;
+                if let Some(mut stdin) = child.stdin.take() {
+                    stdin.write_all(batch.as_bytes()).await?;
+                }
+                run("sftp upload", child.wait_with_output().await)?;
+            }
+            Upload::S3 { .. } => {
+                let output = Command::new("aws")
+                    .args(["s3", "cp", "--recursive", "--only-show-errors"])
+                    .arg(&job.destination)
+                    .arg(format!("{}/", location))
+                    .output()
+                    .await;
+                run("aws s3 cp", output)?;
+            }
+            Upload::Command(command) => {
+                run(command, self.hook(command, job).output().await)?;
+            }
+        }
+
+        Ok(Some(location))
+    }
+
+    async fn verify(&self, job: &CompletedJob) -> Result<()> {
+        match &self.verify_command {
+            Some(command) => run(command, self.hook(command, job).output().await),
+            None => Ok(()),
+        }
+    }
+
+    fn keeps_local(&self) -> bool {
+        self.keep_local
+    }
+}
+
+/// Turn a finished command into an error unless it exited zero.
+fn run(what: &str, output: std::io::Result<std::process::Output>) -> Result<()> {
+    let output = output.map_err(|e| anyhow!("Failed to run `{}`: {}", what, e))?;
+    if !output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let detail = [stdout.trim(), stderr.trim()]
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join("; ");
+        bail!("`{}` failed ({}): {}", what, output.status, detail);
+    }
+    Ok(())
+}
@@ -0,0 +1,273 @@
+//! Which connected devices get backed up.
+//!
+//! Without a `[device_filter]` every device that mounts is backed up, so a
+//! multi-terabyte USB disk plugged into the same host would be copied too.
+//! `min_device_size_gb` / `max_device_size_gb` rule out anything too small
+//! or too big to be camera media. `allow` rules narrow it down further to
+//! the devices that should be backed up (when any are set, a device has to
+//! match one) and `deny` rules pick out ones that never should be, winning
+//! over `allow`. A rule matches a device when every field it sets does:
+//! UUID, label glob, filesystem and capacity bounds.
+//!
+//! Uploads and the archive drive aren't cards, so aren't filtered. Devices
+//! that are left connected after being turned away are kept in
+//! [`IgnoredDevices`] for the `devices.ignored` RPC method.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::config::{AppConfig, DeviceMatch};
+use crate::core::BlockDevice;
+use crate::core::timestamp;
+
+const GB: u64 = 1_000_000_000;
+
+/// Compiled `device_filter` rules and device size limits.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceFilter {
+    allow: Vec<Rule>,
+    deny: Vec<Rule>,
+    min_bytes: Option<u64>,
+    max_bytes: Option<u64>,
+    /// Rejects every device, standing in for rules that didn't compile
+    reject_all: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    spec: DeviceMatch,
+    label: Option<GlobMatcher>,
+}
+
+impl DeviceFilter {
+    pub fn new(config: &AppConfig) -> Result<Self> {
+        Ok(Self {
+            allow: compile(&config.device_filter.allow)?,
+            deny: compile(&config.device_filter.deny)?,
+            min_bytes: config.min_device_size_gb.map(|gb| gb.saturating_mul(GB)),
+            max_bytes: config.max_device_size_gb.map(|gb| gb.saturating_mul(GB)),
+            reject_all: false,
+        })
+    }
+
+    /// A filter no device passes. Used when the configured rules are
+    /// invalid, so a typo can't let every disk through.
+    pub fn reject_all() -> Self {
+        Self {
+            reject_all: true,
+            ..Self::default()
+        }
+    }
+
+    /// Why a device isn't to be backed up, or None if it is.
+    pub fn rejects(&self, device: &BlockDevice) -> Option<&'static str> {
+        if self.reject_all {
+            Some("device filter is invalid")
+        } else if self.min_bytes.is_some_and(|min| device.capacity < min) {
+            Some("smaller than min_device_size_gb")
+        } else if self.max_bytes.is_some_and(|max| device.capacity > max) {
+            Some("larger than max_device_size_gb")
+        } else if self.deny.iter().any(|rule| rule.matches(device)) {
+            Some("matches a deny rule")
+        } else if !self.allow.is_empty() && !self.allow.iter().any(|rule| rule.matches(device)) {
+            Some("matches no allow rule")
+        } else {
+            None
+        }
+    }
+}
+
+/// A connected device bksd turned away.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IgnoredDevice {
+    #[serde(flatten)]
+    pub device: BlockDevice,
+    /// Why it isn't backed up
+    pub reason: String,
+    /// When it was connected (UTC RFC3339)
+    pub seen_at: String,
+}
+
+/// Devices turned away by the filter that are still connected, by UUID.
+#[derive(Clone, Default)]
+pub struct IgnoredDevices {
+    inner: Arc<Mutex<HashMap<String, IgnoredDevice>>>,
+}
+
+impl IgnoredDevices {
+    pub async fn add(&self, device: &BlockDevice, reason: &str) {
+        self.inner.lock().await.insert(
+            device.uuid.clone(),
+            IgnoredDevice {
+                device: device.clone(),
+                reason: reason.to_string(),
+                seen_at: timestamp::now_utc(),
+            },
+        );
+    }
+
+    pub async fn remove(&self, uuid: &str) {
+        self.inner.lock().await.remove(uuid);
+    }
+
+    /// Every ignored device, oldest first.
+    pub async fn list(&self) -> Vec<IgnoredDevice> {
+        let mut devices: Vec<_> = self.inner.lock().await.values().cloned().collect();
+        devices.sort_by(|a, b| a.seen_at.cmp(&b.seen_at));
+        devices
+    }
+}
+
+fn compile(rules: &[DeviceMatch]) -> Result<Vec<Rule>> {
+    rules
+        .iter()
+        .map(|spec| {
+            let label = spec
+                .label
+                .as_deref()
+                .map(|pattern| {
+                    GlobBuilder::new(pattern)
+                        .case_insensitive(true)
+                        .build()
+                        .map(|glob| glob.compile_matcher())
+                        .with_context(|| format!("Invalid label pattern \"{}\"", pattern))
+                })
+                .transpose()?;
+            Ok(Rule {
+                spec: spec.clone(),
+                label,
+            })
+        })
+        .collect()
+}
+
+impl Rule {
+    fn matches(&self, device: &BlockDevice) -> bool {
+        let spec = &self.spec;
+        spec.uuid
+            .as_ref()
+            .is_none_or(|uuid| uuid.eq_ignore_ascii_case(&device.uuid))
+            && self
+                .label
+                .as_ref()
+                .is_none_or(|label| label.is_match(&device.label))
+            && spec
+                .filesystem
+                .as_ref()
+                .is_none_or(|filesystem| filesystem.eq_ignore_ascii_case(&device.filesystem))
+            && spec
+                .min_capacity_gb
+                .is_none_or(|gb| device.capacity >= gb.saturating_mul(GB))
+            && spec
+                .max_capacity_gb
+                .is_none_or(|gb| device.capacity <= gb.saturating_mul(GB))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DeviceFilterConfig;
+    use std::path::PathBuf;
+
+    fn rules(device_filter: DeviceFilterConfig) -> Result<DeviceFilter> {
+        DeviceFilter::new(&AppConfig {
+            device_filter,
+            ..Default::default()
+        })
+    }
+
+    fn device(label: &str, filesystem: &str, capacity_gb: u64) -> BlockDevice {
+        BlockDevice {
+            uuid: format!("{}-uuid", label),
+            label: label.to_string(),
+            path: PathBuf::from("/dev/sdz1"),
+            mount_point: PathBuf::from("/run/bksd/card"),
+            capacity: capacity_gb * GB - GB / 2,
+            filesystem: filesystem.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_allow_and_deny_rules() {
+        let card = device("EOS_DIGITAL", "exfat", 64);
+        let drone = device("DJI_0001", "vfat", 32);
+        let usb_disk = device("MEDIA", "ntfs", 4000);
+
+        // No rules: everything is backed up
+        let filter = DeviceFilter::new(&AppConfig::default()).unwrap();
+        assert_eq!(filter.rejects(&usb_disk), None);
+
+        let filter = rules(DeviceFilterConfig {
+            allow: vec![
+                DeviceMatch {
+                    label: Some("eos_*".to_string()),
+                    ..Default::default()
+                },
+                DeviceMatch {
+                    filesystem: Some("vfat".to_string()),
+                    max_capacity_gb: Some(512),
+                    ..Default::default()
+                },
+            ],
+            deny: vec![DeviceMatch {
+                uuid: Some("DJI_0001-UUID".to_string()),
+                ..Default::default()
+            }],
+        })
+        .unwrap();
+        assert_eq!(filter.rejects(&card), None);
+        assert_eq!(filter.rejects(&drone), Some("matches a deny rule"));
+        assert_eq!(filter.rejects(&usb_disk), Some("matches no allow rule"));
+        assert_eq!(
+            filter.rejects(&device("NO NAME", "vfat", 1000)),
+            Some("matches no allow rule")
+        );
+        assert_eq!(filter.rejects(&device("NO NAME", "vfat", 512)), None);
+
+        let filter = rules(DeviceFilterConfig {
+            deny: vec![DeviceMatch {
+                min_capacity_gb: Some(1000),
+                ..Default::default()
+            }],
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(filter.rejects(&card), None);
+        assert!(filter.rejects(&usb_disk).is_some());
+
+        assert!(
+            rules(DeviceFilterConfig {
+                allow: vec![DeviceMatch {
+                    label: Some("EOS_[".to_string()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            })
+            .is_err()
+        );
+        assert!(DeviceFilter::reject_all().rejects(&card).is_some());
+
+        // Sizes no camera card comes in
+        let filter = DeviceFilter::new(&AppConfig {
+            min_device_size_gb: Some(2),
+            max_device_size_gb: Some(1000),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(filter.rejects(&card), None);
+        assert_eq!(
+            filter.rejects(&device("EFI", "vfat", 1)),
+            Some("smaller than min_device_size_gb")
+        );
+        assert_eq!(
+            filter.rejects(&usb_disk),
+            Some("larger than max_device_size_gb")
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Self-contained job documents, for moving history between daemons.
+//!
+//! `jobs.export` bundles a job with its status log, notices and file
+//! manifest into one JSON document, and `jobs.import` loads that document
+//! into another daemon's store with its ids and timestamps unchanged. This
+//! is how a field kit's history reaches the studio: export on the kit,
+//! import at the studio. Imported jobs aren't forwarded to the hub (the
+//! station that ran them reports them itself) and aren't linked to archive
+//! drives. A destination outside this daemon's backup directory is dropped,
+//! so archiving and scrubbing never reach a directory they don't own.
+
+use std::path::{Component, Path};
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::core::transfer_engine::FileHash;
+use crate::core::{JobWithHistory, destination, timestamp};
+use crate::db::Store;
+
+/// Identifies the document format
+pub const FORMAT: &str = "bksd-job/1";
+
+/// One job as exported.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobDocument {
+    /// Always `FORMAT`
+    pub format: String,
+    pub exported_at: String,
+    /// Hostname of the daemon it was exported from
+    pub exported_from: String,
+    #[serde(flatten)]
+    pub job: JobWithHistory,
+    /// Every file in the job's backup, with its BLAKE3 hash
+    pub files: Vec<FileHash>,
+}
+
+/// Export a job.
+pub async fn export(db: &dyn Store, job_id: &str) -> Result<JobDocument> {
+    let job = db.get_with_history(job_id.to_string()).await?;
+    let files = db.list_files(job_id.to_string()).await?;
+    Ok(JobDocument {
+        format: FORMAT.to_string(),
+        exported_at: timestamp::now_utc(),
+        exported_from: destination::hostname(),
+        job,
+        files,
+    })
+}
+
+/// Import a document, returning the job's ID. Only finished jobs can be
+/// imported, and only once.
+pub async fn import(
+    db: &dyn Store,
+    mut document: JobDocument,
+    backup_directory: &Path,
+) -> Result<String> {
+    if document.format != FORMAT {
+        bail!(
+            "Unsupported job document format \"{}\" (expected \"{}\")",
+            document.format,
+            FORMAT
+        );
+    }
+    let job_id = document.job.job.id.clone();
+    if uuid::Uuid::parse_str(&job_id).is_err() {
+        bail!("Invalid job ID \"{}\"", job_id);
+    }
+    if timestamp::parse(&document.job.job.created_at).is_none() {
+        bail!(
+            "Job {} has an invalid created_at \"{}\"",
+            job_id,
+            document.job.job.created_at
+        );
+    }
+    let finished = document.job.history.last().is_some_and(|entry| {
+        matches!(
+            entry.status.as_str(),
+            "complete" | "failed" | "cancelled" | "skipped"
+        )
+    });
+    if !finished {
+        bail!("Job {} hasn't finished; export it once it has", job_id);
+    }
+    let destination = &mut document.job.job.destination_path;
+    if destination
+        .as_deref()
+        .is_some_and(|path| !is_within(Path::new(path), backup_directory))
+    {
+        *destination = None;
+    }
+    db.import_job(document.job, document.files).await?;
+    Ok(job_id)
+}
+
+/// Whether `path` is inside `root`, judged by its components and, where
+/// both exist, by where they really are.
+fn is_within(path: &Path, root: &Path) -> bool {
+    if !path.is_absolute()
+        || path.components().any(|c| c == Component::ParentDir)
+        || !path.starts_with(root)
+    {
+        return false;
+    }
+    match (path.canonicalize(), root.canonicalize()) {
+        (Ok(path), Ok(root)) => path.starts_with(root),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::TargetDrive;
+    use crate::core::transfer_engine::TransferStats;
+    use crate::db::memory::MemoryStore;
+    use std::path::PathBuf;
+
+    const JOB_ID: &str = "01943a1e-7b2c-7d4e-8f00-0123456789ab";
+
+    #[tokio::test]
+    async fn test_export_import_round_trip() {
+        let kit = MemoryStore::open(None).unwrap();
+        let drive = TargetDrive {
+            uuid: "card-1".to_string(),
+            label: "EOS_DIGITAL".to_string(),
+            mount_path: "/run/bksd/card".to_string(),
+            raw_size: 64,
+        };
+        kit.create_job(
+            JOB_ID.to_string(),
+            drive,
+            "/backups/EOS_DIGITAL/2026-01-01".to_string(),
+            "kit-01".to_string(),
+            Default::default(),
+        )
+        .await
+        .unwrap();
+        kit.record_file(
+            JOB_ID.to_string(),
+            FileHash {
+                relative_path: PathBuf::from("DCIM/A.JPG"),
+                hash: [7; 32],
+                size: 5,
+            },
+        )
+        .await
+        .unwrap();
+
+        // A running job can't be imported yet
+        let running = export(&kit, JOB_ID).await.unwrap();
+        let studio = MemoryStore::open(None).unwrap();
+        assert!(
+            import(&studio, running, Path::new("/backups"))
+                .await
+                .is_err()
+        );
+
+        kit.record_completion(
+            JOB_ID.to_string(),
+            "Copied".to_string(),
+            5,
+            1,
+            TransferStats::default(),
+        )
+        .await
+        .unwrap();
+        let document = export(&kit, JOB_ID).await.unwrap();
+        let json = serde_json::to_string(&document).unwrap();
+        let document: JobDocument = serde_json::from_str(&json).unwrap();
+
+        let backups = Path::new("/backups");
+        assert_eq!(
+            import(&studio, document.clone(), backups).await.unwrap(),
+            JOB_ID
+        );
+        let imported = studio.get_with_history(JOB_ID.to_string()).await.unwrap();
+        let original = kit.get_with_history(JOB_ID.to_string()).await.unwrap();
+        assert_eq!(imported.job.status, "complete");
+        assert_eq!(imported.job.origin_host.as_deref(), Some("kit-01"));
+        assert_eq!(imported.job.created_at, original.job.created_at);
+        assert_eq!(
+            imported.job.destination_path.as_deref(),
+            Some("/backups/EOS_DIGITAL/2026-01-01")
+        );
+        assert_eq!(imported.history.len(), original.history.len());
+        assert_eq!(
+            studio.list_files(JOB_ID.to_string()).await.unwrap().len(),
+            1
+        );
+        // Imported history isn't reported to the hub as this station's
+        assert!(studio.outbox_pending(10).await.unwrap().is_empty());
+
+        // Only once
+        assert!(import(&studio, document.clone(), backups).await.is_err());
+        let mut other = document.clone();
+        other.format = "bksd-job/99".to_string();
+        assert!(import(&studio, other, backups).await.is_err());
+
+        // Job IDs and timestamps must be well-formed
+        let elsewhere = MemoryStore::open(None).unwrap();
+        let mut other = document.clone();
+        other.job.job.id = "job".to_string();
+        assert!(import(&elsewhere, other, backups).await.is_err());
+        let mut other = document.clone();
+        other.job.job.created_at = "yesterday".to_string();
+        assert!(import(&elsewhere, other, backups).await.is_err());
+
+        // A destination outside the backup directory isn't kept
+        for path in ["/home/x", "/backups/../home/x", "backups/EOS_DIGITAL"] {
+            let elsewhere = MemoryStore::open(None).unwrap();
+            let mut other = document.clone();
+            other.job.job.destination_path = Some(path.to_string());
+            import(&elsewhere, other, backups).await.unwrap();
+            let imported = elsewhere
+                .get_with_history(JOB_ID.to_string())
+                .await
+                .unwrap();
+            assert_eq!(imported.job.destination_path, None);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Operator-facing hints for failed jobs.
+//!
+//! Job errors are recorded as the message the failing stage produced, which
+//! tends to end in an OS error a non-technical operator can't act on. This
+//! sorts a message into a [`FailureKind`] and gives the kinds bksd knows how
+//! to recover from a one-line hint, shown next to the error in failure
+//! notifications, the TUI's job details, `bksd status` and `bksd jobs <id>`.
+
+/// Error a job fails with when its card is pulled mid-transfer
+pub const DEVICE_REMOVED: &str = "Device removed during the transfer";
+
+/// What went wrong with a job, as far as the operator is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// The backup drive ran out of space (or quota)
+    DestinationFull,
+    /// The card went away mid-copy
+    SourceRemoved,
+    /// A folder with the backup's name was already there
+    DestinationExists,
+    /// The backup drive is gone or mounted read-only
+    DestinationUnavailable,
+    /// bksd isn't allowed to write where it was told to
+    PermissionDenied,
+    /// The copy doesn't match the card
+    VerificationFailed,
+    /// Uploaded files don't match the manifest the sender supplied
+    ManifestMismatch,
+    /// The quarantine scan didn't pass
+    ScanFailed,
+    /// Anything else
+    Other,
+}
+
+impl FailureKind {
+    /// Sort a recorded job error into a kind.
+    pub fn classify(error: &str) -> Self {
+        let error = error.to_lowercase();
+        let has = |needles: &[&str]| needles.iter().any(|needle| error.contains(needle));
+
+        // Checked first: these list file names, which could contain anything
+        if error.starts_with("verification failed") {
+            FailureKind::VerificationFailed
+        } else if error.contains("don't match the sender's manifest") {
+            FailureKind::ManifestMismatch
+        } else if error.starts_with("scan `") || error.starts_with("failed to run scan") {
+            FailureKind::ScanFailed
+        } else if has(&[
+            "no space left",
+            "disk quota exceeded",
+            "os error 28)",
+            "os error 122)",
+        ]) {
+            FailureKind::DestinationFull
+        } else if has(&[
+            "device appears to have been removed",
+            "device removed during",
+            "no such device",
+            "os error 19)",
+            "os error 6)",
+            "failed to read from source",
+            "failed to scan source",
+        ]) {
+            FailureKind::SourceRemoved
+        } else if error.starts_with("destination already exists") {
+            FailureKind::DestinationExists
+        } else if has(&["read-only file system", "os error 30)"])
+            || (error.contains("destination") && has(&["no such file", "os error 2)"]))
+        {
+            FailureKind::DestinationUnavailable
+        } else if has(&[
+            "permission denied",
+            "os error 13)",
+            "operation not permitted",
+        ]) {
+            FailureKind::PermissionDenied
+        } else {
+            FailureKind::Other
+        }
+    }
+
+    /// Whether running the job again as it is can't help: the operator has
+    /// to free space, fix the drive or reinsert the card first. Transient
+    /// read/write errors and failed verifications are worth retrying
+    pub fn is_permanent(self) -> bool {
+        !matches!(self, FailureKind::VerificationFailed | FailureKind::Other)
+    }
+
+    /// What the operator can do about it, if there's anything specific
+    pub fn hint(self) -> Option<&'static str> {
+        match self {
+            FailureKind::DestinationFull => Some(
+                "Destination full - free up space or shorten retention, then reinsert the card",
+            ),
+            FailureKind::SourceRemoved => {
+                Some("Card removed early - reinsert it to resume the backup")
+            }
+            FailureKind::DestinationExists => Some(
+                "A backup folder with this name is already there - move or rename it, then reinsert the card",
+            ),
+            FailureKind::DestinationUnavailable => Some(
+                "Backup drive missing or read-only - check it is connected and mounted, then reinsert the card",
+            ),
+            FailureKind::PermissionDenied => {
+                Some("bksd can't write to the backup directory - check its owner and permissions")
+            }
+            FailureKind::VerificationFailed => Some(
+                "Copy doesn't match the card - reinsert it to back up again; if this repeats, try another reader or cable",
+            ),
+            FailureKind::ManifestMismatch => {
+                Some("Uploaded files differ from the sender's - ask them to upload again")
+            }
+            FailureKind::ScanFailed => Some(
+                "Quarantine scan didn't pass - the files are held for review, don't use them yet",
+            ),
+            FailureKind::Other => None,
+        }
+    }
+}
+
+/// Hint for a recorded job error, if it is one bksd recognises.
+pub fn hint(error: &str) -> Option<&'static str> {
+    FailureKind::classify(error).hint()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_job_errors() {
+        let cases = [
+            (
+                "Transfer failed: Failed to write to destination: No space left on device (os error 28)",
+                FailureKind::DestinationFull,
+            ),
+            (
+                "Rsync failed with status: exit status: 11; Disk quota exceeded",
+                FailureKind::DestinationFull,
+            ),
+            (
+                "Device appears to have been removed: No such file or directory (os error 2)",
+                FailureKind::SourceRemoved,
+            ),
+            (
+                "Transfer failed: Failed to read from source: Input/output error (os error 5)",
+                FailureKind::SourceRemoved,
+            ),
+            (DEVICE_REMOVED, FailureKind::SourceRemoved),
+            (
+                "Destination already exists: /backups/A001. Refusing to overwrite.",
+                FailureKind::DestinationExists,
+            ),
+            (
+                "Failed to create destination directory: No such file or directory (os error 2)",
+                FailureKind::DestinationUnavailable,
+            ),
+            (
+                "Failed to create destination directory: Read-only file system (os error 30)",
+                FailureKind::DestinationUnavailable,
+            ),
+            (
+                "Failed to create destination directory: Permission denied (os error 13)",
+                FailureKind::PermissionDenied,
+            ),
+            (
+                "Verification failed: 2 file(s) did not match",
+                FailureKind::VerificationFailed,
+            ),
+            (
+                "1 uploaded file(s) don't match the sender's manifest: A.MOV",
+                FailureKind::ManifestMismatch,
+            ),
+            (
+                "Scan `clamscan` failed (exit status: 1): infected; files kept in /q/A001",
+                FailureKind::ScanFailed,
+            ),
+            (
+                "Rsync failed with status: exit status: 23",
+                FailureKind::Other,
+            ),
+        ];
+        for (error, kind) in cases {
+            assert_eq!(FailureKind::classify(error), kind, "{}", error);
+        }
+
+        // A file name mentioning full disks doesn't make a mismatch one
+        assert_eq!(
+            FailureKind::classify("Verification failed: no space left.txt does not match"),
+            FailureKind::VerificationFailed
+        );
+        assert!(hint("Transfer failed: No space left on device (os error 28)").is_some());
+        assert!(FailureKind::DestinationExists.is_permanent());
+        assert!(!FailureKind::classify("Rsync failed with status: exit status: 23").is_permanent());
+        assert_eq!(hint("Rsync failed with status: exit status: 23"), None);
+    }
+}
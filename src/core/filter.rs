@@ -0,0 +1,333 @@
+//! Include/exclude glob filters for backups.
+//!
+//! Patterns follow rsync's conventions so both engines agree: a pattern
+//! without a `/` (`*.tmp`, `.DS_Store`) matches a file or directory name at
+//! any depth, while one containing a `/` (`.Trashes/**`, `DCIM/*.JPG`) is
+//! matched against the whole path relative to the card root. `*` stops at
+//! `/`, `**` does not.
+//!
+//! Excludes win over includes. When any include pattern is set, only files
+//! matching one are copied; directories are still descended into.
+//!
+//! `source_paths` narrows a backup down to some directories of the card,
+//! such as `DCIM` and `PRIVATE`, leaving out whatever else the camera or a
+//! computer put on it. Files outside them are skipped before any pattern is
+//! looked at. Uploads and manual backups aren't laid out like a card, so
+//! are always copied whole.
+//!
+//! Patterns and paths are compared in NFC, so a pattern typed on Linux
+//! matches the decomposed names a Mac writes to a card. rsync compares
+//! bytes, so it doesn't get this.
+
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+
+use crate::config::AppConfig;
+use crate::core::BlockDevice;
+use crate::core::unicode::{nfc, nfc_path};
+
+/// Compiled include/exclude patterns for one job.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+    include_set: GlobSet,
+    exclude_set: GlobSet,
+    /// Card directories to back up, none nested in another (empty = all)
+    roots: Vec<PathBuf>,
+}
+
+impl PathFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Self {
+            include: include.to_vec(),
+            exclude: exclude.to_vec(),
+            include_set: compile(include)?,
+            exclude_set: compile(exclude)?,
+            roots: Vec::new(),
+        })
+    }
+
+    /// Only back up these directories of the card (relative to its root).
+    pub fn with_source_paths(mut self, paths: &[String]) -> Result<Self> {
+        let mut roots = Vec::new();
+        for path in paths {
+            let root = nfc_path(Path::new(path.trim_start_matches('/')));
+            if root.as_os_str().is_empty()
+                || !root.components().all(|c| matches!(c, Component::Normal(_)))
+            {
+                bail!("Invalid source path \"{}\"", path);
+            }
+            // Without any trailing `/`
+            roots.push(root.components().collect::<PathBuf>());
+        }
+        // A directory inside another one listed adds nothing
+        roots.sort();
+        roots.dedup_by(|inner, outer| inner.starts_with(outer));
+        self.roots = roots;
+        Ok(self)
+    }
+
+    /// Global patterns plus those configured for a device, matched by
+    /// filesystem UUID or volume label, and the device's `source_paths`
+    /// (else the global ones) if it is a card.
+    pub fn for_device(config: &AppConfig, device: &BlockDevice) -> Result<Self> {
+        let mut include = config.include.clone();
+        let mut exclude = config.exclude.clone();
+        let mut source_paths = None;
+        for key in [&device.uuid, &device.label] {
+            if let Some(settings) = config.devices.get(key) {
+                include.extend(settings.include.iter().cloned());
+                exclude.extend(settings.exclude.iter().cloned());
+                source_paths = source_paths.or(settings.source_paths.as_ref());
+            }
+        }
+        let filter = Self::new(&include, &exclude)?;
+        if device.is_upload() || device.is_manual() {
+            return Ok(filter);
+        }
+        filter.with_source_paths(source_paths.unwrap_or(&config.source_paths))
+    }
+
+    /// Whether any pattern or source path can leave files of the card out.
+    pub fn narrows(&self) -> bool {
+        !self.include.is_empty() || !self.exclude.is_empty() || !self.roots.is_empty()
+    }
+
+    /// Whether includes are restricting which files are copied.
+    pub fn has_includes(&self) -> bool {
+        !self.include.is_empty()
+    }
+
+    /// Whether a directory (relative to the source root) should be skipped
+    /// entirely.
+    pub fn excludes_dir(&self, relative: &Path) -> bool {
+        // A pattern like `.Trashes/**` leaves nothing inside to copy, so the
+        // directory itself isn't created either
+        let relative = nfc_path(relative);
+        self.exclude_set.is_match(&relative)
+            || self.exclude_set.is_match(relative.join("*"))
+            || (!self.roots.is_empty()
+                && !self
+                    .roots
+                    .iter()
+                    .any(|root| relative.starts_with(root) || root.starts_with(&relative)))
+    }
+
+    /// Whether a file (relative to the source root) should be copied.
+    pub fn includes_file(&self, relative: &Path) -> bool {
+        let relative = nfc_path(relative);
+        if self.exclude_set.is_match(&relative)
+            || (!self.roots.is_empty() && !self.roots.iter().any(|root| relative.starts_with(root)))
+        {
+            return false;
+        }
+        self.include.is_empty() || self.include_set.is_match(&relative)
+    }
+
+    /// The same rules as rsync filter arguments.
+    pub fn rsync_args(&self) -> Vec<String> {
+        let mut args: Vec<String> = self
+            .exclude
+            .iter()
+            .map(|p| format!("--exclude={}", rsync_pattern(p)))
+            .collect();
+
+        if !self.roots.is_empty() {
+            // Enter each source path and the directories leading to it,
+            // leaving out everything else beside them. Single-level
+            // excludes don't reach below, so include patterns still apply
+            let mut entered: Vec<PathBuf> = Vec::new();
+            let mut above: Vec<PathBuf> = Vec::new();
+            for root in &self.roots {
+                for dir in root
+                    .ancestors()
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .rev()
+                    .skip(1)
+                {
+                    if !entered.iter().any(|seen| seen == dir) {
+                        entered.push(dir.to_path_buf());
+                    }
+                    if dir != root && !above.iter().any(|seen| seen == dir) {
+                        above.push(dir.to_path_buf());
+                    }
+                }
+            }
+            args.extend(
+                entered
+                    .iter()
+                    .map(|dir| format!("--include=/{}/", dir.to_string_lossy())),
+            );
+            args.extend(
+                above
+                    .iter()
+                    .map(|dir| format!("--exclude=/{}/*", dir.to_string_lossy())),
+            );
+            args.push("--exclude=/*".to_string());
+        }
+
+        if !self.include.is_empty() {
+            // Descend into every directory, then drop anything not included
+            args.push("--include=*/".to_string());
+            args.extend(
+                self.include
+                    .iter()
+                    .map(|p| format!("--include={}", rsync_pattern(p))),
+            );
+            args.push("--exclude=*".to_string());
+            args.push("--prune-empty-dirs".to_string());
+        }
+        args
+    }
+}
+
+fn compile(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(glob(pattern).with_context(|| format!("Invalid pattern \"{}\"", pattern))?);
+    }
+    Ok(builder.build()?)
+}
+
+fn glob(pattern: &str) -> Result<Glob, globset::Error> {
+    let pattern = nfc(pattern);
+    let anchored = if pattern.contains('/') {
+        pattern.trim_start_matches('/').to_string()
+    } else {
+        format!("**/{}", pattern)
+    };
+    GlobBuilder::new(&anchored).literal_separator(true).build()
+}
+
+/// rsync anchors patterns to the transfer root only with a leading `/`.
+fn rsync_pattern(pattern: &str) -> String {
+    if pattern.contains('/') && !pattern.starts_with('/') {
+        format!("/{}", pattern)
+    } else {
+        pattern.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(list: &[&str]) -> Vec<String> {
+        list.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_exclude_patterns() {
+        let filter = PathFilter::new(
+            &[],
+            &patterns(&["*.tmp", ".Trashes/**", "System Volume Information"]),
+        )
+        .unwrap();
+
+        assert!(!filter.includes_file(Path::new("DCIM/100CANON/IMG_0001.tmp")));
+        assert!(filter.includes_file(Path::new("DCIM/100CANON/IMG_0001.JPG")));
+        assert!(filter.excludes_dir(Path::new(".Trashes")));
+        assert!(filter.excludes_dir(Path::new("System Volume Information")));
+        assert!(!filter.excludes_dir(Path::new("DCIM")));
+        assert!(filter.narrows());
+        assert!(!PathFilter::default().narrows());
+        assert!(
+            PathFilter::default()
+                .with_source_paths(&patterns(&["DCIM"]))
+                .unwrap()
+                .narrows()
+        );
+    }
+
+    #[test]
+    fn test_include_patterns() {
+        let filter = PathFilter::new(
+            &patterns(&["*.CR3", "PRIVATE/M4ROOT/**"]),
+            &patterns(&["._*"]),
+        )
+        .unwrap();
+
+        assert!(filter.includes_file(Path::new("DCIM/100CANON/IMG_0001.CR3")));
+        assert!(filter.includes_file(Path::new("PRIVATE/M4ROOT/CLIP/C0001.MP4")));
+        assert!(!filter.includes_file(Path::new("DCIM/100CANON/IMG_0001.JPG")));
+        // Excludes win
+        assert!(!filter.includes_file(Path::new("DCIM/100CANON/._IMG_0001.CR3")));
+        // Includes never prune directories
+        assert!(!filter.excludes_dir(Path::new("MISC")));
+    }
+
+    #[test]
+    fn test_rsync_args() {
+        let filter =
+            PathFilter::new(&patterns(&["*.CR3"]), &patterns(&["*.tmp", ".Trashes/**"])).unwrap();
+        assert_eq!(
+            filter.rsync_args(),
+            vec![
+                "--exclude=*.tmp",
+                "--exclude=/.Trashes/**",
+                "--include=*/",
+                "--include=*.CR3",
+                "--exclude=*",
+                "--prune-empty-dirs",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_source_paths() {
+        let filter = PathFilter::new(&patterns(&["*.MP4"]), &[])
+            .unwrap()
+            .with_source_paths(&patterns(&["PRIVATE/M4ROOT", "DCIM/", "DCIM/100CANON"]))
+            .unwrap();
+
+        assert!(filter.includes_file(Path::new("DCIM/100CANON/MVI_0001.MP4")));
+        assert!(filter.includes_file(Path::new("PRIVATE/M4ROOT/CLIP/C0001.MP4")));
+        assert!(!filter.includes_file(Path::new("PRIVATE/AVCHD/00000.MP4")));
+        assert!(!filter.includes_file(Path::new("Trailer.MP4")));
+        assert!(!filter.excludes_dir(Path::new("PRIVATE")));
+        assert!(!filter.excludes_dir(Path::new("DCIM/100CANON")));
+        assert!(filter.excludes_dir(Path::new("PRIVATE/AVCHD")));
+        assert!(filter.excludes_dir(Path::new(".Spotlight-V100")));
+        assert_eq!(
+            filter.rsync_args(),
+            vec![
+                "--include=/DCIM/",
+                "--include=/PRIVATE/",
+                "--include=/PRIVATE/M4ROOT/",
+                "--exclude=/PRIVATE/*",
+                "--exclude=/*",
+                "--include=*/",
+                "--include=*.MP4",
+                "--exclude=*",
+                "--prune-empty-dirs",
+            ]
+        );
+
+        for invalid in ["", "/", "../DCIM", "DCIM/../.."] {
+            assert!(
+                PathFilter::default()
+                    .with_source_paths(&patterns(&[invalid]))
+                    .is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_patterns_match_either_unicode_form() {
+        // Pattern typed precomposed, card written decomposed by macOS
+        let filter = PathFilter::new(&[], &patterns(&["Caf\u{e9}/**"])).unwrap();
+        assert!(filter.excludes_dir(Path::new("Cafe\u{301}")));
+        assert!(!filter.includes_file(Path::new("Cafe\u{301}/A.JPG")));
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        assert!(PathFilter::new(&[], &patterns(&["[unclosed"])).is_err());
+    }
+}
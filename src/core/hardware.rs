@@ -1,5 +1,8 @@
-use std::path::PathBuf;
+#[cfg(not(target_os = "macos"))]
+use std::fs;
+use std::path::{Path, PathBuf};
 
+use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 
 #[derive(Debug, Clone)]
@@ -8,7 +11,7 @@ pub enum HardwareEvent {
     DeviceRemoved(String),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlockDevice {
     pub uuid: String,
     pub label: String,
@@ -18,6 +21,26 @@ pub struct BlockDevice {
     pub filesystem: String,
 }
 
+/// `filesystem` of the synthetic devices completed uploads arrive as
+pub const UPLOAD_FILESYSTEM: &str = "upload";
+
+/// `filesystem` of the synthetic devices manual backups arrive as
+pub const MANUAL_FILESYSTEM: &str = "manual";
+
+impl BlockDevice {
+    /// Whether this is a completed upload's staging directory rather than
+    /// real media, so has nothing to unmount or eject.
+    pub fn is_upload(&self) -> bool {
+        self.filesystem == UPLOAD_FILESYSTEM
+    }
+
+    /// Whether this is a directory backed up on request (`backup.trigger`),
+    /// which bksd didn't mount so mustn't unmount, eject or wipe.
+    pub fn is_manual(&self) -> bool {
+        self.filesystem == MANUAL_FILESYSTEM
+    }
+}
+
 /// Supported filesystems for backup operations
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SupportedFilesystem {
@@ -29,6 +52,7 @@ pub enum SupportedFilesystem {
 }
 
 impl SupportedFilesystem {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
             "ext4" => Some(Self::Ext4),
@@ -59,11 +83,43 @@ pub trait HardwareAdapter: Send + Sync {
     /// Stop the hardware monitor gracefully.
     fn stop(&self);
 
-    /// List all currently connected and valid devices.
+    /// List all currently connected and valid devices, mounting any that
+    /// aren't (when the adapter auto-mounts), as an add event would.
+    /// NOTE: Blocking, like `cleanup_device`.
     fn list_devices(&self) -> anyhow::Result<Vec<BlockDevice>>;
 
     /// Cleanup a device: sync filesystem and unmount.
     /// NOTE: This method performs blocking I/O (syncfs, umount) and should be
     /// called from a blocking context (e.g., via spawn_blocking).
     fn cleanup_device(&self, device: &BlockDevice) -> anyhow::Result<()>;
+
+    /// Power a cleaned-up device down so it can be pulled safely.
+    /// NOTE: Blocking, like `cleanup_device`.
+    fn eject_device(&self, device: &BlockDevice) -> anyhow::Result<()>;
+}
+
+/// The whole disk a partition belongs to, e.g. `/dev/sdb` for `/dev/sdb1`.
+/// None if `device` isn't a partition.
+#[cfg(not(target_os = "macos"))]
+pub fn parent_disk(device: &Path) -> Option<PathBuf> {
+    let name = device.file_name()?;
+    let sys = Path::new("/sys/class/block").join(name);
+    if !sys.join("partition").exists() {
+        return None;
+    }
+    let parent = fs::canonicalize(&sys)
+        .ok()?
+        .parent()?
+        .file_name()?
+        .to_owned();
+    Some(Path::new("/dev").join(parent))
+}
+
+/// The whole disk a slice belongs to, e.g. `/dev/disk4` for `/dev/disk4s1`.
+/// None if `device` isn't a slice.
+#[cfg(target_os = "macos")]
+pub fn parent_disk(device: &Path) -> Option<PathBuf> {
+    let name = device.file_name()?.to_str()?;
+    let (disk, _slice) = name.strip_prefix("disk")?.split_once('s')?;
+    Some(Path::new("/dev").join(format!("disk{}", disk)))
 }
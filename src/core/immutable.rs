@@ -0,0 +1,46 @@
+//! Immutable attribute handling for finalized backups.
+//!
+//! Once a backup is verified nothing should change it. Setting the
+//! filesystem immutable flag (`chattr +i`) stops other users of a shared
+//! destination - or root by accident - from modifying or deleting the copy.
+//! Anything that removes backups (archive tiering, pruning) must clear the
+//! flag first.
+
+use std::path::Path;
+
+use anyhow::{Result, anyhow, bail};
+use tokio::process::Command;
+use tracing::info;
+
+/// Recursively set the immutable flag on a backup directory.
+pub async fn protect(path: &Path) -> Result<()> {
+    chattr("+i", path).await?;
+    info!(path = %path.display(), "Backup marked immutable");
+    Ok(())
+}
+
+/// Recursively clear the immutable flag so the backup can be moved or removed.
+pub async fn release(path: &Path) -> Result<()> {
+    chattr("-i", path).await
+}
+
+async fn chattr(flag: &str, path: &Path) -> Result<()> {
+    let output = Command::new("chattr")
+        .arg("-R")
+        .arg(flag)
+        .arg(path)
+        .output()
+        .await
+        .map_err(|e| anyhow!("Failed to run chattr: {}", e))?;
+
+    if !output.status.success() {
+        bail!(
+            "chattr {} {} failed: {}",
+            flag,
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+//! Slate fields read from structured card volume labels.
+//!
+//! Crews often name cards by convention, e.g. `A047_DAY2_CAMB` for reel
+//! A047 shot on day 2 by camera B. `label_pattern` is a regex with named
+//! groups `reel`, `day` and `camera`; whichever groups match are stored on
+//! the job and can be used in the destination template. The camera model
+//! detected from the card's photos (see `camera`) travels with them.
+
+use anyhow::{Result, bail};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Named groups a label pattern may capture
+const GROUPS: [&str; 3] = ["reel", "day", "camera"];
+
+/// Fields parsed from a card's volume label. All None when there is no
+/// pattern or the label doesn't match it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LabelFields {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reel: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub day: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub camera: Option<String>,
+    /// Camera body read from the card's photos rather than its label
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera_model: Option<String>,
+}
+
+impl LabelFields {
+    pub fn is_empty(&self) -> bool {
+        self.reel.is_none()
+            && self.day.is_none()
+            && self.camera.is_none()
+            && self.camera_model.is_none()
+    }
+
+    /// e.g. `Reel A047 · Day DAY2 · Camera CAMB · Canon EOS R5`, or None if
+    /// no field is set.
+    pub fn summary(&self) -> Option<String> {
+        let parts: Vec<String> = [
+            ("Reel", &self.reel),
+            ("Day", &self.day),
+            ("Camera", &self.camera),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.as_ref().map(|v| format!("{} {}", name, v)))
+        .chain(self.camera_model.clone())
+        .collect();
+        (!parts.is_empty()).then(|| parts.join(" · "))
+    }
+}
+
+/// A compiled `label_pattern`.
+#[derive(Debug, Clone)]
+pub struct LabelPattern(Regex);
+
+impl LabelPattern {
+    /// Compile `pattern`, which must capture at least one of the named
+    /// groups `reel`, `day` or `camera`.
+    pub fn new(pattern: &str) -> Result<Self> {
+        let regex = Regex::new(pattern)?;
+        if !regex
+            .capture_names()
+            .flatten()
+            .any(|name| GROUPS.contains(&name))
+        {
+            bail!("Label pattern has no (?P<reel>), (?P<day>) or (?P<camera>) group");
+        }
+        Ok(Self(regex))
+    }
+
+    pub fn parse(&self, label: &str) -> LabelFields {
+        let Some(captures) = self.0.captures(label) else {
+            return LabelFields::default();
+        };
+        let field = |name| {
+            captures
+                .name(name)
+                .map(|m| m.as_str().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        LabelFields {
+            reel: field("reel"),
+            day: field("day"),
+            camera: field("camera"),
+            camera_model: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_label() {
+        let pattern =
+            LabelPattern::new(r"^(?P<reel>[A-Z]\d{3})_DAY(?P<day>\d+)_CAM(?P<camera>[A-Z])$")
+                .unwrap();
+
+        let fields = pattern.parse("A047_DAY2_CAMB");
+        assert_eq!(fields.reel.as_deref(), Some("A047"));
+        assert_eq!(fields.day.as_deref(), Some("2"));
+        assert_eq!(fields.camera.as_deref(), Some("B"));
+        assert_eq!(fields.summary().unwrap(), "Reel A047 · Day 2 · Camera B");
+        let detected = LabelFields {
+            camera_model: Some("Canon EOS R5".to_string()),
+            ..fields
+        };
+        assert_eq!(
+            detected.summary().unwrap(),
+            "Reel A047 · Day 2 · Camera B · Canon EOS R5"
+        );
+
+        let fields = pattern.parse("EOS_DIGITAL");
+        assert!(fields.is_empty());
+        assert_eq!(fields.summary(), None);
+
+        assert!(LabelPattern::new(r"^(?P<roll>\w+)$").is_err());
+        assert!(LabelPattern::new(r"^(?P<reel>[").is_err());
+    }
+}
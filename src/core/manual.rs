@@ -0,0 +1,159 @@
+//! Backups started by hand for a directory that is already mounted.
+//!
+//! `backup.trigger` (and `bksd backup <path>`) is for cards someone mounted
+//! themselves and for network folders, neither of which the hardware
+//! adapter sees. The directory becomes a synthetic device (filesystem
+//! `manual`, UUID `manual-<path>`) sent to the orchestrator like a card
+//! insertion, so it goes through the normal copy, verify, notify and history
+//! pipeline. The UUID stays the same for the same path, so an interrupted
+//! job resumes and an unchanged directory is skipped as a card would be.
+//! Nothing is unmounted, ejected or wiped afterwards.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result, anyhow, bail};
+use tokio::sync::{Mutex, mpsc};
+use tracing::info;
+
+use crate::config::AppConfig;
+use crate::core::capacity;
+use crate::core::destination;
+use crate::core::hardware::{BlockDevice, HardwareEvent, MANUAL_FILESYSTEM};
+use crate::core::transfer_engine::TransferEngineType;
+use crate::core::upload::sanitize_label;
+
+/// Hands manually requested backups to the orchestrator, shared through
+/// `AppContext`.
+#[derive(Clone, Default)]
+pub struct ManualBackups {
+    /// The orchestrator's device event channel, once it is running
+    events: Arc<Mutex<Option<mpsc::Sender<HardwareEvent>>>>,
+}
+
+impl ManualBackups {
+    /// Send requested backups to the orchestrator on `events`.
+    pub async fn attach(&self, events: mpsc::Sender<HardwareEvent>) {
+        *self.events.lock().await = Some(events);
+    }
+
+    /// Start backing up `device` (from [`device`]).
+    pub async fn trigger(&self, device: BlockDevice) -> Result<()> {
+        let events = self
+            .events
+            .lock()
+            .await
+            .clone()
+            .ok_or_else(|| anyhow!("The daemon is not accepting devices yet"))?;
+        info!(source = %device.mount_point.display(), label = %device.label, "Manual backup requested");
+        events
+            .send(HardwareEvent::DeviceAdded(device))
+            .await
+            .map_err(|_| anyhow!("The daemon is shutting down"))
+    }
+}
+
+/// The synthetic device a manual backup of `path` is made from, filed under
+/// `label` or else the directory's name.
+pub fn device(config: &AppConfig, path: &Path, label: Option<&str>) -> Result<BlockDevice> {
+    if config.transfer_engine == TransferEngineType::RawImage {
+        bail!("The raw image engine needs a block device, not a directory");
+    }
+
+    if config.manual_backup_roots.is_empty() {
+        bail!("Manual backups are disabled (set manual_backup_roots)");
+    }
+
+    let source = path
+        .canonicalize()
+        .with_context(|| format!("Cannot back up {}", path.display()))?;
+    if !config
+        .manual_backup_roots
+        .iter()
+        .filter_map(|root| root.canonicalize().ok())
+        .any(|root| source.starts_with(root))
+    {
+        bail!("{} is not under manual_backup_roots", source.display());
+    }
+    if !source.is_dir() {
+        bail!("{} is not a directory", source.display());
+    }
+    destination::check_outside_source(&source, &config.backup_directory)?;
+    if let Ok(backup_directory) = config.backup_directory.canonicalize()
+        && source.starts_with(&backup_directory)
+    {
+        bail!(
+            "{} is inside the backup directory, so is already backed up",
+            source.display()
+        );
+    }
+
+    let name = source.file_name().map(|name| name.to_string_lossy());
+    let label = label
+        .or(name.as_deref())
+        .and_then(sanitize_label)
+        .unwrap_or_else(|| "manual".to_string());
+    let capacity = capacity::disk_space(&source).map_or(0, |(total, _)| total);
+
+    Ok(BlockDevice {
+        uuid: format!("manual-{}", source.display()),
+        label,
+        path: source.clone(),
+        mount_point: source,
+        capacity,
+        filesystem: MANUAL_FILESYSTEM.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_directory_becomes_device() {
+        let temp = tempdir().unwrap();
+        let card = temp.path().join("EOS DIGITAL");
+        std::fs::create_dir_all(card.join("DCIM")).unwrap();
+        let mut config = AppConfig {
+            backup_directory: temp.path().join("backups"),
+            ..Default::default()
+        };
+        assert!(super::device(&config, &card, None).is_err());
+        config.manual_backup_roots = vec![temp.path().to_path_buf()];
+
+        let device = device(&config, &card, None).unwrap();
+        assert_eq!(device.label, "EOS_DIGITAL");
+        assert_eq!(device.mount_point, card.canonicalize().unwrap());
+        assert_eq!(
+            device.uuid,
+            format!("manual-{}", card.canonicalize().unwrap().display())
+        );
+        assert!(device.is_manual());
+        assert_eq!(
+            super::device(&config, &card, Some("A001")).unwrap().label,
+            "A001"
+        );
+
+        // Not a directory, the backup directory's parent or inside it
+        assert!(super::device(&config, &card.join("DCIM/missing"), None).is_err());
+        assert!(super::device(&config, temp.path(), None).is_err());
+        std::fs::create_dir_all(config.backup_directory.join("old")).unwrap();
+        assert!(super::device(&config, &config.backup_directory.join("old"), None).is_err());
+
+        // Outside every root
+        config.manual_backup_roots = vec![card.join("DCIM")];
+        assert!(super::device(&config, &card, None).is_err());
+        assert!(super::device(&config, &card.join("DCIM"), None).is_ok());
+
+        let backups = ManualBackups::default();
+        assert!(backups.trigger(device.clone()).await.is_err());
+        let (tx, mut rx) = mpsc::channel(1);
+        backups.attach(tx).await;
+        backups.trigger(device).await.unwrap();
+        let Some(HardwareEvent::DeviceAdded(sent)) = rx.recv().await else {
+            panic!("expected a device");
+        };
+        assert_eq!(sent.label, "EOS_DIGITAL");
+    }
+}
@@ -0,0 +1,534 @@
+//! Media file classification and lightweight container parsing.
+//!
+//! Used by post-scan checks to reason about camera files without pulling in
+//! a full media toolkit. Only the structure needed for sanity checks is parsed,
+//! plus the capture time EXIF and `mvhd` headers record.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+/// Bytes read from the start of a file looking for EXIF
+const EXIF_SEARCH_BYTES: u64 = 256 * 1024;
+
+/// Seconds from the BMFF epoch (1904-01-01) to the Unix epoch
+const BMFF_EPOCH_OFFSET: u64 = 2_082_844_800;
+
+/// Broad category of a camera file, derived from its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    /// Processed still image (JPEG, HEIC, ...)
+    Image,
+    /// Camera RAW still image (CR3, NEF, ARW, ...)
+    Raw,
+    /// Video in an ISO base media (MP4/MOV) container
+    Video,
+    /// Video in a container we don't parse (MTS, AVI, ...)
+    OtherVideo,
+}
+
+impl MediaKind {
+    /// Classify a path by extension. Returns None for non-media files.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "jpg" | "jpeg" | "heic" | "heif" | "hif" | "png" | "tif" | "tiff" => Some(Self::Image),
+            "cr2" | "cr3" | "crw" | "nef" | "nrw" | "arw" | "srf" | "sr2" | "dng" | "raf"
+            | "orf" | "rw2" | "pef" | "srw" | "x3f" | "3fr" | "iiq" => Some(Self::Raw),
+            "mp4" | "mov" | "m4v" | "3gp" | "lrv" => Some(Self::Video),
+            "mts" | "m2ts" | "avi" | "mxf" | "braw" | "r3d" => Some(Self::OtherVideo),
+            _ => None,
+        }
+    }
+}
+
+/// Top-level structure of an ISO base media file (MP4/MOV).
+#[derive(Debug, Default)]
+pub struct BmffLayout {
+    /// Four-character codes of the top-level boxes, in file order
+    pub boxes: Vec<[u8; 4]>,
+    /// True if a box header claims more bytes than the file contains
+    pub truncated: bool,
+}
+
+impl BmffLayout {
+    /// Whether a top-level box with the given type was found.
+    pub fn has_box(&self, kind: &[u8; 4]) -> bool {
+        self.boxes.iter().any(|b| b == kind)
+    }
+}
+
+/// Outcome of a structural probe of a video container.
+#[derive(Debug)]
+pub enum VideoProbe {
+    /// Container has a sample index (moov with tracks) and media data
+    Ok,
+    /// Container is structurally unusable; reason is human-readable
+    Broken(String),
+}
+
+/// Location of a single box within a file
+struct BoxHeader {
+    kind: [u8; 4],
+    /// Offset of the box payload (after the header)
+    payload_offset: u64,
+    /// End offset of the box (exclusive)
+    end: u64,
+}
+
+/// Walk the top-level boxes of an ISO base media file.
+///
+/// Only box headers are read, so this is cheap even for multi-gigabyte clips.
+/// Stops at the first header that runs past the end of the file and marks the
+/// layout as truncated.
+pub fn read_bmff_layout(path: &Path) -> io::Result<BmffLayout> {
+    let mut file = File::open(path)?;
+    let file_len = file.metadata()?.len();
+    let (boxes, truncated) = walk_boxes(&mut file, 0, file_len)?;
+
+    Ok(BmffLayout {
+        boxes: boxes.iter().map(|b| b.kind).collect(),
+        truncated,
+    })
+}
+
+/// Check that a video container can actually be played back.
+///
+/// A bit-perfect copy of a recording that was cut off (battery pulled, card
+/// full) still lacks its `moov` index. This confirms the index exists, holds
+/// at least one track, and that the file has media data.
+pub fn probe_video(path: &Path) -> io::Result<VideoProbe> {
+    let mut file = File::open(path)?;
+    let file_len = file.metadata()?.len();
+    let (boxes, truncated) = walk_boxes(&mut file, 0, file_len)?;
+
+    if truncated {
+        return Ok(VideoProbe::Broken(
+            "container ends mid-box (truncated)".to_string(),
+        ));
+    }
+
+    let Some(moov) = boxes.iter().find(|b| &b.kind == b"moov") else {
+        return Ok(VideoProbe::Broken(
+            "missing moov index (recording not finalized)".to_string(),
+        ));
+    };
+
+    if !boxes.iter().any(|b| &b.kind == b"mdat") {
+        return Ok(VideoProbe::Broken("missing mdat media data".to_string()));
+    }
+
+    let (children, truncated) = walk_boxes(&mut file, moov.payload_offset, moov.end)?;
+    if truncated {
+        return Ok(VideoProbe::Broken("moov index is corrupt".to_string()));
+    }
+    if !children.iter().any(|b| &b.kind == b"trak") {
+        return Ok(VideoProbe::Broken("moov index has no tracks".to_string()));
+    }
+
+    Ok(VideoProbe::Ok)
+}
+
+/// When a photo or clip was taken, as its metadata records it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureTime {
+    /// EXIF wall-clock time, in the camera's own timezone
+    Local(NaiveDateTime),
+    /// Container creation time (MP4/MOV `mvhd`), in UTC
+    Utc(DateTime<Utc>),
+}
+
+/// Read the capture time of a JPEG, TIFF-based RAW or MP4/MOV file: EXIF
+/// `DateTimeOriginal` (else `DateTime`), or the movie header's creation
+/// time. None if the file has neither, or they are unset.
+pub fn capture_time(path: &Path) -> io::Result<Option<CaptureTime>> {
+    let mut file = File::open(path)?;
+    let head = read_head(&mut file)?;
+
+    if let Some(tiff) = Tiff::find(&head) {
+        return Ok(tiff.datetime().map(CaptureTime::Local));
+    }
+    if head.get(4..8) == Some(b"ftyp") {
+        return movie_created(&mut file).map(|created| created.map(CaptureTime::Utc));
+    }
+    Ok(None)
+}
+
+/// The camera a JPEG or TIFF-based RAW file was taken with, from its EXIF
+/// `Make` and `Model`, e.g. `Canon EOS R5` or `SONY ILCE-7M4`.
+pub fn camera_model(path: &Path) -> io::Result<Option<String>> {
+    let head = read_head(&mut File::open(path)?)?;
+    let Some(tiff) = Tiff::find(&head) else {
+        return Ok(None);
+    };
+    let Some(ifd0) = tiff.ifd0() else {
+        return Ok(None);
+    };
+    let make = tiff.text(ifd0, MAKE);
+    let model = tiff.text(ifd0, MODEL);
+    Ok(match (make, model) {
+        // Most models already start with the make
+        (Some(make), Some(model)) if !starts_with_ignore_case(model, make) => {
+            Some(format!("{} {}", make, model))
+        }
+        (_, Some(model)) => Some(model.to_string()),
+        (make, None) => make.map(str::to_string),
+    })
+}
+
+fn starts_with_ignore_case(text: &str, prefix: &str) -> bool {
+    text.get(..prefix.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+}
+
+/// The start of a file, where any EXIF is.
+fn read_head(file: &mut File) -> io::Result<Vec<u8>> {
+    let mut head = Vec::new();
+    file.take(EXIF_SEARCH_BYTES).read_to_end(&mut head)?;
+    Ok(head)
+}
+
+/// EXIF IFD0 tags
+const MAKE: u16 = 0x010F;
+const MODEL: u16 = 0x0110;
+const DATE_TIME: u16 = 0x0132;
+const EXIF_IFD: u16 = 0x8769;
+/// EXIF IFD tag
+const DATE_TIME_ORIGINAL: u16 = 0x9003;
+
+/// A TIFF structure holding EXIF: the whole of a TIFF-based RAW file, or a
+/// JPEG's `Exif` APP1 segment.
+struct Tiff<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    /// The TIFF structure at the start of a file, if it has one.
+    fn find(head: &'a [u8]) -> Option<Self> {
+        let data = if head.starts_with(&[0xFF, 0xD8]) {
+            jpeg_exif(head)?
+        } else {
+            head
+        };
+        let little_endian = match data.get(0..2)? {
+            b"II" => true,
+            b"MM" => false,
+            _ => return None,
+        };
+        Some(Self {
+            data,
+            little_endian,
+        })
+    }
+
+    fn u16_at(&self, offset: usize) -> Option<u16> {
+        let bytes = [*self.data.get(offset)?, *self.data.get(offset + 1)?];
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    fn ifd0(&self) -> Option<usize> {
+        self.u32_at(4).map(|offset| offset as usize)
+    }
+
+    /// The entry for `tag` in the IFD at `ifd`: (type, count, offset of its
+    /// value or of the offset to it).
+    fn entry(&self, ifd: usize, tag: u16) -> Option<(u16, u32, usize)> {
+        (0..self.u16_at(ifd)? as usize)
+            .map(|index| ifd + 2 + index * 12)
+            .find(|&entry| self.u16_at(entry) == Some(tag))
+            .and_then(|entry| Some((self.u16_at(entry + 2)?, self.u32_at(entry + 4)?, entry + 8)))
+    }
+
+    /// An ASCII value, trimmed of padding. None if unset or not ASCII.
+    fn text(&self, ifd: usize, tag: u16) -> Option<&'a str> {
+        const ASCII: u16 = 2;
+        let (kind, count, value) = self.entry(ifd, tag)?;
+        if kind != ASCII || count == 0 {
+            return None;
+        }
+        // Up to four bytes are held in the entry itself
+        let start = if count <= 4 {
+            value
+        } else {
+            self.u32_at(value)? as usize
+        };
+        let bytes = self.data.get(start..start + count as usize)?;
+        let text = std::str::from_utf8(bytes).ok()?;
+        let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+        (!text.is_empty()).then_some(text)
+    }
+
+    /// `DateTimeOriginal` from the EXIF IFD, else IFD0's `DateTime`.
+    fn datetime(&self) -> Option<NaiveDateTime> {
+        let ifd0 = self.ifd0()?;
+        let parse = |text: &str| NaiveDateTime::parse_from_str(text, "%Y:%m:%d %H:%M:%S").ok();
+        self.entry(ifd0, EXIF_IFD)
+            .and_then(|(_, _, value)| self.text(self.u32_at(value)? as usize, DATE_TIME_ORIGINAL))
+            .and_then(parse)
+            .or_else(|| self.text(ifd0, DATE_TIME).and_then(parse))
+    }
+}
+
+/// The TIFF structure inside a JPEG's `Exif` APP1 segment.
+fn jpeg_exif(jpeg: &[u8]) -> Option<&[u8]> {
+    let mut offset = 2;
+    while jpeg.get(offset) == Some(&0xFF) {
+        let marker = *jpeg.get(offset + 1)?;
+        // Markers without a length
+        if marker == 0x01 || (0xD0..=0xD8).contains(&marker) || marker == 0xFF {
+            offset += if marker == 0xFF { 1 } else { 2 };
+            continue;
+        }
+        // Start of scan: image data follows, EXIF comes before it
+        if marker == 0xDA {
+            return None;
+        }
+        let length = u16::from_be_bytes([*jpeg.get(offset + 2)?, *jpeg.get(offset + 3)?]) as usize;
+        let segment = jpeg.get(offset + 4..offset + 2 + length)?;
+        if marker == 0xE1 && segment.starts_with(b"Exif\0\0") {
+            return Some(&segment[6..]);
+        }
+        offset += 2 + length;
+    }
+    None
+}
+
+/// Creation time from the `mvhd` box of an ISO base media file, if set.
+fn movie_created(file: &mut File) -> io::Result<Option<DateTime<Utc>>> {
+    let file_len = file.metadata()?.len();
+    let (boxes, _) = walk_boxes(file, 0, file_len)?;
+    let Some(moov) = boxes.iter().find(|b| &b.kind == b"moov") else {
+        return Ok(None);
+    };
+    let (children, _) = walk_boxes(file, moov.payload_offset, moov.end)?;
+    let Some(mvhd) = children.iter().find(|b| &b.kind == b"mvhd") else {
+        return Ok(None);
+    };
+
+    // Version and flags, then the creation time: 64-bit in version 1
+    file.seek(SeekFrom::Start(mvhd.payload_offset))?;
+    let mut header = [0u8; 12];
+    if mvhd.end - mvhd.payload_offset < 12 || file.read_exact(&mut header).is_err() {
+        return Ok(None);
+    }
+    let created = if header[0] == 1 {
+        u64::from_be_bytes(header[4..12].try_into().expect("8 bytes"))
+    } else {
+        u32::from_be_bytes(header[4..8].try_into().expect("4 bytes")) as u64
+    };
+    // Cameras without a clock leave it 0
+    Ok(created
+        .checked_sub(BMFF_EPOCH_OFFSET)
+        .filter(|&secs| secs > 0)
+        .and_then(|secs| DateTime::from_timestamp(secs as i64, 0)))
+}
+
+/// Read consecutive box headers between `start` and `end`.
+/// Returns the boxes found and whether the walk hit a truncated header.
+fn walk_boxes(file: &mut File, start: u64, end: u64) -> io::Result<(Vec<BoxHeader>, bool)> {
+    let mut boxes = Vec::new();
+    let mut offset = start;
+
+    while offset < end {
+        if end - offset < 8 {
+            return Ok((boxes, true));
+        }
+
+        file.seek(SeekFrom::Start(offset))?;
+        let mut header = [0u8; 8];
+        file.read_exact(&mut header)?;
+
+        let size32 = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64;
+        let kind = [header[4], header[5], header[6], header[7]];
+
+        let (box_size, header_len) = match size32 {
+            // Box extends to end of the enclosing range
+            0 => (end - offset, 8),
+            // 64-bit extended size follows the type
+            1 => {
+                let mut ext = [0u8; 8];
+                if end - offset < 16 || file.read_exact(&mut ext).is_err() {
+                    return Ok((boxes, true));
+                }
+                (u64::from_be_bytes(ext), 16)
+            }
+            n => (n, 8),
+        };
+
+        if box_size < header_len || offset.saturating_add(box_size) > end {
+            boxes.push(BoxHeader {
+                kind,
+                payload_offset: offset + header_len,
+                end,
+            });
+            return Ok((boxes, true));
+        }
+
+        boxes.push(BoxHeader {
+            kind,
+            payload_offset: offset + header_len,
+            end: offset + box_size,
+        });
+        offset += box_size;
+    }
+
+    Ok((boxes, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// Build a box with the given type and payload length
+    fn make_box(kind: &[u8; 4], payload_len: usize) -> Vec<u8> {
+        let mut data = ((payload_len + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(kind);
+        data.extend(std::iter::repeat_n(0u8, payload_len));
+        data
+    }
+
+    #[test]
+    fn test_media_kind_from_path() {
+        assert_eq!(
+            MediaKind::from_path(Path::new("DCIM/IMG_0001.CR3")),
+            Some(MediaKind::Raw)
+        );
+        assert_eq!(
+            MediaKind::from_path(Path::new("clip.MP4")),
+            Some(MediaKind::Video)
+        );
+        assert_eq!(MediaKind::from_path(Path::new("notes.txt")), None);
+        assert_eq!(MediaKind::from_path(Path::new("README")), None);
+    }
+
+    #[test]
+    fn test_read_bmff_layout_complete() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("ok.mp4");
+
+        let mut data = make_box(b"ftyp", 16);
+        data.extend(make_box(b"mdat", 1024));
+        data.extend(make_box(b"moov", 64));
+        std::fs::write(&path, data).unwrap();
+
+        let layout = read_bmff_layout(&path).unwrap();
+        assert!(!layout.truncated);
+        assert!(layout.has_box(b"ftyp"));
+        assert!(layout.has_box(b"moov"));
+        assert_eq!(layout.boxes.len(), 3);
+    }
+
+    #[test]
+    fn test_read_bmff_layout_truncated() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("cut.mp4");
+
+        let mut data = make_box(b"ftyp", 16);
+        let mut mdat = make_box(b"mdat", 4096);
+        mdat.truncate(512); // recording stopped mid-write
+        data.extend(mdat);
+        std::fs::write(&path, data).unwrap();
+
+        let layout = read_bmff_layout(&path).unwrap();
+        assert!(layout.truncated);
+        assert!(!layout.has_box(b"moov"));
+    }
+
+    #[test]
+    fn test_probe_video() {
+        let temp = tempdir().unwrap();
+
+        // Playable: moov with a track
+        let good = temp.path().join("good.mov");
+        let mut data = make_box(b"ftyp", 16);
+        data.extend(make_box(b"mdat", 256));
+        let mut moov = make_box(b"mvhd", 100);
+        moov.extend(make_box(b"trak", 40));
+        let mut moov_box = ((moov.len() + 8) as u32).to_be_bytes().to_vec();
+        moov_box.extend_from_slice(b"moov");
+        moov_box.extend(moov);
+        data.extend(moov_box);
+        std::fs::write(&good, data).unwrap();
+        assert!(matches!(probe_video(&good).unwrap(), VideoProbe::Ok));
+
+        // Unfinalized: media data but no index
+        let bad = temp.path().join("bad.mov");
+        let mut data = make_box(b"ftyp", 16);
+        data.extend(make_box(b"mdat", 256));
+        std::fs::write(&bad, data).unwrap();
+        match probe_video(&bad).unwrap() {
+            VideoProbe::Broken(reason) => assert!(reason.contains("moov")),
+            VideoProbe::Ok => panic!("expected broken video"),
+        }
+    }
+
+    #[test]
+    fn test_capture_time() {
+        let temp = tempdir().unwrap();
+
+        // JPEG whose EXIF IFD holds DateTimeOriginal
+        let mut tiff = b"II\x2a\0\x08\0\0\0".to_vec();
+        tiff.extend([1, 0, 0x69, 0x87, 4, 0, 1, 0, 0, 0, 26, 0, 0, 0, 0, 0, 0, 0]);
+        tiff.extend([1, 0, 0x03, 0x90, 2, 0, 20, 0, 0, 0, 44, 0, 0, 0, 0, 0, 0, 0]);
+        tiff.extend(b"2025:01:14 23:30:00\0");
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0, 4, 0, 0, 0xFF, 0xE1];
+        jpeg.extend(((tiff.len() + 8) as u16).to_be_bytes());
+        jpeg.extend(b"Exif\0\0");
+        jpeg.extend(tiff);
+        jpeg.extend([0xFF, 0xDA, 0, 2]);
+        let photo = temp.path().join("IMG_0001.JPG");
+        std::fs::write(&photo, jpeg).unwrap();
+        assert_eq!(
+            capture_time(&photo).unwrap(),
+            Some(CaptureTime::Local(
+                NaiveDateTime::parse_from_str("2025-01-14 23:30:00", "%Y-%m-%d %H:%M:%S").unwrap()
+            ))
+        );
+
+        // Clip whose movie header was created 2025-01-14 12:00 UTC
+        let mut mvhd = vec![0u8; 100];
+        mvhd[4..8].copy_from_slice(&((1_736_856_000 + BMFF_EPOCH_OFFSET) as u32).to_be_bytes());
+        let mut moov = (108u32 + 8).to_be_bytes().to_vec();
+        moov.extend_from_slice(b"moov");
+        moov.extend(108u32.to_be_bytes());
+        moov.extend_from_slice(b"mvhd");
+        moov.extend(mvhd);
+        let mut data = make_box(b"ftyp", 16);
+        data.extend(moov);
+        data.extend(make_box(b"mdat", 64));
+        let clip = temp.path().join("C0001.MP4");
+        std::fs::write(&clip, data).unwrap();
+        assert_eq!(
+            capture_time(&clip).unwrap(),
+            Some(CaptureTime::Utc(
+                DateTime::from_timestamp(1_736_856_000, 0).unwrap()
+            ))
+        );
+
+        // No metadata, or an unset clock
+        let notes = temp.path().join("notes.txt");
+        std::fs::write(&notes, b"card 3").unwrap();
+        assert_eq!(capture_time(&notes).unwrap(), None);
+        let mut unset = make_box(b"ftyp", 16);
+        unset.extend(make_box(b"moov", 0));
+        std::fs::write(&clip, unset).unwrap();
+        assert_eq!(capture_time(&clip).unwrap(), None);
+    }
+}
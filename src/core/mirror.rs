@@ -0,0 +1,234 @@
+//! Mirror copies of each verified backup.
+//!
+//! With `mirror_directories` set, a backup that verified is copied on to
+//! each mirror in turn (e.g. a secondary USB disk next to the RAID), at the
+//! same path under the mirror as it has under `backup_directory`. The copy
+//! is made by the native copy engine from the verified backup, so it works
+//! whichever engine wrote the backup, and is verified on its own. Every
+//! mirror gets `mirror_copying` and `mirror_complete` or `mirror_failed`
+//! entries in the job's status log; a failed mirror is also a job notice but
+//! doesn't fail the job, whose primary copy is already safe.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, bail};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::config::AppConfig;
+use crate::core::JobNotice;
+use crate::core::filter::PathFilter;
+use crate::core::ownership::get_backup_owner;
+use crate::core::transfer_engine::{self, PauseToken, TransferRequest, TransferResult};
+use crate::core::verifier::verify_from_hashes;
+use crate::db::Store;
+
+/// Where a backup at `destination` goes on `mirror`.
+pub fn destination_for(mirror: &Path, backup_directory: &Path, destination: &Path) -> PathBuf {
+    match destination.strip_prefix(backup_directory) {
+        Ok(relative) => mirror.join(relative),
+        Err(_) => mirror.join(destination.file_name().unwrap_or_default()),
+    }
+}
+
+/// Copy the verified backup at `destination` to every configured mirror,
+/// one after another, recording each outcome on the job.
+pub async fn copy_all(
+    config: &AppConfig,
+    db: &dyn Store,
+    job_id: &str,
+    destination: &Path,
+    cancel: &CancellationToken,
+    pause: &PauseToken,
+) {
+    for mirror in &config.mirror_directories {
+        if cancel.is_cancelled() {
+            return;
+        }
+        let target = destination_for(mirror, &config.backup_directory, destination);
+        let _ = db
+            .update_status(
+                job_id.to_string(),
+                "mirror_copying".to_string(),
+                Some(target.display().to_string()),
+                None,
+                None,
+            )
+            .await;
+
+        match copy(config, job_id, destination, &target, cancel, pause).await {
+            Ok(result) => {
+                info!(
+                    job_id = %job_id,
+                    mirror = %target.display(),
+                    files = result.file_hashes.as_ref().map_or(0, Vec::len),
+                    "Mirror copy complete"
+                );
+                let _ = db
+                    .update_status(
+                        job_id.to_string(),
+                        "mirror_complete".to_string(),
+                        Some(target.display().to_string()),
+                        Some(result.total_bytes),
+                        Some(result.duration_secs),
+                    )
+                    .await;
+            }
+            Err(e) => {
+                warn!(job_id = %job_id, mirror = %target.display(), error = %e, "Mirror copy failed");
+                let message = format!("{}: {}", target.display(), e);
+                let _ = db
+                    .update_status(
+                        job_id.to_string(),
+                        "mirror_failed".to_string(),
+                        Some(message.clone()),
+                        None,
+                        None,
+                    )
+                    .await;
+                let notice = JobNotice {
+                    kind: "mirror_failed".to_string(),
+                    path: None,
+                    message: format!("Mirror copy to {}", message),
+                };
+                if let Err(e) = db.add_notices(job_id.to_string(), vec![notice]).await {
+                    warn!(job_id = %job_id, error = %e, "Failed to record notice");
+                }
+            }
+        }
+    }
+}
+
+/// Copy one backup to one mirror and verify it there.
+async fn copy(
+    config: &AppConfig,
+    job_id: &str,
+    backup: &Path,
+    target: &Path,
+    cancel: &CancellationToken,
+    pause: &PauseToken,
+) -> Result<TransferResult> {
+    let Some(mirror) = target.parent() else {
+        bail!("Mirror destination has no parent directory");
+    };
+    tokio::fs::create_dir_all(mirror).await?;
+
+    let engine = transfer_engine::create_backup_copy_engine(config);
+    let request = TransferRequest {
+        job_id: job_id.to_string(),
+        source: backup.to_path_buf(),
+        device: None,
+        destination: target.to_path_buf(),
+        owner: get_backup_owner(mirror),
+        completed: Vec::new(),
+        file_log: None,
+        filter: PathFilter::default(),
+        backed_up: Vec::new(),
+        link_dest: None,
+        cancel: cancel.clone(),
+        pause: pause.clone(),
+    };
+
+    // The job's progress already reads verified, so mirror progress is dropped
+    let (progress_tx, mut progress_rx) = mpsc::channel(100);
+    let drain = tokio::spawn(async move { while progress_rx.recv().await.is_some() {} });
+    let result = engine.transfer(&request, progress_tx).await;
+    let _ = drain.await;
+    let result = result?;
+
+    if config.verify_transfers
+        && !config.simulation
+        && let Some(hashes) = &result.file_hashes
+    {
+        verify_from_hashes(job_id, target, hashes, None, cancel).await?;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::TargetDrive;
+    use crate::db::memory::MemoryStore;
+
+    #[test]
+    fn test_mirror_destination_keeps_layout() {
+        assert_eq!(
+            destination_for(
+                Path::new("/mnt/usb"),
+                Path::new("/backups"),
+                Path::new("/backups/EOS_DIGITAL/2026-01-01_T1000_00"),
+            ),
+            PathBuf::from("/mnt/usb/EOS_DIGITAL/2026-01-01_T1000_00")
+        );
+        assert_eq!(
+            destination_for(
+                Path::new("/mnt/usb"),
+                Path::new("/backups"),
+                Path::new("/elsewhere/job"),
+            ),
+            PathBuf::from("/mnt/usb/job")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_each_mirror_is_copied_and_recorded() {
+        let temp = tempfile::tempdir().unwrap();
+        let backups = temp.path().join("backups");
+        let backup = backups.join("EOS_DIGITAL/2026-01-01_T1000_00");
+        std::fs::create_dir_all(backup.join("DCIM")).unwrap();
+        std::fs::write(backup.join("DCIM/A.JPG"), b"photo").unwrap();
+        // A file where the mirror's directory should be can't be copied into
+        let blocked = temp.path().join("blocked");
+        std::fs::write(&blocked, b"").unwrap();
+
+        let config = AppConfig {
+            backup_directory: backups,
+            mirror_directories: vec![temp.path().join("usb"), blocked],
+            ..Default::default()
+        };
+        let db = MemoryStore::open(None).unwrap();
+        let drive = TargetDrive {
+            uuid: "card-1".to_string(),
+            label: "EOS_DIGITAL".to_string(),
+            mount_path: "/run/bksd/card".to_string(),
+            raw_size: 64,
+        };
+        db.create_job(
+            "job-1".to_string(),
+            drive,
+            backup.to_string_lossy().to_string(),
+            "host".to_string(),
+            Default::default(),
+        )
+        .await
+        .unwrap();
+
+        copy_all(
+            &config,
+            &db,
+            "job-1",
+            &backup,
+            &CancellationToken::new(),
+            &PauseToken::default(),
+        )
+        .await;
+
+        let copied = temp
+            .path()
+            .join("usb/EOS_DIGITAL/2026-01-01_T1000_00/DCIM/A.JPG");
+        assert_eq!(std::fs::read(copied).unwrap(), b"photo");
+
+        let job = db.get_with_history("job-1".to_string()).await.unwrap();
+        let statuses: Vec<&str> = job.history.iter().map(|e| e.status.as_str()).collect();
+        assert!(statuses.ends_with(&[
+            "mirror_copying",
+            "mirror_complete",
+            "mirror_copying",
+            "mirror_failed"
+        ]));
+        assert_eq!(job.notices.len(), 1);
+        assert_eq!(job.notices[0].kind, "mirror_failed");
+    }
+}
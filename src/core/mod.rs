@@ -1,15 +1,53 @@
+pub mod anomalies;
+pub mod archive;
+pub mod badge;
+pub mod camera;
+pub mod capacity;
+pub mod clock;
+pub mod completion;
+pub mod dcim;
+pub mod destination;
+pub mod device_filter;
+pub mod export;
+pub mod failure;
+pub mod filter;
+pub mod fingerprint;
 pub mod hardware;
+pub mod immutable;
+pub mod label_fields;
+pub mod manual;
+pub mod media;
+pub mod mirror;
 pub mod models;
 pub mod notifications;
 pub mod orchestrator;
 pub mod ownership;
+pub mod parity;
+pub mod partial;
+pub mod partitions;
+pub mod power;
 pub mod progress;
+pub mod quarantine;
+pub mod restore;
+pub mod scrub;
+pub mod signing;
+pub mod snapshot;
+pub mod space;
+pub mod subvolume;
+pub mod telemetry;
+pub mod timeline;
+pub mod timestamp;
 pub mod transfer_engine;
+pub mod unicode;
+pub mod upload;
 pub mod verifier;
+pub mod video_probe;
+pub mod window;
 
 pub use hardware::{BlockDevice, HardwareAdapter, HardwareEvent};
-pub use models::{Job, JobStatusEntry, JobWithHistory, TargetDrive};
+pub use models::{ArchiveVolume, Job, JobNotice, JobStatusEntry, JobWithHistory, TargetDrive};
 pub use orchestrator::Orchestrator;
 pub use ownership::{FileOwner, get_backup_owner};
+pub use power::PowerInhibitor;
 pub use progress::ProgressTracker;
 pub use verifier::{VerifyResult, verify_from_hashes};
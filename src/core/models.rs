@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
 
+use crate::core::label_fields::LabelFields;
+use crate::core::transfer_engine::TransferStats;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TargetDrive {
     pub uuid: String,
@@ -15,6 +18,16 @@ pub struct Job {
     pub destination_path: Option<String>,
     pub created_at: String,
     pub status: String,
+    /// Label of the archive drive holding this backup, if it has been tiered
+    /// off the backup directory
+    #[serde(default)]
+    pub archive_label: Option<String>,
+    /// Hostname of the ingest station that ran the job
+    #[serde(default)]
+    pub origin_host: Option<String>,
+    /// Reel, day and camera parsed from the card's volume label
+    #[serde(flatten)]
+    pub label_fields: LabelFields,
 }
 
 /// A single entry from the job status log.
@@ -25,13 +38,44 @@ pub struct JobStatusEntry {
     pub description: Option<String>,
     pub total_bytes: Option<u64>,
     pub duration_secs: Option<u64>,
+    /// File counts and throughput, on the `complete` entry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<TransferStats>,
     pub created_at: String,
 }
 
+/// An operator-facing observation attached to a job, such as a suspicious file
+/// found on the card. Notices don't change the job's status.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobNotice {
+    /// Machine-readable category (e.g. "zero_byte_file")
+    pub kind: String,
+    /// Path relative to the source root, if the notice refers to a file
+    pub path: Option<String>,
+    /// Human-readable description
+    pub message: String,
+}
+
+/// An external drive used for cold archives, tracked by filesystem UUID so
+/// its contents stay known while it sits on a shelf.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveVolume {
+    pub uuid: String,
+    pub label: String,
+    pub capacity: Option<u64>,
+    /// Whether the drive is currently plugged in
+    pub connected: bool,
+    pub last_seen_at: String,
+    /// Number of backups stored on the drive
+    pub job_count: u64,
+}
+
 /// Job with full status history.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JobWithHistory {
     #[serde(flatten)]
     pub job: Job,
     pub history: Vec<JobStatusEntry>,
+    #[serde(default)]
+    pub notices: Vec<JobNotice>,
 }
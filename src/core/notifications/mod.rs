@@ -6,6 +6,10 @@ use std::path::PathBuf;
 use std::sync::Arc;
 
 use crate::config::{NotificationChannelType, NotificationConfig};
+use crate::core::label_fields::LabelFields;
+use crate::core::models::JobNotice;
+use crate::core::transfer_engine::TransferStats;
+use crate::db::scrubs::DamagedFile;
 
 /// Events that trigger notifications
 #[derive(Debug, Clone)]
@@ -16,18 +20,55 @@ pub enum JobEvent {
         device_uuid: String,
         source: PathBuf,
         destination: PathBuf,
+        /// Reel, day and camera parsed from the label
+        label_fields: LabelFields,
     },
     Completed {
         job_id: String,
         device_label: String,
+        label_fields: LabelFields,
         total_bytes: u64,
         duration_secs: u64,
+        /// Average/peak throughput, verification speed and retries
+        stats: TransferStats,
+        /// Anomalies and other findings the operator should review
+        notices: Vec<JobNotice>,
     },
     Failed {
         job_id: String,
         device_label: String,
         error: String,
+        /// What the operator can do about it (`core::failure`)
+        hint: Option<String>,
     },
+    /// Raised by the hub: a card was ingested on two stations within
+    /// minutes, most likely re-inserted after it was already backed up
+    DuplicateIngest {
+        target_id: String,
+        station: String,
+        job_id: String,
+        other_station: String,
+        other_job_id: String,
+        minutes_apart: u32,
+    },
+    /// A scrub found files in an old backup that no longer match the hashes
+    /// recorded when it was made (bit-rot, or changed behind bksd's back)
+    BackupDamaged {
+        job_id: String,
+        destination: PathBuf,
+        label_fields: LabelFields,
+        files_checked: u64,
+        damaged: Vec<DamagedFile>,
+    },
+    /// The backup directory's free space dropped below `min_free_space_gb`,
+    /// so new jobs are held until it is freed
+    SpaceLow {
+        path: PathBuf,
+        free_bytes: u64,
+        min_free_bytes: u64,
+    },
+    /// Space was freed again and held jobs are starting
+    SpaceFreed { path: PathBuf, free_bytes: u64 },
 }
 
 /// Trait for notification channel implementations (Slack, Discord, etc.)
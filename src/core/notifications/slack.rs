@@ -1,4 +1,7 @@
 use super::{JobEvent, NotificationChannel};
+use crate::core::label_fields::LabelFields;
+use crate::core::models::JobNotice;
+use crate::core::transfer_engine::TransferStats;
 use anyhow::Result;
 use async_trait::async_trait;
 use serde_json::json;
@@ -23,10 +26,11 @@ impl SlackNotifier {
                 device_label,
                 source,
                 destination,
+                label_fields,
                 ..
             } => {
                 let short_id = &job_id[..8.min(job_id.len())];
-                json!({
+                let mut message = json!({
                     "blocks": [
                         {
                             "type": "header",
@@ -58,13 +62,22 @@ impl SlackNotifier {
                             ]
                         }
                     ]
-                })
+                });
+                if let Some(block) = label_fields_block(label_fields)
+                    && let Some(blocks) = message["blocks"].as_array_mut()
+                {
+                    blocks.push(block);
+                }
+                message
             }
             JobEvent::Completed {
                 job_id,
                 device_label,
+                label_fields,
                 total_bytes,
                 duration_secs,
+                stats,
+                notices,
             } => {
                 let short_id = &job_id[..8.min(job_id.len())];
                 let size_mb = *total_bytes as f64 / (1024.0 * 1024.0);
@@ -73,7 +86,7 @@ impl SlackNotifier {
                 } else {
                     0.0
                 };
-                json!({
+                let mut message = json!({
                     "blocks": [
                         {
                             "type": "header",
@@ -101,19 +114,42 @@ impl SlackNotifier {
                                 {
                                     "type": "mrkdwn",
                                     "text": format!("*Duration:*\n{}s ({:.1} MB/s)", duration_secs, speed_mbps)
+                                },
+                                {
+                                    "type": "mrkdwn",
+                                    "text": format!("*Files:*\n{}", stats.file_counts())
+                                },
+                                {
+                                    "type": "mrkdwn",
+                                    "text": format!("*Largest:*\n{}", largest_text(stats))
+                                }
+                            ]
+                        },
+                        {
+                            "type": "context",
+                            "elements": [
+                                {
+                                    "type": "mrkdwn",
+                                    "text": stats.summary()
                                 }
                             ]
                         }
                     ]
-                })
+                });
+                if let Some(blocks) = message["blocks"].as_array_mut() {
+                    blocks.extend(label_fields_block(label_fields));
+                    blocks.extend(notices_block(notices));
+                }
+                message
             }
             JobEvent::Failed {
                 job_id,
                 device_label,
                 error,
+                hint,
             } => {
                 let short_id = &job_id[..8.min(job_id.len())];
-                json!({
+                let mut message = json!({
                     "blocks": [
                         {
                             "type": "header",
@@ -144,12 +180,254 @@ impl SlackNotifier {
                             }
                         }
                     ]
+                });
+                if let Some(hint) = hint
+                    && let Some(blocks) = message["blocks"].as_array_mut()
+                {
+                    blocks.push(json!({
+                        "type": "section",
+                        "text": {
+                            "type": "mrkdwn",
+                            "text": format!("*What to do:*\n{}", hint)
+                        }
+                    }));
+                }
+                message
+            }
+            JobEvent::DuplicateIngest {
+                target_id,
+                station,
+                job_id,
+                other_station,
+                other_job_id,
+                minutes_apart,
+            } => {
+                json!({
+                    "blocks": [
+                        {
+                            "type": "header",
+                            "text": {
+                                "type": "plain_text",
+                                "text": "Card Ingested Twice",
+                                "emoji": true
+                            }
+                        },
+                        {
+                            "type": "section",
+                            "fields": [
+                                {
+                                    "type": "mrkdwn",
+                                    "text": format!("*Card:*\n`{}`", target_id)
+                                },
+                                {
+                                    "type": "mrkdwn",
+                                    "text": format!("*Apart:*\n{} min", minutes_apart)
+                                },
+                                {
+                                    "type": "mrkdwn",
+                                    "text": format!("*First:*\n{} `{}`", other_station, &other_job_id[..8.min(other_job_id.len())])
+                                },
+                                {
+                                    "type": "mrkdwn",
+                                    "text": format!("*Again:*\n{} `{}`", station, &job_id[..8.min(job_id.len())])
+                                }
+                            ]
+                        },
+                        {
+                            "type": "section",
+                            "text": {
+                                "type": "mrkdwn",
+                                "text": "This card may have been backed up already. Check before the second copy uses up destination space."
+                            }
+                        }
+                    ]
+                })
+            }
+            JobEvent::BackupDamaged {
+                job_id,
+                destination,
+                label_fields,
+                files_checked,
+                damaged,
+            } => {
+                let short_id = &job_id[..8.min(job_id.len())];
+                let mut text = format!("*Damaged files ({}):*", damaged.len());
+                for file in damaged.iter().take(5) {
+                    text.push_str(&format!(
+                        "\n• `{}`: {}",
+                        file.relative_path.display(),
+                        file.reason
+                    ));
+                }
+                if damaged.len() > 5 {
+                    text.push_str(&format!("\n… and {} more", damaged.len() - 5));
+                }
+                let mut message = json!({
+                    "blocks": [
+                        {
+                            "type": "header",
+                            "text": {
+                                "type": "plain_text",
+                                "text": "Backup Damaged",
+                                "emoji": true
+                            }
+                        },
+                        {
+                            "type": "section",
+                            "fields": [
+                                {
+                                    "type": "mrkdwn",
+                                    "text": format!("*Backup:*\n`{}`", destination.display())
+                                },
+                                {
+                                    "type": "mrkdwn",
+                                    "text": format!("*Job ID:*\n`{}`", short_id)
+                                },
+                                {
+                                    "type": "mrkdwn",
+                                    "text": format!("*Checked:*\n{} files", files_checked)
+                                }
+                            ]
+                        },
+                        {
+                            "type": "section",
+                            "text": {
+                                "type": "mrkdwn",
+                                "text": text
+                            }
+                        }
+                    ]
+                });
+                if let Some(blocks) = message["blocks"].as_array_mut() {
+                    blocks.extend(label_fields_block(label_fields));
+                }
+                message
+            }
+            JobEvent::SpaceLow {
+                path,
+                free_bytes,
+                min_free_bytes,
+            } => {
+                json!({
+                    "blocks": [
+                        {
+                            "type": "header",
+                            "text": {
+                                "type": "plain_text",
+                                "text": "Backup Drive Low on Space",
+                                "emoji": true
+                            }
+                        },
+                        {
+                            "type": "section",
+                            "fields": [
+                                {
+                                    "type": "mrkdwn",
+                                    "text": format!("*Backup directory:*\n`{}`", path.display())
+                                },
+                                {
+                                    "type": "mrkdwn",
+                                    "text": format!("*Free:*\n{} (minimum {})", gb_text(*free_bytes), gb_text(*min_free_bytes))
+                                }
+                            ]
+                        },
+                        {
+                            "type": "section",
+                            "text": {
+                                "type": "mrkdwn",
+                                "text": "New backups are queued until space is freed. Running backups carry on."
+                            }
+                        }
+                    ]
+                })
+            }
+            JobEvent::SpaceFreed { path, free_bytes } => {
+                json!({
+                    "blocks": [
+                        {
+                            "type": "header",
+                            "text": {
+                                "type": "plain_text",
+                                "text": "Backup Space Freed",
+                                "emoji": true
+                            }
+                        },
+                        {
+                            "type": "section",
+                            "fields": [
+                                {
+                                    "type": "mrkdwn",
+                                    "text": format!("*Backup directory:*\n`{}`", path.display())
+                                },
+                                {
+                                    "type": "mrkdwn",
+                                    "text": format!("*Free:*\n{}", gb_text(*free_bytes))
+                                }
+                            ]
+                        },
+                        {
+                            "type": "section",
+                            "text": {
+                                "type": "mrkdwn",
+                                "text": "Queued backups are starting."
+                            }
+                        }
+                    ]
                 })
             }
         }
     }
 }
 
+/// A byte count in GB, e.g. "12.5 GB"
+fn gb_text(bytes: u64) -> String {
+    format!("{:.1} GB", bytes as f64 / 1_000_000_000.0)
+}
+
+/// Build a context line with the reel, day and camera, if any were parsed
+fn label_fields_block(label_fields: &LabelFields) -> Option<serde_json::Value> {
+    Some(json!({
+        "type": "context",
+        "elements": [
+            {
+                "type": "mrkdwn",
+                "text": label_fields.summary()?
+            }
+        ]
+    }))
+}
+
+/// Build a warning section listing job notices (first few only)
+fn notices_block(notices: &[JobNotice]) -> Option<serde_json::Value> {
+    if notices.is_empty() {
+        return None;
+    }
+
+    let mut text = format!("*Review before formatting the card ({}):*", notices.len());
+    for notice in notices.iter().take(5) {
+        text.push_str(&format!("\n• {}", notice.message));
+    }
+    if notices.len() > 5 {
+        text.push_str(&format!("\n… and {} more", notices.len() - 5));
+    }
+
+    Some(json!({
+        "type": "section",
+        "text": {
+            "type": "mrkdwn",
+            "text": text
+        }
+    }))
+}
+
+/// e.g. "`DCIM/100CANON/MVI_0001.MP4` (3.9 GB)"
+fn largest_text(stats: &TransferStats) -> String {
+    match &stats.largest_file {
+        Some(largest) => format!("`{}` ({})", largest.path, largest.size_text()),
+        None => "-".to_string(),
+    }
+}
+
 #[async_trait]
 impl NotificationChannel for SlackNotifier {
     async fn notify(&self, event: JobEvent) -> Result<()> {
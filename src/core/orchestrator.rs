@@ -1,36 +1,99 @@
-use chrono::Local;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
 use tracing::{Instrument, error, info, info_span, warn};
 
+use crate::adapters;
+use crate::config::{AppConfig, VerifyMode};
 use crate::context::AppContext;
-use crate::core::TargetDrive;
-use crate::core::hardware::{BlockDevice, HardwareAdapter, HardwareEvent};
+use crate::core::anomalies;
+use crate::core::archive;
+use crate::core::badge;
+use crate::core::camera;
+use crate::core::clock::Stopwatch;
+use crate::core::completion::{self, CompletedJob};
+use crate::core::destination::{self, Destination, TemplateValues};
+use crate::core::failure::{self, FailureKind};
+use crate::core::filter::PathFilter;
+use crate::core::fingerprint::{self, Fingerprint};
+use crate::core::hardware::{self, BlockDevice, HardwareAdapter, HardwareEvent};
+use crate::core::immutable;
+use crate::core::label_fields::LabelFields;
+use crate::core::mirror;
 use crate::core::notifications::JobEvent;
 use crate::core::ownership::get_backup_owner;
-use crate::core::transfer_engine::{self, TransferRequest, TransferStatus};
-use crate::core::verifier::verify_from_hashes;
+use crate::core::parity;
+use crate::core::partial;
+use crate::core::partitions::{self, Partitions};
+use crate::core::progress::{JobControls, JobInfo, PeakRate};
+use crate::core::quarantine::{self, Quarantine};
+use crate::core::snapshot;
+use crate::core::subvolume;
+use crate::core::timestamp;
+use crate::core::transfer_engine::{
+    self, FileHash, LinkDest, ProgressInterval, ProgressSender, SimulatedEngine, TransferEngine,
+    TransferEngineType, TransferRequest, TransferResult, TransferStats, TransferStatus,
+};
+use crate::core::upload;
+use crate::core::verifier::{self, verify_from_hashes, verify_three_way};
+use crate::core::video_probe;
+use crate::core::{Job, JobNotice, TargetDrive};
+use crate::db::Store;
+use crate::db::catalog::{CatalogEntry, DeviceFingerprint};
 use crate::logging::LogThrottle;
-use crate::{adapters, db};
 use anyhow::Result;
+use chrono::Utc;
+use ed25519_dalek::SigningKey;
+use tokio_util::sync::CancellationToken;
 
 pub struct Orchestrator {
     ctx: AppContext,
     adapter: Arc<dyn HardwareAdapter>,
+    /// Jobs for cards inserted outside `backup_window`, or waiting for
+    /// another partition of their card, by device UUID
+    queued: tokio::sync::Mutex<HashMap<String, QueuedJob>>,
+    /// Cards with a partition being backed up
+    partitions: Arc<Partitions>,
+}
+
+/// A job waiting to be started.
+struct QueuedJob {
+    device: BlockDevice,
+    job_id: String,
+    destination: PathBuf,
+    /// Files already copied, when the job is a resumed one
+    completed: Vec<FileHash>,
+    reason: QueueReason,
+}
+
+/// What a queued job is waiting for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum QueueReason {
+    /// The backup window to open
+    Window,
+    /// Another partition of the same card to be backed up
+    Partition,
+    /// Space to be freed in the backup directory (`min_free_space_gb`)
+    Space,
 }
 
 impl Orchestrator {
-    pub fn new(ctx: AppContext) -> Self {
-        let adapter: Box<dyn HardwareAdapter> = adapters::get_adapter(&ctx.config);
-        Self {
+    pub fn new(ctx: AppContext) -> Result<Self> {
+        let adapter: Box<dyn HardwareAdapter> =
+            adapters::get_adapter(&ctx.config, ctx.simulator.as_ref())?;
+        Ok(Self {
             ctx,
             adapter: Arc::from(adapter),
-        }
+            queued: Default::default(),
+            partitions: Default::default(),
+        })
     }
 
-    pub async fn start(&self) -> Result<()> {
+    /// Handle device events until `shutdown` resolves, then stop taking new
+    /// devices and give running jobs time to finish (see `drain`).
+    pub async fn start(&self, shutdown: impl Future<Output = ()>) -> Result<()> {
         let backup_dir = self.ctx.config.backup_directory.display().to_string();
         let simulation = self.ctx.config.simulation;
 
@@ -43,20 +106,292 @@ impl Orchestrator {
         async {
             info!("Sentinel Daemon starting");
 
+            if let Err(e) = self.ctx.db.reset_connected().await {
+                warn!(error = %e, "Failed to reset archive drive state");
+            }
+
+            match self.ctx.db.fail_interrupted().await {
+                Ok(0) => {}
+                Ok(count) => warn!(count, "Closed jobs interrupted by the last shutdown"),
+                Err(e) => warn!(error = %e, "Failed to mark interrupted jobs"),
+            }
+
             let (tx, mut rx) = mpsc::channel(32);
 
+            if let Some(uploads) = &self.ctx.uploads {
+                uploads.attach(tx.clone()).await;
+            }
+            self.ctx.manual.attach(tx.clone()).await;
             self.adapter.start(tx);
+            self.scan_connected().await;
 
-            while let Some(event) = rx.recv().await {
-                self.handle_device_event(event).await;
+            tokio::pin!(shutdown);
+            loop {
+                let (window_waiting, space_waiting) = {
+                    let queued = self.queued.lock().await;
+                    let waiting = |reason| queued.values().any(|queued| queued.reason == reason);
+                    (waiting(QueueReason::Window), waiting(QueueReason::Space))
+                };
+                tokio::select! {
+                    event = rx.recv() => match event {
+                        Some(event) => self.handle_device_event(event).await,
+                        None => return Ok(()),
+                    },
+                    _ = self.window_opens(), if window_waiting => {
+                        self.start_queued(QueueReason::Window).await
+                    }
+                    _ = self.ctx.space.freed(), if space_waiting => {
+                        self.start_queued(QueueReason::Space).await
+                    }
+                    _ = self.partitions.released() => self.start_next_partitions().await,
+                    _ = &mut shutdown => break,
+                }
             }
 
+            info!("Shutting down, no longer accepting devices");
+            self.drain().await;
             Ok(())
         }
         .instrument(span)
         .await
     }
 
+    /// Back up the cards already inserted when the daemon started, as if
+    /// they had just been plugged in. One backed up within
+    /// `startup_scan_skip_hours` is left alone: it most likely stayed in the
+    /// reader while the daemon restarted.
+    async fn scan_connected(&self) {
+        if !self.ctx.config.scan_on_startup {
+            return;
+        }
+
+        let adapter = self.adapter.clone();
+        let devices = match tokio::task::spawn_blocking(move || adapter.list_devices())
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|devices| devices)
+        {
+            Ok(devices) => devices,
+            Err(e) => {
+                warn!(error = %e, "Failed to list connected devices");
+                return;
+            }
+        };
+        if !devices.is_empty() {
+            info!(
+                devices = devices.len(),
+                "Checking devices connected at startup"
+            );
+        }
+
+        for dev in devices {
+            if self.recently_backed_up(&dev).await {
+                info!(
+                    uuid = %dev.uuid,
+                    label = %dev.label,
+                    "Device connected at startup was backed up recently, not backing it up again"
+                );
+                continue;
+            }
+            self.handle_device_added(dev).await;
+        }
+    }
+
+    /// Whether the device's latest successful backup is within
+    /// `startup_scan_skip_hours`.
+    async fn recently_backed_up(&self, dev: &BlockDevice) -> bool {
+        let since =
+            Utc::now() - chrono::Duration::hours(self.ctx.config.startup_scan_skip_hours as i64);
+        match self.ctx.db.latest_backup(dev.uuid.clone()).await {
+            Ok(Some(job)) => timestamp::parse(&job.created_at).is_some_and(|at| at >= since),
+            Ok(None) => false,
+            Err(e) => {
+                warn!(error = %e, "Failed to look up previous backup");
+                false
+            }
+        }
+    }
+
+    /// Resolves once `backup_window` is open.
+    async fn window_opens(&self) {
+        if let Some(window) = &self.ctx.backup_window {
+            let now = Utc::now();
+            let opens = window.opens_after(now, self.ctx.zone);
+            tokio::time::sleep((opens - now).to_std().unwrap_or_default()).await;
+        }
+    }
+
+    /// Whether a card inserted now has to wait for `backup_window`. Uploads
+    /// and manual backups were asked for explicitly, so never wait.
+    fn outside_window(&self, dev: &BlockDevice) -> bool {
+        !dev.is_upload()
+            && !dev.is_manual()
+            && self
+                .ctx
+                .backup_window
+                .as_ref()
+                .is_some_and(|window| !window.is_open(Utc::now(), self.ctx.zone))
+    }
+
+    /// What a job for `dev` has to wait for before starting, if anything.
+    fn held_for(&self, dev: &BlockDevice) -> Option<QueueReason> {
+        if self.outside_window(dev) {
+            Some(QueueReason::Window)
+        } else if self.ctx.space.is_low() {
+            Some(QueueReason::Space)
+        } else {
+            None
+        }
+    }
+
+    /// Start every job queued for `reason`, now that it no longer holds
+    /// them. A job something else still holds up is queued for that.
+    async fn start_queued(&self, reason: QueueReason) {
+        let devices: Vec<BlockDevice> = self
+            .queued
+            .lock()
+            .await
+            .values()
+            .filter(|queued| queued.reason == reason)
+            .map(|queued| queued.device.clone())
+            .collect();
+        info!(jobs = devices.len(), ?reason, "Starting queued jobs");
+        for dev in devices {
+            self.handle_device_added(dev).await;
+        }
+    }
+
+    /// Start the next partition of each card whose previous one is done.
+    async fn start_next_partitions(&self) {
+        for uuid in self.partitions.next().await {
+            let Some(dev) = self
+                .queued
+                .lock()
+                .await
+                .get(&uuid)
+                .map(|queued| queued.device.clone())
+            else {
+                continue;
+            };
+            info!(label = %dev.label, "Previous partition of the card done, starting the next");
+            self.handle_device_added(dev).await;
+        }
+    }
+
+    /// Keep the job queued for `uuid` waiting, now for `reason`. False if
+    /// there is no such job.
+    async fn requeue(&self, uuid: &str, reason: QueueReason) -> bool {
+        let queued = {
+            let mut jobs = self.queued.lock().await;
+            match jobs.get(uuid).map(|queued| queued.reason) {
+                None => return false,
+                Some(current) if current == reason => return true,
+                Some(_) => jobs.remove(uuid),
+            }
+        };
+        if let Some(queued) = queued {
+            self.queue(
+                queued.device,
+                queued.job_id,
+                queued.destination,
+                queued.completed,
+                reason,
+            )
+            .await;
+        }
+        true
+    }
+
+    /// Hold a job until what it is waiting for is done.
+    async fn queue(
+        &self,
+        dev: BlockDevice,
+        job_id: String,
+        destination: PathBuf,
+        completed: Vec<FileHash>,
+        reason: QueueReason,
+    ) {
+        let description = match (reason, &self.ctx.backup_window) {
+            (QueueReason::Partition, _) => {
+                "Waiting for another partition of the card to be backed up".to_string()
+            }
+            (QueueReason::Window, Some(window)) => format!(
+                "Queued until the backup window ({}) opens at {}",
+                window,
+                self.ctx
+                    .zone
+                    .format_utc(window.opens_after(Utc::now(), self.ctx.zone), "%H:%M")
+            ),
+            (QueueReason::Window, None) => "Queued".to_string(),
+            (QueueReason::Space, _) => format!(
+                "Queued until the backup directory has {} GB free",
+                self.ctx.config.min_free_space_gb.unwrap_or_default()
+            ),
+        };
+        info!(job_id = %job_id, "{}", description);
+        let _ = self
+            .ctx
+            .db
+            .update_status(
+                job_id.clone(),
+                "queued".to_string(),
+                Some(description),
+                None,
+                None,
+            )
+            .await;
+        self.queued.lock().await.insert(
+            dev.uuid.clone(),
+            QueuedJob {
+                device: dev,
+                job_id,
+                destination,
+                completed,
+                reason,
+            },
+        );
+    }
+
+    /// Wait up to `shutdown_timeout_secs` for running jobs to finish. Any
+    /// still going are stopped and marked failed as interrupted; their
+    /// manifests hold what was copied, so reinserting the card resumes them
+    /// (native copy).
+    async fn drain(&self) {
+        const POLL_INTERVAL: Duration = Duration::from_millis(500);
+        // Time stopped jobs get to kill their rsync or par2 and wind down
+        const STOP_GRACE: Duration = Duration::from_secs(5);
+
+        let timeout = Duration::from_secs(self.ctx.config.shutdown_timeout_secs);
+        let waited = Stopwatch::start();
+        loop {
+            let running = self.ctx.progress.running().await;
+            if running.is_empty() {
+                info!("No jobs running");
+                return;
+            }
+            if waited.elapsed() >= timeout {
+                warn!(
+                    jobs = running.len(),
+                    "Jobs still running at shutdown, stopping them"
+                );
+                self.ctx.progress.interrupt_all().await;
+                break;
+            }
+            info!(jobs = running.len(), "Waiting for running jobs to finish");
+            tokio::time::sleep(POLL_INTERVAL.min(timeout.saturating_sub(waited.elapsed()))).await;
+        }
+
+        let stopping = Stopwatch::start();
+        while !self.ctx.progress.running().await.is_empty() && stopping.elapsed() < STOP_GRACE {
+            tokio::time::sleep(POLL_INTERVAL.min(STOP_GRACE.saturating_sub(stopping.elapsed())))
+                .await;
+        }
+
+        if let Err(e) = self.ctx.db.fail_interrupted().await {
+            warn!(error = %e, "Failed to mark interrupted jobs");
+        }
+    }
+
     pub async fn handle_device_event(&self, event: HardwareEvent) {
         match event {
             HardwareEvent::DeviceAdded(dev) => self.handle_device_added(dev).await,
@@ -64,9 +399,230 @@ impl Orchestrator {
         }
     }
 
-    fn build_destination(&self, label: &str) -> PathBuf {
-        let timestamp = Local::now().format("%Y-%m-%d_T%H%M_%S").to_string();
-        self.ctx.config.backup_directory.join(label).join(timestamp)
+    /// Reel, day and camera from a device's label, if `label_pattern` is
+    /// set, and the camera model its photos were taken with.
+    async fn job_fields(&self, dev: &BlockDevice) -> LabelFields {
+        let mut fields = self
+            .ctx
+            .label_pattern
+            .as_ref()
+            .map(|pattern| pattern.parse(&dev.label))
+            .unwrap_or_default();
+        fields.camera_model = camera::detect(&dev.mount_point).await;
+        if let Some(model) = &fields.camera_model {
+            info!(camera_model = %model, "Detected camera model");
+        }
+        fields
+    }
+
+    fn build_destination(
+        &self,
+        label: &str,
+        hostname: &str,
+        label_fields: &LabelFields,
+    ) -> PathBuf {
+        let timestamp = self.ctx.zone.now("%Y-%m-%d_T%H%M_%S");
+        let config = &self.ctx.config;
+        destination::build(
+            &config.backup_directory,
+            &config.destination_template,
+            &TemplateValues {
+                label,
+                timestamp: &timestamp,
+                hostname,
+                operator: config.operator.as_deref(),
+                label_fields,
+            },
+        )
+    }
+
+    /// If this device's last job was interrupted part-way, pick it back up:
+    /// same job ID and destination, with the files already copied.
+    async fn find_resumable(&self, dev: &BlockDevice) -> Option<(String, PathBuf, Vec<FileHash>)> {
+        let config = &self.ctx.config;
+        if !config.resume_interrupted
+            || config.transfer_engine != TransferEngineType::NativeCopy
+            || dev.is_upload()
+        {
+            return None;
+        }
+
+        let job = match self.ctx.db.find_resumable(dev.uuid.clone()).await {
+            Ok(Some(job)) => job,
+            Ok(None) => return None,
+            Err(e) => {
+                warn!(error = %e, "Failed to check for an interrupted job");
+                return None;
+            }
+        };
+
+        // Its copy is resumed at `.partial`, wherever it was left
+        let destination = PathBuf::from(job.destination_path.as_deref()?);
+        if !partial::restore(&destination).await {
+            return None;
+        }
+
+        let completed = self
+            .ctx
+            .db
+            .list_files(job.id.clone())
+            .await
+            .ok()
+            .filter(|files| !files.is_empty())?;
+
+        info!(
+            job_id = %job.id,
+            destination = %destination.display(),
+            files_done = completed.len(),
+            "Resuming interrupted job"
+        );
+
+        let _ = self
+            .ctx
+            .db
+            .update_status(
+                job.id.clone(),
+                "Ready".to_string(),
+                Some(format!(
+                    "Resuming interrupted job ({} files already copied)",
+                    completed.len()
+                )),
+                None,
+                None,
+            )
+            .await;
+
+        Some((job.id, destination, completed))
+    }
+
+    /// The device's previous backup to hardlink unchanged files from, if it
+    /// is still on disk.
+    async fn find_link_dest(&self, target_id: &str, job_id: &str) -> Option<LinkDest> {
+        let previous = match self.ctx.db.latest_backup(target_id.to_string()).await {
+            Ok(Some(job)) if job.id != job_id => job,
+            Ok(_) => return None,
+            Err(e) => {
+                warn!(error = %e, "Failed to look up previous backup");
+                return None;
+            }
+        };
+
+        let directory = PathBuf::from(previous.destination_path.as_deref()?);
+        if !directory.is_dir() {
+            return None;
+        }
+
+        let files = self
+            .ctx
+            .db
+            .list_files(previous.id.clone())
+            .await
+            .unwrap_or_default();
+        info!(
+            previous_job = %previous.id,
+            link_dest = %directory.display(),
+            "Hardlinking unchanged files from previous backup"
+        );
+        Some(LinkDest { directory, files })
+    }
+
+    /// The card's content fingerprint, if unchanged cards are skipped and it
+    /// could be read.
+    async fn fingerprint(&self, dev: &BlockDevice) -> Option<Fingerprint> {
+        let config = &self.ctx.config;
+        if !config.skip_unchanged_cards || config.simulation || dev.is_upload() {
+            return None;
+        }
+
+        match fingerprint::compute(&dev.mount_point).await {
+            Ok(fingerprint) => Some(fingerprint),
+            Err(e) => {
+                warn!(error = %e, "Failed to fingerprint device, copying it anyway");
+                None
+            }
+        }
+    }
+
+    /// The device's latest backup, if the card is unchanged since it was
+    /// made and it is still in the backup directory.
+    async fn unchanged_since(&self, dev: &BlockDevice, fingerprint: &Fingerprint) -> Option<Job> {
+        let stored = match self.ctx.db.fingerprint_lookup(dev.uuid.clone()).await {
+            Ok(Some(stored)) if stored.digest == fingerprint.digest => stored,
+            Ok(_) => return None,
+            Err(e) => {
+                warn!(error = %e, "Failed to look up device fingerprint");
+                return None;
+            }
+        };
+
+        let backup = match self.ctx.db.latest_backup(dev.uuid.clone()).await {
+            Ok(Some(job)) if job.id == stored.job_id => job,
+            Ok(_) => return None,
+            Err(e) => {
+                warn!(error = %e, "Failed to look up previous backup");
+                return None;
+            }
+        };
+        Path::new(backup.destination_path.as_deref()?)
+            .is_dir()
+            .then_some(backup)
+    }
+
+    /// Record a job for a card left alone because `backup` already holds
+    /// everything on it.
+    async fn record_unchanged(&self, dev: &BlockDevice, fingerprint: &Fingerprint, backup: &Job) {
+        let job_id = uuid::Uuid::now_v7().to_string();
+        let destination = backup.destination_path.clone().unwrap_or_default();
+        let target_drive = TargetDrive {
+            uuid: dev.uuid.clone(),
+            label: dev.label.clone(),
+            mount_path: dev.mount_point.to_string_lossy().to_string(),
+            raw_size: dev.capacity,
+        };
+
+        if let Err(e) = self
+            .ctx
+            .db
+            .create_job(
+                job_id.clone(),
+                target_drive,
+                destination.clone(),
+                destination::hostname(),
+                self.job_fields(dev).await,
+            )
+            .await
+        {
+            error!(error = %e, "Failed to create job in database");
+            return;
+        }
+
+        info!(
+            job_id = %job_id,
+            previous_job = %backup.id,
+            files = fingerprint.files,
+            "Card unchanged since its last backup, not copying it"
+        );
+        let description = format!(
+            "Skipped (unchanged): {} files ({:.1} MB) already backed up by job {} in {}",
+            fingerprint.files,
+            fingerprint.bytes as f64 / (1024.0 * 1024.0),
+            backup.id,
+            destination
+        );
+        if let Err(e) = self
+            .ctx
+            .db
+            .update_status(
+                job_id.clone(),
+                "skipped".to_string(),
+                Some(description),
+                None,
+                None,
+            )
+            .await
+        {
+            warn!(job_id = %job_id, error = %e, "Failed to record skipped job");
+        }
     }
 
     async fn handle_device_added(&self, dev: BlockDevice) {
@@ -85,37 +641,196 @@ impl Orchestrator {
             "New device detected"
         );
 
-        let job_id = uuid::Uuid::now_v7().to_string();
-        let destination = self.build_destination(&dev.label);
+        if !dev.is_manual() && archive::is_archive_drive(&self.ctx.config, &dev) {
+            self.handle_archive_drive(dev);
+            return;
+        }
 
-        let target_drive = TargetDrive {
-            uuid: dev.uuid.clone(),
-            label: dev.label.clone(),
-            mount_path: dev.mount_point.to_string_lossy().to_string(),
-            raw_size: dev.capacity,
+        // Uploads and manual backups were asked for explicitly
+        if !dev.is_upload()
+            && !dev.is_manual()
+            && let Some(reason) = self.ctx.device_filter.rejects(&dev)
+        {
+            info!(reason, "Not backing up device, excluded by device filter");
+            self.ctx.ignored_devices.add(&dev, reason).await;
+            return;
+        }
+
+        // A profiled simulated device's files exist only in its profile
+        let profile = self
+            .ctx
+            .simulator
+            .as_ref()
+            .and_then(|s| s.profile(&dev.uuid));
+        let transfer_engine: Box<dyn TransferEngine> = match profile {
+            Some(profile) => Box::new(SimulatedEngine::from_profile(&profile)),
+            None => transfer_engine::create_engine(
+                self.ctx.config.transfer_engine.clone(),
+                &self.ctx.config,
+            ),
         };
 
-        let transfer_engine =
-            transfer_engine::create_engine(self.ctx.config.transfer_engine.clone());
+        // A queued job is started once nothing holds it up; until then,
+        // seeing its card again (a repeated event) changes nothing
+        let held = self.held_for(&dev);
+        let queued = match held {
+            Some(reason) if self.requeue(&dev.uuid, reason).await => return,
+            Some(_) => None,
+            None => self.queued.lock().await.remove(&dev.uuid),
+        };
 
-        if let Err(e) = db::jobs::create(
-            &self.ctx.db,
-            job_id.clone(),
-            target_drive,
-            destination.to_string_lossy().to_string(),
-        )
-        .await
+        // Untrusted cards are staged in the quarantine directory, so are never
+        // resumed into (or hardlinked from) the backup tree
+        let quarantine = quarantine::for_device(&self.ctx.config, &dev.uuid, &dev.label);
+        let resumable = match (queued, &quarantine) {
+            (Some(queued), _) => {
+                let _ = self
+                    .ctx
+                    .db
+                    .update_status(
+                        queued.job_id.clone(),
+                        "Ready".to_string(),
+                        Some(
+                            match queued.reason {
+                                QueueReason::Window => "Backup window open, starting",
+                                QueueReason::Partition => {
+                                    "Previous partition of the card done, starting"
+                                }
+                                QueueReason::Space => "Backup space freed, starting",
+                            }
+                            .to_string(),
+                        ),
+                        None,
+                        None,
+                    )
+                    .await;
+                Some((queued.job_id, queued.destination, queued.completed))
+            }
+            (None, Some(_)) => None,
+            (None, None) => self.find_resumable(&dev).await,
+        };
+
+        // Plugging in the backup drive itself must not start a job that
+        // copies the backup directory into itself
+        if let Err(e) =
+            destination::check_outside_source(&dev.mount_point, &self.ctx.config.backup_directory)
         {
-            error!(error = %e, "Failed to create job in database");
+            error!(error = %e, "Not backing up device");
             return;
         }
 
-        info!(
-            job_id = %job_id,
-            source = %dev.mount_point.display(),
-            destination = %destination.display(),
-            "Job created"
-        );
+        // A card reinserted unchanged since its last backup isn't copied again
+        let fingerprint = self.fingerprint(&dev).await;
+        if resumable.is_none()
+            && let Some(fingerprint) = &fingerprint
+            && let Some(backup) = self.unchanged_since(&dev, fingerprint).await
+        {
+            self.record_unchanged(&dev, fingerprint, &backup).await;
+            return;
+        }
+
+        let label_fields = self.job_fields(&dev).await;
+        let (job_id, destination, completed) = match resumable {
+            Some(resume) => resume,
+            None => {
+                let job_id = uuid::Uuid::now_v7().to_string();
+                let hostname = destination::hostname();
+                let destination = self.build_destination(&dev.label, &hostname, &label_fields);
+                if let Err(e) = destination::check_outside_source(&dev.mount_point, &destination) {
+                    error!(error = %e, "Not backing up device");
+                    return;
+                }
+
+                let target_drive = TargetDrive {
+                    uuid: dev.uuid.clone(),
+                    label: dev.label.clone(),
+                    mount_path: dev.mount_point.to_string_lossy().to_string(),
+                    raw_size: dev.capacity,
+                };
+
+                if let Err(e) = self
+                    .ctx
+                    .db
+                    .create_job(
+                        job_id.clone(),
+                        target_drive,
+                        destination.to_string_lossy().to_string(),
+                        hostname,
+                        label_fields.clone(),
+                    )
+                    .await
+                {
+                    error!(error = %e, "Failed to create job in database");
+                    return;
+                }
+
+                info!(
+                    job_id = %job_id,
+                    source = %dev.mount_point.display(),
+                    destination = %destination.display(),
+                    "Job created"
+                );
+
+                (job_id, destination, Vec::new())
+            }
+        };
+
+        if let Some(reason) = held {
+            self.queue(dev, job_id, destination, completed, reason)
+                .await;
+            return;
+        }
+
+        let filter = match PathFilter::for_device(&self.ctx.config, &dev) {
+            Ok(filter) => filter,
+            Err(e) => {
+                error!(job_id = %job_id, error = %e, "Invalid file filter");
+                let _ = self
+                    .ctx
+                    .db
+                    .update_status(
+                        job_id,
+                        "failed".to_string(),
+                        Some(format!("Invalid file filter: {:#}", e)),
+                        None,
+                        None,
+                    )
+                    .await;
+                return;
+            }
+        };
+
+        let backend = match destination::for_device(&self.ctx.config, &dev.uuid, &dev.label) {
+            Ok(backend) => backend,
+            Err(e) => {
+                error!(job_id = %job_id, error = %e, "Invalid destination");
+                let _ = self
+                    .ctx
+                    .db
+                    .update_status(
+                        job_id,
+                        "failed".to_string(),
+                        Some(format!("Invalid destination: {:#}", e)),
+                        None,
+                        None,
+                    )
+                    .await;
+                return;
+            }
+        };
+
+        // Partitions of one card are backed up one at a time
+        let disk = match dev.is_upload() || dev.is_manual() {
+            true => None,
+            false => hardware::parent_disk(&dev.path),
+        };
+        if let Some(disk) = &disk
+            && !self.partitions.claim(disk, &dev.uuid).await
+        {
+            self.queue(dev, job_id, destination, completed, QueueReason::Partition)
+                .await;
+            return;
+        }
 
         // Send "Started" notification
         if let Some(ref notifier) = self.ctx.notifier {
@@ -125,6 +840,7 @@ impl Orchestrator {
                 device_uuid: dev.uuid.clone(),
                 source: dev.mount_point.clone(),
                 destination: destination.clone(),
+                label_fields: label_fields.clone(),
             };
             let notifier = notifier.clone();
             tokio::spawn(async move {
@@ -134,20 +850,104 @@ impl Orchestrator {
             });
         }
 
-        let transfer_req = TransferRequest {
+        // Record each copied file so the job can be resumed if interrupted
+        let (file_log, mut file_log_rx) = mpsc::unbounded_channel();
+        let manifest_db = self.ctx.db.clone();
+        let manifest_job_id = job_id.clone();
+        tokio::spawn(async move {
+            while let Some(file) = file_log_rx.recv().await {
+                if let Err(e) = manifest_db.record_file(manifest_job_id.clone(), file).await {
+                    warn!(job_id = %manifest_job_id, error = %e, "Failed to record copied file");
+                }
+            }
+        });
+
+        // Incremental mode: files this card already had backed up are skipped
+        let catalog = if self.ctx.config.incremental
+            && self.ctx.config.transfer_engine == TransferEngineType::NativeCopy
+        {
+            self.ctx
+                .db
+                .catalog_lookup(dev.uuid.clone())
+                .await
+                .unwrap_or_else(|e| {
+                    warn!(error = %e, "Failed to read device catalog, copying everything");
+                    Vec::new()
+                })
+        } else {
+            Vec::new()
+        };
+
+        let link_dest = if self.ctx.config.hardlink_unchanged
+            && quarantine.is_none()
+            && backend.capabilities().supports_hardlink
+        {
+            self.find_link_dest(&dev.uuid, &job_id).await
+        } else {
+            None
+        };
+
+        if let Some(quarantine) = &quarantine {
+            info!(
+                job_id = %job_id,
+                staging = %quarantine.staging(&job_id).display(),
+                "Quarantine ingest"
+            );
+        }
+
+        let progress_tracker = self.ctx.progress.clone();
+        let copy_destination = match &quarantine {
+            Some(quarantine) => quarantine.staging(&job_id),
+            None => partial::partial_path(&destination),
+        };
+        // A fresh destination becomes its own subvolume; if that fails the
+        // engine creates a plain directory as usual
+        if self.ctx.subvolumes
+            && !copy_destination.exists()
+            && let Err(e) = subvolume::create(&copy_destination).await
+        {
+            warn!(job_id = %job_id, error = %e, "Failed to create backup subvolume");
+        }
+
+        let controls = progress_tracker
+            .register(
+                &job_id,
+                JobInfo {
+                    device_label: dev.label.clone(),
+                    device_uuid: dev.uuid.clone(),
+                    source: dev.mount_point.clone(),
+                    destination: copy_destination.clone(),
+                },
+            )
+            .await;
+
+        let mut transfer_req = TransferRequest {
             job_id: job_id.clone(),
             source: dev.mount_point.clone(),
-            destination,
-            owner: get_backup_owner(&self.ctx.config.backup_directory),
+            device: Some(dev.path.clone()),
+            destination: copy_destination,
+            owner: match quarantine {
+                Some(_) => None,
+                None => get_backup_owner(&self.ctx.config.backup_directory),
+            },
+            completed,
+            file_log: Some(file_log),
+            filter,
+            backed_up: catalog.iter().map(|entry| entry.file.clone()).collect(),
+            link_dest,
+            cancel: controls.cancel.clone(),
+            pause: controls.pause.clone(),
         };
 
         let (progress_tx, mut progress_rx) = mpsc::channel(100);
         let db = self.ctx.db.clone();
         let adapter = self.adapter.clone();
-        let progress_tracker = self.ctx.progress.clone();
 
         // Progress throttle: only log every 500ms
         let throttle = LogThrottle::new(Duration::from_millis(500));
+        // Progress snapshots written to the status log, so a crash leaves a
+        // record of how far the job got
+        let snapshot_interval = Duration::from_secs(self.ctx.config.progress_snapshot_secs);
 
         let job_span = info_span!(
             "job",
@@ -158,54 +958,202 @@ impl Orchestrator {
 
         let config = self.ctx.config.clone();
         let notifier = self.ctx.notifier.clone();
+        let failed_destination = self.ctx.config.failed_destination;
         let device_label = dev.label.clone();
+        let target_id = dev.uuid.clone();
         let job_id_for_consumer = job_id.clone();
+        let db_for_transfer = self.ctx.db.clone();
+        let signing_key = self.ctx.signing_key.clone();
+        // Per-device actions for after the backup completes
+        let on_complete = completion::actions_for(&self.ctx.config, &dev.uuid, &dev.label);
+        // Only a full verification vouches for the copied files, only a
+        // backup of the whole card holds every file on it, and a manually
+        // backed up directory is never bksd's to empty
+        let wipe_refusal = if !self.ctx.config.verify_transfers
+            || self.ctx.config.verify_mode != VerifyMode::Full
+            || self.ctx.config.simulation
+            || dev.is_manual()
+        {
+            Some("transfers are not fully verified")
+        } else if transfer_req.filter.narrows() {
+            Some("filters or source_paths left files out of the backup")
+        } else {
+            None
+        };
+        let completed_job = CompletedJob {
+            job_id: job_id.clone(),
+            device_label: dev.label.clone(),
+            device_uuid: dev.uuid.clone(),
+            source: transfer_req.source.clone(),
+            destination,
+            total_bytes: 0,
+        };
+        let scanned_job = completed_job.clone();
+        // Keep the machine awake until the consumer sees the job finish
+        let inhibit_guard = self.ctx.inhibitor.acquire();
+        let upload = dev.is_upload().then(|| dev.clone());
+        let partitions = self.partitions.clone();
+        let mut disk = disk;
+        let transfer_controls = controls.clone();
 
         // Spawn transfer task
         tokio::spawn(async move {
-            let transfer_result = transfer_engine
-                .transfer(&transfer_req, progress_tx.clone())
+            // Hold an upload to the manifest its sender hashed it with
+            if let Some(upload) = &upload
+                && let Some(check) = upload::check_manifest(upload).await
+            {
+                let failure = match check {
+                    Ok(check) => {
+                        info!(
+                            job_id = %job_id,
+                            verified = check.verified,
+                            mismatched = check.mismatched.len(),
+                            "Upload checked against manifest"
+                        );
+                        record_notices(db_for_transfer.as_ref(), &job_id, check.notices.clone())
+                            .await;
+                        check.failure()
+                    }
+                    Err(e) => Some(format!("Failed to check upload manifest: {:#}", e)),
+                };
+                if let Some(failure) = failure {
+                    warn!(job_id = %job_id, error = %failure, "Upload failed manifest check");
+                    let _ = progress_tx.send(TransferStatus::Failed(failure)).await;
+                    return;
+                }
+            }
+
+            // Flag files that look like failed in-camera writes before copying
+            if config.detect_anomalies {
+                let notices = anomalies::detect(&transfer_req.source).await;
+                for notice in &notices {
+                    warn!(
+                        job_id = %job_id,
+                        kind = %notice.kind,
+                        file = notice.path.as_deref().unwrap_or(""),
+                        "Source anomaly detected"
+                    );
+                }
+                record_notices(db_for_transfer.as_ref(), &job_id, notices).await;
+            }
+
+            let transfer_result = transfer_with_retries(
+                transfer_engine.as_ref(),
+                &mut transfer_req,
+                &progress_tx,
+                db_for_transfer.as_ref(),
+                &config,
+            )
+            .await;
+
+            if transfer_req.cancel.is_cancelled() {
+                stop_transfer(
+                    db_for_transfer.as_ref(),
+                    &transfer_req,
+                    &transfer_controls,
+                    &progress_tx,
+                )
                 .await;
+                return;
+            }
 
             match transfer_result {
                 Ok(result) => {
                     let _ = progress_tx.send(TransferStatus::CopyComplete).await;
+                    record_copy_notices(db_for_transfer.as_ref(), &transfer_req, &result, &catalog)
+                        .await;
 
-                    // Verify if enabled and we have file hashes from the transfer
-                    let verification_passed = if config.verify_transfers && !config.simulation {
-                        match &result.file_hashes {
-                            Some(hashes) => {
-                                // Fast path: verify using hashes computed during copy
-                                match verify_from_hashes(&job_id, &transfer_req.destination, hashes)
-                                    .await
-                                {
-                                    Ok(_) => true,
-                                    Err(e) => {
-                                        let _ = progress_tx
-                                            .send(TransferStatus::Failed(e.to_string()))
-                                            .await;
-                                        false
-                                    }
-                                }
-                            }
-                            None => {
-                                // Engine handles verification internally (e.g., rsync --checksum)
-                                // or doesn't support it (simulated) - trust the transfer
-                                true
-                            }
-                        }
-                    } else {
-                        true
+                    let (verified, verify_bytes_per_sec) = verify_copy(
+                        &config,
+                        db_for_transfer.as_ref(),
+                        &transfer_req,
+                        &result,
+                        &progress_tx,
+                    )
+                    .await;
+
+                    if transfer_req.cancel.is_cancelled() {
+                        stop_transfer(
+                            db_for_transfer.as_ref(),
+                            &transfer_req,
+                            &transfer_controls,
+                            &progress_tx,
+                        )
+                        .await;
+                        return;
+                    }
+
+                    // Either has failed the job already if it returns false
+                    if !verified
+                        || !place_copy(
+                            quarantine.as_ref(),
+                            &transfer_req,
+                            &scanned_job,
+                            &progress_tx,
+                        )
+                        .await
+                    {
+                        return;
+                    }
+
+                    let backup = VerifiedBackup {
+                        config: &config,
+                        db: db_for_transfer.as_ref(),
+                        job_id: &job_id,
+                        destination: &scanned_job.destination,
+                        result: &result,
                     };
+                    backup.probe_videos().await;
+                    backup
+                        .write_parity(&progress_tx, &transfer_req.cancel)
+                        .await;
+                    backup.write_badge(signing_key).await;
+                    if !backup
+                        .deliver(backend.as_ref(), &scanned_job, &progress_tx)
+                        .await
+                    {
+                        return;
+                    }
+                    backup.snapshot().await;
+                    // The rest protects and copies the local backup, if kept
+                    let local_kept = backend.keeps_local();
+                    if local_kept {
+                        backup.make_immutable().await;
+                        backup.seal_subvolume().await;
+                    }
+                    backup.remember(&target_id, fingerprint.as_ref()).await;
 
-                    if verification_passed {
-                        let _ = progress_tx
-                            .send(TransferStatus::Complete {
-                                total_bytes: result.total_bytes,
-                                duration_secs: result.duration_secs,
-                            })
-                            .await;
+                    // Further copies, each verified on its own. Cancelling
+                    // now only skips the mirrors; the backup itself is kept
+                    if local_kept {
+                        mirror::copy_all(
+                            &config,
+                            db_for_transfer.as_ref(),
+                            &job_id,
+                            &scanned_job.destination,
+                            &transfer_req.cancel,
+                            &transfer_req.pause,
+                        )
+                        .await;
                     }
+
+                    let _ = progress_tx
+                        .send(TransferStatus::Complete {
+                            total_bytes: result.total_bytes,
+                            duration_secs: result.duration_secs,
+                            stats: TransferStats {
+                                avg_bytes_per_sec: result.avg_bytes_per_sec(),
+                                peak_bytes_per_sec: 0,
+                                verify_bytes_per_sec,
+                                retried_files: result.retried.len(),
+                                files_copied: result.files_copied,
+                                files_skipped: result.files_skipped(),
+                                files_failed: result.files_failed,
+                                files_rescued: result.rescued.len(),
+                                largest_file: result.largest_file.clone(),
+                            },
+                        })
+                        .await;
                 }
                 Err(e) => {
                     error!(job_id = %job_id, error = %e, "Transfer error");
@@ -219,12 +1167,20 @@ impl Orchestrator {
         // Spawn progress consumer task
         tokio::spawn(
             async move {
+                let _inhibit_guard = inhibit_guard;
+                let mut last_snapshot: Option<(&str, Stopwatch)> = None;
+                let clock = Stopwatch::start();
+                let mut peak = PeakRate::default();
                 while let Some(status) = progress_rx.recv().await {
+                    if let TransferStatus::InProgress { bytes_copied, .. } = &status {
+                        peak.record(clock.elapsed(), *bytes_copied);
+                    }
+
                     // Log progress with throttling
-                    if let TransferStatus::InProgress { percentage, .. } = &status {
-                        if throttle.should_log() {
-                            info!(percentage = %percentage, "Transfer progress");
-                        }
+                    if let TransferStatus::InProgress { percentage, .. } = &status
+                        && throttle.should_log()
+                    {
+                        info!(percentage = %percentage, "Transfer progress");
                     }
 
                     // Update in-memory tracker
@@ -232,71 +1188,236 @@ impl Orchestrator {
                         .update(&job_id_for_consumer, status.clone())
                         .await;
 
-                    // Persist and notify based on status
-                    match &status {
-                        TransferStatus::CopyComplete => {
-                            let _ = db::jobs::update_status(
-                                &db,
+                    // Snapshot on entering copying/verifying (which also marks
+                    // phase boundaries for jobs.timeline), then periodically
+                    if let Some((state, description)) = progress_snapshot(&status)
+                        && last_snapshot.is_none_or(|(last_state, taken)| {
+                            last_state != state
+                                || (!snapshot_interval.is_zero()
+                                    && taken.elapsed() >= snapshot_interval)
+                        })
+                    {
+                        last_snapshot = Some((state, Stopwatch::start()));
+                        let _ = db
+                            .update_status(
                                 job_id_for_consumer.clone(),
-                                "copy_complete".to_string(),
-                                None,
+                                state.to_string(),
+                                Some(description),
                                 None,
                                 None,
                             )
                             .await;
+                    }
+
+                    // Persist and notify based on status
+                    match &status {
+                        TransferStatus::CopyComplete => {
+                            let _ = db
+                                .update_status(
+                                    job_id_for_consumer.clone(),
+                                    "copy_complete".to_string(),
+                                    None,
+                                    None,
+                                    None,
+                                )
+                                .await;
                         }
                         TransferStatus::Complete {
                             total_bytes,
                             duration_secs,
+                            stats,
                         } => {
-                            let _ = db::jobs::update_status(
-                                &db,
-                                job_id_for_consumer.clone(),
-                                "complete".to_string(),
-                                None,
-                                Some(*total_bytes),
-                                Some(*duration_secs),
-                            )
-                            .await;
+                            // Too short a copy to measure a peak over still
+                            // ran at least as fast as its average
+                            let stats = TransferStats {
+                                peak_bytes_per_sec: peak.peak().max(stats.avg_bytes_per_sec),
+                                ..stats.clone()
+                            };
+                            info!(
+                                summary = %stats.summary(),
+                                files = %stats.files_summary(),
+                                "Job statistics"
+                            );
+
+                            let _ = db
+                                .record_completion(
+                                    job_id_for_consumer.clone(),
+                                    format!("{}; {}", stats.summary(), stats.files_summary()),
+                                    *total_bytes,
+                                    *duration_secs,
+                                    stats.clone(),
+                                )
+                                .await;
+
+                            let notices = db
+                                .list_notices(job_id_for_consumer.clone())
+                                .await
+                                .unwrap_or_default();
+                            let unreadable = stats.files_failed > 0;
+                            let rescued = stats.files_rescued > 0;
+                            let event = JobEvent::Completed {
+                                job_id: job_id_for_consumer.clone(),
+                                device_label: device_label.clone(),
+                                label_fields: label_fields.clone(),
+                                total_bytes: *total_bytes,
+                                duration_secs: *duration_secs,
+                                stats,
+                                notices,
+                            };
+
+                            // One notification per card, once its last
+                            // partition is done
+                            let card_event = match disk.take() {
+                                Some(disk) => partitions
+                                    .finish(&disk, Some(event.clone()))
+                                    .await
+                                    .and_then(partitions::combine),
+                                None => Some(event.clone()),
+                            };
+                            let last_partition = card_event.is_some();
 
                             // Send completion notification
-                            if let Some(ref notifier) = notifier {
-                                let event = JobEvent::Completed {
-                                    job_id: job_id_for_consumer.clone(),
-                                    device_label: device_label.clone(),
-                                    total_bytes: *total_bytes,
-                                    duration_secs: *duration_secs,
+                            if let Some(ref notifier) = notifier
+                                && let Some(card_event) = card_event
+                                && let Err(e) = notifier.notify(card_event).await
+                            {
+                                warn!(error = %e, "Failed to send completion notification");
+                            }
+
+                            // Device rule actions, while the card is still mounted
+                            let job = CompletedJob {
+                                total_bytes: *total_bytes,
+                                ..completed_job.clone()
+                            };
+                            // Nor if the copy left out files it couldn't read
+                            let wipe_refusal = wipe_refusal
+                                .or(unreadable
+                                    .then_some("unreadable files were left out of the backup"))
+                                .or(rescued.then_some(
+                                    "files were copied with unreadable ranges zero-filled",
+                                ));
+                            let actions_ok =
+                                match completion::run(&on_complete, &job, &event, wipe_refusal)
+                                    .await
+                                {
+                                    Ok(()) => true,
+                                    Err(e) => {
+                                        warn!(error = %e, "Completion action failed");
+                                        let notice = JobNotice {
+                                            kind: "completion_action_failed".to_string(),
+                                            path: None,
+                                            message: e.to_string(),
+                                        };
+                                        record_notices(
+                                            db.as_ref(),
+                                            &job_id_for_consumer,
+                                            vec![notice],
+                                        )
+                                        .await;
+                                        false
+                                    }
                                 };
-                                if let Err(e) = notifier.notify(event).await {
-                                    warn!(error = %e, "Failed to send completion notification");
-                                }
+
+                            // An upload has no device to release, just its staging copy
+                            if dev.is_upload() {
+                                upload::remove_staging(&dev).await;
+                                progress_tracker.remove(&job_id_for_consumer).await;
+                                break;
+                            }
+
+                            // A manually backed up directory was mounted by someone else
+                            if dev.is_manual() {
+                                progress_tracker.remove(&job_id_for_consumer).await;
+                                break;
                             }
 
                             // Cleanup: unmount device if we mounted it
-                            if let Err(e) = adapter.cleanup_device(&dev) {
-                                error!(error = %e, "Failed to cleanup device");
+                            let cleaned_up = match adapter.cleanup_device(&dev) {
+                                Ok(()) => true,
+                                Err(e) => {
+                                    error!(error = %e, "Failed to cleanup device");
+                                    false
+                                }
+                            };
+
+                            if actions_ok && cleaned_up && completion::wants_eject(&on_complete) {
+                                if !last_partition {
+                                    info!("Not ejecting yet, other partitions of the card are still to be backed up");
+                                } else if let Err(e) = adapter.eject_device(&dev) {
+                                    warn!(error = %e, "Failed to eject device");
+                                }
                             }
 
                             progress_tracker.remove(&job_id_for_consumer).await;
                             break;
                         }
+                        // Shutting down; the job is marked interrupted once
+                        // every job has stopped
+                        TransferStatus::Failed(_) | TransferStatus::Cancelled
+                            if controls.interrupted() =>
+                        {
+                            info!("Job stopped by shutdown, keeping partial copy");
+                            progress_tracker.remove(&job_id_for_consumer).await;
+                            break;
+                        }
+                        // Engines report the abort as a failure of their own;
+                        // a removed card fails the job instead
+                        TransferStatus::Failed(_) | TransferStatus::Cancelled
+                            if controls.cancel.is_cancelled() && !controls.device_removed() =>
+                        {
+                            info!("Job cancelled");
+                            let _ = db
+                                .update_status(
+                                    job_id_for_consumer.clone(),
+                                    "cancelled".to_string(),
+                                    Some("Cancelled on request; partial copy removed".to_string()),
+                                    None,
+                                    None,
+                                )
+                                .await;
+                            progress_tracker.remove(&job_id_for_consumer).await;
+                            break;
+                        }
                         TransferStatus::Failed(error) => {
-                            let _ = db::jobs::update_status(
-                                &db,
-                                job_id_for_consumer.clone(),
-                                "failed".to_string(),
-                                Some(error.clone()),
-                                None,
-                                None,
-                            )
-                            .await;
+                            // Whatever the engine made of its source vanishing
+                            let removed = failure::DEVICE_REMOVED.to_string();
+                            let error = if controls.device_removed() {
+                                &removed
+                            } else {
+                                error
+                            };
+                            // Don't leave the partial copy looking like a
+                            // backup; a folder that was there before the job
+                            // isn't its to move
+                            let error = match FailureKind::classify(error) {
+                                FailureKind::DestinationExists => error.clone(),
+                                _ => match partial::set_aside(
+                                    failed_destination,
+                                    &completed_job.destination,
+                                )
+                                .await
+                                {
+                                    Some(outcome) => format!("{}; {}", error, outcome),
+                                    None => error.clone(),
+                                },
+                            };
+                            let _ = db
+                                .update_status(
+                                    job_id_for_consumer.clone(),
+                                    "failed".to_string(),
+                                    Some(error.clone()),
+                                    None,
+                                    None,
+                                )
+                                .await;
 
                             // Send failure notification
                             if let Some(ref notifier) = notifier {
                                 let event = JobEvent::Failed {
                                     job_id: job_id_for_consumer.clone(),
                                     device_label: device_label.clone(),
-                                    error: error.clone(),
+                                    hint: failure::hint(&error).map(str::to_string),
+                                    error,
                                 };
                                 if let Err(e) = notifier.notify(event).await {
                                     warn!(error = %e, "Failed to send failure notification");
@@ -309,12 +1430,1168 @@ impl Orchestrator {
                         _ => {}
                     }
                 }
+
+                // A failed partition still lets the next one go, and the last
+                // reports the ones that completed before it
+                if let Some(disk) = disk
+                    && let Some(event) = partitions.finish(&disk, None).await.and_then(partitions::combine)
+                    && let Some(ref notifier) = notifier
+                    && let Err(e) = notifier.notify(event).await
+                {
+                    warn!(error = %e, "Failed to send completion notification");
+                }
             }
             .instrument(job_span),
         );
     }
 
+    /// Move aged backups onto the archive drive, then release it.
+    fn handle_archive_drive(&self, dev: BlockDevice) {
+        let db = self.ctx.db.clone();
+        let config = self.ctx.config.clone();
+        let adapter = self.adapter.clone();
+        let span = info_span!("archive", label = %dev.label);
+
+        tokio::spawn(
+            async move {
+                if let Err(e) = db
+                    .mark_connected(dev.uuid.clone(), dev.label.clone(), dev.capacity)
+                    .await
+                {
+                    warn!(error = %e, "Failed to record archive drive");
+                }
+
+                match archive::run_tiering(db.as_ref(), &config, &dev).await {
+                    Ok(summary) => info!(
+                        archived = summary.archived,
+                        failed = summary.failed,
+                        bytes_moved = summary.bytes_moved,
+                        "Archive tiering complete"
+                    ),
+                    Err(e) => error!(error = %e, "Archive tiering failed"),
+                }
+
+                if let Err(e) = adapter.cleanup_device(&dev) {
+                    error!(error = %e, "Failed to cleanup archive drive");
+                }
+            }
+            .instrument(span),
+        );
+    }
+
     async fn handle_device_removed(&self, uuid: String) {
         info!(uuid = %uuid, "Device removed");
+        self.ctx.ignored_devices.remove(&uuid).await;
+
+        self.partitions.forget(&uuid).await;
+        for job_id in self.ctx.progress.device_removed(&uuid).await {
+            warn!(job_id = %job_id, "Device removed mid-transfer, stopping its job");
+        }
+        if let Some(queued) = self.queued.lock().await.remove(&uuid) {
+            info!(job_id = %queued.job_id, "Queued card removed, not backing it up");
+            let _ = self
+                .ctx
+                .db
+                .update_status(
+                    queued.job_id,
+                    "cancelled".to_string(),
+                    Some(
+                        match queued.reason {
+                            QueueReason::Window => "Card removed before the backup window opened",
+                            QueueReason::Partition => "Card removed before this partition's turn",
+                            QueueReason::Space => "Card removed while waiting for backup space",
+                        }
+                        .to_string(),
+                    ),
+                    None,
+                    None,
+                )
+                .await;
+        }
+
+        if let Err(e) = self.ctx.db.mark_disconnected(uuid).await {
+            warn!(error = %e, "Failed to update archive drive state");
+        }
+    }
+}
+
+/// Run a transfer, retrying a failure another attempt could get past up to
+/// `retry_attempts` times, waiting `retry_backoff_secs` and doubling it each
+/// time. Each retry is logged to the job's status log. A native copy picks
+/// up from its manifest; other engines start over once the partial copy is
+/// removed.
+async fn transfer_with_retries(
+    engine: &dyn TransferEngine,
+    req: &mut TransferRequest,
+    progress_tx: &mpsc::Sender<TransferStatus>,
+    db: &dyn Store,
+    config: &AppConfig,
+) -> Result<TransferResult> {
+    let mut attempt: u32 = 0;
+    loop {
+        // Engines report their failure as a status too; hold it back, as
+        // the caller reports the last attempt's and the rest are retried
+        let (tx, mut rx) = mpsc::channel(100);
+        let forward_tx = progress_tx.clone();
+        let forward = tokio::spawn(async move {
+            while let Some(status) = rx.recv().await {
+                if !matches!(status, TransferStatus::Failed(_)) {
+                    let _ = forward_tx.send(status).await;
+                }
+            }
+        });
+        let result = engine.transfer(req, tx).await;
+        let _ = forward.await;
+
+        let error = match result {
+            Ok(result) => return Ok(result),
+            Err(e) => e,
+        };
+        if attempt >= config.retry_attempts
+            || req.cancel.is_cancelled()
+            || FailureKind::classify(&error.to_string()).is_permanent()
+        {
+            return Err(error);
+        }
+
+        attempt += 1;
+        let delay = Duration::from_secs(
+            config
+                .retry_backoff_secs
+                .saturating_mul(1 << (attempt - 1).min(10)),
+        );
+        warn!(
+            job_id = %req.job_id,
+            attempt,
+            error = %error,
+            retry_in_secs = delay.as_secs(),
+            "Transfer failed, retrying"
+        );
+        let _ = db
+            .update_status(
+                req.job_id.clone(),
+                "retrying".to_string(),
+                Some(format!(
+                    "Attempt {} of {} failed: {}; retrying in {}s",
+                    attempt,
+                    config.retry_attempts + 1,
+                    error,
+                    delay.as_secs()
+                )),
+                None,
+                None,
+            )
+            .await;
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = req.cancel.cancelled() => return Err(error),
+        }
+
+        req.completed = if config.transfer_engine == TransferEngineType::NativeCopy {
+            db.list_files(req.job_id.clone()).await.unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        if req.completed.is_empty() {
+            discard_partial(db, req).await;
+        }
+    }
+}
+
+/// End a transfer its cancel token stopped. A removed card fails the job
+/// and keeps what was copied, to resume from when it is reinserted, as does
+/// a shutdown; a cancel request throws the partial copy away.
+async fn stop_transfer(
+    db: &dyn Store,
+    req: &TransferRequest,
+    controls: &JobControls,
+    progress_tx: &mpsc::Sender<TransferStatus>,
+) {
+    if controls.interrupted() {
+        let _ = progress_tx.send(TransferStatus::Cancelled).await;
+    } else if controls.device_removed() {
+        let _ = progress_tx
+            .send(TransferStatus::Failed(failure::DEVICE_REMOVED.to_string()))
+            .await;
+    } else {
+        discard_partial(db, req).await;
+        let _ = progress_tx.send(TransferStatus::Cancelled).await;
+    }
+}
+
+/// Remove what a cancelled or failed attempt copied, and its manifest so
+/// nothing tries to resume from it.
+async fn discard_partial(db: &dyn Store, req: &TransferRequest) {
+    match tokio::fs::remove_dir_all(&req.destination).await {
+        Ok(()) => info!(destination = %req.destination.display(), "Removed partial copy"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => warn!(
+            destination = %req.destination.display(),
+            error = %e,
+            "Failed to remove partial copy"
+        ),
+    }
+    if let Err(e) = db.clear_files(req.job_id.clone()).await {
+        warn!(job_id = %req.job_id, error = %e, "Failed to clear file manifest");
+    }
+}
+
+/// Status log entry recording how far an in-flight job has got, as
+/// (status, description).
+fn progress_snapshot(status: &TransferStatus) -> Option<(&'static str, String)> {
+    match status {
+        TransferStatus::InProgress {
+            total_bytes,
+            bytes_copied,
+            percentage,
+            ..
+        } => Some((
+            "in_progress",
+            format!(
+                "Copied {:.1} of {:.1} MB ({}%)",
+                *bytes_copied as f64 / (1024.0 * 1024.0),
+                *total_bytes as f64 / (1024.0 * 1024.0),
+                percentage
+            ),
+        )),
+        TransferStatus::Verifying {
+            current,
+            total,
+            bytes_verified,
+            total_bytes,
+            ..
+        } => Some((
+            "verifying",
+            if *total_bytes > 0 {
+                format!(
+                    "Verified {:.1} of {:.1} MB ({} of {} files)",
+                    *bytes_verified as f64 / (1024.0 * 1024.0),
+                    *total_bytes as f64 / (1024.0 * 1024.0),
+                    current,
+                    total
+                )
+            } else {
+                format!("Verified {} of {} files", current, total)
+            },
+        )),
+        TransferStatus::GeneratingParity { percentage } => Some((
+            "generating_parity",
+            format!("Generated {}% of parity data", percentage),
+        )),
+        _ => None,
+    }
+}
+
+/// Attach `notices` to a job. Failing to record them doesn't fail the job.
+async fn record_notices(db: &dyn Store, job_id: &str, notices: Vec<JobNotice>) {
+    if notices.is_empty() {
+        return;
+    }
+    if let Err(e) = db.add_notices(job_id.to_string(), notices).await {
+        warn!(job_id = %job_id, error = %e, "Failed to record notices");
+    }
+}
+
+/// Note what a copy did besides copying: files it left out as unchanged,
+/// hardlinked, retried, rescued or renamed.
+async fn record_copy_notices(
+    db: &dyn Store,
+    req: &TransferRequest,
+    result: &TransferResult,
+    catalog: &[CatalogEntry],
+) {
+    if !result.skipped.is_empty() {
+        record_skipped(db, &req.job_id, catalog, &result.skipped).await;
+    }
+
+    let mut notices = Vec::new();
+    if result.linked > 0
+        && let Some(link_dest) = &req.link_dest
+    {
+        notices.push(JobNotice {
+            kind: "hardlinked_files".to_string(),
+            path: None,
+            message: format!(
+                "Hardlinked {} unchanged file(s) from {}",
+                result.linked,
+                link_dest.directory.display()
+            ),
+        });
+    }
+    // Files a flaky reader only gave up on a later attempt
+    notices.extend(result.retried.iter().map(|file| JobNotice {
+        kind: "retried_file".to_string(),
+        path: Some(file.relative_path.to_string_lossy().to_string()),
+        message: format!("Copied after {} attempts", file.attempts),
+    }));
+    // Parts of files a failing card wouldn't give up
+    notices.extend(result.rescued.iter().map(|file| JobNotice {
+        kind: "rescued_file".to_string(),
+        path: Some(file.relative_path.to_string_lossy().to_string()),
+        message: format!(
+            "{} byte(s) in {} range(s) couldn't be read and were left as zeros (see {})",
+            file.missing_bytes(),
+            file.missing.len(),
+            transfer_engine::RESCUE_MAP_NAME
+        ),
+    }));
+    // Entries renamed so the destination could hold them
+    notices.extend(result.renamed.iter().map(|rename| JobNotice {
+        kind: "renamed_file".to_string(),
+        path: Some(rename.from.to_string_lossy().to_string()),
+        message: format!("Saved as {}", rename.to.display()),
+    }));
+    record_notices(db, &req.job_id, notices).await;
+}
+
+/// Check a copy against the hashes its engine took while copying. Engines
+/// that verify their own output (rsync, archive, image) or can't
+/// (simulated) are trusted. Returns whether the copy passed and, if it was
+/// read back, how fast. A failed check fails the job; a cancelled one is
+/// left for the caller to stop.
+async fn verify_copy(
+    config: &AppConfig,
+    db: &dyn Store,
+    req: &TransferRequest,
+    result: &TransferResult,
+    progress_tx: &mpsc::Sender<TransferStatus>,
+) -> (bool, Option<u64>) {
+    let job_id = &req.job_id;
+    let Some(all_hashes) = result
+        .file_hashes
+        .as_ref()
+        .filter(|_| config.verify_transfers && !config.simulation)
+    else {
+        return (true, None);
+    };
+
+    // Sampling checks part of a big card; say so in the job log, as it
+    // isn't a full verification
+    let sampled = (config.verify_mode == VerifyMode::Sample)
+        .then(|| verifier::sample(job_id, all_hashes, config.verify_sample_percent));
+    let hashes = sampled.as_deref().unwrap_or(all_hashes);
+    if sampled.is_some() {
+        let description = format!(
+            "Sampled verification: checking {} of {} files ({}%, largest first)",
+            hashes.len(),
+            all_hashes.len(),
+            config.verify_sample_percent.clamp(1, 100)
+        );
+        info!(job_id = %job_id, "{}", description);
+        let _ = db
+            .update_status(
+                job_id.clone(),
+                "verifying".to_string(),
+                Some(description),
+                None,
+                None,
+            )
+            .await;
+    }
+
+    // Fast path: verify using hashes computed during copy, re-reading the
+    // card too if paranoid
+    let progress = || ProgressSender {
+        tx: progress_tx.clone(),
+        interval: ProgressInterval::from_config(config),
+    };
+    let verify_time = Stopwatch::start();
+    let verified = if config.paranoid_verify {
+        let moved: Vec<_> = result
+            .renamed
+            .iter()
+            .chain(&result.relocated)
+            .cloned()
+            .collect();
+        verify_three_way(
+            job_id,
+            &req.source,
+            &req.destination,
+            hashes,
+            &moved,
+            Some(progress()),
+            &req.cancel,
+        )
+        .await
+    } else {
+        verify_from_hashes(
+            job_id,
+            &req.destination,
+            hashes,
+            Some(progress()),
+            &req.cancel,
+        )
+        .await
+    };
+    match verified {
+        Ok(verified) => {
+            let secs = verify_time.elapsed().as_secs_f64().max(0.001);
+            (true, Some((verified.bytes_verified as f64 / secs) as u64))
+        }
+        Err(_) if req.cancel.is_cancelled() => (false, None),
+        Err(e) => {
+            // The manifest can't be trusted; don't resume from it
+            if let Err(e) = db.clear_files(job_id.clone()).await {
+                warn!(job_id = %job_id, error = %e, "Failed to clear file manifest");
+            }
+            let _ = progress_tx
+                .send(TransferStatus::Failed(e.to_string()))
+                .await;
+            (false, None)
+        }
+    }
+}
+
+/// Put a verified copy where it is kept. A quarantined copy is released
+/// into the backup tree once it scans clean, and a failed scan keeps it
+/// where it is; any other copy takes its destination's name. Returns false,
+/// having failed the job, if the copy couldn't be moved.
+async fn place_copy(
+    quarantine: Option<&Quarantine>,
+    req: &TransferRequest,
+    job: &CompletedJob,
+    progress_tx: &mpsc::Sender<TransferStatus>,
+) -> bool {
+    let placed = match quarantine {
+        Some(quarantine) => match quarantine.scan(job).await {
+            Ok(()) => match quarantine.release(job).await {
+                Ok(()) => {
+                    info!(job_id = %job.job_id, "Quarantined copy released");
+                    Ok(())
+                }
+                Err(e) => Err(e.to_string()),
+            },
+            Err(e) => {
+                warn!(job_id = %job.job_id, error = %e, "Quarantine scan failed");
+                Err(format!(
+                    "{}; files kept in {}",
+                    e,
+                    req.destination.display()
+                ))
+            }
+        },
+        None => partial::promote(&req.destination, &job.destination)
+            .await
+            .map_err(|e| e.to_string()),
+    };
+    match placed {
+        Ok(()) => true,
+        Err(error) => {
+            let _ = progress_tx.send(TransferStatus::Failed(error)).await;
+            false
+        }
+    }
+}
+
+/// A verified backup in its final place, for the stages run on it before
+/// its job completes. A stage that goes wrong leaves a notice on the job
+/// rather than failing it, unless it says otherwise.
+struct VerifiedBackup<'a> {
+    config: &'a AppConfig,
+    db: &'a dyn Store,
+    job_id: &'a str,
+    destination: &'a Path,
+    result: &'a TransferResult,
+}
+
+impl VerifiedBackup<'_> {
+    async fn notice(&self, kind: &str, message: String) {
+        let notice = JobNotice {
+            kind: kind.to_string(),
+            path: None,
+            message,
+        };
+        record_notices(self.db, self.job_id, vec![notice]).await;
+    }
+
+    /// Check copied clips are playable, not just bit-identical.
+    async fn probe_videos(&self) {
+        if !self.config.probe_videos || self.config.simulation {
+            return;
+        }
+        let probes = video_probe::probe_destination(self.job_id, self.destination).await;
+        let notices = video_probe::notices(&probes);
+        if let Err(e) = self.db.record_probes(self.job_id.to_string(), probes).await {
+            warn!(job_id = %self.job_id, error = %e, "Failed to record video probe results");
+        }
+        record_notices(self.db, self.job_id, notices).await;
+    }
+
+    /// Recovery data for repairing bit-rot found by a later scrub. The
+    /// backup is already in place, so cancelling now only skips its parity.
+    async fn write_parity(
+        &self,
+        progress_tx: &mpsc::Sender<TransferStatus>,
+        cancel: &CancellationToken,
+    ) {
+        if self.config.parity.redundancy == 0 || self.config.simulation {
+            return;
+        }
+        match parity::create(
+            &self.config.parity,
+            self.job_id,
+            self.destination,
+            progress_tx,
+            cancel,
+        )
+        .await
+        {
+            Ok(files) => info!(job_id = %self.job_id, files = files.len(), "Parity files written"),
+            Err(_) if cancel.is_cancelled() => {
+                info!(job_id = %self.job_id, "Parity generation cancelled");
+                self.notice(
+                    "parity_skipped",
+                    "Parity generation was cancelled".to_string(),
+                )
+                .await;
+            }
+            Err(e) => {
+                warn!(job_id = %self.job_id, error = %e, "Failed to generate parity");
+                self.notice(
+                    "parity_failed",
+                    format!("Parity data could not be generated: {}", e),
+                )
+                .await;
+            }
+        }
+    }
+
+    /// Sign the verified folder before anything freezes it.
+    async fn write_badge(&self, key: Option<SigningKey>) {
+        let Some(key) = key else {
+            return;
+        };
+        let root = self.destination.to_path_buf();
+        let badge_job = self.job_id.to_string();
+        let hashes = self.result.file_hashes.clone();
+        let written = tokio::task::spawn_blocking(move || {
+            badge::write(
+                &root,
+                &badge_job,
+                &timestamp::now_utc(),
+                hashes.as_deref(),
+                &key,
+            )
+        })
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|result| result);
+        match written {
+            Ok(path) => {
+                info!(job_id = %self.job_id, path = %path.display(), "Wrote integrity badge")
+            }
+            Err(e) => {
+                warn!(job_id = %self.job_id, error = %e, "Failed to write integrity badge");
+                self.notice(
+                    "badge_failed",
+                    format!("Integrity badge could not be written: {}", e),
+                )
+                .await;
+            }
+        }
+    }
+
+    /// Hand the backup to its destination backend. A failed delivery fails
+    /// the job but keeps the local copy, and returns false.
+    async fn deliver(
+        &self,
+        backend: &dyn Destination,
+        job: &CompletedJob,
+        progress_tx: &mpsc::Sender<TransferStatus>,
+    ) -> bool {
+        if self.config.simulation {
+            return true;
+        }
+        match destination::deliver(backend, self.db, job).await {
+            Ok(()) => true,
+            Err(e) => {
+                warn!(job_id = %self.job_id, error = %e, "Destination delivery failed");
+                let _ = progress_tx
+                    .send(TransferStatus::Failed(e.to_string()))
+                    .await;
+                false
+            }
+        }
+    }
+
+    /// Point-in-time protection on top of the copied files.
+    async fn snapshot(&self) {
+        if self.config.simulation {
+            return;
+        }
+        match snapshot::capture(
+            &self.config.destination_snapshot,
+            &self.config.backup_directory,
+            self.job_id,
+        )
+        .await
+        {
+            Ok(Some(snapshot)) => {
+                self.notice("snapshot", format!("Destination snapshot: {}", snapshot))
+                    .await
+            }
+            Ok(None) => {}
+            Err(e) => {
+                warn!(job_id = %self.job_id, error = %e, "Destination snapshot failed");
+                self.notice(
+                    "snapshot_failed",
+                    format!("Destination snapshot failed: {}", e),
+                )
+                .await;
+            }
+        }
+    }
+
+    /// Lock the finalized backup against modification.
+    async fn make_immutable(&self) {
+        if !self.config.immutable_backups || self.config.simulation {
+            return;
+        }
+        if let Err(e) = immutable::protect(self.destination).await {
+            warn!(job_id = %self.job_id, error = %e, "Failed to mark backup immutable");
+            self.notice(
+                "immutable_failed",
+                format!("Backup could not be marked immutable: {}", e),
+            )
+            .await;
+        }
+    }
+
+    /// Freeze the backup's own subvolume, after chattr which a read-only
+    /// subvolume would refuse.
+    async fn seal_subvolume(&self) {
+        if !subvolume::is_subvolume(self.destination) {
+            return;
+        }
+        if let Err(e) = subvolume::seal(self.destination).await {
+            warn!(job_id = %self.job_id, error = %e, "Failed to make backup subvolume read-only");
+            self.notice(
+                "subvolume_failed",
+                format!("Backup subvolume could not be made read-only: {}", e),
+            )
+            .await;
+        }
+    }
+
+    /// Remember what the card held, so it isn't copied again unchanged, and
+    /// what this backup holds for later incremental runs. Not the card after
+    /// a rescue, whose zero-filled gaps a later attempt may still read.
+    async fn remember(&self, target_id: &str, fingerprint: Option<&Fingerprint>) {
+        if self.result.rescued.is_empty()
+            && let Some(fingerprint) = fingerprint
+            && let Err(e) = self
+                .db
+                .fingerprint_update(
+                    target_id.to_string(),
+                    DeviceFingerprint {
+                        digest: fingerprint.digest.clone(),
+                        job_id: self.job_id.to_string(),
+                    },
+                )
+                .await
+        {
+            warn!(job_id = %self.job_id, error = %e, "Failed to record device fingerprint");
+        }
+
+        if !self.config.simulation
+            && let Some(hashes) = &self.result.file_hashes
+            && let Err(e) = self
+                .db
+                .catalog_update(
+                    target_id.to_string(),
+                    self.job_id.to_string(),
+                    hashes.clone(),
+                )
+                .await
+        {
+            warn!(job_id = %self.job_id, error = %e, "Failed to update device catalog");
+        }
+    }
+}
+
+/// Note the files an incremental job left out, and which earlier backup
+/// holds each of them.
+async fn record_skipped(
+    db: &dyn Store,
+    job_id: &str,
+    catalog: &[CatalogEntry],
+    skipped: &[FileHash],
+) {
+    let by_path: HashMap<&Path, &CatalogEntry> = catalog
+        .iter()
+        .map(|entry| (entry.file.relative_path.as_path(), entry))
+        .collect();
+    let entries: Vec<CatalogEntry> = skipped
+        .iter()
+        .filter_map(|file| by_path.get(file.relative_path.as_path()))
+        .map(|entry| (*entry).clone())
+        .collect();
+
+    let bytes: u64 = skipped.iter().map(|f| f.size).sum();
+    let notice = JobNotice {
+        kind: "unchanged_files".to_string(),
+        path: None,
+        message: format!(
+            "Skipped {} unchanged file(s) ({:.1} MB) already in earlier backups",
+            skipped.len(),
+            bytes as f64 / (1024.0 * 1024.0)
+        ),
+    };
+
+    if let Err(e) = db.record_skipped(job_id.to_string(), entries).await {
+        warn!(job_id = %job_id, error = %e, "Failed to record skipped files");
+    }
+    record_notices(db, job_id, vec![notice]).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::SimulatedAdapter;
+    use crate::config::AppConfig;
+    use crate::db::memory::MemoryStore;
+    use tempfile::tempdir;
+
+    fn orchestrator(config: AppConfig) -> Orchestrator {
+        let db = Arc::new(MemoryStore::open(None).unwrap());
+        let (adapter, _simulator) = SimulatedAdapter::new();
+        Orchestrator {
+            ctx: AppContext::new(config, db),
+            adapter: Arc::new(adapter),
+            queued: Default::default(),
+            partitions: Default::default(),
+        }
+    }
+
+    fn device(uuid: &str) -> BlockDevice {
+        BlockDevice {
+            uuid: uuid.to_string(),
+            label: "EOS_DIGITAL".to_string(),
+            path: PathBuf::from("/dev/sdz1"),
+            mount_point: PathBuf::from("/run/bksd/card"),
+            capacity: 64,
+            filesystem: "exfat".to_string(),
+        }
+    }
+
+    fn file(path: &str) -> FileHash {
+        FileHash {
+            relative_path: PathBuf::from(path),
+            hash: [1; 32],
+            size: 10,
+        }
+    }
+
+    async fn add_job(orch: &Orchestrator, job_id: &str, dev: &BlockDevice, destination: &Path) {
+        let drive = TargetDrive {
+            uuid: dev.uuid.clone(),
+            label: dev.label.clone(),
+            mount_path: dev.mount_point.to_string_lossy().to_string(),
+            raw_size: dev.capacity,
+        };
+        orch.ctx
+            .db
+            .create_job(
+                job_id.to_string(),
+                drive,
+                destination.to_string_lossy().to_string(),
+                "host".to_string(),
+                LabelFields::default(),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_find_resumable_continues_failed_job() {
+        let temp = tempdir().unwrap();
+        let orch = orchestrator(AppConfig {
+            transfer_engine: TransferEngineType::NativeCopy,
+            simulation: true,
+            ..Default::default()
+        });
+        let dev = device("card-1");
+
+        add_job(&orch, "job-1", &dev, temp.path()).await;
+        orch.ctx
+            .db
+            .record_file("job-1".to_string(), file("DCIM/IMG_0001.JPG"))
+            .await
+            .unwrap();
+        orch.ctx
+            .db
+            .update_status("job-1".to_string(), "failed".to_string(), None, None, None)
+            .await
+            .unwrap();
+
+        let (job_id, destination, completed) = orch.find_resumable(&dev).await.unwrap();
+        assert_eq!(job_id, "job-1");
+        assert_eq!(destination, temp.path());
+        assert_eq!(completed.len(), 1);
+
+        let job = orch.ctx.db.get_with_history(job_id).await.unwrap();
+        assert_eq!(job.job.status, "Ready");
+    }
+
+    #[tokio::test]
+    async fn test_find_link_dest_skips_current_job() {
+        let temp = tempdir().unwrap();
+        let orch = orchestrator(AppConfig {
+            simulation: true,
+            ..Default::default()
+        });
+        let dev = device("card-1");
+
+        add_job(&orch, "job-1", &dev, temp.path()).await;
+        orch.ctx
+            .db
+            .record_file("job-1".to_string(), file("DCIM/IMG_0001.JPG"))
+            .await
+            .unwrap();
+        orch.ctx
+            .db
+            .update_status(
+                "job-1".to_string(),
+                "complete".to_string(),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert!(orch.find_link_dest(&dev.uuid, "job-1").await.is_none());
+
+        let link_dest = orch.find_link_dest(&dev.uuid, "job-2").await.unwrap();
+        assert_eq!(link_dest.directory, temp.path());
+        assert_eq!(link_dest.files.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_startup_scan_skips_recent_backups() {
+        let temp = tempdir().unwrap();
+        let orch = orchestrator(AppConfig {
+            simulation: true,
+            ..Default::default()
+        });
+        let dev = device("card-1");
+        assert!(!orch.recently_backed_up(&dev).await);
+
+        add_job(&orch, "job-1", &dev, temp.path()).await;
+        assert!(!orch.recently_backed_up(&dev).await);
+        orch.ctx
+            .db
+            .update_status(
+                "job-1".to_string(),
+                "complete".to_string(),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(orch.recently_backed_up(&dev).await);
+        assert!(!orch.recently_backed_up(&device("card-2")).await);
+
+        // Any backup is too old with a zero-hour window
+        let orch = Orchestrator {
+            ctx: AppContext::new(
+                AppConfig {
+                    startup_scan_skip_hours: 0,
+                    ..orch.ctx.config.as_ref().clone()
+                },
+                orch.ctx.db.clone(),
+            ),
+            ..orch
+        };
+        assert!(!orch.recently_backed_up(&dev).await);
+    }
+
+    #[tokio::test]
+    async fn test_backup_drive_is_not_backed_up_into_itself() {
+        let temp = tempdir().unwrap();
+        let orch = orchestrator(AppConfig {
+            backup_directory: temp.path().join("backups"),
+            simulation: true,
+            ..Default::default()
+        });
+        let dev = BlockDevice {
+            mount_point: temp.path().to_path_buf(),
+            ..device("backup-drive")
+        };
+
+        orch.handle_device_added(dev).await;
+        assert!(orch.ctx.db.list_jobs(10, 0, None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_waits_for_running_jobs() {
+        let temp = tempdir().unwrap();
+        let info = JobInfo {
+            device_label: "EOS_DIGITAL".to_string(),
+            device_uuid: "card-1".to_string(),
+            source: PathBuf::from("/run/bksd/card"),
+            destination: temp.path().to_path_buf(),
+        };
+        let orch = Arc::new(orchestrator(AppConfig {
+            simulation: true,
+            shutdown_timeout_secs: 5,
+            ..Default::default()
+        }));
+
+        // Finishes while the daemon waits for it
+        add_job(&orch, "job-1", &device("card-1"), temp.path()).await;
+        orch.ctx.progress.register("job-1", info.clone()).await;
+        let finishing = orch.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            finishing
+                .ctx
+                .db
+                .update_status(
+                    "job-1".to_string(),
+                    "complete".to_string(),
+                    None,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap();
+            finishing.ctx.progress.remove("job-1").await;
+        });
+        orch.drain().await;
+        let job = orch
+            .ctx
+            .db
+            .get_with_history("job-1".to_string())
+            .await
+            .unwrap();
+        assert_eq!(job.job.status, "complete");
+
+        // Still copying when the timeout runs out
+        let orch = orchestrator(AppConfig {
+            simulation: true,
+            shutdown_timeout_secs: 0,
+            ..Default::default()
+        });
+        add_job(&orch, "job-2", &device("card-2"), temp.path()).await;
+        let controls = orch.ctx.progress.register("job-2", info).await;
+        let progress = orch.ctx.progress.clone();
+        let stopped = tokio::spawn(async move {
+            controls.cancel.cancelled().await;
+            progress.remove("job-2").await;
+            controls.interrupted()
+        });
+        orch.drain().await;
+        assert!(stopped.await.unwrap());
+        let job = orch
+            .ctx
+            .db
+            .get_with_history("job-2".to_string())
+            .await
+            .unwrap();
+        assert_eq!(job.job.status, "failed");
+        assert!(job.history.iter().any(|entry| entry.description.as_deref()
+            == Some("Interrupted: daemon stopped before the job finished")));
+    }
+
+    #[tokio::test]
+    async fn test_failed_transfer_is_retried() {
+        let temp = tempdir().unwrap();
+        let orch = orchestrator(AppConfig {
+            simulation: true,
+            retry_attempts: 2,
+            retry_backoff_secs: 0,
+            ..Default::default()
+        });
+        let dev = device("card-1");
+        let destination = temp.path().join("job-1");
+        add_job(&orch, "job-1", &dev, &destination).await;
+
+        let engine = SimulatedEngine {
+            fail_at_percent: Some(0),
+            ..Default::default()
+        };
+        let mut req = TransferRequest {
+            job_id: "job-1".to_string(),
+            source: dev.mount_point.clone(),
+            device: None,
+            destination,
+            owner: None,
+            completed: Vec::new(),
+            file_log: None,
+            filter: PathFilter::default(),
+            backed_up: Vec::new(),
+            link_dest: None,
+            cancel: Default::default(),
+            pause: Default::default(),
+        };
+
+        let (tx, mut rx) = mpsc::channel(100);
+        let err = transfer_with_retries(
+            &engine,
+            &mut req,
+            &tx,
+            orch.ctx.db.as_ref(),
+            &orch.ctx.config,
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("Simulated read error"));
+
+        // Only the caller reports the failure, once retries run out
+        drop(tx);
+        while let Some(status) = rx.recv().await {
+            assert!(!matches!(status, TransferStatus::Failed(_)));
+        }
+        let job = orch
+            .ctx
+            .db
+            .get_with_history("job-1".to_string())
+            .await
+            .unwrap();
+        let retries: Vec<_> = job
+            .history
+            .iter()
+            .filter(|entry| entry.status == "retrying")
+            .collect();
+        assert_eq!(retries.len(), 2);
+        assert!(
+            retries[0]
+                .description
+                .as_deref()
+                .unwrap()
+                .starts_with("Attempt 1 of 3 failed: Simulated read error")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_card_outside_window_is_queued() {
+        let temp = tempdir().unwrap();
+        let card = temp.path().join("card");
+        std::fs::create_dir_all(card.join("DCIM")).unwrap();
+        std::fs::write(card.join("DCIM/IMG_0001.JPG"), b"jpeg").unwrap();
+
+        // A window starting two hours from now
+        let opens = Utc::now() + chrono::Duration::hours(2);
+        let closes = opens + chrono::Duration::hours(1);
+        let mut orch = orchestrator(AppConfig {
+            backup_directory: temp.path().join("backups"),
+            transfer_engine: TransferEngineType::Simulated,
+            simulation: true,
+            timezone: Some("UTC".to_string()),
+            backup_window: Some(format!(
+                "{}-{}",
+                opens.format("%H:%M"),
+                closes.format("%H:%M")
+            )),
+            ..Default::default()
+        });
+        let dev = BlockDevice {
+            mount_point: card.clone(),
+            ..device("card-1")
+        };
+
+        orch.handle_device_added(dev.clone()).await;
+        orch.handle_device_added(dev.clone()).await;
+        let jobs = orch.ctx.db.list_jobs(10, 0, None).await.unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].status, "queued");
+        let job_id = jobs[0].id.clone();
+
+        // Removing the card drops it from the queue
+        orch.handle_device_removed(dev.uuid.clone()).await;
+        let job = orch.ctx.db.get_with_history(job_id).await.unwrap();
+        assert_eq!(job.job.status, "cancelled");
+        assert!(orch.queued.lock().await.is_empty());
+
+        // Once the window opens, the queued job itself is started
+        orch.handle_device_added(dev.clone()).await;
+        let job_id = orch.queued.lock().await[&dev.uuid].job_id.clone();
+        orch.ctx.backup_window = None;
+
+        // ...unless the backup directory is low on space by then
+        orch.ctx.space.set_low(true);
+        orch.start_queued(QueueReason::Window).await;
+        assert_eq!(
+            orch.queued.lock().await[&dev.uuid].reason,
+            QueueReason::Space
+        );
+        orch.ctx.space.set_low(false);
+        orch.start_queued(QueueReason::Space).await;
+        assert!(orch.queued.lock().await.is_empty());
+        let job = orch.ctx.db.get_with_history(job_id).await.unwrap();
+        assert!(
+            job.history.iter().any(|entry| {
+                entry.description.as_deref() == Some("Backup space freed, starting")
+            })
+        );
+        assert_eq!(orch.ctx.db.list_jobs(10, 0, None).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_card_is_skipped() {
+        let temp = tempdir().unwrap();
+        let card = temp.path().join("card");
+        let backup = temp.path().join("backups/EOS_DIGITAL/job-1");
+        std::fs::create_dir_all(card.join("DCIM")).unwrap();
+        std::fs::create_dir_all(&backup).unwrap();
+        std::fs::write(card.join("DCIM/IMG_0001.JPG"), b"jpeg").unwrap();
+
+        let orch = orchestrator(AppConfig {
+            backup_directory: temp.path().join("backups"),
+            ..Default::default()
+        });
+        let dev = BlockDevice {
+            mount_point: card.clone(),
+            ..device("card-1")
+        };
+
+        add_job(&orch, "job-1", &dev, &backup).await;
+        orch.ctx
+            .db
+            .update_status(
+                "job-1".to_string(),
+                "complete".to_string(),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let fingerprint = orch.fingerprint(&dev).await.unwrap();
+        assert!(orch.unchanged_since(&dev, &fingerprint).await.is_none());
+
+        orch.ctx
+            .db
+            .fingerprint_update(
+                dev.uuid.clone(),
+                DeviceFingerprint {
+                    digest: fingerprint.digest.clone(),
+                    job_id: "job-1".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        let previous = orch.unchanged_since(&dev, &fingerprint).await.unwrap();
+        assert_eq!(previous.id, "job-1");
+
+        orch.handle_device_added(dev.clone()).await;
+        let jobs = orch.ctx.db.list_jobs(10, 0, None).await.unwrap();
+        assert_eq!(jobs.len(), 2);
+        // Both jobs can share a creation time, so don't rely on the order
+        let skipped = jobs.iter().find(|job| job.id != "job-1").unwrap();
+        assert_eq!(skipped.status, "skipped");
+        assert_eq!(
+            skipped.destination_path.as_deref(),
+            Some(backup.to_str().unwrap())
+        );
+
+        // Anything shot since means a new backup
+        std::fs::write(card.join("DCIM/IMG_0002.JPG"), b"jpeg").unwrap();
+        let fingerprint = orch.fingerprint(&dev).await.unwrap();
+        assert!(orch.unchanged_since(&dev, &fingerprint).await.is_none());
     }
 }
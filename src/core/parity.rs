@@ -0,0 +1,244 @@
+//! PAR2 parity data for repairing a backup that rots later.
+//!
+//! With `[parity] redundancy` set, each verified backup gets PAR2 recovery
+//! files at its root, made by par2cmdline (`par2 create`). If files are
+//! later damaged - a scrub reports them - running `par2 repair
+//! BKSD_PARITY.par2` in the folder rebuilds them, as long as the damage is
+//! within the redundancy. Progress is reported as
+//! `TransferStatus::GeneratingParity`.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use anyhow::{Result, anyhow, bail};
+use tokio::io::AsyncReadExt;
+use tokio::process::Command;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use crate::config::ParityConfig;
+use crate::core::restore::walk;
+use crate::core::transfer_engine::TransferStatus;
+
+/// Index file of the parity set; the recovery volumes are named after it
+/// (`BKSD_PARITY.vol00+01.par2`, ...)
+pub const PARITY_NAME: &str = "BKSD_PARITY.par2";
+
+/// Whether `path`, relative to the backup root, is one of its parity files
+pub fn is_parity_file(path: &Path) -> bool {
+    path.parent() == Some(Path::new(""))
+        && path
+            .to_str()
+            .is_some_and(|name| name.starts_with("BKSD_PARITY.") && name.ends_with(".par2"))
+}
+
+/// Arguments for `par2 create` over `files` (relative to `root`).
+fn arguments(config: &ParityConfig, root: &Path, files: &[PathBuf]) -> Vec<OsString> {
+    let mut args: Vec<OsString> = vec!["create".into(), format!("-r{}", config.redundancy).into()];
+    args.extend(config.args.iter().map(OsString::from));
+    let mut base = OsString::from("-B");
+    base.push(root);
+    args.push(base);
+    args.push(root.join(PARITY_NAME).into());
+    args.extend(files.iter().map(|file| root.join(file).into()));
+    args
+}
+
+/// Percentage from a `Processing: 45.2%` progress line
+fn parse_progress(line: &str) -> Option<u8> {
+    let value = line.trim().strip_prefix("Processing:")?.trim();
+    let percent: f64 = value.strip_suffix('%')?.trim().parse().ok()?;
+    Some(percent.clamp(0.0, 100.0) as u8)
+}
+
+/// Write parity files for the backup at `root`, sending progress as it
+/// goes. Returns the parity files created, relative to `root`. Stops par2
+/// and fails if `cancel` fires.
+pub async fn create(
+    config: &ParityConfig,
+    job_id: &str,
+    root: &Path,
+    progress: &mpsc::Sender<TransferStatus>,
+    cancel: &CancellationToken,
+) -> Result<Vec<PathBuf>> {
+    let listing_root = root.to_path_buf();
+    let files: Vec<PathBuf> = tokio::task::spawn_blocking(move || walk(&listing_root, &[]))
+        .await??
+        .into_iter()
+        .filter(|path| !is_parity_file(path))
+        .collect();
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    info!(job_id = %job_id, files = files.len(), redundancy = config.redundancy, "Generating parity");
+    let _ = progress
+        .send(TransferStatus::GeneratingParity { percentage: 0 })
+        .await;
+
+    let mut child = Command::new(&config.binary)
+        .args(arguments(config, root, &files))
+        .current_dir(root)
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| anyhow!("Failed to run {}: {}", config.binary.display(), e))?;
+    let mut stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow!("Failed to get par2 output"))?;
+
+    // par2 redraws its progress line with carriage returns
+    let mut buffer = [0u8; 4096];
+    let mut line = Vec::new();
+    let mut last = 0;
+    loop {
+        let read = tokio::select! {
+            read = stdout.read(&mut buffer) => read?,
+            _ = cancel.cancelled() => {
+                let _ = child.kill().await;
+                // Half-written recovery files would only mislead a repair
+                let listing_root = root.to_path_buf();
+                let _ = tokio::task::spawn_blocking(move || {
+                    for path in walk(&listing_root, &[])?.into_iter().filter(|p| is_parity_file(p)) {
+                        std::fs::remove_file(listing_root.join(path))?;
+                    }
+                    Ok::<_, anyhow::Error>(())
+                })
+                .await;
+                bail!("Parity generation cancelled");
+            }
+        };
+        if read == 0 {
+            break;
+        }
+        for &byte in &buffer[..read] {
+            if byte != b'\r' && byte != b'\n' {
+                line.push(byte);
+                continue;
+            }
+            if let Some(percentage) = parse_progress(&String::from_utf8_lossy(&line))
+                && percentage != last
+            {
+                last = percentage;
+                let _ = progress
+                    .send(TransferStatus::GeneratingParity { percentage })
+                    .await;
+            }
+            line.clear();
+        }
+    }
+
+    let status = child.wait().await?;
+    if !status.success() {
+        bail!("{} exited with {}", config.binary.display(), status);
+    }
+
+    let listing_root = root.to_path_buf();
+    let created: Vec<PathBuf> = tokio::task::spawn_blocking(move || walk(&listing_root, &[]))
+        .await??
+        .into_iter()
+        .filter(|path| is_parity_file(path))
+        .collect();
+    if created.is_empty() {
+        bail!("{} wrote no parity files", config.binary.display());
+    }
+    Ok(created)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_arguments_and_progress() {
+        let config = ParityConfig {
+            redundancy: 10,
+            args: vec!["-n4".to_string()],
+            ..ParityConfig::default()
+        };
+        let args = arguments(
+            &config,
+            Path::new("/backups/card"),
+            &[PathBuf::from("DCIM/A.JPG")],
+        );
+        assert_eq!(
+            args,
+            [
+                "create",
+                "-r10",
+                "-n4",
+                "-B/backups/card",
+                "/backups/card/BKSD_PARITY.par2",
+                "/backups/card/DCIM/A.JPG"
+            ]
+            .map(OsString::from)
+        );
+
+        assert_eq!(parse_progress("Processing: 45.2%"), Some(45));
+        assert_eq!(parse_progress("Processing: 100.0%"), Some(100));
+        assert_eq!(parse_progress("Opening: DCIM/A.JPG"), None);
+
+        assert!(is_parity_file(Path::new("BKSD_PARITY.par2")));
+        assert!(is_parity_file(Path::new("BKSD_PARITY.vol00+01.par2")));
+        assert!(!is_parity_file(Path::new("DCIM/BKSD_PARITY.par2")));
+        assert!(!is_parity_file(Path::new("clip.par2")));
+    }
+
+    #[tokio::test]
+    async fn test_create_reports_progress() {
+        let temp = tempdir().unwrap();
+        let root = temp.path().join("backup");
+        fs::create_dir_all(root.join("DCIM")).unwrap();
+        fs::write(root.join("DCIM/A.JPG"), b"frame").unwrap();
+        // Stands in for par2: prints progress and writes the index file
+        let binary = temp.path().join("par2");
+        fs::write(
+            &binary,
+            "#!/bin/sh\nprintf 'Opening: DCIM/A.JPG\\nProcessing: 50.0%%\\rProcessing: 100.0%%\\r\\nDone\\n'\n\
+             touch BKSD_PARITY.par2 BKSD_PARITY.vol0+1.par2\n",
+        )
+        .unwrap();
+        fs::set_permissions(&binary, fs::Permissions::from_mode(0o755)).unwrap();
+        let config = ParityConfig {
+            redundancy: 5,
+            binary,
+            args: Vec::new(),
+        };
+
+        let (tx, mut rx) = mpsc::channel(16);
+        let created = create(&config, "job-1", &root, &tx, &CancellationToken::new())
+            .await
+            .unwrap();
+        assert_eq!(
+            created,
+            vec![
+                PathBuf::from("BKSD_PARITY.par2"),
+                PathBuf::from("BKSD_PARITY.vol0+1.par2")
+            ]
+        );
+        drop(tx);
+        let mut percentages = Vec::new();
+        while let Some(TransferStatus::GeneratingParity { percentage }) = rx.recv().await {
+            percentages.push(percentage);
+        }
+        assert_eq!(percentages, vec![0, 50, 100]);
+
+        // A failing par2 fails the stage
+        let config = ParityConfig {
+            binary: PathBuf::from("false"),
+            ..config
+        };
+        let (tx, _rx) = mpsc::channel(16);
+        assert!(
+            create(&config, "job-1", &root, &tx, &CancellationToken::new())
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Backups in the making, and the folder a failed job leaves behind.
+//!
+//! A job copies into `<name>.partial` next to its destination, and only once
+//! the copy has verified is it renamed ([`promote`]) to the destination
+//! itself, so an interrupted job never leaves a folder that looks like a
+//! finished backup. Untrusted cards are staged in the quarantine directory
+//! instead and released from there.
+//!
+//! A transfer that fails part-way, or whose copy doesn't verify, leaves its
+//! partial copy behind. By default (`failed_destination = "rename"`) it is
+//! renamed to `<name>.failed`, and the new location is added to the job's
+//! error. Native copy can still resume into it: [`restore`] moves it back to
+//! `<name>.partial` when the card is reinserted. `delete` removes it instead
+//! and `keep` leaves it alone. A folder the job refused to write into
+//! because it was already there is never touched.
+
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow, bail};
+use tracing::{info, warn};
+
+use crate::config::FailedDestination;
+
+/// Suffix of the folder a job copies into until it has verified
+pub const PARTIAL_SUFFIX: &str = ".partial";
+
+/// Suffix a failed job's destination is renamed with
+pub const FAILED_SUFFIX: &str = ".failed";
+
+/// Where a job bound for `destination` copies to.
+pub fn partial_path(destination: &Path) -> PathBuf {
+    with_suffix(destination, PARTIAL_SUFFIX)
+}
+
+/// Where a failed job's `destination` is set aside to.
+pub fn failed_path(destination: &Path) -> PathBuf {
+    with_suffix(destination, FAILED_SUFFIX)
+}
+
+fn with_suffix(destination: &Path, suffix: &str) -> PathBuf {
+    let mut name = destination
+        .file_name()
+        .map(OsString::from)
+        .unwrap_or_default();
+    name.push(suffix);
+    destination.with_file_name(name)
+}
+
+/// Rename the verified copy at `copy` to `destination`, and sync the
+/// directory holding it so the new name survives a power loss. Nothing is
+/// done if nothing was copied (simulation).
+pub async fn promote(copy: &Path, destination: &Path) -> Result<()> {
+    if !copy.exists() {
+        return Ok(());
+    }
+    // rename() would replace an empty directory, but not one with files
+    if std::fs::read_dir(destination).is_ok_and(|mut entries| entries.next().is_some()) {
+        bail!(
+            "Destination already exists: {}. Refusing to overwrite.",
+            destination.display()
+        );
+    }
+    tokio::fs::rename(copy, destination).await.map_err(|e| {
+        anyhow!(
+            "Failed to move {} to {}: {}",
+            copy.display(),
+            destination.display(),
+            e
+        )
+    })?;
+    sync_parent(destination)
+        .await
+        .map_err(|e| anyhow!("Failed to sync {}: {}", destination.display(), e))
+}
+
+/// fsync the directory holding `path`, which a rename into it needs to be
+/// durable.
+pub async fn sync_parent(path: &Path) -> io::Result<()> {
+    let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+    else {
+        return Ok(());
+    };
+    tokio::task::spawn_blocking(move || std::fs::File::open(parent)?.sync_all())
+        .await
+        .map_err(io::Error::other)?
+}
+
+/// Deal with the copy a failed job bound for `destination` left, as
+/// `action` says. Returns what became of it, for the job's error, or None
+/// if nothing was done.
+pub async fn set_aside(action: FailedDestination, destination: &Path) -> Option<String> {
+    if action == FailedDestination::Keep {
+        return None;
+    }
+    // A job that failed after it verified (delivery) has no partial copy,
+    // and its backup at the destination stays where it is
+    let copy = partial_path(destination);
+    if !copy.is_dir() {
+        return None;
+    }
+
+    let outcome = match action {
+        FailedDestination::Rename => {
+            let failed = failed_path(destination);
+            if failed.exists() {
+                Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} already exists", failed.display()),
+                ))
+            } else {
+                tokio::fs::rename(&copy, &failed)
+                    .await
+                    .map(|()| format!("partial copy moved to {}", failed.display()))
+            }
+        }
+        FailedDestination::Delete => tokio::fs::remove_dir_all(&copy)
+            .await
+            .map(|()| "partial copy deleted".to_string()),
+        FailedDestination::Keep => return None,
+    };
+    match outcome {
+        Ok(outcome) => {
+            info!(destination = %destination.display(), "Failed job's {}", outcome);
+            Some(outcome)
+        }
+        Err(e) => {
+            warn!(
+                destination = %destination.display(),
+                error = %e,
+                "Failed to set aside partial copy"
+            );
+            None
+        }
+    }
+}
+
+/// Put the partial copy of an interrupted job bound for `destination` back
+/// at `<name>.partial`, to resume into it. True if it is there now.
+pub async fn restore(destination: &Path) -> bool {
+    let partial = partial_path(destination);
+    if partial.is_dir() {
+        return true;
+    }
+    // Set aside as failed, or copied straight into place by an older bksd
+    let Some(copy) = [failed_path(destination), destination.to_path_buf()]
+        .into_iter()
+        .find(|path| path.is_dir())
+    else {
+        return false;
+    };
+    match tokio::fs::rename(&copy, &partial).await {
+        Ok(()) => true,
+        Err(e) => {
+            warn!(
+                destination = %copy.display(),
+                error = %e,
+                "Failed to restore partial copy"
+            );
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_failed_destination_set_aside_and_restored() {
+        let temp = tempdir().unwrap();
+        let destination = temp.path().join("A001/2025-01-14_T0900_00");
+        let partial = temp.path().join("A001/2025-01-14_T0900_00.partial");
+        assert_eq!(partial_path(&destination), partial);
+        std::fs::create_dir_all(&partial).unwrap();
+        std::fs::write(partial.join("IMG_0001.JPG"), b"jpeg").unwrap();
+        let failed = temp.path().join("A001/2025-01-14_T0900_00.failed");
+        assert_eq!(failed_path(&destination), failed);
+
+        assert_eq!(set_aside(FailedDestination::Keep, &destination).await, None);
+        assert!(partial.is_dir());
+
+        let outcome = set_aside(FailedDestination::Rename, &destination)
+            .await
+            .unwrap();
+        assert!(outcome.contains(".failed"));
+        assert!(!partial.exists());
+        assert!(!destination.exists());
+        assert!(failed.join("IMG_0001.JPG").is_file());
+        // Nothing left to set aside
+        assert_eq!(
+            set_aside(FailedDestination::Rename, &destination).await,
+            None
+        );
+
+        // Reinserting the card resumes into it
+        assert!(restore(&destination).await);
+        assert!(partial.join("IMG_0001.JPG").is_file());
+        assert!(!failed.exists());
+
+        // Verified, it takes the destination's name
+        promote(&partial, &destination).await.unwrap();
+        assert!(destination.join("IMG_0001.JPG").is_file());
+        assert!(!partial.exists());
+        std::fs::create_dir_all(&partial).unwrap();
+        std::fs::write(partial.join("IMG_0002.JPG"), b"jpeg").unwrap();
+        let err = promote(&partial, &destination).await.unwrap_err();
+        assert!(err.to_string().starts_with("Destination already exists"));
+        std::fs::remove_dir_all(&partial).unwrap();
+
+        // A verified backup whose delivery failed is left alone
+        assert_eq!(
+            set_aside(FailedDestination::Delete, &destination).await,
+            None
+        );
+        assert_eq!(
+            set_aside(FailedDestination::Rename, &destination).await,
+            None
+        );
+        assert!(destination.join("IMG_0001.JPG").is_file());
+        assert!(!failed.exists());
+
+        // Only the partial copy goes
+        std::fs::create_dir_all(&partial).unwrap();
+        assert_eq!(
+            set_aside(FailedDestination::Delete, &destination)
+                .await
+                .as_deref(),
+            Some("partial copy deleted")
+        );
+        assert!(!partial.exists());
+        assert!(destination.join("IMG_0001.JPG").is_file());
+    }
+}
@@ -0,0 +1,254 @@
+//! Backing up the partitions of one card in turn.
+//!
+//! A card with several partitions (a camera's media and system partitions,
+//! a recorder formatted with two volumes) shows up as one device per
+//! partition. Copying them all at once has the jobs fight over the same
+//! reader, and ejecting after whichever finishes first pulls the card out
+//! from under the others. Partitions sharing a parent disk are backed up
+//! one at a time: the rest get a `queued` job that starts when the one
+//! before it ends. Each keeps its own job and backup folder, but only the
+//! card's last partition sends a completion notification, covering all of
+//! them, and only then is the card ejected.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use tokio::sync::{Mutex, Notify};
+
+use crate::core::notifications::JobEvent;
+use crate::core::transfer_engine::TransferStats;
+
+/// Which cards have a partition being backed up, shared by the orchestrator
+/// and its jobs.
+#[derive(Default)]
+pub struct Partitions {
+    disks: Mutex<HashMap<PathBuf, Disk>>,
+    /// Signalled when a partition's job ends with others still waiting
+    released: Notify,
+}
+
+#[derive(Default)]
+struct Disk {
+    running: bool,
+    /// UUIDs of partitions waiting their turn, in arrival order
+    waiting: Vec<String>,
+    /// Completion events of the partitions done so far
+    completed: Vec<JobEvent>,
+}
+
+impl Partitions {
+    /// Take `disk` for the partition `uuid`. False if another partition of
+    /// the card is being backed up, in which case `uuid` waits its turn.
+    pub async fn claim(&self, disk: &Path, uuid: &str) -> bool {
+        let mut disks = self.disks.lock().await;
+        let entry = disks.entry(disk.to_path_buf()).or_default();
+        if entry.running {
+            if !entry.waiting.iter().any(|waiting| waiting == uuid) {
+                entry.waiting.push(uuid.to_string());
+            }
+            return false;
+        }
+        entry.running = true;
+        entry.waiting.retain(|waiting| waiting != uuid);
+        true
+    }
+
+    /// Whether the partition `uuid` is waiting for another one to finish.
+    pub async fn is_waiting(&self, uuid: &str) -> bool {
+        self.disks
+            .lock()
+            .await
+            .values()
+            .any(|disk| disk.waiting.iter().any(|waiting| waiting == uuid))
+    }
+
+    /// The job holding `disk` ended, with `completed` if it succeeded.
+    /// Returns the completion events of every partition of the card once it
+    /// was the last one, or None while others are still to go.
+    pub async fn finish(&self, disk: &Path, completed: Option<JobEvent>) -> Option<Vec<JobEvent>> {
+        let mut disks = self.disks.lock().await;
+        let entry = disks.entry(disk.to_path_buf()).or_default();
+        entry.running = false;
+        entry.completed.extend(completed);
+        if entry.waiting.is_empty() {
+            return disks.remove(disk).map(|disk| disk.completed);
+        }
+        self.released.notify_one();
+        None
+    }
+
+    /// The partition to start next on each card no longer being backed up.
+    pub async fn next(&self) -> Vec<String> {
+        self.disks
+            .lock()
+            .await
+            .values()
+            .filter(|disk| !disk.running)
+            .filter_map(|disk| disk.waiting.first().cloned())
+            .collect()
+    }
+
+    /// Resolves once a partition's job has ended and another is waiting.
+    pub async fn released(&self) {
+        self.released.notified().await
+    }
+
+    /// Stop waiting for the partition `uuid`, removed before its turn.
+    pub async fn forget(&self, uuid: &str) {
+        self.disks.lock().await.retain(|_, disk| {
+            disk.waiting.retain(|waiting| waiting != uuid);
+            disk.running || !disk.waiting.is_empty()
+        });
+    }
+}
+
+/// One completion event for a whole card from those of its partitions:
+/// labels joined, sizes, times and file counts added up, notices merged.
+pub fn combine(events: Vec<JobEvent>) -> Option<JobEvent> {
+    events
+        .into_iter()
+        .reduce(|combined, event| match (combined, event) {
+            (
+                JobEvent::Completed {
+                    device_label,
+                    label_fields,
+                    total_bytes,
+                    duration_secs,
+                    stats,
+                    mut notices,
+                    ..
+                },
+                JobEvent::Completed {
+                    job_id,
+                    device_label: next_label,
+                    total_bytes: next_bytes,
+                    duration_secs: next_secs,
+                    stats: next_stats,
+                    notices: next_notices,
+                    ..
+                },
+            ) => {
+                let total_bytes = total_bytes + next_bytes;
+                let duration_secs = duration_secs + next_secs;
+                notices.extend(next_notices);
+                JobEvent::Completed {
+                    job_id,
+                    device_label: format!("{} + {}", device_label, next_label),
+                    label_fields,
+                    total_bytes,
+                    duration_secs,
+                    stats: add_stats(&stats, &next_stats, total_bytes, duration_secs),
+                    notices,
+                }
+            }
+            (combined, _) => combined,
+        })
+}
+
+/// Stats of two partitions' jobs taken as one, `total_bytes` copied in
+/// `duration_secs` between them.
+fn add_stats(
+    a: &TransferStats,
+    b: &TransferStats,
+    total_bytes: u64,
+    duration_secs: u64,
+) -> TransferStats {
+    TransferStats {
+        avg_bytes_per_sec: match duration_secs {
+            0 => a.avg_bytes_per_sec.max(b.avg_bytes_per_sec),
+            secs => total_bytes / secs,
+        },
+        peak_bytes_per_sec: a.peak_bytes_per_sec.max(b.peak_bytes_per_sec),
+        verify_bytes_per_sec: match (a.verify_bytes_per_sec, b.verify_bytes_per_sec) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (rate, None) | (None, rate) => rate,
+        },
+        retried_files: a.retried_files + b.retried_files,
+        files_copied: a.files_copied + b.files_copied,
+        files_skipped: a.files_skipped + b.files_skipped,
+        files_failed: a.files_failed + b.files_failed,
+        files_rescued: a.files_rescued + b.files_rescued,
+        largest_file: [&a.largest_file, &b.largest_file]
+            .into_iter()
+            .flatten()
+            .max_by_key(|largest| largest.size)
+            .cloned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::label_fields::LabelFields;
+
+    fn completed(label: &str, total_bytes: u64, files_copied: usize) -> JobEvent {
+        JobEvent::Completed {
+            job_id: format!("{}-job", label),
+            device_label: label.to_string(),
+            label_fields: LabelFields::default(),
+            total_bytes,
+            duration_secs: 10,
+            stats: TransferStats {
+                files_copied,
+                ..Default::default()
+            },
+            notices: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_partitions_take_turns() {
+        let partitions = Partitions::default();
+        let card = Path::new("/dev/sdb");
+
+        assert!(partitions.claim(card, "media").await);
+        assert!(!partitions.claim(card, "system").await);
+        assert!(!partitions.claim(card, "system").await);
+        assert!(partitions.is_waiting("system").await);
+        // Another card isn't held up
+        assert!(partitions.claim(Path::new("/dev/sdc"), "other").await);
+        assert!(partitions.next().await.is_empty());
+
+        // The first partition done, the second goes next
+        assert!(
+            partitions
+                .finish(card, Some(completed("MEDIA", 1000, 3)))
+                .await
+                .is_none()
+        );
+        assert_eq!(partitions.next().await, vec!["system".to_string()]);
+        assert!(partitions.claim(card, "system").await);
+        assert!(!partitions.is_waiting("system").await);
+
+        let events = partitions
+            .finish(card, Some(completed("SYSTEM", 500, 2)))
+            .await
+            .unwrap();
+        let Some(JobEvent::Completed {
+            job_id,
+            device_label,
+            total_bytes,
+            duration_secs,
+            stats,
+            ..
+        }) = combine(events)
+        else {
+            panic!("expected a completion");
+        };
+        assert_eq!(job_id, "SYSTEM-job");
+        assert_eq!(device_label, "MEDIA + SYSTEM");
+        assert_eq!((total_bytes, duration_secs), (1500, 20));
+        assert_eq!((stats.files_copied, stats.avg_bytes_per_sec), (5, 75));
+
+        // A partition removed while waiting no longer holds the card up
+        assert!(partitions.claim(card, "media").await);
+        assert!(!partitions.claim(card, "system").await);
+        partitions.forget("system").await;
+        assert!(
+            partitions
+                .finish(card, None)
+                .await
+                .is_some_and(|events| events.is_empty())
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Sleep/shutdown inhibition while jobs are running.
+//!
+//! A laptop used as an ingest station will happily suspend mid-copy. While at
+//! least one job is active we hold a logind inhibitor lock by keeping a
+//! `systemd-inhibit` process alive; the lock is released (the process killed)
+//! when the last job finishes.
+
+use std::sync::{Arc, Mutex};
+
+use tokio::process::{Child, Command};
+use tracing::{debug, warn};
+
+#[derive(Default)]
+struct InhibitState {
+    active_jobs: usize,
+    child: Option<Child>,
+}
+
+/// Reference-counted inhibitor lock shared via `AppContext`.
+#[derive(Clone)]
+pub struct PowerInhibitor {
+    state: Arc<Mutex<InhibitState>>,
+    /// Command holding the lock; None when inhibition is disabled
+    command: Option<Arc<(String, Vec<String>)>>,
+}
+
+impl PowerInhibitor {
+    /// Inhibitor backed by `systemd-inhibit`.
+    pub fn new(enabled: bool) -> Self {
+        let command = enabled.then(|| {
+            (
+                "systemd-inhibit".to_string(),
+                vec![
+                    "--what=sleep:shutdown:idle".to_string(),
+                    "--who=bksd".to_string(),
+                    "--why=Backup in progress".to_string(),
+                    "--mode=block".to_string(),
+                    "sleep".to_string(),
+                    "infinity".to_string(),
+                ],
+            )
+        });
+        Self::with_command(command)
+    }
+
+    fn with_command(command: Option<(String, Vec<String>)>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(InhibitState::default())),
+            command: command.map(Arc::new),
+        }
+    }
+
+    /// Take the lock for the duration of a job. The lock is held until every
+    /// returned guard has been dropped.
+    pub fn acquire(&self) -> InhibitGuard {
+        let mut state = self.state.lock().unwrap();
+        state.active_jobs += 1;
+
+        if state.child.is_none()
+            && let Some(command) = &self.command
+        {
+            let (program, args) = command.as_ref();
+            match Command::new(program).args(args).kill_on_drop(true).spawn() {
+                Ok(child) => {
+                    debug!("Sleep inhibitor lock taken");
+                    state.child = Some(child);
+                }
+                Err(e) => warn!(error = %e, "Failed to inhibit sleep during backup"),
+            }
+        }
+
+        InhibitGuard {
+            inhibitor: self.clone(),
+        }
+    }
+
+    /// Whether the inhibitor process is currently running.
+    pub fn is_held(&self) -> bool {
+        self.state.lock().unwrap().child.is_some()
+    }
+
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.active_jobs = state.active_jobs.saturating_sub(1);
+
+        if state.active_jobs == 0 && state.child.take().is_some() {
+            // Dropping the child kills it (kill_on_drop), releasing the lock
+            debug!("Sleep inhibitor lock released");
+        }
+    }
+}
+
+/// Releases one reference on the inhibitor lock when dropped.
+pub struct InhibitGuard {
+    inhibitor: PowerInhibitor,
+}
+
+impl Drop for InhibitGuard {
+    fn drop(&mut self) {
+        self.inhibitor.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_lock_held_while_jobs_active() {
+        let inhibitor =
+            PowerInhibitor::with_command(Some(("sleep".to_string(), vec!["60".to_string()])));
+
+        let first = inhibitor.acquire();
+        let second = inhibitor.acquire();
+        assert!(inhibitor.is_held());
+
+        drop(first);
+        assert!(inhibitor.is_held());
+
+        drop(second);
+        assert!(!inhibitor.is_held());
+    }
+
+    #[tokio::test]
+    async fn test_disabled_inhibitor() {
+        let inhibitor = PowerInhibitor::new(false);
+        let _guard = inhibitor.acquire();
+        assert!(!inhibitor.is_held());
+    }
+}
@@ -7,10 +7,17 @@
 //! Future IPC/TCP endpoints can query this tracker for real-time progress updates.
 
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 
-use super::transfer_engine::TransferStatus;
+use super::clock::Stopwatch;
+use super::transfer_engine::{PauseToken, TransferStatus};
 
 /// Thread-safe in-memory store for active job progress.
 ///
@@ -20,17 +27,161 @@ use super::transfer_engine::TransferStatus;
 #[derive(Clone, Default)]
 pub struct ProgressTracker {
     inner: Arc<RwLock<HashMap<String, TransferStatus>>>,
+    jobs: Arc<RwLock<HashMap<String, TrackedJob>>>,
+}
+
+/// Static facts about an active job, recorded when it starts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobInfo {
+    pub device_label: String,
+    #[serde(default)]
+    pub device_uuid: String,
+    pub source: PathBuf,
+    pub destination: PathBuf,
+}
+
+/// Enriched view of an active job for status displays.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobProgressDetails {
+    #[serde(flatten)]
+    pub info: JobInfo,
+    /// scanning, copying or verifying
+    pub phase: String,
+    /// Seconds spent in the current phase
+    pub phase_secs: u64,
+    /// Current copy speed as smoothed by the engine, or averaged over the
+    /// copying phase before it has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes_per_sec: Option<u64>,
+    /// Estimated seconds until the current phase finishes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eta_seconds: Option<u64>,
+}
+
+struct TrackedJob {
+    info: JobInfo,
+    phase: &'static str,
+    phase_started: Stopwatch,
+    controls: JobControls,
+}
+
+/// Handles a running job's transfer watches, so RPC methods can stop or
+/// hold it.
+#[derive(Clone, Default)]
+pub struct JobControls {
+    pub cancel: CancellationToken,
+    pub pause: PauseToken,
+    /// Set before `cancel` when the job's device was removed, so the job
+    /// fails rather than counting as cancelled
+    pub removed: Arc<AtomicBool>,
+    /// Set before `cancel` when the daemon is shutting down, so the job
+    /// keeps its partial copy to resume from
+    pub interrupted: Arc<AtomicBool>,
+}
+
+impl JobControls {
+    /// Whether the job was stopped because its device went away.
+    pub fn device_removed(&self) -> bool {
+        self.removed.load(Ordering::SeqCst)
+    }
+
+    /// Whether the job was stopped because the daemon is shutting down.
+    pub fn interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::SeqCst)
+    }
 }
 
 impl ProgressTracker {
     pub fn new() -> Self {
-        Self {
-            inner: Arc::new(RwLock::new(HashMap::new())),
+        Self::default()
+    }
+
+    /// Record the device and destination of a job that is about to start.
+    /// Returns the tokens that `cancel()`, `pause()` and `resume()` flip
+    /// for it.
+    pub async fn register(&self, job_id: &str, info: JobInfo) -> JobControls {
+        let controls = JobControls::default();
+        let mut jobs = self.jobs.write().await;
+        jobs.insert(
+            job_id.to_string(),
+            TrackedJob {
+                info,
+                phase: TransferStatus::Ready.phase(),
+                phase_started: Stopwatch::start(),
+                controls: controls.clone(),
+            },
+        );
+        controls
+    }
+
+    /// Ask a running job to stop. Returns false if no such job is running.
+    pub async fn cancel(&self, job_id: &str) -> bool {
+        match self.jobs.read().await.get(job_id) {
+            Some(job) => {
+                job.controls.cancel.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stop the running jobs copying from the device `uuid`, which has been
+    /// removed. Returns their IDs.
+    pub async fn device_removed(&self, uuid: &str) -> Vec<String> {
+        self.jobs
+            .read()
+            .await
+            .iter()
+            .filter(|(_, job)| job.info.device_uuid == uuid)
+            .map(|(id, job)| {
+                job.controls.removed.store(true, Ordering::SeqCst);
+                job.controls.cancel.cancel();
+                id.clone()
+            })
+            .collect()
+    }
+
+    /// Stop every running job because the daemon is shutting down. Returns
+    /// their IDs.
+    pub async fn interrupt_all(&self) -> Vec<String> {
+        self.jobs
+            .read()
+            .await
+            .iter()
+            .map(|(id, job)| {
+                job.controls.interrupted.store(true, Ordering::SeqCst);
+                job.controls.cancel.cancel();
+                id.clone()
+            })
+            .collect()
+    }
+
+    /// Hold a running job before its next file. Returns false if no such
+    /// job is running or it is already paused.
+    pub async fn pause(&self, job_id: &str) -> bool {
+        match self.jobs.read().await.get(job_id) {
+            Some(job) => job.controls.pause.pause(),
+            None => false,
+        }
+    }
+
+    /// Let a paused job continue. Returns false if no such job is paused.
+    pub async fn resume(&self, job_id: &str) -> bool {
+        match self.jobs.read().await.get(job_id) {
+            Some(job) => job.controls.pause.resume(),
+            None => false,
         }
     }
 
     /// Update the progress for a job. Called on every progress tick from transfer engines.
     pub async fn update(&self, job_id: &str, status: TransferStatus) {
+        if let Some(job) = self.jobs.write().await.get_mut(job_id)
+            && job.phase != status.phase()
+        {
+            job.phase = status.phase();
+            job.phase_started = Stopwatch::start();
+        }
+
         let mut map = self.inner.write().await;
         map.insert(job_id.to_string(), status);
     }
@@ -43,6 +194,7 @@ impl ProgressTracker {
 
     /// Remove a job from tracking (called when job completes or fails).
     pub async fn remove(&self, job_id: &str) {
+        self.jobs.write().await.remove(job_id);
         let mut map = self.inner.write().await;
         map.remove(job_id);
     }
@@ -53,6 +205,72 @@ impl ProgressTracker {
         map.clone()
     }
 
+    /// Get device, destination, phase, speed and ETA for registered jobs.
+    pub async fn get_details(&self) -> HashMap<String, JobProgressDetails> {
+        let jobs = self.jobs.read().await;
+        let statuses = self.inner.read().await;
+
+        jobs.iter()
+            .map(|(id, job)| {
+                let elapsed = job.phase_started.elapsed().as_secs_f64();
+                let (bytes_per_sec, eta_seconds) = match statuses.get(id) {
+                    Some(TransferStatus::InProgress {
+                        bytes_copied,
+                        eta_seconds,
+                        bytes_per_sec,
+                        ..
+                    }) => {
+                        // The engine's current speed, else the phase average
+                        let speed = match *bytes_per_sec {
+                            0 => (elapsed >= 1.0).then(|| (*bytes_copied as f64 / elapsed) as u64),
+                            current => Some(current),
+                        };
+                        (speed, *eta_seconds)
+                    }
+                    Some(TransferStatus::Verifying {
+                        bytes_verified,
+                        total_bytes,
+                        bytes_per_sec,
+                        ..
+                    }) if *bytes_verified > 0 && *total_bytes > 0 => {
+                        let remaining = total_bytes.saturating_sub(*bytes_verified) as f64;
+                        (
+                            Some(*bytes_per_sec).filter(|&speed| speed > 0),
+                            Some((elapsed / *bytes_verified as f64 * remaining) as u64),
+                        )
+                    }
+                    Some(TransferStatus::Verifying { current, total, .. }) if *current > 0 => {
+                        let remaining = total.saturating_sub(*current) as f64;
+                        (None, Some((elapsed / *current as f64 * remaining) as u64))
+                    }
+                    Some(TransferStatus::GeneratingParity { percentage }) if *percentage > 0 => {
+                        let remaining = 100u8.saturating_sub(*percentage) as f64;
+                        (
+                            None,
+                            Some((elapsed / *percentage as f64 * remaining) as u64),
+                        )
+                    }
+                    _ => (None, None),
+                };
+
+                let details = JobProgressDetails {
+                    info: job.info.clone(),
+                    phase: job.phase.to_string(),
+                    phase_secs: elapsed as u64,
+                    bytes_per_sec,
+                    eta_seconds,
+                };
+                (id.clone(), details)
+            })
+            .collect()
+    }
+
+    /// IDs of the jobs registered and not yet removed, whether or not they
+    /// have reported progress.
+    pub async fn running(&self) -> Vec<String> {
+        self.jobs.read().await.keys().cloned().collect()
+    }
+
     /// Get the number of currently active jobs.
     pub async fn active_count(&self) -> usize {
         let map = self.inner.read().await;
@@ -60,10 +278,58 @@ impl ProgressTracker {
     }
 }
 
+/// Span the peak copy rate is measured over, so a burst into the page cache
+/// doesn't count as the peak.
+const PEAK_WINDOW: Duration = Duration::from_secs(3);
+
+/// Fastest copy rate across a job's `InProgress` updates.
+#[derive(Debug, Default)]
+pub struct PeakRate {
+    window_start: Option<(Duration, u64)>,
+    peak: u64,
+}
+
+impl PeakRate {
+    /// Note `bytes_copied` as of `elapsed` on any clock that started before
+    /// the first call.
+    pub fn record(&mut self, elapsed: Duration, bytes_copied: u64) {
+        let Some((started, start_bytes)) = self.window_start else {
+            self.window_start = Some((elapsed, bytes_copied));
+            return;
+        };
+        let span = elapsed.saturating_sub(started);
+        if span >= PEAK_WINDOW {
+            let rate = bytes_copied.saturating_sub(start_bytes) as f64 / span.as_secs_f64();
+            self.peak = self.peak.max(rate as u64);
+            self.window_start = Some((elapsed, bytes_copied));
+        }
+    }
+
+    /// Highest rate in bytes/s over a full window; 0 for copies too short to
+    /// fill one.
+    pub fn peak(&self) -> u64 {
+        self.peak
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_peak_rate_uses_full_windows() {
+        let mut peak = PeakRate::default();
+        let mb = 1024 * 1024;
+        peak.record(Duration::from_secs(0), 0);
+        // A 1s burst doesn't close a window on its own
+        peak.record(Duration::from_secs(1), 500 * mb);
+        assert_eq!(peak.peak(), 0);
+        peak.record(Duration::from_secs(3), 600 * mb);
+        assert_eq!(peak.peak(), 200 * mb);
+        peak.record(Duration::from_secs(6), 750 * mb);
+        assert_eq!(peak.peak(), 200 * mb);
+    }
+
     #[tokio::test]
     async fn test_progress_tracker_basic_operations() {
         let tracker = ProgressTracker::new();
@@ -82,6 +348,7 @@ mod tests {
                     current_file: "test.txt".to_string(),
                     percentage: 50,
                     eta_seconds: Some(10),
+                    bytes_per_sec: 0,
                 },
             )
             .await;
@@ -102,6 +369,7 @@ mod tests {
                     current_file: "test.txt".to_string(),
                     percentage: 75,
                     eta_seconds: Some(5),
+                    bytes_per_sec: 50,
                 },
             )
             .await;
@@ -132,6 +400,7 @@ mod tests {
                     current_file: "file.txt".to_string(),
                     percentage: 50,
                     eta_seconds: None,
+                    bytes_per_sec: 0,
                 },
             )
             .await;
@@ -145,4 +414,75 @@ mod tests {
         assert!(all.contains_key("job-2"));
         assert!(all.contains_key("job-3"));
     }
+
+    #[tokio::test]
+    async fn test_progress_tracker_details() {
+        let tracker = ProgressTracker::new();
+        let controls = tracker
+            .register(
+                "job-1",
+                JobInfo {
+                    device_label: "EOS_DIGITAL".to_string(),
+                    device_uuid: "card-1".to_string(),
+                    source: PathBuf::from("/media/eos"),
+                    destination: PathBuf::from("/backups/eos"),
+                },
+            )
+            .await;
+
+        let details = tracker.get_details().await;
+        assert_eq!(details["job-1"].phase, "scanning");
+        assert_eq!(details["job-1"].info.device_label, "EOS_DIGITAL");
+
+        tracker
+            .update(
+                "job-1",
+                TransferStatus::Verifying {
+                    current: 1,
+                    total: 4,
+                    bytes_verified: 0,
+                    total_bytes: 0,
+                    bytes_per_sec: 0,
+                },
+            )
+            .await;
+        let details = tracker.get_details().await;
+        assert_eq!(details["job-1"].phase, "verifying");
+        assert!(details["job-1"].eta_seconds.is_some());
+        assert_eq!(details["job-1"].bytes_per_sec, None);
+
+        tracker
+            .update(
+                "job-1",
+                TransferStatus::Verifying {
+                    current: 1,
+                    total: 4,
+                    bytes_verified: 1 << 30,
+                    total_bytes: 4 << 30,
+                    bytes_per_sec: 180 << 20,
+                },
+            )
+            .await;
+        let details = tracker.get_details().await;
+        assert_eq!(details["job-1"].bytes_per_sec, Some(180 << 20));
+        assert!(details["job-1"].eta_seconds.is_some());
+
+        assert!(tracker.pause("job-1").await);
+        assert!(!tracker.pause("job-1").await);
+        assert!(controls.pause.is_paused());
+        assert!(tracker.resume("job-1").await);
+        assert!(!controls.pause.is_paused());
+
+        assert!(tracker.device_removed("card-2").await.is_empty());
+        assert!(!controls.cancel.is_cancelled());
+        assert!(tracker.cancel("job-1").await);
+        assert!(controls.cancel.is_cancelled());
+        assert!(!controls.device_removed());
+        assert_eq!(tracker.device_removed("card-1").await, vec!["job-1"]);
+        assert!(controls.device_removed());
+
+        tracker.remove("job-1").await;
+        assert!(tracker.get_details().await.is_empty());
+        assert!(!tracker.cancel("job-1").await);
+    }
 }
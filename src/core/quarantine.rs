@@ -0,0 +1,162 @@
+//! Quarantine ingest for untrusted cards.
+//!
+//! A device rule with `quarantine = true` is mounted read-only and copied
+//! without ownership changes into its own directory under the quarantine
+//! directory, away from the backup tree. Once the copy has verified, the
+//! rule's `scan_command` (a malware scanner) runs over it; only when that
+//! passes is the directory renamed into its real destination. A failed scan
+//! fails the job and leaves the files where they are for inspection.
+
+use std::path::PathBuf;
+
+use anyhow::{Result, anyhow, bail};
+
+use crate::config::AppConfig;
+use crate::core::completion::{self, CompletedJob};
+use crate::core::partial;
+
+/// Quarantine handling for one device.
+#[derive(Debug, Clone)]
+pub struct Quarantine {
+    pub directory: PathBuf,
+    pub scan_command: Option<String>,
+}
+
+/// Quarantine directory, defaulting to `.quarantine` in the backup directory.
+pub fn directory(config: &AppConfig) -> PathBuf {
+    config
+        .quarantine_directory
+        .clone()
+        .unwrap_or_else(|| config.backup_directory.join(".quarantine"))
+}
+
+/// Quarantine handling for a device, if a rule matching its filesystem UUID
+/// or volume label asks for it (UUID rule's scan command first).
+pub fn for_device(config: &AppConfig, uuid: &str, label: &str) -> Option<Quarantine> {
+    let rules: Vec<_> = [uuid, label]
+        .into_iter()
+        .filter_map(|key| config.devices.get(key))
+        .filter(|device| device.quarantine)
+        .collect();
+    if rules.is_empty() {
+        return None;
+    }
+
+    Some(Quarantine {
+        directory: directory(config),
+        scan_command: rules.iter().find_map(|device| device.scan_command.clone()),
+    })
+}
+
+impl Quarantine {
+    /// Where a job copies to until it is released.
+    pub fn staging(&self, job_id: &str) -> PathBuf {
+        self.directory.join(job_id)
+    }
+
+    /// Run the scan command over the staged copy, with the job in the usual
+    /// hook variables plus `BKSD_QUARANTINE_PATH`. Without a scan command
+    /// the copy passes as is.
+    pub async fn scan(&self, job: &CompletedJob) -> Result<()> {
+        let Some(command) = &self.scan_command else {
+            return Ok(());
+        };
+
+        let output = completion::hook_command(command, job)
+            .env("BKSD_QUARANTINE_PATH", self.staging(&job.job_id))
+            .output()
+            .await
+            .map_err(|e| anyhow!("Failed to run scan `{}`: {}", command, e))?;
+
+        if !output.status.success() {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let detail = [stdout.trim(), stderr.trim()]
+                .into_iter()
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+                .join("; ");
+            bail!("Scan `{}` failed ({}): {}", command, output.status, detail);
+        }
+        Ok(())
+    }
+
+    /// Move the staged copy into the job's destination in the backup tree,
+    /// syncing the directory it lands in.
+    pub async fn release(&self, job: &CompletedJob) -> Result<()> {
+        let staging = self.staging(&job.job_id);
+        if let Some(parent) = job.destination.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::rename(&staging, &job.destination)
+            .await
+            .map_err(|e| {
+                anyhow!(
+                    "Failed to move {} to {} (the quarantine directory must be on the \
+                     backup directory's filesystem): {}",
+                    staging.display(),
+                    job.destination.display(),
+                    e
+                )
+            })?;
+        partial::sync_parent(&job.destination)
+            .await
+            .map_err(|e| anyhow!("Failed to sync {}: {}", job.destination.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DeviceConfig;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    fn job(destination: &Path) -> CompletedJob {
+        CompletedJob {
+            job_id: "job-1".to_string(),
+            device_label: "UNTRUSTED".to_string(),
+            device_uuid: "1234-ABCD".to_string(),
+            source: PathBuf::from("/run/bksd/1234-ABCD"),
+            destination: destination.to_path_buf(),
+            total_bytes: 3,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scan_gates_release() {
+        let temp = tempdir().unwrap();
+        let mut config = AppConfig {
+            backup_directory: temp.path().to_path_buf(),
+            ..Default::default()
+        };
+        config.devices.insert(
+            "UNTRUSTED".to_string(),
+            DeviceConfig {
+                quarantine: true,
+                scan_command: Some("! grep -rq EICAR \"$BKSD_QUARANTINE_PATH\"".to_string()),
+                ..Default::default()
+            },
+        );
+
+        assert!(for_device(&config, "other", "EOS_DIGITAL").is_none());
+        let quarantine = for_device(&config, "1234-ABCD", "UNTRUSTED").unwrap();
+        let staging = quarantine.staging("job-1");
+        assert_eq!(staging, temp.path().join(".quarantine/job-1"));
+
+        let job = job(&temp.path().join("UNTRUSTED/2024-01-01"));
+        fs::create_dir_all(staging.join("DCIM")).unwrap();
+        fs::write(staging.join("DCIM/payload.exe"), b"EICAR").unwrap();
+
+        // A flagged copy stays in quarantine
+        assert!(quarantine.scan(&job).await.is_err());
+        assert!(staging.join("DCIM/payload.exe").exists());
+
+        fs::write(staging.join("DCIM/payload.exe"), b"raw").unwrap();
+        quarantine.scan(&job).await.unwrap();
+        quarantine.release(&job).await.unwrap();
+        assert!(job.destination.join("DCIM/payload.exe").exists());
+        assert!(!staging.exists());
+    }
+}
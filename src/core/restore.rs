@@ -0,0 +1,296 @@
+//! Restoring single files from a backup.
+//!
+//! A file is found in its job's backup directory (on the archive drive, if
+//! the job has been tiered off and the drive is connected), copied to the
+//! target and checked against the hash recorded when it was backed up, so a
+//! single clip can be pulled back without copying the whole job.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::core::archive;
+use crate::core::transfer_engine::FileHash;
+use crate::db::Store;
+
+/// A file in a job's backup, where it is now.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackedUpFile {
+    pub job_id: String,
+    pub relative_path: PathBuf,
+    /// Absolute path on the backup directory or archive drive
+    pub path: PathBuf,
+    pub size: u64,
+    /// BLAKE3 hash (hex) recorded at backup time. Engines that verify
+    /// internally (rsync) keep no manifest, so there may be none
+    pub hash: Option<String>,
+}
+
+/// Where a job's backup is, with the files recorded for it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupLocation {
+    pub job_id: String,
+    pub path: PathBuf,
+    /// Manifest with each file's hash; empty for engines that keep none
+    pub files: Vec<FileHash>,
+}
+
+/// Split a `<job_id>:<relative_path>` file spec.
+pub fn parse_spec(spec: &str) -> Result<(String, PathBuf)> {
+    match spec.split_once(':') {
+        Some((job_id, path)) if !job_id.is_empty() && !path.is_empty() => {
+            Ok((job_id.to_string(), PathBuf::from(path)))
+        }
+        _ => bail!("Expected <job_id>:<relative_path>, got '{}'", spec),
+    }
+}
+
+/// Reject paths that would reach outside a backup.
+pub fn check_relative(relative: &Path) -> Result<()> {
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        bail!(
+            "Path must be relative to the backup: {}",
+            relative.display()
+        );
+    }
+    Ok(())
+}
+
+/// Find a job's backup, checking it is readable (its archive drive, if it
+/// has one, is connected).
+pub async fn backup(store: &dyn Store, job_id: &str) -> Result<BackupLocation> {
+    let job = store.get_with_history(job_id.to_string()).await?.job;
+    archive::ensure_available(store, job_id).await?;
+    let path = job
+        .destination_path
+        .map(PathBuf::from)
+        .filter(|path| path.is_dir())
+        .ok_or_else(|| anyhow!("Job {} has no backup directory", job_id))?;
+
+    Ok(BackupLocation {
+        job_id: job_id.to_string(),
+        path,
+        files: store.list_files(job_id.to_string()).await?,
+    })
+}
+
+/// Find a file in a job's backup.
+pub async fn locate(store: &dyn Store, job_id: &str, relative: &Path) -> Result<BackedUpFile> {
+    check_relative(relative)?;
+    let backup = backup(store, job_id).await?;
+
+    let path = backup.path.join(relative);
+    let metadata = fs::metadata(&path)
+        .ok()
+        .filter(|m| m.is_file())
+        .ok_or_else(|| anyhow!("{} is not in backup {}", relative.display(), job_id))?;
+
+    let hash = backup
+        .files
+        .into_iter()
+        .find(|f| f.relative_path == relative)
+        .map(|f| blake3::Hash::from(f.hash).to_hex().to_string());
+
+    Ok(BackedUpFile {
+        job_id: job_id.to_string(),
+        relative_path: relative.to_path_buf(),
+        path,
+        size: metadata.len(),
+        hash,
+    })
+}
+
+/// Files in a backup under each of `subpaths` (the whole backup when there
+/// are none), relative to it and sorted. Symlinks are left out.
+pub fn walk(root: &Path, subpaths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    fn visit(root: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+        let path = root.join(relative);
+        let metadata = fs::symlink_metadata(&path)
+            .with_context(|| format!("{} is not in the backup", relative.display()))?;
+        if metadata.is_file() {
+            files.push(relative.to_path_buf());
+        } else if metadata.is_dir() {
+            for entry in fs::read_dir(&path)? {
+                visit(root, &relative.join(entry?.file_name()), files)?;
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    if subpaths.is_empty() {
+        visit(root, Path::new(""), &mut files)?;
+    }
+    for subpath in subpaths {
+        check_relative(subpath)?;
+        visit(root, subpath, &mut files)?;
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Copy a backed-up file to `target` (into it, if it is a directory) and
+/// check it against the recorded hash. Never overwrites an existing file.
+/// Returns the restored file's path.
+pub async fn restore(file: &BackedUpFile, target: &Path) -> Result<PathBuf> {
+    let target = if target.is_dir() {
+        let name = file
+            .relative_path
+            .file_name()
+            .ok_or_else(|| anyhow!("{} has no file name", file.relative_path.display()))?;
+        target.join(name)
+    } else {
+        target.to_path_buf()
+    };
+
+    let file = file.clone();
+    tokio::task::spawn_blocking(move || {
+        let hash = copy_hashing(&file.path, &target)?;
+        if let Some(expected) = &file.hash
+            && hash.to_hex().as_str() != expected
+        {
+            let _ = fs::remove_file(&target);
+            bail!(
+                "Verification failed: {} does not match the hash recorded for job {}",
+                file.relative_path.display(),
+                file.job_id
+            );
+        }
+        // Keep the clip's original modification time
+        if let Ok(mtime) = fs::metadata(&file.path).and_then(|m| m.modified()) {
+            let _ = filetime::set_file_mtime(&target, filetime::FileTime::from_system_time(mtime));
+        }
+        Ok(target)
+    })
+    .await?
+}
+
+fn copy_hashing(source: &Path, target: &Path) -> Result<blake3::Hash> {
+    let mut reader =
+        File::open(source).with_context(|| format!("Failed to open {}", source.display()))?;
+    let mut writer = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(target)
+        .with_context(|| format!("Failed to create {}", target.display()))?;
+
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0u8; 128 * 1024];
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+        writer.write_all(&buffer[..n])?;
+    }
+    writer.sync_all()?;
+    Ok(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::TargetDrive;
+    use crate::db::memory::MemoryStore;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_spec() {
+        let (job, path) = parse_spec("job-1:DCIM/100CANON/IMG_0001.CR3").unwrap();
+        assert_eq!(job, "job-1");
+        assert_eq!(path, PathBuf::from("DCIM/100CANON/IMG_0001.CR3"));
+        assert!(parse_spec("job-1").is_err());
+        assert!(parse_spec(":DCIM").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_restore_single_file() {
+        let temp = tempdir().unwrap();
+        let backup = temp.path().join("backup");
+        let target = temp.path().join("restored");
+        fs::create_dir_all(backup.join("DCIM")).unwrap();
+        fs::create_dir_all(&target).unwrap();
+        fs::write(backup.join("DCIM/IMG_0001.CR3"), b"raw").unwrap();
+        fs::write(backup.join("DCIM/IMG_0002.CR3"), b"bitrot").unwrap();
+
+        let store = MemoryStore::open(None).unwrap();
+        let drive = TargetDrive {
+            uuid: "card-1".to_string(),
+            label: "EOS_DIGITAL".to_string(),
+            mount_path: "/run/bksd/card-1".to_string(),
+            raw_size: 64,
+        };
+        store
+            .create_job(
+                "job-1".to_string(),
+                drive,
+                backup.to_string_lossy().to_string(),
+                "host".to_string(),
+                Default::default(),
+            )
+            .await
+            .unwrap();
+        for (path, content) in [("DCIM/IMG_0001.CR3", b"raw"), ("DCIM/IMG_0002.CR3", b"raw")] {
+            store
+                .record_file(
+                    "job-1".to_string(),
+                    FileHash {
+                        relative_path: PathBuf::from(path),
+                        hash: *blake3::hash(content).as_bytes(),
+                        size: 3,
+                    },
+                )
+                .await
+                .unwrap();
+        }
+
+        let file = locate(&store, "job-1", Path::new("DCIM/IMG_0001.CR3"))
+            .await
+            .unwrap();
+        let restored = restore(&file, &target).await.unwrap();
+        assert_eq!(restored, target.join("IMG_0001.CR3"));
+        assert_eq!(fs::read(&restored).unwrap(), b"raw");
+
+        // Never overwrites
+        assert!(restore(&file, &target).await.is_err());
+
+        // A copy that doesn't match its recorded hash is removed again
+        let file = locate(&store, "job-1", Path::new("DCIM/IMG_0002.CR3"))
+            .await
+            .unwrap();
+        assert!(restore(&file, &target).await.is_err());
+        assert!(!target.join("IMG_0002.CR3").exists());
+
+        assert_eq!(
+            walk(&backup, &[]).unwrap(),
+            vec![
+                PathBuf::from("DCIM/IMG_0001.CR3"),
+                PathBuf::from("DCIM/IMG_0002.CR3")
+            ]
+        );
+        assert_eq!(
+            walk(&backup, &[PathBuf::from("DCIM/IMG_0002.CR3")]).unwrap(),
+            vec![PathBuf::from("DCIM/IMG_0002.CR3")]
+        );
+        assert!(walk(&backup, &[PathBuf::from("../backup")]).is_err());
+
+        assert!(
+            locate(&store, "job-1", Path::new("../backup/DCIM/IMG_0001.CR3"))
+                .await
+                .is_err()
+        );
+        assert!(
+            locate(&store, "job-1", Path::new("DCIM/missing.CR3"))
+                .await
+                .is_err()
+        );
+    }
+}
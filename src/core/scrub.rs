@@ -0,0 +1,490 @@
+//! Scheduled re-verification ("scrubbing") of old backups.
+//!
+//! With `scrub_schedule` set, the daemon wakes on a cron-style schedule and
+//! re-hashes up to `scrub_jobs_per_run` completed backups at least
+//! `scrub_min_age_days` old against the manifests recorded when they were
+//! made, starting with those that have gone longest without a check. Every
+//! scrub is recorded, and files that no longer match - bit-rot on the
+//! backup disk, or something changing the backup behind bksd's back - are
+//! sent to the notifier. Backups on a disconnected archive drive wait for
+//! it to come back. `verify.run` starts a scrub on request.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Result, anyhow, bail};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, Timelike, Utc};
+use tracing::{info, warn};
+
+use crate::context::AppContext;
+use crate::core::notifications::JobEvent;
+use crate::core::restore;
+use crate::core::timestamp::{self, DisplayZone};
+use crate::core::verifier;
+use crate::db::scrubs::{DamagedFile, ScrubResult, ScrubStatus};
+
+/// How far ahead to look for the next matching minute before deciding a
+/// schedule never fires (e.g. `0 0 30 2 *`)
+const LOOKAHEAD_DAYS: i64 = 5 * 366;
+
+/// A cron expression: `minute hour day-of-month month day-of-week`, each a
+/// `*`, a value, a range, a list or a `/step` of them; or one of `@hourly`,
+/// `@daily`, `@weekly`, `@monthly`, `@yearly`. As in cron, when both day
+/// fields are restricted a day matching either one fires.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    /// Sunday is 0
+    weekdays: u64,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl FromStr for Schedule {
+    type Err = anyhow::Error;
+
+    fn from_str(expression: &str) -> Result<Self> {
+        let expanded = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            bail!(
+                "Schedule \"{}\" needs 5 fields: minute hour day month weekday",
+                expression
+            );
+        };
+
+        let field = |text: &str, name: &str, min: u32, max: u32| {
+            parse_field(text, min, max)
+                .map_err(|e| anyhow!("Invalid {} in schedule \"{}\": {}", name, expression, e))
+        };
+        let weekdays = field(weekday, "weekday", 0, 7)?;
+        Ok(Schedule {
+            expression: expression.trim().to_string(),
+            minutes: field(minute, "minute", 0, 59)?,
+            hours: field(hour, "hour", 0, 23)?,
+            days: field(day, "day", 1, 31)?,
+            months: field(month, "month", 1, 12)?,
+            // 7 is Sunday too
+            weekdays: (weekdays | weekdays >> 7) & 0x7f,
+            days_restricted: day != "*",
+            weekdays_restricted: weekday != "*",
+        })
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+/// Parse one cron field into a bitmask of the values it allows.
+fn parse_field(text: &str, min: u32, max: u32) -> Result<u64> {
+    let mut mask = 0u64;
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, Some(step.parse::<u32>()?)),
+            None => (part, None),
+        };
+        let (low, high) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((low, high)) => (low.parse()?, high.parse()?),
+            // `5/15` runs from 5 to the end of the range
+            None if step.is_some() => (range.parse()?, max),
+            None => {
+                let value = range.parse()?;
+                (value, value)
+            }
+        };
+        if low < min || high > max || low > high {
+            bail!("{} is outside {}-{}", part, min, max);
+        }
+        let step = step.unwrap_or(1);
+        if step == 0 {
+            bail!("step can't be 0");
+        }
+        for value in (low..=high).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+impl Schedule {
+    /// First time after `after` that the schedule fires, reading it as
+    /// wall-clock time in `zone`. Times skipped by clocks going forward
+    /// don't fire. None if it never fires.
+    pub fn next_after(&self, after: DateTime<Utc>, zone: DisplayZone) -> Option<DateTime<Utc>> {
+        let local = zone.to_local(after);
+        let start =
+            local.date().and_hms_opt(local.hour(), local.minute(), 0)? + Duration::minutes(1);
+        let limit = start + Duration::days(LOOKAHEAD_DAYS);
+
+        let mut t = start;
+        while t < limit {
+            if !has(self.months, t.month()) {
+                let (year, month) = match t.month() {
+                    12 => (t.year() + 1, 1),
+                    month => (t.year(), month + 1),
+                };
+                t = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.day_matches(t) {
+                t = midnight_after(t)?;
+            } else if !has(self.hours, t.hour()) {
+                t = t.date().and_hms_opt(t.hour(), 0, 0)? + Duration::hours(1);
+            } else if !has(self.minutes, t.minute()) {
+                t += Duration::minutes(1);
+            } else if let Some(at) = zone.from_local(t) {
+                return Some(at);
+            } else {
+                t += Duration::minutes(1);
+            }
+        }
+        None
+    }
+
+    fn day_matches(&self, t: NaiveDateTime) -> bool {
+        let day = has(self.days, t.day());
+        let weekday = has(self.weekdays, t.weekday().num_days_from_sunday());
+        if self.days_restricted && self.weekdays_restricted {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+fn has(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+fn midnight_after(t: NaiveDateTime) -> Option<NaiveDateTime> {
+    t.date().succ_opt()?.and_hms_opt(0, 0, 0)
+}
+
+/// Lets one scrub run at a time, whether scheduled or requested.
+#[derive(Clone, Default)]
+pub struct Scrubber {
+    running: Arc<AtomicBool>,
+}
+
+/// Held for the length of a scrub run
+pub struct ScrubGuard(Arc<AtomicBool>);
+
+impl Scrubber {
+    /// Claim the scrubber; None if a scrub is already running.
+    pub fn try_start(&self) -> Option<ScrubGuard> {
+        self.running
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .ok()
+            .map(|_| ScrubGuard(self.running.clone()))
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Acquire)
+    }
+}
+
+impl Drop for ScrubGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/// Re-hash one job's backup against its manifest, record the result and
+/// notify if files are damaged. A backup that can't be checked (folder gone,
+/// archive drive disconnected, no manifest) is recorded as an `error`.
+pub async fn scrub_job(ctx: &AppContext, job_id: &str) -> Result<ScrubResult> {
+    let started_at = timestamp::now_utc();
+    let job = ctx.db.get_with_history(job_id.to_string()).await?.job;
+    info!(job_id = %job_id, "Scrubbing backup");
+
+    let mut result = ScrubResult {
+        job_id: job_id.to_string(),
+        started_at,
+        finished_at: String::new(),
+        status: ScrubStatus::Ok,
+        files_checked: 0,
+        bytes_checked: 0,
+        damaged: Vec::new(),
+        error: None,
+    };
+    let location = match restore::backup(ctx.db.as_ref(), job_id).await {
+        Ok(location) if location.files.is_empty() => {
+            Err(anyhow!("No manifest was recorded for this backup"))
+        }
+        other => other,
+    };
+    match location {
+        Ok(location) => {
+            let (bytes, mismatches) = verifier::rehash(&location.path, &location.files).await?;
+            result.files_checked = location.files.len() as u64;
+            result.bytes_checked = bytes;
+            result.damaged = mismatches
+                .into_iter()
+                .map(|m| DamagedFile {
+                    relative_path: m.relative_path,
+                    reason: m.reason.describe().to_string(),
+                })
+                .collect();
+            if !result.damaged.is_empty() {
+                result.status = ScrubStatus::Damaged;
+                warn!(
+                    job_id = %job_id,
+                    damaged = result.damaged.len(),
+                    files = result.files_checked,
+                    "Scrub found damaged files"
+                );
+                if let Some(notifier) = &ctx.notifier {
+                    let event = JobEvent::BackupDamaged {
+                        job_id: job_id.to_string(),
+                        destination: location.path,
+                        label_fields: job.label_fields,
+                        files_checked: result.files_checked,
+                        damaged: result.damaged.clone(),
+                    };
+                    if let Err(e) = notifier.notify(event).await {
+                        warn!(error = %e, "Failed to send damaged backup notification");
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            warn!(job_id = %job_id, error = %e, "Backup could not be scrubbed");
+            result.status = ScrubStatus::Error;
+            result.error = Some(e.to_string());
+        }
+    }
+
+    result.finished_at = timestamp::now_utc();
+    ctx.db.record_scrub(result.clone()).await?;
+    Ok(result)
+}
+
+/// Scrub jobs one after another. `_guard` keeps other runs out until done.
+pub async fn run(ctx: AppContext, job_ids: Vec<String>, _guard: ScrubGuard) -> Vec<ScrubResult> {
+    let mut results = Vec::new();
+    for job_id in job_ids {
+        match scrub_job(&ctx, &job_id).await {
+            Ok(result) => results.push(result),
+            Err(e) => warn!(job_id = %job_id, error = %e, "Scrub failed"),
+        }
+    }
+    let damaged = results
+        .iter()
+        .filter(|r| r.status == ScrubStatus::Damaged)
+        .count();
+    info!(scrubbed = results.len(), damaged, "Scrub run finished");
+    results
+}
+
+/// The backups the next scheduled run would scrub.
+pub async fn due(ctx: &AppContext) -> Result<Vec<String>> {
+    Ok(ctx
+        .db
+        .scrub_candidates(ctx.config.scrub_min_age_days, ctx.config.scrub_jobs_per_run)
+        .await?
+        .into_iter()
+        .map(|job| job.id)
+        .collect())
+}
+
+/// Scrub on `schedule` until the task is aborted. A run still going when
+/// the next one is due makes that one skip.
+pub async fn run_scheduled(ctx: AppContext, schedule: Schedule) {
+    info!(schedule = %schedule, "Scrubbing old backups on schedule");
+    loop {
+        let Some(next) = schedule.next_after(Utc::now(), ctx.zone) else {
+            warn!(schedule = %schedule, "Scrub schedule never fires");
+            return;
+        };
+        tokio::time::sleep((next - Utc::now()).to_std().unwrap_or_default()).await;
+
+        let Some(guard) = ctx.scrubber.try_start() else {
+            info!("Previous scrub still running, skipping this one");
+            continue;
+        };
+        match due(&ctx).await {
+            Ok(job_ids) => {
+                run(ctx.clone(), job_ids, guard).await;
+            }
+            Err(e) => warn!(error = %e, "Failed to find backups to scrub"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::core::TargetDrive;
+    use crate::core::transfer_engine::FileHash;
+    use crate::db::Store;
+    use crate::db::memory::MemoryStore;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    fn at(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_schedule_next_after() {
+        let utc = DisplayZone::from_config(Some("UTC")).unwrap();
+        let daily: Schedule = "30 3 * * *".parse().unwrap();
+        assert_eq!(
+            daily.next_after(at("2026-03-01T02:00:00Z"), utc),
+            Some(at("2026-03-01T03:30:00Z"))
+        );
+        assert_eq!(
+            daily.next_after(at("2026-03-01T03:30:00Z"), utc),
+            Some(at("2026-03-02T03:30:00Z"))
+        );
+
+        // Sundays (7 and 0 alike), every 15 minutes from 1am to 2am
+        let sundays: Schedule = "*/15 1-2 * * 7".parse().unwrap();
+        assert_eq!(
+            sundays.next_after(at("2026-03-02T00:00:00Z"), utc),
+            Some(at("2026-03-08T01:00:00Z"))
+        );
+        assert_eq!(
+            sundays.next_after(at("2026-03-08T02:45:00Z"), utc),
+            Some(at("2026-03-15T01:00:00Z"))
+        );
+
+        // Either day field matches when both are restricted
+        let either: Schedule = "0 0 1 * 1".parse().unwrap();
+        assert_eq!(
+            either.next_after(at("2026-03-25T00:00:00Z"), utc),
+            Some(at("2026-03-30T00:00:00Z"))
+        );
+        assert_eq!(
+            either.next_after(at("2026-03-30T00:00:00Z"), utc),
+            Some(at("2026-04-01T00:00:00Z"))
+        );
+
+        // Wall-clock time in the configured zone
+        let sydney = DisplayZone::from_config(Some("Australia/Sydney")).unwrap();
+        assert_eq!(
+            daily.next_after(at("2026-03-01T00:00:00Z"), sydney),
+            Some(at("2026-03-01T16:30:00Z"))
+        );
+
+        let monthly: Schedule = "@monthly".parse().unwrap();
+        assert_eq!(
+            monthly.next_after(at("2026-12-15T00:00:00Z"), utc),
+            Some(at("2027-01-01T00:00:00Z"))
+        );
+        let never: Schedule = "0 0 30 2 *".parse().unwrap();
+        assert_eq!(never.next_after(at("2026-01-01T00:00:00Z"), utc), None);
+
+        assert!("0 3 * *".parse::<Schedule>().is_err());
+        assert!("60 3 * * *".parse::<Schedule>().is_err());
+        assert!("*/0 * * * *".parse::<Schedule>().is_err());
+        assert!("0 5-2 * * *".parse::<Schedule>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_scrub_finds_damage() {
+        let temp = tempdir().unwrap();
+        let backup = temp.path().join("backup");
+        std::fs::create_dir_all(backup.join("DCIM")).unwrap();
+        std::fs::write(backup.join("DCIM/IMG_0001.CR3"), b"raw").unwrap();
+        std::fs::write(backup.join("DCIM/IMG_0002.CR3"), b"raw").unwrap();
+
+        let store = Arc::new(MemoryStore::open(None).unwrap());
+        let drive = TargetDrive {
+            uuid: "card-1".to_string(),
+            label: "EOS_DIGITAL".to_string(),
+            mount_path: "/run/bksd/card-1".to_string(),
+            raw_size: 64,
+        };
+        store
+            .create_job(
+                "job-1".to_string(),
+                drive,
+                backup.to_string_lossy().to_string(),
+                "host".to_string(),
+                Default::default(),
+            )
+            .await
+            .unwrap();
+        for path in [
+            "DCIM/IMG_0001.CR3",
+            "DCIM/IMG_0002.CR3",
+            "DCIM/IMG_0003.CR3",
+        ] {
+            store
+                .record_file(
+                    "job-1".to_string(),
+                    FileHash {
+                        relative_path: PathBuf::from(path),
+                        hash: *blake3::hash(b"raw").as_bytes(),
+                        size: 3,
+                    },
+                )
+                .await
+                .unwrap();
+        }
+        store
+            .update_status(
+                "job-1".to_string(),
+                "complete".to_string(),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let config = AppConfig {
+            scrub_min_age_days: 0,
+            ..AppConfig::default()
+        };
+        let ctx = AppContext::new(config, store);
+
+        assert_eq!(due(&ctx).await.unwrap(), vec!["job-1".to_string()]);
+        let result = scrub_job(&ctx, "job-1").await.unwrap();
+        assert_eq!(result.status, ScrubStatus::Damaged);
+        assert_eq!(result.files_checked, 3);
+        assert_eq!(result.damaged.len(), 1);
+
+        std::fs::write(backup.join("DCIM/IMG_0001.CR3"), b"rot").unwrap();
+        std::fs::write(backup.join("DCIM/IMG_0003.CR3"), b"raw").unwrap();
+        let guard = ctx.scrubber.try_start().unwrap();
+        assert!(ctx.scrubber.try_start().is_none());
+        let results = run(ctx.clone(), vec!["job-1".to_string()], guard).await;
+        assert!(!ctx.scrubber.is_running());
+        assert_eq!(
+            results[0].damaged,
+            vec![DamagedFile {
+                relative_path: PathBuf::from("DCIM/IMG_0001.CR3"),
+                reason: "hash mismatch".to_string(),
+            }]
+        );
+
+        let recorded = ctx
+            .db
+            .list_scrubs(Some("job-1".to_string()), 10)
+            .await
+            .unwrap();
+        assert_eq!(recorded.len(), 2);
+        assert_eq!(recorded[0].damaged, results[0].damaged);
+
+        std::fs::remove_dir_all(&backup).unwrap();
+        let result = scrub_job(&ctx, "job-1").await.unwrap();
+        assert_eq!(result.status, ScrubStatus::Error);
+    }
+}
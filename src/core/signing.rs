@@ -0,0 +1,188 @@
+//! The daemon's ed25519 signing key.
+//!
+//! Generated on first use and kept as a hex seed in `signing_key` (default
+//! `/var/lib/bksd/signing.key`, readable only by its owner). The public half
+//! is what downstream tools trust when checking a `BKSD_OK` badge.
+//!
+//! `bksd key rotate` replaces the key and appends the old public key to
+//! `<signing_key>.retired`, so backups badged before the rotation still
+//! verify on this machine. A running daemon picks up the new key on restart.
+
+use std::ffi::OsString;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use tracing::info;
+
+use crate::core::timestamp;
+
+/// A public key that used to sign, from `<signing_key>.retired`
+#[derive(Debug, Clone)]
+pub struct RetiredKey {
+    pub public_key: VerifyingKey,
+    pub retired_at: String,
+}
+
+/// Load the signing key at `path`, generating it if the file doesn't exist.
+pub fn load_or_create(path: &Path) -> Result<SigningKey> {
+    if path.exists() {
+        return load(path);
+    }
+    generate(path)
+}
+
+/// Generate a new signing key at `path`, which must not exist yet.
+pub fn generate(path: &Path) -> Result<SigningKey> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let key = write_new(path)?;
+    info!(
+        path = %path.display(),
+        public_key = %hex(key.verifying_key().as_bytes()),
+        "Generated signing key"
+    );
+    Ok(key)
+}
+
+/// Replace the key at `path` with a new one, retiring the old public key.
+/// Returns the old public key and the new key.
+pub fn rotate(path: &Path) -> Result<(VerifyingKey, SigningKey)> {
+    let old = load(path)?.verifying_key();
+
+    // Write the new key beside the old one so a failure leaves the old in place
+    let staged = with_suffix(path, ".new");
+    let _ = std::fs::remove_file(&staged);
+    let key = write_new(&staged)?;
+
+    let mut retired = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(retired_path(path))
+        .with_context(|| format!("Failed to record retired key for {}", path.display()))?;
+    writeln!(retired, "{}  {}", hex(old.as_bytes()), timestamp::now_utc())?;
+    retired.sync_all()?;
+
+    std::fs::rename(&staged, path)
+        .with_context(|| format!("Failed to replace signing key {}", path.display()))?;
+    info!(
+        path = %path.display(),
+        retired = %hex(old.as_bytes()),
+        public_key = %hex(key.verifying_key().as_bytes()),
+        "Rotated signing key"
+    );
+    Ok((old, key))
+}
+
+fn write_new(path: &Path) -> Result<SigningKey> {
+    let mut seed = [0u8; 32];
+    getrandom::getrandom(&mut seed).map_err(|e| anyhow!("No randomness for signing key: {}", e))?;
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+        .with_context(|| format!("Failed to create signing key {}", path.display()))?;
+    writeln!(file, "{}", hex(&seed))?;
+    file.sync_all()?;
+    Ok(SigningKey::from_bytes(&seed))
+}
+
+/// Load an existing signing key.
+pub fn load(path: &Path) -> Result<SigningKey> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read signing key {}", path.display()))?;
+    let seed = parse_hex::<32>(text.trim())
+        .with_context(|| format!("{} is not a signing key", path.display()))?;
+    Ok(SigningKey::from_bytes(&seed))
+}
+
+/// Keys retired from `path` by earlier rotations, oldest first.
+pub fn retired(path: &Path) -> Result<Vec<RetiredKey>> {
+    let retired = retired_path(path);
+    let text = match std::fs::read_to_string(&retired) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", retired.display())),
+    };
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (key, retired_at) = line.split_once("  ").unwrap_or((line, ""));
+            Ok(RetiredKey {
+                public_key: parse_public_key(key)
+                    .with_context(|| format!("Bad line in {}", retired.display()))?,
+                retired_at: retired_at.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Public keys whose signatures this machine accepts: the current key and
+/// every retired one.
+pub fn trusted_keys(path: &Path) -> Result<Vec<VerifyingKey>> {
+    let mut keys = vec![load(path)?.verifying_key()];
+    keys.extend(retired(path)?.into_iter().map(|r| r.public_key));
+    Ok(keys)
+}
+
+/// Where retired public keys for the key at `path` are listed.
+pub fn retired_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".retired")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Parse a hex public key, e.g. from `--public-key`.
+pub fn parse_public_key(text: &str) -> Result<VerifyingKey> {
+    let bytes = parse_hex::<32>(text.trim()).context("Public key must be 64 hex digits")?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| anyhow!("Invalid public key: {}", e))
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn parse_hex<const N: usize>(text: &str) -> Result<[u8; N]> {
+    if text.len() != N * 2 || !text.is_ascii() {
+        return Err(anyhow!("Expected {} hex digits", N * 2));
+    }
+    let mut bytes = [0u8; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[i * 2..i * 2 + 2], 16)?;
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation_keeps_old_key_trusted() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("keys/signing.key");
+
+        let first = generate(&path).unwrap();
+        assert!(generate(&path).is_err());
+        assert_eq!(trusted_keys(&path).unwrap(), vec![first.verifying_key()]);
+
+        let (old, second) = rotate(&path).unwrap();
+        assert_eq!(old, first.verifying_key());
+        assert_eq!(load(&path).unwrap().verifying_key(), second.verifying_key());
+        assert_eq!(
+            trusted_keys(&path).unwrap(),
+            vec![second.verifying_key(), first.verifying_key()]
+        );
+        let retired = retired(&path).unwrap();
+        assert_eq!(retired.len(), 1);
+        assert!(timestamp::parse(&retired[0].retired_at).is_some());
+    }
+}
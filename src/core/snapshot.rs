@@ -0,0 +1,112 @@
+//! Filesystem snapshots of the backup directory.
+//!
+//! On btrfs and ZFS destinations a read-only snapshot taken after a job is
+//! verified gives point-in-time protection that survives later accidental
+//! edits or deletes of the copied files. Snapshots are created with the
+//! filesystem's own CLI tools and named after the job.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow, bail};
+use tokio::process::Command;
+use tracing::info;
+
+use crate::config::SnapshotMode;
+
+/// Directory (inside the backup subvolume) holding btrfs snapshots
+const BTRFS_SNAPSHOT_DIR: &str = ".snapshots";
+
+/// Snapshot name for a job
+fn snapshot_name(job_id: &str) -> String {
+    format!("bksd-{}", job_id)
+}
+
+/// Take a snapshot of the backup directory after a job completes.
+///
+/// Returns the snapshot identifier (a path for btrfs, `dataset@name` for
+/// ZFS), or None when snapshots are disabled.
+pub async fn capture(
+    mode: &SnapshotMode,
+    backup_dir: &Path,
+    job_id: &str,
+) -> Result<Option<String>> {
+    let snapshot = match mode {
+        SnapshotMode::None => return Ok(None),
+        SnapshotMode::Btrfs => btrfs_snapshot(backup_dir, job_id).await?,
+        SnapshotMode::Zfs => zfs_snapshot(backup_dir, job_id).await?,
+    };
+
+    info!(job_id = %job_id, snapshot = %snapshot, "Destination snapshot created");
+    Ok(Some(snapshot))
+}
+
+/// `btrfs subvolume snapshot -r <backup_dir> <backup_dir>/.snapshots/bksd-<job>`
+async fn btrfs_snapshot(backup_dir: &Path, job_id: &str) -> Result<String> {
+    let snapshot_dir = backup_dir.join(BTRFS_SNAPSHOT_DIR);
+    tokio::fs::create_dir_all(&snapshot_dir).await?;
+
+    let target: PathBuf = snapshot_dir.join(snapshot_name(job_id));
+    run(Command::new("btrfs")
+        .args(["subvolume", "snapshot", "-r"])
+        .arg(backup_dir)
+        .arg(&target))
+    .await?;
+
+    Ok(target.to_string_lossy().to_string())
+}
+
+/// `zfs snapshot <dataset>@bksd-<job>`, where the dataset is the one
+/// mounted at the backup directory.
+async fn zfs_snapshot(backup_dir: &Path, job_id: &str) -> Result<String> {
+    let dataset = run(Command::new("zfs")
+        .args(["list", "-H", "-o", "name"])
+        .arg(backup_dir))
+    .await?;
+    let dataset = dataset.trim();
+    if dataset.is_empty() {
+        bail!("No ZFS dataset found for {}", backup_dir.display());
+    }
+
+    let snapshot = format!("{}@{}", dataset, snapshot_name(job_id));
+    run(Command::new("zfs").arg("snapshot").arg(&snapshot)).await?;
+
+    Ok(snapshot)
+}
+
+/// Run a command, returning its stdout or an error carrying its stderr.
+pub(crate) async fn run(cmd: &mut Command) -> Result<String> {
+    let program = format!("{:?}", cmd.as_std().get_program());
+    let output = cmd
+        .output()
+        .await
+        .map_err(|e| anyhow!("Failed to run {}: {}", program, e))?;
+
+    if !output.status.success() {
+        bail!(
+            "{} exited with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_name() {
+        assert_eq!(snapshot_name("0192-abcd"), "bksd-0192-abcd");
+    }
+
+    #[tokio::test]
+    async fn test_capture_disabled() {
+        let result = capture(&SnapshotMode::None, Path::new("/tmp"), "job")
+            .await
+            .unwrap();
+        assert!(result.is_none());
+    }
+}
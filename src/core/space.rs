@@ -0,0 +1,132 @@
+//! Holding new backups while the backup drive is nearly full.
+//!
+//! With `min_free_space_gb` set, the daemon checks the backup directory's
+//! free space every `space_check_interval_secs`. Once it drops below the
+//! minimum, cards inserted from then on get a `queued` job instead of
+//! starting, and the notifier is told. Backups already running carry on.
+//! When space is freed again (old backups deleted or tiered to the archive
+//! drive) the held jobs start and the notifier hears about that too.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::context::AppContext;
+use crate::core::capacity;
+use crate::core::notifications::JobEvent;
+
+const GB: u64 = 1_000_000_000;
+
+/// Whether the backup directory is low on space, shared through
+/// `AppContext`.
+#[derive(Clone)]
+pub struct SpaceMonitor {
+    low: Arc<watch::Sender<bool>>,
+}
+
+impl Default for SpaceMonitor {
+    fn default() -> Self {
+        Self {
+            low: Arc::new(watch::channel(false).0),
+        }
+    }
+}
+
+impl SpaceMonitor {
+    /// Whether new jobs are to be held.
+    pub fn is_low(&self) -> bool {
+        *self.low.borrow()
+    }
+
+    /// Resolves once the backup directory isn't low on space.
+    pub async fn freed(&self) {
+        let _ = self.low.subscribe().wait_for(|low| !*low).await;
+    }
+
+    /// Record the latest check. Returns whether that changed the state.
+    pub(crate) fn set_low(&self, low: bool) -> bool {
+        self.low
+            .send_if_modified(|state| std::mem::replace(state, low) != low)
+    }
+}
+
+/// Check the backup directory's free space until the task is aborted.
+pub async fn run(ctx: AppContext, min_free_space_gb: u64) {
+    let min_free_bytes = min_free_space_gb.saturating_mul(GB);
+    let interval = Duration::from_secs(ctx.config.space_check_interval_secs.max(1));
+    info!(
+        min_free_space_gb,
+        "Holding new jobs while the backup directory is low on space"
+    );
+    loop {
+        let path = ctx.config.backup_directory.clone();
+        match tokio::task::spawn_blocking(move || capacity::disk_space(&path))
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|space| space)
+        {
+            Ok((_, free_bytes)) => check(&ctx, free_bytes, min_free_bytes).await,
+            Err(e) => warn!(error = %e, "Failed to check backup space"),
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Act on `free_bytes` left in the backup directory.
+async fn check(ctx: &AppContext, free_bytes: u64, min_free_bytes: u64) {
+    let low = free_bytes < min_free_bytes;
+    if !ctx.space.set_low(low) {
+        return;
+    }
+
+    let path = ctx.config.backup_directory.clone();
+    let event = if low {
+        warn!(
+            free_bytes,
+            min_free_bytes, "Backup directory low on space, holding new jobs"
+        );
+        JobEvent::SpaceLow {
+            path,
+            free_bytes,
+            min_free_bytes,
+        }
+    } else {
+        info!(free_bytes, "Backup space freed, starting held jobs");
+        JobEvent::SpaceFreed { path, free_bytes }
+    };
+    if let Some(notifier) = &ctx.notifier
+        && let Err(e) = notifier.notify(event).await
+    {
+        warn!(error = %e, "Failed to send backup space notification");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::db::memory::MemoryStore;
+
+    #[tokio::test]
+    async fn test_low_space_holds_jobs_until_freed() {
+        let db = Arc::new(MemoryStore::open(None).unwrap());
+        let ctx = AppContext::new(AppConfig::default(), db);
+        assert!(!ctx.space.is_low());
+
+        check(&ctx, 5 * GB, 10 * GB).await;
+        assert!(ctx.space.is_low());
+        // Still low: nothing changes
+        assert!(!ctx.space.set_low(true));
+
+        let space = ctx.space.clone();
+        let freed = tokio::spawn(async move { space.freed().await });
+        tokio::task::yield_now().await;
+        assert!(!freed.is_finished());
+
+        check(&ctx, 20 * GB, 10 * GB).await;
+        freed.await.unwrap();
+        assert!(!ctx.space.is_low());
+    }
+}
@@ -0,0 +1,96 @@
+//! Per-job btrfs subvolumes.
+//!
+//! With `btrfs_subvolumes` set and the backup directory on btrfs, each job
+//! is copied into a subvolume of its own, which is made read-only once the
+//! copy verifies. Unlike `destination_snapshot`, which snapshots the whole
+//! backup directory, this protects every backup separately and doesn't need
+//! the backup directory itself to be a subvolume.
+
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+use anyhow::Result;
+#[cfg(target_os = "linux")]
+use nix::sys::statfs::{BTRFS_SUPER_MAGIC, statfs};
+use tokio::process::Command;
+use tracing::info;
+
+use crate::core::snapshot::run;
+
+/// Inode number of every btrfs subvolume's root directory
+const SUBVOLUME_ROOT_INODE: u64 = 256;
+
+/// Whether `path`, or its nearest existing ancestor, is on btrfs.
+#[cfg(target_os = "linux")]
+pub fn is_btrfs(path: &Path) -> bool {
+    path.ancestors()
+        .find(|p| p.exists())
+        .and_then(|p| statfs(p).ok())
+        .is_some_and(|fs| fs.filesystem_type() == BTRFS_SUPER_MAGIC)
+}
+
+/// btrfs is Linux-only.
+#[cfg(not(target_os = "linux"))]
+pub fn is_btrfs(_path: &Path) -> bool {
+    false
+}
+
+/// Whether `path` is the root of a btrfs subvolume.
+pub fn is_subvolume(path: &Path) -> bool {
+    path.metadata()
+        .is_ok_and(|m| m.is_dir() && m.ino() == SUBVOLUME_ROOT_INODE)
+        && is_btrfs(path)
+}
+
+/// Create `path` as an empty subvolume, with its parent directories.
+pub async fn create(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    run(Command::new("btrfs")
+        .args(["subvolume", "create"])
+        .arg(path))
+    .await?;
+    info!(path = %path.display(), "Created backup subvolume");
+    Ok(())
+}
+
+/// Make a verified backup's subvolume read-only.
+pub async fn seal(path: &Path) -> Result<()> {
+    set_read_only(path, true).await?;
+    info!(path = %path.display(), "Backup subvolume made read-only");
+    Ok(())
+}
+
+/// Make a backup's subvolume writable again so it can be moved or removed.
+pub async fn unseal(path: &Path) -> Result<()> {
+    set_read_only(path, false).await
+}
+
+async fn set_read_only(path: &Path, read_only: bool) -> Result<()> {
+    run(Command::new("btrfs")
+        .args(["property", "set", "-ts"])
+        .arg(path)
+        .args(["ro", if read_only { "true" } else { "false" }]))
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_directory_is_not_subvolume() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().join("backup");
+        std::fs::create_dir(&dir).unwrap();
+        assert!(!is_subvolume(&dir));
+        assert!(!is_subvolume(&temp.path().join("missing")));
+        // Not-yet-created paths are judged by their parent's filesystem
+        assert_eq!(
+            is_btrfs(&temp.path().join("missing/deeper")),
+            is_btrfs(temp.path())
+        );
+    }
+}
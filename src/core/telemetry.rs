@@ -0,0 +1,307 @@
+//! Opt-in anonymized fleet telemetry.
+//!
+//! With `telemetry_endpoint` set, the daemon POSTs a small JSON summary of
+//! the jobs that finished in the last `telemetry_interval_hours` to it: job
+//! counts per outcome, jobs per day, the transfer engine and a throughput
+//! histogram. Nothing identifying goes out - no labels, paths, device UUIDs
+//! or hostnames. Stations are told apart by `kit_id`, a random ID generated
+//! on first use and kept in the data directory, so it stays the same across
+//! restarts. A report that can't be sent is folded into the next one.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::Serialize;
+use tracing::{debug, info, warn};
+
+use crate::context::AppContext;
+use crate::core::models::JobWithHistory;
+use crate::core::{signing, timestamp};
+use crate::db;
+
+/// Recent jobs looked at for each report
+const JOBS_SCANNED: u32 = 1000;
+
+/// File in the data directory holding the kit ID
+const KIT_ID_FILENAME: &str = "telemetry.id";
+
+/// Throughput buckets in MB/s: label and exclusive upper bound
+const BUCKETS: [(&str, f64); 4] = [
+    ("<50MB/s", 50.0),
+    ("50-150MB/s", 150.0),
+    ("150-400MB/s", 400.0),
+    (">=400MB/s", f64::INFINITY),
+];
+
+/// One anonymized report
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TelemetryReport {
+    pub kit_id: String,
+    pub version: String,
+    pub engine: String,
+    pub period_start: String,
+    pub period_end: String,
+    pub jobs: u32,
+    pub completed: u32,
+    pub failed: u32,
+    pub cancelled: u32,
+    pub jobs_per_day: f64,
+    /// Failed jobs as a fraction of all finished jobs; 0 with no jobs
+    pub failure_rate: f64,
+    /// Completed jobs per throughput bucket
+    pub throughput: BTreeMap<&'static str, u32>,
+}
+
+/// A finished job as far as telemetry cares
+#[derive(Debug, Clone)]
+pub struct FinishedJob {
+    pub status: String,
+    /// Average bytes per second, for completed jobs
+    pub bytes_per_sec: Option<f64>,
+}
+
+/// Anonymous identifier for this station, read from `path`, or generated
+/// and written there if it doesn't exist yet.
+pub fn kit_id(path: &Path) -> Result<String> {
+    match std::fs::read_to_string(path) {
+        Ok(text) if !text.trim().is_empty() => return Ok(text.trim().to_string()),
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut bytes = [0u8; 8];
+    getrandom::getrandom(&mut bytes).map_err(|e| anyhow!("No randomness for kit ID: {}", e))?;
+    let id = signing::hex(&bytes);
+    std::fs::write(path, format!("{}\n", id))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(id)
+}
+
+/// When `job` reached its final status, or None if it hasn't finished.
+fn finished_at(job: &JobWithHistory) -> Option<DateTime<Utc>> {
+    if !matches!(job.job.status.as_str(), "complete" | "failed" | "cancelled") {
+        return None;
+    }
+    job.history
+        .iter()
+        .rev()
+        .find(|entry| entry.status == job.job.status)
+        .and_then(|entry| timestamp::parse(&entry.created_at))
+}
+
+/// Summarize `jobs`, which finished between `start` and `end`.
+pub fn summarize(
+    kit_id: &str,
+    engine: &str,
+    jobs: &[FinishedJob],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> TelemetryReport {
+    let count = |status: &str| jobs.iter().filter(|j| j.status == status).count() as u32;
+    let (completed, failed, cancelled) = (count("complete"), count("failed"), count("cancelled"));
+
+    let mut throughput: BTreeMap<&'static str, u32> =
+        BUCKETS.iter().map(|(label, _)| (*label, 0)).collect();
+    for bytes_per_sec in jobs.iter().filter_map(|j| j.bytes_per_sec) {
+        let mb_per_sec = bytes_per_sec / 1_000_000.0;
+        if let Some((label, _)) = BUCKETS.iter().find(|(_, upper)| mb_per_sec < *upper) {
+            *throughput.entry(label).or_default() += 1;
+        }
+    }
+
+    let total = jobs.len() as u32;
+    let days = (end - start).num_seconds().max(1) as f64 / 86_400.0;
+    TelemetryReport {
+        kit_id: kit_id.to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        engine: engine.to_string(),
+        period_start: start.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        period_end: end.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        jobs: total,
+        completed,
+        failed,
+        cancelled,
+        jobs_per_day: total as f64 / days,
+        failure_rate: if total == 0 {
+            0.0
+        } else {
+            failed as f64 / total as f64
+        },
+        throughput,
+    }
+}
+
+/// Send a report every `telemetry_interval_hours` until the task is aborted.
+pub async fn run(ctx: AppContext, endpoint: String) {
+    let interval = Duration::from_secs(ctx.config.telemetry_interval_hours.max(1) * 3600);
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .unwrap_or_default();
+    let kit_id = match kit_id(&db::data_dir().join(KIT_ID_FILENAME)) {
+        Ok(id) => id,
+        Err(e) => {
+            warn!(error = %e, "No telemetry kit ID, not sending telemetry");
+            return;
+        }
+    };
+    let engine = ctx
+        .config
+        .transfer_engine
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default();
+
+    info!(endpoint = %endpoint, kit_id = %kit_id, "Sending anonymized telemetry");
+
+    let mut start = Utc::now();
+    loop {
+        tokio::time::sleep(interval).await;
+        let end = Utc::now();
+        let result = async {
+            let jobs = finished_jobs(&ctx, start, end).await?;
+            let report = summarize(&kit_id, &engine, &jobs, start, end);
+            let response = client.post(&endpoint).json(&report).send().await?;
+            if !response.status().is_success() {
+                bail!("Endpoint answered {}", response.status());
+            }
+            debug!(jobs = report.jobs, "Telemetry report sent");
+            Ok(())
+        }
+        .await;
+
+        match result {
+            Ok(()) => start = end,
+            Err(e) => warn!(error = %e, "Failed to send telemetry, will include it next time"),
+        }
+    }
+}
+
+/// Jobs that finished between `start` and `end`, whenever they started, so
+/// each is counted in exactly one report. Jobs still running are left for a
+/// later report.
+async fn finished_jobs(
+    ctx: &AppContext,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<FinishedJob>> {
+    let mut finished = Vec::new();
+    for job in ctx.db.list_jobs(JOBS_SCANNED, 0, None).await? {
+        if !matches!(job.status.as_str(), "complete" | "failed" | "cancelled") {
+            continue;
+        }
+        let job = ctx.db.get_with_history(job.id.clone()).await?;
+        if !finished_at(&job).is_some_and(|at| at >= start && at < end) {
+            continue;
+        }
+
+        let bytes_per_sec = if job.job.status == "complete" {
+            job.history
+                .iter()
+                .rev()
+                .find(|entry| entry.status == "complete")
+                .and_then(|entry| match (entry.total_bytes, entry.duration_secs) {
+                    (Some(bytes), Some(secs)) => Some(bytes as f64 / secs.max(1) as f64),
+                    _ => None,
+                })
+        } else {
+            None
+        };
+        finished.push(FinishedJob {
+            status: job.job.status,
+            bytes_per_sec,
+        });
+    }
+    Ok(finished)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::models::{Job, JobStatusEntry};
+
+    #[test]
+    fn test_summary_counts_and_buckets() {
+        let job = |status: &str, mbps: Option<f64>| FinishedJob {
+            status: status.to_string(),
+            bytes_per_sec: mbps.map(|m| m * 1_000_000.0),
+        };
+        let jobs = vec![
+            job("complete", Some(30.0)),
+            job("complete", Some(120.0)),
+            job("complete", Some(900.0)),
+            job("failed", None),
+        ];
+        let end = Utc::now();
+        let start = end - chrono::Duration::days(2);
+
+        let report = summarize("abc", "rsync", &jobs, start, end);
+        assert_eq!((report.jobs, report.completed, report.failed), (4, 3, 1));
+        assert_eq!(report.jobs_per_day, 2.0);
+        assert_eq!(report.failure_rate, 0.25);
+        assert_eq!(report.throughput["<50MB/s"], 1);
+        assert_eq!(report.throughput["50-150MB/s"], 1);
+        assert_eq!(report.throughput["150-400MB/s"], 0);
+        assert_eq!(report.throughput[">=400MB/s"], 1);
+
+        let empty = summarize("abc", "rsync", &[], start, end);
+        assert_eq!(empty.failure_rate, 0.0);
+    }
+
+    #[test]
+    fn test_finished_at_uses_final_status() {
+        let entry = |status: &str, at: &str| JobStatusEntry {
+            id: status.to_string(),
+            status: status.to_string(),
+            description: None,
+            total_bytes: None,
+            duration_secs: None,
+            stats: None,
+            created_at: at.to_string(),
+        };
+        let mut job = JobWithHistory {
+            job: Job {
+                id: "job-1".to_string(),
+                target_id: "card-1".to_string(),
+                destination_path: None,
+                created_at: "2026-03-01T23:50:00Z".to_string(),
+                status: "in_progress".to_string(),
+                archive_label: None,
+                origin_host: None,
+                label_fields: Default::default(),
+            },
+            history: vec![
+                entry("ready", "2026-03-01T23:50:00Z"),
+                entry("in_progress", "2026-03-01T23:51:00Z"),
+            ],
+            notices: Vec::new(),
+        };
+        assert_eq!(finished_at(&job), None);
+
+        // Started before midnight, counted in the report it finished in
+        job.job.status = "complete".to_string();
+        job.history.push(entry("complete", "2026-03-02T00:10:00Z"));
+        assert_eq!(finished_at(&job), timestamp::parse("2026-03-02T00:10:00Z"));
+    }
+
+    #[test]
+    fn test_kit_id_is_kept() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("bksd").join(KIT_ID_FILENAME);
+
+        let id = kit_id(&path).unwrap();
+        assert_eq!(id.len(), 16);
+        assert_eq!(kit_id(&path).unwrap(), id);
+
+        let other = temp.path().join("other.id");
+        assert_ne!(kit_id(&other).unwrap(), id);
+    }
+}
@@ -0,0 +1,161 @@
+//! Per-job timing breakdown derived from the status log.
+//!
+//! Each status entry marks the start of a phase (scanning the card, copying,
+//! verifying), so the time between consecutive phase changes is how long
+//! that phase took. `complete`/`failed` close the last phase; a job that is
+//! still running has an open phase measured up to now.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::core::models::JobStatusEntry;
+use crate::core::timestamp;
+
+/// One phase of a job and how long it took.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhaseTiming {
+    /// `queued`, `scan`, `copy`, `verify`, `upload`, `publish` or `mirror`
+    pub phase: String,
+    pub started_at: String,
+    /// None while the phase is still running
+    pub ended_at: Option<String>,
+    pub duration_secs: f64,
+}
+
+/// Timing breakdown for a job.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobTimeline {
+    pub job_id: String,
+    pub phases: Vec<PhaseTiming>,
+    /// Sum of the phase durations
+    pub total_secs: f64,
+    /// Whether the job has reached `complete` or `failed`
+    pub finished: bool,
+}
+
+/// Phase a status log entry starts, or None for terminal and unrecognised
+/// statuses.
+fn phase_for(status: &str) -> Option<&'static str> {
+    match status.to_ascii_lowercase().as_str() {
+        "queued" => Some("queued"),
+        "ready" => Some("scan"),
+        "in_progress" => Some("copy"),
+        "copy_complete" | "verifying" => Some("verify"),
+        "generating_parity" => Some("parity"),
+        "uploading" => Some("upload"),
+        "publishing" => Some("publish"),
+        "mirror_copying" => Some("mirror"),
+        _ => None,
+    }
+}
+
+fn is_terminal(status: &str) -> bool {
+    ["complete", "failed", "cancelled", "skipped"]
+        .iter()
+        .any(|s| status.eq_ignore_ascii_case(s))
+}
+
+/// Build the timeline for a job from its status history (oldest first).
+pub fn from_history(job_id: &str, history: &[JobStatusEntry], now: DateTime<Utc>) -> JobTimeline {
+    let mut phases: Vec<PhaseTiming> = Vec::new();
+    let mut open: Option<(&'static str, DateTime<Utc>, &str)> = None;
+    let mut finished = false;
+
+    let mut close = |open: &mut Option<(&'static str, DateTime<Utc>, &str)>,
+                     at: DateTime<Utc>,
+                     ended_at: Option<&str>| {
+        if let Some((phase, started, started_at)) = open.take() {
+            phases.push(PhaseTiming {
+                phase: phase.to_string(),
+                started_at: started_at.to_string(),
+                ended_at: ended_at.map(str::to_string),
+                duration_secs: (at - started).num_milliseconds().max(0) as f64 / 1000.0,
+            });
+        }
+    };
+
+    for entry in history {
+        let Some(at) = timestamp::parse(&entry.created_at) else {
+            continue;
+        };
+
+        if is_terminal(&entry.status) {
+            close(&mut open, at, Some(&entry.created_at));
+            finished = true;
+        } else if let Some(phase) = phase_for(&entry.status)
+            && open.is_none_or(|(current, _, _)| current != phase)
+        {
+            // A resumed job starts over after its `failed` entry
+            close(&mut open, at, Some(&entry.created_at));
+            open = Some((phase, at, &entry.created_at));
+            finished = false;
+        }
+    }
+    close(&mut open, now, None);
+
+    let total_secs = phases.iter().map(|p| p.duration_secs).sum();
+    JobTimeline {
+        job_id: job_id.to_string(),
+        phases,
+        total_secs,
+        finished,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(status: &str, created_at: &str) -> JobStatusEntry {
+        JobStatusEntry {
+            id: String::new(),
+            status: status.to_string(),
+            description: None,
+            total_bytes: None,
+            duration_secs: None,
+            stats: None,
+            created_at: created_at.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_completed_job_phases() {
+        let history = vec![
+            entry("Ready", "2025-01-14T10:00:00.000Z"),
+            entry("in_progress", "2025-01-14T10:00:05.000Z"),
+            entry("in_progress", "2025-01-14T10:00:35.000Z"),
+            entry("copy_complete", "2025-01-14T10:02:05.000Z"),
+            entry("verifying", "2025-01-14T10:02:06.000Z"),
+            entry("complete", "2025-01-14T10:03:05.000Z"),
+        ];
+        let timeline = from_history("job-1", &history, Utc::now());
+
+        let phases: Vec<(&str, f64)> = timeline
+            .phases
+            .iter()
+            .map(|p| (p.phase.as_str(), p.duration_secs))
+            .collect();
+        assert_eq!(
+            phases,
+            vec![("scan", 5.0), ("copy", 120.0), ("verify", 60.0)]
+        );
+        assert_eq!(timeline.total_secs, 185.0);
+        assert!(timeline.finished);
+    }
+
+    #[test]
+    fn test_running_job_measures_to_now() {
+        let history = vec![
+            entry("Ready", "2025-01-14T10:00:00.000Z"),
+            entry("in_progress", "2025-01-14T10:00:10.000Z"),
+        ];
+        let now = timestamp::parse("2025-01-14T10:01:10.000Z").unwrap();
+        let timeline = from_history("job-1", &history, now);
+
+        assert!(!timeline.finished);
+        let copy = timeline.phases.last().unwrap();
+        assert_eq!(copy.phase, "copy");
+        assert_eq!(copy.duration_secs, 60.0);
+        assert!(copy.ended_at.is_none());
+    }
+}
@@ -0,0 +1,123 @@
+//! Timestamp storage and display.
+//!
+//! Everything persisted is UTC in RFC3339 form, so stations in different
+//! zones (or a station whose zone changes) agree on ordering. Only
+//! presentation - destination folder names, TUI columns - and the scrub
+//! schedule are in the configured `timezone`, falling back to the system's
+//! local zone.
+
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Local, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// Current time as stored in the database.
+pub fn now_utc() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Parse a stored timestamp. Rows written before timestamps were RFC3339
+/// use SQLite's `YYYY-MM-DD HH:MM:SS`, which is also UTC.
+pub fn parse(stored: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(stored)
+        .map(|t| t.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(stored, "%Y-%m-%d %H:%M:%S").map(|t| t.and_utc())
+        })
+        .ok()
+}
+
+/// Zone used to present timestamps.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum DisplayZone {
+    #[default]
+    Local,
+    Named(Tz),
+}
+
+impl DisplayZone {
+    /// Resolve the `timezone` config option (an IANA name such as
+    /// `Europe/Berlin`); unset means the system zone.
+    pub fn from_config(timezone: Option<&str>) -> Result<Self> {
+        match timezone {
+            None => Ok(DisplayZone::Local),
+            Some(name) => Tz::from_str(name)
+                .map(DisplayZone::Named)
+                .map_err(|_| anyhow!("unknown timezone \"{}\"", name)),
+        }
+    }
+
+    /// Format the current time in this zone.
+    pub fn now(&self, format: &str) -> String {
+        self.format_utc(Utc::now(), format)
+    }
+
+    /// Format a stored timestamp in this zone; unparseable values are
+    /// returned unchanged.
+    pub fn format(&self, stored: &str, format: &str) -> String {
+        match parse(stored) {
+            Some(t) => self.format_utc(t, format),
+            None => stored.to_string(),
+        }
+    }
+
+    /// Wall-clock time in this zone.
+    pub fn to_local(&self, t: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            DisplayZone::Local => Local.from_utc_datetime(&t.naive_utc()).naive_local(),
+            DisplayZone::Named(tz) => tz.from_utc_datetime(&t.naive_utc()).naive_local(),
+        }
+    }
+
+    /// The instant a wall-clock time in this zone names: the first one when
+    /// clocks go back, None when clocks going forward skip it.
+    pub fn from_local(&self, t: NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            DisplayZone::Local => Local
+                .from_local_datetime(&t)
+                .earliest()
+                .map(|t| t.with_timezone(&Utc)),
+            DisplayZone::Named(tz) => tz
+                .from_local_datetime(&t)
+                .earliest()
+                .map(|t| t.with_timezone(&Utc)),
+        }
+    }
+
+    /// Format an instant in this zone.
+    pub fn format_utc(&self, t: DateTime<Utc>, format: &str) -> String {
+        match self {
+            DisplayZone::Local => Local.from_utc_datetime(&t.naive_utc()).format(format),
+            DisplayZone::Named(tz) => tz.from_utc_datetime(&t.naive_utc()).format(format),
+        }
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_in_named_zone() {
+        let zone = DisplayZone::from_config(Some("Australia/Sydney")).unwrap();
+        assert_eq!(
+            zone.format("2025-01-14T10:32:05.000Z", "%Y-%m-%d %H:%M"),
+            "2025-01-14 21:32"
+        );
+        // Legacy SQLite CURRENT_TIMESTAMP rows are UTC too
+        assert_eq!(zone.format("2025-01-14 10:32:05", "%H:%M:%S"), "21:32:05");
+        assert_eq!(zone.format("garbage", "%H:%M"), "garbage");
+    }
+
+    #[test]
+    fn test_unknown_zone_rejected() {
+        assert!(DisplayZone::from_config(Some("Mars/Olympus")).is_err());
+    }
+
+    #[test]
+    fn test_now_utc_is_rfc3339() {
+        assert!(parse(&now_utc()).is_some());
+    }
+}
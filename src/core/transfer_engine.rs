@@ -1,24 +1,64 @@
+mod buffer_tune;
+mod external;
+mod filenames;
+mod image;
 mod native_copy;
 mod rsync;
 mod simulated;
+mod tarball;
+mod xattrs;
 
+use crate::config::{AppConfig, FilenameTranslation, UnicodeNormalization};
+use crate::core::filter::PathFilter;
 use crate::core::ownership::FileOwner;
+use crate::core::timestamp::DisplayZone;
 use anyhow::Result;
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use std::future::Future;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
-use tokio::sync::mpsc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch};
+use tokio_util::sync::CancellationToken;
+
+pub use filenames::RenamedPath;
+pub use native_copy::RESCUE_MAP_NAME;
+pub use simulated::SimulatedEngine;
 
 /// Hash of a file computed during transfer (for verification)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileHash {
     pub relative_path: PathBuf,
     pub hash: [u8; 32], // BLAKE3 hash (32 bytes)
     pub size: u64,
 }
 
+/// A file that only copied after one or more retries
+#[derive(Debug, Clone)]
+pub struct RetriedFile {
+    pub relative_path: PathBuf,
+    /// Attempts it took, including the successful one
+    pub attempts: u32,
+}
+
+/// A file rescue mode copied without some unreadable parts, which are left
+/// as zeros in the backup
+#[derive(Debug, Clone)]
+pub struct RescuedFile {
+    pub relative_path: PathBuf,
+    /// `(offset, length)` of each range that couldn't be read, in order
+    pub missing: Vec<(u64, u64)>,
+}
+
+impl RescuedFile {
+    /// Bytes of the file that couldn't be read
+    pub fn missing_bytes(&self) -> u64 {
+        self.missing.iter().map(|(_, length)| length).sum()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
 pub enum TransferEngineType {
     /// Native Rust file copy - safe, fast, with progress tracking
@@ -27,15 +67,253 @@ pub enum TransferEngineType {
     Rsync,
     /// Simulated transfers for testing
     Simulated,
+    /// Single zstd-compressed tar per card, with an embedded checksum index
+    Archive,
+    /// Block-level image of the whole card, readable even when its
+    /// filesystem is damaged
+    RawImage,
+    /// User-configured program speaking JSON lines on stdin/stdout
+    External,
+}
+
+/// An earlier backup of the same device that unchanged files can be
+/// hardlinked from instead of copied, like rsync's `--link-dest`.
+#[derive(Debug, Clone)]
+pub struct LinkDest {
+    /// Root of the earlier backup
+    pub directory: PathBuf,
+    /// Hashes recorded for the earlier backup's files. The native copy engine
+    /// only links a file whose source still hashes to the recorded value
+    pub files: Vec<FileHash>,
 }
 
 #[derive(Debug, Clone)]
 pub struct TransferRequest {
     pub job_id: String,
     pub source: PathBuf,
+    /// Block device node behind `source`, for engines that image the device
+    /// rather than copy its files
+    pub device: Option<PathBuf>,
     pub destination: PathBuf,
     /// Owner for transferred files. If None, files will be owned by the process user (root).
     pub owner: Option<FileOwner>,
+    /// Files already copied by an interrupted run of this job. When non-empty
+    /// the destination may already exist, and files whose size still matches
+    /// are skipped. Only honoured by the native copy engine.
+    pub completed: Vec<FileHash>,
+    /// Receives each file's hash as soon as it has been copied, so an
+    /// interrupted job can later be resumed
+    pub file_log: Option<mpsc::UnboundedSender<FileHash>>,
+    /// Include/exclude patterns deciding which source files are copied
+    pub filter: PathFilter,
+    /// Files earlier jobs already backed up from this device (incremental
+    /// mode). Source files with the same path, size and hash are skipped.
+    /// Only honoured by the native copy engine.
+    pub backed_up: Vec<FileHash>,
+    /// Earlier backup to hardlink unchanged files from
+    pub link_dest: Option<LinkDest>,
+    /// Cancelled to abort the transfer. Engines check it between files and
+    /// chunks and return an error once it fires
+    pub cancel: CancellationToken,
+    /// Holds the transfer between files while paused
+    pub pause: PauseToken,
+}
+
+/// How often a blocked copy thread looks to see if it has been resumed
+const PAUSE_POLL: Duration = Duration::from_millis(250);
+
+/// Shared pause switch for one transfer, flipped by `jobs.pause` and
+/// `jobs.resume`. Engines call `wait` (or `blocking_wait` from copy threads)
+/// between files, which reports `TransferStatus::Paused` and returns once
+/// the job is resumed or cancelled.
+#[derive(Debug, Clone)]
+pub struct PauseToken {
+    paused: Arc<watch::Sender<bool>>,
+}
+
+impl Default for PauseToken {
+    fn default() -> Self {
+        Self {
+            paused: Arc::new(watch::channel(false).0),
+        }
+    }
+}
+
+impl PauseToken {
+    /// Pause the transfer. False if it already was.
+    pub fn pause(&self) -> bool {
+        !self.paused.send_replace(true)
+    }
+
+    /// Let the transfer continue. False if it wasn't paused.
+    pub fn resume(&self) -> bool {
+        self.paused.send_replace(false)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Wait out a pause, if there is one.
+    pub async fn wait(&self, tx: &mpsc::Sender<TransferStatus>, cancel: &CancellationToken) {
+        if !self.is_paused() {
+            return;
+        }
+        let _ = tx.send(TransferStatus::Paused).await;
+        let mut paused = self.paused.subscribe();
+        tokio::select! {
+            _ = paused.wait_for(|paused| !paused) => {}
+            _ = cancel.cancelled() => {}
+        }
+    }
+
+    /// `wait` for threads outside the runtime.
+    pub fn blocking_wait(&self, tx: &mpsc::Sender<TransferStatus>, cancel: &CancellationToken) {
+        if !self.is_paused() {
+            return;
+        }
+        let _ = tx.blocking_send(TransferStatus::Paused);
+        while self.is_paused() && !cancel.is_cancelled() {
+            std::thread::sleep(PAUSE_POLL);
+        }
+    }
+
+    /// Watch the switch, for engines that pause an external process rather
+    /// than waiting between files.
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.paused.subscribe()
+    }
+}
+
+/// How often copy and verification progress is sent: once at least `bytes`
+/// have been processed and `ms` have passed since the last update. Slow
+/// network destinations want both small; fast local disks want them large
+/// enough not to flood the progress channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgressInterval {
+    pub bytes: u64,
+    pub ms: u64,
+}
+
+impl Default for ProgressInterval {
+    fn default() -> Self {
+        Self {
+            bytes: 1024 * 1024,
+            ms: 500,
+        }
+    }
+}
+
+impl ProgressInterval {
+    /// The interval set by `progress_update_interval_bytes`/`_ms`.
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            bytes: config.progress_update_interval_bytes,
+            ms: config.progress_update_interval_ms,
+        }
+    }
+}
+
+/// Decides when the next progress update is due under a `ProgressInterval`.
+/// The first update is always due.
+#[derive(Debug)]
+pub struct ProgressGate {
+    interval: ProgressInterval,
+    last_bytes: u64,
+    last_at: Option<Instant>,
+}
+
+impl ProgressGate {
+    pub fn new(interval: ProgressInterval) -> Self {
+        Self {
+            interval,
+            last_bytes: 0,
+            last_at: None,
+        }
+    }
+
+    /// Whether an update is due with `bytes` processed so far. A due update
+    /// is counted as sent.
+    pub fn due(&mut self, bytes: u64) -> bool {
+        let now = Instant::now();
+        if let Some(last_at) = self.last_at
+            && (bytes.saturating_sub(self.last_bytes) < self.interval.bytes
+                || now.duration_since(last_at) < Duration::from_millis(self.interval.ms))
+        {
+            return false;
+        }
+        self.last_bytes = bytes;
+        self.last_at = Some(now);
+        true
+    }
+}
+
+/// A progress channel and how often to send on it
+#[derive(Debug, Clone)]
+pub struct ProgressSender {
+    pub tx: mpsc::Sender<TransferStatus>,
+    pub interval: ProgressInterval,
+}
+
+/// Sends `Verifying` statuses from a verification pass as it reads through
+/// its files, from a blocking task.
+#[derive(Debug)]
+pub struct VerifyProgress {
+    tx: mpsc::Sender<TransferStatus>,
+    gate: ProgressGate,
+    speed: native_copy::SpeedMeter,
+    current: u64,
+    total: u64,
+    bytes_read: u64,
+    total_bytes: u64,
+}
+
+impl VerifyProgress {
+    /// Progress over `total` files holding `total_bytes` to read.
+    pub fn new(progress: ProgressSender, total: u64, total_bytes: u64) -> Self {
+        Self {
+            tx: progress.tx,
+            gate: ProgressGate::new(progress.interval),
+            speed: native_copy::SpeedMeter::new(0),
+            current: 0,
+            total,
+            bytes_read: 0,
+            total_bytes,
+        }
+    }
+
+    /// File `index` is about to be read, with `bytes_read` read before it.
+    pub fn file_started(&mut self, index: u64, bytes_read: u64) {
+        self.current = index;
+        self.bytes_read = bytes_read;
+        self.send(false);
+    }
+
+    /// `bytes` more of the current file were read.
+    pub fn read(&mut self, bytes: u64) {
+        self.bytes_read += bytes;
+        self.send(false);
+    }
+
+    /// Every file has been read.
+    pub fn finish(&mut self) {
+        self.current = self.total;
+        self.bytes_read = self.bytes_read.max(self.total_bytes);
+        self.send(true);
+    }
+
+    fn send(&mut self, force: bool) {
+        let bytes_per_sec = self.speed.update(self.bytes_read);
+        if self.gate.due(self.bytes_read) || force {
+            let _ = self.tx.blocking_send(TransferStatus::Verifying {
+                current: self.current,
+                total: self.total,
+                bytes_verified: self.bytes_read,
+                total_bytes: self.total_bytes,
+                bytes_per_sec,
+            });
+        }
+    }
 }
 
 /// Result returned by transfer engines on successful completion
@@ -46,9 +324,71 @@ pub struct TransferResult {
     /// Duration of the transfer in seconds
     pub duration_secs: u64,
     /// File hashes computed during transfer (for verification).
-    /// None if the engine handles verification internally (e.g., rsync --checksum)
+    /// None if the engine verifies its own output (rsync, archive, raw image)
     /// or doesn't support inline hashing (e.g., simulated).
     pub file_hashes: Option<Vec<FileHash>>,
+    /// Files left out because `backed_up` already holds an identical copy
+    pub skipped: Vec<FileHash>,
+    /// Number of files hardlinked from `link_dest` rather than copied
+    pub linked: usize,
+    /// Files that failed transiently and copied on a later attempt
+    pub retried: Vec<RetriedFile>,
+    /// Files copied with unreadable ranges zero-filled (rescue mode)
+    pub rescued: Vec<RescuedFile>,
+    /// Files now in the backup, including resumed and hardlinked ones
+    pub files_copied: usize,
+    /// Source entries that couldn't be read and were left out
+    pub files_failed: usize,
+    /// Biggest file in the backup
+    pub largest_file: Option<LargestFile>,
+    /// Entries written under a different name than on the card
+    pub renamed: Vec<RenamedPath>,
+    /// Files filed by capture date rather than under their card directory
+    pub relocated: Vec<RenamedPath>,
+}
+
+impl TransferResult {
+    /// Files left out because an earlier backup already holds them
+    pub fn files_skipped(&self) -> usize {
+        self.skipped.len()
+    }
+
+    /// Bytes copied per second over the whole transfer
+    pub fn avg_bytes_per_sec(&self) -> u64 {
+        self.total_bytes / self.duration_secs.max(1)
+    }
+}
+
+/// The biggest file a transfer wrote
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LargestFile {
+    /// Path relative to the backup root
+    pub path: String,
+    pub size: u64,
+}
+
+impl LargestFile {
+    /// The biggest of `files`, given as relative path and size.
+    pub fn of<'a>(files: impl IntoIterator<Item = (&'a Path, u64)>) -> Option<Self> {
+        files
+            .into_iter()
+            .max_by_key(|(_, size)| *size)
+            .map(|(path, size)| Self {
+                path: path.to_string_lossy().to_string(),
+                size,
+            })
+    }
+
+    /// Size in MB, or GB from 1 GB up
+    pub fn size_text(&self) -> String {
+        const MB: f64 = 1024.0 * 1024.0;
+        let size = self.size as f64;
+        if size >= 1024.0 * MB {
+            format!("{:.1} GB", size / (1024.0 * MB))
+        } else {
+            format!("{:.1} MB", size / MB)
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,15 +404,133 @@ pub enum TransferStatus {
         /// know total size upfront (e.g., native_copy). None for rsync.
         #[serde(skip_serializing_if = "Option::is_none")]
         eta_seconds: Option<u64>,
+        /// Current copy speed, smoothed over recent updates (0 until the
+        /// engine has a measurement)
+        #[serde(default)]
+        bytes_per_sec: u64,
     },
     CopyComplete,
+    /// Destination files are being re-hashed against the copy
+    Verifying {
+        current: u64,
+        total: u64,
+        /// Bytes read back so far, out of `total_bytes` (0 from engines
+        /// that only count files)
+        #[serde(default)]
+        bytes_verified: u64,
+        #[serde(default)]
+        total_bytes: u64,
+        /// Current read-back speed, smoothed like `InProgress::bytes_per_sec`
+        #[serde(default)]
+        bytes_per_sec: u64,
+    },
+    /// PAR2 recovery files are being written for the verified backup
+    GeneratingParity {
+        percentage: u8,
+    },
     Complete {
         /// Total bytes transferred during the backup
         total_bytes: u64,
         /// Duration of the actual transfer in seconds (not including queue time)
         duration_secs: u64,
+        /// Throughput figures for the summary and notification
+        #[serde(default)]
+        stats: TransferStats,
     },
     Failed(String),
+    /// Aborted through `jobs.cancel`; the partial destination is removed
+    Cancelled,
+    /// Held between files by `jobs.pause` until `jobs.resume`
+    Paused,
+}
+
+impl TransferStatus {
+    /// Coarse phase name shown by status displays.
+    pub fn phase(&self) -> &'static str {
+        match self {
+            TransferStatus::Ready => "scanning",
+            TransferStatus::InProgress { .. } => "copying",
+            TransferStatus::CopyComplete | TransferStatus::Verifying { .. } => "verifying",
+            TransferStatus::GeneratingParity { .. } => "parity",
+            TransferStatus::Complete { .. } => "complete",
+            TransferStatus::Failed(_) => "failed",
+            TransferStatus::Cancelled => "cancelled",
+            TransferStatus::Paused => "paused",
+        }
+    }
+}
+
+/// Throughput and file counts for a finished job.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransferStats {
+    /// Bytes copied per second over the whole transfer
+    pub avg_bytes_per_sec: u64,
+    /// Fastest copy rate sustained over a few seconds
+    pub peak_bytes_per_sec: u64,
+    /// Rate the destination was re-hashed at, if bksd verified it itself
+    pub verify_bytes_per_sec: Option<u64>,
+    /// Files that only copied after retrying
+    pub retried_files: usize,
+    /// Files now in the backup
+    pub files_copied: usize,
+    /// Files left out as already backed up (incremental mode)
+    pub files_skipped: usize,
+    /// Unreadable source entries left out
+    pub files_failed: usize,
+    /// Files copied with unreadable ranges zero-filled (rescue mode)
+    pub files_rescued: usize,
+    /// Biggest file in the backup
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub largest_file: Option<LargestFile>,
+}
+
+impl TransferStats {
+    /// One-line summary, e.g. "42.0 MB/s avg, 88.1 MB/s peak, verified at
+    /// 310.2 MB/s, 2 file(s) retried".
+    pub fn summary(&self) -> String {
+        let mbps = |rate: u64| rate as f64 / (1024.0 * 1024.0);
+        let mut parts = vec![
+            format!("{:.1} MB/s avg", mbps(self.avg_bytes_per_sec)),
+            format!("{:.1} MB/s peak", mbps(self.peak_bytes_per_sec)),
+        ];
+        if let Some(rate) = self.verify_bytes_per_sec {
+            parts.push(format!("verified at {:.1} MB/s", mbps(rate)));
+        }
+        if self.retried_files > 0 {
+            parts.push(format!("{} file(s) retried", self.retried_files));
+        }
+        parts.join(", ")
+    }
+
+    /// e.g. "214 file(s) copied, 3 skipped, 1 unreadable".
+    pub fn file_counts(&self) -> String {
+        let mut parts = vec![format!("{} file(s) copied", self.files_copied)];
+        if self.files_skipped > 0 {
+            parts.push(format!("{} skipped", self.files_skipped));
+        }
+        if self.files_failed > 0 {
+            parts.push(format!("{} unreadable", self.files_failed));
+        }
+        if self.files_rescued > 0 {
+            parts.push(format!("{} rescued", self.files_rescued));
+        }
+        parts.join(", ")
+    }
+
+    /// File counts plus the largest file, e.g. "214 file(s) copied, largest
+    /// DCIM/100CANON/MVI_0001.MP4 (3.9 GB)".
+    pub fn files_summary(&self) -> String {
+        let mut parts = vec![self.file_counts()];
+        if let Some(largest) = &self.largest_file {
+            parts.push(format!(
+                "largest {} ({})",
+                largest.path,
+                largest.size_text()
+            ));
+        }
+        parts.join(", ")
+    }
 }
 
 pub trait TransferEngine: Send + Sync {
@@ -83,10 +541,96 @@ pub trait TransferEngine: Send + Sync {
     ) -> Pin<Box<dyn Future<Output = Result<TransferResult>> + Send>>;
 }
 
-pub fn create_engine(engine_type: TransferEngineType) -> Box<dyn TransferEngine> {
+/// Whether a fresh transfer must refuse `destination`: anything already
+/// there except an empty directory (such as a subvolume created for the job).
+fn destination_in_use(destination: &Path) -> bool {
+    destination.exists()
+        && std::fs::read_dir(destination)
+            .map(|mut entries| entries.next().is_some())
+            .unwrap_or(true)
+}
+
+/// Native copy engine for copying a finished backup somewhere else (archive
+/// tiering, mirrors), file for file. It never zero-fills what it can't read:
+/// that would hide damage in what may be the only good copy, which is then
+/// removed. Nor does it rename or refile anything, so the copy still matches
+/// the job's manifest, marker and parity files.
+pub fn create_backup_copy_engine(config: &AppConfig) -> Box<dyn TransferEngine> {
+    let max_throughput_mbps = config.max_throughput_mbps.filter(|mbps| *mbps > 0);
+    Box::new(native_copy::NativeCopyEngine {
+        copy_threads: config.copy_threads.max(1),
+        max_bytes_per_sec: max_throughput_mbps.map(|mbps| mbps * 1024 * 1024),
+        preserve_xattrs: config.preserve_xattrs,
+        file_retries: config.file_retries,
+        retry_backoff: Duration::from_millis(config.file_retry_backoff_ms),
+        rescue: false,
+        buffer_size: match config.copy_buffer_kb {
+            0 => None,
+            kb => Some(kb as usize * 1024),
+        },
+        filenames: FilenameTranslation::Off,
+        normalization: UnicodeNormalization::Off,
+        organize_by_date: None,
+        progress_interval: ProgressInterval::from_config(config),
+        ..Default::default()
+    })
+}
+
+pub fn create_engine(
+    engine_type: TransferEngineType,
+    config: &AppConfig,
+) -> Box<dyn TransferEngine> {
+    // 0 is treated as "no limit" rather than stalling every transfer
+    let max_throughput_mbps = config.max_throughput_mbps.filter(|mbps| *mbps > 0);
+    let progress_interval = ProgressInterval::from_config(config);
+
     match engine_type {
-        TransferEngineType::NativeCopy => Box::new(native_copy::NativeCopyEngine::default()),
-        TransferEngineType::Rsync => Box::new(rsync::RsyncEngine),
+        TransferEngineType::NativeCopy => Box::new(native_copy::NativeCopyEngine {
+            copy_threads: config.copy_threads.max(1),
+            max_bytes_per_sec: max_throughput_mbps.map(|mbps| mbps * 1024 * 1024),
+            preserve_xattrs: config.preserve_xattrs,
+            file_retries: config.file_retries,
+            retry_backoff: Duration::from_millis(config.file_retry_backoff_ms),
+            rescue: config.rescue_mode,
+            buffer_size: match config.copy_buffer_kb {
+                0 => None,
+                kb => Some(kb as usize * 1024),
+            },
+            filenames: config.filename_translation,
+            normalization: config.unicode_normalization,
+            organize_by_date: config
+                .organize_by_date
+                .then(|| DisplayZone::from_config(config.timezone.as_deref()).unwrap_or_default()),
+            progress_interval,
+            ..Default::default()
+        }),
+        TransferEngineType::Rsync => Box::new(rsync::RsyncEngine {
+            binary: config.rsync.binary.clone(),
+            extra_args: config.rsync.args.clone(),
+            bwlimit_kib: max_throughput_mbps.map(|mbps| mbps * 1024),
+            preserve_xattrs: config.preserve_xattrs,
+            verify: config.verify_transfers && !config.simulation,
+            progress_interval,
+        }),
         TransferEngineType::Simulated => Box::new(simulated::SimulatedEngine::default()),
+        TransferEngineType::Archive => Box::new(tarball::ArchiveEngine {
+            compression_level: config.archive_compression_level,
+            verify: config.verify_transfers && !config.simulation,
+            max_bytes_per_sec: max_throughput_mbps.map(|mbps| mbps * 1024 * 1024),
+            progress_interval,
+        }),
+        TransferEngineType::RawImage => Box::new(image::ImageEngine {
+            compression_level: config.image_compression_level,
+            whole_device: config.image_whole_device,
+            verify: config.verify_transfers && !config.simulation,
+            max_bytes_per_sec: max_throughput_mbps.map(|mbps| mbps * 1024 * 1024),
+            progress_interval,
+        }),
+        TransferEngineType::External => Box::new(external::ExternalEngine {
+            program: config.external.program.clone(),
+            args: config.external.args.clone(),
+            verify: config.verify_transfers && !config.simulation,
+            progress_interval,
+        }),
     }
 }
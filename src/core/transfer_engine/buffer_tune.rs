@@ -0,0 +1,147 @@
+//! I/O buffer size auto-tuning for the native copy engine.
+//!
+//! The best buffer size depends on the reader and the destination disk: a
+//! UHS-II reader into an NVMe drive wants much larger reads than a USB2
+//! card reader into spinning rust. At job start each candidate size copies
+//! its own slice of the largest source file into a scratch file in the
+//! destination, fsynced, and the fastest wins. Each candidate reads a
+//! different slice so none of them is served from the page cache.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use tracing::debug;
+
+use super::native_copy::{BUFFER_SIZE, FileInfo};
+use crate::core::clock::Stopwatch;
+
+/// Buffer sizes tried, smallest first
+const CANDIDATES: [usize; 5] = [
+    64 * 1024,
+    128 * 1024,
+    256 * 1024,
+    1024 * 1024,
+    4 * 1024 * 1024,
+];
+
+/// Bytes each candidate copies
+const SAMPLE_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Scratch file written in the destination, removed afterwards
+const SCRATCH_NAME: &str = ".bksd-buffer-tune";
+
+/// Pick the fastest buffer size for copying `files` into `destination`.
+/// Falls back to `BUFFER_SIZE` when no file is large enough to measure or
+/// the benchmark fails.
+pub(super) fn tune(files: &[FileInfo], destination: &Path) -> usize {
+    let Some(sample) = files.iter().max_by_key(|f| f.size) else {
+        return BUFFER_SIZE;
+    };
+    match benchmark(&sample.path, sample.size, destination, SAMPLE_BYTES) {
+        Ok(Some(size)) => size,
+        Ok(None) => {
+            debug!("No file large enough to tune the buffer size on");
+            BUFFER_SIZE
+        }
+        Err(e) => {
+            debug!(error = %e, "Buffer size benchmark failed");
+            BUFFER_SIZE
+        }
+    }
+}
+
+/// Time each candidate copying `sample_bytes` of `source` and return the
+/// fastest, or None if `source` is too small to give every candidate its
+/// own slice.
+fn benchmark(
+    source: &Path,
+    source_size: u64,
+    destination: &Path,
+    sample_bytes: u64,
+) -> io::Result<Option<usize>> {
+    if source_size < sample_bytes * CANDIDATES.len() as u64 {
+        return Ok(None);
+    }
+
+    let scratch = destination.join(SCRATCH_NAME);
+    let result = (|| {
+        let mut best: Option<(usize, Duration)> = None;
+        for (i, &size) in CANDIDATES.iter().enumerate() {
+            let elapsed = time_copy(
+                source,
+                i as u64 * sample_bytes,
+                sample_bytes,
+                &scratch,
+                size,
+            )?;
+            debug!(
+                buffer_kb = size / 1024,
+                elapsed_ms = elapsed.as_millis() as u64,
+                "Buffer size candidate"
+            );
+            if best.is_none_or(|(_, fastest)| elapsed < fastest) {
+                best = Some((size, elapsed));
+            }
+        }
+        Ok(best.map(|(size, _)| size))
+    })();
+    let _ = fs::remove_file(&scratch);
+    result
+}
+
+/// Copy `len` bytes of `source` from `offset` into `scratch` with a buffer
+/// of `size`, synced, and return how long it took.
+fn time_copy(
+    source: &Path,
+    offset: u64,
+    len: u64,
+    scratch: &Path,
+    size: usize,
+) -> io::Result<Duration> {
+    let clock = Stopwatch::start();
+    let mut reader = File::open(source)?;
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut reader = reader.take(len);
+    let mut writer = File::create(scratch)?;
+    let mut buffer = vec![0u8; size];
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..n])?;
+    }
+    writer.sync_all()?;
+    Ok(clock.elapsed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_benchmark_picks_a_candidate_and_cleans_up() {
+        let temp = tempdir().unwrap();
+        let source = temp.path().join("clip.mp4");
+        let sample = 64 * 1024;
+        fs::write(&source, vec![7u8; sample as usize * CANDIDATES.len()]).unwrap();
+        let size = fs::metadata(&source).unwrap().len();
+
+        let chosen = benchmark(&source, size, temp.path(), sample)
+            .unwrap()
+            .unwrap();
+        assert!(CANDIDATES.contains(&chosen));
+        assert!(!temp.path().join(SCRATCH_NAME).exists());
+
+        // Too small to give every candidate its own slice
+        assert_eq!(
+            benchmark(&source, size, temp.path(), sample * 2).unwrap(),
+            None
+        );
+        let files = vec![FileInfo { path: source, size }];
+        assert_eq!(tune(&files, temp.path()), BUFFER_SIZE);
+    }
+}
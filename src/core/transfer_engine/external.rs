@@ -0,0 +1,461 @@
+//! Transfers handed to a user-supplied program.
+//!
+//! bksd starts the configured program and writes one JSON line describing
+//! the job to its stdin, then closes it:
+//!
+//! ```text
+//! {"job_id":"...","source":"/run/bksd/card","destination":"/backups/...",
+//!  "device":"/dev/sdb1","owner":"1000:1000","total_bytes":123,"files":["DCIM/A.JPG"]}
+//! ```
+//!
+//! `files` is the source listing after include/exclude patterns, relative to
+//! `source`. The program reports back with JSON lines on stdout:
+//!
+//! ```text
+//! {"event":"progress","bytes_copied":4096,"current_file":"DCIM/A.JPG"}
+//! {"event":"complete"}
+//! {"event":"error","message":"Licence server unreachable"}
+//! ```
+//!
+//! Other output is logged and ignored, and stderr goes to the daemon's log.
+//! The transfer succeeds only if the program reports `complete` and exits 0.
+//! As with rsync, pausing stops the process, cancelling kills it, and with
+//! `verify_transfers` every file is hashed on both sides afterwards.
+
+use crate::core::clock::Stopwatch;
+use crate::core::transfer_engine::native_copy::{SpeedMeter, calculate_eta, scan_directory};
+use crate::core::transfer_engine::{
+    LargestFile, ProgressInterval, ProgressSender, TransferEngine, TransferRequest, TransferResult,
+    TransferStatus, destination_in_use,
+};
+use crate::core::verifier::verify_against_source;
+use anyhow::{Result, anyhow, bail};
+use nix::sys::signal::{Signal, kill};
+use nix::unistd::Pid;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::process::Stdio;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    process::{Child, Command},
+    sync::mpsc,
+};
+use tracing::{Instrument, debug, info, info_span, warn};
+
+pub struct ExternalEngine {
+    /// Program to run. None fails every transfer
+    pub program: Option<PathBuf>,
+    /// Arguments passed to the program
+    pub args: Vec<String>,
+    /// Hash every file on both sides after the program exits
+    pub verify: bool,
+    /// How often verification reports progress
+    pub progress_interval: ProgressInterval,
+}
+
+/// The job, as written to the program's stdin
+#[derive(Debug, Serialize)]
+struct Request<'a> {
+    job_id: &'a str,
+    source: &'a PathBuf,
+    destination: &'a PathBuf,
+    device: Option<&'a PathBuf>,
+    owner: Option<String>,
+    total_bytes: u64,
+    files: &'a [PathBuf],
+}
+
+/// One line of the program's stdout
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event {
+    Progress {
+        bytes_copied: u64,
+        #[serde(default)]
+        current_file: String,
+    },
+    Complete,
+    Error {
+        message: String,
+    },
+}
+
+impl TransferEngine for ExternalEngine {
+    fn transfer(
+        &self,
+        req: &TransferRequest,
+        tx: mpsc::Sender<TransferStatus>,
+    ) -> Pin<Box<dyn Future<Output = Result<TransferResult>> + Send>> {
+        let req = req.clone();
+        let program = self.program.clone();
+        let args = self.args.clone();
+        let verify = self.verify;
+        let progress_interval = self.progress_interval;
+        Box::pin(async move {
+            let _ = tx.send(TransferStatus::Ready).await;
+
+            let result = async {
+                let Some(program) = program else {
+                    bail!("No program set for the external transfer engine ([external] program)");
+                };
+                if destination_in_use(&req.destination) {
+                    bail!(
+                        "Destination already exists: {}. Refusing to overwrite.",
+                        req.destination.display()
+                    );
+                }
+                std::fs::create_dir_all(&req.destination)
+                    .map_err(|e| anyhow!("Failed to create destination directory: {}", e))?;
+
+                let span = info_span!(
+                    "external_transfer",
+                    program = %program.display(),
+                    source = %req.source.display(),
+                    destination = %req.destination.display()
+                );
+                run(&program, &args, &req, verify, progress_interval, &tx)
+                    .instrument(span)
+                    .await
+            }
+            .await;
+
+            if let Err(e) = &result
+                && !req.cancel.is_cancelled()
+            {
+                let _ = tx.send(TransferStatus::Failed(e.to_string())).await;
+            }
+            result
+        })
+    }
+}
+
+async fn run(
+    program: &PathBuf,
+    args: &[String],
+    req: &TransferRequest,
+    verify: bool,
+    progress_interval: ProgressInterval,
+    tx: &mpsc::Sender<TransferStatus>,
+) -> Result<TransferResult> {
+    info!("Starting external transfer");
+
+    let scan = scan_directory(&req.source, &req.filter).await?;
+    let total_bytes = scan.total_bytes;
+    let sized: Vec<(PathBuf, u64)> = scan
+        .files
+        .into_iter()
+        .filter_map(|file| {
+            file.path
+                .strip_prefix(&req.source)
+                .ok()
+                .map(|relative| (relative.to_path_buf(), file.size))
+        })
+        .collect();
+    let largest_file = LargestFile::of(sized.iter().map(|(path, size)| (path.as_path(), *size)));
+    let files: Vec<PathBuf> = sized.into_iter().map(|(path, _)| path).collect();
+    let files_copied = files.len();
+    info!(total_files = files.len(), total_bytes, "Scan complete");
+
+    if req.cancel.is_cancelled() {
+        bail!("Transfer cancelled");
+    }
+
+    let request = Request {
+        job_id: &req.job_id,
+        source: &req.source,
+        destination: &req.destination,
+        device: req.device.as_ref(),
+        owner: req.owner.as_ref().map(|owner| owner.as_chown_arg()),
+        total_bytes,
+        files: &files,
+    };
+    let mut line = serde_json::to_vec(&request)?;
+    line.push(b'\n');
+
+    let start_time = Stopwatch::start();
+    let mut speed = SpeedMeter::new(0);
+    let mut last_bytes_copied: u64 = 0;
+
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| anyhow!("Failed to spawn {}: {}", program.display(), e))?;
+
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("Failed to get stdin"))?;
+    // Written alongside reading stdout, as a long file list can fill the
+    // pipe before the program gets round to reading it
+    tokio::spawn(async move {
+        if let Err(e) = stdin.write_all(&line).await {
+            debug!(error = %e, "External program did not read its request");
+        }
+    });
+
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow!("Failed to get stdout"))?;
+    let mut lines = BufReader::new(stdout).lines();
+
+    let mut paused = req.pause.subscribe();
+    if *paused.borrow_and_update() {
+        signal_child(&child, Signal::SIGSTOP);
+        let _ = tx.send(TransferStatus::Paused).await;
+    }
+
+    let mut completed = false;
+    let mut reported_error = None;
+    loop {
+        let next = tokio::select! {
+            next = lines.next_line() => next,
+            _ = req.cancel.cancelled() => {
+                let _ = child.kill().await;
+                info!("External program killed, transfer cancelled");
+                bail!("Transfer cancelled");
+            }
+            Ok(()) = paused.changed() => {
+                if *paused.borrow_and_update() {
+                    signal_child(&child, Signal::SIGSTOP);
+                    info!("External program stopped, transfer paused");
+                    let _ = tx.send(TransferStatus::Paused).await;
+                } else {
+                    signal_child(&child, Signal::SIGCONT);
+                    info!("External program continued, transfer resumed");
+                }
+                continue;
+            }
+        };
+        let Ok(Some(line)) = next else { break };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str::<Event>(line) {
+            Ok(Event::Progress {
+                bytes_copied,
+                current_file,
+            }) => {
+                last_bytes_copied = bytes_copied;
+                let total = total_bytes.max(bytes_copied);
+                let _ = tx
+                    .send(TransferStatus::InProgress {
+                        total_bytes: total,
+                        bytes_copied,
+                        current_file,
+                        percentage: (bytes_copied * 100).checked_div(total).unwrap_or(0) as u8,
+                        eta_seconds: calculate_eta(start_time, bytes_copied, total_bytes),
+                        bytes_per_sec: speed.update(bytes_copied),
+                    })
+                    .await;
+            }
+            Ok(Event::Complete) => completed = true,
+            Ok(Event::Error { message }) => {
+                warn!(message = %message, "External program reported an error");
+                reported_error = Some(message);
+            }
+            Err(_) => debug!(line = %line, "Ignoring external program output"),
+        }
+    }
+
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| anyhow!("Failed to wait for {}: {}", program.display(), e))?;
+
+    if let Some(message) = reported_error {
+        bail!("External transfer failed: {}", message);
+    }
+    if !status.success() {
+        bail!("External program failed with status: {}", status);
+    }
+    if !completed {
+        bail!("External program exited without reporting completion");
+    }
+
+    let duration_secs = start_time.elapsed().as_secs();
+    info!(
+        total_bytes = last_bytes_copied,
+        duration_secs, "External transfer finished"
+    );
+
+    if verify {
+        verify_against_source(
+            &req.job_id,
+            &req.source,
+            &req.destination,
+            files,
+            ProgressSender {
+                tx: tx.clone(),
+                interval: progress_interval,
+            },
+            &req.cancel,
+        )
+        .await?;
+    }
+
+    Ok(TransferResult {
+        skipped: Vec::new(),
+        linked: 0,
+        retried: Vec::new(),
+        rescued: Vec::new(),
+        renamed: Vec::new(),
+        relocated: Vec::new(),
+        total_bytes: last_bytes_copied.max(total_bytes),
+        duration_secs,
+        files_copied,
+        files_failed: scan.unreadable,
+        largest_file,
+        // Verified above, if enabled
+        file_hashes: None,
+    })
+}
+
+/// Stop or continue the program for `jobs.pause`/`jobs.resume`.
+fn signal_child(child: &Child, signal: Signal) {
+    let Some(pid) = child.id() else { return };
+    if let Err(e) = kill(Pid::from_raw(pid as i32), signal) {
+        warn!(signal = %signal, error = %e, "Failed to signal external program");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::filter::PathFilter;
+    use crate::core::transfer_engine::PauseToken;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::tempdir;
+    use tokio_util::sync::CancellationToken;
+
+    fn request(source: PathBuf, destination: PathBuf) -> TransferRequest {
+        TransferRequest {
+            job_id: "test-job".to_string(),
+            source,
+            device: None,
+            destination,
+            owner: None,
+            completed: Vec::new(),
+            file_log: None,
+            filter: PathFilter::default(),
+            backed_up: Vec::new(),
+            link_dest: None,
+            cancel: CancellationToken::new(),
+            pause: PauseToken::default(),
+        }
+    }
+
+    fn script(dir: &std::path::Path, name: &str, body: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\n{}", body)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_parse_events() {
+        assert_eq!(
+            serde_json::from_str::<Event>(r#"{"event":"progress","bytes_copied":5}"#).unwrap(),
+            Event::Progress {
+                bytes_copied: 5,
+                current_file: String::new()
+            }
+        );
+        assert_eq!(
+            serde_json::from_str::<Event>(r#"{"event":"complete"}"#).unwrap(),
+            Event::Complete
+        );
+        assert!(serde_json::from_str::<Event>("sending incremental file list").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_external_program_copies_and_reports() {
+        let temp = tempdir().unwrap();
+        let source = temp.path().join("source");
+        let dest = temp.path().join("dest");
+        fs::create_dir_all(source.join("DCIM")).unwrap();
+        fs::write(source.join("DCIM/A.JPG"), b"photo").unwrap();
+
+        // Pulls source and destination out of the request and copies with cp
+        let copier = script(
+            temp.path(),
+            "copier",
+            r#"read request
+src=$(echo "$request" | sed 's/.*"source":"\([^"]*\)".*/\1/')
+dst=$(echo "$request" | sed 's/.*"destination":"\([^"]*\)".*/\1/')
+echo "starting"
+cp -R "$src"/. "$dst"/
+echo '{"event":"progress","bytes_copied":5,"current_file":"DCIM/A.JPG"}'
+echo '{"event":"complete"}'
+"#,
+        );
+        let engine = ExternalEngine {
+            program: Some(copier),
+            args: Vec::new(),
+            verify: true,
+            progress_interval: ProgressInterval::default(),
+        };
+        let (tx, mut rx) = mpsc::channel(100);
+        let req = request(source, dest.clone());
+        let handle = tokio::spawn(async move { engine.transfer(&req, tx).await });
+        let mut updates = Vec::new();
+        while let Some(status) = rx.recv().await {
+            updates.push(status);
+        }
+
+        let result = handle.await.unwrap().unwrap();
+        assert_eq!(result.total_bytes, 5);
+        assert_eq!(fs::read(dest.join("DCIM/A.JPG")).unwrap(), b"photo");
+        assert!(updates.iter().any(|s| matches!(
+            s,
+            TransferStatus::InProgress { current_file, .. } if current_file == "DCIM/A.JPG"
+        )));
+        assert!(
+            updates
+                .iter()
+                .any(|s| matches!(s, TransferStatus::Verifying { .. }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_external_program_errors_fail_the_transfer() {
+        let temp = tempdir().unwrap();
+        let source = temp.path().join("source");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("A.JPG"), b"photo").unwrap();
+
+        let failing = script(
+            temp.path(),
+            "failing",
+            "echo '{\"event\":\"error\",\"message\":\"licence expired\"}'\n",
+        );
+        let silent = script(temp.path(), "silent", "exit 0\n");
+
+        for (program, expected) in [
+            (failing, "licence expired"),
+            (silent, "without reporting completion"),
+        ] {
+            let engine = ExternalEngine {
+                program: Some(program.clone()),
+                args: Vec::new(),
+                verify: false,
+                progress_interval: ProgressInterval::default(),
+            };
+            let (tx, _rx) = mpsc::channel(100);
+            let req = request(
+                source.clone(),
+                temp.path().join("dest").join(program.file_name().unwrap()),
+            );
+            let err = engine.transfer(&req, tx).await.unwrap_err();
+            assert!(err.to_string().contains(expected), "{}", err);
+        }
+    }
+}
@@ -0,0 +1,356 @@
+//! Filename translation for destinations that can't hold a card's names.
+//!
+//! exFAT and NTFS cards can carry names a destination rejects or merges:
+//! `IMG_0001.JPG` beside `img_0001.jpg` on a case-insensitive NAS share,
+//! or `clip:1.mov` and `notes.` on an SMB/exFAT disk. Depending on
+//! `filename_translation`, native copy renames such entries as it writes
+//! them and reports every rename; the backup, its hashes and the resume
+//! manifest all use the new names. `unicode_normalization` rewrites names
+//! into NFC or NFD the same way, so a card written on macOS and one written
+//! on Windows produce identical manifests.
+//!
+//! With `organize_by_date`, files are also moved out of the card's layout
+//! into `YYYY/MM/DD` folders for the day they were taken. Such relocations
+//! are kept apart from renames, as every file has one.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::config::{FilenameTranslation, UnicodeNormalization};
+use crate::core::unicode::normalize_name;
+
+/// Characters Windows, SMB and exFAT refuse in a name
+const RESERVED_CHARS: &[char] = &['<', '>', ':', '"', '\\', '|', '?', '*'];
+
+/// Device names Windows refuses with any extension
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// An entry written under a different name than it has on the card
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenamedPath {
+    /// Path on the card, relative to its root
+    pub from: PathBuf,
+    /// Path in the backup, relative to its root
+    pub to: PathBuf,
+}
+
+/// Renames for one transfer, keyed by the card-relative path of each
+/// renamed entry. Entries under a renamed directory follow it.
+#[derive(Debug, Clone, Default)]
+pub struct Translation {
+    renamed: HashMap<PathBuf, OsString>,
+    /// Backup paths of files organized by date, by card-relative path
+    relocated: HashMap<PathBuf, PathBuf>,
+}
+
+impl Translation {
+    /// Work out new names for `entries` (card-relative directories and
+    /// files). Names that are fine keep them; renamed ones never take a name
+    /// another entry already has.
+    pub fn new(
+        mode: FilenameTranslation,
+        normalization: UnicodeNormalization,
+        entries: &[&Path],
+    ) -> Self {
+        let mut translation = Self::default();
+        if mode == FilenameTranslation::Off && normalization == UnicodeNormalization::Off {
+            return translation;
+        }
+
+        // Siblings are decided together, shallowest first so each
+        // directory's own name is settled before its children's
+        let mut by_parent: HashMap<&Path, Vec<&OsStr>> = HashMap::new();
+        for entry in entries {
+            if let (Some(parent), Some(name)) = (entry.parent(), entry.file_name()) {
+                by_parent.entry(parent).or_default().push(name);
+            }
+        }
+        let mut parents: Vec<&Path> = by_parent.keys().copied().collect();
+        parents.sort_by_key(|p| (p.components().count(), p.to_path_buf()));
+
+        for parent in parents {
+            let mut names = by_parent.remove(parent).unwrap_or_default();
+            names.sort();
+            names.dedup();
+
+            let mut taken = HashSet::new();
+            let mut pending = Vec::new();
+            // Names that need no change keep them, first come first served
+            for name in names {
+                if sanitize(mode, normalization, name) == name && taken.insert(key(mode, name)) {
+                    continue;
+                }
+                pending.push(name);
+            }
+            for name in pending {
+                let new_name = unique(mode, &sanitize(mode, normalization, name), &mut taken);
+                translation.renamed.insert(parent.join(name), new_name);
+            }
+        }
+        translation
+    }
+
+    /// File each of `files` (card-relative, with the day it was taken)
+    /// under `YYYY/MM/DD/<name>` instead of its card directory. Files taken
+    /// the same day with the same name get ` (2)`, ` (3)`... in card path
+    /// order, so a resumed transfer files them the same way.
+    pub fn organize_by_date(&mut self, mode: FilenameTranslation, files: &[(&Path, NaiveDate)]) {
+        let mut files = files.to_vec();
+        files.sort();
+
+        let mut taken: HashMap<NaiveDate, HashSet<OsString>> = HashMap::new();
+        for (file, date) in files {
+            let translated = self.destination(file);
+            let Some(name) = translated.file_name() else {
+                continue;
+            };
+            let name = unique(mode, name, taken.entry(date).or_default());
+            let folder = format!("{:04}/{:02}/{:02}", date.year(), date.month(), date.day());
+            self.relocated
+                .insert(file.to_path_buf(), Path::new(&folder).join(name));
+        }
+    }
+
+    /// Where a card-relative path goes in the backup.
+    pub fn destination(&self, relative: &Path) -> PathBuf {
+        if let Some(relocated) = self.relocated.get(relative) {
+            return relocated.clone();
+        }
+        if self.renamed.is_empty() {
+            return relative.to_path_buf();
+        }
+        let mut source = PathBuf::new();
+        let mut destination = PathBuf::new();
+        for component in relative.components() {
+            source.push(component);
+            match self.renamed.get(&source) {
+                Some(name) => destination.push(name),
+                None => destination.push(component),
+            }
+        }
+        destination
+    }
+
+    /// Every rename, sorted by card path. Once files are organized by date
+    /// that is each file saved under another name, as the card's
+    /// directories aren't recreated.
+    pub fn report(&self) -> Vec<RenamedPath> {
+        let mut report: Vec<RenamedPath> = if self.relocated.is_empty() {
+            self.renamed
+                .keys()
+                .map(|from| RenamedPath {
+                    from: from.clone(),
+                    to: self.destination(from),
+                })
+                .collect()
+        } else {
+            self.relocations()
+                .into_iter()
+                .filter(|moved| moved.from.file_name() != moved.to.file_name())
+                .collect()
+        };
+        report.sort_by(|a, b| a.from.cmp(&b.from));
+        report
+    }
+
+    /// Every file organized by date, sorted by card path.
+    pub fn relocations(&self) -> Vec<RenamedPath> {
+        let mut relocations: Vec<RenamedPath> = self
+            .relocated
+            .iter()
+            .map(|(from, to)| RenamedPath {
+                from: from.clone(),
+                to: to.clone(),
+            })
+            .collect();
+        relocations.sort_by(|a, b| a.from.cmp(&b.from));
+        relocations
+    }
+}
+
+/// The name `name` should have, before avoiding its siblings. Portable
+/// names are also valid Unicode.
+fn sanitize(
+    mode: FilenameTranslation,
+    normalization: UnicodeNormalization,
+    name: &OsStr,
+) -> OsString {
+    let name = normalize_name(normalization, name);
+    if mode != FilenameTranslation::Portable {
+        return name;
+    }
+
+    let text = name.to_string_lossy();
+    let mut clean: String = text
+        .chars()
+        .map(|c| {
+            if RESERVED_CHARS.contains(&c) || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    let trimmed = clean.trim_end_matches(['.', ' ']).len();
+    clean.truncate(trimmed);
+    if clean.is_empty() {
+        clean.push('_');
+    }
+
+    let (stem, extension) = match clean.find('.') {
+        Some(dot) => clean.split_at(dot),
+        None => (clean.as_str(), ""),
+    };
+    if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem)) {
+        clean = format!("{}_{}", stem, extension);
+    }
+    OsString::from(clean)
+}
+
+/// What two names must differ in to coexist on the destination.
+fn key(mode: FilenameTranslation, name: &OsStr) -> OsString {
+    match mode {
+        FilenameTranslation::Off => name.to_os_string(),
+        _ => OsString::from(name.to_string_lossy().to_lowercase()),
+    }
+}
+
+/// `name`, or `name (2)`, `name (3)`... before the extension, whichever is
+/// free first. Claims the name it returns.
+fn unique(mode: FilenameTranslation, name: &OsStr, taken: &mut HashSet<OsString>) -> OsString {
+    if taken.insert(key(mode, name)) {
+        return name.to_os_string();
+    }
+    let text = name.to_string_lossy();
+    let (stem, extension) = match text.rfind('.') {
+        Some(dot) if dot > 0 => text.split_at(dot),
+        _ => (text.as_ref(), ""),
+    };
+    (2..)
+        .map(|n| OsString::from(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| taken.insert(key(mode, candidate)))
+        .expect("some numbered name is free")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translate(mode: FilenameTranslation, entries: &[&str]) -> Translation {
+        let paths: Vec<&Path> = entries.iter().map(Path::new).collect();
+        Translation::new(mode, UnicodeNormalization::Off, &paths)
+    }
+
+    #[test]
+    fn test_portable_names() {
+        let translation = translate(
+            FilenameTranslation::Portable,
+            &[
+                "DCIM",
+                "DCIM/clip:1.mov",
+                "DCIM/notes. ",
+                "DCIM/con.txt",
+                "DCIM/IMG_1.JPG",
+                "DCIM/img_1.jpg",
+                "Bad|Dir",
+                "Bad|Dir/A.JPG",
+            ],
+        );
+        let dest = |p: &str| translation.destination(Path::new(p));
+        assert_eq!(dest("DCIM/clip:1.mov"), PathBuf::from("DCIM/clip_1.mov"));
+        assert_eq!(dest("DCIM/notes. "), PathBuf::from("DCIM/notes"));
+        assert_eq!(dest("DCIM/con.txt"), PathBuf::from("DCIM/con_.txt"));
+        assert_eq!(dest("DCIM/IMG_1.JPG"), PathBuf::from("DCIM/IMG_1.JPG"));
+        assert_eq!(dest("DCIM/img_1.jpg"), PathBuf::from("DCIM/img_1 (2).jpg"));
+        // Children follow their renamed directory
+        assert_eq!(dest("Bad|Dir/A.JPG"), PathBuf::from("Bad_Dir/A.JPG"));
+        assert_eq!(translation.report().len(), 5);
+    }
+
+    #[test]
+    fn test_case_insensitive_and_off() {
+        let entries = ["A.JPG", "a.jpg", "a (2).jpg", "b:c"];
+        let translation = translate(FilenameTranslation::CaseInsensitive, &entries);
+        assert_eq!(
+            translation.report(),
+            vec![RenamedPath {
+                from: PathBuf::from("a.jpg"),
+                to: PathBuf::from("a (3).jpg"),
+            }]
+        );
+
+        let off = translate(FilenameTranslation::Off, &entries);
+        assert!(off.report().is_empty());
+        assert_eq!(off.destination(Path::new("b:c")), PathBuf::from("b:c"));
+    }
+
+    #[test]
+    fn test_unicode_normalization() {
+        // The same name spelled decomposed and precomposed on one card
+        let entries = [
+            Path::new("Cafe\u{301}"),
+            Path::new("Cafe\u{301}/Cre\u{300}me.JPG"),
+            Path::new("Caf\u{e9}"),
+        ];
+        let translation = Translation::new(
+            FilenameTranslation::Off,
+            UnicodeNormalization::Nfc,
+            &entries,
+        );
+        assert_eq!(
+            translation.destination(Path::new("Cafe\u{301}/Cre\u{300}me.JPG")),
+            PathBuf::from("Caf\u{e9} (2)/Cr\u{e8}me.JPG")
+        );
+        assert_eq!(
+            translation.destination(Path::new("Caf\u{e9}")),
+            PathBuf::from("Caf\u{e9}")
+        );
+    }
+
+    #[test]
+    fn test_organize_by_date() {
+        let mut translation = translate(
+            FilenameTranslation::Portable,
+            &["DCIM/100CANON/IMG:1.JPG", "DCIM/101CANON/IMG_0001.JPG"],
+        );
+        let day = NaiveDate::from_ymd_opt(2025, 1, 14).unwrap();
+        let next_day = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+        translation.organize_by_date(
+            FilenameTranslation::Portable,
+            &[
+                (Path::new("DCIM/101CANON/IMG_0001.JPG"), day),
+                (Path::new("DCIM/100CANON/IMG_0001.JPG"), day),
+                (Path::new("DCIM/100CANON/IMG:1.JPG"), next_day),
+            ],
+        );
+        let dest = |p: &str| translation.destination(Path::new(p));
+        assert_eq!(
+            dest("DCIM/100CANON/IMG_0001.JPG"),
+            PathBuf::from("2025/01/14/IMG_0001.JPG")
+        );
+        assert_eq!(
+            dest("DCIM/101CANON/IMG_0001.JPG"),
+            PathBuf::from("2025/01/14/IMG_0001 (2).JPG")
+        );
+        assert_eq!(
+            dest("DCIM/100CANON/IMG:1.JPG"),
+            PathBuf::from("2025/01/15/IMG_1.JPG")
+        );
+        assert_eq!(translation.relocations().len(), 3);
+        // Only files saved under another name are renames
+        assert_eq!(
+            translation
+                .report()
+                .iter()
+                .map(|rename| rename.from.to_str().unwrap())
+                .collect::<Vec<_>>(),
+            vec!["DCIM/100CANON/IMG:1.JPG", "DCIM/101CANON/IMG_0001.JPG"]
+        );
+    }
+}
@@ -0,0 +1,497 @@
+//! Raw device image transfer engine.
+//!
+//! Copies the card block by block, like `dd`, into a single image file rather
+//! than reading files off its filesystem. Nothing depends on the filesystem
+//! being intact, so a card that will no longer mount can still be preserved
+//! and recovered from later. The image is hashed with BLAKE3 as it is read
+//! and the hash is written next to it as a b3sum-format sidecar. With
+//! compression enabled the image is zstd-compressed as it is written; the
+//! sidecar still holds the hash of the raw image, so
+//! `zstd -d card.img.zst && b3sum -c card.img.b3` checks it.
+
+use crate::core::clock::Stopwatch;
+use crate::core::hardware::parent_disk;
+use crate::core::transfer_engine::native_copy::{
+    BUFFER_SIZE, OwnerIds, SpeedMeter, Throttle, calculate_eta, is_device_removed_error,
+    resolve_owner,
+};
+use crate::core::transfer_engine::{
+    LargestFile, PauseToken, ProgressGate, ProgressInterval, ProgressSender, TransferEngine,
+    TransferRequest, TransferResult, TransferStatus, VerifyProgress, destination_in_use,
+};
+use anyhow::{Result, anyhow, bail};
+use nix::unistd::chown;
+use std::fs::{self, File};
+use std::future::Future;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::pin::Pin;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, debug, info, info_span, warn};
+
+/// File name of the uncompressed image inside the job's destination directory
+pub const IMAGE_NAME: &str = "card.img";
+
+/// File name of the image when compressed
+pub const COMPRESSED_IMAGE_NAME: &str = "card.img.zst";
+
+/// File name of the BLAKE3 sidecar
+pub const IMAGE_HASH_NAME: &str = "card.img.b3";
+
+/// Transfer engine that images the block device behind the source.
+pub struct ImageEngine {
+    /// zstd level (1-22) to compress the image with. None writes a plain
+    /// `.img` that can be loop-mounted directly
+    pub compression_level: Option<i32>,
+    /// Image the whole disk the partition belongs to, including its
+    /// partition table, rather than just the partition
+    pub whole_device: bool,
+    /// Read the image back and check it against the hash taken while reading
+    pub verify: bool,
+    /// Optional cap on read throughput in bytes per second
+    pub max_bytes_per_sec: Option<u64>,
+    /// How often imaging reports progress
+    pub progress_interval: ProgressInterval,
+}
+
+impl Default for ImageEngine {
+    fn default() -> Self {
+        Self {
+            compression_level: None,
+            whole_device: false,
+            verify: true,
+            max_bytes_per_sec: None,
+            progress_interval: ProgressInterval::default(),
+        }
+    }
+}
+
+impl TransferEngine for ImageEngine {
+    fn transfer(
+        &self,
+        req: &TransferRequest,
+        tx: mpsc::Sender<TransferStatus>,
+    ) -> Pin<Box<dyn Future<Output = Result<TransferResult>> + Send>> {
+        let req = req.clone();
+        let compression_level = self.compression_level;
+        let whole_device = self.whole_device;
+        let verify = self.verify;
+        let max_bytes_per_sec = self.max_bytes_per_sec;
+        let progress_interval = self.progress_interval;
+
+        Box::pin(async move {
+            let _ = tx.send(TransferStatus::Ready).await;
+
+            let Some(partition) = req.device.clone() else {
+                let msg = "No block device to image".to_string();
+                let _ = tx.send(TransferStatus::Failed(msg.clone())).await;
+                return Err(anyhow!(msg));
+            };
+            let device = if whole_device {
+                parent_disk(&partition).unwrap_or(partition)
+            } else {
+                partition
+            };
+            let destination = req.destination.clone();
+
+            // Safety check: fail if destination already exists to prevent overwrites
+            if destination_in_use(&destination) {
+                let msg = format!(
+                    "Destination already exists: {}. Refusing to overwrite.",
+                    destination.display()
+                );
+                let _ = tx.send(TransferStatus::Failed(msg.clone())).await;
+                return Err(anyhow!(msg));
+            }
+
+            if let Err(e) = fs::create_dir_all(&destination) {
+                let msg = format!("Failed to create destination directory: {}", e);
+                let _ = tx.send(TransferStatus::Failed(msg.clone())).await;
+                return Err(anyhow!(msg));
+            }
+
+            let span = info_span!(
+                "image_transfer",
+                device = %device.display(),
+                destination = %destination.display()
+            );
+
+            async {
+                info!(?compression_level, "Starting device image");
+                let start_time = Stopwatch::start();
+
+                let owner_ids = match &req.owner {
+                    Some(o) => match resolve_owner(o) {
+                        Ok(ids) => Some(ids),
+                        Err(e) => {
+                            warn!(error = %e, "Failed to resolve owner, image will be owned by process user");
+                            None
+                        }
+                    },
+                    None => None,
+                };
+
+                let image_path = destination.join(match compression_level {
+                    Some(_) => COMPRESSED_IMAGE_NAME,
+                    None => IMAGE_NAME,
+                });
+
+                let write_result = {
+                    let device = device.clone();
+                    let image_path = image_path.clone();
+                    let tx = tx.clone();
+                    let cancel = req.cancel.clone();
+                    let pause = req.pause.clone();
+                    tokio::task::spawn_blocking(move || {
+                        let options = ImageOptions {
+                            compression_level,
+                            owner_ids,
+                            throttle: max_bytes_per_sec.map(Throttle::new),
+                            progress_interval,
+                            cancel,
+                            pause,
+                        };
+                        write_image(&device, &image_path, &options, start_time, &tx)
+                    })
+                    .await
+                    .map_err(|e| anyhow!(e))
+                    .and_then(|r| r)
+                };
+
+                let (total_bytes, hash) = match write_result {
+                    Ok(result) => result,
+                    Err(e) => {
+                        let msg = format!("Transfer failed: {}", e);
+                        let _ = tx.send(TransferStatus::Failed(msg.clone())).await;
+                        return Err(anyhow!(msg));
+                    }
+                };
+
+                let hash_path = destination.join(IMAGE_HASH_NAME);
+                if let Err(e) = fs::write(&hash_path, format!("{}  {}\n", hash.to_hex(), IMAGE_NAME))
+                {
+                    let msg = format!("Failed to write {}: {}", hash_path.display(), e);
+                    let _ = tx.send(TransferStatus::Failed(msg.clone())).await;
+                    return Err(anyhow!(msg));
+                }
+
+                if verify {
+                    let image_path = image_path.clone();
+                    let progress = ProgressSender {
+                        tx: tx.clone(),
+                        interval: progress_interval,
+                    };
+                    let verify_result = tokio::task::spawn_blocking(move || {
+                        verify_image(
+                            &image_path,
+                            compression_level.is_some(),
+                            &hash,
+                            VerifyProgress::new(progress, 1, total_bytes),
+                        )
+                    })
+                    .await
+                    .map_err(|e| anyhow!(e))
+                    .and_then(|r| r);
+
+                    if let Err(e) = verify_result {
+                        let msg = e.to_string();
+                        let _ = tx.send(TransferStatus::Failed(msg.clone())).await;
+                        return Err(anyhow!(msg));
+                    }
+                }
+
+                let duration_secs = start_time.elapsed().as_secs();
+                let image_bytes = fs::metadata(&image_path).map(|m| m.len()).unwrap_or(0);
+
+                info!(
+                    total_bytes,
+                    image_bytes,
+                    duration_secs,
+                    hash = %hash.to_hex(),
+                    "Device image complete"
+                );
+
+                Ok(TransferResult {
+                    total_bytes,
+                    duration_secs,
+                    files_copied: 1,
+                    files_failed: 0,
+                    largest_file: image_path.file_name().map(|name| LargestFile {
+                        path: name.to_string_lossy().to_string(),
+                        size: image_bytes,
+                    }),
+                    // Already verified against the hash taken while reading
+                    file_hashes: None,
+                    skipped: Vec::new(),
+                    linked: 0,
+                    retried: Vec::new(),
+                    rescued: Vec::new(),
+                    renamed: Vec::new(),
+                    relocated: Vec::new(),
+                })
+            }
+            .instrument(span)
+            .await
+        })
+    }
+}
+
+/// Options for writing the image
+struct ImageOptions {
+    compression_level: Option<i32>,
+    /// Owner UID/GID applied to the image file
+    owner_ids: Option<OwnerIds>,
+    throttle: Option<Throttle>,
+    progress_interval: ProgressInterval,
+    cancel: CancellationToken,
+    pause: PauseToken,
+}
+
+/// Copy the device into the image file, returning the number of bytes read
+/// and the BLAKE3 hash of the raw image.
+fn write_image(
+    device: &Path,
+    image_path: &Path,
+    options: &ImageOptions,
+    start_time: Stopwatch,
+    tx: &mpsc::Sender<TransferStatus>,
+) -> Result<(u64, blake3::Hash)> {
+    let current_file = device.to_string_lossy().to_string();
+    let device_error = |e: io::Error, action: &str| {
+        if is_device_removed_error(&e) {
+            anyhow!("Device removed during imaging of {}", current_file)
+        } else {
+            anyhow!("Failed to {} {}: {}", action, current_file, e)
+        }
+    };
+
+    let mut source = File::open(device).map_err(|e| device_error(e, "open"))?;
+    // Block devices report no length in their metadata, but can seek to the end
+    let total_bytes = source
+        .seek(SeekFrom::End(0))
+        .map_err(|e| device_error(e, "size"))?;
+    source
+        .seek(SeekFrom::Start(0))
+        .map_err(|e| device_error(e, "rewind"))?;
+
+    let image_file = File::create(image_path)
+        .map_err(|e| anyhow!("Failed to create {}: {}", image_path.display(), e))?;
+    let mut writer = BufWriter::with_capacity(BUFFER_SIZE, image_file);
+
+    let (bytes_copied, hash) = match options.compression_level {
+        Some(level) => {
+            let mut encoder = zstd::Encoder::new(&mut writer, level)?;
+            let copied = copy_blocks(
+                &mut source,
+                &current_file,
+                &mut encoder,
+                total_bytes,
+                options,
+                start_time,
+                tx,
+            )?;
+            encoder.finish()?;
+            copied
+        }
+        None => copy_blocks(
+            &mut source,
+            &current_file,
+            &mut writer,
+            total_bytes,
+            options,
+            start_time,
+            tx,
+        )?,
+    };
+
+    if bytes_copied != total_bytes {
+        bail!(
+            "{} ended after {} of {} bytes",
+            current_file,
+            bytes_copied,
+            total_bytes
+        );
+    }
+
+    let image_file = writer
+        .into_inner()
+        .map_err(|e| anyhow!("Failed to flush image: {}", e.error()))?;
+    image_file.sync_all()?;
+
+    if let Some(ids) = &options.owner_ids
+        && let Err(e) = chown(image_path, Some(ids.uid), Some(ids.gid))
+    {
+        debug!(
+            dest = %image_path.display(),
+            error = %e,
+            "Failed to set image ownership"
+        );
+    }
+
+    Ok((bytes_copied, hash))
+}
+
+/// Stream the device into `writer` in `BUFFER_SIZE` blocks, hashing and
+/// reporting progress as it goes.
+fn copy_blocks(
+    source: &mut File,
+    current_file: &str,
+    writer: &mut dyn Write,
+    total_bytes: u64,
+    options: &ImageOptions,
+    start_time: Stopwatch,
+    tx: &mpsc::Sender<TransferStatus>,
+) -> Result<(u64, blake3::Hash)> {
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut bytes_copied: u64 = 0;
+    let mut gate = ProgressGate::new(options.progress_interval);
+    let mut speed = SpeedMeter::new(0);
+
+    loop {
+        options.pause.blocking_wait(tx, &options.cancel);
+        if options.cancel.is_cancelled() {
+            bail!("Transfer cancelled");
+        }
+        let bytes_read = match source.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) if is_device_removed_error(&e) => {
+                bail!("Device removed during imaging of {}", current_file)
+            }
+            Err(e) => bail!("Failed to read {}: {}", current_file, e),
+        };
+
+        if let Some(throttle) = &options.throttle {
+            throttle.consume(bytes_read as u64);
+        }
+
+        hasher.update(&buffer[..bytes_read]);
+        writer.write_all(&buffer[..bytes_read])?;
+        bytes_copied += bytes_read as u64;
+
+        if gate.due(bytes_copied) || bytes_copied == total_bytes {
+            let percentage = if total_bytes > 0 {
+                ((bytes_copied as f64 / total_bytes as f64) * 100.0) as u8
+            } else {
+                100
+            };
+            let _ = tx.blocking_send(TransferStatus::InProgress {
+                total_bytes,
+                bytes_copied,
+                current_file: current_file.to_string(),
+                percentage,
+                eta_seconds: calculate_eta(start_time, bytes_copied, total_bytes),
+                bytes_per_sec: speed.update(bytes_copied),
+            });
+        }
+    }
+
+    Ok((bytes_copied, hasher.finalize()))
+}
+
+/// Read the image back (decompressing if needed) and compare its hash with
+/// the one taken from the device.
+fn verify_image(
+    image_path: &Path,
+    compressed: bool,
+    expected: &blake3::Hash,
+    mut progress: VerifyProgress,
+) -> Result<()> {
+    progress.file_started(0, 0);
+
+    let image_file = File::open(image_path)
+        .map_err(|e| anyhow!("Failed to open {}: {}", image_path.display(), e))?;
+    let mut reader: Box<dyn Read> = if compressed {
+        Box::new(zstd::Decoder::new(image_file)?)
+    } else {
+        Box::new(io::BufReader::with_capacity(BUFFER_SIZE, image_file))
+    };
+
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        progress.read(read as u64);
+    }
+    let actual = hasher.finalize();
+
+    if &actual != expected {
+        bail!(
+            "Verification failed: image hash {} does not match device hash {}",
+            actual.to_hex(),
+            expected.to_hex()
+        );
+    }
+
+    progress.finish();
+    info!("Image verified against device hash");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::filter::PathFilter;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_image_engine_compressed_round_trip() {
+        let temp = tempdir().unwrap();
+        // A regular file stands in for the block device
+        let device = temp.path().join("sdz1");
+        let contents: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&device, &contents).unwrap();
+        let dest = temp.path().join("dest");
+
+        let engine = ImageEngine {
+            compression_level: Some(3),
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::channel(100);
+        let req = TransferRequest {
+            job_id: "test-job".to_string(),
+            source: PathBuf::new(),
+            device: Some(device.clone()),
+            destination: dest.clone(),
+            owner: None,
+            completed: Vec::new(),
+            file_log: None,
+            filter: PathFilter::default(),
+            backed_up: Vec::new(),
+            link_dest: None,
+            cancel: CancellationToken::new(),
+            pause: PauseToken::default(),
+        };
+
+        let handle = tokio::spawn(async move { engine.transfer(&req, tx).await });
+        let mut updates = Vec::new();
+        while let Some(status) = rx.recv().await {
+            updates.push(status);
+        }
+
+        let result = handle.await.unwrap().unwrap();
+        assert_eq!(result.total_bytes, 300_000);
+        assert!(result.file_hashes.is_none());
+        assert!(
+            updates
+                .iter()
+                .any(|s| matches!(s, TransferStatus::Verifying { .. }))
+        );
+
+        let image =
+            zstd::decode_all(File::open(dest.join(COMPRESSED_IMAGE_NAME)).unwrap()).unwrap();
+        assert_eq!(image, contents);
+        assert_eq!(
+            fs::read_to_string(dest.join(IMAGE_HASH_NAME)).unwrap(),
+            format!("{}  {}\n", blake3::hash(&contents).to_hex(), IMAGE_NAME)
+        );
+    }
+}
@@ -1,40 +1,111 @@
+use crate::config::{FilenameTranslation, UnicodeNormalization};
+use crate::core::clock::Stopwatch;
+use crate::core::filter::PathFilter;
+use crate::core::media::{self, CaptureTime};
+use crate::core::timestamp::DisplayZone;
+use crate::core::transfer_engine::filenames::{RenamedPath, Translation};
 use crate::core::transfer_engine::{
-    FileHash, TransferEngine, TransferRequest, TransferResult, TransferStatus,
+    FileHash, LargestFile, LinkDest, PauseToken, ProgressGate, ProgressInterval, RescuedFile,
+    RetriedFile, TransferEngine, TransferRequest, TransferResult, TransferStatus, buffer_tune,
+    destination_in_use, xattrs,
 };
+use crate::core::verifier::hash_file_sync;
 use anyhow::{Result, anyhow, bail};
+use chrono::{DateTime, NaiveDate, Utc};
 use nix::unistd::{Gid, Group, Uid, User, chown};
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
 use std::future::Future;
 use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::os::unix::fs::FileExt;
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
-use std::time::Instant;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 use tracing::{Instrument, debug, error, info, info_span, warn};
 
-/// Buffer size for file I/O operations (128KB for optimal throughput)
-const BUFFER_SIZE: usize = 128 * 1024;
+/// Default buffer size for file I/O operations (128KB)
+pub(super) const BUFFER_SIZE: usize = 128 * 1024;
 
-/// How often to send progress updates (bytes between updates)
-const PROGRESS_UPDATE_INTERVAL: u64 = 1024 * 1024; // 1MB
+/// File name of the card path to backup path listing written when files
+/// are organized by date
+pub const ORIGINAL_PATHS_NAME: &str = "BKSD_ORIGINAL_PATHS.tsv";
+
+/// File name of the map of unreadable ranges written in rescue mode
+pub const RESCUE_MAP_NAME: &str = "BKSD_RESCUE_MAP.tsv";
+
+/// Size of the reads rescue mode retries a failed chunk in
+const RESCUE_BLOCK: usize = 4096;
+
+/// Extra reads of a block in rescue mode before it is given up on
+const RESCUE_RETRIES: u32 = 2;
 
 /// Native file copy transfer engine.
 ///
 /// Implements a safe, resilient file transfer with:
 /// - Pre-scan for accurate progress reporting
-/// - Large buffered I/O for performance
+/// - Large buffered I/O for performance, optionally auto-tuned per job
 /// - Metadata preservation (permissions, timestamps)
 /// - Optional ownership transfer
+/// - Optional extended attribute and ACL preservation
 /// - Graceful handling of device removal
+/// - Per-file retry with backoff for transient read errors
+/// - Optional rescue mode copying around unreadable parts of a file
 /// - Per-file fsync for durability
+/// - Optional worker pool copying several files at once
+/// - Optional renaming of names the destination can't hold, and Unicode
+///   normalization of names
+/// - Optional filing of files into `YYYY/MM/DD` folders by capture date
 pub struct NativeCopyEngine {
-    /// Whether to fsync each file after writing (safer but slower)
+    /// Whether to fsync each file after writing, and the directories
+    /// holding them once all are copied (safer but slower)
     pub sync_files: bool,
+    /// Number of files copied concurrently (1 = sequential)
+    pub copy_threads: usize,
+    /// Combined write rate cap across all workers (None = unlimited)
+    pub max_bytes_per_sec: Option<u64>,
+    /// Copy extended attributes and POSIX ACLs onto files and directories
+    pub preserve_xattrs: bool,
+    /// Extra attempts for a file that fails to copy (0 = fail on first error)
+    pub file_retries: u32,
+    /// Wait before the first retry, doubled for each one after
+    pub retry_backoff: Duration,
+    /// Zero-fill and report ranges of a file that can't be read rather than
+    /// fail it
+    pub rescue: bool,
+    /// Read/write buffer size (None = benchmark a few sizes at job start)
+    pub buffer_size: Option<usize>,
+    /// Which card names are rewritten for the destination
+    pub filenames: FilenameTranslation,
+    /// Unicode form names are written in
+    pub normalization: UnicodeNormalization,
+    /// File everything under the day it was taken, in this timezone, rather
+    /// than mirroring the card's layout (None = mirror it)
+    pub organize_by_date: Option<DisplayZone>,
+    /// How often progress is sent, including partway through large files
+    pub progress_interval: ProgressInterval,
 }
 
 impl Default for NativeCopyEngine {
     fn default() -> Self {
-        Self { sync_files: true }
+        Self {
+            sync_files: true,
+            copy_threads: 1,
+            max_bytes_per_sec: None,
+            preserve_xattrs: false,
+            file_retries: 2,
+            retry_backoff: Duration::from_millis(500),
+            rescue: false,
+            buffer_size: Some(BUFFER_SIZE),
+            filenames: FilenameTranslation::Off,
+            normalization: UnicodeNormalization::Off,
+            organize_by_date: None,
+            progress_interval: ProgressInterval::default(),
+        }
     }
 }
 
@@ -46,6 +117,17 @@ impl TransferEngine for NativeCopyEngine {
     ) -> Pin<Box<dyn Future<Output = Result<TransferResult>> + Send>> {
         let req = req.clone();
         let sync_files = self.sync_files;
+        let copy_threads = self.copy_threads.max(1);
+        let max_bytes_per_sec = self.max_bytes_per_sec;
+        let preserve_xattrs = self.preserve_xattrs;
+        let file_retries = self.file_retries;
+        let retry_backoff = self.retry_backoff;
+        let rescue = self.rescue;
+        let buffer_size = self.buffer_size;
+        let filenames = self.filenames;
+        let normalization = self.normalization;
+        let organize_by_date = self.organize_by_date;
+        let progress_interval = self.progress_interval;
 
         Box::pin(async move {
             let _ = tx.send(TransferStatus::Ready).await;
@@ -53,9 +135,11 @@ impl TransferEngine for NativeCopyEngine {
             let source = req.source.clone();
             let destination = req.destination.clone();
             let owner = req.owner.clone();
+            let resuming = !req.completed.is_empty();
 
-            // Safety check: fail if destination already exists to prevent overwrites
-            if destination.exists() {
+            // Safety check: fail if destination already exists to prevent overwrites,
+            // unless we're continuing an interrupted run into it
+            if destination_in_use(&destination) && !resuming {
                 let msg = format!(
                     "Destination already exists: {}. Refusing to overwrite.",
                     destination.display()
@@ -79,11 +163,11 @@ impl TransferEngine for NativeCopyEngine {
 
             async {
                 info!("Starting native copy transfer");
-                let start_time = Instant::now();
+                let start_time = Stopwatch::start();
 
                 // Phase 1: Scan source directory for files and total size
                 info!("Scanning source directory");
-                let scan_result = match scan_directory(&source).await {
+                let scan_result = match scan_directory(&source, &req.filter).await {
                     Ok(result) => result,
                     Err(e) => {
                         let msg = format!("Failed to scan source directory: {}", e);
@@ -111,31 +195,190 @@ impl TransferEngine for NativeCopyEngine {
                     None => None,
                 };
 
+                // Names the destination can't hold are written under new ones
+                let mut translation = {
+                    let entries: Vec<&Path> = scan_result
+                        .directories
+                        .iter()
+                        .chain(scan_result.files.iter().map(|f| &f.path))
+                        .filter_map(|path| path.strip_prefix(&source).ok())
+                        .collect();
+                    Translation::new(filenames, normalization, &entries)
+                };
+                if let Some(zone) = organize_by_date {
+                    info!("Reading capture dates");
+                    let files: Vec<PathBuf> =
+                        scan_result.files.iter().map(|f| f.path.clone()).collect();
+                    let dates = match tokio::task::spawn_blocking(move || {
+                        files
+                            .into_iter()
+                            .map(|path| {
+                                let date = capture_date(&path, zone);
+                                (path, date)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .await
+                    {
+                        Ok(dates) => dates,
+                        Err(e) => {
+                            let msg = format!("Failed to read capture dates: {}", e);
+                            let _ = tx.send(TransferStatus::Failed(msg.clone())).await;
+                            return Err(anyhow!(msg));
+                        }
+                    };
+                    let dates: Vec<(&Path, NaiveDate)> = dates
+                        .iter()
+                        .filter_map(|(path, date)| Some((path.strip_prefix(&source).ok()?, *date)))
+                        .collect();
+                    translation.organize_by_date(filenames, &dates);
+                }
+                let renamed = translation.report();
+                for rename in &renamed {
+                    info!(
+                        from = %rename.from.display(),
+                        to = %rename.to.display(),
+                        "Renaming entry for the destination"
+                    );
+                }
+
                 // Phase 2: Create directory structure
-                if let Err(e) = create_directory_structure(
-                    &source,
-                    &destination,
-                    &scan_result.directories,
-                    owner_ids.as_ref(),
-                )
-                .await
-                {
+                let created = if organize_by_date.is_some() {
+                    create_date_directories(&destination, &translation, owner_ids.as_ref()).await
+                } else {
+                    create_directory_structure(
+                        &source,
+                        &destination,
+                        &scan_result.directories,
+                        &translation,
+                        owner_ids.as_ref(),
+                        preserve_xattrs,
+                    )
+                    .await
+                };
+                if let Err(e) = created {
                     let msg = format!("Failed to create directory structure: {}", e);
                     let _ = tx.send(TransferStatus::Failed(msg.clone())).await;
                     return Err(anyhow!(msg));
                 }
 
+                // Skip files an interrupted run already copied
+                let (files, resumed) =
+                    split_resumed(&source, scan_result.files, &req.completed, &translation);
+                if !resumed.is_empty() {
+                    info!(
+                        skipped_files = resumed.len(),
+                        remaining_files = files.len(),
+                        "Resuming interrupted transfer"
+                    );
+                }
+
+                // Incremental mode: leave out files an earlier backup holds unchanged
+                let (files, skipped) = if req.backed_up.is_empty() {
+                    (files, Vec::new())
+                } else {
+                    let source = source.clone();
+                    let backed_up = req.backed_up.clone();
+                    let translation = translation.clone();
+                    match tokio::task::spawn_blocking(move || {
+                        split_unchanged(&source, files, &backed_up, &translation)
+                    })
+                    .await
+                    {
+                        Ok(split) => split,
+                        Err(e) => {
+                            let msg = format!("Failed to compare against earlier backups: {}", e);
+                            let _ = tx.send(TransferStatus::Failed(msg.clone())).await;
+                            return Err(anyhow!(msg));
+                        }
+                    }
+                };
+                if !skipped.is_empty() {
+                    info!(
+                        skipped_files = skipped.len(),
+                        remaining_files = files.len(),
+                        "Skipping files already backed up"
+                    );
+                }
+
+                // Hardlink files unchanged since the device's last backup
+                let (files, linked) = match req.link_dest.clone() {
+                    None => (files, Vec::new()),
+                    Some(link_dest) => {
+                        let source = source.clone();
+                        let destination = destination.clone();
+                        let translation = translation.clone();
+                        match tokio::task::spawn_blocking(move || {
+                            link_unchanged(&source, &destination, files, &link_dest, &translation)
+                        })
+                        .await
+                        {
+                            Ok(split) => split,
+                            Err(e) => {
+                                let msg = format!("Failed to link from earlier backup: {}", e);
+                                let _ = tx.send(TransferStatus::Failed(msg.clone())).await;
+                                return Err(anyhow!(msg));
+                            }
+                        }
+                    }
+                };
+                if !linked.is_empty() {
+                    info!(
+                        linked_files = linked.len(),
+                        remaining_files = files.len(),
+                        "Hardlinked unchanged files from earlier backup"
+                    );
+                    if let Some(log) = &req.file_log {
+                        for file in &linked {
+                            let _ = log.send(file.clone());
+                        }
+                    }
+                }
+
+                let buffer_size = match buffer_size {
+                    Some(size) => size,
+                    None => {
+                        let sample = files.clone();
+                        let destination = destination.clone();
+                        let size = tokio::task::spawn_blocking(move || {
+                            buffer_tune::tune(&sample, &destination)
+                        })
+                        .await
+                        .unwrap_or(BUFFER_SIZE);
+                        info!(buffer_kb = size / 1024, "Auto-tuned I/O buffer size");
+                        size
+                    }
+                };
+
                 // Phase 3: Copy files with progress reporting
                 let copy_options = CopyOptions {
                     sync_files,
                     owner_ids,
+                    copy_threads,
+                    max_bytes_per_sec,
+                    preserve_xattrs,
+                    file_retries,
+                    retry_backoff,
+                    rescue,
+                    buffer_size,
+                    file_log: req.file_log.clone(),
+                    translation: translation.clone(),
+                    progress_interval,
+                    cancel: req.cancel.clone(),
+                    pause: req.pause.clone(),
                 };
 
                 let result = copy_files_with_progress(
                     &source,
                     &destination,
-                    &scan_result.files,
+                    &files,
                     scan_result.total_bytes,
+                    resumed
+                        .iter()
+                        .chain(&skipped)
+                        .chain(&linked)
+                        .map(|f| f.size)
+                        .sum(),
                     &copy_options,
                     start_time,
                     tx.clone(),
@@ -143,7 +386,55 @@ impl TransferEngine for NativeCopyEngine {
                 .await;
 
                 match result {
-                    Ok((bytes_copied, file_hashes)) => {
+                    Ok((bytes_copied, copied_hashes, retried, rescued)) => {
+                        let linked_count = linked.len();
+                        let mut file_hashes = resumed;
+                        file_hashes.extend(linked);
+                        file_hashes.extend(copied_hashes);
+
+                        // Where each file came from on the card
+                        let relocated = translation.relocations();
+                        if organize_by_date.is_some()
+                            && let Err(e) =
+                                write_original_paths(&destination, &relocated, &file_hashes)
+                        {
+                            let msg = format!("Failed to write {}: {}", ORIGINAL_PATHS_NAME, e);
+                            let _ = tx.send(TransferStatus::Failed(msg.clone())).await;
+                            return Err(anyhow!(msg));
+                        }
+
+                        // What rescue mode couldn't read, next to the files
+                        if !rescued.is_empty() {
+                            if let Err(e) = write_rescue_map(&destination, &rescued) {
+                                let msg = format!("Failed to write {}: {}", RESCUE_MAP_NAME, e);
+                                let _ = tx.send(TransferStatus::Failed(msg.clone())).await;
+                                return Err(anyhow!(msg));
+                            }
+                            warn!(
+                                files = rescued.len(),
+                                missing_bytes = rescued
+                                    .iter()
+                                    .map(RescuedFile::missing_bytes)
+                                    .sum::<u64>(),
+                                "Copied with unreadable ranges zero-filled"
+                            );
+                        }
+
+                        // The files' own fsyncs don't cover their names
+                        if sync_files {
+                            let root = destination.clone();
+                            if let Err(e) =
+                                tokio::task::spawn_blocking(move || sync_directories(&root))
+                                    .await
+                                    .map_err(io::Error::other)
+                                    .and_then(|synced| synced)
+                            {
+                                let msg = format!("Failed to sync destination directories: {}", e);
+                                let _ = tx.send(TransferStatus::Failed(msg.clone())).await;
+                                return Err(anyhow!(msg));
+                            }
+                        }
+
                         let duration_secs = start_time.elapsed().as_secs();
                         let speed_mbps = if duration_secs > 0 {
                             bytes_copied as f64 / (1024.0 * 1024.0) / duration_secs as f64
@@ -156,13 +447,27 @@ impl TransferEngine for NativeCopyEngine {
                             duration_secs = duration_secs,
                             speed_mbps = format!("{:.2}", speed_mbps),
                             files_hashed = file_hashes.len(),
+                            files_retried = retried.len(),
                             "Native copy transfer complete"
                         );
 
                         Ok(TransferResult {
                             total_bytes: bytes_copied,
                             duration_secs,
+                            files_copied: file_hashes.len(),
+                            files_failed: scan_result.unreadable,
+                            largest_file: LargestFile::of(
+                                file_hashes
+                                    .iter()
+                                    .map(|fh| (fh.relative_path.as_path(), fh.size)),
+                            ),
                             file_hashes: Some(file_hashes),
+                            skipped,
+                            linked: linked_count,
+                            retried,
+                            rescued,
+                            renamed,
+                            relocated,
                         })
                     }
                     Err(e) => {
@@ -179,29 +484,31 @@ impl TransferEngine for NativeCopyEngine {
 }
 
 /// Result of scanning a directory
-struct ScanResult {
+pub(super) struct ScanResult {
     /// All files found (absolute paths)
-    files: Vec<FileInfo>,
+    pub(super) files: Vec<FileInfo>,
     /// All directories found (absolute paths), in creation order (parents before children)
-    directories: Vec<PathBuf>,
+    pub(super) directories: Vec<PathBuf>,
     /// Total size of all files in bytes
-    total_bytes: u64,
+    pub(super) total_bytes: u64,
+    /// Entries skipped because they couldn't be read
+    pub(super) unreadable: usize,
 }
 
 /// Information about a file to copy
 #[derive(Clone)]
-struct FileInfo {
+pub(super) struct FileInfo {
     /// Absolute path to the file
-    path: PathBuf,
+    pub(super) path: PathBuf,
     /// File size in bytes
-    size: u64,
+    pub(super) size: u64,
 }
 
 /// Resolved owner UID and GID
 #[derive(Clone)]
-struct OwnerIds {
-    uid: Uid,
-    gid: Gid,
+pub(super) struct OwnerIds {
+    pub(super) uid: Uid,
+    pub(super) gid: Gid,
 }
 
 /// Options for the copy operation
@@ -210,40 +517,77 @@ struct CopyOptions {
     sync_files: bool,
     /// Owner UID/GID if ownership should be changed
     owner_ids: Option<OwnerIds>,
+    /// Number of worker threads copying files
+    copy_threads: usize,
+    /// Throughput cap shared by all workers
+    max_bytes_per_sec: Option<u64>,
+    /// Whether to copy extended attributes and ACLs
+    preserve_xattrs: bool,
+    /// Extra attempts for a file that fails to copy
+    file_retries: u32,
+    /// Wait before the first retry, doubled for each one after
+    retry_backoff: Duration,
+    /// Whether to copy around unreadable ranges
+    rescue: bool,
+    /// Read/write buffer size
+    buffer_size: usize,
+    /// Where to report each copied file for the resume manifest
+    file_log: Option<mpsc::UnboundedSender<FileHash>>,
+    /// Backup paths for card paths
+    translation: Translation,
+    /// How often progress is sent
+    progress_interval: ProgressInterval,
+    /// Stops the copy between files and chunks
+    cancel: CancellationToken,
+    /// Holds the copy between files
+    pause: PauseToken,
 }
 
-/// Scan a directory recursively, collecting files and directories.
-async fn scan_directory(source: &Path) -> Result<ScanResult> {
+/// Scan a directory recursively, collecting the files and directories that
+/// pass `filter`.
+pub(super) async fn scan_directory(source: &Path, filter: &PathFilter) -> Result<ScanResult> {
     let source = source.to_path_buf();
+    let filter = filter.clone();
 
     tokio::task::spawn_blocking(move || {
         let mut files = Vec::new();
         let mut directories = Vec::new();
         let mut total_bytes: u64 = 0;
+        let mut unreadable = 0;
 
         scan_directory_recursive(
             &source,
             &source,
+            &filter,
             &mut files,
             &mut directories,
             &mut total_bytes,
+            &mut unreadable,
         )?;
 
+        // With includes, only recreate directories that hold an included file
+        if filter.has_includes() {
+            directories.retain(|dir| files.iter().any(|f| f.path.starts_with(dir)));
+        }
+
         Ok(ScanResult {
             files,
             directories,
             total_bytes,
+            unreadable,
         })
     })
     .await?
 }
 
 fn scan_directory_recursive(
-    base: &Path,
+    root: &Path,
     current: &Path,
+    filter: &PathFilter,
     files: &mut Vec<FileInfo>,
     directories: &mut Vec<PathBuf>,
     total_bytes: &mut u64,
+    unreadable: &mut usize,
 ) -> Result<()> {
     let entries = fs::read_dir(current).map_err(|e| {
         if is_device_removed_error(&e) {
@@ -272,14 +616,33 @@ fn scan_directory_recursive(
                     bail!("Device appears to have been removed: {}", e);
                 }
                 warn!(path = %path.display(), error = %e, "Skipping unreadable entry");
+                *unreadable += 1;
                 continue;
             }
         };
 
+        let relative = path.strip_prefix(root).unwrap_or(&path);
+
         if metadata.is_dir() {
+            if filter.excludes_dir(relative) {
+                debug!(path = %path.display(), "Skipping excluded directory");
+                continue;
+            }
             directories.push(path.clone());
-            scan_directory_recursive(base, &path, files, directories, total_bytes)?;
+            scan_directory_recursive(
+                root,
+                &path,
+                filter,
+                files,
+                directories,
+                total_bytes,
+                unreadable,
+            )?;
         } else if metadata.is_file() {
+            if !filter.includes_file(relative) {
+                debug!(path = %path.display(), "Skipping filtered file");
+                continue;
+            }
             let size = metadata.len();
             *total_bytes += size;
             files.push(FileInfo { path, size });
@@ -293,7 +656,7 @@ fn scan_directory_recursive(
 }
 
 /// Resolve a FileOwner to UID/GID
-fn resolve_owner(owner: &crate::core::ownership::FileOwner) -> Result<OwnerIds> {
+pub(super) fn resolve_owner(owner: &crate::core::ownership::FileOwner) -> Result<OwnerIds> {
     let user = User::from_name(&owner.user)
         .map_err(|e| anyhow!("Failed to lookup user '{}': {}", owner.user, e))?
         .ok_or_else(|| anyhow!("User '{}' not found", owner.user))?;
@@ -313,11 +676,14 @@ async fn create_directory_structure(
     source: &Path,
     destination: &Path,
     directories: &[PathBuf],
+    translation: &Translation,
     owner_ids: Option<&OwnerIds>,
+    preserve_xattrs: bool,
 ) -> Result<()> {
     let source = source.to_path_buf();
     let destination = destination.to_path_buf();
     let directories = directories.to_vec();
+    let translation = translation.clone();
     let owner_ids = owner_ids.cloned();
 
     tokio::task::spawn_blocking(move || {
@@ -325,7 +691,7 @@ async fn create_directory_structure(
             let relative = dir_path
                 .strip_prefix(&source)
                 .expect("directory should be under source");
-            let dest_dir = destination.join(relative);
+            let dest_dir = destination.join(translation.destination(relative));
 
             // Get source directory metadata for permissions
             let metadata = fs::metadata(dir_path)?;
@@ -338,8 +704,78 @@ async fn create_directory_structure(
             fs::set_permissions(&dest_dir, permissions)?;
 
             // Set ownership if specified
-            if let Some(ref ids) = owner_ids {
-                if let Err(e) = chown(&dest_dir, Some(ids.uid), Some(ids.gid)) {
+            if let Some(ref ids) = owner_ids
+                && let Err(e) = chown(&dest_dir, Some(ids.uid), Some(ids.gid))
+            {
+                warn!(
+                    path = %dest_dir.display(),
+                    error = %e,
+                    "Failed to set directory ownership"
+                );
+            }
+
+            // Copy xattrs and ACLs, including default ACLs
+            if preserve_xattrs && let Err(e) = xattrs::copy_all(dir_path, &dest_dir) {
+                debug!(
+                    path = %dest_dir.display(),
+                    error = %e,
+                    "Failed to preserve directory extended attributes"
+                );
+            }
+        }
+        Ok(())
+    })
+    .await?
+}
+
+/// fsync every directory under `destination`, the destination itself and
+/// the directory holding it, so a power loss can't drop the entries of the
+/// files copied into them.
+fn sync_directories(destination: &Path) -> io::Result<()> {
+    let mut pending = vec![destination.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                pending.push(entry.path());
+            }
+        }
+        File::open(&dir)?.sync_all()?;
+    }
+    if let Some(parent) = destination.parent() {
+        File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Create the `YYYY/MM/DD` folders files organized by date go in.
+async fn create_date_directories(
+    destination: &Path,
+    translation: &Translation,
+    owner_ids: Option<&OwnerIds>,
+) -> Result<()> {
+    let mut folders: Vec<PathBuf> = translation
+        .relocations()
+        .into_iter()
+        .filter_map(|moved| moved.to.parent().map(Path::to_path_buf))
+        .collect();
+    folders.sort();
+    folders.dedup();
+    let destination = destination.to_path_buf();
+    let owner_ids = owner_ids.cloned();
+
+    tokio::task::spawn_blocking(move || {
+        for folder in folders {
+            // The year and month too, so they get the owner as well
+            for dir in folder.ancestors().filter(|dir| !dir.as_os_str().is_empty()) {
+                let dest_dir = destination.join(dir);
+                if dest_dir.is_dir() {
+                    continue;
+                }
+                fs::create_dir_all(&dest_dir)?;
+                if let Some(ref ids) = owner_ids
+                    && let Err(e) = chown(&dest_dir, Some(ids.uid), Some(ids.gid))
+                {
                     warn!(
                         path = %dest_dir.display(),
                         error = %e,
@@ -353,95 +789,391 @@ async fn create_directory_structure(
     .await?
 }
 
+/// The day a file was taken in `zone`: its EXIF or movie header capture
+/// time, else its modification time.
+fn capture_date(path: &Path, zone: DisplayZone) -> NaiveDate {
+    match media::capture_time(path) {
+        Ok(Some(CaptureTime::Local(taken))) => return taken.date(),
+        Ok(Some(CaptureTime::Utc(taken))) => return zone.to_local(taken).date(),
+        Ok(None) => {}
+        Err(e) => debug!(file = %path.display(), error = %e, "Failed to read capture time"),
+    }
+    let modified = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map(DateTime::<Utc>::from)
+        .unwrap_or_default();
+    zone.to_local(modified).date()
+}
+
+/// Write `ORIGINAL_PATHS_NAME` into the backup: a `<card path>\t<backup
+/// path>` line for each file in it, sorted by card path.
+fn write_original_paths(
+    destination: &Path,
+    relocated: &[RenamedPath],
+    file_hashes: &[FileHash],
+) -> io::Result<()> {
+    let backed_up: HashSet<&Path> = file_hashes
+        .iter()
+        .map(|fh| fh.relative_path.as_path())
+        .collect();
+    let mut listing = String::new();
+    for moved in relocated
+        .iter()
+        .filter(|moved| backed_up.contains(moved.to.as_path()))
+    {
+        listing.push_str(&format!(
+            "{}\t{}\n",
+            moved.from.to_string_lossy(),
+            moved.to.to_string_lossy()
+        ));
+    }
+    fs::write(destination.join(ORIGINAL_PATHS_NAME), listing)
+}
+
+/// Positional reads of a card file, so tests can stand in one with
+/// unreadable blocks.
+trait CardRead {
+    fn read_chunk(&self, buffer: &mut [u8], offset: u64) -> io::Result<usize>;
+    fn read_block(&self, buffer: &mut [u8], offset: u64) -> io::Result<()>;
+}
+
+impl CardRead for File {
+    fn read_chunk(&self, buffer: &mut [u8], offset: u64) -> io::Result<usize> {
+        self.read_at(buffer, offset)
+    }
+
+    fn read_block(&self, buffer: &mut [u8], offset: u64) -> io::Result<()> {
+        self.read_exact_at(buffer, offset)
+    }
+}
+
+/// Read the chunk of `file` (the card file at `path`, `size` bytes long) at
+/// `offset` into `buffer` for rescue mode. A chunk that fails with an I/O
+/// error is read again a block at a time, each block retried before it is
+/// zero-filled and added to `missing`. Errors other than EIO, or the card
+/// going away, still fail the read. Returns the bytes read, 0 at the end.
+fn rescue_read(
+    file: &impl CardRead,
+    path: &Path,
+    offset: u64,
+    size: u64,
+    buffer: &mut [u8],
+    missing: &mut Vec<(u64, u64)>,
+) -> io::Result<usize> {
+    let is_eio = |e: &io::Error| e.raw_os_error() == Some(libc::EIO);
+    let chunk = match file.read_chunk(buffer, offset) {
+        Ok(read) => return Ok(read),
+        Err(e) if is_eio(&e) && path.exists() => {
+            buffer.len().min(size.saturating_sub(offset) as usize)
+        }
+        Err(e) => return Err(e),
+    };
+
+    for start in (0..chunk).step_by(RESCUE_BLOCK) {
+        let block = &mut buffer[start..(start + RESCUE_BLOCK).min(chunk)];
+        let at = offset + start as u64;
+        let mut tries = 0;
+        loop {
+            match file.read_block(block, at) {
+                Ok(()) => break,
+                Err(e) if is_eio(&e) && tries < RESCUE_RETRIES => tries += 1,
+                Err(e) if is_eio(&e) && path.exists() => {
+                    debug!(
+                        offset = at,
+                        length = block.len(),
+                        "Unreadable block, zero-filling"
+                    );
+                    block.fill(0);
+                    add_missing(missing, at, block.len() as u64);
+                    break;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    Ok(chunk)
+}
+
+/// Record `length` unreadable bytes at `offset`, joining them onto the
+/// range before when they follow straight on.
+fn add_missing(missing: &mut Vec<(u64, u64)>, offset: u64, length: u64) {
+    match missing.last_mut() {
+        Some((start, len)) if *start + *len == offset => *len += length,
+        _ => missing.push((offset, length)),
+    }
+}
+
+/// Add the ranges rescue mode couldn't read to `RESCUE_MAP_NAME` in the
+/// backup, one `<backup path>\t<offset>\t<length>` line each. A resumed
+/// job adds to what the run before it found.
+fn write_rescue_map(destination: &Path, rescued: &[RescuedFile]) -> io::Result<()> {
+    let mut listing = String::new();
+    for file in rescued {
+        for (offset, length) in &file.missing {
+            listing.push_str(&format!(
+                "{}\t{}\t{}\n",
+                file.relative_path.to_string_lossy(),
+                offset,
+                length
+            ));
+        }
+    }
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(destination.join(RESCUE_MAP_NAME))?
+        .write_all(listing.as_bytes())
+}
+
 /// Copy all files with progress reporting.
-/// Returns (bytes_copied, file_hashes) on success.
+///
+/// Files are handed out to `copy_threads` workers from a shared queue. Progress
+/// is aggregated across workers into one `InProgress` stream and per-file
+/// errors are collected; a device-removal error stops all workers.
+/// `resumed_bytes` is the size of files skipped because an earlier run copied
+/// them; it counts towards progress but not towards the speed estimate.
+/// A file that fails is retried up to `file_retries` times while it is still
+/// reachable, so a flaky reader's transient EIO doesn't fail the job. In
+/// rescue mode a read error doesn't fail the file in the first place.
+/// Returns (bytes_copied, file_hashes, retried_files, rescued_files) on
+/// success, hashes in scan order.
+#[allow(clippy::too_many_arguments)]
 async fn copy_files_with_progress(
     source: &Path,
     destination: &Path,
     files: &[FileInfo],
     total_bytes: u64,
+    resumed_bytes: u64,
     options: &CopyOptions,
-    start_time: Instant,
+    start_time: Stopwatch,
     tx: mpsc::Sender<TransferStatus>,
-) -> Result<(u64, Vec<FileHash>)> {
+) -> Result<(u64, Vec<FileHash>, Vec<RetriedFile>, Vec<RescuedFile>)> {
     let source = source.to_path_buf();
     let destination = destination.to_path_buf();
     let files = files.to_vec();
     let sync_files = options.sync_files;
     let owner_ids = options.owner_ids.clone();
+    let preserve_xattrs = options.preserve_xattrs;
+    let file_retries = options.file_retries;
+    let retry_backoff = options.retry_backoff;
+    let rescue = options.rescue;
+    let buffer_size = options.buffer_size;
+    let file_log = options.file_log.clone();
+    let translation = options.translation.clone();
+    let workers = options.copy_threads.clamp(1, files.len().max(1));
+    let throttle = options.max_bytes_per_sec.map(Throttle::new);
+    let progress_interval = options.progress_interval;
+    let cancel = options.cancel.clone();
+    let pause = options.pause.clone();
 
     tokio::task::spawn_blocking(move || {
-        let mut bytes_copied: u64 = 0;
-        let mut last_progress_update: u64 = 0;
-        let mut errors: Vec<CopyError> = Vec::new();
-        let mut file_hashes: Vec<FileHash> = Vec::with_capacity(files.len());
-
-        for file_info in &files {
-            let relative = file_info
-                .path
-                .strip_prefix(&source)
-                .expect("file should be under source");
-            let dest_path = destination.join(relative);
-            let current_file = relative.to_string_lossy().to_string();
-
-            debug!(file = %current_file, size = file_info.size, "Copying file");
-
-            match copy_single_file(&file_info.path, &dest_path, sync_files, owner_ids.as_ref()) {
-                Ok((file_bytes, hash)) => {
-                    bytes_copied += file_bytes;
-
-                    // Store the hash for verification
-                    file_hashes.push(FileHash {
-                        relative_path: relative.to_path_buf(),
-                        hash: *hash.as_bytes(),
-                        size: file_bytes,
-                    });
-
-                    // Send progress update if enough bytes have been copied
-                    if bytes_copied - last_progress_update >= PROGRESS_UPDATE_INTERVAL
-                        || bytes_copied == total_bytes
-                    {
-                        let percentage = if total_bytes > 0 {
-                            ((bytes_copied as f64 / total_bytes as f64) * 100.0) as u8
-                        } else {
-                            100
-                        };
-
-                        // Calculate ETA based on current transfer speed
-                        let eta_seconds = calculate_eta(start_time, bytes_copied, total_bytes);
+        let next_file = AtomicUsize::new(0);
+        let aborted = AtomicBool::new(false);
+        let state = Mutex::new(CopyState {
+            bytes_copied: resumed_bytes,
+            in_flight: 0,
+            progress: ProgressGate::new(progress_interval),
+            speed: SpeedMeter::new(resumed_bytes),
+            errors: Vec::new(),
+            file_hashes: Vec::with_capacity(files.len()),
+            retried: Vec::new(),
+            rescued: Vec::new(),
+            removed_at: None,
+        });
+
+        // Send an update if one is due (or `force`d), counting the bytes of
+        // files still being copied
+        let report = |state: &mut CopyState, current_file: &str, force: bool| {
+            let bytes_copied = state.bytes_copied + state.in_flight;
+            if !state.progress.due(bytes_copied) && !force {
+                return;
+            }
+            let percentage = if total_bytes > 0 {
+                ((bytes_copied as f64 / total_bytes as f64) * 100.0) as u8
+            } else {
+                100
+            };
+
+            // Calculate ETA based on current transfer speed
+            let eta_seconds = calculate_eta(
+                start_time,
+                bytes_copied - resumed_bytes,
+                total_bytes - resumed_bytes,
+            );
 
-                        let _ = tx.blocking_send(TransferStatus::InProgress {
-                            total_bytes,
-                            bytes_copied,
-                            current_file: current_file.clone(),
-                            percentage,
-                            eta_seconds,
-                        });
+            let _ = tx.blocking_send(TransferStatus::InProgress {
+                total_bytes,
+                bytes_copied,
+                current_file: current_file.to_string(),
+                percentage,
+                eta_seconds,
+                bytes_per_sec: state.speed.update(bytes_copied),
+            });
+        };
 
-                        last_progress_update = bytes_copied;
-                    }
-                }
-                Err(e) => {
-                    // Check if this is a device removal error - if so, fail immediately
-                    if e.is_device_removed {
-                        return Err(anyhow!(
-                            "Device removed during transfer at file: {}",
-                            current_file
-                        ));
-                    }
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| {
+                    loop {
+                        pause.blocking_wait(&tx, &cancel);
+                        if aborted.load(Ordering::Relaxed) || cancel.is_cancelled() {
+                            break;
+                        }
+                        let index = next_file.fetch_add(1, Ordering::Relaxed);
+                        let Some(file_info) = files.get(index) else {
+                            break;
+                        };
 
-                    error!(
-                        file = %current_file,
-                        error = %e.message,
-                        "Failed to copy file"
-                    );
+                        let relative = translation.destination(
+                            file_info
+                                .path
+                                .strip_prefix(&source)
+                                .expect("file should be under source"),
+                        );
+                        let dest_path = destination.join(&relative);
+                        let current_file = relative.to_string_lossy().to_string();
+
+                        debug!(file = %current_file, size = file_info.size, "Copying file");
+
+                        let mut attempts = 1;
+                        let mut missing = Vec::new();
+                        let result = loop {
+                            let mut attempt_bytes = 0;
+                            missing.clear();
+                            let result = copy_single_file(
+                                &file_info.path,
+                                &dest_path,
+                                sync_files,
+                                owner_ids.as_ref(),
+                                preserve_xattrs,
+                                buffer_size,
+                                throttle.as_ref(),
+                                rescue.then_some(&mut missing),
+                                &cancel,
+                                &mut |bytes| {
+                                    attempt_bytes += bytes;
+                                    let mut state = state.lock().unwrap();
+                                    state.in_flight += bytes;
+                                    report(&mut state, &current_file, false);
+                                },
+                            );
+                            // Counted again below if the file made it
+                            state.lock().unwrap().in_flight -= attempt_bytes;
+                            match result {
+                                // Retry only while the file can still be
+                                // reached; once the card is gone it's fatal
+                                Err(e)
+                                    if attempts <= file_retries
+                                        && !aborted.load(Ordering::Relaxed)
+                                        && !cancel.is_cancelled()
+                                        && file_info.path.exists() =>
+                                {
+                                    let delay = retry_backoff
+                                        .saturating_mul(1 << (attempts - 1).min(10));
+                                    warn!(
+                                        file = %current_file,
+                                        attempt = attempts,
+                                        error = %e.message,
+                                        retry_in_ms = delay.as_millis() as u64,
+                                        "File copy failed, retrying"
+                                    );
+                                    thread::sleep(delay);
+                                    attempts += 1;
+                                }
+                                result => break result,
+                            }
+                        };
 
-                    errors.push(CopyError {
-                        file: current_file,
-                        message: e.message,
-                    });
-                }
+                        let mut state = state.lock().unwrap();
+                        match result {
+                            Ok((file_bytes, hash)) => {
+                                state.bytes_copied += file_bytes;
+                                if attempts > 1 {
+                                    info!(file = %current_file, attempts, "File copied after retrying");
+                                    state.retried.push(RetriedFile {
+                                        relative_path: relative.to_path_buf(),
+                                        attempts,
+                                    });
+                                }
+                                if !missing.is_empty() {
+                                    warn!(
+                                        file = %current_file,
+                                        ranges = missing.len(),
+                                        "Copied with unreadable ranges zero-filled"
+                                    );
+                                    state.rescued.push(RescuedFile {
+                                        relative_path: relative.to_path_buf(),
+                                        missing: std::mem::take(&mut missing),
+                                    });
+                                }
+
+                                let file_hash = FileHash {
+                                    relative_path: relative.to_path_buf(),
+                                    hash: *hash.as_bytes(),
+                                    size: file_bytes,
+                                };
+                                if let Some(log) = &file_log {
+                                    let _ = log.send(file_hash.clone());
+                                }
+
+                                // Store the hash for verification
+                                state.file_hashes.push((index, file_hash));
+
+                                // The last file always reports 100%
+                                let finished = state.bytes_copied == total_bytes;
+                                report(&mut state, &current_file, finished);
+                            }
+                            // The half-written file goes with the rest
+                            Err(_) if cancel.is_cancelled() => break,
+                            Err(e) => {
+                                // Device removal is fatal - stop every worker
+                                if e.is_device_removed {
+                                    aborted.store(true, Ordering::Relaxed);
+                                    state.removed_at.get_or_insert(current_file);
+                                    break;
+                                }
+
+                                error!(
+                                    file = %current_file,
+                                    error = %e.message,
+                                    attempts,
+                                    "Failed to copy file"
+                                );
+
+                                let message = if attempts > 1 {
+                                    format!("{} (after {} attempts)", e.message, attempts)
+                                } else {
+                                    e.message
+                                };
+                                state.errors.push(CopyError {
+                                    file: current_file,
+                                    message,
+                                });
+                            }
+                        }
+                    }
+                });
             }
+        });
+
+        let CopyState {
+            bytes_copied,
+            errors,
+            mut file_hashes,
+            retried,
+            rescued,
+            removed_at,
+            ..
+        } = state.into_inner().unwrap();
+
+        if let Some(current_file) = removed_at {
+            return Err(anyhow!(
+                "Device removed during transfer at file: {}",
+                current_file
+            ));
+        }
+        if cancel.is_cancelled() {
+            bail!("Transfer cancelled");
         }
 
         // Report any non-fatal errors
@@ -467,11 +1199,185 @@ async fn copy_files_with_progress(
             return Err(anyhow!(error_summary));
         }
 
-        Ok((bytes_copied, file_hashes))
+        file_hashes.sort_by_key(|(index, _)| *index);
+        let file_hashes = file_hashes.into_iter().map(|(_, hash)| hash).collect();
+
+        Ok((bytes_copied, file_hashes, retried, rescued))
     })
     .await?
 }
 
+/// Hardlink files that are unchanged since an earlier backup into the
+/// destination: same relative path and size, and the source hashes to the
+/// value recorded for that backup. Anything that can't be linked (missing,
+/// on another filesystem, immutable) is left to be copied.
+/// Returns (files to copy, hashes of linked files).
+fn link_unchanged(
+    source: &Path,
+    destination: &Path,
+    files: Vec<FileInfo>,
+    link_dest: &LinkDest,
+    translation: &Translation,
+) -> (Vec<FileInfo>, Vec<FileHash>) {
+    let previous: HashMap<&Path, &FileHash> = link_dest
+        .files
+        .iter()
+        .map(|f| (f.relative_path.as_path(), f))
+        .collect();
+
+    let mut remaining = Vec::new();
+    let mut linked = Vec::new();
+    for file in files {
+        let relative =
+            translation.destination(file.path.strip_prefix(source).unwrap_or(&file.path));
+        let relative = relative.as_path();
+        let unchanged = previous.get(relative).is_some_and(|prev| {
+            prev.size == file.size
+                && hash_file_sync(&file.path).is_ok_and(|hash| hash.as_bytes() == &prev.hash)
+        });
+        if !unchanged {
+            remaining.push(file);
+            continue;
+        }
+
+        match fs::hard_link(
+            link_dest.directory.join(relative),
+            destination.join(relative),
+        ) {
+            Ok(()) => linked.push(previous[relative].clone()),
+            Err(e) => {
+                debug!(file = %relative.display(), error = %e, "Hardlink failed, copying instead");
+                remaining.push(file);
+            }
+        }
+    }
+    (remaining, linked)
+}
+
+/// Split scanned files into those still to copy and hashes of those an
+/// interrupted run already copied (same relative path and size).
+fn split_resumed(
+    source: &Path,
+    files: Vec<FileInfo>,
+    completed: &[FileHash],
+    translation: &Translation,
+) -> (Vec<FileInfo>, Vec<FileHash>) {
+    let completed: HashMap<&Path, &FileHash> = completed
+        .iter()
+        .map(|f| (f.relative_path.as_path(), f))
+        .collect();
+
+    let mut remaining = Vec::new();
+    let mut resumed = Vec::new();
+    for file in files {
+        let relative =
+            translation.destination(file.path.strip_prefix(source).unwrap_or(&file.path));
+        match completed.get(relative.as_path()) {
+            Some(done) if done.size == file.size => resumed.push((*done).clone()),
+            _ => remaining.push(file),
+        }
+    }
+    (remaining, resumed)
+}
+
+/// Split off files an earlier backup already holds: same relative path and
+/// size, and the source still hashes to the catalogued value. Files that
+/// can't be read are left in to be copied, where the error is reported.
+/// Returns (files to copy, skipped files).
+fn split_unchanged(
+    source: &Path,
+    files: Vec<FileInfo>,
+    backed_up: &[FileHash],
+    translation: &Translation,
+) -> (Vec<FileInfo>, Vec<FileHash>) {
+    let backed_up: HashMap<&Path, &FileHash> = backed_up
+        .iter()
+        .map(|f| (f.relative_path.as_path(), f))
+        .collect();
+
+    let mut remaining = Vec::new();
+    let mut skipped = Vec::new();
+    for file in files {
+        let relative =
+            translation.destination(file.path.strip_prefix(source).unwrap_or(&file.path));
+        match backed_up.get(relative.as_path()) {
+            Some(previous)
+                if previous.size == file.size
+                    && hash_file_sync(&file.path)
+                        .is_ok_and(|hash| hash.as_bytes() == &previous.hash) =>
+            {
+                skipped.push((*previous).clone())
+            }
+            _ => remaining.push(file),
+        }
+    }
+    (remaining, skipped)
+}
+
+/// Limits the combined write rate of the copy workers.
+///
+/// Workers report each chunk they write and sleep whenever the total is
+/// ahead of where the configured rate says it should be. After a long idle
+/// gap (a paused job) the count starts over, so the rate isn't made up in
+/// a burst.
+pub(super) struct Throttle {
+    bytes_per_sec: u64,
+    window: Mutex<(Instant, u64)>,
+}
+
+/// Idle time after which the throttle stops counting earlier bytes
+const THROTTLE_IDLE_RESET: Duration = Duration::from_secs(1);
+
+impl Throttle {
+    pub(super) fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec,
+            window: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    /// Account for `bytes` just written, blocking until the rate allows more.
+    pub(super) fn consume(&self, bytes: u64) {
+        let wait = {
+            let mut window = self.window.lock().unwrap();
+            let (started, total) = &mut *window;
+            let elapsed = started.elapsed();
+            if elapsed > self.due(*total) + THROTTLE_IDLE_RESET {
+                *started = Instant::now();
+                *total = 0;
+            }
+            *total += bytes;
+            self.due(*total).saturating_sub(started.elapsed())
+        };
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+
+    fn due(&self, bytes: u64) -> Duration {
+        Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64)
+    }
+}
+
+/// Progress and results shared between copy workers
+struct CopyState {
+    /// Bytes of finished files
+    bytes_copied: u64,
+    /// Bytes written so far of files still being copied
+    in_flight: u64,
+    progress: ProgressGate,
+    speed: SpeedMeter,
+    errors: Vec<CopyError>,
+    /// Hashes tagged with their scan index so order can be restored
+    file_hashes: Vec<(usize, FileHash)>,
+    /// Files that copied only after retrying
+    retried: Vec<RetriedFile>,
+    /// Files copied with unreadable ranges zero-filled
+    rescued: Vec<RescuedFile>,
+    /// File being copied when the device disappeared
+    removed_at: Option<String>,
+}
+
 /// Error information from a file copy operation
 #[derive(Debug)]
 struct FileCopyError {
@@ -485,13 +1391,22 @@ struct CopyError {
     message: String,
 }
 
-/// Copy a single file with metadata preservation.
+/// Copy a single file with metadata preservation, passing the size of each
+/// chunk written to `on_chunk`. With `rescue`, ranges that can't be read are
+/// zero-filled and added to it rather than failing the copy.
 /// Returns (bytes_written, blake3_hash) on success.
+#[allow(clippy::too_many_arguments)]
 fn copy_single_file(
     source: &Path,
     dest: &Path,
     sync_file: bool,
     owner_ids: Option<&OwnerIds>,
+    preserve_xattrs: bool,
+    buffer_size: usize,
+    throttle: Option<&Throttle>,
+    mut rescue: Option<&mut Vec<(u64, u64)>>,
+    cancel: &CancellationToken,
+    on_chunk: &mut dyn FnMut(u64),
 ) -> Result<(u64, blake3::Hash), FileCopyError> {
     // Read source file metadata first
     let source_metadata = fs::metadata(source).map_err(|e| FileCopyError {
@@ -504,22 +1419,44 @@ fn copy_single_file(
         message: format!("Failed to open source file: {}", e),
         is_device_removed: is_device_removed_error(&e),
     })?;
-    let mut reader = BufReader::with_capacity(BUFFER_SIZE, source_file);
+    let mut reader = BufReader::with_capacity(buffer_size, &source_file);
+
+    // Replace rather than truncate: when resuming, a file left at the
+    // destination may be a hardlink into an earlier backup
+    let _ = fs::remove_file(dest);
 
     // Create destination file
     let dest_file = File::create(dest).map_err(|e| FileCopyError {
         message: format!("Failed to create destination file: {}", e),
         is_device_removed: is_device_removed_error(&e),
     })?;
-    let mut writer = BufWriter::with_capacity(BUFFER_SIZE, dest_file);
+    let mut writer = BufWriter::with_capacity(buffer_size, dest_file);
 
     // Copy data in chunks while hashing
-    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut buffer = vec![0u8; buffer_size];
     let mut bytes_written: u64 = 0;
     let mut hasher = blake3::Hasher::new();
 
     loop {
-        let bytes_read = reader.read(&mut buffer).map_err(|e| FileCopyError {
+        if cancel.is_cancelled() {
+            return Err(FileCopyError {
+                message: "Transfer cancelled".to_string(),
+                is_device_removed: false,
+            });
+        }
+
+        let bytes_read = match rescue.as_deref_mut() {
+            Some(missing) => rescue_read(
+                &source_file,
+                source,
+                bytes_written,
+                source_metadata.len(),
+                &mut buffer,
+                missing,
+            ),
+            None => reader.read(&mut buffer),
+        }
+        .map_err(|e| FileCopyError {
             message: format!("Failed to read from source: {}", e),
             is_device_removed: is_device_removed_error(&e),
         })?;
@@ -539,6 +1476,11 @@ fn copy_single_file(
             })?;
 
         bytes_written += bytes_read as u64;
+        on_chunk(bytes_read as u64);
+
+        if let Some(throttle) = throttle {
+            throttle.consume(bytes_read as u64);
+        }
     }
 
     // Flush and optionally sync
@@ -550,7 +1492,7 @@ fn copy_single_file(
     if sync_file {
         let inner = writer.into_inner().map_err(|e| FileCopyError {
             message: format!("Failed to get inner file handle: {}", e.error()),
-            is_device_removed: is_device_removed_error(&e.error()),
+            is_device_removed: is_device_removed_error(e.error()),
         })?;
 
         inner.sync_all().map_err(|e| FileCopyError {
@@ -580,14 +1522,23 @@ fn copy_single_file(
     }
 
     // Set ownership if specified
-    if let Some(ids) = owner_ids {
-        if let Err(e) = chown(dest, Some(ids.uid), Some(ids.gid)) {
-            debug!(
-                dest = %dest.display(),
-                error = %e,
-                "Failed to set file ownership"
-            );
-        }
+    if let Some(ids) = owner_ids
+        && let Err(e) = chown(dest, Some(ids.uid), Some(ids.gid))
+    {
+        debug!(
+            dest = %dest.display(),
+            error = %e,
+            "Failed to set file ownership"
+        );
+    }
+
+    // Copy xattrs and ACLs last, as chown clears security.capability
+    if preserve_xattrs && let Err(e) = xattrs::copy_all(source, dest) {
+        debug!(
+            dest = %dest.display(),
+            error = %e,
+            "Failed to preserve file extended attributes"
+        );
     }
 
     Ok((bytes_written, hasher.finalize()))
@@ -611,7 +1562,11 @@ fn preserve_timestamps(source: &Path, dest: &Path) -> Result<()> {
 /// - Transfer just started (< 1 second elapsed, speed unreliable)
 /// - No bytes copied yet
 /// - Already complete
-fn calculate_eta(start_time: Instant, bytes_copied: u64, total_bytes: u64) -> Option<u64> {
+pub(super) fn calculate_eta(
+    start_time: Stopwatch,
+    bytes_copied: u64,
+    total_bytes: u64,
+) -> Option<u64> {
     if bytes_copied == 0 || bytes_copied >= total_bytes {
         return None;
     }
@@ -635,8 +1590,56 @@ fn calculate_eta(start_time: Instant, bytes_copied: u64, total_bytes: u64) -> Op
     Some(eta_secs.ceil() as u64)
 }
 
+/// Weight of each new sample in the smoothed speed: high enough to follow a
+/// change of reader or destination within a few updates, low enough that
+/// one slow file doesn't make the figure jump around.
+const SPEED_SMOOTHING: f64 = 0.3;
+
+/// Samples closer together than this are folded into the next one, as the
+/// rate over a few milliseconds says more about the page cache than the card.
+const SPEED_MIN_SPAN: Duration = Duration::from_millis(250);
+
+/// Exponentially smoothed transfer speed for `InProgress::bytes_per_sec`.
+#[derive(Debug)]
+pub(super) struct SpeedMeter {
+    clock: Stopwatch,
+    last: (Duration, u64),
+    rate: f64,
+}
+
+impl SpeedMeter {
+    /// Start measuring from `bytes_copied` (non-zero when resuming).
+    pub(super) fn new(bytes_copied: u64) -> Self {
+        Self {
+            clock: Stopwatch::start(),
+            last: (Duration::ZERO, bytes_copied),
+            rate: 0.0,
+        }
+    }
+
+    /// Note `bytes_copied` now and return the smoothed speed in bytes/s.
+    pub(super) fn update(&mut self, bytes_copied: u64) -> u64 {
+        self.update_at(self.clock.elapsed(), bytes_copied)
+    }
+
+    fn update_at(&mut self, elapsed: Duration, bytes_copied: u64) -> u64 {
+        let (last_elapsed, last_bytes) = self.last;
+        let span = elapsed.saturating_sub(last_elapsed);
+        if span >= SPEED_MIN_SPAN {
+            let sample = bytes_copied.saturating_sub(last_bytes) as f64 / span.as_secs_f64();
+            self.rate = if self.rate == 0.0 {
+                sample
+            } else {
+                self.rate + SPEED_SMOOTHING * (sample - self.rate)
+            };
+            self.last = (elapsed, bytes_copied);
+        }
+        self.rate as u64
+    }
+}
+
 /// Check if an I/O error indicates the device has been removed
-fn is_device_removed_error(error: &io::Error) -> bool {
+pub(super) fn is_device_removed_error(error: &io::Error) -> bool {
     match error.kind() {
         // Common error kinds when device is removed
         ErrorKind::NotFound => true,
@@ -720,10 +1723,22 @@ mod tests {
         assert!(!is_device_removed_error(&error));
     }
 
+    #[test]
+    fn test_speed_meter_smooths_updates() {
+        let mb = 1024 * 1024;
+        let mut speed = SpeedMeter::new(0);
+        assert_eq!(speed.update_at(Duration::from_millis(100), mb), 0);
+        assert_eq!(speed.update_at(Duration::from_secs(1), 100 * mb), 100 * mb);
+        // A slow second moves the figure only part of the way
+        assert_eq!(speed.update_at(Duration::from_secs(2), 110 * mb), 73 * mb);
+    }
+
     #[tokio::test]
     async fn test_scan_empty_directory() {
         let temp = tempdir().unwrap();
-        let result = scan_directory(temp.path()).await.unwrap();
+        let result = scan_directory(temp.path(), &PathFilter::default())
+            .await
+            .unwrap();
 
         assert!(result.files.is_empty());
         assert!(result.directories.is_empty());
@@ -740,13 +1755,50 @@ mod tests {
         fs::create_dir(temp.path().join("subdir")).unwrap();
         fs::write(temp.path().join("subdir/nested.txt"), b"nested").unwrap();
 
-        let result = scan_directory(temp.path()).await.unwrap();
+        let result = scan_directory(temp.path(), &PathFilter::default())
+            .await
+            .unwrap();
 
         assert_eq!(result.files.len(), 3);
         assert_eq!(result.directories.len(), 1);
         assert_eq!(result.total_bytes, 5 + 8 + 6); // hello + world!!! + nested
     }
 
+    #[tokio::test]
+    async fn test_scan_applies_filter() {
+        let temp = tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("DCIM/100CANON")).unwrap();
+        fs::create_dir_all(temp.path().join(".Trashes/501")).unwrap();
+        fs::create_dir(temp.path().join("MISC")).unwrap();
+        fs::write(temp.path().join("DCIM/100CANON/IMG_0001.CR3"), b"raw").unwrap();
+        fs::write(temp.path().join("DCIM/100CANON/IMG_0001.JPG"), b"jpeg").unwrap();
+        fs::write(temp.path().join("DCIM/100CANON/IMG_0002.CR3.tmp"), b"x").unwrap();
+        fs::write(temp.path().join(".Trashes/501/IMG_0000.CR3"), b"old").unwrap();
+        fs::write(temp.path().join("MISC/AUTPRINT.MRK"), b"dpof").unwrap();
+
+        let filter = PathFilter::new(
+            &["*.CR3".to_string()],
+            &["*.tmp".to_string(), ".Trashes/**".to_string()],
+        )
+        .unwrap();
+        let result = scan_directory(temp.path(), &filter).await.unwrap();
+
+        assert_eq!(result.files.len(), 1);
+        assert!(result.files[0].path.ends_with("DCIM/100CANON/IMG_0001.CR3"));
+        assert_eq!(result.total_bytes, 3);
+        // Only directories holding included files are recreated
+        let mut dirs: Vec<_> = result
+            .directories
+            .iter()
+            .map(|d| d.strip_prefix(temp.path()).unwrap().to_path_buf())
+            .collect();
+        dirs.sort();
+        assert_eq!(
+            dirs,
+            vec![PathBuf::from("DCIM"), PathBuf::from("DCIM/100CANON")]
+        );
+    }
+
     #[tokio::test]
     async fn test_native_copy_engine() {
         let temp = tempdir().unwrap();
@@ -768,8 +1820,16 @@ mod tests {
         let req = TransferRequest {
             job_id: "test-job".to_string(),
             source: source.clone(),
+            device: None,
             destination: dest.clone(),
             owner: None,
+            completed: Vec::new(),
+            file_log: None,
+            filter: PathFilter::default(),
+            backed_up: Vec::new(),
+            link_dest: None,
+            cancel: CancellationToken::new(),
+            pause: PauseToken::default(),
         };
 
         let handle = tokio::spawn(async move { engine.transfer(&req, tx).await });
@@ -801,6 +1861,123 @@ mod tests {
         assert!(matches!(updates.first(), Some(TransferStatus::Ready)));
     }
 
+    #[tokio::test]
+    async fn test_native_copy_reports_progress_within_files() {
+        let temp = tempdir().unwrap();
+        let source = temp.path().join("source");
+        let dest = temp.path().join("dest");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("CLIP.MP4"), vec![7u8; 1024 * 1024]).unwrap();
+
+        let engine = NativeCopyEngine {
+            sync_files: false,
+            buffer_size: Some(64 * 1024),
+            progress_interval: ProgressInterval {
+                bytes: 256 * 1024,
+                ms: 0,
+            },
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::channel(100);
+        let req = TransferRequest {
+            job_id: "test-job".to_string(),
+            source: source.clone(),
+            device: None,
+            destination: dest.clone(),
+            owner: None,
+            completed: Vec::new(),
+            file_log: None,
+            filter: PathFilter::default(),
+            backed_up: Vec::new(),
+            link_dest: None,
+            cancel: CancellationToken::new(),
+            pause: PauseToken::default(),
+        };
+
+        let handle = tokio::spawn(async move { engine.transfer(&req, tx).await });
+        let mut copied = Vec::new();
+        while let Some(status) = rx.recv().await {
+            if let TransferStatus::InProgress { bytes_copied, .. } = status {
+                copied.push(bytes_copied);
+            }
+        }
+        handle.await.unwrap().unwrap();
+
+        // One file, but an update every 256KB of it and one at the end
+        assert_eq!(
+            copied,
+            vec![64 * 1024, 320 * 1024, 576 * 1024, 832 * 1024, 1024 * 1024]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_native_copy_worker_pool() {
+        let temp = tempdir().unwrap();
+        let source = temp.path().join("source");
+        let dest = temp.path().join("dest");
+
+        fs::create_dir_all(source.join("DCIM")).unwrap();
+        for i in 0..20 {
+            fs::write(
+                source.join(format!("DCIM/IMG_{:04}.JPG", i)),
+                vec![i as u8; 1000 + i],
+            )
+            .unwrap();
+        }
+
+        let engine = NativeCopyEngine {
+            copy_threads: 4,
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::channel(100);
+
+        let req = TransferRequest {
+            job_id: "test-job".to_string(),
+            source: source.clone(),
+            device: None,
+            destination: dest.clone(),
+            owner: None,
+            completed: Vec::new(),
+            file_log: None,
+            filter: PathFilter::default(),
+            backed_up: Vec::new(),
+            link_dest: None,
+            cancel: CancellationToken::new(),
+            pause: PauseToken::default(),
+        };
+
+        let handle = tokio::spawn(async move { engine.transfer(&req, tx).await });
+
+        let mut last = None;
+        while let Some(status) = rx.recv().await {
+            last = Some(status);
+        }
+
+        let result = handle.await.unwrap().unwrap();
+        let expected: u64 = (0..20).map(|i| 1000 + i as u64).sum();
+        assert_eq!(result.total_bytes, expected);
+
+        // Hashes come back in scan order regardless of which worker copied them
+        let hashes = result.file_hashes.unwrap();
+        assert_eq!(hashes.len(), 20);
+        let scan = scan_directory(&source, &PathFilter::default())
+            .await
+            .unwrap();
+        for (hash, file) in hashes.iter().zip(&scan.files) {
+            assert_eq!(source.join(&hash.relative_path), file.path);
+            let content = fs::read(dest.join(&hash.relative_path)).unwrap();
+            assert_eq!(&hash.hash, blake3::hash(&content).as_bytes());
+        }
+
+        assert!(matches!(
+            last,
+            Some(TransferStatus::InProgress {
+                percentage: 100,
+                ..
+            })
+        ));
+    }
+
     #[tokio::test]
     async fn test_native_copy_refuses_existing_destination() {
         let temp = tempdir().unwrap();
@@ -809,6 +1986,7 @@ mod tests {
 
         fs::create_dir(&source).unwrap();
         fs::create_dir(&dest).unwrap(); // Pre-create destination
+        fs::write(dest.join("earlier.jpg"), b"jpeg").unwrap();
 
         let engine = NativeCopyEngine::default();
         let (tx, _rx) = mpsc::channel(100);
@@ -816,13 +1994,437 @@ mod tests {
         let req = TransferRequest {
             job_id: "test-job".to_string(),
             source,
-            destination: dest,
+            device: None,
+            destination: dest.clone(),
             owner: None,
+            completed: Vec::new(),
+            file_log: None,
+            filter: PathFilter::default(),
+            backed_up: Vec::new(),
+            link_dest: None,
+            cancel: CancellationToken::new(),
+            pause: PauseToken::default(),
         };
 
-        let result = engine.transfer(&req, tx).await;
+        let result = engine.transfer(&req, tx.clone()).await;
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("already exists"));
+
+        // An empty directory, like a subvolume made for the job, is used
+        fs::remove_file(dest.join("earlier.jpg")).unwrap();
+        assert!(engine.transfer(&req, tx).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_native_copy_resumes_from_manifest() {
+        let temp = tempdir().unwrap();
+        let source = temp.path().join("source");
+        let dest = temp.path().join("dest");
+
+        fs::create_dir_all(&source).unwrap();
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(source.join("done.jpg"), b"already copied").unwrap();
+        fs::write(source.join("todo.jpg"), b"still to copy").unwrap();
+        // Copied before the interruption; a marker proves it isn't rewritten
+        fs::write(dest.join("done.jpg"), b"already copied").unwrap();
+
+        let done = FileHash {
+            relative_path: PathBuf::from("done.jpg"),
+            hash: *blake3::hash(b"already copied").as_bytes(),
+            size: 14,
+        };
+        let engine = NativeCopyEngine::default();
+        let (tx, _rx) = mpsc::channel(100);
+        let (log_tx, mut log_rx) = mpsc::unbounded_channel();
+
+        let req = TransferRequest {
+            job_id: "test-job".to_string(),
+            source,
+            device: None,
+            destination: dest.clone(),
+            owner: None,
+            completed: vec![done.clone()],
+            file_log: Some(log_tx),
+            filter: PathFilter::default(),
+            backed_up: Vec::new(),
+            link_dest: None,
+            cancel: CancellationToken::new(),
+            pause: PauseToken::default(),
+        };
+
+        let result = engine.transfer(&req, tx).await.unwrap();
+        assert_eq!(result.total_bytes, 27);
+        assert_eq!(fs::read(dest.join("todo.jpg")).unwrap(), b"still to copy");
+
+        // Resumed file's hash is carried over; only the new copy is logged
+        let hashes = result.file_hashes.unwrap();
+        assert_eq!(hashes.len(), 2);
+        assert!(
+            hashes
+                .iter()
+                .any(|h| h.relative_path == done.relative_path && h.hash == done.hash)
+        );
+        drop(req);
+        let logged = log_rx.recv().await.unwrap();
+        assert_eq!(logged.relative_path, PathBuf::from("todo.jpg"));
+        assert!(log_rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_native_copy_translates_filenames() {
+        let temp = tempdir().unwrap();
+        let source = temp.path().join("source");
+        let dest = temp.path().join("dest");
+
+        fs::create_dir_all(source.join("A|B")).unwrap();
+        fs::write(source.join("A|B/clip:1.mov"), b"clip").unwrap();
+        fs::write(source.join("IMG_1.JPG"), b"upper").unwrap();
+        fs::write(source.join("img_1.jpg"), b"lower").unwrap();
+
+        let engine = NativeCopyEngine {
+            filenames: FilenameTranslation::Portable,
+            ..Default::default()
+        };
+        let (tx, _rx) = mpsc::channel(100);
+        let req = TransferRequest {
+            job_id: "test-job".to_string(),
+            source,
+            device: None,
+            destination: dest.clone(),
+            owner: None,
+            completed: Vec::new(),
+            file_log: None,
+            filter: PathFilter::default(),
+            backed_up: Vec::new(),
+            link_dest: None,
+            cancel: CancellationToken::new(),
+            pause: PauseToken::default(),
+        };
+
+        let result = engine.transfer(&req, tx).await.unwrap();
+        assert_eq!(fs::read(dest.join("A_B/clip_1.mov")).unwrap(), b"clip");
+        assert_eq!(fs::read(dest.join("IMG_1.JPG")).unwrap(), b"upper");
+        assert_eq!(fs::read(dest.join("img_1 (2).jpg")).unwrap(), b"lower");
+        let renamed: Vec<_> = result.renamed.iter().map(|r| r.to.clone()).collect();
+        assert_eq!(
+            renamed,
+            vec![
+                PathBuf::from("A_B"),
+                PathBuf::from("A_B/clip_1.mov"),
+                PathBuf::from("img_1 (2).jpg"),
+            ]
+        );
+        // Hashes name the files as they are in the backup
+        let hashes = result.file_hashes.unwrap();
+        assert!(hashes.iter().all(|h| dest.join(&h.relative_path).is_file()));
+    }
+
+    #[tokio::test]
+    async fn test_native_copy_organizes_by_date() {
+        let temp = tempdir().unwrap();
+        let source = temp.path().join("source");
+        let dest = temp.path().join("dest");
+
+        // No capture metadata, so the modification time decides
+        fs::create_dir_all(source.join("DCIM/100CANON")).unwrap();
+        fs::create_dir_all(source.join("DCIM/101CANON")).unwrap();
+        let january = filetime::FileTime::from_unix_time(1_736_856_000, 0);
+        let march = filetime::FileTime::from_unix_time(1_741_000_000, 0);
+        for (path, mtime) in [
+            ("DCIM/100CANON/IMG_0001.JPG", january),
+            ("DCIM/101CANON/IMG_0001.JPG", january),
+            ("DCIM/101CANON/IMG_0002.JPG", march),
+        ] {
+            fs::write(source.join(path), path).unwrap();
+            filetime::set_file_mtime(source.join(path), mtime).unwrap();
+        }
+
+        let engine = NativeCopyEngine {
+            organize_by_date: Some(DisplayZone::Named(chrono_tz::UTC)),
+            ..Default::default()
+        };
+        let (tx, _rx) = mpsc::channel(100);
+        let req = TransferRequest {
+            job_id: "test-job".to_string(),
+            source,
+            device: None,
+            destination: dest.clone(),
+            owner: None,
+            completed: Vec::new(),
+            file_log: None,
+            filter: PathFilter::default(),
+            backed_up: Vec::new(),
+            link_dest: None,
+            cancel: CancellationToken::new(),
+            pause: PauseToken::default(),
+        };
+
+        let result = engine.transfer(&req, tx).await.unwrap();
+        assert_eq!(
+            fs::read(dest.join("2025/01/14/IMG_0001.JPG")).unwrap(),
+            b"DCIM/100CANON/IMG_0001.JPG"
+        );
+        assert_eq!(
+            fs::read(dest.join("2025/01/14/IMG_0001 (2).JPG")).unwrap(),
+            b"DCIM/101CANON/IMG_0001.JPG"
+        );
+        assert!(dest.join("2025/03/03/IMG_0002.JPG").is_file());
+        assert!(!dest.join("DCIM").exists());
+        assert_eq!(result.relocated.len(), 3);
+        assert_eq!(result.renamed.len(), 1);
+        assert_eq!(
+            fs::read_to_string(dest.join(ORIGINAL_PATHS_NAME)).unwrap(),
+            "DCIM/100CANON/IMG_0001.JPG\t2025/01/14/IMG_0001.JPG\n\
+             DCIM/101CANON/IMG_0001.JPG\t2025/01/14/IMG_0001 (2).JPG\n\
+             DCIM/101CANON/IMG_0002.JPG\t2025/03/03/IMG_0002.JPG\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_native_copy_skips_unchanged_files() {
+        let temp = tempdir().unwrap();
+        let source = temp.path().join("source");
+        let dest = temp.path().join("dest");
+
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("old.jpg"), b"shot yesterday").unwrap();
+        fs::write(source.join("edited.jpg"), b"new content!").unwrap();
+        fs::write(source.join("new.jpg"), b"shot today").unwrap();
+
+        let backed_up = vec![
+            FileHash {
+                relative_path: PathBuf::from("old.jpg"),
+                hash: *blake3::hash(b"shot yesterday").as_bytes(),
+                size: 14,
+            },
+            // Same size, different content: must be copied again
+            FileHash {
+                relative_path: PathBuf::from("edited.jpg"),
+                hash: *blake3::hash(b"old content!").as_bytes(),
+                size: 12,
+            },
+        ];
+
+        let engine = NativeCopyEngine::default();
+        let (tx, _rx) = mpsc::channel(100);
+        let req = TransferRequest {
+            job_id: "test-job".to_string(),
+            source,
+            device: None,
+            destination: dest.clone(),
+            owner: None,
+            completed: Vec::new(),
+            file_log: None,
+            filter: PathFilter::default(),
+            backed_up,
+            link_dest: None,
+            cancel: CancellationToken::new(),
+            pause: PauseToken::default(),
+        };
+
+        let result = engine.transfer(&req, tx).await.unwrap();
+
+        assert_eq!(result.skipped.len(), 1);
+        assert_eq!(result.skipped[0].relative_path, PathBuf::from("old.jpg"));
+        assert!(!dest.join("old.jpg").exists());
+        assert!(dest.join("edited.jpg").exists());
+        assert!(dest.join("new.jpg").exists());
+        // Skipped files aren't in the destination, so they aren't verified
+        assert_eq!(result.file_hashes.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_native_copy_hardlinks_unchanged_files() {
+        use std::os::unix::fs::MetadataExt;
+
+        let temp = tempdir().unwrap();
+        let source = temp.path().join("source");
+        let previous = temp.path().join("previous");
+        let dest = temp.path().join("dest");
+
+        fs::create_dir_all(&source).unwrap();
+        fs::create_dir_all(&previous).unwrap();
+        fs::write(source.join("same.jpg"), b"unchanged").unwrap();
+        fs::write(source.join("new.jpg"), b"brand new").unwrap();
+        fs::write(previous.join("same.jpg"), b"unchanged").unwrap();
+
+        let engine = NativeCopyEngine::default();
+        let (tx, _rx) = mpsc::channel(100);
+        let req = TransferRequest {
+            job_id: "test-job".to_string(),
+            source,
+            device: None,
+            destination: dest.clone(),
+            owner: None,
+            completed: Vec::new(),
+            file_log: None,
+            filter: PathFilter::default(),
+            backed_up: Vec::new(),
+            link_dest: Some(LinkDest {
+                directory: previous.clone(),
+                files: vec![FileHash {
+                    relative_path: PathBuf::from("same.jpg"),
+                    hash: *blake3::hash(b"unchanged").as_bytes(),
+                    size: 9,
+                }],
+            }),
+            cancel: CancellationToken::new(),
+            pause: PauseToken::default(),
+        };
+
+        let result = engine.transfer(&req, tx).await.unwrap();
+
+        assert_eq!(result.linked, 1);
+        assert_eq!(result.total_bytes, 18);
+        let linked = fs::metadata(dest.join("same.jpg")).unwrap();
+        assert_eq!(
+            linked.ino(),
+            fs::metadata(previous.join("same.jpg")).unwrap().ino()
+        );
+        assert_eq!(fs::read(dest.join("new.jpg")).unwrap(), b"brand new");
+        // Linked files are still verified as part of the backup
+        assert_eq!(result.file_hashes.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_file_is_retried_before_erroring() {
+        let temp = tempdir().unwrap();
+        let source = temp.path().join("source");
+        let dest = temp.path().join("dest");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("IMG_0001.JPG"), b"jpeg").unwrap();
+        // A directory in the way makes every attempt fail
+        fs::create_dir_all(dest.join("IMG_0001.JPG")).unwrap();
+
+        let scan = scan_directory(&source, &PathFilter::default())
+            .await
+            .unwrap();
+        let options = CopyOptions {
+            sync_files: false,
+            owner_ids: None,
+            copy_threads: 1,
+            max_bytes_per_sec: None,
+            preserve_xattrs: false,
+            file_retries: 2,
+            retry_backoff: Duration::from_millis(1),
+            rescue: false,
+            buffer_size: BUFFER_SIZE,
+            file_log: None,
+            translation: Translation::default(),
+            progress_interval: ProgressInterval::default(),
+            cancel: CancellationToken::new(),
+            pause: PauseToken::default(),
+        };
+        let (tx, _rx) = mpsc::channel(100);
+
+        let err = copy_files_with_progress(
+            &source,
+            &dest,
+            &scan.files,
+            scan.total_bytes,
+            0,
+            &options,
+            Stopwatch::start(),
+            tx,
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("after 3 attempts"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_transfer_stops() {
+        let temp = tempdir().unwrap();
+        let source = temp.path().join("source");
+        let dest = temp.path().join("dest");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("IMG_0001.JPG"), b"jpeg").unwrap();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let req = TransferRequest {
+            job_id: "job-1".to_string(),
+            source: source.clone(),
+            device: None,
+            destination: dest.clone(),
+            owner: None,
+            completed: Vec::new(),
+            file_log: None,
+            filter: PathFilter::default(),
+            backed_up: Vec::new(),
+            link_dest: None,
+            cancel,
+            pause: PauseToken::default(),
+        };
+        let (tx, _rx) = mpsc::channel(100);
+
+        let err = NativeCopyEngine::default()
+            .transfer(&req, tx)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("cancelled"), "{}", err);
+        assert!(!dest.join("IMG_0001.JPG").exists());
+    }
+
+    #[tokio::test]
+    async fn test_paused_transfer_waits_for_resume() {
+        let temp = tempdir().unwrap();
+        let source = temp.path().join("source");
+        let dest = temp.path().join("dest");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("IMG_0001.JPG"), b"jpeg").unwrap();
+
+        let pause = PauseToken::default();
+        assert!(pause.pause());
+        let req = TransferRequest {
+            job_id: "job-1".to_string(),
+            source: source.clone(),
+            device: None,
+            destination: dest.clone(),
+            owner: None,
+            completed: Vec::new(),
+            file_log: None,
+            filter: PathFilter::default(),
+            backed_up: Vec::new(),
+            link_dest: None,
+            cancel: CancellationToken::new(),
+            pause: pause.clone(),
+        };
+        let (tx, mut rx) = mpsc::channel(100);
+        let transfer =
+            tokio::spawn(async move { NativeCopyEngine::default().transfer(&req, tx).await });
+
+        loop {
+            match rx.recv().await {
+                Some(TransferStatus::Paused) => break,
+                Some(_) => continue,
+                None => panic!("transfer ended without pausing"),
+            }
+        }
+        assert!(!dest.join("IMG_0001.JPG").exists());
+
+        assert!(pause.resume());
+        transfer.await.unwrap().unwrap();
+        assert_eq!(fs::read(dest.join("IMG_0001.JPG")).unwrap(), b"jpeg");
+    }
+
+    #[test]
+    fn test_throttle_limits_rate() {
+        let throttle = Throttle::new(1024 * 1024);
+        let start = Instant::now();
+        for _ in 0..4 {
+            throttle.consume(64 * 1024);
+        }
+        // 256KB at 1MB/s takes about a quarter of a second
+        assert!(start.elapsed() >= Duration::from_millis(240));
+
+        // An idle gap (a paused job) isn't made up afterwards
+        thread::sleep(Duration::from_millis(1500));
+        let start = Instant::now();
+        for _ in 0..4 {
+            throttle.consume(64 * 1024);
+        }
+        assert!(start.elapsed() >= Duration::from_millis(180));
     }
 
     #[test]
@@ -834,10 +2436,23 @@ mod tests {
         let content = b"test file content for copying";
         fs::write(&source, content).unwrap();
 
-        let result = copy_single_file(&source, &dest, true, None);
+        let mut chunks = 0;
+        let result = copy_single_file(
+            &source,
+            &dest,
+            true,
+            None,
+            false,
+            BUFFER_SIZE,
+            None,
+            None,
+            &CancellationToken::new(),
+            &mut |bytes| chunks += bytes,
+        );
         assert!(result.is_ok());
         let (bytes, hash) = result.unwrap();
         assert_eq!(bytes, content.len() as u64);
+        assert_eq!(chunks, bytes);
 
         // Verify content was copied correctly
         let copied_content = fs::read(&dest).unwrap();
@@ -847,4 +2462,162 @@ mod tests {
         let expected_hash = blake3::hash(content);
         assert_eq!(hash, expected_hash);
     }
+
+    #[test]
+    fn test_copy_single_file_preserves_xattrs() {
+        let temp = tempdir().unwrap();
+        let source = temp.path().join("source.txt");
+        let dest = temp.path().join("dest.txt");
+        fs::write(&source, b"tagged").unwrap();
+
+        let name = std::ffi::CString::new("user.bksd.test").unwrap();
+        if xattrs::set(&source, &name, b"keep me").is_err() {
+            // Temp filesystem without user xattrs (e.g. some tmpfs setups)
+            return;
+        }
+
+        copy_single_file(
+            &source,
+            &dest,
+            false,
+            None,
+            false,
+            BUFFER_SIZE,
+            None,
+            None,
+            &CancellationToken::new(),
+            &mut |_| {},
+        )
+        .unwrap();
+        assert!(xattrs::get(&dest, &name).is_err());
+
+        copy_single_file(
+            &source,
+            &dest,
+            false,
+            None,
+            true,
+            BUFFER_SIZE,
+            None,
+            None,
+            &CancellationToken::new(),
+            &mut |_| {},
+        )
+        .unwrap();
+        assert_eq!(xattrs::get(&dest, &name).unwrap(), b"keep me");
+    }
+
+    #[test]
+    fn test_rescue_mode_maps_missing_ranges() {
+        let temp = tempdir().unwrap();
+        let source = temp.path().join("IMG_0001.CR3");
+        let dest = temp.path().join("copy.CR3");
+        let content = vec![7u8; 3 * RESCUE_BLOCK + 100];
+        fs::write(&source, &content).unwrap();
+
+        // A readable file copies as usual, in chunks smaller than the file
+        let mut missing = Vec::new();
+        let (bytes, hash) = copy_single_file(
+            &source,
+            &dest,
+            false,
+            None,
+            false,
+            RESCUE_BLOCK,
+            None,
+            Some(&mut missing),
+            &CancellationToken::new(),
+            &mut |_| {},
+        )
+        .unwrap();
+        assert_eq!(bytes, content.len() as u64);
+        assert_eq!(hash, blake3::hash(&content));
+        assert!(missing.is_empty());
+
+        // Neighbouring bad blocks make one range
+        add_missing(&mut missing, 4096, 4096);
+        add_missing(&mut missing, 8192, 4096);
+        add_missing(&mut missing, 65536, 512);
+        assert_eq!(missing, vec![(4096, 8192), (65536, 512)]);
+
+        let rescued = RescuedFile {
+            relative_path: PathBuf::from("DCIM/100CANON/IMG_0001.CR3"),
+            missing,
+        };
+        assert_eq!(rescued.missing_bytes(), 8704);
+        write_rescue_map(temp.path(), std::slice::from_ref(&rescued)).unwrap();
+        // A resumed job adds to the map
+        write_rescue_map(temp.path(), &[rescued]).unwrap();
+        let map = fs::read_to_string(temp.path().join(RESCUE_MAP_NAME)).unwrap();
+        assert_eq!(map.lines().count(), 4);
+        assert_eq!(
+            map.lines().next(),
+            Some("DCIM/100CANON/IMG_0001.CR3\t4096\t8192")
+        );
+    }
+
+    /// A card file whose blocks starting at the listed offsets fail with EIO
+    struct BadBlocks {
+        content: Vec<u8>,
+        bad: Vec<u64>,
+    }
+
+    impl CardRead for BadBlocks {
+        fn read_chunk(&self, buffer: &mut [u8], offset: u64) -> io::Result<usize> {
+            let end = (offset as usize + buffer.len()).min(self.content.len());
+            if self
+                .bad
+                .iter()
+                .any(|&bad| bad >= offset && (bad as usize) < end)
+            {
+                return Err(io::Error::from_raw_os_error(libc::EIO));
+            }
+            let read = end.saturating_sub(offset as usize);
+            buffer[..read].copy_from_slice(&self.content[offset as usize..end]);
+            Ok(read)
+        }
+
+        fn read_block(&self, buffer: &mut [u8], offset: u64) -> io::Result<()> {
+            if self.bad.contains(&offset) {
+                return Err(io::Error::from_raw_os_error(libc::EIO));
+            }
+            let start = offset as usize;
+            buffer.copy_from_slice(&self.content[start..start + buffer.len()]);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_rescue_read_zero_fills_unreadable_blocks() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("IMG_0001.CR3");
+        fs::write(&path, b"").unwrap();
+        let card = BadBlocks {
+            content: vec![7u8; 4 * RESCUE_BLOCK],
+            bad: vec![RESCUE_BLOCK as u64, 2 * RESCUE_BLOCK as u64],
+        };
+        let size = card.content.len() as u64;
+
+        let mut buffer = vec![1u8; 4 * RESCUE_BLOCK];
+        let mut missing = Vec::new();
+        let read = rescue_read(&card, &path, 0, size, &mut buffer, &mut missing).unwrap();
+        assert_eq!(read, 4 * RESCUE_BLOCK);
+        assert_eq!(
+            missing,
+            vec![(RESCUE_BLOCK as u64, 2 * RESCUE_BLOCK as u64)]
+        );
+        assert!(buffer[..RESCUE_BLOCK].iter().all(|&b| b == 7));
+        assert!(
+            buffer[RESCUE_BLOCK..3 * RESCUE_BLOCK]
+                .iter()
+                .all(|&b| b == 0)
+        );
+        assert!(buffer[3 * RESCUE_BLOCK..].iter().all(|&b| b == 7));
+
+        // A card that has gone away fails the read instead
+        fs::remove_file(&path).unwrap();
+        let mut missing = Vec::new();
+        assert!(rescue_read(&card, &path, 0, size, &mut buffer, &mut missing).is_err());
+        assert!(missing.is_empty());
+    }
 }
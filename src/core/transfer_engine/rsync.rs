@@ -1,19 +1,39 @@
+use crate::core::clock::Stopwatch;
+use crate::core::transfer_engine::native_copy::{SpeedMeter, calculate_eta, scan_directory};
 use crate::core::transfer_engine::{
-    TransferEngine, TransferRequest, TransferResult, TransferStatus,
+    LargestFile, ProgressInterval, ProgressSender, TransferEngine, TransferRequest, TransferResult,
+    TransferStatus, destination_in_use,
 };
+use crate::core::verifier::verify_against_source;
 use anyhow::{Result, anyhow};
+use nix::sys::signal::{Signal, kill};
+use nix::unistd::Pid;
 use regex::Regex;
 use std::future::Future;
+use std::path::PathBuf;
 use std::pin::Pin;
-use std::time::Instant;
 use tokio::{
     io::{AsyncReadExt, BufReader},
-    process::Command,
+    process::{Child, Command},
     sync::mpsc,
 };
-use tracing::{Instrument, info, info_span};
+use tracing::{Instrument, info, info_span, warn};
 
-pub struct RsyncEngine;
+pub struct RsyncEngine {
+    /// rsync binary to run
+    pub binary: PathBuf,
+    /// Arguments appended after the built-in ones
+    pub extra_args: Vec<String>,
+    /// Value for `--bwlimit`, in KiB/s
+    pub bwlimit_kib: Option<u64>,
+    /// Pass `-X -A` to copy extended attributes and ACLs
+    pub preserve_xattrs: bool,
+    /// Hash every file on both sides after the copy. `--checksum` only
+    /// decides what to send, so this is the only check of what was written
+    pub verify: bool,
+    /// How often verification reports progress
+    pub progress_interval: ProgressInterval,
+}
 
 impl TransferEngine for RsyncEngine {
     fn transfer(
@@ -22,6 +42,12 @@ impl TransferEngine for RsyncEngine {
         tx: mpsc::Sender<TransferStatus>,
     ) -> Pin<Box<dyn Future<Output = Result<TransferResult>> + Send>> {
         let req = req.clone();
+        let bwlimit_kib = self.bwlimit_kib;
+        let preserve_xattrs = self.preserve_xattrs;
+        let verify = self.verify;
+        let progress_interval = self.progress_interval;
+        let binary = self.binary.clone();
+        let extra_args = self.extra_args.clone();
         Box::pin(async move {
             let _ = tx.send(TransferStatus::Ready).await;
 
@@ -29,7 +55,7 @@ impl TransferEngine for RsyncEngine {
             let destination = req.destination.to_string_lossy().to_string();
 
             // Safety check: fail if destination already exists to prevent overwrites
-            if req.destination.exists() {
+            if destination_in_use(&req.destination) {
                 let msg = format!(
                     "Destination already exists: {}. Refusing to overwrite.",
                     req.destination.display()
@@ -53,27 +79,79 @@ impl TransferEngine for RsyncEngine {
             async {
                 info!("Starting rsync transfer");
 
-                let start_time = Instant::now();
+                // progress2 only knows the total once rsync has built its
+                // whole file list, so size the source up front for the ETA
+                let scan = match scan_directory(&req.source, &req.filter).await {
+                    Ok(scan) => {
+                        info!(
+                            total_files = scan.files.len(),
+                            total_bytes = scan.total_bytes,
+                            "Scan complete"
+                        );
+                        Some(scan)
+                    }
+                    Err(e) => {
+                        warn!(error = %e, "Failed to size source, reporting progress without an ETA");
+                        None
+                    }
+                };
+                let total_bytes = scan.as_ref().map_or(0, |scan| scan.total_bytes);
+
+                if req.cancel.is_cancelled() {
+                    return Err(anyhow!("Transfer cancelled"));
+                }
+
+                let start_time = Stopwatch::start();
                 let mut last_bytes_copied: u64 = 0;
+                let mut speed = SpeedMeter::new(0);
 
-                let mut cmd = Command::new("rsync");
+                let mut cmd = Command::new(&binary);
                 cmd.arg("-av")
-                    .arg("--checksum") // Verify file checksums during transfer
+                    .arg("--checksum") // Compare by checksum rather than size and mtime
                     .arg("--chmod=u+rw,g+r,o+r")
                     .arg("--info=progress2")
                     .arg("--no-inc-recursive");
 
+                if let Some(kib) = bwlimit_kib {
+                    cmd.arg(format!("--bwlimit={}", kib));
+                    info!(bwlimit_kib = kib, "Limiting transfer rate");
+                }
+
+                if preserve_xattrs {
+                    cmd.arg("-X").arg("-A");
+                }
+
+                let filter_args = req.filter.rsync_args();
+                if !filter_args.is_empty() {
+                    info!(filters = ?filter_args, "Applying include/exclude filters");
+                    cmd.args(filter_args);
+                }
+
+                if let Some(ref link_dest) = req.link_dest {
+                    cmd.arg(format!("--link-dest={}", link_dest.directory.display()));
+                    info!(link_dest = %link_dest.directory.display(), "Hardlinking unchanged files");
+                }
+
                 if let Some(ref owner) = req.owner {
                     cmd.arg(format!("--chown={}", owner.as_chown_arg()));
                     info!(owner = %owner.as_chown_arg(), "Setting file ownership");
                 }
 
+                if !extra_args.is_empty() {
+                    info!(args = ?extra_args, "Appending configured rsync arguments");
+                    cmd.args(&extra_args);
+                }
+
                 let mut child_process = cmd
                     .arg(format!("{}/", source)) // trailing slash to copy contents
                     .arg(destination.as_str())
                     .stdout(std::process::Stdio::piped())
+                    // Don't leave rsync copying if the daemon exits first
+                    .kill_on_drop(true)
                     .spawn()
-                    .map_err(|e| anyhow!("Failed to spawn rsync process: {}", e))?;
+                    .map_err(|e| {
+                        anyhow!("Failed to spawn rsync ({}): {}", binary.display(), e)
+                    })?;
 
                 let stdout = child_process
                     .stdout
@@ -81,13 +159,41 @@ impl TransferEngine for RsyncEngine {
                     .ok_or_else(|| anyhow!("Failed to get stdout"))?;
                 let mut reader = BufReader::new(stdout);
 
+                // rsync can't be held between files, so pausing stops the
+                // process outright
+                let mut paused = req.pause.subscribe();
+                if *paused.borrow_and_update() {
+                    signal_rsync(&child_process, Signal::SIGSTOP);
+                    let _ = tx.send(TransferStatus::Paused).await;
+                }
+
                 // Regex: "  12,345,678   45%  10.2MB/s ..."
                 let re = Regex::new(r"^\s*([\d,]+)\s+(\d+)%").unwrap();
 
                 let mut line_buffer = Vec::new();
                 let mut byte_buffer = [0u8; 1];
 
-                while let Ok(n) = reader.read(&mut byte_buffer).await {
+                loop {
+                    let read = tokio::select! {
+                        read = reader.read(&mut byte_buffer) => read,
+                        _ = req.cancel.cancelled() => {
+                            let _ = child_process.kill().await;
+                            info!("Rsync killed, transfer cancelled");
+                            return Err(anyhow!("Transfer cancelled"));
+                        }
+                        Ok(()) = paused.changed() => {
+                            if *paused.borrow_and_update() {
+                                signal_rsync(&child_process, Signal::SIGSTOP);
+                                info!("Rsync stopped, transfer paused");
+                                let _ = tx.send(TransferStatus::Paused).await;
+                            } else {
+                                signal_rsync(&child_process, Signal::SIGCONT);
+                                info!("Rsync continued, transfer resumed");
+                            }
+                            continue;
+                        }
+                    };
+                    let Ok(n) = read else { break };
                     if n == 0 {
                         break;
                     }
@@ -117,11 +223,16 @@ impl TransferEngine for RsyncEngine {
 
                             let _ = tx
                                 .send(TransferStatus::InProgress {
-                                    total_bytes: 0,
+                                    total_bytes: total_bytes.max(bytes_copied),
                                     bytes_copied,
                                     current_file: String::new(),
                                     percentage,
-                                    eta_seconds: None, // rsync doesn't know total size upfront
+                                    eta_seconds: calculate_eta(
+                                        start_time,
+                                        bytes_copied,
+                                        total_bytes,
+                                    ),
+                                    bytes_per_sec: speed.update(bytes_copied),
                                 })
                                 .await;
                         }
@@ -137,31 +248,88 @@ impl TransferEngine for RsyncEngine {
                     .await
                     .map_err(|e| anyhow!("Failed to wait for rsync: {}", e))?;
 
-                if status.success() {
-                    let duration_secs = start_time.elapsed().as_secs();
-                    info!(
-                        total_bytes = last_bytes_copied,
-                        duration_secs = duration_secs,
-                        "Rsync transfer finished (checksum verified)"
-                    );
-                    Ok(TransferResult {
-                        total_bytes: last_bytes_copied,
-                        duration_secs,
-                        // rsync --checksum handles verification internally
-                        file_hashes: None,
-                    })
-                } else {
+                if !status.success() {
                     let _ = tx
                         .send(TransferStatus::Failed(format!(
                             "Rsync failed with status: {}",
                             status
                         )))
                         .await;
-                    Err(anyhow!("Rsync failed with status: {}", status))
+                    return Err(anyhow!("Rsync failed with status: {}", status));
+                }
+
+                let duration_secs = start_time.elapsed().as_secs();
+                info!(
+                    total_bytes = last_bytes_copied,
+                    duration_secs = duration_secs,
+                    "Rsync transfer finished"
+                );
+
+                if verify {
+                    // Rescan rather than reuse the sizing pass, which may
+                    // have failed or predate files rsync picked up
+                    let files = scan_directory(&req.source, &req.filter)
+                        .await?
+                        .files
+                        .into_iter()
+                        .filter_map(|file| {
+                            file.path
+                                .strip_prefix(&req.source)
+                                .ok()
+                                .map(|relative| relative.to_path_buf())
+                        })
+                        .collect();
+                    if let Err(e) = verify_against_source(
+                        &req.job_id,
+                        &req.source,
+                        &req.destination,
+                        files,
+                        ProgressSender {
+                            tx: tx.clone(),
+                            interval: progress_interval,
+                        },
+                        &req.cancel,
+                    )
+                    .await
+                    {
+                        if !req.cancel.is_cancelled() {
+                            let _ = tx.send(TransferStatus::Failed(e.to_string())).await;
+                        }
+                        return Err(e);
+                    }
                 }
+
+                Ok(TransferResult {
+                    skipped: Vec::new(),
+                    linked: 0,
+                    retried: Vec::new(),
+                    rescued: Vec::new(),
+                    renamed: Vec::new(),
+                    relocated: Vec::new(),
+                    total_bytes: last_bytes_copied,
+                    duration_secs,
+                    // rsync itself fails on unreadable files
+                    files_copied: scan.as_ref().map_or(0, |scan| scan.files.len()),
+                    files_failed: 0,
+                    largest_file: scan.as_ref().and_then(|scan| {
+                        LargestFile::of(scan.files.iter().filter_map(|file| {
+                            Some((file.path.strip_prefix(&req.source).ok()?, file.size))
+                        }))
+                    }),
+                    // Verified above, if enabled
+                    file_hashes: None,
+                })
             }
             .instrument(span)
             .await
         })
     }
 }
+
+/// Stop or continue the rsync process for `jobs.pause`/`jobs.resume`.
+fn signal_rsync(child: &Child, signal: Signal) {
+    let Some(pid) = child.id() else { return };
+    if let Err(e) = kill(Pid::from_raw(pid as i32), signal) {
+        warn!(signal = %signal, error = %e, "Failed to signal rsync");
+    }
+}
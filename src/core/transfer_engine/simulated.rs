@@ -1,51 +1,129 @@
+use crate::config::{SimFileSizes, SimProfile};
+use crate::core::clock::Stopwatch;
 use crate::core::transfer_engine::{
-    TransferEngine, TransferRequest, TransferResult, TransferStatus,
+    LargestFile, TransferEngine, TransferRequest, TransferResult, TransferStatus,
 };
 use anyhow::Result;
 use std::future::Future;
 use std::pin::Pin;
-use std::time::Instant;
 use tokio::sync::mpsc;
 use tokio::time::{Duration, sleep};
 
+const MIB: u64 = 1024 * 1024;
+
+/// A file on a simulated card
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimFile {
+    pub path: String,
+    pub size: u64,
+}
+
 pub struct SimulatedEngine {
     pub speed_mb_per_sec: u64,
+    /// The card's files, copied in order
+    pub files: Vec<SimFile>,
+    /// Fail with a read error once this far through the copy
+    pub fail_at_percent: Option<u8>,
 }
 
 impl Default for SimulatedEngine {
     fn default() -> Self {
         Self {
             speed_mb_per_sec: 100,
+            files: vec![SimFile {
+                path: "simulated_file.dat".to_string(),
+                size: 500 * MIB,
+            }],
+            fail_at_percent: None,
         }
     }
 }
 
+impl SimulatedEngine {
+    /// Engine copying the files a device profile describes.
+    pub fn from_profile(profile: &SimProfile) -> Self {
+        Self {
+            speed_mb_per_sec: profile.speed_mb_per_sec,
+            files: files(profile),
+            fail_at_percent: profile.fail_at_percent,
+        }
+    }
+}
+
+/// Lay out a profile's data as files. The same profile always gives the same
+/// files, so runs are comparable.
+fn files(profile: &SimProfile) -> Vec<SimFile> {
+    let count = profile.files.max(1);
+    let weight = |i: u64| match profile.file_sizes {
+        SimFileSizes::Even => 1,
+        SimFileSizes::Clips if i.is_multiple_of(10) => 50,
+        SimFileSizes::Clips => 1,
+    };
+    let total_weight: u64 = (0..count).map(weight).sum();
+    let total = profile.data_mb * MIB;
+
+    let mut remaining = total;
+    (0..count)
+        .map(|i| {
+            let size = if i + 1 == count {
+                remaining
+            } else {
+                (total as u128 * weight(i) as u128 / total_weight as u128) as u64
+            };
+            remaining -= size;
+            let extension = match profile.file_sizes {
+                SimFileSizes::Clips if weight(i) > 1 => "MOV",
+                SimFileSizes::Clips => "JPG",
+                SimFileSizes::Even => "DAT",
+            };
+            SimFile {
+                path: format!("DCIM/100SIMUL/SIM_{:04}.{}", i + 1, extension),
+                size,
+            }
+        })
+        .collect()
+}
+
 impl TransferEngine for SimulatedEngine {
     fn transfer(
         &self,
         req: &TransferRequest,
         tx: mpsc::Sender<TransferStatus>,
     ) -> Pin<Box<dyn Future<Output = Result<TransferResult>> + Send>> {
-        let _req = req.clone();
-        let speed = self.speed_mb_per_sec;
+        let cancel = req.cancel.clone();
+        let pause = req.pause.clone();
+        let speed = self.speed_mb_per_sec.max(1);
+        let files = self.files.clone();
+        let fail_at_percent = self.fail_at_percent;
 
         Box::pin(async move {
-            let start_time = Instant::now();
+            let start_time = Stopwatch::start();
 
             let _ = tx.send(TransferStatus::Ready).await;
             sleep(Duration::from_millis(500)).await;
 
-            let total_size: u64 = 1024 * 1024 * 500; // 500 MB
-            let chunk_size = speed * 1024 * 1024 / 2; // update twice per second
+            let total_size: u64 = files.iter().map(|f| f.size).sum();
+            let chunk_size = speed * MIB / 2; // update twice per second
             let mut copied: u64 = 0;
 
             while copied < total_size {
+                pause.wait(&tx, &cancel).await;
+                if cancel.is_cancelled() {
+                    anyhow::bail!("Transfer cancelled");
+                }
                 copied += chunk_size;
                 if copied > total_size {
                     copied = total_size;
                 }
 
                 let percentage = ((copied as f64 / total_size as f64) * 100.0) as u8;
+                let current_file = current_file(&files, copied);
+
+                if let Some(fail_at) = fail_at_percent
+                    && percentage >= fail_at
+                {
+                    anyhow::bail!("Simulated read error on {}", current_file);
+                }
 
                 // Calculate ETA based on simulated speed
                 let elapsed_secs = start_time.elapsed().as_secs_f64();
@@ -61,9 +139,10 @@ impl TransferEngine for SimulatedEngine {
                     .send(TransferStatus::InProgress {
                         total_bytes: total_size,
                         bytes_copied: copied,
-                        current_file: "simulated_file.dat".to_string(),
+                        current_file: current_file.to_string(),
                         percentage,
                         eta_seconds,
+                        bytes_per_sec: speed * MIB,
                     })
                     .await;
 
@@ -71,14 +150,66 @@ impl TransferEngine for SimulatedEngine {
             }
 
             let duration_secs = start_time.elapsed().as_secs();
+            let largest_file = files.iter().max_by_key(|f| f.size).map(|f| LargestFile {
+                path: f.path.clone(),
+                size: f.size,
+            });
 
             // Return transfer result - orchestrator handles CopyComplete and verification
             Ok(TransferResult {
+                skipped: Vec::new(),
+                linked: 0,
+                retried: Vec::new(),
+                rescued: Vec::new(),
+                renamed: Vec::new(),
+                relocated: Vec::new(),
                 total_bytes: total_size,
                 duration_secs,
+                files_copied: files.len(),
+                files_failed: 0,
+                largest_file,
                 // Simulated engine doesn't produce file hashes
                 file_hashes: None,
             })
         })
     }
 }
+
+/// The file being copied once `copied` bytes are done.
+fn current_file(files: &[SimFile], copied: u64) -> &str {
+    let mut end = 0;
+    for file in files {
+        end += file.size;
+        if copied <= end {
+            return &file.path;
+        }
+    }
+    files.last().map(|f| f.path.as_str()).unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_files() {
+        let profile = SimProfile {
+            files: 20,
+            file_sizes: SimFileSizes::Clips,
+            data_mb: 118,
+            ..Default::default()
+        };
+        let files = files(&profile);
+
+        assert_eq!(files.len(), 20);
+        assert_eq!(files.iter().map(|f| f.size).sum::<u64>(), 118 * MIB);
+        // Two clips of 50 parts each, eighteen stills of one part
+        assert_eq!(files[0].path, "DCIM/100SIMUL/SIM_0001.MOV");
+        assert_eq!(files[0].size, 50 * MIB);
+        assert_eq!(files[1].size, MIB);
+        assert_eq!(files[10].size, 50 * MIB);
+        assert_eq!(current_file(&files, 51 * MIB), "DCIM/100SIMUL/SIM_0002.JPG");
+        // Same profile, same card
+        assert_eq!(super::files(&profile), files);
+    }
+}
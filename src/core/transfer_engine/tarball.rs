@@ -0,0 +1,573 @@
+//! Compressed archive transfer engine.
+//!
+//! Streams the card into a single zstd-compressed tar at the destination
+//! rather than a directory tree, for cold storage where space matters more
+//! than browsing. Each file is hashed with BLAKE3 as it is read, and the
+//! hashes are written into the archive as a final `BKSD-CHECKSUMS.b3` entry
+//! (b3sum format, so `b3sum -c` works on an extracted copy). The finished
+//! archive is then read back and every entry re-hashed against that index,
+//! so there is nothing left for the orchestrator's verifier to check.
+
+use crate::core::clock::Stopwatch;
+use crate::core::transfer_engine::native_copy::{
+    BUFFER_SIZE, FileInfo, OwnerIds, SpeedMeter, Throttle, calculate_eta, is_device_removed_error,
+    resolve_owner, scan_directory,
+};
+use crate::core::transfer_engine::{
+    FileHash, LargestFile, PauseToken, ProgressGate, ProgressInterval, ProgressSender,
+    TransferEngine, TransferRequest, TransferResult, TransferStatus, VerifyProgress,
+    destination_in_use,
+};
+use anyhow::{Result, anyhow, bail};
+use nix::unistd::chown;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::future::Future;
+use std::io::{self, BufReader, BufWriter, Read};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, debug, info, info_span, warn};
+
+/// File name of the archive inside the job's destination directory
+pub const ARCHIVE_NAME: &str = "backup.tar.zst";
+
+/// Name of the checksum index entry, written last
+pub const CHECKSUM_INDEX: &str = "BKSD-CHECKSUMS.b3";
+
+/// Transfer engine that writes a zstd-compressed tar archive.
+pub struct ArchiveEngine {
+    /// zstd level, 1 (fastest) to 22 (smallest)
+    pub compression_level: i32,
+    /// Read back and check the archive against its checksum index
+    pub verify: bool,
+    /// Optional cap on read throughput in bytes per second
+    pub max_bytes_per_sec: Option<u64>,
+    /// How often writing and reading back the archive report progress
+    pub progress_interval: ProgressInterval,
+}
+
+impl Default for ArchiveEngine {
+    fn default() -> Self {
+        Self {
+            compression_level: 3,
+            verify: true,
+            max_bytes_per_sec: None,
+            progress_interval: ProgressInterval::default(),
+        }
+    }
+}
+
+impl TransferEngine for ArchiveEngine {
+    fn transfer(
+        &self,
+        req: &TransferRequest,
+        tx: mpsc::Sender<TransferStatus>,
+    ) -> Pin<Box<dyn Future<Output = Result<TransferResult>> + Send>> {
+        let req = req.clone();
+        let compression_level = self.compression_level;
+        let verify = self.verify;
+        let max_bytes_per_sec = self.max_bytes_per_sec;
+        let progress_interval = self.progress_interval;
+
+        Box::pin(async move {
+            let _ = tx.send(TransferStatus::Ready).await;
+
+            let source = req.source.clone();
+            let destination = req.destination.clone();
+
+            // Safety check: fail if destination already exists to prevent overwrites
+            if destination_in_use(&destination) {
+                let msg = format!(
+                    "Destination already exists: {}. Refusing to overwrite.",
+                    destination.display()
+                );
+                let _ = tx.send(TransferStatus::Failed(msg.clone())).await;
+                return Err(anyhow!(msg));
+            }
+
+            if let Err(e) = fs::create_dir_all(&destination) {
+                let msg = format!("Failed to create destination directory: {}", e);
+                let _ = tx.send(TransferStatus::Failed(msg.clone())).await;
+                return Err(anyhow!(msg));
+            }
+
+            let span = info_span!(
+                "archive_transfer",
+                source = %source.display(),
+                destination = %destination.display()
+            );
+
+            async {
+                info!(compression_level, "Starting archive transfer");
+                let start_time = Stopwatch::start();
+
+                let scan_result = match scan_directory(&source, &req.filter).await {
+                    Ok(result) => result,
+                    Err(e) => {
+                        let msg = format!("Failed to scan source directory: {}", e);
+                        let _ = tx.send(TransferStatus::Failed(msg.clone())).await;
+                        return Err(anyhow!(msg));
+                    }
+                };
+
+                info!(
+                    total_files = scan_result.files.len(),
+                    total_bytes = scan_result.total_bytes,
+                    "Scan complete"
+                );
+
+                let owner_ids = match &req.owner {
+                    Some(o) => match resolve_owner(o) {
+                        Ok(ids) => Some(ids),
+                        Err(e) => {
+                            warn!(error = %e, "Failed to resolve owner, archive will be owned by process user");
+                            None
+                        }
+                    },
+                    None => None,
+                };
+
+                let archive_path = destination.join(ARCHIVE_NAME);
+                let total_bytes = scan_result.total_bytes;
+                let unreadable = scan_result.unreadable;
+                let files = scan_result.files;
+
+                let write_result = {
+                    let source = source.clone();
+                    let archive_path = archive_path.clone();
+                    let tx = tx.clone();
+                    let cancel = req.cancel.clone();
+                    let pause = req.pause.clone();
+                    tokio::task::spawn_blocking(move || {
+                        let options = ArchiveOptions {
+                            compression_level,
+                            owner_ids,
+                            throttle: max_bytes_per_sec.map(Throttle::new),
+                            progress_interval,
+                            cancel,
+                            pause,
+                        };
+                        write_archive(
+                            &source,
+                            &archive_path,
+                            &files,
+                            total_bytes,
+                            &options,
+                            start_time,
+                            &tx,
+                        )
+                    })
+                    .await
+                    .map_err(|e| anyhow!(e))
+                    .and_then(|r| r)
+                };
+
+                let file_hashes: Vec<FileHash> = match write_result {
+                    Ok(hashes) => hashes,
+                    Err(e) => {
+                        let msg = format!("Transfer failed: {}", e);
+                        let _ = tx.send(TransferStatus::Failed(msg.clone())).await;
+                        return Err(anyhow!(msg));
+                    }
+                };
+                let file_count = file_hashes.len();
+                let largest_file = LargestFile::of(
+                    file_hashes
+                        .iter()
+                        .map(|fh| (fh.relative_path.as_path(), fh.size)),
+                );
+
+                if verify {
+                    let archive_path = archive_path.clone();
+                    let progress = ProgressSender {
+                        tx: tx.clone(),
+                        interval: progress_interval,
+                    };
+                    let verify_result = tokio::task::spawn_blocking(move || {
+                        verify_archive(&archive_path, &file_hashes, progress)
+                    })
+                    .await
+                    .map_err(|e| anyhow!(e))
+                    .and_then(|r| r);
+
+                    if let Err(e) = verify_result {
+                        let msg = e.to_string();
+                        let _ = tx.send(TransferStatus::Failed(msg.clone())).await;
+                        return Err(anyhow!(msg));
+                    }
+                }
+
+                let duration_secs = start_time.elapsed().as_secs();
+                let archive_bytes = fs::metadata(&archive_path).map(|m| m.len()).unwrap_or(0);
+
+                info!(
+                    total_bytes,
+                    archive_bytes,
+                    duration_secs,
+                    "Archive transfer complete"
+                );
+
+                Ok(TransferResult {
+                    total_bytes,
+                    duration_secs,
+                    files_copied: file_count,
+                    files_failed: unreadable,
+                    largest_file,
+                    // Already verified against the embedded index
+                    file_hashes: None,
+                    skipped: Vec::new(),
+                    linked: 0,
+                    retried: Vec::new(),
+                    rescued: Vec::new(),
+                    renamed: Vec::new(),
+                    relocated: Vec::new(),
+                })
+            }
+            .instrument(span)
+            .await
+        })
+    }
+}
+
+/// Options for writing the archive
+struct ArchiveOptions {
+    compression_level: i32,
+    /// Owner UID/GID applied to the archive file
+    owner_ids: Option<OwnerIds>,
+    throttle: Option<Throttle>,
+    progress_interval: ProgressInterval,
+    cancel: CancellationToken,
+    pause: PauseToken,
+}
+
+/// Passes reads through while hashing them and reporting how many bytes
+/// were read. Fails once `cancel` fires.
+struct HashingReader<'a, R> {
+    inner: R,
+    hasher: blake3::Hasher,
+    on_read: &'a mut dyn FnMut(u64),
+    cancel: &'a CancellationToken,
+}
+
+impl<R: Read> Read for HashingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.cancel.is_cancelled() {
+            return Err(io::Error::other("Transfer cancelled"));
+        }
+        let bytes_read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..bytes_read]);
+        (self.on_read)(bytes_read as u64);
+        Ok(bytes_read)
+    }
+}
+
+/// Write every scanned file and then the checksum index into a new archive.
+/// Returns the hash of each archived file.
+fn write_archive(
+    source: &Path,
+    archive_path: &Path,
+    files: &[FileInfo],
+    total_bytes: u64,
+    options: &ArchiveOptions,
+    start_time: Stopwatch,
+    tx: &mpsc::Sender<TransferStatus>,
+) -> Result<Vec<FileHash>> {
+    let archive_file = File::create(archive_path)
+        .map_err(|e| anyhow!("Failed to create {}: {}", archive_path.display(), e))?;
+    let encoder = zstd::Encoder::new(
+        BufWriter::with_capacity(BUFFER_SIZE, archive_file),
+        options.compression_level,
+    )?;
+    let mut builder = tar::Builder::new(encoder);
+
+    let mut bytes_copied: u64 = 0;
+    let mut gate = ProgressGate::new(options.progress_interval);
+    let mut speed = SpeedMeter::new(0);
+    let mut file_hashes = Vec::with_capacity(files.len());
+
+    for file_info in files {
+        options.pause.blocking_wait(tx, &options.cancel);
+        if options.cancel.is_cancelled() {
+            bail!("Transfer cancelled");
+        }
+        let relative = file_info
+            .path
+            .strip_prefix(source)
+            .expect("file should be under source");
+        let current_file = relative.to_string_lossy().to_string();
+
+        debug!(file = %current_file, size = file_info.size, "Archiving file");
+
+        let device_error = |e: io::Error, action: &str| {
+            if is_device_removed_error(&e) {
+                anyhow!("Device removed during transfer at file: {}", current_file)
+            } else {
+                anyhow!("Failed to {} {}: {}", action, current_file, e)
+            }
+        };
+
+        let metadata = fs::metadata(&file_info.path).map_err(|e| device_error(e, "read"))?;
+        let source_file = File::open(&file_info.path).map_err(|e| device_error(e, "open"))?;
+
+        let mut header = tar::Header::new_gnu();
+        header.set_metadata_in_mode(&metadata, tar::HeaderMode::Complete);
+        let size = metadata.len();
+
+        let mut on_read = |bytes_read: u64| {
+            if let Some(throttle) = &options.throttle {
+                throttle.consume(bytes_read);
+            }
+            bytes_copied += bytes_read;
+
+            if gate.due(bytes_copied) || bytes_copied == total_bytes {
+                let percentage = if total_bytes > 0 {
+                    ((bytes_copied as f64 / total_bytes as f64) * 100.0) as u8
+                } else {
+                    100
+                };
+                let _ = tx.blocking_send(TransferStatus::InProgress {
+                    total_bytes,
+                    bytes_copied,
+                    current_file: current_file.clone(),
+                    percentage,
+                    eta_seconds: calculate_eta(start_time, bytes_copied, total_bytes),
+                    bytes_per_sec: speed.update(bytes_copied),
+                });
+            }
+        };
+
+        // The header's size is fixed before the data is read, so never
+        // take more than it promises
+        let mut reader = HashingReader {
+            inner: BufReader::with_capacity(BUFFER_SIZE, source_file).take(size),
+            hasher: blake3::Hasher::new(),
+            on_read: &mut on_read,
+            cancel: &options.cancel,
+        };
+        builder
+            .append_data(&mut header, relative, &mut reader)
+            .map_err(|e| device_error(e, "archive"))?;
+
+        // tar pads a short read with zeros, which would archive the wrong data
+        let HashingReader { inner, hasher, .. } = reader;
+        if inner.limit() > 0 {
+            bail!("{} changed size while being archived", current_file);
+        }
+
+        file_hashes.push(FileHash {
+            relative_path: relative.to_path_buf(),
+            hash: *hasher.finalize().as_bytes(),
+            size,
+        });
+    }
+
+    let index = checksum_index(&file_hashes);
+    let mut header = tar::Header::new_gnu();
+    header.set_size(index.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    );
+    builder.append_data(&mut header, CHECKSUM_INDEX, index.as_bytes())?;
+
+    let archive_file = builder
+        .into_inner()?
+        .finish()?
+        .into_inner()
+        .map_err(|e| anyhow!("Failed to flush archive: {}", e.error()))?;
+    archive_file.sync_all()?;
+
+    if let Some(ids) = &options.owner_ids
+        && let Err(e) = chown(archive_path, Some(ids.uid), Some(ids.gid))
+    {
+        debug!(
+            dest = %archive_path.display(),
+            error = %e,
+            "Failed to set archive ownership"
+        );
+    }
+
+    Ok(file_hashes)
+}
+
+/// b3sum-style index: one `<hex hash>  <relative path>` line per file.
+fn checksum_index(file_hashes: &[FileHash]) -> String {
+    file_hashes
+        .iter()
+        .map(|fh| {
+            format!(
+                "{}  {}\n",
+                blake3::Hash::from(fh.hash).to_hex(),
+                fh.relative_path.display()
+            )
+        })
+        .collect()
+}
+
+fn parse_checksum_index(index: &str) -> HashMap<PathBuf, blake3::Hash> {
+    index
+        .lines()
+        .filter_map(|line| {
+            let (hash, path) = line.split_once("  ")?;
+            Some((PathBuf::from(path), blake3::Hash::from_hex(hash).ok()?))
+        })
+        .collect()
+}
+
+/// Read the archive back, re-hashing every entry, and check both the
+/// entries and the embedded index against the hashes taken while writing.
+fn verify_archive(
+    archive_path: &Path,
+    expected: &[FileHash],
+    progress: ProgressSender,
+) -> Result<()> {
+    let archive_file = File::open(archive_path)
+        .map_err(|e| anyhow!("Failed to open {}: {}", archive_path.display(), e))?;
+    let mut archive = tar::Archive::new(zstd::Decoder::new(archive_file)?);
+    let total = expected.len() as u64;
+    let total_bytes = expected.iter().map(|fh| fh.size).sum();
+    let mut progress = VerifyProgress::new(progress, total, total_bytes);
+    let mut bytes_read = 0;
+
+    let mut actual: HashMap<PathBuf, (blake3::Hash, u64)> = HashMap::new();
+    let mut index = None;
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+
+        if path == Path::new(CHECKSUM_INDEX) {
+            let mut text = String::new();
+            entry.read_to_string(&mut text)?;
+            index = Some(parse_checksum_index(&text));
+            continue;
+        }
+
+        progress.file_started(actual.len() as u64, bytes_read);
+        let mut hasher = blake3::Hasher::new();
+        let mut buffer = vec![0u8; BUFFER_SIZE];
+        let mut size = 0;
+        loop {
+            let read = entry.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            size += read as u64;
+            progress.read(read as u64);
+        }
+        bytes_read += size;
+        actual.insert(path, (hasher.finalize(), size));
+    }
+    progress.finish();
+
+    let Some(index) = index else {
+        bail!("Verification failed: archive has no {}", CHECKSUM_INDEX);
+    };
+
+    let mismatches: Vec<&Path> = expected
+        .iter()
+        .filter(|fh| {
+            let hash = blake3::Hash::from(fh.hash);
+            index.get(&fh.relative_path) != Some(&hash)
+                || actual.get(&fh.relative_path) != Some(&(hash, fh.size))
+        })
+        .map(|fh| fh.relative_path.as_path())
+        .collect();
+
+    if !mismatches.is_empty() {
+        let mut msg = format!(
+            "Verification failed: {} file(s) did not match",
+            mismatches.len()
+        );
+        for path in mismatches.iter().take(10) {
+            msg.push_str(&format!("\n  - {}", path.display()));
+        }
+        bail!(msg);
+    }
+
+    info!(
+        files_verified = total,
+        "Archive verified against checksum index"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::filter::PathFilter;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_archive_engine_round_trip() {
+        let temp = tempdir().unwrap();
+        let source = temp.path().join("source");
+        let dest = temp.path().join("dest");
+
+        fs::create_dir_all(source.join("DCIM/100CANON")).unwrap();
+        fs::write(
+            source.join("DCIM/100CANON/IMG_0001.CR3"),
+            vec![7u8; 300_000],
+        )
+        .unwrap();
+        fs::write(source.join("notes.txt"), b"hello world").unwrap();
+
+        let engine = ArchiveEngine::default();
+        let (tx, mut rx) = mpsc::channel(100);
+        let req = TransferRequest {
+            job_id: "test-job".to_string(),
+            source: source.clone(),
+            device: None,
+            destination: dest.clone(),
+            owner: None,
+            completed: Vec::new(),
+            file_log: None,
+            filter: PathFilter::default(),
+            backed_up: Vec::new(),
+            link_dest: None,
+            cancel: CancellationToken::new(),
+            pause: PauseToken::default(),
+        };
+
+        let handle = tokio::spawn(async move { engine.transfer(&req, tx).await });
+        let mut updates = Vec::new();
+        while let Some(status) = rx.recv().await {
+            updates.push(status);
+        }
+
+        let result = handle.await.unwrap().unwrap();
+        assert_eq!(result.total_bytes, 300_000 + 11);
+        assert!(result.file_hashes.is_none());
+        assert!(
+            updates
+                .iter()
+                .any(|s| matches!(s, TransferStatus::Verifying { .. }))
+        );
+
+        // Extract and check contents and the index
+        let archive_file = File::open(dest.join(ARCHIVE_NAME)).unwrap();
+        let mut archive = tar::Archive::new(zstd::Decoder::new(archive_file).unwrap());
+        let extracted = temp.path().join("extracted");
+        archive.unpack(&extracted).unwrap();
+
+        assert_eq!(
+            fs::read(extracted.join("DCIM/100CANON/IMG_0001.CR3")).unwrap(),
+            vec![7u8; 300_000]
+        );
+        assert_eq!(
+            fs::read_to_string(extracted.join("notes.txt")).unwrap(),
+            "hello world"
+        );
+
+        let index =
+            parse_checksum_index(&fs::read_to_string(extracted.join(CHECKSUM_INDEX)).unwrap());
+        assert_eq!(index.len(), 2);
+        assert_eq!(index[Path::new("notes.txt")], blake3::hash(b"hello world"));
+    }
+}
@@ -0,0 +1,155 @@
+//! Extended attribute copying for the native copy engine.
+//!
+//! POSIX ACLs are stored in the `system.posix_acl_access` and
+//! `system.posix_acl_default` attributes, so copying every attribute carries
+//! ACLs across as well, the same as `rsync -X -A`. Symlinks are never
+//! followed (the `l*xattr` calls on Linux, `XATTR_NOFOLLOW` on macOS).
+
+use std::ffi::{CStr, CString};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+fn c_path(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contains a NUL byte"))
+}
+
+#[cfg(target_os = "linux")]
+unsafe fn list_raw(path: *const libc::c_char, buf: *mut libc::c_char, len: usize) -> isize {
+    unsafe { libc::llistxattr(path, buf, len) }
+}
+
+#[cfg(target_os = "macos")]
+unsafe fn list_raw(path: *const libc::c_char, buf: *mut libc::c_char, len: usize) -> isize {
+    unsafe { libc::listxattr(path, buf, len, libc::XATTR_NOFOLLOW) }
+}
+
+#[cfg(target_os = "linux")]
+unsafe fn get_raw(
+    path: *const libc::c_char,
+    name: *const libc::c_char,
+    buf: *mut libc::c_void,
+    len: usize,
+) -> isize {
+    unsafe { libc::lgetxattr(path, name, buf, len) }
+}
+
+#[cfg(target_os = "macos")]
+unsafe fn get_raw(
+    path: *const libc::c_char,
+    name: *const libc::c_char,
+    buf: *mut libc::c_void,
+    len: usize,
+) -> isize {
+    unsafe { libc::getxattr(path, name, buf, len, 0, libc::XATTR_NOFOLLOW) }
+}
+
+#[cfg(target_os = "linux")]
+unsafe fn set_raw(
+    path: *const libc::c_char,
+    name: *const libc::c_char,
+    value: *const libc::c_void,
+    len: usize,
+) -> libc::c_int {
+    unsafe { libc::lsetxattr(path, name, value, len, 0) }
+}
+
+#[cfg(target_os = "macos")]
+unsafe fn set_raw(
+    path: *const libc::c_char,
+    name: *const libc::c_char,
+    value: *const libc::c_void,
+    len: usize,
+) -> libc::c_int {
+    unsafe { libc::setxattr(path, name, value, len, 0, libc::XATTR_NOFOLLOW) }
+}
+
+fn is_unsupported(e: &io::Error) -> bool {
+    e.raw_os_error() == Some(libc::ENOTSUP)
+}
+
+/// Read a variable-length value with the usual "ask for the size, then
+/// fetch" xattr protocol, retrying if it grew in between.
+fn read_sized(mut call: impl FnMut(*mut libc::c_char, usize) -> isize) -> io::Result<Vec<u8>> {
+    loop {
+        let size = call(std::ptr::null_mut(), 0);
+        if size < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut buf = vec![0u8; size as usize];
+        if buf.is_empty() {
+            return Ok(buf);
+        }
+        let size = call(buf.as_mut_ptr().cast(), buf.len());
+        if size < 0 {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() == Some(libc::ERANGE) {
+                continue;
+            }
+            return Err(err);
+        }
+        buf.truncate(size as usize);
+        return Ok(buf);
+    }
+}
+
+/// Names of the extended attributes set on `path`. A filesystem without
+/// xattr support (FAT, exFAT) has none.
+pub(super) fn list(path: &Path) -> io::Result<Vec<CString>> {
+    let c_path = c_path(path)?;
+    let names = match read_sized(|buf, len| unsafe { list_raw(c_path.as_ptr(), buf, len) }) {
+        Ok(names) => names,
+        Err(e) if is_unsupported(&e) => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(names
+        .split(|b| *b == 0)
+        .filter(|name| !name.is_empty())
+        .filter_map(|name| CString::new(name).ok())
+        .collect())
+}
+
+/// Value of one extended attribute.
+pub(super) fn get(path: &Path, name: &CStr) -> io::Result<Vec<u8>> {
+    let c_path = c_path(path)?;
+    read_sized(|buf, len| unsafe { get_raw(c_path.as_ptr(), name.as_ptr(), buf.cast(), len) })
+}
+
+/// Set one extended attribute, replacing any existing value.
+pub(super) fn set(path: &Path, name: &CStr, value: &[u8]) -> io::Result<()> {
+    let c_path = c_path(path)?;
+    let result = unsafe {
+        set_raw(
+            c_path.as_ptr(),
+            name.as_ptr(),
+            value.as_ptr().cast(),
+            value.len(),
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Copy every extended attribute (and with them any ACLs) from `source` to
+/// `dest`. An attribute the destination refuses, e.g. `trusted.*` without
+/// privilege or any attribute on a filesystem without xattr support, doesn't
+/// stop the rest; the first such error is returned once all were tried.
+pub(super) fn copy_all(source: &Path, dest: &Path) -> io::Result<()> {
+    let mut first_error = None;
+    for name in list(source)? {
+        let result = get(source, &name).and_then(|value| set(dest, &name, &value));
+        if let Err(e) = result {
+            first_error.get_or_insert(io::Error::new(
+                e.kind(),
+                format!("{}: {}", name.to_string_lossy(), e),
+            ));
+        }
+    }
+    match first_error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,59 @@
+//! Unicode normalization of file names.
+//!
+//! The same accented name can be spelled two ways: precomposed (NFC, as
+//! Windows and Linux tools write it) or decomposed (NFD, as macOS writes it
+//! to exFAT cards). Byte-wise they differ, so an NFD name on a card doesn't
+//! match the NFC name in a filter pattern or a manifest made elsewhere.
+//! Comparisons here treat both spellings as the same name, and native copy
+//! can rewrite names into one form (`unicode_normalization`).
+
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+use unicode_normalization::UnicodeNormalization as _;
+
+use crate::config::UnicodeNormalization;
+
+/// `name` in the form `mode` asks for. Names that aren't valid UTF-8 are
+/// left as they are.
+pub fn normalize_name(mode: UnicodeNormalization, name: &OsStr) -> OsString {
+    match (mode, name.to_str()) {
+        (UnicodeNormalization::Nfc, Some(text)) => text.nfc().collect::<String>().into(),
+        (UnicodeNormalization::Nfd, Some(text)) => text.nfd().collect::<String>().into(),
+        _ => name.to_os_string(),
+    }
+}
+
+/// `text` in NFC.
+pub fn nfc(text: &str) -> String {
+    text.nfc().collect()
+}
+
+/// `path` with every component in NFC, for comparing names regardless of
+/// how they were spelled.
+pub fn nfc_path(path: &Path) -> PathBuf {
+    if path.to_str().is_some_and(|text| text.is_ascii()) {
+        return path.to_path_buf();
+    }
+    path.components()
+        .map(|c| normalize_name(UnicodeNormalization::Nfc, c.as_os_str()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forms() {
+        let nfd = OsStr::new("Cafe\u{301}.JPG");
+        let nfc = OsStr::new("Caf\u{e9}.JPG");
+        assert_eq!(normalize_name(UnicodeNormalization::Nfc, nfd), nfc);
+        assert_eq!(normalize_name(UnicodeNormalization::Nfd, nfc), nfd);
+        assert_eq!(normalize_name(UnicodeNormalization::Off, nfd), nfd);
+        assert_eq!(
+            nfc_path(Path::new("DCIM/Cafe\u{301}/Cafe\u{301}.JPG")),
+            PathBuf::from("DCIM/Caf\u{e9}/Caf\u{e9}.JPG")
+        );
+    }
+}
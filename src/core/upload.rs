@@ -0,0 +1,314 @@
+//! Manual ingests over HTTP, for remote shooters with no card to hand in.
+//!
+//! An upload session stages files under `upload_directory/<session id>`.
+//! Completing it turns the staging directory into a synthetic device
+//! (filesystem `upload`, UUID `upload-<label>`) sent to the orchestrator
+//! like a card insertion, so the upload is copied, verified, catalogued and
+//! notified through the normal pipeline. The staging copy is removed once
+//! the job completes, and kept if it fails.
+//!
+//! A session can carry the sender's manifest (see `manifest`), kept beside
+//! the staging directory; the job checks the upload against it before
+//! copying and fails on any mismatch.
+
+pub mod manifest;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, mpsc};
+use tracing::{info, warn};
+
+use super::hardware::{BlockDevice, HardwareEvent, UPLOAD_FILESYSTEM};
+use super::restore::check_relative;
+
+/// Longest label kept from the client
+const MAX_LABEL_LEN: usize = 64;
+
+/// An upload that is still receiving files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadSession {
+    pub id: String,
+    /// Label the job is filed under, as a card's volume label would be
+    pub label: String,
+    pub directory: PathBuf,
+    pub files: u64,
+    pub bytes: u64,
+    /// Files listed in the sender's manifest, once one is uploaded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest_files: Option<usize>,
+}
+
+/// Open upload sessions, shared between the web server and the
+/// orchestrator through `AppContext`.
+#[derive(Clone)]
+pub struct UploadSessions {
+    root: PathBuf,
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    sessions: HashMap<String, UploadSession>,
+    /// The orchestrator's device event channel, once it is running
+    events: Option<mpsc::Sender<HardwareEvent>>,
+}
+
+impl UploadSessions {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            inner: Arc::default(),
+        }
+    }
+
+    /// Send completed uploads to the orchestrator on `events`.
+    pub async fn attach(&self, events: mpsc::Sender<HardwareEvent>) {
+        self.inner.lock().await.events = Some(events);
+    }
+
+    /// Open a session filed under `label`, creating its staging directory.
+    pub async fn create(&self, label: &str) -> Result<UploadSession> {
+        let label = sanitize_label(label).ok_or_else(|| anyhow!("Upload label is empty"))?;
+        let id = uuid::Uuid::now_v7().to_string();
+        let directory = self.root.join(&id);
+        tokio::fs::create_dir_all(&directory).await?;
+
+        let session = UploadSession {
+            id: id.clone(),
+            label,
+            directory,
+            files: 0,
+            bytes: 0,
+            manifest_files: None,
+        };
+        info!(upload_id = %id, label = %session.label, "Upload session opened");
+        self.inner.lock().await.sessions.insert(id, session.clone());
+        Ok(session)
+    }
+
+    /// Where to write `relative` for session `id`, with its parent
+    /// directories created. Existing files are never overwritten.
+    pub async fn file_path(&self, id: &str, relative: &Path) -> Result<PathBuf> {
+        check_relative(relative)?;
+        if relative.file_name().is_none() {
+            bail!("Upload path names no file");
+        }
+        let directory = self.get(id).await?.directory;
+        let path = directory.join(relative);
+        if tokio::fs::symlink_metadata(&path).await.is_ok() {
+            bail!("{} was already uploaded", relative.display());
+        }
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        Ok(path)
+    }
+
+    /// Count a file written to session `id`.
+    pub async fn record(&self, id: &str, bytes: u64) {
+        if let Some(session) = self.inner.lock().await.sessions.get_mut(id) {
+            session.files += 1;
+            session.bytes += bytes;
+        }
+    }
+
+    /// Store the sender's manifest for session `id`, replacing any earlier
+    /// one. Returns the number of files it lists.
+    pub async fn set_manifest(&self, id: &str, text: &str) -> Result<usize> {
+        let entries = manifest::parse(text)?;
+        let directory = self.get(id).await?.directory;
+        tokio::fs::write(manifest_path(&directory), text).await?;
+        if let Some(session) = self.inner.lock().await.sessions.get_mut(id) {
+            session.manifest_files = Some(entries.len());
+        }
+        info!(upload_id = %id, files = entries.len(), "Upload manifest received");
+        Ok(entries.len())
+    }
+
+    pub async fn get(&self, id: &str) -> Result<UploadSession> {
+        self.inner
+            .lock()
+            .await
+            .sessions
+            .get(id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Upload session {} not found", id))
+    }
+
+    pub async fn list(&self) -> Vec<UploadSession> {
+        self.inner.lock().await.sessions.values().cloned().collect()
+    }
+
+    /// Close session `id` and hand its files to the orchestrator as a
+    /// newly inserted device.
+    pub async fn complete(&self, id: &str) -> Result<UploadSession> {
+        let mut inner = self.inner.lock().await;
+        let events = inner
+            .events
+            .clone()
+            .ok_or_else(|| anyhow!("The daemon is not accepting devices yet"))?;
+        let session = inner
+            .sessions
+            .get(id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Upload session {} not found", id))?;
+        if session.files == 0 {
+            bail!("Upload session {} has no files", id);
+        }
+
+        events
+            .send(HardwareEvent::DeviceAdded(session.device()))
+            .await
+            .map_err(|_| anyhow!("The daemon is shutting down"))?;
+        inner.sessions.remove(id);
+        info!(
+            upload_id = %id,
+            files = session.files,
+            bytes = session.bytes,
+            "Upload complete, starting job"
+        );
+        Ok(session)
+    }
+
+    /// Drop session `id` and everything uploaded to it.
+    pub async fn abort(&self, id: &str) -> Result<()> {
+        let session = self
+            .inner
+            .lock()
+            .await
+            .sessions
+            .remove(id)
+            .ok_or_else(|| anyhow!("Upload session {} not found", id))?;
+        tokio::fs::remove_dir_all(&session.directory).await?;
+        let _ = tokio::fs::remove_file(manifest_path(&session.directory)).await;
+        info!(upload_id = %id, "Upload session aborted");
+        Ok(())
+    }
+}
+
+impl UploadSession {
+    /// The synthetic device the orchestrator ingests this upload from.
+    fn device(&self) -> BlockDevice {
+        BlockDevice {
+            uuid: format!("upload-{}", self.label),
+            label: self.label.clone(),
+            path: self.directory.clone(),
+            mount_point: self.directory.clone(),
+            capacity: self.bytes,
+            filesystem: UPLOAD_FILESYSTEM.to_string(),
+        }
+    }
+}
+
+/// The sender's manifest for an upload staged in `directory`.
+fn manifest_path(directory: &Path) -> PathBuf {
+    let mut path = directory.as_os_str().to_owned();
+    path.push(".manifest");
+    PathBuf::from(path)
+}
+
+/// Check an upload device's files against the manifest sent with them.
+/// None if the sender didn't provide one.
+pub async fn check_manifest(device: &BlockDevice) -> Option<Result<manifest::ManifestCheck>> {
+    let text = tokio::fs::read_to_string(manifest_path(&device.mount_point))
+        .await
+        .ok()?;
+    let root = device.mount_point.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let entries = manifest::parse(&text)?;
+        manifest::check(&root, &entries)
+    })
+    .await
+    .map_err(anyhow::Error::from)
+    .and_then(|result| result);
+    Some(result)
+}
+
+/// Remove an upload's staging directory (and manifest) once its job has
+/// completed.
+pub async fn remove_staging(device: &BlockDevice) {
+    let _ = tokio::fs::remove_file(manifest_path(&device.mount_point)).await;
+    match tokio::fs::remove_dir_all(&device.mount_point).await {
+        Ok(()) => info!(staging = %device.mount_point.display(), "Removed upload staging copy"),
+        Err(e) => warn!(
+            staging = %device.mount_point.display(),
+            error = %e,
+            "Failed to remove upload staging copy"
+        ),
+    }
+}
+
+/// Keep a label usable as a path component and a device rule key.
+pub fn sanitize_label(label: &str) -> Option<String> {
+    let label: String = label
+        .trim()
+        .chars()
+        .take(MAX_LABEL_LEN)
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let label = label.trim_matches('.').to_string();
+    (!label.is_empty()).then_some(label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_upload_session_becomes_device() {
+        let root = std::env::temp_dir().join(format!("bksd_upload_{}", std::process::id()));
+        let sessions = UploadSessions::new(root.clone());
+        let (tx, mut rx) = mpsc::channel(1);
+
+        let session = sessions.create("Jo's A7 IV").await.unwrap();
+        assert_eq!(session.label, "Jo_s_A7_IV");
+        assert!(sessions.complete(&session.id).await.is_err());
+
+        let path = sessions
+            .file_path(&session.id, Path::new("DCIM/C0001.MP4"))
+            .await
+            .unwrap();
+        tokio::fs::write(&path, b"clip").await.unwrap();
+        sessions.record(&session.id, 4).await;
+        assert!(
+            sessions
+                .file_path(&session.id, Path::new("DCIM/C0001.MP4"))
+                .await
+                .is_err()
+        );
+        assert!(
+            sessions
+                .file_path(&session.id, Path::new("../escape"))
+                .await
+                .is_err()
+        );
+
+        // Nothing to send to until the orchestrator attaches
+        assert!(sessions.complete(&session.id).await.is_err());
+        sessions.attach(tx).await;
+        sessions.complete(&session.id).await.unwrap();
+        assert!(sessions.list().await.is_empty());
+
+        let Some(HardwareEvent::DeviceAdded(device)) = rx.recv().await else {
+            panic!("expected a device");
+        };
+        assert_eq!(device.uuid, "upload-Jo_s_A7_IV");
+        assert_eq!(device.mount_point, session.directory);
+        assert_eq!(device.capacity, 4);
+        assert!(device.is_upload());
+
+        remove_staging(&device).await;
+        assert!(!session.directory.exists());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,330 @@
+//! Client manifests for uploads: the hashes a sender took of their files
+//! before pushing them, checked against what arrived.
+//!
+//! Two formats are read: `b3sum` style lines (`<blake3 hex>  <path>`, as in
+//! the archive engine's `BKSD-CHECKSUMS.b3`), and MHL XML as written by
+//! on-set offload tools. MHL entries are checked when they carry a SHA-1;
+//! entries with only hashes bksd doesn't compute (MD5, xxHash, C4) are
+//! reported as unverifiable rather than trusted. Paths are matched in NFC,
+//! so a manifest written on a Mac matches files named on Windows or Linux.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, bail};
+use regex::Regex;
+use sha1::{Digest, Sha1};
+
+use crate::core::JobNotice;
+use crate::core::restore::{check_relative, walk};
+use crate::core::unicode::nfc_path;
+
+/// Files listed as mismatched in a failed job's description
+const MISMATCHES_SHOWN: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub relative_path: PathBuf,
+    /// `blake3`, `sha1`, or the MHL element name of a hash bksd can't check
+    pub algorithm: String,
+    /// Lowercase hex
+    pub hash: String,
+}
+
+/// Outcome of checking an upload against its manifest.
+#[derive(Debug, Default)]
+pub struct ManifestCheck {
+    /// One per mismatched or missing file, plus summaries
+    pub notices: Vec<JobNotice>,
+    pub verified: usize,
+    pub mismatched: Vec<String>,
+}
+
+impl ManifestCheck {
+    /// Why the job fails, if any file didn't match.
+    pub fn failure(&self) -> Option<String> {
+        if self.mismatched.is_empty() {
+            return None;
+        }
+        let mut shown = self.mismatched[..self.mismatched.len().min(MISMATCHES_SHOWN)].join(", ");
+        if self.mismatched.len() > MISMATCHES_SHOWN {
+            shown.push_str(", ...");
+        }
+        Some(format!(
+            "{} uploaded file(s) don't match the sender's manifest: {}",
+            self.mismatched.len(),
+            shown
+        ))
+    }
+}
+
+/// Parse a manifest in either format.
+pub fn parse(text: &str) -> Result<Vec<ManifestEntry>> {
+    let entries = if text.trim_start().starts_with('<') {
+        parse_mhl(text)
+    } else {
+        parse_b3sum(text)?
+    };
+    if entries.is_empty() {
+        bail!("Manifest lists no files");
+    }
+    for entry in &entries {
+        check_relative(&entry.relative_path)?;
+    }
+    Ok(entries)
+}
+
+fn parse_b3sum(text: &str) -> Result<Vec<ManifestEntry>> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let Some((hash, path)) = line.split_once("  ").or_else(|| line.split_once(" *")) else {
+                bail!("Not a BLAKE3 manifest line: {}", line);
+            };
+            if blake3::Hash::from_hex(hash).is_err() {
+                bail!("Not a BLAKE3 hash: {}", hash);
+            }
+            Ok(ManifestEntry {
+                relative_path: PathBuf::from(path),
+                algorithm: "blake3".to_string(),
+                hash: hash.to_ascii_lowercase(),
+            })
+        })
+        .collect()
+}
+
+/// MHL v1 (`<hash><file>`) and ASC MHL v2 (`<hash><path>`) entries. Of
+/// several hashes for one file, a checkable one is preferred.
+fn parse_mhl(text: &str) -> Vec<ManifestEntry> {
+    let block = Regex::new(r"(?s)<hash\b[^>]*>(.*?)</hash>").unwrap();
+    let path = Regex::new(r"<(?:file|path)\b[^>]*>([^<]*)</(?:file|path)>").unwrap();
+    let hash = Regex::new(r"<(\w+)\b[^>]*>\s*([0-9A-Za-z+/=]+)\s*</(\w+)>").unwrap();
+
+    block
+        .captures_iter(text)
+        .filter_map(|block| {
+            let body = &block[1];
+            let relative_path = PathBuf::from(unescape(path.captures(body)?[1].trim()));
+            let hashes: Vec<(String, String)> = hash
+                .captures_iter(body)
+                .filter(|c| c[1] == c[3] && is_hash_element(&c[1]))
+                .map(|c| (c[1].to_ascii_lowercase(), c[2].to_ascii_lowercase()))
+                .collect();
+            let (algorithm, hash) = hashes
+                .iter()
+                .find(|(algorithm, _)| algorithm == "sha1")
+                .or(hashes.first())?
+                .clone();
+            Some(ManifestEntry {
+                relative_path,
+                algorithm,
+                hash,
+            })
+        })
+        .collect()
+}
+
+fn is_hash_element(name: &str) -> bool {
+    matches!(
+        name.to_ascii_lowercase().as_str(),
+        "md5" | "sha1" | "c4" | "xxhash" | "xxhash64" | "xxhash64be" | "xxh64" | "xxh3" | "xxh128"
+    )
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Check the files under `root` against `entries`. Blocking: hashes every
+/// listed file.
+pub fn check(root: &Path, entries: &[ManifestEntry]) -> Result<ManifestCheck> {
+    let mut result = ManifestCheck::default();
+    let mut unverifiable = HashSet::new();
+    let received: HashMap<PathBuf, PathBuf> = walk(root, &[])?
+        .into_iter()
+        .map(|path| (nfc_path(&path), path))
+        .collect();
+
+    for entry in entries {
+        let received_path = received.get(&nfc_path(&entry.relative_path));
+        let path = root.join(received_path.unwrap_or(&entry.relative_path));
+        let display = entry.relative_path.to_string_lossy().to_string();
+        let actual = match entry.algorithm.as_str() {
+            "blake3" | "sha1" if received_path.is_none() => None,
+            "blake3" => Some(hash_file(
+                &path,
+                blake3::Hasher::new(),
+                |h, data| {
+                    h.update(data);
+                },
+                |h| h.finalize().to_hex().to_string(),
+            )?),
+            "sha1" => Some(hash_file(
+                &path,
+                Sha1::new(),
+                |h, data| {
+                    h.update(data);
+                },
+                |h| format!("{:x}", h.finalize()),
+            )?),
+            other => {
+                unverifiable.insert(other.to_string());
+                continue;
+            }
+        };
+
+        match actual {
+            Some(actual) if actual == entry.hash => result.verified += 1,
+            Some(actual) => {
+                result.notices.push(JobNotice {
+                    kind: "manifest_mismatch".to_string(),
+                    path: Some(display.clone()),
+                    message: format!(
+                        "{} {} expected, {} received",
+                        entry.algorithm, entry.hash, actual
+                    ),
+                });
+                result.mismatched.push(display);
+            }
+            None => {
+                result.notices.push(JobNotice {
+                    kind: "manifest_mismatch".to_string(),
+                    path: Some(display.clone()),
+                    message: "Listed in the manifest but not uploaded".to_string(),
+                });
+                result.mismatched.push(display);
+            }
+        }
+    }
+
+    let listed: HashSet<PathBuf> = entries.iter().map(|e| nfc_path(&e.relative_path)).collect();
+    let unlisted = received
+        .keys()
+        .filter(|path| !listed.contains(*path))
+        .count();
+    if unlisted > 0 {
+        result.notices.push(JobNotice {
+            kind: "manifest_unlisted".to_string(),
+            path: None,
+            message: format!("{} uploaded file(s) are not in the manifest", unlisted),
+        });
+    }
+    if !unverifiable.is_empty() {
+        let mut algorithms: Vec<_> = unverifiable.into_iter().collect();
+        algorithms.sort();
+        let count = entries.len() - result.verified - result.mismatched.len();
+        result.notices.push(JobNotice {
+            kind: "manifest_unverifiable".to_string(),
+            path: None,
+            message: format!(
+                "{} file(s) only have {} hashes in the manifest, which can't be checked",
+                count,
+                algorithms.join("/")
+            ),
+        });
+    }
+    if result.mismatched.is_empty() {
+        result.notices.push(JobNotice {
+            kind: "manifest_verified".to_string(),
+            path: None,
+            message: format!("{} file(s) match the sender's manifest", result.verified),
+        });
+    }
+    Ok(result)
+}
+
+fn hash_file<H>(
+    path: &Path,
+    mut hasher: H,
+    update: impl Fn(&mut H, &[u8]),
+    finish: impl FnOnce(H) -> String,
+) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0u8; 1024 * 1024];
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        update(&mut hasher, &buffer[..n]);
+    }
+    Ok(finish(hasher))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_manifest() {
+        let root = std::env::temp_dir().join(format!("bksd_manifest_{}", std::process::id()));
+        std::fs::create_dir_all(root.join("CLIP")).unwrap();
+        std::fs::write(root.join("CLIP/A.MP4"), b"good").unwrap();
+        std::fs::write(root.join("CLIP/B.MP4"), b"flipped").unwrap();
+        std::fs::write(root.join("extra.txt"), b"?").unwrap();
+
+        let b3sum = format!(
+            "{}  CLIP/A.MP4\n{}  CLIP/B.MP4\n",
+            blake3::hash(b"good").to_hex(),
+            blake3::hash(b"original").to_hex()
+        );
+        let entries = parse(&b3sum).unwrap();
+        let result = check(&root, &entries).unwrap();
+        assert_eq!(result.verified, 1);
+        assert_eq!(result.mismatched, vec!["CLIP/B.MP4"]);
+        assert!(result.failure().unwrap().contains("CLIP/B.MP4"));
+        assert!(result.notices.iter().any(|n| n.kind == "manifest_unlisted"));
+
+        let mhl = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<hashlist version="1.1">
+  <hash>
+    <file>CLIP/A.MP4</file>
+    <size>4</size>
+    <xxhash64be>0123456789abcdef</xxhash64be>
+    <sha1>{:x}</sha1>
+  </hash>
+  <hash>
+    <file>CLIP/B.MP4</file>
+    <md5>0123456789abcdef0123456789abcdef</md5>
+  </hash>
+  <hash>
+    <file>CLIP/C&amp;D.MP4</file>
+    <sha1>0000000000000000000000000000000000000000</sha1>
+  </hash>
+</hashlist>"#,
+            Sha1::digest(b"good")
+        );
+        let entries = parse(&mhl).unwrap();
+        assert_eq!(entries[1].algorithm, "md5");
+        assert_eq!(entries[2].relative_path, PathBuf::from("CLIP/C&D.MP4"));
+        let result = check(&root, &entries).unwrap();
+        assert_eq!(result.verified, 1);
+        assert_eq!(result.mismatched, vec!["CLIP/C&D.MP4"]);
+        assert!(
+            result
+                .notices
+                .iter()
+                .any(|n| n.kind == "manifest_unverifiable")
+        );
+
+        // A decomposed name in the manifest matches the precomposed upload
+        std::fs::write(root.join("CLIP/Caf\u{e9}.MP4"), b"accent").unwrap();
+        let b3sum = format!(
+            "{}  CLIP/Cafe\u{301}.MP4\n",
+            blake3::hash(b"accent").to_hex()
+        );
+        let result = check(&root, &parse(&b3sum).unwrap()).unwrap();
+        assert_eq!(result.verified, 1);
+
+        assert!(parse("not a manifest").is_err());
+        assert!(parse(&format!("{}  ../escape", blake3::hash(b"").to_hex())).is_err());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
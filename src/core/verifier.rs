@@ -1,9 +1,12 @@
 use anyhow::{Result, bail};
+use std::collections::HashMap;
 use std::io::Read;
 use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info};
 
-use crate::core::transfer_engine::FileHash;
+use crate::core::restore::walk;
+use crate::core::transfer_engine::{FileHash, ProgressSender, RenamedPath, VerifyProgress};
 
 /// Result of a successful verification
 #[derive(Debug)]
@@ -26,6 +29,23 @@ pub enum MismatchReason {
     HashMismatch,
     /// File exists in source but not in destination
     MissingInDestination,
+    /// Re-reading the source gave a different hash than the copy did, so
+    /// the card returned bad data once (paranoid mode)
+    SourceMismatch,
+    /// The source file could no longer be read (paranoid mode)
+    SourceUnreadable,
+}
+
+impl MismatchReason {
+    /// Short description for messages and scrub reports
+    pub fn describe(&self) -> &'static str {
+        match self {
+            MismatchReason::HashMismatch => "hash mismatch",
+            MismatchReason::MissingInDestination => "missing in destination",
+            MismatchReason::SourceMismatch => "source re-read differs from the copy",
+            MismatchReason::SourceUnreadable => "source could not be re-read",
+        }
+    }
 }
 
 /// Verify destination files against pre-computed hashes from the transfer.
@@ -33,11 +53,20 @@ pub enum MismatchReason {
 /// This is the fast verification path - it only reads destination files
 /// since source files were already hashed during the copy operation.
 ///
+/// When a progress channel is given, `Verifying` statuses with the files and
+/// bytes checked so far are sent as files are read, as often as its interval
+/// allows.
+///
+/// `cancel` is checked between files; once it fires the pass stops with a
+/// "Verification cancelled" error rather than reading the rest of the card.
+///
 /// Returns Ok if all files match, Err with details if any mismatches found.
 pub async fn verify_from_hashes(
     job_id: &str,
     destination: &Path,
     file_hashes: &[FileHash],
+    progress: Option<ProgressSender>,
+    cancel: &CancellationToken,
 ) -> Result<VerifyResult> {
     let total = file_hashes.len() as u64;
 
@@ -54,79 +83,366 @@ pub async fn verify_from_hashes(
     let destination = destination.to_path_buf();
     let file_hashes = file_hashes.to_vec();
     let job_id = job_id.to_string();
+    let total_bytes = file_hashes.iter().map(|fh| fh.size).sum();
+    let cancel = cancel.clone();
 
     // Run verification in a blocking task since it's I/O heavy
     tokio::task::spawn_blocking(move || {
         let mut mismatches: Vec<FileMismatch> = Vec::new();
         let mut bytes_verified: u64 = 0;
+        let mut reporter = progress.map(|p| VerifyProgress::new(p, total, total_bytes));
+        let mut bytes_read: u64 = 0;
 
-        for fh in &file_hashes {
-            let dest_path = destination.join(&fh.relative_path);
+        for (index, fh) in file_hashes.iter().enumerate() {
+            check_cancelled(&job_id, &cancel, index as u64, total)?;
+            if let Some(reporter) = &mut reporter {
+                reporter.file_started(index as u64, bytes_read);
+            }
+            bytes_read += fh.size;
 
-            debug!(file = %fh.relative_path.display(), "Verifying file");
+            let on_read = &mut |bytes| {
+                if let Some(reporter) = &mut reporter {
+                    reporter.read(bytes);
+                }
+            };
+            match check_file(&destination, fh, on_read) {
+                Some(reason) => mismatches.push(FileMismatch {
+                    relative_path: fh.relative_path.clone(),
+                    reason,
+                }),
+                None => bytes_verified += fh.size,
+            }
+        }
+        if let Some(reporter) = &mut reporter {
+            reporter.finish();
+        }
 
-            if !dest_path.exists() {
-                mismatches.push(FileMismatch {
+        finish(&job_id, mismatches, total, bytes_verified)
+    })
+    .await?
+}
+
+/// Pick `percent` of the files (at least one) to verify: the largest half of
+/// the sample by size, which hold most of the data, then the rest spread
+/// across the card in an order keyed on the job ID. Files keep their
+/// original order.
+pub fn sample(job_id: &str, file_hashes: &[FileHash], percent: u8) -> Vec<FileHash> {
+    let percent = percent.clamp(1, 100) as usize;
+    let count = (file_hashes.len() * percent).div_ceil(100);
+    if count >= file_hashes.len() {
+        return file_hashes.to_vec();
+    }
+
+    let mut by_size: Vec<usize> = (0..file_hashes.len()).collect();
+    by_size.sort_by_key(|&i| std::cmp::Reverse(file_hashes[i].size));
+    let largest = count.div_ceil(2);
+    let (chosen, rest) = by_size.split_at_mut(largest);
+    rest.sort_by_cached_key(|&i| {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(job_id.as_bytes());
+        hasher.update(file_hashes[i].relative_path.as_os_str().as_encoded_bytes());
+        *hasher.finalize().as_bytes()
+    });
+
+    let mut picked: Vec<usize> = chosen
+        .iter()
+        .chain(&rest[..count - largest])
+        .copied()
+        .collect();
+    picked.sort_unstable();
+    picked.into_iter().map(|i| file_hashes[i].clone()).collect()
+}
+
+/// Re-hash a backup against its manifest, returning the bytes that matched
+/// and every file that didn't, rather than failing on the first pass.
+/// Used to scrub old backups for bit-rot.
+pub async fn rehash(
+    destination: &Path,
+    file_hashes: &[FileHash],
+) -> Result<(u64, Vec<FileMismatch>)> {
+    let destination = destination.to_path_buf();
+    let file_hashes = file_hashes.to_vec();
+
+    tokio::task::spawn_blocking(move || {
+        let mut mismatches = Vec::new();
+        let mut bytes_verified = 0;
+        for fh in &file_hashes {
+            match check_file(&destination, fh, &mut |_| {}) {
+                Some(reason) => mismatches.push(FileMismatch {
                     relative_path: fh.relative_path.clone(),
-                    reason: MismatchReason::MissingInDestination,
-                });
-                continue;
+                    reason,
+                }),
+                None => bytes_verified += fh.size,
+            }
+        }
+        (bytes_verified, mismatches)
+    })
+    .await
+    .map_err(Into::into)
+}
+
+/// Hash every file under a still-mounted `source`, to check a backup of it
+/// with `rehash` when no manifest was kept.
+pub async fn hash_source(source: &Path) -> Result<Vec<FileHash>> {
+    let source = source.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        walk(&source, &[])?
+            .into_iter()
+            .map(|relative_path| {
+                let path = source.join(&relative_path);
+                Ok(FileHash {
+                    hash: *hash_file_sync(&path)?.as_bytes(),
+                    size: std::fs::metadata(&path)?.len(),
+                    relative_path,
+                })
+            })
+            .collect()
+    })
+    .await?
+}
+
+/// Verify a copy by hashing each file on both sides, for engines that don't
+/// hash the source as they copy (rsync).
+///
+/// `files` are relative to both `source` and `destination`. `Verifying`
+/// statuses count each file's bytes once, half from each side's read. Stops
+/// between files once `cancel` fires.
+pub async fn verify_against_source(
+    job_id: &str,
+    source: &Path,
+    destination: &Path,
+    files: Vec<PathBuf>,
+    progress: ProgressSender,
+    cancel: &CancellationToken,
+) -> Result<VerifyResult> {
+    let total = files.len() as u64;
+    info!(job_id = %job_id, total_files = total, "Starting source/destination verification");
+
+    let source = source.to_path_buf();
+    let destination = destination.to_path_buf();
+    let job_id = job_id.to_string();
+    let cancel = cancel.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let mut mismatches: Vec<FileMismatch> = Vec::new();
+        let mut bytes_verified: u64 = 0;
+
+        let total_bytes = files
+            .iter()
+            .filter_map(|path| std::fs::metadata(source.join(path)).ok())
+            .map(|metadata| metadata.len())
+            .sum();
+        let mut reporter = VerifyProgress::new(progress, total, total_bytes);
+        let mut bytes_read: u64 = 0;
+
+        for (index, relative_path) in files.into_iter().enumerate() {
+            check_cancelled(&job_id, &cancel, index as u64, total)?;
+            reporter.file_started(index as u64, bytes_read);
+
+            let source_path = source.join(&relative_path);
+            let fh = FileHash {
+                hash: *hash_file_with(&source_path, &mut |bytes| reporter.read(bytes / 2))?
+                    .as_bytes(),
+                size: std::fs::metadata(&source_path)?.len(),
+                relative_path,
+            };
+            bytes_read += fh.size;
+
+            match check_file(&destination, &fh, &mut |bytes| reporter.read(bytes / 2)) {
+                Some(reason) => mismatches.push(FileMismatch {
+                    relative_path: fh.relative_path,
+                    reason,
+                }),
+                None => bytes_verified += fh.size,
             }
+        }
+        reporter.finish();
 
-            // Hash the destination file
-            match hash_file_sync(&dest_path) {
-                Ok(dest_hash) => {
-                    if dest_hash.as_bytes() != &fh.hash {
-                        mismatches.push(FileMismatch {
-                            relative_path: fh.relative_path.clone(),
-                            reason: MismatchReason::HashMismatch,
-                        });
-                    } else {
-                        bytes_verified += fh.size;
-                    }
+        finish(&job_id, mismatches, total, bytes_verified)
+    })
+    .await?
+}
+
+/// Paranoid verification: re-read every source file after the copy and
+/// compare three ways, so a read error that gave the copy (and its hash) bad
+/// data is caught too. The hash taken during the copy must match both the
+/// source re-read and the destination.
+///
+/// `file_hashes` hold destination paths; `renamed` maps any translated names
+/// back to the card's. `Verifying` statuses count each file's bytes once,
+/// half from the source re-read and half from the destination's. Stops
+/// between files once `cancel` fires.
+pub async fn verify_three_way(
+    job_id: &str,
+    source: &Path,
+    destination: &Path,
+    file_hashes: &[FileHash],
+    renamed: &[RenamedPath],
+    progress: Option<ProgressSender>,
+    cancel: &CancellationToken,
+) -> Result<VerifyResult> {
+    let total = file_hashes.len() as u64;
+    info!(job_id = %job_id, total_files = total, "Starting three-way verification");
+
+    let source = source.to_path_buf();
+    let destination = destination.to_path_buf();
+    let file_hashes = file_hashes.to_vec();
+    let renamed: HashMap<PathBuf, PathBuf> = renamed
+        .iter()
+        .map(|r| (r.to.clone(), r.from.clone()))
+        .collect();
+    let job_id = job_id.to_string();
+    let total_bytes = file_hashes.iter().map(|fh| fh.size).sum();
+    let cancel = cancel.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let mut mismatches: Vec<FileMismatch> = Vec::new();
+        let mut bytes_verified: u64 = 0;
+
+        let mut reporter = progress.map(|p| VerifyProgress::new(p, total, total_bytes));
+        let mut bytes_read: u64 = 0;
+
+        for (index, fh) in file_hashes.iter().enumerate() {
+            check_cancelled(&job_id, &cancel, index as u64, total)?;
+            if let Some(reporter) = &mut reporter {
+                reporter.file_started(index as u64, bytes_read);
+            }
+            bytes_read += fh.size;
+
+            let on_read = &mut |bytes: u64| {
+                if let Some(reporter) = &mut reporter {
+                    reporter.read(bytes / 2);
                 }
+            };
+            let source_path = source.join(card_path(&fh.relative_path, &renamed));
+            let reason = match hash_uncached(&source_path, on_read) {
+                Ok(hash) if hash.as_bytes() == &fh.hash => check_file(&destination, fh, on_read),
+                Ok(_) => Some(MismatchReason::SourceMismatch),
                 Err(e) => {
-                    debug!(
-                        file = %fh.relative_path.display(),
-                        error = %e,
-                        "Failed to hash destination file"
-                    );
-                    mismatches.push(FileMismatch {
-                        relative_path: fh.relative_path.clone(),
-                        reason: MismatchReason::HashMismatch,
-                    });
+                    debug!(file = %fh.relative_path.display(), error = %e, "Failed to re-read source file");
+                    Some(MismatchReason::SourceUnreadable)
                 }
+            };
+            match reason {
+                Some(reason) => mismatches.push(FileMismatch {
+                    relative_path: fh.relative_path.clone(),
+                    reason,
+                }),
+                None => bytes_verified += fh.size,
             }
         }
+        if let Some(reporter) = &mut reporter {
+            reporter.finish();
+        }
+
+        finish(&job_id, mismatches, total, bytes_verified)
+    })
+    .await?
+}
 
-        // Report results
-        if !mismatches.is_empty() {
-            let error_msg = format_mismatch_error(&mismatches);
-            info!(
-                job_id = %job_id,
-                mismatches = mismatches.len(),
-                "Verification failed"
+/// Fail a verification pass that has been cancelled, before its next file.
+fn check_cancelled(
+    job_id: &str,
+    cancel: &CancellationToken,
+    checked: u64,
+    total: u64,
+) -> Result<()> {
+    if cancel.is_cancelled() {
+        info!(job_id = %job_id, checked, total, "Verification cancelled");
+        bail!(
+            "Verification cancelled after {} of {} files",
+            checked,
+            total
+        );
+    }
+    Ok(())
+}
+
+/// A destination path as it is on the card, undoing renames keyed by
+/// destination path (a renamed directory renames everything under it).
+fn card_path(relative: &Path, renamed: &HashMap<PathBuf, PathBuf>) -> PathBuf {
+    if renamed.is_empty() {
+        return relative.to_path_buf();
+    }
+    let mut destination = PathBuf::new();
+    let mut source = PathBuf::new();
+    for component in relative.components() {
+        destination.push(component);
+        match renamed.get(&destination) {
+            Some(from) => source = from.clone(),
+            None => source.push(component),
+        }
+    }
+    source
+}
+
+/// Compare one destination file with its expected hash. None if it matches.
+/// `on_read` is called with the size of each chunk read.
+fn check_file(
+    destination: &Path,
+    fh: &FileHash,
+    on_read: &mut dyn FnMut(u64),
+) -> Option<MismatchReason> {
+    let dest_path = destination.join(&fh.relative_path);
+
+    debug!(file = %fh.relative_path.display(), "Verifying file");
+
+    if !dest_path.exists() {
+        return Some(MismatchReason::MissingInDestination);
+    }
+
+    // Hash the destination file
+    match hash_file_with(&dest_path, on_read) {
+        Ok(dest_hash) if dest_hash.as_bytes() == &fh.hash => None,
+        Ok(_) => Some(MismatchReason::HashMismatch),
+        Err(e) => {
+            debug!(
+                file = %fh.relative_path.display(),
+                error = %e,
+                "Failed to hash destination file"
             );
-            bail!(error_msg);
+            Some(MismatchReason::HashMismatch)
         }
+    }
+}
 
+/// Report the outcome of a verification pass.
+fn finish(
+    job_id: &str,
+    mismatches: Vec<FileMismatch>,
+    total: u64,
+    bytes_verified: u64,
+) -> Result<VerifyResult> {
+    if !mismatches.is_empty() {
+        let error_msg = format_mismatch_error(&mismatches);
         info!(
             job_id = %job_id,
-            files_verified = total,
-            bytes_verified = bytes_verified,
-            "Verification complete"
+            mismatches = mismatches.len(),
+            "Verification failed"
         );
+        bail!(error_msg);
+    }
 
-        Ok(VerifyResult {
-            files_verified: total,
-            bytes_verified,
-        })
+    info!(
+        job_id = %job_id,
+        files_verified = total,
+        bytes_verified = bytes_verified,
+        "Verification complete"
+    );
+
+    Ok(VerifyResult {
+        files_verified: total,
+        bytes_verified,
     })
-    .await?
 }
 
 /// Hash a file using BLAKE3, streaming in chunks to handle large files (sync version)
-fn hash_file_sync(path: &Path) -> Result<blake3::Hash> {
+pub(crate) fn hash_file_sync(path: &Path) -> Result<blake3::Hash> {
+    hash_file_with(path, &mut |_| {})
+}
+
+/// `hash_file_sync`, calling `on_read` with the size of each chunk read
+fn hash_file_with(path: &Path, on_read: &mut dyn FnMut(u64)) -> Result<blake3::Hash> {
     let file = std::fs::File::open(path)
         .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", path.display(), e))?;
 
@@ -140,11 +456,33 @@ fn hash_file_sync(path: &Path) -> Result<blake3::Hash> {
             break;
         }
         hasher.update(&buffer[..bytes_read]);
+        on_read(bytes_read as u64);
     }
 
     Ok(hasher.finalize())
 }
 
+/// Hash a file as read from its device rather than from the page cache the
+/// copy just filled, so a re-read really touches the card.
+fn hash_uncached(path: &Path, on_read: &mut dyn FnMut(u64)) -> Result<blake3::Hash> {
+    #[cfg(target_os = "linux")]
+    {
+        use nix::fcntl::{PosixFadviseAdvice, posix_fadvise};
+        use std::os::fd::AsRawFd;
+
+        // Best effort: without it the hash is still right, just maybe cached
+        if let Ok(file) = std::fs::File::open(path) {
+            let _ = posix_fadvise(
+                file.as_raw_fd(),
+                0,
+                0,
+                PosixFadviseAdvice::POSIX_FADV_DONTNEED,
+            );
+        }
+    }
+    hash_file_with(path, on_read)
+}
+
 /// Format mismatch errors into a human-readable message
 fn format_mismatch_error(mismatches: &[FileMismatch]) -> String {
     let mut msg = format!(
@@ -154,11 +492,11 @@ fn format_mismatch_error(mismatches: &[FileMismatch]) -> String {
 
     // Show details for first 10 mismatches
     for m in mismatches.iter().take(10) {
-        let reason = match &m.reason {
-            MismatchReason::HashMismatch => "hash mismatch",
-            MismatchReason::MissingInDestination => "missing in destination",
-        };
-        msg.push_str(&format!("\n  - {}: {}", m.relative_path.display(), reason));
+        msg.push_str(&format!(
+            "\n  - {}: {}",
+            m.relative_path.display(),
+            m.reason.describe()
+        ));
     }
 
     if mismatches.len() > 10 {
@@ -171,7 +509,9 @@ fn format_mismatch_error(mismatches: &[FileMismatch]) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::transfer_engine::{ProgressInterval, TransferStatus};
     use tempfile::tempdir;
+    use tokio::sync::mpsc;
 
     /// Helper to create a FileHash from content
     fn make_hash(relative_path: &str, content: &[u8]) -> FileHash {
@@ -183,6 +523,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_sample_includes_largest_files() {
+        let hashes: Vec<FileHash> = (0..40)
+            .map(|i| FileHash {
+                relative_path: PathBuf::from(format!("DCIM/IMG_{:04}.JPG", i)),
+                hash: [0; 32],
+                size: if i == 7 { 1 << 30 } else { 1000 + i },
+            })
+            .collect();
+
+        let paths = |picked: Vec<FileHash>| -> Vec<PathBuf> {
+            picked.into_iter().map(|fh| fh.relative_path).collect()
+        };
+        let picked = sample("job-1", &hashes, 10);
+        assert_eq!(picked.len(), 4);
+        assert!(picked.iter().any(|fh| fh.size == 1 << 30));
+        let picked = paths(picked);
+        assert!(picked.is_sorted());
+        // Same job, same files; another job checks others
+        assert_eq!(picked, paths(sample("job-1", &hashes, 10)));
+        assert_ne!(picked, paths(sample("job-2", &hashes, 10)));
+
+        assert_eq!(sample("job-1", &hashes, 0).len(), 1);
+        assert_eq!(sample("job-1", &hashes, 100).len(), 40);
+        assert!(sample("job-1", &[], 10).is_empty());
+    }
+
     #[tokio::test]
     async fn test_verify_from_hashes_success() {
         let temp = tempdir().unwrap();
@@ -201,7 +568,14 @@ mod tests {
             make_hash("subdir/nested.txt", b"nested content"),
         ];
 
-        let result = verify_from_hashes("test-job", &dest, &file_hashes).await;
+        let result = verify_from_hashes(
+            "test-job",
+            &dest,
+            &file_hashes,
+            None,
+            &CancellationToken::new(),
+        )
+        .await;
         assert!(result.is_ok());
 
         let verify_result = result.unwrap();
@@ -222,7 +596,14 @@ mod tests {
         // Hash is for "original content" but file contains "corrupted content"
         let file_hashes = vec![make_hash("file.txt", b"original content")];
 
-        let result = verify_from_hashes("test-job", &dest, &file_hashes).await;
+        let result = verify_from_hashes(
+            "test-job",
+            &dest,
+            &file_hashes,
+            None,
+            &CancellationToken::new(),
+        )
+        .await;
         assert!(result.is_err());
 
         let err = result.unwrap_err().to_string();
@@ -240,7 +621,14 @@ mod tests {
         // Hash for a file that doesn't exist in destination
         let file_hashes = vec![make_hash("missing.txt", b"this file is missing")];
 
-        let result = verify_from_hashes("test-job", &dest, &file_hashes).await;
+        let result = verify_from_hashes(
+            "test-job",
+            &dest,
+            &file_hashes,
+            None,
+            &CancellationToken::new(),
+        )
+        .await;
         assert!(result.is_err());
 
         let err = result.unwrap_err().to_string();
@@ -266,7 +654,14 @@ mod tests {
             make_hash("c.txt", b"content c"),
         ];
 
-        let result = verify_from_hashes("test-job", &dest, &file_hashes).await;
+        let result = verify_from_hashes(
+            "test-job",
+            &dest,
+            &file_hashes,
+            None,
+            &CancellationToken::new(),
+        )
+        .await;
         assert!(result.is_err());
 
         let err = result.unwrap_err().to_string();
@@ -274,6 +669,92 @@ mod tests {
         assert!(err.contains("3 file(s) did not match"));
     }
 
+    #[tokio::test]
+    async fn test_verify_against_source() {
+        let temp = tempdir().unwrap();
+        let source = temp.path().join("source");
+        let dest = temp.path().join("dest");
+        std::fs::create_dir_all(source.join("DCIM")).unwrap();
+        std::fs::create_dir_all(dest.join("DCIM")).unwrap();
+        std::fs::write(source.join("DCIM/a.jpg"), b"jpeg").unwrap();
+        std::fs::write(dest.join("DCIM/a.jpg"), b"jpeg").unwrap();
+        std::fs::write(source.join("DCIM/b.jpg"), b"jpeg").unwrap();
+        std::fs::write(dest.join("DCIM/b.jpg"), b"jpg").unwrap();
+
+        let (tx, mut rx) = mpsc::channel(10);
+        let result = verify_against_source(
+            "test-job",
+            &source,
+            &dest,
+            vec![PathBuf::from("DCIM/a.jpg")],
+            ProgressSender {
+                tx: tx.clone(),
+                interval: ProgressInterval::default(),
+            },
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.bytes_verified, 4);
+        assert!(matches!(
+            rx.recv().await,
+            Some(TransferStatus::Verifying {
+                current: 0,
+                total: 1,
+                total_bytes: 4,
+                ..
+            })
+        ));
+        // A last update once every file has been read
+        let mut last = None;
+        while let Ok(status) = rx.try_recv() {
+            last = Some(status);
+        }
+        assert!(matches!(
+            last,
+            Some(TransferStatus::Verifying {
+                current: 1,
+                bytes_verified: 4,
+                ..
+            })
+        ));
+
+        let err = verify_against_source(
+            "test-job",
+            &source,
+            &dest,
+            vec![PathBuf::from("DCIM/a.jpg"), PathBuf::from("DCIM/b.jpg")],
+            ProgressSender {
+                tx,
+                interval: ProgressInterval::default(),
+            },
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("1 file(s) did not match"));
+        assert!(err.contains("DCIM/b.jpg: hash mismatch"));
+    }
+
+    #[tokio::test]
+    async fn test_verify_stops_when_cancelled() {
+        let temp = tempdir().unwrap();
+        let dest = temp.path().join("dest");
+        std::fs::create_dir_all(&dest).unwrap();
+        std::fs::write(dest.join("a.txt"), b"content a").unwrap();
+
+        let file_hashes = vec![make_hash("a.txt", b"content a")];
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let err = verify_from_hashes("test-job", &dest, &file_hashes, None, &cancel)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert_eq!(err, "Verification cancelled after 0 of 1 files");
+    }
+
     #[tokio::test]
     async fn test_verify_from_hashes_empty() {
         let temp = tempdir().unwrap();
@@ -283,11 +764,94 @@ mod tests {
 
         let file_hashes: Vec<FileHash> = vec![];
 
-        let result = verify_from_hashes("test-job", &dest, &file_hashes).await;
+        let result = verify_from_hashes(
+            "test-job",
+            &dest,
+            &file_hashes,
+            None,
+            &CancellationToken::new(),
+        )
+        .await;
         assert!(result.is_ok());
 
         let verify_result = result.unwrap();
         assert_eq!(verify_result.files_verified, 0);
         assert_eq!(verify_result.bytes_verified, 0);
     }
+
+    #[tokio::test]
+    async fn test_verify_three_way() {
+        let temp = tempdir().unwrap();
+        let source = temp.path().join("source");
+        let dest = temp.path().join("dest");
+        std::fs::create_dir_all(source.join("CON")).unwrap();
+        std::fs::create_dir_all(dest.join("CON_")).unwrap();
+
+        std::fs::write(source.join("good.mov"), b"clip").unwrap();
+        std::fs::write(dest.join("good.mov"), b"clip").unwrap();
+        // Found under its translated directory name
+        std::fs::write(source.join("CON/a.jpg"), b"still").unwrap();
+        std::fs::write(dest.join("CON_/a.jpg"), b"still").unwrap();
+        let renamed = vec![RenamedPath {
+            from: PathBuf::from("CON"),
+            to: PathBuf::from("CON_"),
+        }];
+
+        let hashes = vec![
+            make_hash("good.mov", b"clip"),
+            make_hash("CON_/a.jpg", b"still"),
+        ];
+        let result = verify_three_way(
+            "test-job",
+            &source,
+            &dest,
+            &hashes,
+            &renamed,
+            None,
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.files_verified, 2);
+
+        // The card returned bad data during the copy: the destination matches
+        // the copy's hash, but re-reading the card gives something else
+        std::fs::write(dest.join("flaky.mov"), b"clip with a bad sector").unwrap();
+        std::fs::write(source.join("flaky.mov"), b"clip with a good sector").unwrap();
+        let hashes = vec![make_hash("flaky.mov", b"clip with a bad sector")];
+        let err = verify_three_way(
+            "test-job",
+            &source,
+            &dest,
+            &hashes,
+            &[],
+            None,
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("flaky.mov: source re-read differs"));
+        // The plain check can't tell
+        assert!(
+            verify_from_hashes("test-job", &dest, &hashes, None, &CancellationToken::new())
+                .await
+                .is_ok()
+        );
+
+        let hashes = vec![make_hash("gone.mov", b"clip")];
+        let err = verify_three_way(
+            "test-job",
+            &source,
+            &dest,
+            &hashes,
+            &[],
+            None,
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("source could not be re-read"));
+    }
 }
@@ -0,0 +1,171 @@
+//! Post-backup structural probe of copied video files.
+//!
+//! Verification proves the destination matches the card byte-for-byte, but a
+//! perfect copy of a recording that was never finalized is still unplayable.
+//! This walks the backup and checks every MP4/MOV container for a usable
+//! sample index. The result for each file is recorded in the catalog, and
+//! each broken file plus a summary become notices on the job.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use tracing::{debug, info, warn};
+
+use crate::core::media::{MediaKind, VideoProbe, probe_video};
+use crate::core::models::JobNotice;
+use crate::db::files::ProbedVideo;
+
+/// Probe all video containers under `destination`.
+///
+/// Returns one result per video file, or an empty list if the backup
+/// contains no videos or couldn't be walked.
+pub async fn probe_destination(job_id: &str, destination: &Path) -> Vec<ProbedVideo> {
+    let destination = destination.to_path_buf();
+    let job_id = job_id.to_string();
+
+    let result = tokio::task::spawn_blocking(move || {
+        let mut videos = Vec::new();
+        collect_videos(&destination, &mut videos)?;
+
+        let mut probes = Vec::new();
+        for path in &videos {
+            let relative = path.strip_prefix(&destination).unwrap_or(path);
+
+            let reason = match probe_video(path) {
+                Ok(VideoProbe::Ok) => {
+                    debug!(file = %relative.display(), "Video probe passed");
+                    None
+                }
+                Ok(VideoProbe::Broken(reason)) => Some(reason),
+                Err(e) => Some(format!("could not be read: {}", e)),
+            };
+            if let Some(reason) = &reason {
+                warn!(
+                    job_id = %job_id,
+                    file = %relative.display(),
+                    reason = %reason,
+                    "Video probe failed"
+                );
+            }
+            probes.push(ProbedVideo {
+                relative_path: relative.to_path_buf(),
+                ok: reason.is_none(),
+                reason,
+            });
+        }
+
+        if !probes.is_empty() {
+            info!(
+                job_id = %job_id,
+                probed = probes.len(),
+                broken = probes.iter().filter(|p| !p.ok).count(),
+                "Video probe complete"
+            );
+        }
+
+        Ok::<_, anyhow::Error>(probes)
+    })
+    .await;
+
+    match result {
+        Ok(Ok(probes)) => probes,
+        Ok(Err(e)) => {
+            warn!(error = %e, "Video probe skipped");
+            Vec::new()
+        }
+        Err(e) => {
+            warn!(error = %e, "Video probe task failed");
+            Vec::new()
+        }
+    }
+}
+
+/// One notice per broken file followed by a summary notice, or none if
+/// nothing was probed.
+pub fn notices(probes: &[ProbedVideo]) -> Vec<JobNotice> {
+    let mut notices: Vec<JobNotice> = probes
+        .iter()
+        .filter_map(|probe| {
+            let reason = probe.reason.as_deref().filter(|_| !probe.ok)?;
+            let relative = probe.relative_path.to_string_lossy().to_string();
+            Some(JobNotice {
+                kind: "video_probe_failed".to_string(),
+                message: format!("{}: {}", relative, reason),
+                path: Some(relative),
+            })
+        })
+        .collect();
+
+    if !probes.is_empty() {
+        notices.push(JobNotice {
+            kind: "video_probe".to_string(),
+            path: None,
+            message: format!(
+                "Probed {} video file(s): {} problem(s)",
+                probes.len(),
+                notices.len()
+            ),
+        });
+    }
+    notices
+}
+
+fn collect_videos(dir: &Path, videos: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Ok(metadata) = path.symlink_metadata() else {
+            continue;
+        };
+
+        if metadata.is_dir() {
+            collect_videos(&path, videos)?;
+        } else if metadata.is_file() && MediaKind::from_path(&path) == Some(MediaKind::Video) {
+            videos.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_probe_destination_reports_broken_clips() {
+        let temp = tempdir().unwrap();
+
+        // ftyp + mdat, never finalized with a moov index
+        let mut clip = 24u32.to_be_bytes().to_vec();
+        clip.extend_from_slice(b"ftyp");
+        clip.extend([0u8; 16]);
+        clip.extend(16u32.to_be_bytes());
+        clip.extend_from_slice(b"mdat");
+        clip.extend([0u8; 8]);
+        fs::write(temp.path().join("C0001.MP4"), clip).unwrap();
+        fs::write(temp.path().join("IMG_0001.JPG"), b"jpeg").unwrap();
+
+        let probes = probe_destination("test-job", temp.path()).await;
+        assert_eq!(probes.len(), 1);
+        assert_eq!(probes[0].relative_path, PathBuf::from("C0001.MP4"));
+        assert!(!probes[0].ok);
+        assert!(probes[0].reason.is_some());
+
+        let notices = notices(&probes);
+        assert_eq!(notices.len(), 2);
+        assert_eq!(notices[0].kind, "video_probe_failed");
+        assert_eq!(notices[0].path.as_deref(), Some("C0001.MP4"));
+        assert!(notices[1].message.contains("1 video file(s): 1 problem(s)"));
+    }
+
+    #[tokio::test]
+    async fn test_probe_destination_without_videos() {
+        let temp = tempdir().unwrap();
+        fs::write(temp.path().join("IMG_0001.JPG"), b"jpeg").unwrap();
+
+        let probes = probe_destination("test-job", temp.path()).await;
+        assert!(probes.is_empty());
+        assert!(notices(&probes).is_empty());
+    }
+}
@@ -0,0 +1,146 @@
+//! The hours of the day backups may run in.
+//!
+//! `backup_window` is one or more `HH:MM-HH:MM` ranges separated by commas,
+//! in the configured timezone. A range may wrap past midnight
+//! (`22:00-06:00`), and `24:00` ends one at midnight. A card inserted
+//! outside the window gets a `queued` job, which the orchestrator starts
+//! when the window next opens.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow, bail};
+use chrono::{DateTime, Duration, NaiveTime, Timelike, Utc};
+
+use crate::core::timestamp::DisplayZone;
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// Parsed `backup_window`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupWindow {
+    expression: String,
+    /// Start and end minute of the day of each range; an end before its
+    /// start wraps past midnight
+    ranges: Vec<(u32, u32)>,
+}
+
+impl FromStr for BackupWindow {
+    type Err = anyhow::Error;
+
+    fn from_str(expression: &str) -> Result<Self> {
+        let ranges = expression
+            .split(',')
+            .map(|range| {
+                let (start, end) = range.trim().split_once('-').ok_or_else(|| {
+                    anyhow!(
+                        "Invalid range \"{}\" in backup window, expected HH:MM-HH:MM",
+                        range.trim()
+                    )
+                })?;
+                let (start, end) = (minute_of_day(start)?, minute_of_day(end)?);
+                if start == end || (start == 0 && end == MINUTES_PER_DAY) {
+                    bail!(
+                        "Backup window range \"{}\" covers the whole day or none of it",
+                        range.trim()
+                    );
+                }
+                Ok((start % MINUTES_PER_DAY, end))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(BackupWindow {
+            expression: expression.trim().to_string(),
+            ranges,
+        })
+    }
+}
+
+impl fmt::Display for BackupWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+/// Minutes since midnight of `HH:MM`, up to `24:00`.
+fn minute_of_day(text: &str) -> Result<u32> {
+    let text = text.trim();
+    let invalid = || anyhow!("Invalid time \"{}\" in backup window, expected HH:MM", text);
+    let (hours, minutes) = text.split_once(':').ok_or_else(invalid)?;
+    let hours: u32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u32 = minutes.parse().map_err(|_| invalid())?;
+    if minutes > 59 || hours * 60 + minutes > MINUTES_PER_DAY {
+        return Err(invalid());
+    }
+    Ok(hours * 60 + minutes)
+}
+
+impl BackupWindow {
+    /// Whether backups may run at `now`.
+    pub fn is_open(&self, now: DateTime<Utc>, zone: DisplayZone) -> bool {
+        let local = zone.to_local(now);
+        let minute = local.hour() * 60 + local.minute();
+        self.ranges.iter().any(|&(start, end)| {
+            if start < end {
+                (start..end).contains(&minute)
+            } else {
+                minute >= start || minute < end
+            }
+        })
+    }
+
+    /// When the window next opens after `now`; `now` itself if it is open.
+    pub fn opens_after(&self, now: DateTime<Utc>, zone: DisplayZone) -> DateTime<Utc> {
+        if self.is_open(now, zone) {
+            return now;
+        }
+
+        let today = zone.to_local(now).date();
+        [today, today + Duration::days(1), today + Duration::days(2)]
+            .into_iter()
+            .flat_map(|day| {
+                self.ranges.iter().filter_map(move |&(start, _)| {
+                    let time = NaiveTime::from_hms_opt(start / 60, start % 60, 0)?;
+                    zone.from_local(day.and_time(time))
+                })
+            })
+            .filter(|opens| *opens > now)
+            .min()
+            // Only when every start falls in a skipped DST hour
+            .unwrap_or(now + Duration::hours(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 14, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_window_opens_and_closes() {
+        let utc = DisplayZone::Named(chrono_tz::UTC);
+        let night: BackupWindow = "22:00-06:00".parse().unwrap();
+        assert!(night.is_open(at(23, 30), utc));
+        assert!(night.is_open(at(5, 59), utc));
+        assert!(!night.is_open(at(6, 0), utc));
+        assert!(!night.is_open(at(12, 0), utc));
+        assert_eq!(night.opens_after(at(12, 0), utc), at(22, 0));
+        assert_eq!(night.opens_after(at(23, 0), utc), at(23, 0));
+
+        // Outside business hours
+        let evenings: BackupWindow = "00:00-09:00, 17:30-24:00".parse().unwrap();
+        assert!(evenings.is_open(at(18, 0), utc));
+        assert!(!evenings.is_open(at(9, 0), utc));
+        assert_eq!(evenings.opens_after(at(10, 0), utc), at(17, 30));
+        assert_eq!(evenings.to_string(), "00:00-09:00, 17:30-24:00");
+
+        assert!("22:00".parse::<BackupWindow>().is_err());
+        assert!("22:00-25:00".parse::<BackupWindow>().is_err());
+        assert!("08:60-09:00".parse::<BackupWindow>().is_err());
+        assert!("06:00-06:00".parse::<BackupWindow>().is_err());
+    }
+}
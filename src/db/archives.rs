@@ -0,0 +1,220 @@
+use anyhow::{Result, anyhow};
+use tokio_rusqlite::{Connection, params, rusqlite};
+
+use crate::core::timestamp;
+use crate::core::{ArchiveVolume, Job};
+use crate::db::jobs::{JOB_COLUMNS, job_from_row};
+use crate::db::outbox;
+
+/// Completed backups older than `?1` days that still live in the backup
+/// directory. Relies on the `status` alias from `JOB_COLUMNS`.
+const ARCHIVABLE_WHERE: &str = "status = 'complete'
+       AND j.created_at <= strftime('%Y-%m-%dT%H:%M:%fZ', 'now', '-' || ?1 || ' days')
+       AND j.destination_path IS NOT NULL
+       AND NOT EXISTS (SELECT 1 FROM job_archives a WHERE a.job_id = j.id)";
+
+/// Size of a job's backup as recorded on its `complete` status entry.
+const BACKUP_BYTES: &str = "(SELECT total_bytes FROM job_status_log
+     WHERE job_id = j.id AND total_bytes IS NOT NULL
+     ORDER BY created_at DESC LIMIT 1)";
+
+/// List completed backups older than `older_than_days` that still live in the
+/// backup directory, oldest first.
+pub async fn list_archivable(conn: &Connection, older_than_days: u32) -> Result<Vec<Job>> {
+    conn.call(move |c| {
+        let mut stmt = c.prepare(&format!(
+            "SELECT {} FROM jobs j
+             WHERE {}
+             ORDER BY j.created_at ASC",
+            JOB_COLUMNS, ARCHIVABLE_WHERE
+        ))?;
+
+        let jobs = stmt
+            .query_map(params![older_than_days], job_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok::<_, rusqlite::Error>(jobs)
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to list archivable jobs: {}", e))
+}
+
+/// Number and combined size of the backups `list_archivable` would return.
+pub async fn archivable_bytes(conn: &Connection, older_than_days: u32) -> Result<(usize, u64)> {
+    conn.call(move |c| {
+        c.query_row(
+            &format!(
+                "SELECT COUNT(*), COALESCE(SUM(size), 0) FROM (
+                     SELECT {}, {} as size FROM jobs j WHERE {}
+                 )",
+                JOB_COLUMNS, BACKUP_BYTES, ARCHIVABLE_WHERE
+            ),
+            params![older_than_days],
+            |row| Ok((row.get::<_, usize>(0)?, row.get::<_, u64>(1)?)),
+        )
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to size archivable jobs: {}", e))
+}
+
+/// Combined size of the backups stored on an archive drive.
+pub async fn stored_bytes(conn: &Connection, uuid: String) -> Result<u64> {
+    conn.call(move |c| {
+        c.query_row(
+            &format!(
+                "SELECT COALESCE(SUM({}), 0) FROM job_archives a
+                 JOIN jobs j ON j.id = a.job_id
+                 WHERE a.volume_uuid = ?1",
+                BACKUP_BYTES
+            ),
+            params![&uuid],
+            |row| row.get(0),
+        )
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to size archive drive contents: {}", e))
+}
+
+/// Record that a job's backup now lives on an archive drive.
+/// The job's destination path is updated to the new location.
+pub async fn record(
+    conn: &Connection,
+    job_id: String,
+    volume_uuid: String,
+    volume_label: String,
+    archive_path: String,
+) -> Result<()> {
+    conn.call(move |c| {
+        let tx = c.transaction()?;
+        let now = timestamp::now_utc();
+
+        tx.execute(
+            "INSERT INTO job_archives (job_id, volume_uuid, volume_label, archive_path, archived_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![&job_id, &volume_uuid, &volume_label, &archive_path, &now],
+        )?;
+
+        tx.execute(
+            "UPDATE jobs SET destination_path = ?1 WHERE id = ?2",
+            params![&archive_path, &job_id],
+        )?;
+        outbox::enqueue(&tx, &outbox::archived_event_id(&job_id), &job_id, &now)?;
+
+        tx.commit()?;
+        Ok::<(), rusqlite::Error>(())
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Register an archive drive as connected, creating it on first sight.
+pub async fn mark_connected(
+    conn: &Connection,
+    uuid: String,
+    label: String,
+    capacity: u64,
+) -> Result<()> {
+    conn.call(move |c| {
+        c.execute(
+            "INSERT INTO archive_volumes (uuid, label, capacity, connected, last_seen_at)
+             VALUES (?1, ?2, ?3, 1, ?4)
+             ON CONFLICT(uuid) DO UPDATE SET
+                label = excluded.label,
+                capacity = excluded.capacity,
+                connected = 1,
+                last_seen_at = excluded.last_seen_at",
+            params![&uuid, &label, capacity, timestamp::now_utc()],
+        )?;
+        Ok::<(), rusqlite::Error>(())
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Mark an archive drive as detached. No-op for devices that aren't archives.
+pub async fn mark_disconnected(conn: &Connection, uuid: String) -> Result<()> {
+    conn.call(move |c| {
+        c.execute(
+            "UPDATE archive_volumes
+             SET connected = 0, last_seen_at = ?2
+             WHERE uuid = ?1 AND connected = 1",
+            params![&uuid, timestamp::now_utc()],
+        )?;
+        Ok::<(), rusqlite::Error>(())
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Forget connection state from a previous run. Drives still plugged in are
+/// re-registered when the adapter reports them.
+pub async fn reset_connected(conn: &Connection) -> Result<()> {
+    conn.call(|c| {
+        c.execute("UPDATE archive_volumes SET connected = 0", [])?;
+        Ok::<(), rusqlite::Error>(())
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// List all known archive drives, connected or not.
+pub async fn list_volumes(conn: &Connection) -> Result<Vec<ArchiveVolume>> {
+    conn.call(|c| {
+        let mut stmt = c.prepare(&format!("{} ORDER BY v.label ASC", VOLUME_SELECT))?;
+        stmt.query_map([], volume_from_row)?
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to list archive volumes: {}", e))
+}
+
+/// Find the archive drive holding a job's backup, if it has been archived.
+pub async fn locate(conn: &Connection, job_id: String) -> Result<Option<ArchiveVolume>> {
+    conn.call(move |c| {
+        let mut stmt = c.prepare(&format!(
+            "{} WHERE v.uuid = (SELECT volume_uuid FROM job_archives WHERE job_id = ?1)",
+            VOLUME_SELECT
+        ))?;
+        let mut rows = stmt.query_map(params![&job_id], volume_from_row)?;
+        rows.next().transpose()
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to locate archived job: {}", e))
+}
+
+/// List the jobs stored on an archive drive, oldest first.
+pub async fn list_contents(conn: &Connection, uuid: String) -> Result<Vec<Job>> {
+    conn.call(move |c| {
+        let mut stmt = c.prepare(&format!(
+            "SELECT {} FROM job_archives a
+             JOIN jobs j ON j.id = a.job_id
+             WHERE a.volume_uuid = ?1
+             ORDER BY j.created_at ASC",
+            JOB_COLUMNS
+        ))?;
+
+        stmt.query_map(params![&uuid], job_from_row)?
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to list archive contents: {}", e))
+}
+
+const VOLUME_SELECT: &str = "SELECT v.uuid, v.label, v.capacity, v.connected, v.last_seen_at,
+     (SELECT COUNT(*) FROM job_archives a WHERE a.volume_uuid = v.uuid) as job_count
+     FROM archive_volumes v";
+
+fn volume_from_row(row: &rusqlite::Row) -> rusqlite::Result<ArchiveVolume> {
+    Ok(ArchiveVolume {
+        uuid: row.get(0)?,
+        label: row.get(1)?,
+        capacity: row.get(2)?,
+        connected: row.get(3)?,
+        last_seen_at: row.get(4)?,
+        job_count: row.get(5)?,
+    })
+}
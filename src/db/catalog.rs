@@ -0,0 +1,187 @@
+//! Per-device catalog of backed-up files, used by incremental mode.
+//!
+//! After a verified job, every file it holds is recorded against the source
+//! device's UUID along with the job that backed it up. The next job for the
+//! same card skips files whose path, size and hash are unchanged, and those
+//! skips are recorded against the new job.
+//!
+//! Each device's content fingerprint (`core::fingerprint`) at its latest
+//! verified backup is kept here too, so a card reinserted unchanged can be
+//! skipped outright.
+
+use std::path::PathBuf;
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use tokio_rusqlite::{Connection, params, rusqlite};
+
+use crate::core::timestamp;
+use crate::core::transfer_engine::FileHash;
+
+/// A catalogued file and the job whose backup holds it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogEntry {
+    pub file: FileHash,
+    pub job_id: String,
+}
+
+/// A device's fingerprint and the verified job that backed up that content.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceFingerprint {
+    pub digest: String,
+    pub job_id: String,
+}
+
+/// Everything backed up so far from a device.
+pub async fn lookup(conn: &Connection, target_id: String) -> Result<Vec<CatalogEntry>> {
+    conn.call(move |c| {
+        let mut stmt = c.prepare(
+            "SELECT relative_path, size, hash, job_id FROM device_files
+             WHERE target_id = ?1
+             ORDER BY relative_path",
+        )?;
+
+        stmt.query_map(params![&target_id], |row| {
+            let path: String = row.get(0)?;
+            let hash: Vec<u8> = row.get(2)?;
+            Ok(CatalogEntry {
+                file: FileHash {
+                    relative_path: PathBuf::from(path),
+                    size: row.get(1)?,
+                    hash: hash.try_into().unwrap_or([0; 32]),
+                },
+                job_id: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to read device catalog: {}", e))
+}
+
+/// Record the files a verified job backed up from a device.
+pub async fn update(
+    conn: &Connection,
+    target_id: String,
+    job_id: String,
+    files: Vec<FileHash>,
+) -> Result<()> {
+    conn.call(move |c| {
+        let tx = c.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO device_files (target_id, relative_path, size, hash, job_id, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT(target_id, relative_path) DO UPDATE SET
+                    size = excluded.size,
+                    hash = excluded.hash,
+                    job_id = excluded.job_id,
+                    created_at = excluded.created_at",
+            )?;
+            let now = timestamp::now_utc();
+            for file in &files {
+                stmt.execute(params![
+                    &target_id,
+                    file.relative_path.to_string_lossy(),
+                    file.size,
+                    &file.hash[..],
+                    &job_id,
+                    &now
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok::<(), rusqlite::Error>(())
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to update device catalog: {}", e))
+}
+
+/// Record files a job skipped because an earlier backup already holds them.
+pub async fn record_skipped(
+    conn: &Connection,
+    job_id: String,
+    skipped: Vec<CatalogEntry>,
+) -> Result<()> {
+    if skipped.is_empty() {
+        return Ok(());
+    }
+
+    conn.call(move |c| {
+        let tx = c.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO job_skipped_files
+                    (job_id, relative_path, size, backed_up_by, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            let now = timestamp::now_utc();
+            for entry in &skipped {
+                stmt.execute(params![
+                    &job_id,
+                    entry.file.relative_path.to_string_lossy(),
+                    entry.file.size,
+                    &entry.job_id,
+                    &now
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok::<(), rusqlite::Error>(())
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to record skipped files: {}", e))
+}
+
+/// The fingerprint a device had at its latest verified backup.
+pub async fn fingerprint(
+    conn: &Connection,
+    target_id: String,
+) -> Result<Option<DeviceFingerprint>> {
+    conn.call(move |c| {
+        let found = c.query_row(
+            "SELECT digest, job_id FROM device_fingerprints WHERE target_id = ?1",
+            params![&target_id],
+            |row| {
+                Ok(DeviceFingerprint {
+                    digest: row.get(0)?,
+                    job_id: row.get(1)?,
+                })
+            },
+        );
+        match found {
+            Ok(fingerprint) => Ok(Some(fingerprint)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to read device fingerprint: {}", e))
+}
+
+/// Replace a device's fingerprint after a verified job backed it up.
+pub async fn record_fingerprint(
+    conn: &Connection,
+    target_id: String,
+    fingerprint: DeviceFingerprint,
+) -> Result<()> {
+    conn.call(move |c| {
+        c.execute(
+            "INSERT INTO device_fingerprints (target_id, digest, job_id, created_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(target_id) DO UPDATE SET
+                digest = excluded.digest,
+                job_id = excluded.job_id,
+                created_at = excluded.created_at",
+            params![
+                &target_id,
+                &fingerprint.digest,
+                &fingerprint.job_id,
+                timestamp::now_utc()
+            ],
+        )?;
+        Ok::<(), rusqlite::Error>(())
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to record device fingerprint: {}", e))
+}
@@ -0,0 +1,182 @@
+//! Per-job manifest of copied files.
+//!
+//! Each file's hash is recorded as soon as it has been copied so that an
+//! interrupted job can be resumed without recopying what already landed.
+//! The manifest also backs re-verification, the file lists in the TUI and
+//! dashboard, and finding files already backed up by another job. The result
+//! of probing each copied video (`probe_videos`) is kept alongside it.
+
+use std::path::PathBuf;
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use tokio_rusqlite::{Connection, params, rusqlite};
+
+use crate::core::timestamp;
+use crate::core::transfer_engine::FileHash;
+
+/// Record a copied file in the job's manifest.
+pub async fn record(conn: &Connection, job_id: String, file: FileHash) -> Result<()> {
+    conn.call(move |c| {
+        c.execute(
+            "INSERT INTO job_files (job_id, relative_path, size, hash, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(job_id, relative_path) DO UPDATE SET
+                size = excluded.size,
+                hash = excluded.hash,
+                created_at = excluded.created_at",
+            params![
+                &job_id,
+                file.relative_path.to_string_lossy(),
+                file.size,
+                &file.hash[..],
+                timestamp::now_utc()
+            ],
+        )?;
+        Ok::<(), rusqlite::Error>(())
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to record copied file: {}", e))
+}
+
+/// All files recorded for a job.
+pub async fn list(conn: &Connection, job_id: String) -> Result<Vec<FileHash>> {
+    conn.call(move |c| {
+        let mut stmt = c.prepare(
+            "SELECT relative_path, size, hash FROM job_files
+             WHERE job_id = ?1
+             ORDER BY relative_path",
+        )?;
+
+        stmt.query_map(params![&job_id], |row| {
+            let path: String = row.get(0)?;
+            let hash: Vec<u8> = row.get(2)?;
+            Ok(FileHash {
+                relative_path: PathBuf::from(path),
+                size: row.get(1)?,
+                hash: hash.try_into().unwrap_or([0; 32]),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to list copied files: {}", e))
+}
+
+/// A file in a job's backup whose contents another job also backed up
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DuplicateFile {
+    pub relative_path: PathBuf,
+    pub size: u64,
+    pub other_job_id: String,
+    /// Where the other job has it
+    pub other_path: PathBuf,
+}
+
+/// Files in a job's manifest with the same size and hash as a file in
+/// another job's.
+pub async fn duplicates(conn: &Connection, job_id: String) -> Result<Vec<DuplicateFile>> {
+    conn.call(move |c| {
+        let mut stmt = c.prepare(
+            "SELECT f.relative_path, f.size, o.job_id, o.relative_path
+             FROM job_files f
+             JOIN job_files o ON o.hash = f.hash AND o.size = f.size AND o.job_id != f.job_id
+             WHERE f.job_id = ?1
+             ORDER BY f.relative_path, o.job_id, o.relative_path",
+        )?;
+
+        stmt.query_map(params![&job_id], |row| {
+            let path: String = row.get(0)?;
+            let other_path: String = row.get(3)?;
+            Ok(DuplicateFile {
+                relative_path: PathBuf::from(path),
+                size: row.get(1)?,
+                other_job_id: row.get(2)?,
+                other_path: PathBuf::from(other_path),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to find duplicate files: {}", e))
+}
+
+/// Result of probing one video file in a job's backup
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProbedVideo {
+    pub relative_path: PathBuf,
+    /// Whether the container has a usable sample index
+    pub ok: bool,
+    /// Why the file is unplayable, when not ok
+    pub reason: Option<String>,
+}
+
+/// Record the probe results for a job's video files, replacing any earlier
+/// result for the same file.
+pub async fn record_probes(
+    conn: &Connection,
+    job_id: String,
+    probes: Vec<ProbedVideo>,
+) -> Result<()> {
+    conn.call(move |c| {
+        let tx = c.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO video_probes (job_id, relative_path, ok, reason, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(job_id, relative_path) DO UPDATE SET
+                    ok = excluded.ok,
+                    reason = excluded.reason,
+                    created_at = excluded.created_at",
+            )?;
+            let now = timestamp::now_utc();
+            for probe in &probes {
+                stmt.execute(params![
+                    &job_id,
+                    probe.relative_path.to_string_lossy(),
+                    probe.ok,
+                    probe.reason,
+                    now
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok::<(), rusqlite::Error>(())
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to record video probe results: {}", e))
+}
+
+/// Probe results for a job's video files.
+pub async fn list_probes(conn: &Connection, job_id: String) -> Result<Vec<ProbedVideo>> {
+    conn.call(move |c| {
+        let mut stmt = c.prepare(
+            "SELECT relative_path, ok, reason FROM video_probes
+             WHERE job_id = ?1
+             ORDER BY relative_path",
+        )?;
+
+        stmt.query_map(params![&job_id], |row| {
+            let path: String = row.get(0)?;
+            Ok(ProbedVideo {
+                relative_path: PathBuf::from(path),
+                ok: row.get(1)?,
+                reason: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to list video probe results: {}", e))
+}
+
+/// Forget a job's manifest so it can't be resumed.
+pub async fn clear(conn: &Connection, job_id: String) -> Result<()> {
+    conn.call(move |c| {
+        c.execute("DELETE FROM job_files WHERE job_id = ?1", params![&job_id])?;
+        Ok::<(), rusqlite::Error>(())
+    })
+    .await?;
+
+    Ok(())
+}
@@ -2,45 +2,91 @@ use anyhow::{Result, anyhow};
 use tokio_rusqlite::{Connection, params, rusqlite};
 use uuid::Uuid;
 
-use crate::core::{Job, JobStatusEntry, JobWithHistory, TargetDrive};
+use crate::core::label_fields::LabelFields;
+use crate::core::timestamp;
+use crate::core::transfer_engine::{FileHash, TransferStats};
+use crate::core::{Job, JobNotice, JobStatusEntry, JobWithHistory, TargetDrive};
+use crate::db::outbox;
+
+/// Columns selected for a `Job`, in the order read by `job_from_row`.
+/// Queries must alias the jobs table as `j`.
+pub(crate) const JOB_COLUMNS: &str = "j.id, j.target_id, j.destination_path, j.created_at,
+     COALESCE((SELECT status FROM job_status_log WHERE job_id = j.id ORDER BY created_at DESC LIMIT 1), 'Unknown') as status,
+     (SELECT volume_label FROM job_archives WHERE job_id = j.id) as archive_label,
+     j.origin_host, j.reel, j.day, j.camera, j.camera_model";
+
+pub(crate) fn job_from_row(row: &rusqlite::Row) -> rusqlite::Result<Job> {
+    Ok(Job {
+        id: row.get(0)?,
+        target_id: row.get(1)?,
+        destination_path: row.get(2)?,
+        created_at: row.get(3)?,
+        status: row.get(4)?,
+        archive_label: row.get(5)?,
+        origin_host: row.get(6)?,
+        label_fields: LabelFields {
+            reel: row.get(7)?,
+            day: row.get(8)?,
+            camera: row.get(9)?,
+            camera_model: row.get(10)?,
+        },
+    })
+}
 
 pub async fn create(
     conn: &Connection,
     job_id: String,
     drive: TargetDrive,
     destination_path: String,
+    origin_host: String,
+    label_fields: LabelFields,
 ) -> Result<()> {
+    let now = timestamp::now_utc();
     conn.call(move |c| {
         let tx = c.transaction()?;
 
         tx.execute(
-            "INSERT INTO targets (id, label, raw_size, adapter, source)
-             VALUES (?1, ?2, ?3, ?4, ?5)
+            "INSERT INTO targets (id, label, raw_size, adapter, source, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
              ON CONFLICT(id) DO UPDATE SET
                 label = excluded.label,
                 source = excluded.source,
-                created_at = CURRENT_TIMESTAMP",
+                created_at = excluded.created_at",
             params![
                 &drive.uuid,
                 &drive.label,
                 drive.raw_size,
                 "SIMULATED",
-                &drive.mount_path
+                &drive.mount_path,
+                &now
             ],
         )?;
 
         tx.execute(
-            "INSERT INTO jobs (id, target_id, destination_path)
-             VALUES (?1, ?2, ?3)",
-            params![&job_id, &drive.uuid, &destination_path],
+            "INSERT INTO jobs
+                 (id, target_id, destination_path, origin_host, reel, day, camera, camera_model,
+                  created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                &job_id,
+                &drive.uuid,
+                &destination_path,
+                &origin_host,
+                &label_fields.reel,
+                &label_fields.day,
+                &label_fields.camera,
+                &label_fields.camera_model,
+                &now
+            ],
         )?;
 
         let log_id = Uuid::now_v7().to_string();
         tx.execute(
-            "INSERT INTO job_status_log (id, job_id, status, description)
-             VALUES (?1, ?2, 'Ready', 'Job created waiting for processor')",
-            params![log_id, &job_id],
+            "INSERT INTO job_status_log (id, job_id, status, description, created_at)
+             VALUES (?1, ?2, 'Ready', 'Job created waiting for processor', ?3)",
+            params![log_id, &job_id, &now],
         )?;
+        outbox::enqueue(&tx, &log_id, &job_id, &now)?;
 
         tx.commit()?;
         Ok::<(), rusqlite::Error>(())
@@ -52,22 +98,12 @@ pub async fn create(
 
 pub async fn get(conn: &Connection, job_id: String) -> Result<Job> {
     conn.call(move |c| {
-        let mut stmt = c.prepare(
-            "SELECT j.id, j.target_id, j.destination_path, j.created_at,
-             COALESCE((SELECT status FROM job_status_log WHERE job_id = j.id ORDER BY created_at DESC LIMIT 1), 'Unknown') as status
-             FROM jobs j
-             WHERE j.id = ?1"
-        )?;
+        let mut stmt = c.prepare(&format!(
+            "SELECT {} FROM jobs j WHERE j.id = ?1",
+            JOB_COLUMNS
+        ))?;
 
-        stmt.query_row(params![job_id], |row| {
-            Ok(Job {
-                id: row.get(0)?,
-                target_id: row.get(1)?,
-                destination_path: row.get(2)?,
-                created_at: row.get(3)?,
-                status: row.get(4)?,
-            })
-        })
+        stmt.query_row(params![job_id], job_from_row)
     })
     .await
     .map_err(|e| anyhow!("Failed to get job: {}", e))
@@ -82,12 +118,44 @@ pub async fn update_status(
     duration_secs: Option<u64>,
 ) -> Result<()> {
     conn.call(move |c| {
+        let tx = c.transaction()?;
+        let log_id = Uuid::now_v7().to_string();
+        let now = timestamp::now_utc();
+        tx.execute(
+            "INSERT INTO job_status_log (id, job_id, status, description, total_bytes, duration_secs, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![log_id, job_id, status, description, total_bytes, duration_secs, now],
+        )?;
+        outbox::enqueue(&tx, &log_id, &job_id, &now)?;
+        tx.commit()?;
+        Ok::<(), rusqlite::Error>(())
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Append the `complete` entry, with the transfer's statistics.
+pub async fn record_completion(
+    conn: &Connection,
+    job_id: String,
+    description: String,
+    total_bytes: u64,
+    duration_secs: u64,
+    stats: TransferStats,
+) -> Result<()> {
+    let stats = serde_json::to_string(&stats)?;
+    conn.call(move |c| {
+        let tx = c.transaction()?;
         let log_id = Uuid::now_v7().to_string();
-        c.execute(
-            "INSERT INTO job_status_log (id, job_id, status, description, total_bytes, duration_secs)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            params![log_id, job_id, status, description, total_bytes, duration_secs],
+        let now = timestamp::now_utc();
+        tx.execute(
+            "INSERT INTO job_status_log (id, job_id, status, description, total_bytes, duration_secs, stats, created_at)
+             VALUES (?1, ?2, 'complete', ?3, ?4, ?5, ?6, ?7)",
+            params![log_id, job_id, description, total_bytes, duration_secs, stats, now],
         )?;
+        outbox::enqueue(&tx, &log_id, &job_id, &now)?;
+        tx.commit()?;
         Ok::<(), rusqlite::Error>(())
     })
     .await?;
@@ -95,6 +163,172 @@ pub async fn update_status(
     Ok(())
 }
 
+/// Most recent job for a device, if it failed part-way with files already
+/// copied and so can be resumed.
+pub async fn find_resumable(conn: &Connection, target_id: String) -> Result<Option<Job>> {
+    conn.call(move |c| {
+        let mut stmt = c.prepare(&format!(
+            "SELECT {} FROM jobs j
+             WHERE j.target_id = ?1
+             ORDER BY j.created_at DESC
+             LIMIT 1",
+            JOB_COLUMNS
+        ))?;
+
+        let job = match stmt.query_row(params![&target_id], job_from_row) {
+            Ok(job) => job,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let has_files: bool = c.query_row(
+            "SELECT EXISTS (SELECT 1 FROM job_files WHERE job_id = ?1)",
+            params![&job.id],
+            |row| row.get(0),
+        )?;
+
+        let resumable = job.status == "failed" && has_files && job.destination_path.is_some();
+        Ok::<_, rusqlite::Error>(resumable.then_some(job))
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to look up resumable job: {}", e))
+}
+
+/// The device's most recent completed backup that is still in the backup
+/// directory (not moved to an archive drive).
+pub async fn latest_backup(conn: &Connection, target_id: String) -> Result<Option<Job>> {
+    conn.call(move |c| {
+        let mut stmt = c.prepare(&format!(
+            "SELECT {} FROM jobs j
+             WHERE j.target_id = ?1
+               AND j.destination_path IS NOT NULL
+               AND status = 'complete'
+               AND archive_label IS NULL
+             ORDER BY j.created_at DESC
+             LIMIT 1",
+            JOB_COLUMNS
+        ))?;
+
+        match stmt.query_row(params![&target_id], job_from_row) {
+            Ok(job) => Ok(Some(job)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to look up latest backup: {}", e))
+}
+
+/// Mark jobs left unfinished by a crash or restart as failed, and queued
+/// ones, which only waited in memory, as cancelled. Returns how many were
+/// updated.
+pub async fn fail_interrupted(conn: &Connection) -> Result<usize> {
+    let now = timestamp::now_utc();
+    conn.call(move |c| {
+        let tx = c.transaction()?;
+        let jobs = {
+            let mut stmt = tx.prepare(&format!(
+                "SELECT {} FROM jobs j WHERE status NOT IN ('complete', 'failed', 'cancelled', 'skipped')",
+                JOB_COLUMNS
+            ))?;
+            stmt.query_map([], job_from_row)?
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        for job in &jobs {
+            let (status, description) = interrupted_status(&job.status);
+            let log_id = Uuid::now_v7().to_string();
+            tx.execute(
+                "INSERT INTO job_status_log (id, job_id, status, description, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![log_id, job.id, status, description, &now],
+            )?;
+            outbox::enqueue(&tx, &log_id, &job.id, &now)?;
+        }
+
+        tx.commit()?;
+        Ok::<_, rusqlite::Error>(jobs.len())
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to mark interrupted jobs: {}", e))
+}
+
+/// Status and description a job left in `status` by a stopped daemon is
+/// closed with. A queued job hadn't started; its card is queued again as a
+/// new job if it is still connected when the daemon comes back.
+pub(crate) fn interrupted_status(status: &str) -> (&'static str, &'static str) {
+    if status == "queued" {
+        (
+            "cancelled",
+            "Cancelled: daemon stopped while the job was queued",
+        )
+    } else {
+        (
+            "failed",
+            "Interrupted: daemon stopped before the job finished",
+        )
+    }
+}
+
+/// Attach notices (anomalies, report findings) to a job.
+pub async fn add_notices(conn: &Connection, job_id: String, notices: Vec<JobNotice>) -> Result<()> {
+    if notices.is_empty() {
+        return Ok(());
+    }
+
+    conn.call(move |c| {
+        let tx = c.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO job_notices (id, job_id, kind, path, message, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            let now = timestamp::now_utc();
+            for notice in &notices {
+                let notice_id = Uuid::now_v7().to_string();
+                stmt.execute(params![
+                    notice_id,
+                    &job_id,
+                    &notice.kind,
+                    &notice.path,
+                    &notice.message,
+                    &now
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok::<(), rusqlite::Error>(())
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Get all notices recorded for a job, oldest first.
+pub async fn list_notices(conn: &Connection, job_id: String) -> Result<Vec<JobNotice>> {
+    conn.call(move |c| query_notices(c, &job_id))
+        .await
+        .map_err(|e| anyhow!("Failed to list job notices: {}", e))
+}
+
+fn query_notices(c: &rusqlite::Connection, job_id: &str) -> rusqlite::Result<Vec<JobNotice>> {
+    let mut stmt = c.prepare(
+        "SELECT kind, path, message
+         FROM job_notices
+         WHERE job_id = ?1
+         ORDER BY id ASC",
+    )?;
+
+    stmt.query_map(params![job_id], |row| {
+        Ok(JobNotice {
+            kind: row.get(0)?,
+            path: row.get(1)?,
+            message: row.get(2)?,
+        })
+    })?
+    .collect()
+}
+
 /// List jobs with optional filtering and pagination.
 /// Returns jobs ordered by creation date (newest first).
 pub async fn list(
@@ -104,14 +338,15 @@ pub async fn list(
     status_filter: Option<String>,
 ) -> Result<Vec<Job>> {
     conn.call(move |c| {
-        let (sql, params_vec): (&str, Vec<Box<dyn rusqlite::ToSql>>) = if let Some(ref status) = status_filter {
+        let (sql, params_vec): (String, Vec<Box<dyn rusqlite::ToSql>>) = if let Some(ref status) = status_filter {
             (
-                "SELECT j.id, j.target_id, j.destination_path, j.created_at,
-                 COALESCE((SELECT status FROM job_status_log WHERE job_id = j.id ORDER BY created_at DESC LIMIT 1), 'Unknown') as status
-                 FROM jobs j
-                 WHERE (SELECT status FROM job_status_log WHERE job_id = j.id ORDER BY created_at DESC LIMIT 1) = ?1
-                 ORDER BY j.created_at DESC
-                 LIMIT ?2 OFFSET ?3",
+                format!(
+                    "SELECT {} FROM jobs j
+                     WHERE (SELECT status FROM job_status_log WHERE job_id = j.id ORDER BY created_at DESC LIMIT 1) = ?1
+                     ORDER BY j.created_at DESC
+                     LIMIT ?2 OFFSET ?3",
+                    JOB_COLUMNS
+                ),
                 vec![
                     Box::new(status.clone()) as Box<dyn rusqlite::ToSql>,
                     Box::new(limit),
@@ -120,11 +355,12 @@ pub async fn list(
             )
         } else {
             (
-                "SELECT j.id, j.target_id, j.destination_path, j.created_at,
-                 COALESCE((SELECT status FROM job_status_log WHERE job_id = j.id ORDER BY created_at DESC LIMIT 1), 'Unknown') as status
-                 FROM jobs j
-                 ORDER BY j.created_at DESC
-                 LIMIT ?1 OFFSET ?2",
+                format!(
+                    "SELECT {} FROM jobs j
+                     ORDER BY j.created_at DESC
+                     LIMIT ?1 OFFSET ?2",
+                    JOB_COLUMNS
+                ),
                 vec![
                     Box::new(limit) as Box<dyn rusqlite::ToSql>,
                     Box::new(offset),
@@ -132,19 +368,11 @@ pub async fn list(
             )
         };
 
-        let mut stmt = c.prepare(sql)?;
+        let mut stmt = c.prepare(&sql)?;
         let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
 
         let jobs = stmt
-            .query_map(params_refs.as_slice(), |row| {
-                Ok(Job {
-                    id: row.get(0)?,
-                    target_id: row.get(1)?,
-                    destination_path: row.get(2)?,
-                    created_at: row.get(3)?,
-                    status: row.get(4)?,
-                })
-            })?
+            .query_map(params_refs.as_slice(), job_from_row)?
             .collect::<Result<Vec<_>, _>>()?;
 
         Ok::<_, rusqlite::Error>(jobs)
@@ -153,33 +381,145 @@ pub async fn list(
     .map_err(|e| anyhow!("Failed to list jobs: {}", e))
 }
 
+/// Insert a job exported from another daemon, keeping its IDs and
+/// timestamps. Nothing is added to the outbox: the station that ran the job
+/// reports it to the hub itself.
+pub async fn import(conn: &Connection, job: JobWithHistory, files: Vec<FileHash>) -> Result<()> {
+    let JobWithHistory {
+        job,
+        history,
+        notices,
+    } = job;
+    let job_id = job.id.clone();
+    let history = history
+        .into_iter()
+        .map(|entry| {
+            let stats = entry
+                .stats
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?;
+            Ok((entry, stats))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let imported = conn
+        .call(move |c| {
+            let tx = c.transaction()?;
+
+            let exists: bool = tx.query_row(
+                "SELECT EXISTS(SELECT 1 FROM jobs WHERE id = ?1)",
+                params![&job.id],
+                |row| row.get(0),
+            )?;
+            if exists {
+                return Ok(false);
+            }
+
+            // The card itself was never seen here; keep any existing record
+            tx.execute(
+                "INSERT INTO targets (id, label, raw_size, adapter, source, created_at)
+                 VALUES (?1, ?1, 0, 'IMPORTED', '', ?2)
+                 ON CONFLICT(id) DO NOTHING",
+                params![&job.target_id, &job.created_at],
+            )?;
+
+            tx.execute(
+                "INSERT INTO jobs
+                     (id, target_id, destination_path, origin_host, reel, day, camera,
+                      camera_model, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    &job.id,
+                    &job.target_id,
+                    &job.destination_path,
+                    &job.origin_host,
+                    &job.label_fields.reel,
+                    &job.label_fields.day,
+                    &job.label_fields.camera,
+                    &job.label_fields.camera_model,
+                    &job.created_at
+                ],
+            )?;
+
+            {
+                let mut stmt = tx.prepare(
+                    "INSERT INTO job_status_log
+                         (id, job_id, status, description, total_bytes, duration_secs, stats, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                )?;
+                for (entry, stats) in &history {
+                    stmt.execute(params![
+                        &entry.id,
+                        &job.id,
+                        &entry.status,
+                        &entry.description,
+                        entry.total_bytes,
+                        entry.duration_secs,
+                        stats,
+                        &entry.created_at
+                    ])?;
+                }
+
+                let mut stmt = tx.prepare(
+                    "INSERT INTO job_notices (id, job_id, kind, path, message, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                )?;
+                for notice in &notices {
+                    stmt.execute(params![
+                        Uuid::now_v7().to_string(),
+                        &job.id,
+                        &notice.kind,
+                        &notice.path,
+                        &notice.message,
+                        &job.created_at
+                    ])?;
+                }
+
+                let mut stmt = tx.prepare(
+                    "INSERT INTO job_files (job_id, relative_path, size, hash, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                )?;
+                for file in &files {
+                    stmt.execute(params![
+                        &job.id,
+                        file.relative_path.to_string_lossy(),
+                        file.size,
+                        &file.hash[..],
+                        &job.created_at
+                    ])?;
+                }
+            }
+
+            tx.commit()?;
+            Ok::<bool, rusqlite::Error>(true)
+        })
+        .await
+        .map_err(|e| anyhow!("Failed to import job: {}", e))?;
+
+    if !imported {
+        return Err(anyhow!("Job {} already exists", job_id));
+    }
+    Ok(())
+}
+
 /// Get a job with its full status history.
 pub async fn get_with_history(conn: &Connection, job_id: String) -> Result<JobWithHistory> {
     conn.call(move |c| {
         // First get the job
         let job = {
-            let mut stmt = c.prepare(
-                "SELECT j.id, j.target_id, j.destination_path, j.created_at,
-                 COALESCE((SELECT status FROM job_status_log WHERE job_id = j.id ORDER BY created_at DESC LIMIT 1), 'Unknown') as status
-                 FROM jobs j
-                 WHERE j.id = ?1",
-            )?;
+            let mut stmt = c.prepare(&format!(
+                "SELECT {} FROM jobs j WHERE j.id = ?1",
+                JOB_COLUMNS
+            ))?;
 
-            stmt.query_row(params![&job_id], |row| {
-                Ok(Job {
-                    id: row.get(0)?,
-                    target_id: row.get(1)?,
-                    destination_path: row.get(2)?,
-                    created_at: row.get(3)?,
-                    status: row.get(4)?,
-                })
-            })?
+            stmt.query_row(params![&job_id], job_from_row)?
         };
 
         // Then get the status history
         let history = {
             let mut stmt = c.prepare(
-                "SELECT id, status, description, total_bytes, duration_secs, created_at
+                "SELECT id, status, description, total_bytes, duration_secs, stats, created_at
                  FROM job_status_log
                  WHERE job_id = ?1
                  ORDER BY created_at ASC",
@@ -192,13 +532,24 @@ pub async fn get_with_history(conn: &Connection, job_id: String) -> Result<JobWi
                     description: row.get(2)?,
                     total_bytes: row.get(3)?,
                     duration_secs: row.get(4)?,
-                    created_at: row.get(5)?,
+                    // Unreadable stats only lose the detail, not the entry
+                    stats: row
+                        .get::<_, Option<String>>(5)?
+                        .and_then(|json| serde_json::from_str(&json).ok()),
+                    created_at: row.get(6)?,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?
         };
 
-        Ok::<_, rusqlite::Error>(JobWithHistory { job, history })
+        // And any notices raised while processing the job
+        let notices = query_notices(c, &job_id)?;
+
+        Ok::<_, rusqlite::Error>(JobWithHistory {
+            job,
+            history,
+            notices,
+        })
     })
     .await
     .map_err(|e| anyhow!("Failed to get job with history: {}", e))
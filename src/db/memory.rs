@@ -0,0 +1,1216 @@
+//! In-memory `Store` with an optional JSONL journal, for constrained
+//! installs where SQLite's data directory can't be written (read-only root
+//! filesystems, appliances).
+//!
+//! Every change is applied to in-memory tables as an `Event`. With a journal
+//! path configured, each event is also appended to that file as one JSON
+//! line and the file is replayed at startup; without one, history lasts only
+//! until the daemon stops. Queries mirror the SQLite backend's semantics.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::core::label_fields::LabelFields;
+use crate::core::timestamp;
+use crate::core::transfer_engine::{FileHash, TransferStats};
+use crate::core::{ArchiveVolume, Job, JobNotice, JobStatusEntry, JobWithHistory, TargetDrive};
+use crate::db::catalog::{CatalogEntry, DeviceFingerprint};
+use crate::db::files::{DuplicateFile, ProbedVideo};
+use crate::db::jobs;
+use crate::db::outbox::{self, OUTBOX_LIMIT, OutboxEvent};
+use crate::db::scrubs::ScrubResult;
+use crate::db::store::Store;
+
+/// One change to the store, as journaled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event {
+    JobCreated {
+        job_id: String,
+        target_id: String,
+        destination_path: String,
+        origin_host: String,
+        #[serde(default)]
+        label_fields: LabelFields,
+        created_at: String,
+    },
+    StatusAdded {
+        job_id: String,
+        entry: JobStatusEntry,
+    },
+    NoticesAdded {
+        job_id: String,
+        notices: Vec<JobNotice>,
+    },
+    JobImported {
+        job: JobWithHistory,
+        files: Vec<FileHash>,
+    },
+    JobArchived {
+        job_id: String,
+        volume_uuid: String,
+        volume_label: String,
+        archive_path: String,
+    },
+    VolumeConnected {
+        uuid: String,
+        label: String,
+        capacity: u64,
+        at: String,
+    },
+    VolumeDisconnected {
+        uuid: String,
+        at: String,
+    },
+    VolumesReset,
+    CatalogUpdated {
+        target_id: String,
+        job_id: String,
+        files: Vec<FileHash>,
+    },
+    SkippedRecorded {
+        job_id: String,
+        skipped: Vec<CatalogEntry>,
+    },
+    FingerprintRecorded {
+        target_id: String,
+        fingerprint: DeviceFingerprint,
+    },
+    FileRecorded {
+        job_id: String,
+        file: FileHash,
+    },
+    FilesCleared {
+        job_id: String,
+    },
+    OutboxAcked {
+        ids: Vec<String>,
+    },
+    ScrubRecorded {
+        result: ScrubResult,
+    },
+    ProbesRecorded {
+        job_id: String,
+        probes: Vec<ProbedVideo>,
+    },
+}
+
+struct JobRecord {
+    id: String,
+    target_id: String,
+    destination_path: Option<String>,
+    origin_host: Option<String>,
+    label_fields: LabelFields,
+    created_at: String,
+    history: Vec<JobStatusEntry>,
+    notices: Vec<JobNotice>,
+}
+
+struct ArchivedJob {
+    volume_uuid: String,
+    volume_label: String,
+}
+
+struct VolumeRecord {
+    label: String,
+    capacity: Option<u64>,
+    connected: bool,
+    last_seen_at: String,
+}
+
+#[derive(Default)]
+struct Tables {
+    /// Jobs in creation order
+    jobs: Vec<JobRecord>,
+    archives: HashMap<String, ArchivedJob>,
+    volumes: BTreeMap<String, VolumeRecord>,
+    device_files: HashMap<String, BTreeMap<String, CatalogEntry>>,
+    skipped: HashMap<String, Vec<CatalogEntry>>,
+    fingerprints: HashMap<String, DeviceFingerprint>,
+    job_files: HashMap<String, BTreeMap<String, FileHash>>,
+    /// Job changes not yet acknowledged by the hub, oldest first
+    outbox: VecDeque<OutboxEvent>,
+    /// Scrubs in the order they were recorded
+    scrubs: Vec<ScrubResult>,
+    video_probes: HashMap<String, BTreeMap<String, ProbedVideo>>,
+}
+
+impl Tables {
+    fn apply(&mut self, event: Event) {
+        match event {
+            Event::JobCreated {
+                job_id,
+                target_id,
+                destination_path,
+                origin_host,
+                label_fields,
+                created_at,
+            } => self.jobs.push(JobRecord {
+                id: job_id,
+                target_id,
+                destination_path: Some(destination_path),
+                origin_host: Some(origin_host),
+                label_fields,
+                created_at,
+                history: Vec::new(),
+                notices: Vec::new(),
+            }),
+            Event::StatusAdded { job_id, entry } => {
+                let (event_id, at) = (entry.id.clone(), entry.created_at.clone());
+                if let Some(job) = self.job_mut(&job_id) {
+                    job.history.push(entry);
+                    self.enqueue(event_id, &job_id, at);
+                }
+            }
+            Event::NoticesAdded { job_id, notices } => {
+                if let Some(job) = self.job_mut(&job_id) {
+                    job.notices.extend(notices);
+                }
+            }
+            // Not enqueued: the station that ran the job reports it
+            Event::JobImported { job, files } => {
+                let JobWithHistory {
+                    job,
+                    history,
+                    notices,
+                } = job;
+                self.job_files.insert(
+                    job.id.clone(),
+                    files
+                        .into_iter()
+                        .map(|f| (f.relative_path.to_string_lossy().to_string(), f))
+                        .collect(),
+                );
+                self.jobs.push(JobRecord {
+                    id: job.id,
+                    target_id: job.target_id,
+                    destination_path: job.destination_path,
+                    origin_host: job.origin_host,
+                    label_fields: job.label_fields,
+                    created_at: job.created_at,
+                    history,
+                    notices,
+                });
+            }
+            Event::JobArchived {
+                job_id,
+                volume_uuid,
+                volume_label,
+                archive_path,
+            } => {
+                if let Some(job) = self.job_mut(&job_id) {
+                    job.destination_path = Some(archive_path);
+                }
+                self.archives.insert(
+                    job_id.clone(),
+                    ArchivedJob {
+                        volume_uuid,
+                        volume_label,
+                    },
+                );
+                self.enqueue(
+                    outbox::archived_event_id(&job_id),
+                    &job_id,
+                    timestamp::now_utc(),
+                );
+            }
+            Event::VolumeConnected {
+                uuid,
+                label,
+                capacity,
+                at,
+            } => {
+                self.volumes.insert(
+                    uuid,
+                    VolumeRecord {
+                        label,
+                        capacity: Some(capacity),
+                        connected: true,
+                        last_seen_at: at,
+                    },
+                );
+            }
+            Event::VolumeDisconnected { uuid, at } => {
+                if let Some(volume) = self.volumes.get_mut(&uuid)
+                    && volume.connected
+                {
+                    volume.connected = false;
+                    volume.last_seen_at = at;
+                }
+            }
+            Event::VolumesReset => {
+                for volume in self.volumes.values_mut() {
+                    volume.connected = false;
+                }
+            }
+            Event::CatalogUpdated {
+                target_id,
+                job_id,
+                files,
+            } => {
+                let catalog = self.device_files.entry(target_id).or_default();
+                for file in files {
+                    catalog.insert(
+                        file.relative_path.to_string_lossy().to_string(),
+                        CatalogEntry {
+                            file,
+                            job_id: job_id.clone(),
+                        },
+                    );
+                }
+            }
+            Event::SkippedRecorded { job_id, skipped } => {
+                self.skipped.entry(job_id).or_default().extend(skipped);
+            }
+            Event::FingerprintRecorded {
+                target_id,
+                fingerprint,
+            } => {
+                self.fingerprints.insert(target_id, fingerprint);
+            }
+            Event::FileRecorded { job_id, file } => {
+                self.job_files
+                    .entry(job_id)
+                    .or_default()
+                    .insert(file.relative_path.to_string_lossy().to_string(), file);
+            }
+            Event::FilesCleared { job_id } => {
+                self.job_files.remove(&job_id);
+            }
+            Event::OutboxAcked { ids } => {
+                self.outbox.retain(|e| !ids.contains(&e.id));
+            }
+            Event::ScrubRecorded { result } => self.scrubs.push(result),
+            Event::ProbesRecorded { job_id, probes } => {
+                let recorded = self.video_probes.entry(job_id).or_default();
+                for probe in probes {
+                    recorded.insert(probe.relative_path.to_string_lossy().to_string(), probe);
+                }
+            }
+        }
+    }
+
+    /// Queue a snapshot of the job as it is now for the hub.
+    fn enqueue(&mut self, id: String, job_id: &str, created_at: String) {
+        let Some(job) = self.record(job_id).map(|r| self.job(r)) else {
+            return;
+        };
+        self.outbox.push_back(OutboxEvent {
+            id,
+            job,
+            created_at,
+        });
+        while self.outbox.len() > OUTBOX_LIMIT {
+            self.outbox.pop_front();
+        }
+    }
+
+    fn job_mut(&mut self, job_id: &str) -> Option<&mut JobRecord> {
+        self.jobs.iter_mut().find(|j| j.id == job_id)
+    }
+
+    fn record(&self, job_id: &str) -> Option<&JobRecord> {
+        self.jobs.iter().find(|j| j.id == job_id)
+    }
+
+    fn status(record: &JobRecord) -> &str {
+        record
+            .history
+            .last()
+            .map(|e| e.status.as_str())
+            .unwrap_or("Unknown")
+    }
+
+    fn job(&self, record: &JobRecord) -> Job {
+        Job {
+            id: record.id.clone(),
+            target_id: record.target_id.clone(),
+            destination_path: record.destination_path.clone(),
+            created_at: record.created_at.clone(),
+            status: Self::status(record).to_string(),
+            archive_label: self
+                .archives
+                .get(&record.id)
+                .map(|a| a.volume_label.clone()),
+            origin_host: record.origin_host.clone(),
+            label_fields: record.label_fields.clone(),
+        }
+    }
+
+    /// Jobs newest first
+    fn newest_first(&self) -> impl Iterator<Item = &JobRecord> {
+        let mut jobs: Vec<&JobRecord> = self.jobs.iter().collect();
+        jobs.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        jobs.into_iter()
+    }
+
+    /// Size of a job's backup as recorded on its `complete` status entry
+    fn backup_bytes(record: &JobRecord) -> u64 {
+        record
+            .history
+            .iter()
+            .rev()
+            .find_map(|e| e.total_bytes)
+            .unwrap_or(0)
+    }
+
+    fn archivable(&self, older_than_days: u32) -> Vec<&JobRecord> {
+        let cutoff = Utc::now() - Duration::days(older_than_days as i64);
+        let mut jobs: Vec<&JobRecord> = self
+            .jobs
+            .iter()
+            .filter(|j| {
+                Self::status(j) == "complete"
+                    && timestamp::parse(&j.created_at).is_some_and(|at| at <= cutoff)
+                    && j.destination_path.is_some()
+                    && !self.archives.contains_key(&j.id)
+            })
+            .collect();
+        jobs.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+        jobs
+    }
+
+    /// Completed backups older than `older_than_days` that can be read now,
+    /// never-scrubbed first, then longest since their last scrub
+    fn scrub_candidates(&self, older_than_days: u32) -> Vec<&JobRecord> {
+        let cutoff = Utc::now() - Duration::days(older_than_days as i64);
+        let last_scrub = |job_id: &str| {
+            self.scrubs
+                .iter()
+                .filter(|s| s.job_id == job_id)
+                .map(|s| s.finished_at.as_str())
+                .max()
+        };
+        let mut jobs: Vec<&JobRecord> = self
+            .jobs
+            .iter()
+            .filter(|j| {
+                Self::status(j) == "complete"
+                    && timestamp::parse(&j.created_at).is_some_and(|at| at <= cutoff)
+                    && j.destination_path.is_some()
+                    && self.archives.get(&j.id).is_none_or(|a| {
+                        self.volumes.get(&a.volume_uuid).is_none_or(|v| v.connected)
+                    })
+            })
+            .collect();
+        // None sorts before any timestamp
+        jobs.sort_by(|a, b| {
+            (last_scrub(&a.id), &a.created_at).cmp(&(last_scrub(&b.id), &b.created_at))
+        });
+        jobs
+    }
+
+    fn volume(&self, uuid: &str) -> Option<ArchiveVolume> {
+        self.volumes.get(uuid).map(|v| ArchiveVolume {
+            uuid: uuid.to_string(),
+            label: v.label.clone(),
+            capacity: v.capacity,
+            connected: v.connected,
+            last_seen_at: v.last_seen_at.clone(),
+            job_count: self
+                .archives
+                .values()
+                .filter(|a| a.volume_uuid == uuid)
+                .count() as u64,
+        })
+    }
+}
+
+struct Inner {
+    tables: Tables,
+    journal: Option<File>,
+}
+
+pub struct MemoryStore {
+    inner: Mutex<Inner>,
+}
+
+impl MemoryStore {
+    /// Open the store, replaying `journal` if it exists and appending to it
+    /// from then on. `None` keeps everything in memory only.
+    pub fn open(journal: Option<&Path>) -> Result<Self> {
+        let mut tables = Tables::default();
+
+        let journal = match journal {
+            None => None,
+            Some(path) => {
+                if path.exists() {
+                    let file = File::open(path)
+                        .with_context(|| format!("Failed to open journal {}", path.display()))?;
+                    for (number, line) in BufReader::new(file).lines().enumerate() {
+                        let line = line?;
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+                        match serde_json::from_str(&line) {
+                            Ok(event) => tables.apply(event),
+                            // A torn final line from a crash mid-write
+                            Err(e) => {
+                                warn!(line = number + 1, error = %e, "Skipping unreadable journal entry")
+                            }
+                        }
+                    }
+                }
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("Failed to open journal {}", path.display()))?;
+                Some(file)
+            }
+        };
+
+        Ok(Self {
+            inner: Mutex::new(Inner { tables, journal }),
+        })
+    }
+
+    /// Journal and apply a batch of events.
+    fn commit(&self, events: Vec<Event>) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(journal) = &mut inner.journal {
+            let mut lines = String::new();
+            for event in &events {
+                lines.push_str(&serde_json::to_string(event)?);
+                lines.push('\n');
+            }
+            journal
+                .write_all(lines.as_bytes())
+                .map_err(|e| anyhow!("Failed to write journal: {}", e))?;
+        }
+        for event in events {
+            inner.tables.apply(event);
+        }
+        Ok(())
+    }
+
+    fn read<T>(&self, query: impl FnOnce(&Tables) -> T) -> T {
+        query(&self.inner.lock().unwrap().tables)
+    }
+}
+
+fn status_entry(
+    status: &str,
+    description: Option<String>,
+    total_bytes: Option<u64>,
+    duration_secs: Option<u64>,
+) -> JobStatusEntry {
+    JobStatusEntry {
+        id: Uuid::now_v7().to_string(),
+        status: status.to_string(),
+        description,
+        total_bytes,
+        duration_secs,
+        stats: None,
+        created_at: timestamp::now_utc(),
+    }
+}
+
+#[async_trait]
+impl Store for MemoryStore {
+    async fn create_job(
+        &self,
+        job_id: String,
+        drive: TargetDrive,
+        destination_path: String,
+        origin_host: String,
+        label_fields: LabelFields,
+    ) -> Result<()> {
+        let entry = status_entry(
+            "Ready",
+            Some("Job created waiting for processor".to_string()),
+            None,
+            None,
+        );
+        self.commit(vec![
+            Event::JobCreated {
+                job_id: job_id.clone(),
+                target_id: drive.uuid,
+                destination_path,
+                origin_host,
+                label_fields,
+                created_at: entry.created_at.clone(),
+            },
+            Event::StatusAdded { job_id, entry },
+        ])
+    }
+
+    async fn update_status(
+        &self,
+        job_id: String,
+        status: String,
+        description: Option<String>,
+        total_bytes: Option<u64>,
+        duration_secs: Option<u64>,
+    ) -> Result<()> {
+        let entry = status_entry(&status, description, total_bytes, duration_secs);
+        self.commit(vec![Event::StatusAdded { job_id, entry }])
+    }
+
+    async fn record_completion(
+        &self,
+        job_id: String,
+        description: String,
+        total_bytes: u64,
+        duration_secs: u64,
+        stats: TransferStats,
+    ) -> Result<()> {
+        let entry = JobStatusEntry {
+            stats: Some(stats),
+            ..status_entry(
+                "complete",
+                Some(description),
+                Some(total_bytes),
+                Some(duration_secs),
+            )
+        };
+        self.commit(vec![Event::StatusAdded { job_id, entry }])
+    }
+
+    async fn find_resumable(&self, target_id: String) -> Result<Option<Job>> {
+        Ok(self.read(|t| {
+            let record = t.newest_first().find(|j| j.target_id == target_id)?;
+            let has_files = t.job_files.get(&record.id).is_some_and(|f| !f.is_empty());
+            let resumable = Tables::status(record) == "failed"
+                && has_files
+                && record.destination_path.is_some();
+            resumable.then(|| t.job(record))
+        }))
+    }
+
+    async fn latest_backup(&self, target_id: String) -> Result<Option<Job>> {
+        Ok(self.read(|t| {
+            t.newest_first()
+                .find(|j| {
+                    j.target_id == target_id
+                        && j.destination_path.is_some()
+                        && Tables::status(j) == "complete"
+                        && !t.archives.contains_key(&j.id)
+                })
+                .map(|j| t.job(j))
+        }))
+    }
+
+    async fn fail_interrupted(&self) -> Result<usize> {
+        let jobs: Vec<(String, &'static str, &'static str)> = self.read(|t| {
+            t.jobs
+                .iter()
+                .filter(|j| {
+                    !matches!(
+                        Tables::status(j),
+                        "complete" | "failed" | "cancelled" | "skipped"
+                    )
+                })
+                .map(|j| {
+                    let (status, description) = jobs::interrupted_status(Tables::status(j));
+                    (j.id.clone(), status, description)
+                })
+                .collect()
+        });
+        let count = jobs.len();
+        let events = jobs
+            .into_iter()
+            .map(|(job_id, status, description)| Event::StatusAdded {
+                job_id,
+                entry: status_entry(status, Some(description.to_string()), None, None),
+            })
+            .collect();
+        self.commit(events)?;
+        Ok(count)
+    }
+
+    async fn import_job(&self, job: JobWithHistory, files: Vec<FileHash>) -> Result<()> {
+        if self.read(|t| t.record(&job.job.id).is_some()) {
+            return Err(anyhow!("Job {} already exists", job.job.id));
+        }
+        self.commit(vec![Event::JobImported { job, files }])
+    }
+
+    async fn add_notices(&self, job_id: String, notices: Vec<JobNotice>) -> Result<()> {
+        if notices.is_empty() {
+            return Ok(());
+        }
+        self.commit(vec![Event::NoticesAdded { job_id, notices }])
+    }
+
+    async fn list_notices(&self, job_id: String) -> Result<Vec<JobNotice>> {
+        Ok(self.read(|t| {
+            t.record(&job_id)
+                .map(|j| j.notices.clone())
+                .unwrap_or_default()
+        }))
+    }
+
+    async fn list_jobs(
+        &self,
+        limit: u32,
+        offset: u32,
+        status_filter: Option<String>,
+    ) -> Result<Vec<Job>> {
+        Ok(self.read(|t| {
+            t.newest_first()
+                .filter(|j| {
+                    status_filter
+                        .as_deref()
+                        .is_none_or(|status| Tables::status(j) == status)
+                })
+                .skip(offset as usize)
+                .take(limit as usize)
+                .map(|j| t.job(j))
+                .collect()
+        }))
+    }
+
+    async fn get_with_history(&self, job_id: String) -> Result<JobWithHistory> {
+        self.read(|t| {
+            let record = t
+                .record(&job_id)
+                .ok_or_else(|| anyhow!("Failed to get job with history: no job {}", job_id))?;
+            Ok(JobWithHistory {
+                job: t.job(record),
+                history: record.history.clone(),
+                notices: record.notices.clone(),
+            })
+        })
+    }
+
+    async fn list_archivable(&self, older_than_days: u32) -> Result<Vec<Job>> {
+        Ok(self.read(|t| {
+            t.archivable(older_than_days)
+                .into_iter()
+                .map(|j| t.job(j))
+                .collect()
+        }))
+    }
+
+    async fn archivable_bytes(&self, older_than_days: u32) -> Result<(usize, u64)> {
+        Ok(self.read(|t| {
+            let jobs = t.archivable(older_than_days);
+            (
+                jobs.len(),
+                jobs.iter().map(|j| Tables::backup_bytes(j)).sum(),
+            )
+        }))
+    }
+
+    async fn stored_bytes(&self, uuid: String) -> Result<u64> {
+        Ok(self.read(|t| {
+            t.jobs
+                .iter()
+                .filter(|j| t.archives.get(&j.id).is_some_and(|a| a.volume_uuid == uuid))
+                .map(Tables::backup_bytes)
+                .sum()
+        }))
+    }
+
+    async fn record_archive(
+        &self,
+        job_id: String,
+        volume_uuid: String,
+        volume_label: String,
+        archive_path: String,
+    ) -> Result<()> {
+        self.commit(vec![Event::JobArchived {
+            job_id,
+            volume_uuid,
+            volume_label,
+            archive_path,
+        }])
+    }
+
+    async fn mark_connected(&self, uuid: String, label: String, capacity: u64) -> Result<()> {
+        self.commit(vec![Event::VolumeConnected {
+            uuid,
+            label,
+            capacity,
+            at: timestamp::now_utc(),
+        }])
+    }
+
+    async fn mark_disconnected(&self, uuid: String) -> Result<()> {
+        self.commit(vec![Event::VolumeDisconnected {
+            uuid,
+            at: timestamp::now_utc(),
+        }])
+    }
+
+    async fn reset_connected(&self) -> Result<()> {
+        self.commit(vec![Event::VolumesReset])
+    }
+
+    async fn list_volumes(&self) -> Result<Vec<ArchiveVolume>> {
+        Ok(self.read(|t| {
+            let mut volumes: Vec<ArchiveVolume> =
+                t.volumes.keys().filter_map(|uuid| t.volume(uuid)).collect();
+            volumes.sort_by(|a, b| a.label.cmp(&b.label));
+            volumes
+        }))
+    }
+
+    async fn locate_archive(&self, job_id: String) -> Result<Option<ArchiveVolume>> {
+        Ok(self.read(|t| {
+            t.archives
+                .get(&job_id)
+                .and_then(|a| t.volume(&a.volume_uuid))
+        }))
+    }
+
+    async fn list_archive_contents(&self, uuid: String) -> Result<Vec<Job>> {
+        Ok(self.read(|t| {
+            let mut jobs: Vec<&JobRecord> = t
+                .jobs
+                .iter()
+                .filter(|j| t.archives.get(&j.id).is_some_and(|a| a.volume_uuid == uuid))
+                .collect();
+            jobs.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+            jobs.into_iter().map(|j| t.job(j)).collect()
+        }))
+    }
+
+    async fn catalog_lookup(&self, target_id: String) -> Result<Vec<CatalogEntry>> {
+        Ok(self.read(|t| {
+            t.device_files
+                .get(&target_id)
+                .map(|files| files.values().cloned().collect())
+                .unwrap_or_default()
+        }))
+    }
+
+    async fn catalog_update(
+        &self,
+        target_id: String,
+        job_id: String,
+        files: Vec<FileHash>,
+    ) -> Result<()> {
+        self.commit(vec![Event::CatalogUpdated {
+            target_id,
+            job_id,
+            files,
+        }])
+    }
+
+    async fn record_skipped(&self, job_id: String, skipped: Vec<CatalogEntry>) -> Result<()> {
+        if skipped.is_empty() {
+            return Ok(());
+        }
+        self.commit(vec![Event::SkippedRecorded { job_id, skipped }])
+    }
+
+    async fn fingerprint_lookup(&self, target_id: String) -> Result<Option<DeviceFingerprint>> {
+        Ok(self.read(|t| t.fingerprints.get(&target_id).cloned()))
+    }
+
+    async fn fingerprint_update(
+        &self,
+        target_id: String,
+        fingerprint: DeviceFingerprint,
+    ) -> Result<()> {
+        self.commit(vec![Event::FingerprintRecorded {
+            target_id,
+            fingerprint,
+        }])
+    }
+
+    async fn record_file(&self, job_id: String, file: FileHash) -> Result<()> {
+        self.commit(vec![Event::FileRecorded { job_id, file }])
+    }
+
+    async fn list_files(&self, job_id: String) -> Result<Vec<FileHash>> {
+        Ok(self.read(|t| {
+            t.job_files
+                .get(&job_id)
+                .map(|files| files.values().cloned().collect())
+                .unwrap_or_default()
+        }))
+    }
+
+    async fn clear_files(&self, job_id: String) -> Result<()> {
+        self.commit(vec![Event::FilesCleared { job_id }])
+    }
+
+    async fn find_duplicates(&self, job_id: String) -> Result<Vec<DuplicateFile>> {
+        Ok(self.read(|t| {
+            let Some(files) = t.job_files.get(&job_id) else {
+                return Vec::new();
+            };
+            let mut duplicates: Vec<DuplicateFile> = Vec::new();
+            for file in files.values() {
+                for (other_job_id, others) in &t.job_files {
+                    if *other_job_id == job_id {
+                        continue;
+                    }
+                    for other in others.values() {
+                        if other.hash == file.hash && other.size == file.size {
+                            duplicates.push(DuplicateFile {
+                                relative_path: file.relative_path.clone(),
+                                size: file.size,
+                                other_job_id: other_job_id.clone(),
+                                other_path: other.relative_path.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+            duplicates.sort_by(|a, b| {
+                (&a.relative_path, &a.other_job_id, &a.other_path).cmp(&(
+                    &b.relative_path,
+                    &b.other_job_id,
+                    &b.other_path,
+                ))
+            });
+            duplicates
+        }))
+    }
+
+    async fn record_probes(&self, job_id: String, probes: Vec<ProbedVideo>) -> Result<()> {
+        self.commit(vec![Event::ProbesRecorded { job_id, probes }])
+    }
+
+    async fn list_probes(&self, job_id: String) -> Result<Vec<ProbedVideo>> {
+        Ok(self.read(|t| {
+            t.video_probes
+                .get(&job_id)
+                .map(|probes| probes.values().cloned().collect())
+                .unwrap_or_default()
+        }))
+    }
+
+    async fn record_scrub(&self, result: ScrubResult) -> Result<()> {
+        self.commit(vec![Event::ScrubRecorded { result }])
+    }
+
+    async fn list_scrubs(&self, job_id: Option<String>, limit: u32) -> Result<Vec<ScrubResult>> {
+        Ok(self.read(|t| {
+            t.scrubs
+                .iter()
+                .rev()
+                .filter(|s| job_id.as_ref().is_none_or(|id| &s.job_id == id))
+                .take(limit as usize)
+                .cloned()
+                .collect()
+        }))
+    }
+
+    async fn scrub_candidates(&self, older_than_days: u32, limit: u32) -> Result<Vec<Job>> {
+        Ok(self.read(|t| {
+            t.scrub_candidates(older_than_days)
+                .into_iter()
+                .take(limit as usize)
+                .map(|j| t.job(j))
+                .collect()
+        }))
+    }
+
+    async fn outbox_pending(&self, limit: u32) -> Result<Vec<OutboxEvent>> {
+        Ok(self.read(|t| t.outbox.iter().take(limit as usize).cloned().collect()))
+    }
+
+    async fn outbox_ack(&self, ids: Vec<String>) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        self.commit(vec![Event::OutboxAcked { ids }])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::transfer_engine::LargestFile;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    fn drive(uuid: &str) -> TargetDrive {
+        TargetDrive {
+            uuid: uuid.to_string(),
+            label: "EOS_DIGITAL".to_string(),
+            mount_path: "/run/bksd/card".to_string(),
+            raw_size: 64,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_job_lifecycle() {
+        let store = MemoryStore::open(None).unwrap();
+        store
+            .create_job(
+                "job-1".to_string(),
+                drive("card-1"),
+                "/backups/a".to_string(),
+                "host".to_string(),
+                LabelFields::default(),
+            )
+            .await
+            .unwrap();
+        store
+            .update_status(
+                "job-1".to_string(),
+                "complete".to_string(),
+                None,
+                Some(500),
+                Some(3),
+            )
+            .await
+            .unwrap();
+
+        let job = store.get_with_history("job-1".to_string()).await.unwrap();
+        assert_eq!(job.job.status, "complete");
+        assert_eq!(job.history.len(), 2);
+
+        let latest = store.latest_backup("card-1".to_string()).await.unwrap();
+        assert_eq!(latest.map(|j| j.id), Some("job-1".to_string()));
+
+        let failed = store
+            .list_jobs(10, 0, Some("failed".to_string()))
+            .await
+            .unwrap();
+        assert!(failed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_completion_keeps_stats() {
+        let temp = tempdir().unwrap();
+        let journal = temp.path().join("bksd.jsonl");
+        let stats = TransferStats {
+            avg_bytes_per_sec: 100,
+            files_copied: 3,
+            files_skipped: 1,
+            largest_file: Some(LargestFile {
+                path: "DCIM/MVI_0001.MP4".to_string(),
+                size: 3 * 1024 * 1024,
+            }),
+            ..Default::default()
+        };
+
+        {
+            let store = MemoryStore::open(Some(&journal)).unwrap();
+            store
+                .create_job(
+                    "job-1".to_string(),
+                    drive("card-1"),
+                    "/backups/a".to_string(),
+                    "host".to_string(),
+                    LabelFields::default(),
+                )
+                .await
+                .unwrap();
+            store
+                .record_completion("job-1".to_string(), stats.summary(), 500, 5, stats.clone())
+                .await
+                .unwrap();
+        }
+
+        let store = MemoryStore::open(Some(&journal)).unwrap();
+        let job = store.get_with_history("job-1".to_string()).await.unwrap();
+        assert_eq!(job.job.status, "complete");
+        let entry = job.history.last().unwrap();
+        assert_eq!(entry.total_bytes, Some(500));
+        assert_eq!(entry.stats.as_ref(), Some(&stats));
+        assert_eq!(
+            stats.files_summary(),
+            "3 file(s) copied, 1 skipped, largest DCIM/MVI_0001.MP4 (3.0 MB)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_journal_replays_on_open() {
+        let temp = tempdir().unwrap();
+        let journal = temp.path().join("state/bksd.jsonl");
+
+        {
+            let store = MemoryStore::open(Some(&journal)).unwrap();
+            store
+                .create_job(
+                    "job-1".to_string(),
+                    drive("card-1"),
+                    "/backups/a".to_string(),
+                    "host".to_string(),
+                    LabelFields::default(),
+                )
+                .await
+                .unwrap();
+            store
+                .record_file(
+                    "job-1".to_string(),
+                    FileHash {
+                        relative_path: PathBuf::from("DCIM/IMG_0001.CR3"),
+                        hash: [7; 32],
+                        size: 10,
+                    },
+                )
+                .await
+                .unwrap();
+        }
+
+        let store = MemoryStore::open(Some(&journal)).unwrap();
+        store
+            .create_job(
+                "job-2".to_string(),
+                drive("card-2"),
+                "/backups/b".to_string(),
+                "host".to_string(),
+                LabelFields::default(),
+            )
+            .await
+            .unwrap();
+        store
+            .update_status(
+                "job-2".to_string(),
+                "queued".to_string(),
+                Some("Queued until the backup window opens".to_string()),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        drop(store);
+
+        // A restart fails the interrupted job and cancels the queued one
+        let store = MemoryStore::open(Some(&journal)).unwrap();
+        assert_eq!(store.fail_interrupted().await.unwrap(), 2);
+        let job = store.get_with_history("job-1".to_string()).await.unwrap();
+        assert_eq!(job.job.status, "failed");
+        let job = store.get_with_history("job-2".to_string()).await.unwrap();
+        assert_eq!(job.job.status, "cancelled");
+
+        let resumable = store.find_resumable("card-1".to_string()).await.unwrap();
+        assert_eq!(resumable.map(|j| j.id), Some("job-1".to_string()));
+
+        let files = store.list_files("job-1".to_string()).await.unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].hash, [7; 32]);
+    }
+
+    #[tokio::test]
+    async fn test_outbox_survives_restart_until_acked() {
+        let temp = tempdir().unwrap();
+        let journal = temp.path().join("bksd.jsonl");
+
+        {
+            let store = MemoryStore::open(Some(&journal)).unwrap();
+            store
+                .create_job(
+                    "job-1".to_string(),
+                    drive("card-1"),
+                    "/backups/a".to_string(),
+                    "host".to_string(),
+                    LabelFields::default(),
+                )
+                .await
+                .unwrap();
+            store
+                .update_status(
+                    "job-1".to_string(),
+                    "complete".to_string(),
+                    None,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap();
+
+            let pending = store.outbox_pending(10).await.unwrap();
+            let statuses: Vec<&str> = pending.iter().map(|e| e.job.status.as_str()).collect();
+            assert_eq!(statuses, ["Ready", "complete"]);
+            store.outbox_ack(vec![pending[0].id.clone()]).await.unwrap();
+        }
+
+        let store = MemoryStore::open(Some(&journal)).unwrap();
+        let pending = store.outbox_pending(10).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].job.status, "complete");
+    }
+
+    #[tokio::test]
+    async fn test_find_duplicates_across_jobs() {
+        let store = MemoryStore::open(None).unwrap();
+        let file = |path: &str, hash: u8| FileHash {
+            relative_path: PathBuf::from(path),
+            hash: [hash; 32],
+            size: 10,
+        };
+        for (job_id, path, hash) in [
+            ("job-1", "DCIM/A001.MOV", 1),
+            ("job-1", "DCIM/A002.MOV", 2),
+            ("job-2", "CLIPS/A001.MOV", 1),
+            ("job-2", "DCIM/A003.MOV", 3),
+        ] {
+            store
+                .record_file(job_id.to_string(), file(path, hash))
+                .await
+                .unwrap();
+        }
+
+        let duplicates = store.find_duplicates("job-1".to_string()).await.unwrap();
+        assert_eq!(
+            duplicates,
+            vec![DuplicateFile {
+                relative_path: PathBuf::from("DCIM/A001.MOV"),
+                size: 10,
+                other_job_id: "job-2".to_string(),
+                other_path: PathBuf::from("CLIPS/A001.MOV"),
+            }]
+        );
+        assert!(
+            store
+                .find_duplicates("job-3".to_string())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_probe_results_survive_restart() {
+        let temp = tempdir().unwrap();
+        let journal = temp.path().join("bksd.jsonl");
+        let probe = |path: &str, reason: Option<&str>| ProbedVideo {
+            relative_path: PathBuf::from(path),
+            ok: reason.is_none(),
+            reason: reason.map(str::to_string),
+        };
+
+        {
+            let store = MemoryStore::open(Some(&journal)).unwrap();
+            store
+                .record_probes(
+                    "job-1".to_string(),
+                    vec![
+                        probe("CLIP/C0002.MP4", Some("missing moov index")),
+                        probe("CLIP/C0001.MP4", None),
+                    ],
+                )
+                .await
+                .unwrap();
+        }
+
+        let store = MemoryStore::open(Some(&journal)).unwrap();
+        assert_eq!(
+            store.list_probes("job-1".to_string()).await.unwrap(),
+            vec![
+                probe("CLIP/C0001.MP4", None),
+                probe("CLIP/C0002.MP4", Some("missing moov index")),
+            ]
+        );
+        assert!(
+            store
+                .list_probes("job-2".to_string())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
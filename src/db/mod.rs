@@ -1,8 +1,22 @@
 use anyhow::Result;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio_rusqlite::Connection;
 
+use crate::config::{AppConfig, StorageBackend};
+
+pub mod archives;
+pub mod catalog;
+pub mod files;
 pub mod jobs;
+#[cfg(any(test, feature = "memory-store"))]
+pub mod memory;
+pub mod outbox;
+pub mod scrubs;
+pub mod sqlite;
+pub mod store;
+
+pub use store::Store;
 
 /// Default directory for bksd persistent data (database).
 pub const DATA_DIR: &str = "/var/lib/bksd";
@@ -10,12 +24,42 @@ pub const DATA_DIR: &str = "/var/lib/bksd";
 /// Database filename within the data directory.
 const DB_FILENAME: &str = "bksd.db";
 
+/// Open the storage backend selected in the config.
+pub async fn open(config: &AppConfig) -> Result<Arc<dyn Store>> {
+    match config.storage {
+        StorageBackend::Sqlite => Ok(Arc::new(sqlite::SqliteStore::new(init().await?))),
+        #[cfg(feature = "memory-store")]
+        StorageBackend::Memory => Ok(Arc::new(memory::MemoryStore::open(
+            config.storage_journal.as_deref(),
+        )?)),
+        #[cfg(not(feature = "memory-store"))]
+        StorageBackend::Memory => {
+            anyhow::bail!("Memory storage requires bksd built with the memory-store feature")
+        }
+    }
+}
+
+/// Where the database is kept. A daemon not running as root (with the
+/// udisks2 adapter) can't write to /var/lib, so uses `$XDG_DATA_HOME/bksd`
+/// (`~/.local/share/bksd`) instead.
+pub fn data_dir() -> PathBuf {
+    if nix::unistd::Uid::effective().is_root() {
+        return PathBuf::from(DATA_DIR);
+    }
+    std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+        .map(|dir| dir.join("bksd"))
+        .unwrap_or_else(|| PathBuf::from(DATA_DIR))
+}
+
 pub async fn init() -> Result<Connection> {
-    let data_dir = Path::new(DATA_DIR);
+    let data_dir = data_dir();
 
     // Create data directory if it doesn't exist
     if !data_dir.exists() {
-        std::fs::create_dir_all(data_dir)?;
+        std::fs::create_dir_all(&data_dir)?;
     }
 
     let db_path = data_dir.join(DB_FILENAME);
@@ -24,6 +68,7 @@ pub async fn init() -> Result<Connection> {
     conn.call(|conn| {
         let schema = include_str!("schema.sql");
         conn.execute_batch(schema)?;
+        migrate(conn)?;
 
         // Enable foreign keys (SQLite disables them by default!)
         conn.execute("PRAGMA foreign_keys = ON;", [])?;
@@ -34,3 +79,56 @@ pub async fn init() -> Result<Connection> {
 
     Ok(conn)
 }
+
+/// Columns added after the initial release. `CREATE TABLE IF NOT EXISTS`
+/// leaves existing tables alone, so older databases get them here.
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("jobs", "origin_host", "TEXT"),
+    ("jobs", "reel", "TEXT"),
+    ("jobs", "day", "TEXT"),
+    ("jobs", "camera", "TEXT"),
+    ("jobs", "camera_model", "TEXT"),
+    ("job_status_log", "stats", "TEXT"),
+];
+
+/// Timestamp columns. Rows written by older versions hold SQLite's
+/// `CURRENT_TIMESTAMP` format (UTC, no `T`/`Z`) and are rewritten as RFC3339
+/// so string ordering and comparison stay correct.
+const TIMESTAMP_COLUMNS: &[(&str, &str)] = &[
+    ("targets", "created_at"),
+    ("jobs", "created_at"),
+    ("job_status_log", "created_at"),
+    ("job_notices", "created_at"),
+    ("job_archives", "archived_at"),
+    ("archive_volumes", "last_seen_at"),
+    ("job_files", "created_at"),
+];
+
+fn migrate(conn: &tokio_rusqlite::rusqlite::Connection) -> tokio_rusqlite::rusqlite::Result<()> {
+    for (table, column, decl) in ADDED_COLUMNS {
+        let exists = conn
+            .prepare(&format!(
+                "SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1",
+                table
+            ))?
+            .exists([column])?;
+        if !exists {
+            conn.execute(
+                &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl),
+                [],
+            )?;
+        }
+    }
+
+    for (table, column) in TIMESTAMP_COLUMNS {
+        conn.execute(
+            &format!(
+                "UPDATE {table} SET {column} = strftime('%Y-%m-%dT%H:%M:%fZ', {column})
+                 WHERE {column} NOT LIKE '%T%'"
+            ),
+            [],
+        )?;
+    }
+
+    Ok(())
+}
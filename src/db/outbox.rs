@@ -0,0 +1,114 @@
+//! Outbox of job changes waiting to be forwarded to the hub.
+//!
+//! Every status change or archive move of a job queues a snapshot of the job
+//! in the same transaction as the change itself, so nothing is lost if the
+//! station's network (or the hub) is down when it happens. The hub reporter
+//! sends pending events oldest first and removes them once the hub has
+//! acknowledged them; the hub ignores event IDs it has already applied, so a
+//! resend after a lost acknowledgement is harmless.
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use tokio_rusqlite::{Connection, params, rusqlite};
+
+use crate::core::Job;
+use crate::db::jobs::{JOB_COLUMNS, job_from_row};
+
+/// Events kept while the hub is unreachable (or no hub is configured).
+/// Beyond this the oldest are dropped; the hub still gets each station's
+/// recent jobs from the regular report.
+pub const OUTBOX_LIMIT: usize = 10_000;
+
+/// A job as it was right after one change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxEvent {
+    /// Unique per station; the hub deduplicates on it
+    pub id: String,
+    pub job: Job,
+    pub created_at: String,
+}
+
+/// Event ID for a job being moved to an archive drive. Each job is archived
+/// at most once, so this is stable across backends and journal replays.
+pub fn archived_event_id(job_id: &str) -> String {
+    format!("{}-archived", job_id)
+}
+
+/// Queue a snapshot of the job as it is now. Call inside the transaction
+/// that changed it.
+pub(crate) fn enqueue(
+    c: &rusqlite::Connection,
+    event_id: &str,
+    job_id: &str,
+    created_at: &str,
+) -> rusqlite::Result<()> {
+    let job = c.query_row(
+        &format!("SELECT {} FROM jobs j WHERE j.id = ?1", JOB_COLUMNS),
+        params![job_id],
+        job_from_row,
+    )?;
+    let payload = serde_json::to_string(&job)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+
+    c.execute(
+        "INSERT OR IGNORE INTO hub_outbox (id, job_id, payload, created_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![event_id, job_id, payload, created_at],
+    )?;
+    c.execute(
+        "DELETE FROM hub_outbox WHERE seq <= (SELECT MAX(seq) FROM hub_outbox) - ?1",
+        params![OUTBOX_LIMIT as i64],
+    )?;
+    Ok(())
+}
+
+/// Oldest pending events, up to `limit`.
+pub async fn pending(conn: &Connection, limit: u32) -> Result<Vec<OutboxEvent>> {
+    let rows = conn
+        .call(move |c| {
+            let mut stmt =
+                c.prepare("SELECT id, payload, created_at FROM hub_outbox ORDER BY seq LIMIT ?1")?;
+            let rows = stmt
+                .query_map(params![limit], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok::<_, rusqlite::Error>(rows)
+        })
+        .await
+        .map_err(|e| anyhow!("Failed to read hub outbox: {}", e))?;
+
+    rows.into_iter()
+        .map(|(id, payload, created_at)| {
+            Ok(OutboxEvent {
+                id,
+                job: serde_json::from_str(&payload)?,
+                created_at,
+            })
+        })
+        .collect()
+}
+
+/// Remove events the hub has acknowledged.
+pub async fn ack(conn: &Connection, ids: Vec<String>) -> Result<()> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+    conn.call(move |c| {
+        let tx = c.transaction()?;
+        {
+            let mut stmt = tx.prepare("DELETE FROM hub_outbox WHERE id = ?1")?;
+            for id in &ids {
+                stmt.execute(params![id])?;
+            }
+        }
+        tx.commit()?;
+        Ok::<(), rusqlite::Error>(())
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to acknowledge hub events: {}", e))
+}
@@ -1,17 +1,24 @@
+-- Timestamps are UTC RFC3339 (e.g. 2025-01-14T10:32:05.123Z)
+
 CREATE TABLE IF NOT EXISTS targets (
     id TEXT PRIMARY KEY,
     label TEXT NOT NULL,
     raw_size INTEGER,
     adapter TEXT NOT NULL,
     source TEXT,
-    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    created_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
 );
 
 CREATE TABLE IF NOT EXISTS jobs (
     id TEXT PRIMARY KEY,
     target_id TEXT NOT NULL,
     destination_path TEXT,
-    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    origin_host TEXT,
+    reel TEXT,
+    day TEXT,
+    camera TEXT,
+    camera_model TEXT,
+    created_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
     FOREIGN KEY(target_id) REFERENCES targets(id)
 );
 
@@ -23,6 +30,116 @@ CREATE TABLE IF NOT EXISTS job_status_log (
     -- Completion metadata (only populated for Complete status)
     total_bytes INTEGER,
     duration_secs INTEGER,
-    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    -- TransferStats as JSON
+    stats TEXT,
+    created_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY(job_id) REFERENCES jobs(id)
+);
+
+CREATE TABLE IF NOT EXISTS job_notices (
+    id TEXT PRIMARY KEY,
+    job_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    path TEXT,
+    message TEXT NOT NULL,
+    created_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY(job_id) REFERENCES jobs(id)
+);
+
+CREATE TABLE IF NOT EXISTS job_archives (
+    job_id TEXT PRIMARY KEY,
+    volume_uuid TEXT NOT NULL,
+    volume_label TEXT NOT NULL,
+    archive_path TEXT NOT NULL,
+    archived_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY(job_id) REFERENCES jobs(id)
+);
+
+CREATE TABLE IF NOT EXISTS archive_volumes (
+    uuid TEXT PRIMARY KEY,
+    label TEXT NOT NULL,
+    capacity INTEGER,
+    connected INTEGER NOT NULL DEFAULT 0,
+    last_seen_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE TABLE IF NOT EXISTS job_files (
+    job_id TEXT NOT NULL,
+    relative_path TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    hash BLOB NOT NULL,
+    created_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    PRIMARY KEY(job_id, relative_path),
+    FOREIGN KEY(job_id) REFERENCES jobs(id)
+);
+
+-- Finds other jobs holding the same file contents
+CREATE INDEX IF NOT EXISTS job_files_hash ON job_files(hash);
+
+-- Structural probe of each video file in a verified backup (probe_videos)
+CREATE TABLE IF NOT EXISTS video_probes (
+    job_id TEXT NOT NULL,
+    relative_path TEXT NOT NULL,
+    ok INTEGER NOT NULL,
+    -- Why the file is unplayable, when not ok
+    reason TEXT,
+    created_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    PRIMARY KEY(job_id, relative_path),
+    FOREIGN KEY(job_id) REFERENCES jobs(id)
+);
+
+-- Latest verified backup of each file seen on a device (incremental mode)
+CREATE TABLE IF NOT EXISTS device_files (
+    target_id TEXT NOT NULL,
+    relative_path TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    hash BLOB NOT NULL,
+    job_id TEXT NOT NULL,
+    created_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    PRIMARY KEY(target_id, relative_path),
+    FOREIGN KEY(job_id) REFERENCES jobs(id)
+);
+
+-- Content fingerprint of each device at its latest verified backup, so a card
+-- reinserted unchanged isn't copied again
+CREATE TABLE IF NOT EXISTS device_fingerprints (
+    target_id TEXT PRIMARY KEY,
+    digest TEXT NOT NULL,
+    job_id TEXT NOT NULL,
+    created_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY(job_id) REFERENCES jobs(id)
+);
+
+-- Files a job left out because an earlier backup already holds them
+CREATE TABLE IF NOT EXISTS job_skipped_files (
+    job_id TEXT NOT NULL,
+    relative_path TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    backed_up_by TEXT NOT NULL,
+    created_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    PRIMARY KEY(job_id, relative_path),
+    FOREIGN KEY(job_id) REFERENCES jobs(id)
+);
+
+-- Job changes waiting to be forwarded to the hub, oldest first
+CREATE TABLE IF NOT EXISTS hub_outbox (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    id TEXT NOT NULL UNIQUE,
+    job_id TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    created_at DATETIME DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+-- Re-verification of old backups against their manifests (bit-rot scrubs)
+CREATE TABLE IF NOT EXISTS scrub_results (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    job_id TEXT NOT NULL,
+    started_at TEXT NOT NULL,
+    finished_at TEXT NOT NULL,
+    status TEXT NOT NULL,
+    files_checked INTEGER NOT NULL,
+    bytes_checked INTEGER NOT NULL,
+    damaged TEXT NOT NULL,
+    error TEXT,
     FOREIGN KEY(job_id) REFERENCES jobs(id)
 );
@@ -0,0 +1,158 @@
+//! Results of scrubbing old backups for bit-rot.
+//!
+//! Each scrub re-hashes one job's backup against its manifest and records
+//! what it found. The next scheduled run starts with the backups that have
+//! gone longest without a check.
+
+use std::path::PathBuf;
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use tokio_rusqlite::{Connection, params, rusqlite};
+
+use crate::core::Job;
+use crate::db::jobs::{JOB_COLUMNS, job_from_row};
+
+/// Outcome of one scrub
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrubStatus {
+    /// Every file still matches its recorded hash
+    Ok,
+    /// Files changed or disappeared since they were backed up
+    Damaged,
+    /// The backup couldn't be checked (folder gone, no manifest)
+    Error,
+}
+
+impl ScrubStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScrubStatus::Ok => "ok",
+            ScrubStatus::Damaged => "damaged",
+            ScrubStatus::Error => "error",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "ok" => ScrubStatus::Ok,
+            "damaged" => ScrubStatus::Damaged,
+            _ => ScrubStatus::Error,
+        }
+    }
+}
+
+/// A file that no longer matches its backup-time hash
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DamagedFile {
+    pub relative_path: PathBuf,
+    pub reason: String,
+}
+
+/// What one scrub of a job's backup found
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrubResult {
+    pub job_id: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub status: ScrubStatus,
+    pub files_checked: u64,
+    pub bytes_checked: u64,
+    pub damaged: Vec<DamagedFile>,
+    /// Why the backup couldn't be checked, for `error`
+    pub error: Option<String>,
+}
+
+/// Completed backups older than `?1` days that can be read now: in the
+/// backup directory, or on an archive drive that is connected. Relies on the
+/// `status` alias from `JOB_COLUMNS`.
+const SCRUBBABLE_WHERE: &str = "status = 'complete'
+       AND j.created_at <= strftime('%Y-%m-%dT%H:%M:%fZ', 'now', '-' || ?1 || ' days')
+       AND j.destination_path IS NOT NULL
+       AND NOT EXISTS (SELECT 1 FROM job_archives a
+                       JOIN archive_volumes v ON v.uuid = a.volume_uuid
+                       WHERE a.job_id = j.id AND v.connected = 0)";
+
+/// Record a scrub.
+pub async fn record(conn: &Connection, result: ScrubResult) -> Result<()> {
+    conn.call(move |c| {
+        let damaged = serde_json::to_string(&result.damaged)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        c.execute(
+            "INSERT INTO scrub_results (job_id, started_at, finished_at, status,
+                 files_checked, bytes_checked, damaged, error)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                result.job_id,
+                result.started_at,
+                result.finished_at,
+                result.status.as_str(),
+                result.files_checked,
+                result.bytes_checked,
+                damaged,
+                result.error,
+            ],
+        )?;
+        Ok::<_, rusqlite::Error>(())
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to record scrub: {}", e))
+}
+
+/// Scrubs newest first, optionally only one job's.
+pub async fn list(
+    conn: &Connection,
+    job_id: Option<String>,
+    limit: u32,
+) -> Result<Vec<ScrubResult>> {
+    conn.call(move |c| {
+        let mut stmt = c.prepare(
+            "SELECT job_id, started_at, finished_at, status, files_checked,
+                    bytes_checked, damaged, error
+             FROM scrub_results
+             WHERE ?1 IS NULL OR job_id = ?1
+             ORDER BY id DESC
+             LIMIT ?2",
+        )?;
+
+        stmt.query_map(params![job_id, limit], |row| {
+            let status: String = row.get(3)?;
+            let damaged: String = row.get(6)?;
+            Ok(ScrubResult {
+                job_id: row.get(0)?,
+                started_at: row.get(1)?,
+                finished_at: row.get(2)?,
+                status: ScrubStatus::parse(&status),
+                files_checked: row.get(4)?,
+                bytes_checked: row.get(5)?,
+                damaged: serde_json::from_str(&damaged).unwrap_or_default(),
+                error: row.get(7)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to list scrubs: {}", e))
+}
+
+/// Up to `limit` backups due a scrub: never-scrubbed ones first, then those
+/// scrubbed longest ago, older backups first within each.
+pub async fn candidates(conn: &Connection, older_than_days: u32, limit: u32) -> Result<Vec<Job>> {
+    conn.call(move |c| {
+        let mut stmt = c.prepare(&format!(
+            "SELECT {} FROM jobs j
+             LEFT JOIN (SELECT job_id, MAX(finished_at) as last_scrub
+                        FROM scrub_results GROUP BY job_id) s ON s.job_id = j.id
+             WHERE {}
+             ORDER BY s.last_scrub IS NOT NULL, s.last_scrub ASC, j.created_at ASC
+             LIMIT ?2",
+            JOB_COLUMNS, SCRUBBABLE_WHERE
+        ))?;
+
+        stmt.query_map(params![older_than_days, limit], job_from_row)?
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to list backups due a scrub: {}", e))
+}
@@ -0,0 +1,244 @@
+//! SQLite-backed `Store`, the default backend.
+//!
+//! The queries live in the `jobs`, `archives`, `catalog`, `files`, `scrubs`
+//! and `outbox` modules; this just routes the trait through them.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio_rusqlite::Connection;
+
+use crate::core::label_fields::LabelFields;
+use crate::core::transfer_engine::{FileHash, TransferStats};
+use crate::core::{ArchiveVolume, Job, JobNotice, JobWithHistory, TargetDrive};
+use crate::db::catalog::{CatalogEntry, DeviceFingerprint};
+use crate::db::files::{DuplicateFile, ProbedVideo};
+use crate::db::outbox::{self, OutboxEvent};
+use crate::db::scrubs::{self, ScrubResult};
+use crate::db::store::Store;
+use crate::db::{archives, catalog, files, jobs};
+
+pub struct SqliteStore {
+    conn: Connection,
+}
+
+impl SqliteStore {
+    pub fn new(conn: Connection) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait]
+impl Store for SqliteStore {
+    async fn create_job(
+        &self,
+        job_id: String,
+        drive: TargetDrive,
+        destination_path: String,
+        origin_host: String,
+        label_fields: LabelFields,
+    ) -> Result<()> {
+        jobs::create(
+            &self.conn,
+            job_id,
+            drive,
+            destination_path,
+            origin_host,
+            label_fields,
+        )
+        .await
+    }
+
+    async fn update_status(
+        &self,
+        job_id: String,
+        status: String,
+        description: Option<String>,
+        total_bytes: Option<u64>,
+        duration_secs: Option<u64>,
+    ) -> Result<()> {
+        jobs::update_status(
+            &self.conn,
+            job_id,
+            status,
+            description,
+            total_bytes,
+            duration_secs,
+        )
+        .await
+    }
+
+    async fn record_completion(
+        &self,
+        job_id: String,
+        description: String,
+        total_bytes: u64,
+        duration_secs: u64,
+        stats: TransferStats,
+    ) -> Result<()> {
+        jobs::record_completion(
+            &self.conn,
+            job_id,
+            description,
+            total_bytes,
+            duration_secs,
+            stats,
+        )
+        .await
+    }
+
+    async fn find_resumable(&self, target_id: String) -> Result<Option<Job>> {
+        jobs::find_resumable(&self.conn, target_id).await
+    }
+
+    async fn latest_backup(&self, target_id: String) -> Result<Option<Job>> {
+        jobs::latest_backup(&self.conn, target_id).await
+    }
+
+    async fn fail_interrupted(&self) -> Result<usize> {
+        jobs::fail_interrupted(&self.conn).await
+    }
+
+    async fn import_job(&self, job: JobWithHistory, files: Vec<FileHash>) -> Result<()> {
+        jobs::import(&self.conn, job, files).await
+    }
+
+    async fn add_notices(&self, job_id: String, notices: Vec<JobNotice>) -> Result<()> {
+        jobs::add_notices(&self.conn, job_id, notices).await
+    }
+
+    async fn list_notices(&self, job_id: String) -> Result<Vec<JobNotice>> {
+        jobs::list_notices(&self.conn, job_id).await
+    }
+
+    async fn list_jobs(
+        &self,
+        limit: u32,
+        offset: u32,
+        status_filter: Option<String>,
+    ) -> Result<Vec<Job>> {
+        jobs::list(&self.conn, limit, offset, status_filter).await
+    }
+
+    async fn get_with_history(&self, job_id: String) -> Result<JobWithHistory> {
+        jobs::get_with_history(&self.conn, job_id).await
+    }
+
+    async fn list_archivable(&self, older_than_days: u32) -> Result<Vec<Job>> {
+        archives::list_archivable(&self.conn, older_than_days).await
+    }
+
+    async fn archivable_bytes(&self, older_than_days: u32) -> Result<(usize, u64)> {
+        archives::archivable_bytes(&self.conn, older_than_days).await
+    }
+
+    async fn stored_bytes(&self, uuid: String) -> Result<u64> {
+        archives::stored_bytes(&self.conn, uuid).await
+    }
+
+    async fn record_archive(
+        &self,
+        job_id: String,
+        volume_uuid: String,
+        volume_label: String,
+        archive_path: String,
+    ) -> Result<()> {
+        archives::record(&self.conn, job_id, volume_uuid, volume_label, archive_path).await
+    }
+
+    async fn mark_connected(&self, uuid: String, label: String, capacity: u64) -> Result<()> {
+        archives::mark_connected(&self.conn, uuid, label, capacity).await
+    }
+
+    async fn mark_disconnected(&self, uuid: String) -> Result<()> {
+        archives::mark_disconnected(&self.conn, uuid).await
+    }
+
+    async fn reset_connected(&self) -> Result<()> {
+        archives::reset_connected(&self.conn).await
+    }
+
+    async fn list_volumes(&self) -> Result<Vec<ArchiveVolume>> {
+        archives::list_volumes(&self.conn).await
+    }
+
+    async fn locate_archive(&self, job_id: String) -> Result<Option<ArchiveVolume>> {
+        archives::locate(&self.conn, job_id).await
+    }
+
+    async fn list_archive_contents(&self, uuid: String) -> Result<Vec<Job>> {
+        archives::list_contents(&self.conn, uuid).await
+    }
+
+    async fn catalog_lookup(&self, target_id: String) -> Result<Vec<CatalogEntry>> {
+        catalog::lookup(&self.conn, target_id).await
+    }
+
+    async fn catalog_update(
+        &self,
+        target_id: String,
+        job_id: String,
+        files: Vec<FileHash>,
+    ) -> Result<()> {
+        catalog::update(&self.conn, target_id, job_id, files).await
+    }
+
+    async fn record_skipped(&self, job_id: String, skipped: Vec<CatalogEntry>) -> Result<()> {
+        catalog::record_skipped(&self.conn, job_id, skipped).await
+    }
+
+    async fn fingerprint_lookup(&self, target_id: String) -> Result<Option<DeviceFingerprint>> {
+        catalog::fingerprint(&self.conn, target_id).await
+    }
+
+    async fn fingerprint_update(
+        &self,
+        target_id: String,
+        fingerprint: DeviceFingerprint,
+    ) -> Result<()> {
+        catalog::record_fingerprint(&self.conn, target_id, fingerprint).await
+    }
+
+    async fn record_file(&self, job_id: String, file: FileHash) -> Result<()> {
+        files::record(&self.conn, job_id, file).await
+    }
+
+    async fn list_files(&self, job_id: String) -> Result<Vec<FileHash>> {
+        files::list(&self.conn, job_id).await
+    }
+
+    async fn clear_files(&self, job_id: String) -> Result<()> {
+        files::clear(&self.conn, job_id).await
+    }
+
+    async fn find_duplicates(&self, job_id: String) -> Result<Vec<DuplicateFile>> {
+        files::duplicates(&self.conn, job_id).await
+    }
+
+    async fn record_probes(&self, job_id: String, probes: Vec<ProbedVideo>) -> Result<()> {
+        files::record_probes(&self.conn, job_id, probes).await
+    }
+
+    async fn list_probes(&self, job_id: String) -> Result<Vec<ProbedVideo>> {
+        files::list_probes(&self.conn, job_id).await
+    }
+
+    async fn record_scrub(&self, result: ScrubResult) -> Result<()> {
+        scrubs::record(&self.conn, result).await
+    }
+
+    async fn list_scrubs(&self, job_id: Option<String>, limit: u32) -> Result<Vec<ScrubResult>> {
+        scrubs::list(&self.conn, job_id, limit).await
+    }
+
+    async fn scrub_candidates(&self, older_than_days: u32, limit: u32) -> Result<Vec<Job>> {
+        scrubs::candidates(&self.conn, older_than_days, limit).await
+    }
+
+    async fn outbox_pending(&self, limit: u32) -> Result<Vec<OutboxEvent>> {
+        outbox::pending(&self.conn, limit).await
+    }
+
+    async fn outbox_ack(&self, ids: Vec<String>) -> Result<()> {
+        outbox::ack(&self.conn, ids).await
+    }
+}
@@ -0,0 +1,196 @@
+//! Storage interface for jobs, archive drives, the device catalog and resume
+//! manifests.
+//!
+//! The daemon talks to persistence only through `Store`, so the backend can
+//! be swapped: SQLite by default, or the in-memory/JSONL store (behind the
+//! `memory-store` feature) on systems whose root filesystem is read-only.
+//! Unit tests always get the in-memory store, so code that takes a `Store`
+//! can be tested without a database on disk.
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::core::label_fields::LabelFields;
+use crate::core::transfer_engine::{FileHash, TransferStats};
+use crate::core::{ArchiveVolume, Job, JobNotice, JobWithHistory, TargetDrive};
+use crate::db::catalog::{CatalogEntry, DeviceFingerprint};
+use crate::db::files::{DuplicateFile, ProbedVideo};
+use crate::db::outbox::OutboxEvent;
+use crate::db::scrubs::ScrubResult;
+
+#[async_trait]
+pub trait Store: Send + Sync {
+    // Jobs
+
+    /// Create a job for a drive, with an initial `Ready` status entry.
+    async fn create_job(
+        &self,
+        job_id: String,
+        drive: TargetDrive,
+        destination_path: String,
+        origin_host: String,
+        label_fields: LabelFields,
+    ) -> Result<()>;
+
+    /// Append an entry to a job's status log.
+    async fn update_status(
+        &self,
+        job_id: String,
+        status: String,
+        description: Option<String>,
+        total_bytes: Option<u64>,
+        duration_secs: Option<u64>,
+    ) -> Result<()>;
+
+    /// Append the `complete` entry to a job's status log, with the
+    /// transfer's statistics.
+    async fn record_completion(
+        &self,
+        job_id: String,
+        description: String,
+        total_bytes: u64,
+        duration_secs: u64,
+        stats: TransferStats,
+    ) -> Result<()>;
+
+    /// Most recent job for a device, if it failed part-way with files already
+    /// copied and so can be resumed.
+    async fn find_resumable(&self, target_id: String) -> Result<Option<Job>>;
+
+    /// The device's most recent completed backup that is still in the backup
+    /// directory (not moved to an archive drive).
+    async fn latest_backup(&self, target_id: String) -> Result<Option<Job>>;
+
+    /// Mark jobs left unfinished by a crash or restart as failed, and queued
+    /// ones as cancelled. Returns how many were updated.
+    async fn fail_interrupted(&self) -> Result<usize>;
+
+    /// Attach notices (anomalies, report findings) to a job.
+    async fn add_notices(&self, job_id: String, notices: Vec<JobNotice>) -> Result<()>;
+
+    /// Get all notices recorded for a job, oldest first.
+    async fn list_notices(&self, job_id: String) -> Result<Vec<JobNotice>>;
+
+    /// List jobs newest first, optionally only those with a given status.
+    async fn list_jobs(
+        &self,
+        limit: u32,
+        offset: u32,
+        status_filter: Option<String>,
+    ) -> Result<Vec<Job>>;
+
+    /// Get a job with its full status history.
+    async fn get_with_history(&self, job_id: String) -> Result<JobWithHistory>;
+
+    /// Load a job exported from another daemon, with its history, notices
+    /// and manifest as they were there. Fails if the job is already known.
+    /// Imported jobs aren't queued for the hub.
+    async fn import_job(&self, job: JobWithHistory, files: Vec<FileHash>) -> Result<()>;
+
+    // Archive tiering
+
+    /// Completed backups older than `older_than_days` that still live in the
+    /// backup directory, oldest first.
+    async fn list_archivable(&self, older_than_days: u32) -> Result<Vec<Job>>;
+
+    /// Number and combined size of the backups `list_archivable` would return.
+    async fn archivable_bytes(&self, older_than_days: u32) -> Result<(usize, u64)>;
+
+    /// Combined size of the backups stored on an archive drive.
+    async fn stored_bytes(&self, uuid: String) -> Result<u64>;
+
+    /// Record that a job's backup now lives on an archive drive, updating the
+    /// job's destination path to the new location.
+    async fn record_archive(
+        &self,
+        job_id: String,
+        volume_uuid: String,
+        volume_label: String,
+        archive_path: String,
+    ) -> Result<()>;
+
+    /// Register an archive drive as connected, creating it on first sight.
+    async fn mark_connected(&self, uuid: String, label: String, capacity: u64) -> Result<()>;
+
+    /// Mark an archive drive as detached. No-op for devices that aren't archives.
+    async fn mark_disconnected(&self, uuid: String) -> Result<()>;
+
+    /// Forget connection state from a previous run.
+    async fn reset_connected(&self) -> Result<()>;
+
+    /// List all known archive drives, connected or not.
+    async fn list_volumes(&self) -> Result<Vec<ArchiveVolume>>;
+
+    /// Find the archive drive holding a job's backup, if it has been archived.
+    async fn locate_archive(&self, job_id: String) -> Result<Option<ArchiveVolume>>;
+
+    /// List the jobs stored on an archive drive, oldest first.
+    async fn list_archive_contents(&self, uuid: String) -> Result<Vec<Job>>;
+
+    // Device catalog (incremental mode)
+
+    /// Everything backed up so far from a device.
+    async fn catalog_lookup(&self, target_id: String) -> Result<Vec<CatalogEntry>>;
+
+    /// Record the files a verified job backed up from a device.
+    async fn catalog_update(
+        &self,
+        target_id: String,
+        job_id: String,
+        files: Vec<FileHash>,
+    ) -> Result<()>;
+
+    /// Record files a job skipped because an earlier backup already holds them.
+    async fn record_skipped(&self, job_id: String, skipped: Vec<CatalogEntry>) -> Result<()>;
+
+    /// The content fingerprint a device had at its latest verified backup.
+    async fn fingerprint_lookup(&self, target_id: String) -> Result<Option<DeviceFingerprint>>;
+
+    /// Replace a device's fingerprint after a verified job backed it up.
+    async fn fingerprint_update(
+        &self,
+        target_id: String,
+        fingerprint: DeviceFingerprint,
+    ) -> Result<()>;
+
+    // Resume manifests
+
+    /// Record a copied file in the job's manifest.
+    async fn record_file(&self, job_id: String, file: FileHash) -> Result<()>;
+
+    /// All files recorded for a job.
+    async fn list_files(&self, job_id: String) -> Result<Vec<FileHash>>;
+
+    /// Forget a job's manifest so it can't be resumed.
+    async fn clear_files(&self, job_id: String) -> Result<()>;
+
+    /// Files in the job's manifest whose contents another job also backed up.
+    async fn find_duplicates(&self, job_id: String) -> Result<Vec<DuplicateFile>>;
+
+    /// Record the probe results for a job's video files.
+    async fn record_probes(&self, job_id: String, probes: Vec<ProbedVideo>) -> Result<()>;
+
+    /// Probe results for a job's video files, by path.
+    async fn list_probes(&self, job_id: String) -> Result<Vec<ProbedVideo>>;
+
+    // Scrubs
+
+    /// Record the outcome of re-verifying a job's backup.
+    async fn record_scrub(&self, result: ScrubResult) -> Result<()>;
+
+    /// Scrubs newest first, optionally only one job's, up to `limit`.
+    async fn list_scrubs(&self, job_id: Option<String>, limit: u32) -> Result<Vec<ScrubResult>>;
+
+    /// Up to `limit` completed backups older than `older_than_days` that can
+    /// be read now (not on a disconnected archive drive), never-scrubbed
+    /// first, then longest since their last scrub.
+    async fn scrub_candidates(&self, older_than_days: u32, limit: u32) -> Result<Vec<Job>>;
+
+    // Hub outbox
+
+    /// Oldest job changes not yet acknowledged by the hub, up to `limit`.
+    async fn outbox_pending(&self, limit: u32) -> Result<Vec<OutboxEvent>>;
+
+    /// Drop events the hub has acknowledged.
+    async fn outbox_ack(&self, ids: Vec<String>) -> Result<()>;
+}
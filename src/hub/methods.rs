@@ -0,0 +1,229 @@
+//! Hub RPC methods.
+//!
+//! `hub.report` is the only write and requires the shared token; the read
+//! methods back the fleet dashboard and are open like the daemon's own.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio_rusqlite::Connection;
+use tracing::{debug, warn};
+
+use super::{DuplicateIngest, StationReport, store};
+use crate::core::clock::Stopwatch;
+use crate::core::notifications::{JobEvent, NotificationChannel};
+use crate::core::timestamp;
+use crate::rpc::access::token_matches;
+use crate::rpc::{Handler, Request, Response};
+
+/// Error code for a report with a missing or wrong token
+const UNAUTHORIZED: i32 = -32001;
+
+/// Answers `hub.*` requests from stations and the fleet dashboard.
+pub struct HubHandler {
+    conn: Connection,
+    token: String,
+    /// Minutes within which the same card on two stations is flagged
+    duplicate_window_mins: u32,
+    notifier: Option<Arc<dyn NotificationChannel>>,
+    started_at: String,
+    uptime: Stopwatch,
+}
+
+impl HubHandler {
+    pub fn new(
+        conn: Connection,
+        token: String,
+        duplicate_window_mins: u32,
+        notifier: Option<Arc<dyn NotificationChannel>>,
+    ) -> Self {
+        Self {
+            conn,
+            token,
+            duplicate_window_mins,
+            notifier,
+            started_at: timestamp::now_utc(),
+            uptime: Stopwatch::start(),
+        }
+    }
+}
+
+#[async_trait]
+impl Handler for HubHandler {
+    async fn handle(&self, request: Request) -> Response {
+        let id = request.id.clone().unwrap_or(Value::Null);
+        let params = request.params.unwrap_or(Value::Null);
+
+        match request.method.as_str() {
+            "hub.status" => self.hub_status(id).await,
+            "hub.report" => self.hub_report(id, params).await,
+            "hub.stations" => self.hub_stations(id).await,
+            "hub.jobs" => self.hub_jobs(id, params).await,
+            "hub.duplicates" => self.hub_duplicates(id, params).await,
+            _ => Response::method_not_found(id, &request.method),
+        }
+    }
+}
+
+impl HubHandler {
+    /// Hub version and uptime.
+    async fn hub_status(&self, id: Value) -> Response {
+        #[derive(Serialize)]
+        struct HubStatus {
+            version: &'static str,
+            uptime_secs: u64,
+            started_at: String,
+        }
+
+        Response::success(
+            id,
+            HubStatus {
+                version: env!("CARGO_PKG_VERSION"),
+                uptime_secs: self.uptime.elapsed().as_secs(),
+                started_at: self.started_at.clone(),
+            },
+        )
+    }
+
+    /// Accept a report pushed by a station.
+    async fn hub_report(&self, id: Value, params: Value) -> Response {
+        #[derive(Deserialize)]
+        struct Params {
+            token: String,
+            #[serde(flatten)]
+            report: StationReport,
+        }
+
+        let params: Params = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return Response::invalid_params(id, e.to_string()),
+        };
+
+        if !token_matches(&params.token, &self.token) {
+            warn!(station = %params.report.station, "Rejected report with invalid token");
+            return Response::error(id, UNAUTHORIZED, "Invalid hub token");
+        }
+
+        let station = params.report.station.clone();
+        let jobs = params.report.jobs.len();
+        match store::record(
+            &self.conn,
+            params.report,
+            timestamp::now_utc(),
+            self.duplicate_window_mins,
+        )
+        .await
+        {
+            Ok(outcome) => {
+                debug!(station = %station, jobs, events = outcome.acked.len(), "Station report recorded");
+                for duplicate in outcome.duplicates {
+                    self.announce_duplicate(duplicate);
+                }
+                Response::success(
+                    id,
+                    serde_json::json!({ "ok": true, "acked": outcome.acked }),
+                )
+            }
+            Err(e) => Response::internal_error(id, e.to_string()),
+        }
+    }
+
+    /// Log a duplicate ingest and send it to the notification channel.
+    fn announce_duplicate(&self, duplicate: DuplicateIngest) {
+        warn!(
+            card = %duplicate.target_id,
+            station = %duplicate.station,
+            job_id = %duplicate.job_id,
+            other_station = %duplicate.other_station,
+            other_job_id = %duplicate.other_job_id,
+            minutes_apart = duplicate.minutes_apart,
+            "Card ingested on two stations"
+        );
+        let Some(notifier) = self.notifier.clone() else {
+            return;
+        };
+        let event = JobEvent::DuplicateIngest {
+            target_id: duplicate.target_id,
+            station: duplicate.station,
+            job_id: duplicate.job_id,
+            other_station: duplicate.other_station,
+            other_job_id: duplicate.other_job_id,
+            minutes_apart: duplicate.minutes_apart,
+        };
+        tokio::spawn(async move {
+            if let Err(e) = notifier.notify(event).await {
+                warn!(error = %e, "Failed to send duplicate ingest notification");
+            }
+        });
+    }
+
+    /// List every station with its live progress.
+    async fn hub_stations(&self, id: Value) -> Response {
+        match store::list_stations(&self.conn).await {
+            Ok(stations) => Response::success(id, stations),
+            Err(e) => Response::internal_error(id, e.to_string()),
+        }
+    }
+
+    /// List jobs across the fleet.
+    async fn hub_jobs(&self, id: Value, params: Value) -> Response {
+        #[derive(Deserialize, Default)]
+        struct Params {
+            #[serde(default)]
+            limit: Option<u32>,
+            #[serde(default)]
+            offset: Option<u32>,
+            #[serde(default)]
+            station: Option<String>,
+            #[serde(default)]
+            status: Option<String>,
+        }
+
+        let params: Params = if params.is_null() {
+            Params::default()
+        } else {
+            match serde_json::from_value(params) {
+                Ok(p) => p,
+                Err(e) => return Response::invalid_params(id, e.to_string()),
+            }
+        };
+
+        match store::list_jobs(
+            &self.conn,
+            params.limit.unwrap_or(50),
+            params.offset.unwrap_or(0),
+            params.station,
+            params.status,
+        )
+        .await
+        {
+            Ok(jobs) => Response::success(id, jobs),
+            Err(e) => Response::internal_error(id, e.to_string()),
+        }
+    }
+
+    /// List flagged duplicate ingests, newest first.
+    async fn hub_duplicates(&self, id: Value, params: Value) -> Response {
+        #[derive(Deserialize, Default)]
+        struct Params {
+            #[serde(default)]
+            limit: Option<u32>,
+        }
+
+        let params: Params = if params.is_null() {
+            Params::default()
+        } else {
+            match serde_json::from_value(params) {
+                Ok(p) => p,
+                Err(e) => return Response::invalid_params(id, e.to_string()),
+            }
+        };
+
+        match store::list_duplicates(&self.conn, params.limit.unwrap_or(50)).await {
+            Ok(duplicates) => Response::success(id, duplicates),
+            Err(e) => Response::internal_error(id, e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Central hub for multi-station facilities.
+//!
+//! `bksd hub` collects reports pushed by every ingest daemon into one
+//! combined database and serves a fleet-wide dashboard over them. Each
+//! station runs the `reporter`, which periodically sends its recent jobs and
+//! live progress to the hub's `hub.report` RPC method, authenticated with a
+//! shared token. Reports are idempotent (jobs are keyed by station and job
+//! ID), so a hub that was offline simply catches up on the next report.
+//!
+//! Job history travels store-and-forward: each job change is queued in the
+//! station's outbox (see `db::outbox`) and resent with backoff until the hub
+//! acknowledges it, so every status change reaches the hub even across long
+//! network outages. The hub deduplicates events by ID.
+//!
+//! The hub also catches a card ingested on two stations within a few
+//! minutes of each other, which is usually an operator re-inserting a card
+//! that was already backed up. Such jobs are flagged as duplicate ingests and
+//! announced through the hub's notification channel.
+//!
+//! ## Architecture
+//!
+//! - `store`: SQLite database of stations and their jobs
+//! - `methods`: `hub.*` RPC methods, served over the same TCP and WebSocket
+//!   transports as the daemon's
+//! - `reporter`: Station-side task that pushes reports to the hub
+
+mod methods;
+pub mod reporter;
+pub mod store;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::core::Job;
+use crate::core::progress::JobProgressDetails;
+use crate::core::transfer_engine::TransferStatus;
+use crate::db::outbox::OutboxEvent;
+
+pub use methods::HubHandler;
+
+/// Dashboard page served by the hub
+pub const HUB_HTML: &str = include_str!("../web/assets/hub.html");
+
+/// Default hub database location.
+pub const DEFAULT_DATABASE: &str = "/var/lib/bksd/hub.db";
+
+/// Everything a station tells the hub in one push.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationReport {
+    /// Station name, unique across the facility
+    pub station: String,
+    /// bksd version the station runs
+    pub version: String,
+    /// Live progress of the station's active jobs, keyed by job ID
+    #[serde(default)]
+    pub active: HashMap<String, TransferStatus>,
+    #[serde(default)]
+    pub details: HashMap<String, JobProgressDetails>,
+    /// The station's most recent jobs
+    #[serde(default)]
+    pub jobs: Vec<Job>,
+    /// Queued job changes from the station's outbox, oldest first
+    #[serde(default)]
+    pub events: Vec<OutboxEvent>,
+}
+
+/// A station as last seen by the hub.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Station {
+    pub name: String,
+    pub version: String,
+    pub last_seen_at: String,
+    pub active: HashMap<String, TransferStatus>,
+    pub details: HashMap<String, JobProgressDetails>,
+    /// Jobs the hub holds for the station
+    pub job_count: u64,
+    pub failed_count: u64,
+}
+
+/// A job reported by a station.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationJob {
+    pub station: String,
+    #[serde(flatten)]
+    pub job: Job,
+}
+
+/// A card that was ingested on a second station shortly after another
+/// station ingested it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateIngest {
+    /// Card UUID both jobs backed up
+    pub target_id: String,
+    /// The job reported second, flagged as the duplicate
+    pub station: String,
+    pub job_id: String,
+    /// The matching job on another station
+    pub other_station: String,
+    pub other_job_id: String,
+    /// Minutes between the two jobs being created
+    pub minutes_apart: u32,
+    pub detected_at: String,
+}
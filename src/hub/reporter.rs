@@ -0,0 +1,116 @@
+//! Station side of the hub: pushes this daemon's recent jobs, live progress
+//! and queued outbox events to the hub every `hub_report_secs`, backing off
+//! while the hub can't be reached.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{info, warn};
+
+use super::StationReport;
+use crate::context::AppContext;
+use crate::core::destination;
+use crate::rpc::RpcClient;
+
+/// Jobs included in each report. The hub keeps older ones from earlier
+/// reports, so this only needs to cover what changed since the last push.
+const REPORT_JOBS: u32 = 50;
+
+/// Outbox events sent per report. A larger backlog is drained over
+/// back-to-back reports.
+const REPORT_EVENTS: u32 = 200;
+
+/// Longest wait between attempts while the hub is unreachable
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Report to the hub until the task is aborted.
+pub async fn run(ctx: AppContext, hub_addr: SocketAddr, token: String) {
+    let station = ctx
+        .config
+        .station_name
+        .clone()
+        .unwrap_or_else(destination::hostname);
+    let interval = Duration::from_secs(ctx.config.hub_report_secs.max(1));
+    let client = RpcClient::new(hub_addr);
+
+    info!(hub = %hub_addr, station = %station, "Reporting to hub");
+
+    let mut failures: u32 = 0;
+    loop {
+        let delay = match push(&ctx, &client, &station, &token).await {
+            Ok(backlog) => {
+                if failures > 0 {
+                    info!(hub = %hub_addr, "Hub reachable again");
+                }
+                failures = 0;
+                // Keep draining a backlog instead of waiting a full interval
+                if backlog { Duration::ZERO } else { interval }
+            }
+            Err(e) => {
+                // Only log the first failure of an outage
+                if failures == 0 {
+                    warn!(hub = %hub_addr, error = %e, "Failed to report to hub, will retry");
+                }
+                failures = failures.saturating_add(1);
+                backoff(interval, failures)
+            }
+        };
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Wait before the next attempt after `failures` failed ones in a row:
+/// the report interval, doubling each time up to `MAX_BACKOFF`.
+fn backoff(interval: Duration, failures: u32) -> Duration {
+    let factor = 1u32 << failures.saturating_sub(1).min(16);
+    interval
+        .saturating_mul(factor)
+        .min(MAX_BACKOFF.max(interval))
+}
+
+/// Send one report and drop the events the hub acknowledged. Returns whether
+/// more events are waiting.
+async fn push(ctx: &AppContext, client: &RpcClient, station: &str, token: &str) -> Result<bool> {
+    #[derive(Deserialize)]
+    struct Ack {
+        #[serde(default)]
+        acked: Vec<String>,
+    }
+
+    let events = ctx.db.outbox_pending(REPORT_EVENTS).await?;
+    let full_batch = events.len() == REPORT_EVENTS as usize;
+    let report = StationReport {
+        station: station.to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        active: ctx.progress.get_all().await,
+        details: ctx.progress.get_details().await,
+        jobs: ctx.db.list_jobs(REPORT_JOBS, 0, None).await?,
+        events,
+    };
+
+    let mut params = serde_json::to_value(report)?;
+    params["token"] = Value::String(token.to_string());
+    let ack: Ack = client.call("hub.report", Some(params)).await?;
+
+    let progressed = !ack.acked.is_empty();
+    ctx.db.outbox_ack(ack.acked).await?;
+    Ok(full_batch && progressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_cap() {
+        let interval = Duration::from_secs(10);
+        assert_eq!(backoff(interval, 1), Duration::from_secs(10));
+        assert_eq!(backoff(interval, 2), Duration::from_secs(20));
+        assert_eq!(backoff(interval, 4), Duration::from_secs(80));
+        assert_eq!(backoff(interval, 10), MAX_BACKOFF);
+        assert_eq!(backoff(interval, u32::MAX), MAX_BACKOFF);
+    }
+}
@@ -0,0 +1,501 @@
+//! Hub database: the stations reporting in and every job they have reported.
+
+use anyhow::{Result, anyhow};
+use std::path::Path;
+use tokio_rusqlite::rusqlite::{self, OptionalExtension};
+use tokio_rusqlite::{Connection, params};
+
+use super::{DuplicateIngest, Station, StationJob, StationReport};
+use crate::core::Job;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS stations (
+    name TEXT PRIMARY KEY,
+    version TEXT NOT NULL,
+    last_seen_at TEXT NOT NULL,
+    -- JSON maps of job ID to TransferStatus / JobProgressDetails
+    active TEXT NOT NULL DEFAULT '{}',
+    details TEXT NOT NULL DEFAULT '{}'
+);
+
+CREATE TABLE IF NOT EXISTS station_jobs (
+    station TEXT NOT NULL REFERENCES stations(name),
+    id TEXT NOT NULL,
+    target_id TEXT NOT NULL,
+    destination_path TEXT,
+    created_at TEXT NOT NULL,
+    status TEXT NOT NULL,
+    archive_label TEXT,
+    origin_host TEXT,
+    PRIMARY KEY (station, id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_station_jobs_created_at ON station_jobs(created_at);
+
+-- Outbox events already applied, so resent ones are ignored
+CREATE TABLE IF NOT EXISTS station_events (
+    station TEXT NOT NULL,
+    id TEXT NOT NULL,
+    received_at TEXT NOT NULL,
+    PRIMARY KEY (station, id)
+);
+
+-- Jobs for a card another station ingested shortly before or after
+CREATE TABLE IF NOT EXISTS duplicate_ingests (
+    station TEXT NOT NULL,
+    job_id TEXT NOT NULL,
+    target_id TEXT NOT NULL,
+    other_station TEXT NOT NULL,
+    other_job_id TEXT NOT NULL,
+    minutes_apart INTEGER NOT NULL,
+    detected_at TEXT NOT NULL,
+    PRIMARY KEY (station, job_id)
+);
+";
+
+/// What the hub made of a station's report.
+#[derive(Debug, Default)]
+pub struct RecordOutcome {
+    /// Outbox events the station can drop
+    pub acked: Vec<String>,
+    /// Jobs first seen in this report whose card another station ingested
+    /// within the duplicate window
+    pub duplicates: Vec<DuplicateIngest>,
+}
+
+/// Open (creating if needed) the hub database at `path`.
+pub async fn open(path: &Path) -> Result<Connection> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    let conn = Connection::open(path).await?;
+    init(&conn).await?;
+    Ok(conn)
+}
+
+/// Create the hub tables if they don't exist.
+pub async fn init(conn: &Connection) -> Result<()> {
+    conn.call(|c| {
+        c.execute_batch(SCHEMA)?;
+        Ok::<_, rusqlite::Error>(())
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to initialise hub database: {}", e))
+}
+
+/// Store a station's report, replacing its live progress and adding or
+/// updating each reported job. Outbox events are applied before the job
+/// snapshot, in order, skipping any already seen. A job new to the hub is
+/// flagged as a duplicate ingest if another station created a job for the
+/// same card within `duplicate_window_mins` of it (0 disables the check).
+pub async fn record(
+    conn: &Connection,
+    report: StationReport,
+    seen_at: String,
+    duplicate_window_mins: u32,
+) -> Result<RecordOutcome> {
+    let active = serde_json::to_string(&report.active)?;
+    let details = serde_json::to_string(&report.details)?;
+
+    conn.call(move |c| {
+        let tx = c.transaction()?;
+        tx.execute(
+            "INSERT INTO stations (name, version, last_seen_at, active, details)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(name) DO UPDATE SET
+                 version = excluded.version,
+                 last_seen_at = excluded.last_seen_at,
+                 active = excluded.active,
+                 details = excluded.details",
+            params![report.station, report.version, seen_at, active, details],
+        )?;
+
+        let mut outcome = RecordOutcome::default();
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO station_jobs
+                     (station, id, target_id, destination_path, created_at, status,
+                      archive_label, origin_host)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                 ON CONFLICT(station, id) DO UPDATE SET
+                     destination_path = excluded.destination_path,
+                     status = excluded.status,
+                     archive_label = excluded.archive_label",
+            )?;
+            let mut seen = tx.prepare(
+                "INSERT OR IGNORE INTO station_events (station, id, received_at)
+                 VALUES (?1, ?2, ?3)",
+            )?;
+            let mut known =
+                tx.prepare("SELECT 1 FROM station_jobs WHERE station = ?1 AND id = ?2")?;
+            let mut other = tx.prepare(
+                "SELECT station, id,
+                     CAST(ROUND(ABS(julianday(created_at) - julianday(?3)) * 1440) AS INTEGER)
+                 FROM station_jobs
+                 WHERE target_id = ?1 AND station != ?2
+                     AND ABS(julianday(created_at) - julianday(?3)) * 1440 <= ?4
+                 ORDER BY ABS(julianday(created_at) - julianday(?3))
+                 LIMIT 1",
+            )?;
+            let mut flag = tx.prepare(
+                "INSERT OR IGNORE INTO duplicate_ingests
+                     (station, job_id, target_id, other_station, other_job_id,
+                      minutes_apart, detected_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+
+            // Events not seen before, then the snapshot of recent jobs
+            let mut jobs = Vec::with_capacity(report.events.len() + report.jobs.len());
+            for event in &report.events {
+                if seen.execute(params![report.station, event.id, seen_at])? > 0 {
+                    jobs.push(&event.job);
+                }
+                outcome.acked.push(event.id.clone());
+            }
+            jobs.extend(&report.jobs);
+
+            for job in jobs {
+                if duplicate_window_mins > 0
+                    && !job.target_id.is_empty()
+                    && !known.exists(params![report.station, job.id])?
+                    && let Some((other_station, other_job_id, minutes_apart)) = other
+                        .query_row(
+                            params![
+                                job.target_id,
+                                report.station,
+                                job.created_at,
+                                duplicate_window_mins
+                            ],
+                            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                        )
+                        .optional()?
+                {
+                    let duplicate = DuplicateIngest {
+                        target_id: job.target_id.clone(),
+                        station: report.station.clone(),
+                        job_id: job.id.clone(),
+                        other_station,
+                        other_job_id,
+                        minutes_apart,
+                        detected_at: seen_at.clone(),
+                    };
+                    flag.execute(params![
+                        duplicate.station,
+                        duplicate.job_id,
+                        duplicate.target_id,
+                        duplicate.other_station,
+                        duplicate.other_job_id,
+                        duplicate.minutes_apart,
+                        duplicate.detected_at,
+                    ])?;
+                    outcome.duplicates.push(duplicate);
+                }
+                stmt.execute(params![
+                    report.station,
+                    job.id,
+                    job.target_id,
+                    job.destination_path,
+                    job.created_at,
+                    job.status,
+                    job.archive_label,
+                    job.origin_host,
+                ])?;
+            }
+        }
+
+        tx.commit()?;
+        Ok::<_, rusqlite::Error>(outcome)
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to record station report: {}", e))
+}
+
+/// All stations that have ever reported, by name.
+pub async fn list_stations(conn: &Connection) -> Result<Vec<Station>> {
+    let rows = conn
+        .call(|c| {
+            let mut stmt = c.prepare(
+                "SELECT s.name, s.version, s.last_seen_at, s.active, s.details,
+                     (SELECT COUNT(*) FROM station_jobs WHERE station = s.name),
+                     (SELECT COUNT(*) FROM station_jobs
+                      WHERE station = s.name AND LOWER(status) = 'failed')
+                 FROM stations s
+                 ORDER BY s.name",
+            )?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, String>(4)?,
+                        row.get::<_, u64>(5)?,
+                        row.get::<_, u64>(6)?,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok::<_, rusqlite::Error>(rows)
+        })
+        .await
+        .map_err(|e| anyhow!("Failed to list stations: {}", e))?;
+
+    rows.into_iter()
+        .map(
+            |(name, version, last_seen_at, active, details, job_count, failed_count)| {
+                Ok(Station {
+                    name,
+                    version,
+                    last_seen_at,
+                    active: serde_json::from_str(&active)?,
+                    details: serde_json::from_str(&details)?,
+                    job_count,
+                    failed_count,
+                })
+            },
+        )
+        .collect()
+}
+
+/// Jobs across all stations newest first, optionally only one station's or
+/// only those with a given status.
+pub async fn list_jobs(
+    conn: &Connection,
+    limit: u32,
+    offset: u32,
+    station: Option<String>,
+    status: Option<String>,
+) -> Result<Vec<StationJob>> {
+    conn.call(move |c| {
+        let mut stmt = c.prepare(
+            "SELECT station, id, target_id, destination_path, created_at, status,
+                 archive_label, origin_host
+             FROM station_jobs
+             WHERE (?1 IS NULL OR station = ?1) AND (?2 IS NULL OR status = ?2)
+             ORDER BY created_at DESC
+             LIMIT ?3 OFFSET ?4",
+        )?;
+        let jobs = stmt
+            .query_map(params![station, status, limit, offset], |row| {
+                Ok(StationJob {
+                    station: row.get(0)?,
+                    job: Job {
+                        id: row.get(1)?,
+                        target_id: row.get(2)?,
+                        destination_path: row.get(3)?,
+                        created_at: row.get(4)?,
+                        status: row.get(5)?,
+                        archive_label: row.get(6)?,
+                        origin_host: row.get(7)?,
+                        label_fields: Default::default(),
+                    },
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok::<_, rusqlite::Error>(jobs)
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to list station jobs: {}", e))
+}
+
+/// Flagged duplicate ingests, newest first.
+pub async fn list_duplicates(conn: &Connection, limit: u32) -> Result<Vec<DuplicateIngest>> {
+    conn.call(move |c| {
+        let mut stmt = c.prepare(
+            "SELECT target_id, station, job_id, other_station, other_job_id,
+                 minutes_apart, detected_at
+             FROM duplicate_ingests
+             ORDER BY detected_at DESC
+             LIMIT ?1",
+        )?;
+        let duplicates = stmt
+            .query_map(params![limit], |row| {
+                Ok(DuplicateIngest {
+                    target_id: row.get(0)?,
+                    station: row.get(1)?,
+                    job_id: row.get(2)?,
+                    other_station: row.get(3)?,
+                    other_job_id: row.get(4)?,
+                    minutes_apart: row.get(5)?,
+                    detected_at: row.get(6)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok::<_, rusqlite::Error>(duplicates)
+    })
+    .await
+    .map_err(|e| anyhow!("Failed to list duplicate ingests: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::outbox::OutboxEvent;
+    use std::collections::HashMap;
+
+    fn job(id: &str, created_at: &str, status: &str) -> Job {
+        Job {
+            id: id.to_string(),
+            target_id: "card-1".to_string(),
+            destination_path: Some(format!("/backups/{}", id)),
+            created_at: created_at.to_string(),
+            status: status.to_string(),
+            archive_label: None,
+            origin_host: Some("ingest-1".to_string()),
+            label_fields: Default::default(),
+        }
+    }
+
+    fn report(station: &str, jobs: Vec<Job>) -> StationReport {
+        StationReport {
+            station: station.to_string(),
+            version: "0.1.0".to_string(),
+            active: HashMap::new(),
+            details: HashMap::new(),
+            jobs,
+            events: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reports_merge_across_stations() {
+        let conn = Connection::open_in_memory().await.unwrap();
+        init(&conn).await.unwrap();
+
+        let t1 = "2026-01-01T10:00:00.000Z";
+        let t2 = "2026-01-01T11:00:00.000Z";
+        record(
+            &conn,
+            report("ingest-1", vec![job("a", t1, "copying")]),
+            t1.to_string(),
+            0,
+        )
+        .await
+        .unwrap();
+        record(
+            &conn,
+            report("ingest-2", vec![job("b", t2, "failed")]),
+            t2.to_string(),
+            0,
+        )
+        .await
+        .unwrap();
+        // A later report updates the job rather than duplicating it
+        record(
+            &conn,
+            report("ingest-1", vec![job("a", t1, "complete")]),
+            t2.to_string(),
+            0,
+        )
+        .await
+        .unwrap();
+
+        let jobs = list_jobs(&conn, 10, 0, None, None).await.unwrap();
+        let ids: Vec<&str> = jobs.iter().map(|j| j.job.id.as_str()).collect();
+        assert_eq!(ids, ["b", "a"]);
+        assert_eq!(jobs[1].job.status, "complete");
+
+        let station_jobs = list_jobs(&conn, 10, 0, Some("ingest-2".to_string()), None)
+            .await
+            .unwrap();
+        assert_eq!(station_jobs.len(), 1);
+
+        let stations = list_stations(&conn).await.unwrap();
+        assert_eq!(stations.len(), 2);
+        assert_eq!(stations[0].name, "ingest-1");
+        assert_eq!(stations[0].last_seen_at, t2);
+        assert_eq!(stations[1].failed_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_resent_events_are_applied_once() {
+        let conn = Connection::open_in_memory().await.unwrap();
+        init(&conn).await.unwrap();
+
+        let t1 = "2026-01-01T10:00:00.000Z";
+        let event = |id: &str, status: &str| OutboxEvent {
+            id: id.to_string(),
+            job: job("a", t1, status),
+            created_at: t1.to_string(),
+        };
+
+        let mut first = report("ingest-1", Vec::new());
+        first.events = vec![event("e1", "copying"), event("e2", "complete")];
+        let acked = record(&conn, first, t1.to_string(), 0).await.unwrap().acked;
+        assert_eq!(acked, ["e1", "e2"]);
+
+        // The acknowledgement was lost, so the station sends e1 again
+        let mut resend = report("ingest-1", Vec::new());
+        resend.events = vec![event("e1", "copying")];
+        let acked = record(&conn, resend, t1.to_string(), 0)
+            .await
+            .unwrap()
+            .acked;
+        assert_eq!(acked, ["e1"]);
+
+        let jobs = list_jobs(&conn, 10, 0, None, None).await.unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].job.status, "complete");
+    }
+
+    #[tokio::test]
+    async fn test_card_ingested_on_two_stations_is_flagged() {
+        let conn = Connection::open_in_memory().await.unwrap();
+        init(&conn).await.unwrap();
+
+        let t1 = "2026-01-01T10:00:00.000Z";
+        let t2 = "2026-01-01T10:04:00.000Z";
+        let t3 = "2026-01-01T12:00:00.000Z";
+        let first = record(
+            &conn,
+            report("ingest-1", vec![job("a", t1, "complete")]),
+            t1.to_string(),
+            10,
+        )
+        .await
+        .unwrap();
+        assert!(first.duplicates.is_empty());
+
+        // Same card on another station four minutes later
+        let second = record(
+            &conn,
+            report("ingest-2", vec![job("b", t2, "copying")]),
+            t2.to_string(),
+            10,
+        )
+        .await
+        .unwrap();
+        assert_eq!(second.duplicates.len(), 1);
+        assert_eq!(second.duplicates[0].other_station, "ingest-1");
+        assert_eq!(second.duplicates[0].other_job_id, "a");
+        assert_eq!(second.duplicates[0].minutes_apart, 4);
+
+        // Later reports of the same job aren't flagged again
+        let again = record(
+            &conn,
+            report("ingest-2", vec![job("b", t2, "complete")]),
+            t3.to_string(),
+            10,
+        )
+        .await
+        .unwrap();
+        assert!(again.duplicates.is_empty());
+
+        // Outside the window, and on the same station, it's a new shoot
+        let later = record(
+            &conn,
+            report("ingest-1", vec![job("c", t3, "copying")]),
+            t3.to_string(),
+            10,
+        )
+        .await
+        .unwrap();
+        assert!(later.duplicates.is_empty());
+
+        let flagged = list_duplicates(&conn, 10).await.unwrap();
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].job_id, "b");
+    }
+}
@@ -4,6 +4,7 @@ pub mod config;
 pub mod context;
 pub mod core;
 pub mod db;
+pub mod hub;
 pub mod logging;
 pub mod rpc;
 pub mod service;
@@ -2,52 +2,56 @@
 //!
 //! This module provides structured logging using the `tracing` ecosystem.
 //! It supports both pretty console output and JSON output for machine parsing.
+//! Access log events can also be written to a file of their own.
 
+use anyhow::Context;
+use std::fs::OpenOptions;
+use std::path::PathBuf;
+use std::sync::Mutex;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Instant;
 use tracing::Level;
 use tracing_subscriber::{
-    EnvFilter,
+    EnvFilter, Layer,
+    filter::Targets,
     fmt::{self, format::FmtSpan},
     layer::SubscriberExt,
     util::SubscriberInitExt,
 };
 
+use crate::rpc::access;
+
 /// Configuration for the logging system.
+#[derive(Default)]
 pub struct LogConfig {
     /// Output logs as JSON (for machine parsing)
     pub json: bool,
     /// Enable verbose logging (sets default level to DEBUG)
     pub verbose: bool,
-}
-
-impl Default for LogConfig {
-    fn default() -> Self {
-        Self {
-            json: false,
-            verbose: false,
-        }
-    }
+    /// Append every RPC and web request to this file as JSON lines
+    pub access_log: Option<PathBuf>,
 }
 
 /// Initialize the tracing subscriber with the given configuration.
 ///
 /// This should be called early in main(), after config is loaded.
 /// The log level can be overridden at runtime via the `RUST_LOG` environment variable.
+/// The access log file gets every access event whatever the level. Fails
+/// if the access log can't be opened.
 ///
 /// # Examples
 ///
 /// ```ignore
 /// // Basic initialization with defaults
-/// bksd::logging::init(LogConfig::default());
+/// bksd::logging::init(LogConfig::default())?;
 ///
 /// // Verbose mode
-/// bksd::logging::init(LogConfig { verbose: true, ..Default::default() });
+/// bksd::logging::init(LogConfig { verbose: true, ..Default::default() })?;
 ///
 /// // JSON output for log aggregation
-/// bksd::logging::init(LogConfig { json: true, ..Default::default() });
+/// bksd::logging::init(LogConfig { json: true, ..Default::default() })?;
 /// ```
-pub fn init(config: LogConfig) {
+pub fn init(config: LogConfig) -> anyhow::Result<()> {
     // Determine default log level based on verbose flag
     let default_level = if config.verbose {
         Level::DEBUG
@@ -59,31 +63,52 @@ pub fn init(config: LogConfig) {
         EnvFilter::new(format!("bksd={}", default_level.as_str().to_lowercase()))
     });
 
+    let access_layer = match &config.access_log {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open access log {}", path.display()))?;
+            Some(
+                fmt::layer()
+                    .json()
+                    .with_writer(Mutex::new(file))
+                    .with_filter(Targets::new().with_target(access::TARGET, Level::TRACE)),
+            )
+        }
+        None => None,
+    };
+
     if config.json {
         // JSON output for structured logging / log aggregation
         tracing_subscriber::registry()
-            .with(env_filter)
+            .with(access_layer)
             .with(
                 fmt::layer()
                     .json()
                     .with_span_events(FmtSpan::CLOSE)
                     .with_current_span(true)
-                    .with_target(true),
+                    .with_target(true)
+                    .with_filter(env_filter),
             )
             .init();
     } else {
         // Pretty console output for human readability
         tracing_subscriber::registry()
-            .with(env_filter)
+            .with(access_layer)
             .with(
                 fmt::layer()
                     .with_target(false)
                     .with_thread_ids(false)
                     .with_file(false)
-                    .with_line_number(false),
+                    .with_line_number(false)
+                    .with_filter(env_filter),
             )
             .init();
     }
+
+    Ok(())
 }
 
 /// A rate limiter for throttling log messages.
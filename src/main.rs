@@ -1,7 +1,24 @@
 use anyhow::{Context, Result};
+use bksd::cli::exit::{self, InsufficientSpace, JobOutcome, NotVerified, TimedOut};
+use bksd::core::BlockDevice;
+use bksd::core::JobWithHistory;
+use bksd::core::badge;
+use bksd::core::capacity::{self, CapacityPlan};
+use bksd::core::failure;
+use bksd::core::models::Job;
+use bksd::core::notifications;
+use bksd::core::progress::JobProgressDetails;
+use bksd::core::restore::{self, BackedUpFile};
+use bksd::core::scrub;
+use bksd::core::signing;
+use bksd::core::space;
+use bksd::core::telemetry;
+use bksd::core::timestamp::{self, DisplayZone};
 use bksd::core::transfer_engine::TransferEngineType;
+use bksd::core::verifier;
+use bksd::hub::{self, HubHandler};
 use bksd::logging::{self, LogConfig};
-use bksd::rpc::{RpcClient, RpcServer};
+use bksd::rpc::{Handler, RpcClient, RpcServer};
 use bksd::service::{ServiceManager, configs_differ, prompt_restart};
 use bksd::web::WebServer;
 use bksd::{config, context, core::Orchestrator, db};
@@ -10,7 +27,9 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::process::ExitCode;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 #[derive(Parser)]
 #[command(name = "bksd")]
@@ -26,8 +45,28 @@ enum Commands {
     Start(StartArgs),
     /// Query daemon status and active jobs
     Status(StatusArgs),
+    /// List recent jobs, or show one job and exit with its outcome
+    Jobs(JobsArgs),
+    /// Block until a job (or every active job) finishes, exiting with its outcome
+    Wait(WaitArgs),
     /// Interactive TUI for browsing jobs
     Tui(TuiArgs),
+    /// Check whether a number of cards will fit in the backup directory
+    Plan(PlanArgs),
+    /// Collect reports from several ingest stations and serve a fleet dashboard
+    Hub(HubArgs),
+    /// Copy a single file back out of a backup, checked against its hash
+    Restore(RestoreArgs),
+    /// Check a backup folder against its badge, manifest or source, without the daemon
+    Verify(VerifyArgs),
+    /// Show, generate or rotate the key badges are signed with
+    Key(KeyArgs),
+    /// Write a finished job, its history and manifest out as one JSON document
+    Export(ExportArgs),
+    /// Load a job document written by `bksd export` on another station
+    Import(ImportArgs),
+    /// Back up an already-mounted directory, such as a card mounted by hand
+    Backup(BackupArgs),
 }
 
 #[derive(Args)]
@@ -36,10 +75,165 @@ struct StatusArgs {
     addr: SocketAddr,
 }
 
+#[derive(Args)]
+struct JobsArgs {
+    /// Job ID to show; exits non-zero if the job failed
+    id: Option<String>,
+
+    /// Only list jobs with this status (e.g. failed)
+    #[arg(long)]
+    status: Option<String>,
+
+    #[arg(short, long, default_value_t = 20)]
+    limit: u32,
+
+    #[arg(short, long, default_value = "127.0.0.1:9847")]
+    addr: SocketAddr,
+}
+
+#[derive(Args)]
+struct WaitArgs {
+    /// Job ID to wait for; defaults to every job active when the wait starts
+    #[arg(long)]
+    job: Option<String>,
+
+    /// Give up after this many seconds
+    #[arg(long)]
+    timeout: Option<u64>,
+
+    #[arg(short, long, default_value = "127.0.0.1:9847")]
+    addr: SocketAddr,
+}
+
+#[derive(Args)]
+struct PlanArgs {
+    /// Card size, e.g. 128G, 64GB, 1.5T or 256GiB
+    card_size: String,
+
+    /// Number of cards
+    #[arg(default_value_t = 1)]
+    count: u64,
+
+    #[arg(short, long, default_value = "127.0.0.1:9847")]
+    addr: SocketAddr,
+}
+
+#[derive(Args)]
+struct HubArgs {
+    /// Address stations push reports to
+    #[arg(long, default_value = "0.0.0.0:9850")]
+    bind: SocketAddr,
+
+    /// Address of the fleet dashboard
+    #[arg(long, default_value = "0.0.0.0:9851")]
+    web_bind: SocketAddr,
+
+    /// Combined database of every station's jobs
+    #[arg(long, default_value = hub::DEFAULT_DATABASE)]
+    database: PathBuf,
+
+    /// Flag a card ingested on two stations within this many minutes
+    /// (0 disables)
+    #[arg(long, default_value_t = 10)]
+    duplicate_window_mins: u32,
+}
+
+#[derive(Args)]
+struct RestoreArgs {
+    /// File to restore, as <job_id>:<relative_path>
+    #[arg(long)]
+    file: String,
+
+    /// File or directory to restore it to
+    target: PathBuf,
+
+    #[arg(short, long, default_value = "127.0.0.1:9847")]
+    addr: SocketAddr,
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    /// Job ID to export
+    id: String,
+
+    /// File to write the document to (default: stdout)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    #[arg(short, long, default_value = "127.0.0.1:9847")]
+    addr: SocketAddr,
+}
+
+#[derive(Args)]
+struct ImportArgs {
+    /// Job document written by `bksd export`
+    file: PathBuf,
+
+    #[arg(short, long, default_value = "127.0.0.1:9847")]
+    addr: SocketAddr,
+}
+
+#[derive(Args)]
+struct BackupArgs {
+    /// Directory to back up, on the daemon's host
+    path: PathBuf,
+
+    /// Label to file the backup under (default: the directory's name)
+    #[arg(long)]
+    label: Option<String>,
+
+    #[arg(short, long, default_value = "127.0.0.1:9847")]
+    addr: SocketAddr,
+}
+
+#[derive(Args)]
+struct VerifyArgs {
+    /// Backup folder to check. Its BKSD_OK badge is used if it has one,
+    /// else its BKSD_MANIFEST.b3
+    path: PathBuf,
+
+    /// Hex ed25519 key the badge must be signed with (default: this
+    /// machine's signing key or one it has retired)
+    #[arg(long)]
+    public_key: Option<String>,
+
+    /// Check against this job's manifest in the local database instead
+    #[arg(long, conflicts_with = "source")]
+    job: Option<String>,
+
+    /// Check against a still-mounted source (the card), hashing both sides
+    #[arg(long)]
+    source: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct KeyArgs {
+    #[command(subcommand)]
+    action: KeyAction,
+
+    /// Signing key file (default: BKSD_SIGNING_KEY)
+    #[arg(long, global = true)]
+    key: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum KeyAction {
+    /// Print the public key clients should trust, and any retired keys
+    Show,
+    /// Create the signing key if there isn't one yet
+    Generate,
+    /// Replace the signing key, keeping the old public key trusted locally
+    Rotate,
+}
+
 #[derive(Args)]
 struct TuiArgs {
     #[arg(short, long, default_value = "127.0.0.1:9847")]
     addr: SocketAddr,
+
+    /// Full-screen rotating summary for a wall display; needs no input
+    #[arg(long)]
+    kiosk: bool,
 }
 
 #[derive(Args, Serialize)]
@@ -76,14 +270,32 @@ struct StartArgs {
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     let cli = Cli::parse();
 
-    match cli.command {
+    let result = match cli.command {
         Commands::Start(args) => run_start(args).await,
         Commands::Status(args) => run_status(args.addr).await,
-        Commands::Tui(args) => bksd::cli::tui::run(args.addr).await,
-    }
+        Commands::Jobs(args) => run_jobs(args).await,
+        Commands::Wait(args) => run_wait(args).await,
+        Commands::Tui(args) => run_tui(args).await,
+        Commands::Plan(args) => run_plan(args).await,
+        Commands::Hub(args) => run_hub(args).await,
+        Commands::Restore(args) => run_restore(args).await,
+        Commands::Verify(args) => run_verify(args).await,
+        Commands::Key(args) => run_key(args),
+        Commands::Export(args) => run_export(args).await,
+        Commands::Import(args) => run_import(args).await,
+        Commands::Backup(args) => run_backup(args).await,
+    };
+
+    exit::report(result)
+}
+
+async fn run_tui(args: TuiArgs) -> Result<()> {
+    let config = config::AppConfig::new(None::<&()>)?;
+    let zone = DisplayZone::from_config(config.timezone.as_deref())?;
+    bksd::cli::tui::run(args.addr, &config.tui, zone, args.kiosk).await
 }
 
 async fn run_start(args: StartArgs) -> Result<()> {
@@ -144,15 +356,25 @@ async fn run_foreground(args: StartArgs) -> Result<()> {
     logging::init(LogConfig {
         json: config.log_json,
         verbose: config.verbose,
-    });
+        access_log: config.access_log.clone(),
+    })?;
 
     #[cfg(target_os = "linux")]
     if !config.simulation {
-        check_root_privileges()?;
+        match config.hardware_adapter {
+            config::HardwareAdapterType::Udev => check_root_privileges()?,
+            // udisks2 mounts on the user's behalf
+            config::HardwareAdapterType::Udisks2 => {
+                #[cfg(not(feature = "udisks2"))]
+                anyhow::bail!(figment::Error::from(
+                    "The udisks2 adapter requires bksd built with the udisks2 feature".to_string()
+                ));
+            }
+        }
     }
 
-    let db_conn = db::init().await?;
-    let ctx = context::AppContext::new(config, db_conn);
+    let store = db::open(&config).await?;
+    let ctx = context::AppContext::new(config, store);
     run_daemon(ctx).await.context("Failed to start daemon")
 }
 
@@ -183,7 +405,59 @@ async fn run_daemon(ctx: context::AppContext) -> Result<()> {
         None
     };
 
-    let result = Orchestrator::new(ctx).start().await;
+    let hub_reporter = match (ctx.config.hub_addr, &ctx.config.hub_token) {
+        (Some(addr), Some(token)) => Some(tokio::spawn(hub::reporter::run(
+            ctx.clone(),
+            addr,
+            token.clone(),
+        ))),
+        (Some(_), None) => {
+            tracing::warn!("BKSD_HUB_ADDR is set without BKSD_HUB_TOKEN, not reporting to hub");
+            None
+        }
+        _ => None,
+    };
+
+    let telemetry = match &ctx.config.telemetry_endpoint {
+        Some(_) if ctx.config.simulation => {
+            tracing::info!("Simulation mode, not sending telemetry");
+            None
+        }
+        Some(endpoint) => Some(tokio::spawn(telemetry::run(ctx.clone(), endpoint.clone()))),
+        None => None,
+    };
+
+    let scrubber = ctx
+        .scrub_schedule
+        .clone()
+        .map(|schedule| tokio::spawn(scrub::run_scheduled(ctx.clone(), schedule)));
+
+    let space_monitor = ctx
+        .config
+        .min_free_space_gb
+        .map(|min_free_space_gb| tokio::spawn(space::run(ctx.clone(), min_free_space_gb)));
+
+    let progress = ctx.progress.clone();
+    let result = match Orchestrator::new(ctx) {
+        Ok(orchestrator) => orchestrator.start(shutdown_signal()).await,
+        Err(e) => Err(e),
+    };
+
+    if let Some(handle) = hub_reporter {
+        handle.abort();
+    }
+
+    if let Some(handle) = telemetry {
+        handle.abort();
+    }
+
+    if let Some(handle) = scrubber {
+        handle.abort();
+    }
+
+    if let Some(handle) = space_monitor {
+        handle.abort();
+    }
 
     if let Some((server, handle)) = rpc_server {
         server.shutdown();
@@ -195,9 +469,65 @@ async fn run_daemon(ctx: context::AppContext) -> Result<()> {
         handle.abort();
     }
 
+    // Interrupted copies run on blocking threads the runtime would wait for
+    // on the way out, so leave without it
+    if result.is_ok() && !progress.running().await.is_empty() {
+        std::process::exit(0);
+    }
+
     result
 }
 
+/// Resolves on SIGTERM (`systemctl stop`) or Ctrl-C.
+async fn shutdown_signal() {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            tokio::select! {
+                _ = terminate.recv() => {}
+                _ = tokio::signal::ctrl_c() => {}
+            }
+        }
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to listen for SIGTERM, stopping on Ctrl-C only");
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+async fn run_hub(args: HubArgs) -> Result<()> {
+    let config = config::AppConfig::new(None::<&()>)?;
+
+    logging::init(LogConfig {
+        json: config.log_json,
+        verbose: config.verbose,
+        access_log: config.access_log.clone(),
+    })?;
+
+    let token = config
+        .hub_token
+        .clone()
+        .context("BKSD_HUB_TOKEN must be set so stations can authenticate")?;
+    let conn = hub::store::open(&args.database).await?;
+    let notifier = notifications::create_notifier(&config.notifications);
+    let handler: Arc<dyn Handler> = Arc::new(HubHandler::new(
+        conn,
+        token,
+        args.duplicate_window_mins,
+        notifier,
+    ));
+
+    let rpc_server = RpcServer::with_handler(handler.clone(), args.bind);
+    let web_server = WebServer::with_handler(handler, hub::HUB_HTML, args.web_bind);
+
+    tokio::select! {
+        result = rpc_server.start() => result.context("Hub RPC server failed"),
+        result = web_server.start() => result.context("Hub dashboard failed"),
+        _ = tokio::signal::ctrl_c() => Ok(()),
+    }
+}
+
 async fn run_status(addr: SocketAddr) -> Result<()> {
     let client = RpcClient::new(addr);
 
@@ -205,6 +535,8 @@ async fn run_status(addr: SocketAddr) -> Result<()> {
     struct DaemonStatus {
         version: String,
         uptime_secs: u64,
+        #[serde(default)]
+        started_at: Option<String>,
         active_jobs: usize,
         simulation: bool,
     }
@@ -217,6 +549,12 @@ async fn run_status(addr: SocketAddr) -> Result<()> {
     println!("Daemon Status");
     println!("  Version:     {}", status.version);
     println!("  Uptime:      {}s", status.uptime_secs);
+    if let Some(started_at) = &status.started_at {
+        println!(
+            "  Started:     {}",
+            DisplayZone::Local.format(started_at, "%Y-%m-%d %H:%M:%S %Z")
+        );
+    }
     println!(
         "  Mode:        {}",
         if status.simulation {
@@ -231,6 +569,8 @@ async fn run_status(addr: SocketAddr) -> Result<()> {
         #[derive(Deserialize)]
         struct ActiveProgress {
             jobs: HashMap<String, serde_json::Value>,
+            #[serde(default)]
+            details: HashMap<String, JobProgressDetails>,
             #[allow(dead_code)]
             count: usize,
         }
@@ -245,6 +585,8 @@ async fn run_status(addr: SocketAddr) -> Result<()> {
                 .unwrap_or("unknown");
 
             let job_short = &job_id[..8];
+            let details = progress.details.get(&job_id);
+            let phase = details.map(|d| d.phase.as_str()).unwrap_or(state);
 
             match state {
                 "in_progress" => {
@@ -257,32 +599,538 @@ async fn run_status(addr: SocketAddr) -> Result<()> {
                         .and_then(|f| f.as_str())
                         .unwrap_or("");
                     let bar = progress_bar(pct, 20);
-                    println!("  {} {} {:>3}% {}", job_short, bar, pct, file);
+                    println!("  {} {} {:>3}% {} {}", job_short, bar, pct, phase, file);
                 }
                 "verifying" => {
                     let current = status.get("current").and_then(|c| c.as_u64()).unwrap_or(0);
                     let total = status.get("total").and_then(|t| t.as_u64()).unwrap_or(0);
-                    let pct = if total > 0 {
-                        ((current * 100) / total) as u8
+                    let field = |name| status.get(name).and_then(|v| v.as_u64()).unwrap_or(0);
+                    let (verified, total_bytes) = (field("bytes_verified"), field("total_bytes"));
+                    if let Some(pct) = (verified * 100).checked_div(total_bytes) {
+                        let pct = pct.min(100) as u8;
+                        let speed = match field("bytes_per_sec") {
+                            0 => String::new(),
+                            rate => format!(" at {}/s", format_bytes(rate)),
+                        };
+                        println!(
+                            "  {} {} {:>3}% verifying {} / {}{}",
+                            job_short,
+                            progress_bar(pct, 20),
+                            pct,
+                            format_bytes(verified),
+                            format_bytes(total_bytes),
+                            speed
+                        );
                     } else {
-                        100
-                    };
-                    let bar = progress_bar(pct, 20);
-                    println!(
-                        "  {} {} {:>3}% verifying {}/{}",
-                        job_short, bar, pct, current, total
-                    );
+                        let pct = (current * 100).checked_div(total).unwrap_or(100) as u8;
+                        let bar = progress_bar(pct, 20);
+                        println!(
+                            "  {} {} {:>3}% verifying {}/{}",
+                            job_short, bar, pct, current, total
+                        );
+                    }
                 }
                 _ => {
-                    println!("  {} - {}", job_short, state);
+                    println!("  {} - {}", job_short, phase);
                 }
             }
+
+            if let Some(details) = details {
+                println!("      Device:      {}", details.info.device_label);
+                println!("      Destination: {}", details.info.destination.display());
+                if let Some(speed) = details.bytes_per_sec {
+                    println!("      Speed:       {}/s", format_bytes(speed));
+                }
+                if let Some(eta) = details.eta_seconds {
+                    println!("      ETA:         {}", format_eta(eta));
+                }
+            }
+        }
+    }
+
+    // Jobs that failed since the daemon started, with what to do about them
+    if let Some(started) = status.started_at.as_deref().and_then(timestamp::parse) {
+        let params = serde_json::json!({ "limit": 5, "status": "failed" });
+        let failed: Vec<Job> = client.call("jobs.list", Some(params)).await?;
+        let failed: Vec<Job> = failed
+            .into_iter()
+            .filter(|job| timestamp::parse(&job.created_at).is_some_and(|t| t >= started))
+            .collect();
+        if !failed.is_empty() {
+            println!("\nRecent Failures:");
+        }
+        for job in failed {
+            let job: JobWithHistory = client
+                .call("jobs.get", Some(serde_json::json!({ "id": job.id })))
+                .await?;
+            let error = last_error(&job).unwrap_or("unknown error");
+            println!("  {} - {}", &job.job.id[..8.min(job.job.id.len())], error);
+            if let Some(hint) = failure::hint(error) {
+                println!("      What to do:  {}", hint);
+            }
         }
     }
 
     Ok(())
 }
 
+async fn run_jobs(args: JobsArgs) -> Result<()> {
+    let client = RpcClient::new(args.addr);
+
+    let Some(id) = args.id else {
+        let params = serde_json::json!({ "limit": args.limit, "status": args.status });
+        let jobs: Vec<Job> = client
+            .call("jobs.list", Some(params))
+            .await
+            .context("Failed to connect to daemon. Is it running?")?;
+
+        for job in jobs {
+            println!(
+                "{}  {:<13}  {}  {}",
+                job.id,
+                job.status,
+                job.created_at,
+                job.destination_path.as_deref().unwrap_or("-")
+            );
+        }
+        return Ok(());
+    };
+
+    let job: JobWithHistory = client
+        .call("jobs.get", Some(serde_json::json!({ "id": id })))
+        .await
+        .context("Failed to connect to daemon. Is it running?")?;
+
+    println!("Job {}", job.job.id);
+    println!("  Status:      {}", job.job.status);
+    println!("  Created:     {}", job.job.created_at);
+    println!(
+        "  Destination: {}",
+        job.job.destination_path.as_deref().unwrap_or("-")
+    );
+    for entry in &job.history {
+        println!(
+            "  {}  {:<13}  {}",
+            entry.created_at,
+            entry.status,
+            entry.description.as_deref().unwrap_or("")
+        );
+    }
+    if job.job.status.eq_ignore_ascii_case("failed")
+        && let Some(hint) = last_error(&job).and_then(failure::hint)
+    {
+        println!("  What to do:  {}", hint);
+    }
+
+    job_outcome(&job)
+}
+
+/// The most recent description in a job's history: the error, for a
+/// failed job
+fn last_error(job: &JobWithHistory) -> Option<&str> {
+    job.history
+        .iter()
+        .rev()
+        .find_map(|e| e.description.as_deref())
+}
+
+/// Ok for a job that didn't fail, otherwise the failure classified for the
+/// exit code. A cancelled job counts as failed.
+fn job_outcome(job: &JobWithHistory) -> Result<()> {
+    if ["failed", "cancelled"]
+        .iter()
+        .any(|s| job.job.status.eq_ignore_ascii_case(s))
+    {
+        let error = last_error(job).unwrap_or("unknown error");
+        return Err(JobOutcome::from_error(&job.job.id, error).into());
+    }
+
+    Ok(())
+}
+
+async fn run_wait(args: WaitArgs) -> Result<()> {
+    const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+    let client = RpcClient::new(args.addr);
+    let started = Instant::now();
+
+    let job_ids = match args.job {
+        Some(id) => vec![id],
+        None => {
+            #[derive(Deserialize)]
+            struct ActiveProgress {
+                jobs: HashMap<String, serde_json::Value>,
+            }
+
+            let progress: ActiveProgress = client
+                .call_no_params("progress.active")
+                .await
+                .context("Failed to connect to daemon. Is it running?")?;
+            progress.jobs.into_keys().collect()
+        }
+    };
+
+    let mut outcome = Ok(());
+    for id in job_ids {
+        let job = loop {
+            let job: JobWithHistory = client
+                .call("jobs.get", Some(serde_json::json!({ "id": id })))
+                .await
+                .context("Failed to connect to daemon. Is it running?")?;
+
+            let finished = ["complete", "failed", "cancelled", "skipped"]
+                .iter()
+                .any(|s| job.job.status.eq_ignore_ascii_case(s));
+            if finished {
+                break job;
+            }
+
+            if let Some(timeout) = args.timeout
+                && started.elapsed() >= Duration::from_secs(timeout)
+            {
+                return Err(TimedOut { secs: timeout }.into());
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        };
+
+        println!("{}  {}", job.job.id, job.job.status);
+        // Report the first failure but keep waiting for the rest
+        if outcome.is_ok() {
+            outcome = job_outcome(&job);
+        }
+    }
+
+    outcome
+}
+
+async fn run_restore(args: RestoreArgs) -> Result<()> {
+    let (job_id, path) = restore::parse_spec(&args.file)?;
+    let client = RpcClient::new(args.addr);
+
+    let file: BackedUpFile = client
+        .call(
+            "jobs.file",
+            Some(serde_json::json!({ "id": job_id, "path": path })),
+        )
+        .await
+        .context("Failed to locate file in backup")?;
+
+    let restored = restore::restore(&file, &args.target).await?;
+    println!(
+        "Restored {} ({}) to {}{}",
+        file.relative_path.display(),
+        format_bytes(file.size),
+        restored.display(),
+        if file.hash.is_some() {
+            ", hash verified"
+        } else {
+            ""
+        }
+    );
+    Ok(())
+}
+
+async fn run_export(args: ExportArgs) -> Result<()> {
+    let client = RpcClient::new(args.addr);
+
+    let document: serde_json::Value = client
+        .call("jobs.export", Some(serde_json::json!({ "id": args.id })))
+        .await
+        .context("Failed to export job")?;
+
+    let json = serde_json::to_string_pretty(&document)?;
+    match &args.output {
+        Some(path) => {
+            std::fs::write(path, json + "\n")
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            eprintln!("Exported job {} to {}", args.id, path.display());
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+async fn run_import(args: ImportArgs) -> Result<()> {
+    let json = std::fs::read_to_string(&args.file)
+        .with_context(|| format!("Failed to read {}", args.file.display()))?;
+    let document: serde_json::Value = serde_json::from_str(&json)
+        .with_context(|| format!("{} is not a job document", args.file.display()))?;
+    let client = RpcClient::new(args.addr);
+
+    let job_id: String = client
+        .call(
+            "jobs.import",
+            Some(serde_json::json!({ "document": document })),
+        )
+        .await
+        .context("Failed to import job")?;
+
+    println!("Imported job {}", job_id);
+    Ok(())
+}
+
+async fn run_backup(args: BackupArgs) -> Result<()> {
+    // The daemon resolves paths from its own working directory
+    let path = std::path::absolute(&args.path)
+        .with_context(|| format!("Invalid path {}", args.path.display()))?;
+    let client = RpcClient::new(args.addr);
+
+    let device: BlockDevice = client
+        .call(
+            "backup.trigger",
+            Some(serde_json::json!({ "path": path, "label": args.label })),
+        )
+        .await
+        .context("Failed to start backup")?;
+
+    println!(
+        "Backing up {} as {}; follow it with `bksd status` or `bksd wait`",
+        device.mount_point.display(),
+        device.label
+    );
+    Ok(())
+}
+
+async fn run_verify(args: VerifyArgs) -> Result<()> {
+    let not_verified = |reason: String| NotVerified {
+        path: args.path.display().to_string(),
+        reason,
+    };
+
+    // Without a badge, check the files against a manifest or the card
+    let against = if let Some(source) = &args.source {
+        let files = verifier::hash_source(source)
+            .await
+            .with_context(|| format!("Failed to read {}", source.display()))?;
+        Some((files, source.display().to_string()))
+    } else if let Some(job_id) = &args.job {
+        let config = config::AppConfig::new(None::<&()>)?;
+        let store = db::open(&config).await?;
+        let files = store.list_files(job_id.clone()).await?;
+        if files.is_empty() {
+            anyhow::bail!(
+                "Job {} has no manifest; use --source to check it against the card",
+                job_id
+            );
+        }
+        Some((files, format!("the manifest of job {}", job_id)))
+    } else if !args.path.join(badge::BADGE_NAME).exists() {
+        let files = badge::read_manifest(&args.path).map_err(|_| {
+            not_verified(format!(
+                "No {} or {}; use --job or --source",
+                badge::BADGE_NAME,
+                badge::MANIFEST_NAME
+            ))
+        })?;
+        Some((files, format!("its unsigned {}", badge::MANIFEST_NAME)))
+    } else {
+        None
+    };
+
+    if let Some((files, what)) = against {
+        let (bytes, mismatches) = verifier::rehash(&args.path, &files).await?;
+        for mismatch in &mismatches {
+            println!(
+                "  {}: {}",
+                mismatch.relative_path.display(),
+                mismatch.reason.describe()
+            );
+        }
+        if !mismatches.is_empty() {
+            return Err(not_verified(format!(
+                "{} of {} file(s) don't match {}",
+                mismatches.len(),
+                files.len(),
+                what
+            ))
+            .into());
+        }
+        println!(
+            "{}: {} file(s), {} match {}",
+            args.path.display(),
+            files.len(),
+            format_bytes(bytes),
+            what
+        );
+        return Ok(());
+    }
+
+    let trusted = match &args.public_key {
+        Some(key) => vec![signing::parse_public_key(key)?],
+        None => {
+            let config = config::AppConfig::new(None::<&()>)?;
+            signing::trusted_keys(&config.signing_key)
+                .context("No --public-key given and no local signing key to trust")?
+        }
+    };
+
+    let path = args.path.clone();
+    let badge = tokio::task::spawn_blocking(move || badge::verify(&path, &trusted))
+        .await?
+        .map_err(|e| not_verified(format!("{:#}", e)))?;
+
+    println!(
+        "{}: {} file(s) match the badge of job {}, verified {}",
+        args.path.display(),
+        badge.files,
+        badge.job_id,
+        badge.completed_at
+    );
+    Ok(())
+}
+
+fn run_key(args: KeyArgs) -> Result<()> {
+    let path = match args.key {
+        Some(path) => path,
+        None => config::AppConfig::new(None::<&()>)?.signing_key,
+    };
+
+    match args.action {
+        KeyAction::Show => {
+            let key = signing::load(&path)?;
+            println!("Signing key  {}", path.display());
+            println!(
+                "Public key   {}",
+                signing::hex(key.verifying_key().as_bytes())
+            );
+            for retired in signing::retired(&path)? {
+                println!(
+                    "Retired      {}  {}",
+                    signing::hex(retired.public_key.as_bytes()),
+                    retired.retired_at
+                );
+            }
+        }
+        KeyAction::Generate => {
+            if path.exists() {
+                anyhow::bail!(
+                    "{} already exists; use `bksd key rotate` to replace it",
+                    path.display()
+                );
+            }
+            let key = signing::generate(&path)?;
+            println!("Generated {}", path.display());
+            println!(
+                "Public key   {}",
+                signing::hex(key.verifying_key().as_bytes())
+            );
+        }
+        KeyAction::Rotate => {
+            let (old, key) = signing::rotate(&path)?;
+            println!("Rotated {}", path.display());
+            println!("Retired      {}", signing::hex(old.as_bytes()));
+            println!(
+                "Public key   {}",
+                signing::hex(key.verifying_key().as_bytes())
+            );
+            println!("Restart the daemon to sign with the new key.");
+        }
+    }
+    Ok(())
+}
+
+async fn run_plan(args: PlanArgs) -> Result<()> {
+    let card_bytes = capacity::parse_size(&args.card_size)?;
+    let client = RpcClient::new(args.addr);
+
+    let plan: CapacityPlan = client
+        .call(
+            "capacity.plan",
+            Some(serde_json::json!({ "card_bytes": card_bytes, "count": args.count })),
+        )
+        .await
+        .context("Failed to connect to daemon. Is it running?")?;
+
+    println!(
+        "{} x {} card(s) need up to {}",
+        plan.count,
+        args.card_size,
+        format_bytes(plan.required_bytes)
+    );
+
+    let backup = &plan.backup;
+    println!("\nBackup directory  {}", backup.path);
+    println!(
+        "  Free:        {} of {}",
+        format_bytes(backup.free_bytes),
+        format_bytes(backup.total_bytes)
+    );
+    if backup.active_jobs > 0 {
+        println!(
+            "  Reserved:    {} for {} active job(s)",
+            format_bytes(backup.reserved_bytes),
+            backup.active_jobs
+        );
+    }
+    println!("  Available:   {}", format_bytes(plan.available_bytes));
+
+    if let Some(archive) = &plan.archive {
+        println!(
+            "\nArchive drive     {} ({})",
+            archive.label,
+            if archive.connected {
+                "connected"
+            } else {
+                "offline"
+            }
+        );
+        println!(
+            "  Archivable:  {} in {} backup(s)",
+            format_bytes(backup.archivable_bytes),
+            backup.archivable_jobs
+        );
+        match archive.free_bytes() {
+            Some(free) => println!("  Room:        {}", format_bytes(free)),
+            None => println!("  Room:        unknown"),
+        }
+        println!("  Reclaimable: {}", format_bytes(plan.reclaimable_bytes));
+    }
+
+    println!();
+    if plan.fits() {
+        println!("Fits: room for {} card(s)", plan.cards_fit);
+        Ok(())
+    } else if plan.fits_after_tiering() {
+        let label = plan.archive.as_ref().map_or("", |a| a.label.as_str());
+        println!(
+            "Fits after archive tiering: room for {} card(s) now, {} once old backups move to {}",
+            plan.cards_fit, plan.cards_fit_after_tiering, label
+        );
+        Ok(())
+    } else {
+        Err(InsufficientSpace {
+            count: plan.count,
+            fit: plan.cards_fit_after_tiering,
+        }
+        .into())
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+fn format_eta(secs: u64) -> String {
+    if secs >= 3600 {
+        format!("{}h {}m", secs / 3600, (secs % 3600) / 60)
+    } else if secs >= 60 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
 fn progress_bar(percentage: u8, width: usize) -> String {
     let percentage = percentage.min(100) as usize;
     let filled = (percentage * width) / 100;
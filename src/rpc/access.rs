@@ -0,0 +1,111 @@
+//! Access log for the RPC server and web dashboard.
+//!
+//! Every request is recorded as a `tracing` event under the [`TARGET`]
+//! target: transport, peer, method (or HTTP path), duration, outcome, the
+//! identity the caller presented and the RPC trace ID. The events are debug level on the
+//! console; `access_log` writes all of them to a JSON lines file.
+
+use std::net::SocketAddr;
+use std::time::Instant;
+
+use serde_json::Value;
+
+use super::protocol::{Request, Response};
+
+/// Target of access log events
+pub const TARGET: &str = "bksd::access";
+
+/// Identity of a caller that presented a token. Whether it was accepted
+/// shows in the outcome.
+pub const TOKEN: &str = "token";
+
+/// Identity of a caller that presented nothing
+pub const ANONYMOUS: &str = "anonymous";
+
+/// Identity presented by an RPC request: a `token` parameter (hub reports)
+/// or nothing.
+pub fn identity(request: &Request) -> &'static str {
+    match &request.params {
+        Some(Value::Object(params)) if params.contains_key("token") => TOKEN,
+        _ => ANONYMOUS,
+    }
+}
+
+/// Compare tokens without exiting early on the first differing byte.
+pub fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Outcome of an RPC response: `ok` or `error <code>`.
+pub fn outcome(response: &Response) -> String {
+    match &response.error {
+        Some(error) => format!("error {}", error.code),
+        None => "ok".to_string(),
+    }
+}
+
+/// Record one request. `trace_id` is that of an RPC call, `-` for plain
+/// HTTP requests.
+pub fn record(
+    transport: &str,
+    peer: SocketAddr,
+    method: &str,
+    started: Instant,
+    outcome: &str,
+    identity: &str,
+    trace_id: &str,
+) {
+    tracing::debug!(
+        target: TARGET,
+        transport,
+        peer = %peer,
+        method,
+        duration_ms = started.elapsed().as_millis() as u64,
+        outcome,
+        identity,
+        trace_id,
+        "request"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(params: Option<Value>) -> Request {
+        Request {
+            jsonrpc: "2.0".to_string(),
+            method: "hub.report".to_string(),
+            params,
+            id: Some(json!(1)),
+            trace_id: None,
+        }
+    }
+
+    #[test]
+    fn test_identity_and_outcome() {
+        assert_eq!(identity(&request(None)), ANONYMOUS);
+        assert_eq!(identity(&request(Some(json!({"id": 3})))), ANONYMOUS);
+        assert_eq!(identity(&request(Some(json!({"token": "x"})))), TOKEN);
+
+        assert_eq!(outcome(&Response::success(json!(1), json!(null))), "ok");
+        assert_eq!(
+            outcome(&Response::method_not_found(json!(1), "x")),
+            "error -32601"
+        );
+    }
+
+    #[test]
+    fn test_token_matches() {
+        assert!(token_matches("s3cret", "s3cret"));
+        assert!(!token_matches("s3creT", "s3cret"));
+        assert!(!token_matches("s3cre", "s3cret"));
+        assert!(!token_matches("", "s3cret"));
+    }
+}
@@ -27,8 +27,8 @@ pub enum ClientError {
     Serialize(serde_json::Error),
     /// Failed to parse response
     Parse(serde_json::Error),
-    /// Server returned an error
-    Rpc(RpcError),
+    /// Server returned an error, with the trace ID it was logged under
+    Rpc(RpcError, Option<String>),
 }
 
 impl std::fmt::Display for ClientError {
@@ -38,7 +38,12 @@ impl std::fmt::Display for ClientError {
             ClientError::Io(e) => write!(f, "Communication error: {}", e),
             ClientError::Serialize(e) => write!(f, "Failed to serialize request: {}", e),
             ClientError::Parse(e) => write!(f, "Failed to parse response: {}", e),
-            ClientError::Rpc(e) => write!(f, "RPC error {}: {}", e.code, e.message),
+            ClientError::Rpc(e, None) => write!(f, "RPC error {}: {}", e.code, e.message),
+            ClientError::Rpc(e, Some(trace_id)) => write!(
+                f,
+                "RPC error {}: {} (trace {})",
+                e.code, e.message, trace_id
+            ),
         }
     }
 }
@@ -66,6 +71,7 @@ impl RpcClient {
             method: method.to_string(),
             params,
             id: Some(Value::Number(1.into())),
+            trace_id: None,
         };
 
         let mut request_json = serde_json::to_string(&request).map_err(ClientError::Serialize)?;
@@ -87,7 +93,7 @@ impl RpcClient {
             serde_json::from_str(&response_line).map_err(ClientError::Parse)?;
 
         if let Some(error) = response.error {
-            return Err(ClientError::Rpc(error));
+            return Err(ClientError::Rpc(error, response.trace_id));
         }
 
         let result = response.result.unwrap_or(Value::Null);
@@ -2,33 +2,45 @@
 //!
 //! Dispatches JSON-RPC method calls to the appropriate handler functions.
 
+use async_trait::async_trait;
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
-use std::time::Instant;
+use std::path::PathBuf;
+use tracing::{Instrument, info};
 
 use crate::context::AppContext;
+use crate::core::export::{self, JobDocument};
+use crate::core::progress::JobProgressDetails;
 use crate::core::transfer_engine::TransferStatus;
-use crate::db;
+use crate::core::{capacity, manual, restore, scrub, timeline};
+use crate::db::scrubs::ScrubResult;
 
 use super::protocol::{Request, Response};
 
+/// Answers JSON-RPC requests. The TCP and WebSocket servers take any
+/// handler, so the hub can serve its own methods over the same transports.
+#[async_trait]
+pub trait Handler: Send + Sync {
+    /// Handle an RPC request and return a response.
+    async fn handle(&self, request: Request) -> Response;
+}
+
 /// Handles RPC method dispatch and execution.
 pub struct MethodHandler {
     ctx: AppContext,
-    start_time: Instant,
 }
 
 impl MethodHandler {
     pub fn new(ctx: AppContext) -> Self {
-        Self {
-            ctx,
-            start_time: Instant::now(),
-        }
+        Self { ctx }
     }
+}
 
-    /// Handle an RPC request and return a response.
-    pub async fn handle(&self, request: Request) -> Response {
+#[async_trait]
+impl Handler for MethodHandler {
+    async fn handle(&self, request: Request) -> Response {
         let id = request.id.clone().unwrap_or(Value::Null);
         let params = request.params.unwrap_or(Value::Null);
 
@@ -36,21 +48,45 @@ impl MethodHandler {
             "daemon.status" => self.daemon_status(id).await,
             "jobs.list" => self.jobs_list(id, params).await,
             "jobs.get" => self.jobs_get(id, params).await,
+            "jobs.timeline" => self.jobs_timeline(id, params).await,
+            "jobs.files" => self.jobs_files(id, params).await,
+            "jobs.duplicates" => self.jobs_duplicates(id, params).await,
+            "jobs.probes" => self.jobs_probes(id, params).await,
+            "jobs.export" => self.jobs_export(id, params).await,
+            "jobs.import" => self.jobs_import(id, params).await,
+            "jobs.file" => self.jobs_file(id, params).await,
+            "jobs.backup" => self.jobs_backup(id, params).await,
+            "jobs.cancel" => self.jobs_cancel(id, params).await,
+            "jobs.pause" => self.jobs_set_paused(id, params, true).await,
+            "jobs.resume" => self.jobs_set_paused(id, params, false).await,
+            "archives.list" => self.archives_list(id).await,
+            "capacity.plan" => self.capacity_plan(id, params).await,
+            "archives.contents" => self.archives_contents(id, params).await,
             "progress.active" => self.progress_active(id).await,
             "progress.get" => self.progress_get(id, params).await,
+            "verify.schedule" => self.verify_schedule(id, params).await,
+            "verify.run" => self.verify_run(id, params).await,
+            "backup.trigger" => self.backup_trigger(id, params).await,
+            "devices.ignored" => self.devices_ignored(id).await,
+            "sim.devices" => self.sim_devices(id),
+            "sim.add" => self.sim_add(id, params),
+            "sim.remove" => self.sim_remove(id, params),
             _ => Response::method_not_found(id, &request.method),
         }
     }
+}
 
+impl MethodHandler {
     /// Get daemon status/health information.
     async fn daemon_status(&self, id: Value) -> Response {
         let active_jobs = self.ctx.progress.active_count().await;
-        let uptime_secs = self.start_time.elapsed().as_secs();
+        let uptime_secs = self.ctx.uptime.elapsed().as_secs();
 
         #[derive(Serialize)]
         struct DaemonStatus {
             version: &'static str,
             uptime_secs: u64,
+            started_at: String,
             active_jobs: usize,
             rpc_bind: String,
             simulation: bool,
@@ -61,6 +97,7 @@ impl MethodHandler {
             DaemonStatus {
                 version: env!("CARGO_PKG_VERSION"),
                 uptime_secs,
+                started_at: self.ctx.started_at.clone(),
                 active_jobs,
                 rpc_bind: self.ctx.config.rpc_bind.to_string(),
                 simulation: self.ctx.config.simulation,
@@ -88,7 +125,7 @@ impl MethodHandler {
         let limit = params.limit.unwrap_or(50);
         let offset = params.offset.unwrap_or(0);
 
-        match db::jobs::list(&self.ctx.db, limit, offset, params.status).await {
+        match self.ctx.db.list_jobs(limit, offset, params.status).await {
             Ok(jobs) => Response::success(id, jobs),
             Err(e) => Response::internal_error(id, e.to_string()),
         }
@@ -106,19 +143,296 @@ impl MethodHandler {
             Err(e) => return Response::invalid_params(id, e.to_string()),
         };
 
-        match db::jobs::get_with_history(&self.ctx.db, params.id).await {
+        match self.ctx.db.get_with_history(params.id).await {
             Ok(job) => Response::success(id, job),
             Err(e) => Response::internal_error(id, e.to_string()),
         }
     }
 
+    /// Get how long each phase of a job took, from its status history.
+    async fn jobs_timeline(&self, id: Value, params: Value) -> Response {
+        #[derive(Deserialize)]
+        struct Params {
+            id: String,
+        }
+
+        let params: Params = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return Response::invalid_params(id, e.to_string()),
+        };
+
+        match self.ctx.db.get_with_history(params.id).await {
+            Ok(job) => Response::success(
+                id,
+                timeline::from_history(&job.job.id, &job.history, Utc::now()),
+            ),
+            Err(e) => Response::internal_error(id, e.to_string()),
+        }
+    }
+
+    /// Export a job, its history and manifest as one JSON document.
+    async fn jobs_export(&self, id: Value, params: Value) -> Response {
+        #[derive(Deserialize)]
+        struct Params {
+            id: String,
+        }
+
+        let params: Params = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return Response::invalid_params(id, e.to_string()),
+        };
+
+        match export::export(self.ctx.db.as_ref(), &params.id).await {
+            Ok(document) => Response::success(id, document),
+            Err(e) => Response::internal_error(id, e.to_string()),
+        }
+    }
+
+    /// Load a job document exported by another daemon.
+    async fn jobs_import(&self, id: Value, params: Value) -> Response {
+        #[derive(Deserialize)]
+        struct Params {
+            document: JobDocument,
+        }
+
+        let params: Params = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return Response::invalid_params(id, e.to_string()),
+        };
+
+        match export::import(
+            self.ctx.db.as_ref(),
+            params.document,
+            &self.ctx.config.backup_directory,
+        )
+        .await
+        {
+            Ok(job_id) => {
+                info!(job_id = %job_id, "Job imported");
+                Response::success(id, job_id)
+            }
+            Err(e) => Response::internal_error(id, e.to_string()),
+        }
+    }
+
+    /// List the files recorded for a job's backup.
+    async fn jobs_files(&self, id: Value, params: Value) -> Response {
+        #[derive(Deserialize)]
+        struct Params {
+            id: String,
+        }
+
+        let params: Params = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return Response::invalid_params(id, e.to_string()),
+        };
+
+        match self.ctx.db.list_files(params.id).await {
+            Ok(files) => Response::success(id, files),
+            Err(e) => Response::internal_error(id, e.to_string()),
+        }
+    }
+
+    /// Files in a job's backup that another job also backed up.
+    async fn jobs_duplicates(&self, id: Value, params: Value) -> Response {
+        #[derive(Deserialize)]
+        struct Params {
+            id: String,
+        }
+
+        let params: Params = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return Response::invalid_params(id, e.to_string()),
+        };
+
+        match self.ctx.db.find_duplicates(params.id).await {
+            Ok(duplicates) => Response::success(id, duplicates),
+            Err(e) => Response::internal_error(id, e.to_string()),
+        }
+    }
+
+    /// Video probe results for a job's backup.
+    async fn jobs_probes(&self, id: Value, params: Value) -> Response {
+        #[derive(Deserialize)]
+        struct Params {
+            id: String,
+        }
+
+        let params: Params = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return Response::invalid_params(id, e.to_string()),
+        };
+
+        match self.ctx.db.list_probes(params.id).await {
+            Ok(probes) => Response::success(id, probes),
+            Err(e) => Response::internal_error(id, e.to_string()),
+        }
+    }
+
+    /// Locate one file in a job's backup, for restoring it.
+    async fn jobs_file(&self, id: Value, params: Value) -> Response {
+        #[derive(Deserialize)]
+        struct Params {
+            id: String,
+            path: PathBuf,
+        }
+
+        let params: Params = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return Response::invalid_params(id, e.to_string()),
+        };
+
+        match restore::locate(self.ctx.db.as_ref(), &params.id, &params.path).await {
+            Ok(file) => Response::success(id, file),
+            Err(e) => Response::internal_error(id, e.to_string()),
+        }
+    }
+
+    /// Locate a job's backup directory, with its file manifest.
+    async fn jobs_backup(&self, id: Value, params: Value) -> Response {
+        #[derive(Deserialize)]
+        struct Params {
+            id: String,
+        }
+
+        let params: Params = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return Response::invalid_params(id, e.to_string()),
+        };
+
+        match restore::backup(self.ctx.db.as_ref(), &params.id).await {
+            Ok(backup) => Response::success(id, backup),
+            Err(e) => Response::internal_error(id, e.to_string()),
+        }
+    }
+
+    /// Abort a running job. The job stops at the next file or chunk (or
+    /// the next file it would verify), its
+    /// partial destination is removed and it is recorded as `cancelled`.
+    async fn jobs_cancel(&self, id: Value, params: Value) -> Response {
+        #[derive(Deserialize)]
+        struct Params {
+            id: String,
+        }
+
+        let params: Params = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return Response::invalid_params(id, e.to_string()),
+        };
+
+        if self.ctx.progress.cancel(&params.id).await {
+            info!(job_id = %params.id, "Cancellation requested");
+            Response::success(
+                id,
+                serde_json::json!({ "id": params.id, "cancelling": true }),
+            )
+        } else {
+            Response::error(
+                id,
+                -32000,
+                format!("Job not found or not active: {}", params.id),
+            )
+        }
+    }
+
+    /// Hold a running job before its next file (`jobs.pause`), or let it
+    /// continue (`jobs.resume`). rsync is stopped and continued instead.
+    async fn jobs_set_paused(&self, id: Value, params: Value, paused: bool) -> Response {
+        #[derive(Deserialize)]
+        struct Params {
+            id: String,
+        }
+
+        let params: Params = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return Response::invalid_params(id, e.to_string()),
+        };
+
+        let changed = if paused {
+            self.ctx.progress.pause(&params.id).await
+        } else {
+            self.ctx.progress.resume(&params.id).await
+        };
+        if changed {
+            info!(job_id = %params.id, paused, "Pause state changed");
+            Response::success(id, serde_json::json!({ "id": params.id, "paused": paused }))
+        } else if paused {
+            Response::error(
+                id,
+                -32000,
+                format!("Job not found, not active or already paused: {}", params.id),
+            )
+        } else {
+            Response::error(
+                id,
+                -32000,
+                format!("Job not found or not paused: {}", params.id),
+            )
+        }
+    }
+
+    /// List known archive drives and whether they are connected.
+    async fn archives_list(&self, id: Value) -> Response {
+        match self.ctx.db.list_volumes().await {
+            Ok(volumes) => Response::success(id, volumes),
+            Err(e) => Response::internal_error(id, e.to_string()),
+        }
+    }
+
+    /// List the backups stored on an archive drive.
+    async fn archives_contents(&self, id: Value, params: Value) -> Response {
+        #[derive(Deserialize)]
+        struct Params {
+            uuid: String,
+        }
+
+        let params: Params = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return Response::invalid_params(id, e.to_string()),
+        };
+
+        match self.ctx.db.list_archive_contents(params.uuid).await {
+            Ok(jobs) => Response::success(id, jobs),
+            Err(e) => Response::internal_error(id, e.to_string()),
+        }
+    }
+
+    /// Check whether a number of cards of a given size will fit.
+    async fn capacity_plan(&self, id: Value, params: Value) -> Response {
+        #[derive(Deserialize)]
+        struct Params {
+            card_bytes: u64,
+            #[serde(default = "default_count")]
+            count: u64,
+        }
+
+        fn default_count() -> u64 {
+            1
+        }
+
+        let params: Params = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return Response::invalid_params(id, e.to_string()),
+        };
+
+        match capacity::gather(self.ctx.db.as_ref(), &self.ctx.config, &self.ctx.progress).await {
+            Ok((backup, archive)) => Response::success(
+                id,
+                capacity::plan(params.card_bytes, params.count, backup, archive),
+            ),
+            Err(e) => Response::internal_error(id, e.to_string()),
+        }
+    }
+
     /// Get all active jobs with their current progress.
     async fn progress_active(&self, id: Value) -> Response {
         let progress = self.ctx.progress.get_all().await;
+        let details = self.ctx.progress.get_details().await;
 
         #[derive(Serialize)]
         struct ActiveProgress {
             jobs: HashMap<String, TransferStatus>,
+            details: HashMap<String, JobProgressDetails>,
             count: usize,
         }
 
@@ -127,6 +441,7 @@ impl MethodHandler {
             id,
             ActiveProgress {
                 jobs: progress,
+                details,
                 count,
             },
         )
@@ -153,4 +468,244 @@ impl MethodHandler {
             ),
         }
     }
+
+    /// Show the scrub schedule, what the next run will check and recent
+    /// scrub results (optionally one job's).
+    async fn verify_schedule(&self, id: Value, params: Value) -> Response {
+        #[derive(Deserialize)]
+        struct Params {
+            #[serde(default)]
+            id: Option<String>,
+            #[serde(default)]
+            limit: Option<u32>,
+        }
+
+        #[derive(Serialize)]
+        struct ScrubSchedule {
+            schedule: Option<String>,
+            next_run: Option<String>,
+            min_age_days: u32,
+            jobs_per_run: u32,
+            running: bool,
+            due: Vec<String>,
+            recent: Vec<ScrubResult>,
+        }
+
+        let params: Params = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return Response::invalid_params(id, e.to_string()),
+        };
+
+        let schedule = self.ctx.scrub_schedule.as_ref();
+        let due = match scrub::due(&self.ctx).await {
+            Ok(due) => due,
+            Err(e) => return Response::internal_error(id, e.to_string()),
+        };
+        match self
+            .ctx
+            .db
+            .list_scrubs(params.id, params.limit.unwrap_or(20))
+            .await
+        {
+            Ok(recent) => Response::success(
+                id,
+                ScrubSchedule {
+                    schedule: schedule.map(ToString::to_string),
+                    next_run: schedule
+                        .and_then(|s| s.next_after(Utc::now(), self.ctx.zone))
+                        .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+                    min_age_days: self.ctx.config.scrub_min_age_days,
+                    jobs_per_run: self.ctx.config.scrub_jobs_per_run,
+                    running: self.ctx.scrubber.is_running(),
+                    due,
+                    recent,
+                },
+            ),
+            Err(e) => Response::internal_error(id, e.to_string()),
+        }
+    }
+
+    /// Start a scrub now in the background: of one completed job, or of the
+    /// backups the next scheduled run would check.
+    async fn verify_run(&self, id: Value, params: Value) -> Response {
+        #[derive(Deserialize)]
+        struct Params {
+            #[serde(default)]
+            id: Option<String>,
+        }
+
+        let params: Params = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return Response::invalid_params(id, e.to_string()),
+        };
+
+        let job_ids = match params.id {
+            Some(job_id) => match self.ctx.db.get_with_history(job_id.clone()).await {
+                Ok(job) if job.job.status == "complete" => vec![job_id],
+                Ok(_) => {
+                    return Response::invalid_params(
+                        id,
+                        format!("Job {} has not completed", job_id),
+                    );
+                }
+                Err(e) => return Response::internal_error(id, e.to_string()),
+            },
+            None => match scrub::due(&self.ctx).await {
+                Ok(due) => due,
+                Err(e) => return Response::internal_error(id, e.to_string()),
+            },
+        };
+
+        let Some(guard) = self.ctx.scrubber.try_start() else {
+            return Response::error(id, -32000, "A scrub is already running");
+        };
+        info!(jobs = job_ids.len(), "Scrub requested");
+        tokio::spawn(scrub::run(self.ctx.clone(), job_ids.clone(), guard).in_current_span());
+        Response::success(id, serde_json::json!({ "started": true, "jobs": job_ids }))
+    }
+
+    /// Back up a directory that is already mounted (a card mounted by hand,
+    /// a network folder) through the normal job pipeline.
+    async fn backup_trigger(&self, id: Value, params: Value) -> Response {
+        #[derive(Deserialize)]
+        struct Params {
+            /// Absolute path on the daemon's host
+            path: PathBuf,
+            /// Label to file the backup under (default: the directory's name)
+            #[serde(default)]
+            label: Option<String>,
+        }
+
+        let params: Params = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return Response::invalid_params(id, e.to_string()),
+        };
+
+        let device = match manual::device(&self.ctx.config, &params.path, params.label.as_deref()) {
+            Ok(device) => device,
+            Err(e) => return Response::invalid_params(id, format!("{:#}", e)),
+        };
+        let details = self.ctx.progress.get_details().await;
+        if let Some((job_id, _)) = details
+            .iter()
+            .find(|(_, job)| job.info.source == device.mount_point)
+        {
+            return Response::error(
+                id,
+                -32000,
+                format!(
+                    "{} is already being backed up by job {}",
+                    device.mount_point.display(),
+                    job_id
+                ),
+            );
+        }
+
+        match self.ctx.manual.trigger(device.clone()).await {
+            Ok(()) => Response::success(id, device),
+            Err(e) => Response::internal_error(id, e.to_string()),
+        }
+    }
+
+    /// Connected devices that aren't being backed up because of the device
+    /// filter or size limits, with the reason.
+    async fn devices_ignored(&self, id: Value) -> Response {
+        Response::success(id, self.ctx.ignored_devices.list().await)
+    }
+
+    /// List the simulated devices currently inserted.
+    fn sim_devices(&self, id: Value) -> Response {
+        match &self.ctx.simulator {
+            Some(simulator) => Response::success(id, simulator.devices()),
+            None => not_simulating(id),
+        }
+    }
+
+    /// Insert a simulated device, as if a card had been plugged in.
+    fn sim_add(&self, id: Value, params: Value) -> Response {
+        #[derive(Deserialize, Default)]
+        struct Params {
+            #[serde(default)]
+            uuid: Option<String>,
+            #[serde(default)]
+            size_gb: Option<u64>,
+            /// Name of a `[sim_profiles]` entry
+            #[serde(default)]
+            profile: Option<String>,
+        }
+
+        let params: Params = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return Response::invalid_params(id, e.to_string()),
+        };
+        let Some(simulator) = &self.ctx.simulator else {
+            return not_simulating(id);
+        };
+
+        let uuid = params.uuid.unwrap_or_else(|| simulator.next_uuid());
+        if simulator.devices().iter().any(|d| d.uuid == uuid) {
+            return Response::error(
+                id,
+                -32000,
+                format!("Simulated device already inserted: {}", uuid),
+            );
+        }
+        let device = match params.profile {
+            Some(name) => {
+                let Some(profile) = self.ctx.config.sim_profiles.get(&name) else {
+                    return Response::invalid_params(
+                        id,
+                        format!("Unknown simulated device profile: {}", name),
+                    );
+                };
+                let mut profile = profile.clone();
+                if let Some(size_gb) = params.size_gb {
+                    profile.size_gb = size_gb;
+                }
+                simulator.add_profiled(&uuid, profile)
+            }
+            None => simulator.add_device(&uuid, params.size_gb.unwrap_or(64)),
+        };
+        info!(uuid = %device.uuid, "Simulated device added");
+        Response::success(id, device)
+    }
+
+    /// Remove a simulated device; the most recently inserted if no UUID is
+    /// given.
+    fn sim_remove(&self, id: Value, params: Value) -> Response {
+        #[derive(Deserialize, Default)]
+        struct Params {
+            #[serde(default)]
+            uuid: Option<String>,
+        }
+
+        let params: Params = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return Response::invalid_params(id, e.to_string()),
+        };
+        let Some(simulator) = &self.ctx.simulator else {
+            return not_simulating(id);
+        };
+
+        let Some(uuid) = params
+            .uuid
+            .or_else(|| simulator.devices().last().map(|d| d.uuid.clone()))
+        else {
+            return Response::error(id, -32000, "No simulated devices inserted");
+        };
+        if !simulator.remove_device(&uuid) {
+            return Response::error(
+                id,
+                -32000,
+                format!("Simulated device not inserted: {}", uuid),
+            );
+        }
+        info!(uuid = %uuid, "Simulated device removed");
+        Response::success(id, serde_json::json!({ "uuid": uuid }))
+    }
+}
+
+/// Error for `sim.*` methods on a daemon not in simulation mode.
+fn not_simulating(id: Value) -> Response {
+    Response::error(id, -32000, "Daemon is not running in simulation mode")
 }
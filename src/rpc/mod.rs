@@ -9,6 +9,9 @@
 //! - `transport`: TCP listener with newline-delimited JSON framing
 //! - `methods`: Method dispatcher and handlers
 //! - `client`: Client for connecting to the daemon
+//! - `access`: Access log events for every request (shared with the web
+//!   dashboard)
+//! - `trace`: Trace IDs and the span each request is handled in
 //!
 //! ## Extensibility
 //!
@@ -16,19 +19,22 @@
 //! - Push notifications: Server can send JSON-RPC notifications to connected clients
 //! - Subscriptions: Add `subscribe.*` methods for real-time event streaming
 
+pub mod access;
 pub mod client;
 pub mod methods;
 mod protocol;
+pub mod trace;
 mod transport;
 
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tokio::sync::broadcast;
 
 use crate::context::AppContext;
 use transport::Transport;
 
 pub use client::RpcClient;
-pub use methods::MethodHandler;
+pub use methods::{Handler, MethodHandler};
 pub use protocol::{Request, Response, RpcError};
 
 /// RPC server that exposes daemon functionality to clients.
@@ -40,7 +46,11 @@ pub struct RpcServer {
 impl RpcServer {
     /// Create a new RPC server bound to the given address.
     pub fn new(ctx: AppContext, bind_addr: SocketAddr) -> Self {
-        let handler = MethodHandler::new(ctx);
+        Self::with_handler(Arc::new(MethodHandler::new(ctx)), bind_addr)
+    }
+
+    /// Create a server that answers requests with a custom handler.
+    pub fn with_handler(handler: Arc<dyn Handler>, bind_addr: SocketAddr) -> Self {
         let transport = Transport::new(bind_addr, handler);
         let (shutdown_tx, _) = broadcast::channel(1);
 
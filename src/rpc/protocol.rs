@@ -19,6 +19,10 @@ pub struct Request {
     /// Request identifier. If None, this is a notification (no response expected).
     #[serde(default)]
     pub id: Option<Value>,
+    /// Caller's ID for finding this request in the server's logs (an
+    /// extension to JSON-RPC); one is generated if missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
 }
 
 /// JSON-RPC 2.0 response object.
@@ -34,6 +38,9 @@ pub struct Response {
     pub error: Option<RpcError>,
     /// Request identifier (echoed from request)
     pub id: Value,
+    /// Trace ID the request was logged under
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
 }
 
 /// JSON-RPC 2.0 error object.
@@ -63,6 +70,7 @@ impl Response {
             result: Some(serde_json::to_value(result).unwrap_or(Value::Null)),
             error: None,
             id,
+            trace_id: None,
         }
     }
 
@@ -77,6 +85,7 @@ impl Response {
                 data: None,
             }),
             id,
+            trace_id: None,
         }
     }
 
@@ -96,6 +105,7 @@ impl Response {
                 data: Some(serde_json::to_value(data).unwrap_or(Value::Null)),
             }),
             id,
+            trace_id: None,
         }
     }
 
@@ -181,6 +191,7 @@ mod tests {
         assert!(json.contains(r#""result":"ok""#));
         assert!(json.contains(r#""id":1"#));
         assert!(!json.contains("error"));
+        assert!(!json.contains("trace_id"));
     }
 
     #[test]
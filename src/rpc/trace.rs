@@ -0,0 +1,82 @@
+//! Trace IDs tying an RPC request to the server's logs.
+//!
+//! A request may carry a `trace_id` next to `method`; otherwise one is made
+//! up. The handler runs inside an `rpc` span with that ID (as do tasks it
+//! spawns, such as a scrub started by `verify.run`), and the response echoes
+//! it, so an error a user sees can be looked up in the log straight away.
+
+use tracing::Instrument;
+use uuid::Uuid;
+
+use super::methods::Handler;
+use super::protocol::{Request, Response};
+
+/// Longest trace ID accepted from a client
+const MAX_LEN: usize = 64;
+
+/// The client's trace ID if it is usable in a log line, else a new one.
+pub fn resolve(requested: Option<&str>) -> String {
+    match requested {
+        Some(id)
+            if !id.is_empty()
+                && id.len() <= MAX_LEN
+                && id
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b"-_.:".contains(&b)) =>
+        {
+            id.to_string()
+        }
+        _ => generate(),
+    }
+}
+
+/// A new trace ID
+pub fn generate() -> String {
+    Uuid::now_v7().simple().to_string()
+}
+
+/// Run `request` through `handler` inside its trace span, and put the trace
+/// ID on the response.
+pub async fn dispatch(handler: &dyn Handler, request: Request) -> Response {
+    let trace_id = resolve(request.trace_id.as_deref());
+    let span = tracing::info_span!("rpc", trace_id = %trace_id, method = %request.method);
+    let mut response = handler.handle(request).instrument(span).await;
+    response.trace_id = Some(trace_id);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use serde_json::json;
+
+    struct Echo;
+
+    #[async_trait]
+    impl Handler for Echo {
+        async fn handle(&self, request: Request) -> Response {
+            Response::success(request.id.unwrap_or_default(), request.method)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_echoes_trace_id() {
+        let request: Request = serde_json::from_value(json!({
+            "jsonrpc": "2.0", "method": "jobs.list", "id": 1, "trace_id": "ui-42"
+        }))
+        .unwrap();
+        let response = dispatch(&Echo, request).await;
+        assert_eq!(response.trace_id.as_deref(), Some("ui-42"));
+
+        // Made up when missing or unfit for a log line
+        let request: Request = serde_json::from_value(json!({
+            "jsonrpc": "2.0", "method": "jobs.list", "id": 2, "trace_id": "a b\n"
+        }))
+        .unwrap();
+        let generated = dispatch(&Echo, request).await.trace_id.unwrap();
+        assert_eq!(generated.len(), 32);
+        assert_ne!(generated, resolve(None));
+        assert_eq!(resolve(Some(&"x".repeat(65))).len(), 32);
+    }
+}
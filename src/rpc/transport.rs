@@ -5,27 +5,26 @@
 
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Instant;
 
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::broadcast;
 use tracing::{debug, error, info, warn};
 
-use super::methods::MethodHandler;
+use super::methods::Handler;
 use super::protocol::{Request, Response};
+use super::{access, trace};
 
 /// Manages the TCP transport layer.
 pub struct Transport {
     bind_addr: SocketAddr,
-    handler: Arc<MethodHandler>,
+    handler: Arc<dyn Handler>,
 }
 
 impl Transport {
-    pub fn new(bind_addr: SocketAddr, handler: MethodHandler) -> Self {
-        Self {
-            bind_addr,
-            handler: Arc::new(handler),
-        }
+    pub fn new(bind_addr: SocketAddr, handler: Arc<dyn Handler>) -> Self {
+        Self { bind_addr, handler }
     }
 
     /// Start listening for connections. Runs until shutdown signal is received.
@@ -67,7 +66,7 @@ impl Transport {
 async fn handle_connection(
     stream: TcpStream,
     peer_addr: SocketAddr,
-    handler: Arc<MethodHandler>,
+    handler: Arc<dyn Handler>,
 ) -> anyhow::Result<()> {
     let (reader, mut writer) = stream.into_split();
     let mut reader = BufReader::new(reader);
@@ -87,26 +86,64 @@ async fn handle_connection(
             continue;
         }
 
+        let started = Instant::now();
         let response = match serde_json::from_str::<Request>(trimmed) {
             Ok(request) => {
+                let method = request.method.clone();
+                let identity = access::identity(&request);
                 // Validate JSON-RPC 2.0 format
-                if let Err(msg) = request.validate() {
-                    warn!(peer = %peer_addr, error = msg, "Invalid request");
+                let response = if let Err(msg) = request.validate() {
+                    let trace_id = trace::resolve(request.trace_id.as_deref());
+                    warn!(peer = %peer_addr, trace_id = %trace_id, error = msg, "Invalid request");
                     let id = request.id.clone().unwrap_or(serde_json::Value::Null);
-                    Response::invalid_request(id)
+                    Response {
+                        trace_id: Some(trace_id),
+                        ..Response::invalid_request(id)
+                    }
                 } else if request.is_notification() {
                     // Notifications don't get responses
                     debug!(peer = %peer_addr, method = %request.method, "Notification received");
-                    handler.handle(request).await;
+                    let response = trace::dispatch(handler.as_ref(), request).await;
+                    let trace_id = response.trace_id.as_deref().unwrap_or("-");
+                    access::record(
+                        "rpc",
+                        peer_addr,
+                        &method,
+                        started,
+                        "notification",
+                        identity,
+                        trace_id,
+                    );
                     continue;
                 } else {
                     // Normal request
-                    handler.handle(request).await
-                }
+                    trace::dispatch(handler.as_ref(), request).await
+                };
+                let outcome = access::outcome(&response);
+                let trace_id = response.trace_id.as_deref().unwrap_or("-");
+                access::record(
+                    "rpc", peer_addr, &method, started, &outcome, identity, trace_id,
+                );
+                response
             }
             Err(e) => {
-                warn!(peer = %peer_addr, error = %e, "Parse error");
-                Response::parse_error()
+                let trace_id = trace::generate();
+                warn!(peer = %peer_addr, trace_id = %trace_id, error = %e, "Parse error");
+                let response = Response::parse_error();
+                let outcome = access::outcome(&response);
+                access::record(
+                    "rpc",
+                    peer_addr,
+                    "-",
+                    started,
+                    &outcome,
+                    access::ANONYMOUS,
+                    &trace_id,
+                );
+                Response {
+                    trace_id: Some(trace_id),
+                    ..response
+                }
             }
         };
 
@@ -17,6 +17,8 @@ Type=simple
 ExecStart={binary_path} start {backup_dir} --foreground
 Restart=always
 RestartSec=5
+# Longer than BKSD_SHUTDOWN_TIMEOUT_SECS, so running jobs can finish
+TimeoutStopSec=90
 
 StartLimitBurst=5
 StartLimitIntervalSec=60
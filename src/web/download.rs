@@ -0,0 +1,392 @@
+//! Download routes: single files, so the dashboard can restore one clip
+//! without copying the whole job, and whole backups as a zip, so remote
+//! editors can fetch a card straight from the ingest box.
+//!
+//! Backups are located through the `jobs.file` and `jobs.backup` RPC
+//! methods, so a handler that doesn't know them (the hub) answers 404.
+
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::path::PathBuf;
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+use tracing::warn;
+
+use super::zip::ZipStream;
+use super::{WebState, check_token};
+use crate::core::restore::{self, BackedUpFile, BackupLocation};
+use crate::rpc::Request;
+
+/// Bytes buffered between the zip writer and the response
+const ARCHIVE_PIPE_BYTES: usize = 1024 * 1024;
+
+/// Call an RPC method on the server's handler, mapping errors to 404.
+async fn call<T: DeserializeOwned>(
+    state: &WebState,
+    method: &str,
+    params: Value,
+) -> Result<T, Response> {
+    let request = Request {
+        jsonrpc: "2.0".to_string(),
+        method: method.to_string(),
+        params: Some(params),
+        id: Some(Value::from(1)),
+        trace_id: None,
+    };
+    let response = state.handler.handle(request).await;
+
+    match (response.result, response.error) {
+        (Some(result), None) => serde_json::from_value(result)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()),
+        (_, Some(error)) => Err((StatusCode::NOT_FOUND, error.message).into_response()),
+        _ => Err(StatusCode::NOT_FOUND.into_response()),
+    }
+}
+
+/// `GET /jobs/{id}/files/{*path}`: stream one file from a job's backup.
+///
+/// Supports single `Range` requests, with an `ETag` (the recorded BLAKE3
+/// hash, or size and mtime without one) and `If-Range`, so a download of a
+/// large clip that drops can resume where it stopped.
+pub async fn download_file(
+    State(state): State<WebState>,
+    Path((job_id, path)): Path<(String, String)>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(response) = check_token(&state, &headers, &query, "File downloads") {
+        return response;
+    }
+
+    let file: BackedUpFile =
+        match call(&state, "jobs.file", json!({ "id": job_id, "path": path })).await {
+            Ok(file) => file,
+            Err(response) => return response,
+        };
+
+    let mut handle = match tokio::fs::File::open(&file.path).await {
+        Ok(handle) => handle,
+        Err(e) => return (StatusCode::NOT_FOUND, e.to_string()).into_response(),
+    };
+    let metadata = match handle.metadata().await {
+        Ok(metadata) => metadata,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    let size = metadata.len();
+    let etag = etag(&file, &metadata);
+
+    if header_str(&headers, header::IF_NONE_MATCH)
+        .is_some_and(|tags| tags.split(',').any(|tag| tag.trim() == etag))
+    {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+
+    // A stale If-Range means the file changed: send all of it
+    let range = header_str(&headers, header::RANGE).filter(|_| {
+        header_str(&headers, header::IF_RANGE).is_none_or(|validator| validator == etag)
+    });
+    let range = match range.map(|range| parse_range(range, size)) {
+        None => None,
+        Some(Some(range)) => Some(range),
+        Some(None) => {
+            return (
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(header::CONTENT_RANGE, format!("bytes */{}", size))],
+            )
+                .into_response();
+        }
+    };
+
+    let name = file
+        .relative_path
+        .file_name()
+        .map(|n| n.to_string_lossy().replace('"', ""))
+        .unwrap_or_default();
+
+    let mut response_headers = vec![
+        (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+        (
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", name),
+        ),
+        (header::ACCEPT_RANGES, "bytes".to_string()),
+        (header::ETAG, etag),
+    ];
+
+    let Some((start, end)) = range else {
+        response_headers.push((header::CONTENT_LENGTH, size.to_string()));
+        return (
+            StatusCode::OK,
+            header_map(response_headers),
+            Body::from_stream(ReaderStream::new(handle)),
+        )
+            .into_response();
+    };
+
+    if let Err(e) = handle.seek(SeekFrom::Start(start)).await {
+        return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+    }
+    let length = end - start + 1;
+    response_headers.push((header::CONTENT_LENGTH, length.to_string()));
+    response_headers.push((
+        header::CONTENT_RANGE,
+        format!("bytes {}-{}/{}", start, end, size),
+    ));
+    (
+        StatusCode::PARTIAL_CONTENT,
+        header_map(response_headers),
+        Body::from_stream(ReaderStream::new(handle.take(length))),
+    )
+        .into_response()
+}
+
+/// Build response headers, dropping any whose value isn't valid in a header.
+fn header_map(pairs: Vec<(header::HeaderName, String)>) -> HeaderMap {
+    pairs
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value.parse().ok()?)))
+        .collect()
+}
+
+fn header_str(headers: &HeaderMap, name: header::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// Strong ETag for a backed-up file: its recorded hash, which changes only
+/// with its content, or its size and mtime for engines that keep no manifest.
+fn etag(file: &BackedUpFile, metadata: &std::fs::Metadata) -> String {
+    match &file.hash {
+        Some(hash) => format!("\"{}\"", hash),
+        None => {
+            let mtime = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_nanos())
+                .unwrap_or_default();
+            format!("\"{:x}-{:x}\"", metadata.len(), mtime)
+        }
+    }
+}
+
+/// Parse a single `bytes=` range against a file of `size` bytes into
+/// inclusive (start, end). None if it can't be satisfied; multiple ranges
+/// aren't supported and are treated the same way.
+fn parse_range(range: &str, size: u64) -> Option<(u64, u64)> {
+    let spec = range.trim().strip_prefix("bytes=")?;
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        // Suffix range: the last N bytes
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            if suffix == 0 {
+                return None;
+            }
+            (size.saturating_sub(suffix), size.checked_sub(1)?)
+        }
+        (start, "") => (start.parse().ok()?, size.checked_sub(1)?),
+        (start, end) => (
+            start.parse().ok()?,
+            end.parse::<u64>().ok()?.min(size.checked_sub(1)?),
+        ),
+    };
+    (start <= end && start < size).then_some((start, end))
+}
+
+/// `GET /api/jobs/{id}/archive.zip[?paths=a,b]`: stream a job's backup, or
+/// the listed paths in it, as a zip. Each file is hashed on the way out and
+/// the download is cut short if one no longer matches its manifest entry,
+/// so a truncated zip is the worst a client can get, never a corrupt one.
+pub async fn download_archive(
+    State(state): State<WebState>,
+    Path(job_id): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(response) = check_token(&state, &headers, &query, "Archive downloads") {
+        return response;
+    }
+
+    let backup: BackupLocation = match call(&state, "jobs.backup", json!({ "id": job_id })).await {
+        Ok(backup) => backup,
+        Err(response) => return response,
+    };
+
+    let subpaths: Vec<PathBuf> = query
+        .get("paths")
+        .map(|paths| {
+            paths
+                .split(',')
+                .filter(|p| !p.is_empty())
+                .map(PathBuf::from)
+                .collect()
+        })
+        .unwrap_or_default();
+    let root = backup.path.clone();
+    let files = match tokio::task::spawn_blocking(move || restore::walk(&root, &subpaths)).await {
+        Ok(Ok(files)) => files,
+        Ok(Err(e)) => return (StatusCode::NOT_FOUND, e.to_string()).into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    let (writer, reader) = tokio::io::duplex(ARCHIVE_PIPE_BYTES);
+    let name = format!("{}.zip", backup.job_id.replace('"', ""));
+    tokio::spawn(async move {
+        if let Err(e) = write_archive(writer, &backup, &files).await {
+            warn!(job_id = %backup.job_id, error = %e, "Archive download aborted");
+        }
+    });
+
+    (
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            // Built on the fly, so it can't be resumed part way
+            (header::ACCEPT_RANGES, "none".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", name),
+            ),
+        ],
+        Body::from_stream(ReaderStream::new(reader)),
+    )
+        .into_response()
+}
+
+/// Write `files` from a backup into a zip on `out`, checking each against
+/// the manifest. Returning early drops `out`, which ends the response
+/// without a central directory.
+async fn write_archive(
+    out: tokio::io::DuplexStream,
+    backup: &BackupLocation,
+    files: &[PathBuf],
+) -> anyhow::Result<()> {
+    let expected: HashMap<_, _> = backup
+        .files
+        .iter()
+        .map(|f| (f.relative_path.as_path(), f.hash))
+        .collect();
+
+    let mut zip = ZipStream::new(out);
+    for relative in files {
+        let path = backup.path.join(relative);
+        let handle = tokio::fs::File::open(&path).await?;
+        let modified = handle.metadata().await?.modified()?;
+        let mut hasher = blake3::Hasher::new();
+        zip.add_file(&relative.to_string_lossy(), modified, handle, |chunk| {
+            hasher.update(chunk);
+        })
+        .await?;
+
+        if let Some(hash) = expected.get(relative.as_path())
+            && hasher.finalize().as_bytes() != hash
+        {
+            anyhow::bail!(
+                "{} does not match the hash recorded at backup time",
+                relative.display()
+            );
+        }
+    }
+    zip.finish().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::Response as RpcResponse;
+    use async_trait::async_trait;
+    use std::sync::Arc;
+
+    /// Answers every `jobs.file` lookup with the same file, so only the
+    /// token check stands in the way
+    struct OneFile(PathBuf);
+
+    #[async_trait]
+    impl crate::rpc::Handler for OneFile {
+        async fn handle(&self, request: Request) -> RpcResponse {
+            let file = json!({
+                "job_id": "job",
+                "path": self.0,
+                "relative_path": "clip.mp4",
+                "size": 4,
+                "hash": null,
+            });
+            RpcResponse::success(request.id.unwrap_or_default(), file)
+        }
+    }
+
+    async fn fetch(
+        token: Option<&str>,
+        query: &[(&str, &str)],
+        bearer: Option<&str>,
+    ) -> StatusCode {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clip.mp4");
+        std::fs::write(&path, b"clip").unwrap();
+        let state = WebState {
+            handler: Arc::new(OneFile(path)),
+            index: "",
+            token: token.map(str::to_string),
+            uploads: None,
+        };
+        let query = query
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let mut headers = HeaderMap::new();
+        if let Some(bearer) = bearer {
+            headers.insert(
+                header::AUTHORIZATION,
+                format!("Bearer {}", bearer).parse().unwrap(),
+            );
+        }
+        let path = Path(("job".to_string(), "clip.mp4".to_string()));
+        download_file(State(state), path, Query(query), headers)
+            .await
+            .status()
+    }
+
+    #[tokio::test]
+    async fn test_download_file_needs_token() {
+        assert_eq!(fetch(None, &[], None).await, StatusCode::NOT_FOUND);
+        assert_eq!(
+            fetch(Some("s3cret"), &[], None).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            fetch(Some("s3cret"), &[("token", "wrong")], None).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            fetch(Some("s3cret"), &[("token", "s3cret")], None).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            fetch(Some("s3cret"), &[], Some("s3cret")).await,
+            StatusCode::OK
+        );
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some((0, 99)));
+        assert_eq!(parse_range("bytes=500-", 1000), Some((500, 999)));
+        assert_eq!(parse_range("bytes=-100", 1000), Some((900, 999)));
+        assert_eq!(parse_range("bytes=900-5000", 1000), Some((900, 999)));
+        assert_eq!(parse_range("bytes=-5000", 1000), Some((0, 999)));
+        assert_eq!(parse_range("bytes=1000-", 1000), None);
+        assert_eq!(parse_range("bytes=5-1", 1000), None);
+        assert_eq!(parse_range("bytes=0-1,5-9", 1000), None);
+        assert_eq!(parse_range("bytes=0-", 0), None);
+        assert_eq!(parse_range("items=0-1", 1000), None);
+    }
+}
@@ -7,21 +7,45 @@
 //!
 //! - `routes`: Axum router with HTTP and WebSocket endpoints
 //! - `websocket`: WebSocket handler that dispatches to RPC method handlers
+//! - `download`: Streams single files, or a zip of a backup, out of a backup
+//! - `zip`: Streaming zip writer for archive downloads
+//! - `upload`: Receives manual ingests into upload sessions
 //!
 //! ## Endpoints
 //!
 //! - `GET /` - Serves the embedded SPA dashboard
 //! - `WS /ws` - WebSocket endpoint for JSON-RPC communication
+//! - `GET /jobs/{id}/files/{*path}` - Download one file from a job's backup
+//!   (needs `web_token`)
+//! - `GET /api/jobs/{id}/archive.zip` - Download a backup as a zip (needs
+//!   `web_token`)
+//! - `POST /api/uploads`, `PUT /api/uploads/{id}/files/{*path}`,
+//!   `PUT /api/uploads/{id}/manifest`, `POST /api/uploads/{id}/complete`, `DELETE /api/uploads/{id}` - Upload
+//!   a folder to ingest (needs `web_token` and `upload_directory`)
 
+mod download;
+mod upload;
 mod websocket;
+mod zip;
 
-use axum::{Router, response::Html, routing::get};
+use axum::{
+    Router,
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, StatusCode, header},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Response},
+    routing::{delete, get, post, put},
+};
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::broadcast;
 
 use crate::context::AppContext;
-use crate::rpc::MethodHandler;
+use crate::core::upload::UploadSessions;
+use crate::rpc::access::{self, token_matches};
+use crate::rpc::{Handler, MethodHandler};
 
 /// Embedded HTML dashboard
 const INDEX_HTML: &str = include_str!("assets/index.html");
@@ -29,7 +53,14 @@ const INDEX_HTML: &str = include_str!("assets/index.html");
 /// Shared state for the web server
 #[derive(Clone)]
 pub struct WebState {
-    pub handler: Arc<MethodHandler>,
+    pub handler: Arc<dyn Handler>,
+    /// Page served at `/`
+    pub index: &'static str,
+    /// Bearer token for file and archive downloads and uploads; None
+    /// disables them
+    pub token: Option<String>,
+    /// Upload sessions; None when uploads are disabled
+    pub uploads: Option<UploadSessions>,
 }
 
 /// Web server for the dashboard UI.
@@ -42,8 +73,27 @@ pub struct WebServer {
 impl WebServer {
     /// Create a new web server bound to the given address.
     pub fn new(ctx: AppContext, bind_addr: SocketAddr) -> Self {
-        let handler = Arc::new(MethodHandler::new(ctx));
-        let state = WebState { handler };
+        let token = ctx.config.web_token.clone();
+        let uploads = ctx.uploads.clone();
+        let mut server =
+            Self::with_handler(Arc::new(MethodHandler::new(ctx)), INDEX_HTML, bind_addr);
+        server.state.token = token;
+        server.state.uploads = uploads;
+        server
+    }
+
+    /// Create a server for a custom handler and dashboard page.
+    pub fn with_handler(
+        handler: Arc<dyn Handler>,
+        index: &'static str,
+        bind_addr: SocketAddr,
+    ) -> Self {
+        let state = WebState {
+            handler,
+            index,
+            token: None,
+            uploads: None,
+        };
         let (shutdown_tx, _) = broadcast::channel(1);
 
         Self {
@@ -58,6 +108,17 @@ impl WebServer {
         let app = Router::new()
             .route("/", get(serve_index))
             .route("/ws", get(websocket::ws_handler))
+            .route("/jobs/{id}/files/{*path}", get(download::download_file))
+            .route(
+                "/api/jobs/{id}/archive.zip",
+                get(download::download_archive),
+            )
+            .route("/api/uploads", post(upload::create))
+            .route("/api/uploads/{id}", delete(upload::abort))
+            .route("/api/uploads/{id}/files/{*path}", put(upload::put_file))
+            .route("/api/uploads/{id}/manifest", put(upload::put_manifest))
+            .route("/api/uploads/{id}/complete", post(upload::complete))
+            .layer(middleware::from_fn(access_log))
             .with_state(self.state.clone());
 
         let listener = tokio::net::TcpListener::bind(self.bind_addr).await?;
@@ -65,11 +126,14 @@ impl WebServer {
 
         let mut shutdown_rx = self.shutdown_tx.subscribe();
 
-        axum::serve(listener, app)
-            .with_graceful_shutdown(async move {
-                let _ = shutdown_rx.recv().await;
-            })
-            .await?;
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(async move {
+            let _ = shutdown_rx.recv().await;
+        })
+        .await?;
 
         Ok(())
     }
@@ -80,7 +144,68 @@ impl WebServer {
     }
 }
 
-/// Serve the embedded index.html
-async fn serve_index() -> Html<&'static str> {
-    Html(INDEX_HTML)
+/// Record each HTTP request in the access log. The path is logged without
+/// its query so `token` parameters stay out of the log; calls made over
+/// the WebSocket are recorded one by one by the socket handler.
+async fn access_log(request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(peer)| *peer)
+        .unwrap_or_else(|| SocketAddr::from(([0, 0, 0, 0], 0)));
+    let method = format!("{} {}", request.method(), request.uri().path());
+    let presented = request.headers().contains_key(header::AUTHORIZATION)
+        || request
+            .uri()
+            .query()
+            .is_some_and(|query| query.split('&').any(|pair| pair.starts_with("token=")));
+    let identity = if presented {
+        access::TOKEN
+    } else {
+        access::ANONYMOUS
+    };
+
+    let response = next.run(request).await;
+    let outcome = response.status().as_u16().to_string();
+    access::record("http", peer, &method, started, &outcome, identity, "-");
+    response
+}
+
+/// Serve the embedded dashboard page
+async fn serve_index(State(state): State<WebState>) -> Html<&'static str> {
+    Html(state.index)
+}
+
+/// Check the request carries the configured token, as a bearer token or a
+/// `token` query parameter (for plain links). `feature` names what a missing
+/// token disables.
+#[allow(clippy::result_large_err)]
+fn check_token(
+    state: &WebState,
+    headers: &HeaderMap,
+    query: &HashMap<String, String>,
+    feature: &str,
+) -> Result<(), Response> {
+    let Some(expected) = &state.token else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("{} are disabled (set web_token)", feature),
+        )
+            .into_response());
+    };
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let given = bearer.or(query.get("token").map(String::as_str));
+    if !given.is_some_and(|given| token_matches(given, expected)) {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            "Missing or wrong token",
+        )
+            .into_response());
+    }
+    Ok(())
 }
@@ -0,0 +1,171 @@
+//! Upload routes: a remote shooter opens a session, `PUT`s each file of a
+//! folder into it, then completes it to have the daemon ingest the folder
+//! as if it were a card (see `core::upload`).
+
+use std::collections::HashMap;
+use std::path::{Path as FsPath, PathBuf};
+
+use axum::{
+    Json,
+    body::{Body, HttpBody},
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+use tokio::io::AsyncWriteExt;
+use tracing::warn;
+
+use super::{WebState, check_token};
+use crate::core::upload::UploadSessions;
+
+/// Largest manifest accepted (a few hundred thousand files)
+const MAX_MANIFEST_BYTES: usize = 64 * 1024 * 1024;
+
+/// The upload sessions, if uploads are enabled and the request carries the
+/// token.
+#[allow(clippy::result_large_err)]
+fn sessions<'a>(
+    state: &'a WebState,
+    headers: &HeaderMap,
+    query: &HashMap<String, String>,
+) -> Result<&'a UploadSessions, Response> {
+    check_token(state, headers, query, "Uploads")?;
+    state.uploads.as_ref().ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            "Uploads are disabled (set upload_directory)",
+        )
+            .into_response()
+    })
+}
+
+/// `POST /api/uploads?label=NAME`: open a session filed under `label`.
+pub async fn create(
+    State(state): State<WebState>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    let sessions = match sessions(&state, &headers, &query) {
+        Ok(sessions) => sessions,
+        Err(response) => return response,
+    };
+    let label = query.get("label").map(String::as_str).unwrap_or_default();
+    match sessions.create(label).await {
+        Ok(session) => (StatusCode::CREATED, Json(session)).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+/// `PUT /api/uploads/{id}/files/{*path}`: store one file, streamed from
+/// the request body. A file that doesn't arrive whole is removed.
+pub async fn put_file(
+    State(state): State<WebState>,
+    Path((id, path)): Path<(String, String)>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: Body,
+) -> Response {
+    let sessions = match sessions(&state, &headers, &query) {
+        Ok(sessions) => sessions,
+        Err(response) => return response,
+    };
+    let relative = PathBuf::from(&path);
+    let destination = match sessions.file_path(&id, &relative).await {
+        Ok(destination) => destination,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+
+    match receive(body, &destination).await {
+        Ok(size) => {
+            sessions.record(&id, size).await;
+            (
+                StatusCode::CREATED,
+                Json(json!({ "path": path, "size": size })),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            warn!(upload_id = %id, path = %path, error = %e, "Upload of file failed");
+            let _ = tokio::fs::remove_file(&destination).await;
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// Write a request body to a new file at `path`, returning its size.
+async fn receive(mut body: Body, path: &FsPath) -> anyhow::Result<u64> {
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .await?;
+    let mut size = 0;
+    while let Some(frame) =
+        std::future::poll_fn(|cx| std::pin::Pin::new(&mut body).poll_frame(cx)).await
+    {
+        if let Ok(data) = frame?.into_data() {
+            file.write_all(&data).await?;
+            size += data.len() as u64;
+        }
+    }
+    file.sync_all().await?;
+    Ok(size)
+}
+
+/// `PUT /api/uploads/{id}/manifest`: the sender's hashes of the files
+/// (b3sum lines or MHL), checked before the upload is copied.
+pub async fn put_manifest(
+    State(state): State<WebState>,
+    Path(id): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: Body,
+) -> Response {
+    let sessions = match sessions(&state, &headers, &query) {
+        Ok(sessions) => sessions,
+        Err(response) => return response,
+    };
+    let text = match axum::body::to_bytes(body, MAX_MANIFEST_BYTES).await {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(e) => return (StatusCode::PAYLOAD_TOO_LARGE, e.to_string()).into_response(),
+    };
+    match sessions.set_manifest(&id, &text).await {
+        Ok(files) => Json(json!({ "files": files })).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+/// `POST /api/uploads/{id}/complete`: close the session and start its job.
+pub async fn complete(
+    State(state): State<WebState>,
+    Path(id): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    let sessions = match sessions(&state, &headers, &query) {
+        Ok(sessions) => sessions,
+        Err(response) => return response,
+    };
+    match sessions.complete(&id).await {
+        Ok(session) => (StatusCode::ACCEPTED, Json(session)).into_response(),
+        Err(e) => (StatusCode::CONFLICT, e.to_string()).into_response(),
+    }
+}
+
+/// `DELETE /api/uploads/{id}`: abandon the session and its files.
+pub async fn abort(
+    State(state): State<WebState>,
+    Path(id): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    let sessions = match sessions(&state, &headers, &query) {
+        Ok(sessions) => sessions,
+        Err(response) => return response,
+    };
+    match sessions.abort(&id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
+    }
+}
@@ -1,21 +1,62 @@
 //! WebSocket handler for JSON-RPC communication.
 
+use std::net::SocketAddr;
+use std::time::Instant;
+
 use axum::{
     extract::ws::{Message, WebSocket},
-    extract::{State, WebSocketUpgrade},
+    extract::{ConnectInfo, State, WebSocketUpgrade},
     response::IntoResponse,
 };
 
+use serde_json::Value;
+
 use super::WebState;
-use crate::rpc::Request;
+use crate::rpc::access::{self, token_matches};
+use crate::rpc::{Request, Response, trace};
+
+/// Read-only methods anyone who can reach the dashboard can call. Every
+/// other method, including any added later, is served over the socket only
+/// with `web_token` as the request's `token` parameter
+const OPEN_METHODS: &[&str] = &[
+    "daemon.status",
+    "jobs.list",
+    "jobs.get",
+    "jobs.timeline",
+    "jobs.files",
+    "jobs.duplicates",
+    "jobs.probes",
+    "jobs.export",
+    "jobs.file",
+    "jobs.backup",
+    "archives.list",
+    "archives.contents",
+    "capacity.plan",
+    "progress.active",
+    "progress.get",
+    "verify.schedule",
+    "devices.ignored",
+    "sim.devices",
+    "hub.status",
+    "hub.stations",
+    "hub.jobs",
+    "hub.duplicates",
+];
+
+/// Error code for a call with a missing or wrong token
+const UNAUTHORIZED: i32 = -32001;
 
 /// Handle WebSocket upgrade requests
-pub async fn ws_handler(ws: WebSocketUpgrade, State(state): State<WebState>) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(socket, state))
+pub async fn ws_handler(
+    ws: WebSocketUpgrade,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    State(state): State<WebState>,
+) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| handle_socket(socket, peer, state))
 }
 
 /// Handle an established WebSocket connection
-async fn handle_socket(mut socket: WebSocket, state: WebState) {
+async fn handle_socket(mut socket: WebSocket, peer: SocketAddr, state: WebState) {
     while let Some(msg) = socket.recv().await {
         let msg = match msg {
             Ok(Message::Text(text)) => text,
@@ -28,18 +69,41 @@ async fn handle_socket(mut socket: WebSocket, state: WebState) {
         };
 
         // Parse JSON-RPC request
+        let started = Instant::now();
         let response = match serde_json::from_str::<Request>(&msg) {
             Ok(request) => {
-                let response = state.handler.handle(request).await;
+                let method = request.method.clone();
+                let identity = access::identity(&request);
+                let response = match authorize(&state, &request) {
+                    Ok(()) => trace::dispatch(state.handler.as_ref(), request).await,
+                    Err(mut response) => {
+                        response.trace_id = Some(trace::resolve(request.trace_id.as_deref()));
+                        response
+                    }
+                };
+                let outcome = access::outcome(&response);
+                let trace_id = response.trace_id.as_deref().unwrap_or("-");
+                access::record("ws", peer, &method, started, &outcome, identity, trace_id);
                 serde_json::to_string(&response).unwrap_or_else(|_| {
                     r#"{"jsonrpc":"2.0","error":{"code":-32603,"message":"Serialization error"},"id":null}"#.to_string()
                 })
             }
             Err(e) => {
                 // Invalid JSON or malformed request
+                let trace_id = trace::generate();
+                access::record(
+                    "ws",
+                    peer,
+                    "-",
+                    started,
+                    "error -32700",
+                    access::ANONYMOUS,
+                    &trace_id,
+                );
                 format!(
-                    r#"{{"jsonrpc":"2.0","error":{{"code":-32700,"message":"Parse error: {}"}},"id":null}}"#,
-                    e.to_string().replace('"', "'")
+                    r#"{{"jsonrpc":"2.0","error":{{"code":-32700,"message":"Parse error: {}"}},"id":null,"trace_id":"{}"}}"#,
+                    e.to_string().replace('"', "'"),
+                    trace_id
                 )
             }
         };
@@ -51,3 +115,99 @@ async fn handle_socket(mut socket: WebSocket, state: WebState) {
 
     tracing::debug!("WebSocket connection closed");
 }
+
+/// Check a call to anything but [`OPEN_METHODS`] carries the configured
+/// token.
+#[allow(clippy::result_large_err)]
+fn authorize(state: &WebState, request: &Request) -> Result<(), Response> {
+    if OPEN_METHODS.contains(&request.method.as_str()) {
+        return Ok(());
+    }
+    let id = request.id.clone().unwrap_or_default();
+    let Some(expected) = &state.token else {
+        return Err(Response::error(
+            id,
+            UNAUTHORIZED,
+            format!(
+                "{} is disabled on the dashboard (set web_token)",
+                request.method
+            ),
+        ));
+    };
+    let given = request
+        .params
+        .as_ref()
+        .and_then(|params| params.get("token"))
+        .and_then(Value::as_str);
+    if !given.is_some_and(|given| token_matches(given, expected)) {
+        return Err(Response::error(id, UNAUTHORIZED, "Missing or wrong token"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    struct Echo;
+
+    #[async_trait]
+    impl crate::rpc::Handler for Echo {
+        async fn handle(&self, request: Request) -> Response {
+            Response::success(request.id.unwrap_or_default(), request.method)
+        }
+    }
+
+    fn call(method: &str, token: Option<&str>) -> Request {
+        let params = match token {
+            Some(token) => json!({ "path": "/media/card", "token": token }),
+            None => json!({ "path": "/media/card" }),
+        };
+        Request {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params: Some(params),
+            id: Some(json!(1)),
+            trace_id: None,
+        }
+    }
+
+    #[test]
+    fn test_methods_that_change_state_need_token() {
+        let mut state = WebState {
+            handler: Arc::new(Echo),
+            index: "",
+            token: None,
+            uploads: None,
+        };
+        assert!(authorize(&state, &call("jobs.list", None)).is_ok());
+        assert!(authorize(&state, &call("backup.trigger", Some("s3cret"))).is_err());
+
+        state.token = Some("s3cret".to_string());
+        assert!(authorize(&state, &call("backup.trigger", None)).is_err());
+        assert!(authorize(&state, &call("backup.trigger", Some("wrong"))).is_err());
+        assert!(authorize(&state, &call("backup.trigger", Some("s3cret"))).is_ok());
+
+        for method in [
+            "jobs.cancel",
+            "jobs.pause",
+            "jobs.resume",
+            "jobs.import",
+            "verify.run",
+            "sim.add",
+            "sim.remove",
+            "hub.report",
+        ] {
+            assert!(
+                authorize(&state, &call(method, None)).is_err(),
+                "{}",
+                method
+            );
+            assert!(authorize(&state, &call(method, Some("s3cret"))).is_ok());
+        }
+        assert!(authorize(&state, &call("progress.active", None)).is_ok());
+    }
+}
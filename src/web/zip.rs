@@ -0,0 +1,260 @@
+//! Streaming zip writer for backup downloads.
+//!
+//! Entries are stored uncompressed (camera media doesn't compress) and
+//! written front to back without seeking: each one's CRC and size follow its
+//! data in a descriptor, so the archive can be sent while it is being read
+//! off disk. Every entry uses Zip64 fields, as video files regularly exceed
+//! 4 GB.
+
+use std::io;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Datelike, Local, Timelike};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR: u32 = 0x0807_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const ZIP64_END: u32 = 0x0606_4b50;
+const ZIP64_LOCATOR: u32 = 0x0706_4b50;
+const END: u32 = 0x0605_4b50;
+
+/// Version 4.5 (Zip64); "made by" also flags Unix file attributes
+const VERSION: u16 = 45;
+const MADE_BY_UNIX: u16 = (3 << 8) | VERSION;
+/// Sizes follow the data in a descriptor; names are UTF-8
+const FLAGS: u16 = 0x0008 | 0x0800;
+/// Regular file, rw-r--r--
+const FILE_MODE: u32 = 0o100644;
+
+const CHUNK: usize = 128 * 1024;
+
+struct Entry {
+    name: Vec<u8>,
+    crc: u32,
+    size: u64,
+    offset: u64,
+    time: u16,
+    date: u16,
+}
+
+/// Writes a zip archive to `out` one file at a time.
+pub struct ZipStream<W> {
+    out: W,
+    offset: u64,
+    entries: Vec<Entry>,
+}
+
+impl<W: AsyncWrite + Unpin> ZipStream<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            offset: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    async fn put(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(bytes).await?;
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Add a file named `name`, copying `data` into the archive. `inspect`
+    /// sees every chunk as it goes out, e.g. to hash it. Returns the bytes
+    /// written.
+    pub async fn add_file(
+        &mut self,
+        name: &str,
+        modified: SystemTime,
+        mut data: impl AsyncRead + Unpin,
+        mut inspect: impl FnMut(&[u8]),
+    ) -> io::Result<u64> {
+        let (time, date) = dos_time(modified);
+        let offset = self.offset;
+
+        let mut header = Vec::with_capacity(30 + name.len() + 20);
+        header.extend_from_slice(&LOCAL_HEADER.to_le_bytes());
+        header.extend_from_slice(&VERSION.to_le_bytes());
+        header.extend_from_slice(&FLAGS.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes()); // stored
+        header.extend_from_slice(&time.to_le_bytes());
+        header.extend_from_slice(&date.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes()); // CRC, in descriptor
+        header.extend_from_slice(&u32::MAX.to_le_bytes());
+        header.extend_from_slice(&u32::MAX.to_le_bytes());
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&20u16.to_le_bytes());
+        header.extend_from_slice(name.as_bytes());
+        // Zip64 extra field; real sizes are in the descriptor
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&16u16.to_le_bytes());
+        header.extend_from_slice(&[0u8; 16]);
+        self.put(&header).await?;
+
+        let mut crc = crc32fast::Hasher::new();
+        let mut size = 0u64;
+        let mut buffer = vec![0u8; CHUNK];
+        loop {
+            let n = data.read(&mut buffer).await?;
+            if n == 0 {
+                break;
+            }
+            crc.update(&buffer[..n]);
+            inspect(&buffer[..n]);
+            self.put(&buffer[..n]).await?;
+            size += n as u64;
+        }
+        let crc = crc.finalize();
+
+        let mut descriptor = Vec::with_capacity(24);
+        descriptor.extend_from_slice(&DATA_DESCRIPTOR.to_le_bytes());
+        descriptor.extend_from_slice(&crc.to_le_bytes());
+        descriptor.extend_from_slice(&size.to_le_bytes());
+        descriptor.extend_from_slice(&size.to_le_bytes());
+        self.put(&descriptor).await?;
+
+        self.entries.push(Entry {
+            name: name.as_bytes().to_vec(),
+            crc,
+            size,
+            offset,
+            time,
+            date,
+        });
+        Ok(size)
+    }
+
+    /// Write the central directory and flush.
+    pub async fn finish(mut self) -> io::Result<W> {
+        let directory_offset = self.offset;
+        let mut directory = Vec::new();
+        for entry in &self.entries {
+            directory.extend_from_slice(&CENTRAL_HEADER.to_le_bytes());
+            directory.extend_from_slice(&MADE_BY_UNIX.to_le_bytes());
+            directory.extend_from_slice(&VERSION.to_le_bytes());
+            directory.extend_from_slice(&FLAGS.to_le_bytes());
+            directory.extend_from_slice(&0u16.to_le_bytes());
+            directory.extend_from_slice(&entry.time.to_le_bytes());
+            directory.extend_from_slice(&entry.date.to_le_bytes());
+            directory.extend_from_slice(&entry.crc.to_le_bytes());
+            directory.extend_from_slice(&u32::MAX.to_le_bytes());
+            directory.extend_from_slice(&u32::MAX.to_le_bytes());
+            directory.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+            directory.extend_from_slice(&28u16.to_le_bytes());
+            directory.extend_from_slice(&0u16.to_le_bytes()); // comment
+            directory.extend_from_slice(&0u16.to_le_bytes()); // disk
+            directory.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+            directory.extend_from_slice(&(FILE_MODE << 16).to_le_bytes());
+            directory.extend_from_slice(&u32::MAX.to_le_bytes());
+            directory.extend_from_slice(&entry.name);
+            directory.extend_from_slice(&1u16.to_le_bytes());
+            directory.extend_from_slice(&24u16.to_le_bytes());
+            directory.extend_from_slice(&entry.size.to_le_bytes());
+            directory.extend_from_slice(&entry.size.to_le_bytes());
+            directory.extend_from_slice(&entry.offset.to_le_bytes());
+        }
+        let directory_size = directory.len() as u64;
+        let count = self.entries.len() as u64;
+        let zip64_end_offset = directory_offset + directory_size;
+
+        let mut end = Vec::with_capacity(56 + 20 + 22);
+        end.extend_from_slice(&ZIP64_END.to_le_bytes());
+        end.extend_from_slice(&44u64.to_le_bytes());
+        end.extend_from_slice(&MADE_BY_UNIX.to_le_bytes());
+        end.extend_from_slice(&VERSION.to_le_bytes());
+        end.extend_from_slice(&0u32.to_le_bytes());
+        end.extend_from_slice(&0u32.to_le_bytes());
+        end.extend_from_slice(&count.to_le_bytes());
+        end.extend_from_slice(&count.to_le_bytes());
+        end.extend_from_slice(&directory_size.to_le_bytes());
+        end.extend_from_slice(&directory_offset.to_le_bytes());
+
+        end.extend_from_slice(&ZIP64_LOCATOR.to_le_bytes());
+        end.extend_from_slice(&0u32.to_le_bytes());
+        end.extend_from_slice(&zip64_end_offset.to_le_bytes());
+        end.extend_from_slice(&1u32.to_le_bytes());
+
+        // Classic record, saturated where Zip64 takes over
+        end.extend_from_slice(&END.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes());
+        end.extend_from_slice(&(count.min(0xFFFF) as u16).to_le_bytes());
+        end.extend_from_slice(&(count.min(0xFFFF) as u16).to_le_bytes());
+        end.extend_from_slice(&(directory_size.min(0xFFFF_FFFF) as u32).to_le_bytes());
+        end.extend_from_slice(&(directory_offset.min(0xFFFF_FFFF) as u32).to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes());
+
+        self.put(&directory).await?;
+        self.put(&end).await?;
+        self.out.flush().await?;
+        Ok(self.out)
+    }
+}
+
+/// MS-DOS (time, date) in local time, clamped to the format's 1980 epoch.
+fn dos_time(modified: SystemTime) -> (u16, u16) {
+    let local: DateTime<Local> = modified.into();
+    if local.year() < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+    let time = (local.hour() << 11) | (local.minute() << 5) | (local.second() / 2);
+    let date = (((local.year() - 1980) as u32) << 9) | (local.month() << 5) | local.day();
+    (time as u16, date as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    fn u64_at(bytes: &[u8], at: usize) -> u64 {
+        u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_zip_layout() {
+        let mut zip = ZipStream::new(Vec::new());
+        let mut seen = 0;
+        zip.add_file(
+            "DCIM/IMG_0001.JPG",
+            SystemTime::now(),
+            &b"jpeg"[..],
+            |chunk| seen += chunk.len(),
+        )
+        .await
+        .unwrap();
+        zip.add_file(
+            "DCIM/IMG_0002.JPG",
+            SystemTime::UNIX_EPOCH,
+            &b""[..],
+            |_| {},
+        )
+        .await
+        .unwrap();
+        let bytes = zip.finish().await.unwrap();
+        assert_eq!(seen, 4);
+
+        // First entry: header, name, Zip64 extra, data, descriptor
+        assert_eq!(u32_at(&bytes, 0), LOCAL_HEADER);
+        let data = 30 + "DCIM/IMG_0001.JPG".len() + 20;
+        assert_eq!(&bytes[data..data + 4], b"jpeg");
+        assert_eq!(u32_at(&bytes, data + 4), DATA_DESCRIPTOR);
+        assert_eq!(u32_at(&bytes, data + 8), crc32fast::hash(b"jpeg"));
+        assert_eq!(u64_at(&bytes, data + 12), 4);
+
+        // End of central directory points back at the Zip64 record
+        let end = bytes.len() - 22;
+        assert_eq!(u32_at(&bytes, end), END);
+        let locator = end - 20;
+        assert_eq!(u32_at(&bytes, locator), ZIP64_LOCATOR);
+        let zip64_end = u64_at(&bytes, locator + 8) as usize;
+        assert_eq!(u32_at(&bytes, zip64_end), ZIP64_END);
+        assert_eq!(u64_at(&bytes, zip64_end + 32), 2);
+        let directory = u64_at(&bytes, zip64_end + 48) as usize;
+        assert_eq!(u32_at(&bytes, directory), CENTRAL_HEADER);
+    }
+}
@@ -136,6 +136,8 @@ async fn test_detects_loopback_device() {
     let config = LinuxAdapterConfig {
         mount_base: PathBuf::from("/tmp/bksd_test"),
         auto_mount: false,
+        keep_unmountable: false,
+        read_only: Default::default(),
     };
     let adapter = LinuxAdapter::new(config);
     let (tx, mut rx) = mpsc::channel(32);
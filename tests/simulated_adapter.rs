@@ -1,4 +1,5 @@
-use bksd::adapters::SimulatedAdapter;
+use bksd::adapters::{SimulatedAdapter, Simulator};
+use bksd::config::SimProfile;
 use bksd::core::{HardwareAdapter, HardwareEvent};
 use std::time::Duration;
 use tokio::sync::mpsc;
@@ -90,3 +91,62 @@ async fn test_stop() {
     adapter.start(tx);
     adapter.stop(); // Should not panic
 }
+
+#[tokio::test]
+async fn test_simulator_tracks_inserted_devices() {
+    let simulator = Simulator::new();
+    let (tx, mut rx) = mpsc::channel(32);
+
+    simulator.adapter().start(tx);
+
+    assert_eq!(simulator.next_uuid(), "sim-1");
+    simulator.add_device("sim-1", 64);
+    simulator.add_device("card-b", 32);
+    assert_eq!(simulator.next_uuid(), "sim-2");
+    assert!(simulator.remove_device("sim-1"));
+    assert!(!simulator.remove_device("sim-1"));
+
+    let uuids: Vec<String> = simulator.devices().into_iter().map(|d| d.uuid).collect();
+    assert_eq!(uuids, vec!["card-b".to_string()]);
+    assert_eq!(simulator.next_uuid(), "sim-1");
+
+    // Every change still reaches the daemon, known or not
+    for _ in 0..4 {
+        timeout(Duration::from_secs(1), rx.recv())
+            .await
+            .expect("timeout")
+            .expect("channel closed");
+    }
+}
+
+#[tokio::test]
+async fn test_profiled_device_is_pulled() {
+    let simulator = Simulator::new();
+    let (tx, mut rx) = mpsc::channel(32);
+
+    simulator.adapter().start(tx);
+
+    let profile = SimProfile {
+        label: Some("A001".to_string()),
+        filesystem: "exfat".to_string(),
+        remove_after_secs: Some(0),
+        ..Default::default()
+    };
+    let device = simulator.add_profiled("sim-1", profile);
+    assert_eq!(device.label, "A001");
+    assert_eq!(device.filesystem, "exfat");
+    assert!(simulator.profile("sim-1").is_some());
+    assert!(simulator.profile("sim-2").is_none());
+
+    let mut events = Vec::new();
+    for _ in 0..2 {
+        let event = timeout(Duration::from_secs(1), rx.recv())
+            .await
+            .expect("timeout")
+            .expect("channel closed");
+        events.push(event);
+    }
+    assert!(matches!(events[0], HardwareEvent::DeviceAdded(_)));
+    assert!(matches!(&events[1], HardwareEvent::DeviceRemoved(uuid) if uuid == "sim-1"));
+    assert!(simulator.devices().is_empty());
+}